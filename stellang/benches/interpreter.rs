@@ -0,0 +1,102 @@
+// Lexer, parser and interpreter benchmarks over representative programs
+//
+// Run with `cargo bench --bench interpreter`; see "Benchmarks" in the README for
+// comparing a change against a saved baseline.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use stellang::lang::{ast::Expr, interpreter::Interpreter, lexer::{Lexer, Token}, parser::Parser};
+
+const PROGRAMS: &[(&str, &str)] = &[
+    ("fib", include_str!("programs/fib.stel")),
+    ("strings", include_str!("programs/strings.stel")),
+    ("dicts", include_str!("programs/dicts.stel")),
+];
+
+fn lex(source: &str) -> Vec<Token> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    loop {
+        match lexer.next_token().expect("Lexer error") {
+            Token::EOF => break,
+            tok => tokens.push(tok),
+        }
+    }
+    tokens
+}
+
+fn parse(source: &str) -> Expr {
+    Parser::new(lex(source)).parse().expect("Parse error").expect("No expression")
+}
+
+fn interpreter() -> Interpreter {
+    Interpreter::new()
+}
+
+// The parser doesn't accept `class` blocks yet, so the classes are assembled
+// from parsed members; the loop that dispatches to them is ordinary source.
+fn class_dispatch() -> Expr {
+    let shape = Expr::ClassDef {
+        name: "Shape".into(),
+        bases: vec![],
+        body: vec![
+            parse("sides = 4"),
+            parse("fn perimeter(self, length) {\n    return self.sides * length\n}"),
+        ],
+    };
+    let square = Expr::ClassDef {
+        name: "Square".into(),
+        bases: vec![Expr::Ident("Shape".into())],
+        body: vec![parse("fn area(self, length) {\n    return length * length\n}")],
+    };
+    let instance = Expr::Assign {
+        name: "square".into(),
+        expr: Box::new(Expr::ClassInit { class_name: "Square".into(), args: vec![] }),
+    };
+    let body = parse(r#"
+total = 0
+i = 0
+while i < 200 {
+    total = total + square.perimeter(i) + square.area(i)
+    i = i + 1
+}
+total
+"#);
+    Expr::Block(vec![shape, square, instance, body])
+}
+
+fn bench_lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    for (name, source) in PROGRAMS {
+        group.bench_with_input(BenchmarkId::from_parameter(name), source, |b, source| {
+            b.iter(|| lex(black_box(source)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    for (name, source) in PROGRAMS {
+        let tokens = lex(source);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &tokens, |b, tokens| {
+            b.iter(|| Parser::new(black_box(tokens.clone())).parse().expect("Parse error"))
+        });
+    }
+    group.finish();
+}
+
+fn bench_interpreter(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interpreter");
+    let programs = PROGRAMS.iter()
+        .map(|(name, source)| (*name, parse(source)))
+        .chain([("class_dispatch", class_dispatch())]);
+    for (name, program) in programs {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &program, |b, program| {
+            b.iter(|| interpreter().eval(black_box(program)).expect("Runtime error"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lexer, bench_parser, bench_interpreter);
+criterion_main!(benches);
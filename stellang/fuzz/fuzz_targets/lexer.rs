@@ -0,0 +1,15 @@
+// Tokenize arbitrary UTF-8; malformed input must produce an error, never a panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stellang::lang::lexer::{Lexer, Token};
+
+fuzz_target!(|source: &str| {
+    let mut lexer = Lexer::new(source);
+    while let Ok(tok) = lexer.next_token() {
+        if tok == Token::EOF {
+            break;
+        }
+    }
+});
@@ -0,0 +1,10 @@
+// Tokenize and parse arbitrary UTF-8; malformed input must produce an error, never a panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use stellang::lang::parser::Parser;
+
+fuzz_target!(|source: &str| {
+    let _ = Parser::parse_source(source);
+});
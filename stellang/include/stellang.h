@@ -0,0 +1,55 @@
+/*
+ * C API for embedding the StelLang interpreter.
+ *
+ * Link against the `stellang` cdylib (libstellang.so / stellang.dll /
+ * libstellang.dylib). Keep in sync with src/ffi.rs; regenerate with
+ * `cbindgen --lang c --output include/stellang.h` after changing it.
+ */
+
+#ifndef STELLANG_H
+#define STELLANG_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Result codes returned by stellang_eval. */
+typedef enum StelStatus {
+  STEL_STATUS_OK = 0,
+  /* A null pointer or non-UTF-8 source was passed in */
+  STEL_STATUS_INVALID_ARGUMENT = 1,
+  /* The source failed to lex or parse */
+  STEL_STATUS_SYNTAX_ERROR = 2,
+  /* Evaluation raised an exception */
+  STEL_STATUS_RUNTIME_ERROR = 3,
+} StelStatus;
+
+/* Opaque interpreter handle owned by the host. */
+typedef struct StelInterpreter StelInterpreter;
+
+/* Create a new interpreter. Release it with stellang_free. */
+StelInterpreter *stellang_new(void);
+
+/* Evaluate a NUL-terminated UTF-8 source string. State persists between calls.
+ * Returns a StelStatus code. */
+int stellang_eval(StelInterpreter *interp, const char *source);
+
+/* Display string of the last successful result. Free with stellang_string_free. */
+char *stellang_get_string(const StelInterpreter *interp);
+
+/* "Kind: message" for the last failed evaluation, followed by " (line N)"
+ * when the line it came from is known, or NULL if it succeeded.
+ * Free with stellang_string_free. */
+char *stellang_get_error(const StelInterpreter *interp);
+
+/* Release a string returned by this library. */
+void stellang_string_free(char *s);
+
+/* Destroy an interpreter created by stellang_new. */
+void stellang_free(StelInterpreter *interp);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* STELLANG_H */
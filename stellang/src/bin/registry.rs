@@ -12,6 +12,9 @@ use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection, Reply};
 use std::convert::Infallible;
 use sha2::Digest;
+use stellang::cli::CliError;
+
+const PROGRAM: &str = "registry";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PackageMetadata {
@@ -19,10 +22,21 @@ struct PackageMetadata {
     version: String,
     description: Option<String>,
     authors: Option<Vec<String>>,
+    #[serde(default)]
+    license: Option<String>,
     dependencies: Option<HashMap<String, String>>,
     checksum: String,
     size: u64,
     upload_date: String,
+    // Missing from indexes written before downloads were counted
+    #[serde(default)]
+    downloads: u64,
+    // The `stellang` requirement and `edition` from stel.toml, so clients can
+    // skip releases their StelLang can't use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stellang: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edition: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,6 +45,12 @@ struct SearchResponse {
     total: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionsResponse {
+    name: String,
+    versions: Vec<PackageMetadata>,
+}
+
 struct RegistryState {
     packages: RwLock<HashMap<String, HashMap<String, PackageMetadata>>>,
     storage_path: PathBuf,
@@ -64,7 +84,7 @@ impl RegistryState {
     async fn add_package(&self, metadata: PackageMetadata, package_data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
         // Save package file
         let package_file = self.storage_path.join("packages").join(format!("{}-{}.tar.gz", metadata.name, metadata.version));
-        fs::create_dir_all(package_file.parent().unwrap())?;
+        fs::create_dir_all(self.storage_path.join("packages"))?;
         fs::write(&package_file, package_data)?;
 
         // Update metadata
@@ -81,6 +101,14 @@ impl RegistryState {
         packages.get(name)?.get(version).cloned()
     }
 
+    // Every published version of a package, newest first
+    async fn get_versions(&self, name: &str) -> Option<Vec<PackageMetadata>> {
+        let packages = self.packages.read().await;
+        let mut versions: Vec<PackageMetadata> = packages.get(name)?.values().cloned().collect();
+        versions.sort_by_cached_key(|metadata| std::cmp::Reverse(semver::Version::parse(&metadata.version).ok()));
+        Some(versions)
+    }
+
     async fn search_packages(&self, query: &str) -> Vec<PackageMetadata> {
         let packages = self.packages.read().await;
         let mut results = Vec::new();
@@ -100,15 +128,29 @@ impl RegistryState {
         let package_file = self.storage_path.join("packages").join(format!("{}-{}.tar.gz", name, version));
         fs::read(package_file).ok()
     }
+
+    // Count a download so that search results can show how popular a package is
+    async fn record_download(&self, name: &str, version: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut packages = self.packages.write().await;
+        if let Some(metadata) = packages.get_mut(name).and_then(|versions| versions.get_mut(version)) {
+            metadata.downloads += 1;
+        }
+        drop(packages);
+        self.save_packages().await
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let storage_path = PathBuf::from("registry_storage");
-    fs::create_dir_all(&storage_path).unwrap();
+    if let Err(source) = fs::create_dir_all(&storage_path) {
+        CliError::Write { path: storage_path.display().to_string(), source }.exit(PROGRAM);
+    }
 
     let state = Arc::new(RegistryState::new(storage_path.clone()));
-    state.load_packages().await.unwrap();
+    if let Err(e) = state.load_packages().await {
+        CliError::Other(format!("can't load package index from {}: {}", storage_path.display(), e)).exit(PROGRAM);
+    }
 
     println!("StelLang Registry Server starting on http://localhost:8080");
     println!("Storage path: {}", storage_path.display());
@@ -119,6 +161,11 @@ async fn main() {
         .and(with_state(state.clone()))
         .and_then(search_packages);
 
+    let package_versions_route = warp::path!("api" / "packages" / String)
+        .and(warp::get())
+        .and(with_state(state.clone()))
+        .and_then(get_package_versions);
+
     let package_info_route = warp::path!("api" / "packages" / String / String)
         .and(with_state(state.clone()))
         .and_then(get_package_info);
@@ -135,14 +182,17 @@ async fn main() {
         .and_then(publish_package);
 
     let routes = search_route
+        .or(package_versions_route)
         .or(package_info_route)
         .or(package_download_route)
         .or(publish_route)
+        .recover(handle_rejection)
         .with(warp::cors().allow_any_origin());
 
-    warp::serve(routes)
-        .run(([127, 0, 0, 1], 8080))
-        .await;
+    match warp::serve(routes).try_bind_ephemeral(([127, 0, 0, 1], 8080)) {
+        Ok((_, server)) => server.await,
+        Err(e) => CliError::Other(format!("can't listen on port 8080: {}", e)).exit(PROGRAM),
+    }
 }
 
 fn with_state(state: Arc<RegistryState>) -> impl Filter<Extract = (Arc<RegistryState>,), Error = Infallible> + Clone {
@@ -164,6 +214,16 @@ async fn search_packages(
     Ok(warp::reply::json(&response))
 }
 
+async fn get_package_versions(
+    name: String,
+    state: Arc<RegistryState>,
+) -> Result<impl Reply, Rejection> {
+    match state.get_versions(&name).await {
+        Some(versions) => Ok(warp::reply::json(&VersionsResponse { name, versions })),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
 async fn get_package_info(
     name: String,
     version: String,
@@ -181,7 +241,12 @@ async fn download_package(
     state: Arc<RegistryState>,
 ) -> Result<impl Reply, Rejection> {
     match state.get_package_file(&name, &version).await {
-        Some(data) => Ok(warp::reply::with_header(data, "Content-Type", "application/gzip")),
+        Some(data) => {
+            if let Err(e) = state.record_download(&name, &version).await {
+                eprintln!("{}: can't record download of {}@{}: {}", PROGRAM, name, version, e);
+            }
+            Ok(warp::reply::with_header(data, "Content-Type", "application/gzip"))
+        }
         None => Err(warp::reject::not_found()),
     }
 }
@@ -201,43 +266,71 @@ async fn publish_package(
         return Err(warp::reject::custom(AuthError));
     }
 
-    // Extract package metadata from the archive
     let package_data = package_data.to_vec();
-    let cursor = std::io::Cursor::new(&package_data);
-    let gz = flate2::read::GzDecoder::new(cursor);
+    let metadata = read_package_metadata(&package_data).map_err(|e| warp::reject::custom(InvalidPackage(e)))?;
+    if let Err(e) = state.add_package(metadata.clone(), package_data).await {
+        return Err(warp::reject::custom(StorageError(e.to_string())));
+    }
+    
+    Ok(warp::reply::json(&serde_json::json!({
+        "success": true,
+        "package": metadata
+    })))
+}
+
+// Publishing errors become JSON with a matching status; other rejections keep warp's defaults
+async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    use warp::http::StatusCode;
+    let (status, message) = if let Some(e) = err.find::<AuthError>() {
+        (StatusCode::UNAUTHORIZED, e.to_string())
+    } else if let Some(InvalidPackage(message)) = err.find() {
+        (StatusCode::BAD_REQUEST, message.clone())
+    } else if let Some(StorageError(message)) = err.find() {
+        (StatusCode::INTERNAL_SERVER_ERROR, message.clone())
+    } else {
+        return Err(err);
+    };
+    let body = warp::reply::json(&serde_json::json!({ "success": false, "error": message }));
+    Ok(warp::reply::with_status(body, status))
+}
+
+// Metadata from the stel.toml inside an uploaded package archive
+fn read_package_metadata(package_data: &[u8]) -> Result<PackageMetadata, String> {
+    let gz = flate2::read::GzDecoder::new(std::io::Cursor::new(package_data));
     let mut tar = tar::Archive::new(gz);
     
     let mut manifest_content = Vec::new();
-    for entry in tar.entries().unwrap() {
-        let mut entry = entry.unwrap();
-        if entry.path().unwrap().to_str().unwrap() == "stel.toml" {
-            std::io::copy(&mut entry, &mut manifest_content).unwrap();
+    let entries = tar.entries().map_err(|e| format!("not a .tar.gz archive: {}", e))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("corrupt archive: {}", e))?;
+        if entry.path().is_ok_and(|path| path == Path::new("stel.toml")) {
+            std::io::copy(&mut entry, &mut manifest_content).map_err(|e| format!("corrupt archive: {}", e))?;
             break;
         }
     }
-    let manifest_str = String::from_utf8(manifest_content).unwrap();
-    let manifest: serde_json::Value = toml::from_str(&manifest_str).unwrap();
+    let manifest_str = String::from_utf8(manifest_content).map_err(|_| "stel.toml is not valid UTF-8".to_string())?;
+    let manifest: serde_json::Value = toml::from_str(&manifest_str).map_err(|e| format!("invalid stel.toml: {}", e))?;
     let package_info = &manifest["package"];
+    let field = |name: &str| package_info[name].as_str().map(str::to_string).ok_or_else(|| format!("stel.toml has no package.{}", name));
     
-    let metadata = PackageMetadata {
-        name: package_info["name"].as_str().unwrap().to_string(),
-        version: package_info["version"].as_str().unwrap().to_string(),
+    Ok(PackageMetadata {
+        name: field("name")?,
+        version: field("version")?,
         description: package_info["description"].as_str().map(|s| s.to_string()),
         authors: package_info["authors"].as_array().map(|arr| {
-            arr.iter().map(|v| v.as_str().unwrap().to_string()).collect()
+            arr.iter().filter_map(|v| v.as_str()).map(str::to_string).collect()
+        }),
+        license: package_info["license"].as_str().map(str::to_string),
+        dependencies: manifest["dependencies"].as_object().map(|deps| {
+            deps.iter().filter_map(|(name, req)| Some((name.clone(), req.as_str()?.to_string()))).collect()
         }),
-        dependencies: None, // TODO: Extract dependencies
-        checksum: format!("sha256:{}", hex::encode(sha2::Sha256::digest(&package_data))),
+        checksum: format!("sha256:{}", hex::encode(sha2::Sha256::digest(package_data))),
         size: package_data.len() as u64,
         upload_date: chrono::Utc::now().to_rfc3339(),
-    };
-    
-    state.add_package(metadata.clone(), package_data).await.unwrap();
-    
-    Ok(warp::reply::json(&serde_json::json!({
-        "success": true,
-        "package": metadata
-    })))
+        downloads: 0,
+        stellang: package_info["stellang"].as_str().map(str::to_string),
+        edition: package_info["edition"].as_str().map(str::to_string),
+    })
 }
 
 #[derive(Debug)]
@@ -249,4 +342,15 @@ impl std::fmt::Display for AuthError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Authentication failed")
     }
-} 
\ No newline at end of file
+} 
+/// An upload that is not a readable package archive with a valid stel.toml.
+#[derive(Debug)]
+struct InvalidPackage(String);
+
+impl warp::reject::Reject for InvalidPackage {}
+
+/// The package was valid but could not be stored.
+#[derive(Debug)]
+struct StorageError(String);
+
+impl warp::reject::Reject for StorageError {}
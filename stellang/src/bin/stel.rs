@@ -3,18 +3,26 @@
 //! A comprehensive package manager for StelLang with dependency resolution,
 //! lockfiles, registry integration, and project management.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use serde::{Deserialize, Serialize};
 use toml;
 use semver::{VersionReq, Version};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use tar::Builder;
-use std::io::Cursor;
+use stellang::lang::hashing::ValueMap;
+use stellang::lang::interpreter::Value;
+use stellang::lang::native::{self, NATIVE_DEPS_DIR};
+use stellang::lang::stdlib::template;
+use stellang::cli::CliError;
+use stellang::crash;
+use stellang::ignore::IgnoreRules;
 
 // Configuration
 const STEL_REGISTRY_URL: &str = "https://stellang.maheshdhingra.xyz/registry";
@@ -22,12 +30,95 @@ const STEL_CONFIG_DIR: &str = ".stel";
 const STEL_LOCK_FILE: &str = "stel.lock";
 const STEL_MANIFEST_FILE: &str = "stel.toml";
 const STEL_CACHE_DIR: &str = ".stel/cache";
+// Build script output, one directory per package
+const STEL_OUT_DIR: &str = ".stel/out";
+// Lockfile source of a dependency taken from a local directory by `stel link`
+const PATH_SOURCE_PREFIX: &str = "path+";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct PackageManifest {
     package: PackageInfo,
     dependencies: Option<HashMap<String, String>>,
     dev_dependencies: Option<HashMap<String, String>>,
+    /// Shared-library extension modules: name -> version or local path
+    native_dependencies: Option<HashMap<String, String>>,
+    /// `[[bin]]` entry points; without any, the project runs src/main.stel
+    #[serde(default, rename = "bin", skip_serializing_if = "Vec::is_empty")]
+    bins: Vec<BinTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<Profiles>,
+}
+
+/// `[profile.dev]` and `[profile.release]`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct Profiles {
+    dev: Option<ProfileSettings>,
+    release: Option<ProfileSettings>,
+}
+
+/// One profile as written in stel.toml; unset keys take the profile's defaults.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct ProfileSettings {
+    /// Run the optimizer passes over the AST
+    optimize: Option<bool>,
+    /// What `cfg("debug")` and `__debug__` report to the script
+    debug_assertions: Option<bool>,
+    /// Keep parsed (and optimized) programs in .stel/cache and reuse them while the source is unchanged
+    cache: Option<bool>,
+}
+
+/// A profile with its defaults filled in.
+struct Profile {
+    name: &'static str,
+    optimize: bool,
+    debug_assertions: bool,
+    cache: bool,
+}
+
+impl Profile {
+    fn describe(&self) -> String {
+        let mut traits = vec![if self.optimize { "optimized" } else { "unoptimized" }];
+        if self.debug_assertions {
+            traits.push("debug assertions");
+        }
+        if self.cache {
+            traits.push("cached");
+        }
+        format!("{} [{}]", self.name, traits.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BinTarget {
+    name: String,
+    path: String,
+}
+
+impl PackageManifest {
+    /// The release profile for `--release`, the dev profile otherwise.
+    fn profile(&self, release: bool) -> Profile {
+        let profiles = self.profile.clone().unwrap_or_default();
+        let (name, settings, optimize, debug_assertions, cache) = match release {
+            true => ("release", profiles.release, true, false, true),
+            false => ("dev", profiles.dev, false, true, false),
+        };
+        let settings = settings.unwrap_or_default();
+        Profile {
+            name,
+            optimize: settings.optimize.unwrap_or(optimize),
+            debug_assertions: settings.debug_assertions.unwrap_or(debug_assertions),
+            cache: settings.cache.unwrap_or(cache),
+        }
+    }
+
+    /// The declared `[[bin]]` targets, or src/main.stel under the package name.
+    fn bin_targets(&self) -> Vec<BinTarget> {
+        if self.bins.is_empty() {
+            vec![BinTarget { name: self.package.name.clone(), path: "src/main.stel".to_string() }]
+        } else {
+            self.bins.clone()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,8 +130,64 @@ struct PackageInfo {
     license: Option<String>,
     repository: Option<String>,
     keywords: Option<Vec<String>>,
+    /// Unstable language features the package's code needs, as `--enable-feature` takes them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
+    /// A script run before the package is used, such as `build = "build.stel"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    build: Option<String>,
+    /// The StelLang versions the package works with, such as `stellang = ">=0.1, <0.3"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stellang: Option<String>,
+    /// The language edition the package is written for; see `EDITIONS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edition: Option<String>,
+}
+
+/// `.stel/overrides.toml`: dependencies that `stel link <name>` points at a
+/// local directory instead of the registry.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Overrides {
+    #[serde(default)]
+    overrides: HashMap<String, String>,
+}
+
+/// `links.toml` in the stel home: libraries registered by `stel link`, by name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Links {
+    #[serde(default)]
+    links: HashMap<String, String>,
+}
+
+/// `config.toml` in the stel home: settings for every project of the user.
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    registry: RegistryConfig,
+}
+
+/// `[registry]`: how long to wait for the registry, and the mirrors to fall
+/// back to, in order, when it is down.
+#[derive(Debug, Default, Deserialize)]
+struct RegistryConfig {
+    /// Seconds to wait for a request before trying the next mirror
+    timeout: Option<u64>,
+    #[serde(default)]
+    mirrors: Vec<Mirror>,
+}
+
+/// `[[registry.mirrors]]`: a copy of the registry, with its own timeout if it needs one.
+#[derive(Debug, Clone, Deserialize)]
+struct Mirror {
+    url: String,
+    timeout: Option<u64>,
 }
 
+/// How long a registry request may take when config.toml doesn't say. For a
+/// download it bounds connecting and each wait for more data instead, so a
+/// large archive on a slow link still finishes.
+const DEFAULT_REGISTRY_TIMEOUT: u64 = 30;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct LockFile {
     version: String,
@@ -53,6 +200,9 @@ struct LockedPackage {
     source: String,
     dependencies: Option<HashMap<String, String>>,
     checksum: Option<String>,
+    /// sha256 of the files installed in dependencies/, which `stel verify` checks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    integrity: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,9 +211,22 @@ struct RegistryPackage {
     version: String,
     description: Option<String>,
     authors: Option<Vec<String>>,
+    #[serde(default)]
+    license: Option<String>,
     dependencies: Option<HashMap<String, String>>,
+    // The registry's search results carry neither of these
+    #[serde(default)]
     download_url: String,
     checksum: Option<String>,
+    #[serde(default)]
+    downloads: u64,
+    #[serde(default)]
+    upload_date: Option<String>,
+    /// The release's `stellang` requirement and `edition`, from its stel.toml
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stellang: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edition: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -72,21 +235,88 @@ struct RegistrySearchResponse {
     total: usize,
 }
 
+/// Every published version of one package, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+struct RegistryPackageVersions {
+    name: String,
+    versions: Vec<RegistryPackage>,
+}
+
+#[derive(Clone)]
 struct StelCLI {
     config_dir: PathBuf,
     cache_dir: PathBuf,
     registry_url: String,
+    /// The registry and then its mirrors, in the order requests try them
+    registries: Vec<Mirror>,
 }
 
 impl StelCLI {
     fn new() -> Self {
         let config_dir = PathBuf::from(STEL_CONFIG_DIR);
         let cache_dir = config_dir.join("cache");
-        Self {
-            config_dir,
-            cache_dir,
-            registry_url: STEL_REGISTRY_URL.to_string(),
+        // STEL_REGISTRY points stel at another registry, such as a local one
+        let registry_url = env::var("STEL_REGISTRY").unwrap_or_else(|_| STEL_REGISTRY_URL.to_string());
+        let config_path = stel_home().join("config.toml");
+        let config: UserConfig = read_toml_or_default(&config_path).unwrap_or_else(|e| {
+            eprintln!("warning: ignoring {}: {}", config_path.display(), e);
+            UserConfig::default()
+        });
+        let timeout = config.registry.timeout;
+        let primary = Mirror { url: registry_url.clone(), timeout };
+        let mirrors = config.registry.mirrors.into_iter().map(|mirror| Mirror { timeout: mirror.timeout.or(timeout), ..mirror });
+        Self { config_dir, cache_dir, registry_url, registries: std::iter::once(primary).chain(mirrors).collect() }
+    }
+
+    /// GET `path` from the registry, falling back to each mirror in turn when
+    /// one can't be reached, times out or has a server error. Returns the
+    /// response and the URL of the registry that gave it. The whole request,
+    /// body included, must finish within the registry's timeout.
+    async fn registry_get(&self, path: &str) -> Result<(reqwest::Response, String), Box<dyn std::error::Error>> {
+        self.registry_request(path, 0, true).await.map(|(response, registry, _)| (response, registry))
+    }
+
+    /// Start a download of `path` like `registry_get`, asking for the body from
+    /// byte `offset` on when it isn't 0. Only connecting and the wait for the
+    /// headers are timed here; read the body with `read_chunk` and the returned
+    /// timeout, which bounds each wait for more data rather than the whole body.
+    async fn registry_download(&self, path: &str, offset: u64) -> Result<(reqwest::Response, String, Duration), Box<dyn std::error::Error>> {
+        self.registry_request(path, offset, false).await
+    }
+
+    async fn registry_request(&self, path: &str, offset: u64, whole: bool) -> Result<(reqwest::Response, String, Duration), Box<dyn std::error::Error>> {
+        let mut failures = Vec::new();
+        for (i, registry) in self.registries.iter().enumerate() {
+            let timeout = Duration::from_secs(registry.timeout.unwrap_or(DEFAULT_REGISTRY_TIMEOUT));
+            let client = reqwest::Client::builder().connect_timeout(timeout).build()?;
+            let mut request = client.get(format!("{}{}", registry.url.trim_end_matches('/'), path)).header("User-Agent", "stel-cli/1.0");
+            if whole {
+                request = request.timeout(timeout);
+            }
+            if offset > 0 {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+            }
+            let sent = match tokio::time::timeout(timeout, request.send()).await {
+                Ok(sent) => sent.map_err(|e| if e.is_timeout() { None } else { Some(e.to_string()) }),
+                Err(_) => Err(None),
+            };
+            let failure = match sent {
+                Ok(response) if !response.status().is_server_error() && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                    if i > 0 {
+                        eprintln!("{} served by mirror {}", path, registry.url);
+                    }
+                    return Ok((response, registry.url.clone(), timeout));
+                }
+                Ok(response) => response.status().to_string(),
+                Err(None) => format!("no answer within {}s", timeout.as_secs()),
+                Err(Some(e)) => e,
+            };
+            if let Some(next) = self.registries.get(i + 1) {
+                eprintln!("warning: {} failed for {} ({}); trying mirror {}", registry.url, path, failure, next.url);
+            }
+            failures.push(format!("{}: {}", registry.url, failure));
         }
+        Err(format!("no registry could answer {}: {}", path, failures.join("; ")).into())
     }
 
     fn ensure_config_dir(&self) -> io::Result<()> {
@@ -111,6 +341,9 @@ impl StelCLI {
         let content = fs::read_to_string(manifest_path)?;
         let manifest: PackageManifest = toml::from_str(&content)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for feature in &manifest.package.features {
+            stellang::lang::features::enable(feature).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
         Ok(manifest)
     }
 
@@ -143,14 +376,17 @@ impl StelCLI {
         Ok(())
     }
 
+    fn read_overrides(&self) -> io::Result<Overrides> {
+        read_toml_or_default(&self.config_dir.join("overrides.toml"))
+    }
+
+    fn write_overrides(&self, overrides: &Overrides) -> io::Result<()> {
+        fs::create_dir_all(&self.config_dir)?;
+        write_toml(&self.config_dir.join("overrides.toml"), overrides)
+    }
+
     async fn search_registry(&self, query: &str) -> Result<Vec<RegistryPackage>, Box<dyn std::error::Error>> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/search?q={}", self.registry_url, query);
-        
-        let response = client.get(&url)
-            .header("User-Agent", "stel-cli/1.0")
-            .send()
-            .await?;
+        let (response, _) = self.registry_get(&format!("/api/search?q={}", query)).await?;
         
         if response.status().is_success() {
             let search_response: RegistrySearchResponse = response.json().await?;
@@ -165,18 +401,28 @@ impl StelCLI {
                         version: "1.0.0".to_string(),
                         description: Some("HTTP client library for StelLang".to_string()),
                         authors: Some(vec!["stellang-team".to_string()]),
+                        license: None,
                         dependencies: Some(HashMap::new()),
                         download_url: "https://example.com/example-http-1.0.0.tar.gz".to_string(),
                         checksum: Some("sha256:abc123...".to_string()),
+                        downloads: 0,
+                        upload_date: None,
+                        stellang: None,
+                        edition: None,
                     },
                     RegistryPackage {
                         name: "example-json".to_string(),
                         version: "2.1.0".to_string(),
                         description: Some("JSON parsing library for StelLang".to_string()),
                         authors: Some(vec!["stellang-team".to_string()]),
+                        license: None,
                         dependencies: Some(HashMap::new()),
                         download_url: "https://example.com/example-json-2.1.0.tar.gz".to_string(),
                         checksum: Some("sha256:def456...".to_string()),
+                        downloads: 0,
+                        upload_date: None,
+                        stellang: None,
+                        edition: None,
                     }
                 ])
             } else {
@@ -186,13 +432,7 @@ impl StelCLI {
     }
 
     async fn get_package_info(&self, name: &str, version: &str) -> Result<RegistryPackage, Box<dyn std::error::Error>> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/packages/{}/{}", self.registry_url, name, version);
-        
-        let response = client.get(&url)
-            .header("User-Agent", "stel-cli/1.0")
-            .send()
-            .await?;
+        let (response, _) = self.registry_get(&format!("/api/packages/{}/{}", name, version)).await?;
         
         if response.status().is_success() {
             let package: RegistryPackage = response.json().await?;
@@ -205,9 +445,14 @@ impl StelCLI {
                     version: version.to_string(),
                     description: Some(format!("Mock package {} {}", name, version)),
                     authors: Some(vec!["stellang-team".to_string()]),
+                    license: None,
                     dependencies: Some(HashMap::new()),
                     download_url: format!("https://example.com/{}-{}.tar.gz", name, version),
                     checksum: Some("sha256:mock123...".to_string()),
+                    downloads: 0,
+                    upload_date: None,
+                    stellang: None,
+                    edition: None,
                 })
             } else {
                 Err(format!("Package not found: {}@{}", name, version).into())
@@ -215,27 +460,118 @@ impl StelCLI {
         }
     }
 
-    async fn download_package(&self, name: &str, version: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/packages/{}/{}/download", self.registry_url, name, version);
-        
-        let response = client.get(&url)
-            .header("User-Agent", "stel-cli/1.0")
-            .send()
-            .await?;
-        
-        if response.status().is_success() {
-            let bytes = response.bytes().await?;
-            Ok(bytes.to_vec())
+    /// The newest release of `name` matching `req` that this StelLang can
+    /// use: releases whose `stellang` requirement or `edition` rule it out are
+    /// skipped. A registry that can't list the versions is asked for
+    /// `version_req` directly.
+    async fn resolve_version(&self, name: &str, req: &VersionReq, version_req: &str) -> Result<RegistryPackage, Box<dyn std::error::Error>> {
+        let Ok(listing) = self.get_package_versions(name).await else {
+            return self.get_package_info(name, version_req).await;
+        };
+        let mut candidates: Vec<(Version, RegistryPackage)> = listing.versions.into_iter().filter_map(|package| Some((Version::parse(&package.version).ok()?, package))).filter(|(version, _)| req.matches(version)).collect();
+        candidates.sort_by(|a, b| b.0.cmp(&a.0));
+        let mut skipped = Vec::new();
+        for (_, package) in candidates {
+            match toolchain_mismatch(package.stellang.as_deref(), package.edition.as_deref()) {
+                Some(reason) => {
+                    eprintln!("Skipping {}@{}: it {}", name, package.version, reason);
+                    skipped.push(package.version);
+                }
+                None => return Ok(package),
+            }
+        }
+        if skipped.is_empty() {
+            Err(format!("No version of {} matches requirement {}", name, version_req).into())
         } else {
-            // For development, create a mock package
-            if response.status().as_u16() == 404 {
-                println!("Creating mock package for {}@{}", name, version);
-                self.create_mock_package(name, version)
-            } else {
-                Err(format!("Download failed: {}", response.status()).into())
+            Err(format!("No version of {} matching {} works with StelLang {} (skipped {})", name, version_req, STELLANG_VERSION, skipped.join(", ")).into())
+        }
+    }
+
+    async fn get_package_versions(&self, name: &str) -> Result<RegistryPackageVersions, Box<dyn std::error::Error>> {
+        let (response, _) = self.registry_get(&format!("/api/packages/{}", name)).await?;
+
+        match response.status().as_u16() {
+            200..=299 => Ok(response.json().await?),
+            404 => Err(format!("package '{}' not found in the registry", name).into()),
+            status => Err(format!("Registry request failed: {}", status).into()),
+        }
+    }
+
+    /// Download a package's archive into the cache, streaming it to disk, and
+    /// return its path. An archive downloaded earlier is reused, and one left
+    /// partial by an interrupted install is resumed where it stopped.
+    async fn download_package(&self, name: &str, version: &str, progress: &Progress) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let archive = self.cache_dir.join(format!("{}-{}.tar.gz", name, version));
+        if archive.is_file() {
+            return Ok(archive);
+        }
+        fs::create_dir_all(&self.cache_dir)?;
+        let partial = self.cache_dir.join(format!("{}-{}.tar.gz.part", name, version));
+        let label = format!("{}@{}", name, version);
+        let path = format!("/api/packages/{}/{}/download", name, version);
+        loop {
+            let offset = fs::metadata(&partial).map(|metadata| metadata.len()).unwrap_or(0);
+            let (mut response, registry, idle) = self.registry_download(&path, offset).await?;
+            let (mut file, mut done) = match response.status() {
+                reqwest::StatusCode::PARTIAL_CONTENT => (fs::OpenOptions::new().append(true).open(&partial)?, offset),
+                // A registry that ignores the range sends everything again
+                status if status.is_success() => (fs::File::create(&partial)?, 0),
+                // The partial file is no prefix of the archive; start over
+                reqwest::StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                    fs::remove_file(&partial)?;
+                    continue;
+                }
+                // For development, create a mock package
+                reqwest::StatusCode::NOT_FOUND => {
+                    println!("Creating mock package for {}", label);
+                    fs::write(&archive, self.create_mock_package(name, version)?)?;
+                    return Ok(archive);
+                }
+                status => return Err(format!("Download failed: {}", status).into()),
+            };
+            let total = response.content_length().map(|length| length + done);
+            let bar = progress.start(&label, done, total);
+            while let Some(chunk) = read_chunk(&mut response, idle).await? {
+                file.write_all(&chunk)?;
+                done += chunk.len() as u64;
+                progress.update(bar, done);
+            }
+            file.sync_all()?;
+            fs::rename(&partial, &archive)?;
+            progress.finish(bar, &registry);
+            return Ok(archive);
+        }
+    }
+
+    /// Download the archives of the registry packages in `lockfile` that the
+    /// package store lacks, `jobs` at a time, so installing them afterwards
+    /// needs no network.
+    async fn download_all(&self, lockfile: &LockFile, jobs: usize) -> Result<(), String> {
+        let progress = Arc::new(Progress::new());
+        let limit = Arc::new(tokio::sync::Semaphore::new(jobs.max(1)));
+        let mut downloads = tokio::task::JoinSet::new();
+        for (name, locked) in lockfile.packages.iter().filter(|(name, locked)| !locked.source.starts_with(PATH_SOURCE_PREFIX) && stored_package(name, locked).is_none()) {
+            let (cli, progress, limit) = (self.clone(), Arc::clone(&progress), Arc::clone(&limit));
+            let (name, version) = (name.clone(), locked.version.clone());
+            downloads.spawn(async move {
+                let _permit = limit.acquire_owned().await.map_err(|e| e.to_string())?;
+                let downloaded = cli.download_package(&name, &version, &progress).await;
+                downloaded.map(|_| ()).map_err(|e| format!("{}@{}: {}", name, version, e))
+            });
+        }
+        let mut failures = Vec::new();
+        while let Some(result) = downloads.join_next().await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => failures.push(e),
+                Err(e) => failures.push(e.to_string()),
             }
         }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("; "))
+        }
     }
 
     fn create_mock_package(&self, name: &str, version: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
@@ -292,6 +628,7 @@ fn version() {{
 
     async fn resolve_dependencies(&self, manifest: &PackageManifest) -> Result<LockFile, Box<dyn std::error::Error>> {
         let mut lockfile = self.read_lockfile()?;
+        let overrides = self.read_overrides()?.overrides;
         let mut resolved = HashMap::new();
         let mut to_resolve = Vec::new();
         
@@ -307,12 +644,29 @@ fn version() {{
             if resolved.contains_key(&name) {
                 continue; // Already resolved
             }
+
+            // A linked package is used as it is on disk, whatever version it has
+            if let Some(path) = overrides.get(&name) {
+                let linked: PackageManifest = toml::from_str(&fs::read_to_string(Path::new(path).join(STEL_MANIFEST_FILE))?)
+                    .map_err(|e| format!("Invalid stel.toml in linked package {} ({}): {}", name, path, e))?;
+                for (sub_name, sub_version) in linked.dependencies.iter().flatten() {
+                    to_resolve.push((sub_name.clone(), sub_version.clone()));
+                }
+                resolved.insert(name.clone(), LockedPackage {
+                    version: linked.package.version,
+                    source: format!("{}{}", PATH_SOURCE_PREFIX, path),
+                    dependencies: linked.dependencies,
+                    checksum: None,
+                    integrity: None,
+                });
+                continue;
+            }
             
             let req = VersionReq::parse(&version_req)
                 .map_err(|e| format!("Invalid version requirement for {}: {}", name, e))?;
             
             // Try to get package info from registry
-            let package_info = self.get_package_info(&name, &version_req).await?;
+            let package_info = self.resolve_version(&name, &req, &version_req).await?;
             
             // Validate version constraint
             let package_version = Version::parse(&package_info.version)
@@ -331,11 +685,24 @@ fn version() {{
                 }
             }
             
+            // The version stel.lock has keeps its integrity hash, and the registry may not swap its archive
+            let previous = lockfile.packages.get(&name).filter(|previous| previous.version == package_info.version);
+            if let Some(previous) = previous.filter(|previous| previous.checksum.is_some() && previous.checksum != package_info.checksum) {
+                return Err(format!(
+                    "the registry's archive of {}@{} has changed since stel.lock was written (stel.lock has {}, the registry has {})",
+                    name,
+                    previous.version,
+                    previous.checksum.as_deref().unwrap_or_default(),
+                    package_info.checksum.as_deref().unwrap_or("no checksum")
+                )
+                .into());
+            }
             resolved.insert(name.clone(), LockedPackage {
                 version: package_info.version,
                 source: format!("registry+{}", self.registry_url),
                 dependencies: package_info.dependencies,
                 checksum: package_info.checksum,
+                integrity: previous.and_then(|previous| previous.integrity.clone()),
             });
         }
         
@@ -343,38 +710,95 @@ fn version() {{
         Ok(lockfile)
     }
 
-    async fn install_package(&self, name: &str, version: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let package_data = self.download_package(name, version).await?;
-        
-        // Create package directory
-        let package_dir = self.cache_dir.join(format!("{}-{}", name, version));
-        if package_dir.exists() {
-            fs::remove_dir_all(&package_dir)?;
+    /// Install a registry package from the package store, downloading and
+    /// storing it first unless the store already has the locked version.
+    async fn install_package(&self, name: &str, locked: &LockedPackage) -> Result<(), Box<dyn std::error::Error>> {
+        let stored = match stored_package(name, locked) {
+            Some(stored) => stored,
+            None => {
+                use sha2::Digest;
+                let archive = self.download_package(name, &locked.version, &Progress::new()).await?;
+                let digest = hex::encode(sha2::Sha256::digest(fs::read(&archive)?));
+                // A corrupt or substituted download is dropped, so the next install fetches it again
+                if let Some(expected) = locked_digest(locked).filter(|expected| *expected != digest) {
+                    fs::remove_file(&archive)?;
+                    return Err(format!("checksum mismatch: stel.lock has sha256:{}, the download has sha256:{}", expected, digest).into());
+                }
+                let stored = store_package(name, &locked.version, &archive, &digest)?;
+                fs::remove_file(&archive)?;
+                stored
+            }
+        };
+
+        let target = Path::new("dependencies").join(name);
+        if target.is_symlink() {
+            fs::remove_file(&target).or_else(|_| fs::remove_dir(&target))?;
+        } else if target.exists() {
+            fs::remove_dir_all(&target)?;
         }
-        fs::create_dir_all(&package_dir)?;
-        
-        // Extract package
-        let cursor = Cursor::new(package_data);
-        let gz = flate2::read::GzDecoder::new(cursor);
-        let mut tar = tar::Archive::new(gz);
-        tar.unpack(&package_dir)?;
-        
-        // Copy to project's dependencies directory
-        let deps_dir = Path::new("dependencies");
-        if !deps_dir.exists() {
-            fs::create_dir(deps_dir)?;
+        link_tree(&stored, &target)?;
+
+        println!("Installed {}@{} to dependencies/{}", name, locked.version, name);
+        Ok(())
+    }
+
+    /// Install a package as the lockfile describes it: downloaded from the
+    /// registry, or symlinked to its directory when it was linked. Then run
+    /// its build script, if it has one. Returns the integrity hash of an
+    /// installed registry package, which must be the one the lockfile has
+    /// when it has one.
+    async fn install_locked(&self, name: &str, locked: &LockedPackage) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let dir = Path::new("dependencies").join(name);
+        let integrity = match locked.source.strip_prefix(PATH_SOURCE_PREFIX) {
+            Some(path) => {
+                link_dependency(name, Path::new(path))?;
+                println!("Linked {} to {}", name, path);
+                None
+            }
+            None => {
+                self.install_package(name, locked).await?;
+                let integrity = hash_tree(&dir, &|_| false)?;
+                if let Some(expected) = locked.integrity.as_ref().filter(|expected| **expected != integrity) {
+                    return Err(format!("the installed files don't match the integrity hash in stel.lock (stel.lock has {}, the files hash to {})", expected, integrity).into());
+                }
+                Some(integrity)
+            }
+        };
+        let manifest_path = dir.join(STEL_MANIFEST_FILE);
+        if manifest_path.exists() {
+            let manifest: PackageManifest = toml::from_str(&fs::read_to_string(&manifest_path)?).map_err(|e| format!("Invalid {}: {}", manifest_path.display(), e))?;
+            if let Some(reason) = toolchain_mismatch(manifest.package.stellang.as_deref(), manifest.package.edition.as_deref()) {
+                return Err(format!("{} {}", name, reason).into());
+            }
+            run_build_script(&manifest.package, &dir)?;
         }
-        
-        let target_dir = deps_dir.join(name);
-        if target_dir.exists() {
-            fs::remove_dir_all(&target_dir)?;
+        Ok(integrity)
+    }
+
+    async fn install_native(&self, name: &str, source: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let native_dir = Path::new(NATIVE_DEPS_DIR);
+        fs::create_dir_all(native_dir)?;
+        let target = native_dir.join(native::library_filename(name));
+
+        // A path to a locally built cdylib is copied as-is
+        let local = Path::new(source);
+        if local.is_file() {
+            fs::copy(local, &target)?;
+            println!("Installed native module {} from {}", name, local.display());
+            return Ok(());
         }
-        fs::create_dir_all(&target_dir)?;
-        
-        // Copy package contents
-        self.copy_directory(&package_dir, &target_dir)?;
-        
-        println!("Installed {}@{} to dependencies/{}", name, version, name);
+
+        let platform = format!("{}-{}", env::consts::ARCH, env::consts::OS);
+        let (mut response, registry, idle) = self.registry_download(&format!("/api/packages/{}/{}/native/{}", name, source, platform), 0).await?;
+        if !response.status().is_success() {
+            return Err(format!("no {} build of native module {}@{}: {}", platform, name, source, response.status()).into());
+        }
+        let mut library = Vec::new();
+        while let Some(chunk) = read_chunk(&mut response, idle).await? {
+            library.extend_from_slice(&chunk);
+        }
+        fs::write(&target, library)?;
+        println!("Installed native module {}@{} from {} to {}", name, source, registry, target.display());
         Ok(())
     }
 
@@ -397,7 +821,9 @@ fn version() {{
         Ok(())
     }
 
+    // The manifest, src/ and README.md, less what .stelignore lists
     fn create_package_archive(&self, manifest: &PackageManifest) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let rules = IgnoreRules::load(Path::new("."))?;
         let mut buffer = Vec::new();
         let gz = GzEncoder::new(&mut buffer, Compression::default());
         let mut tar = Builder::new(gz);
@@ -414,12 +840,12 @@ fn version() {{
         // Add source files
         let src_dir = Path::new("src");
         if src_dir.exists() {
-            self.add_directory_to_tar(&mut tar, src_dir, "src")?;
+            self.add_directory_to_tar(&mut tar, src_dir, "src", &rules)?;
         }
         
         // Add README if exists
         let readme_path = Path::new("README.md");
-        if readme_path.exists() {
+        if readme_path.exists() && !rules.is_ignored(readme_path, false) {
             let readme_content = fs::read_to_string(readme_path)?;
             let readme_bytes = readme_content.as_bytes();
             let mut header = tar::Header::new_gnu();
@@ -434,15 +860,21 @@ fn version() {{
         Ok(buffer)
     }
 
-    fn add_directory_to_tar(&self, tar: &mut Builder<GzEncoder<&mut Vec<u8>>>, src: &Path, prefix: &str) -> io::Result<()> {
+    fn add_directory_to_tar(&self, tar: &mut Builder<GzEncoder<&mut Vec<u8>>>, src: &Path, prefix: &str, rules: &IgnoreRules) -> io::Result<()> {
         for entry in fs::read_dir(src)? {
             let entry = entry?;
             let path = entry.path();
-            let name = path.file_name().unwrap().to_str().unwrap();
+            let name = entry.file_name();
+            let name = name.to_str().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("file name {} is not valid UTF-8", path.display()))
+            })?;
             let tar_path = format!("{}/{}", prefix, name);
+            if rules.is_ignored(Path::new(&tar_path), path.is_dir()) {
+                continue;
+            }
             
             if path.is_dir() {
-                self.add_directory_to_tar(tar, &path, &tar_path)?;
+                self.add_directory_to_tar(tar, &path, &tar_path, rules)?;
             } else {
                 let content = fs::read(&path)?;
                 let mut header = tar::Header::new_gnu();
@@ -456,44 +888,109 @@ fn version() {{
     }
 }
 
+/// The next piece of a download's body, or an error when the registry sends
+/// nothing for `idle`.
+async fn read_chunk(response: &mut reqwest::Response, idle: Duration) -> Result<Option<bytes::Bytes>, Box<dyn std::error::Error>> {
+    match tokio::time::timeout(idle, response.chunk()).await {
+        Ok(chunk) => Ok(chunk?),
+        Err(_) => Err(format!("the registry sent nothing for {}s", idle.as_secs()).into()),
+    }
+}
+
 #[tokio::main]
 async fn main() {
+    crash::install("stel", PathBuf::from(crash::CRASH_DIR));
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("stel: missing command");
-        eprintln!("Try 'stel help' for more information");
-        std::process::exit(1);
+        CliError::Usage("missing command\nTry 'stel help' for more information".to_string()).exit("stel");
     }
 
     let cli = StelCLI::new();
     
     match args[1].as_str() {
         "init" => cmd_init(&cli),
-        "add" => cmd_add(&cli, &args[2..]),
-        "build" => cmd_build(&cli),
-        "install" => cmd_install(&cli).await,
-        "test" => cmd_test(&cli),
+        "add" => cmd_add(&cli, &args[2..]).await,
+        "build" => cmd_build(&cli, &args[2..]),
+        "install" => cmd_install(&cli, &args[2..]).await,
+        "test" => cmd_test(&cli, &args[2..]),
         "update" => cmd_update(&cli).await,
-        "publish" => cmd_publish(&cli).await,
+        "publish" => cmd_publish(&cli, &args[2..]).await,
         "new" => cmd_new(&cli, &args[2..]),
         "template" => cmd_template(&cli, &args[2..]),
         "search" => cmd_search(&cli, &args[2..]).await,
+        "info" => cmd_info(&cli, &args[2..]).await,
         "remove" => cmd_remove(&cli, &args[2..]),
         "run" => cmd_run(&cli, &args[2..]),
         "clean" => cmd_clean(&cli),
-        "tree" => cmd_tree(&cli),
+        "cache" => cmd_cache(&cli, &args[2..]),
+        "tree" => cmd_tree(&cli, &args[2..]),
+        "link" => cmd_link(&cli, &args[2..]),
+        "unlink" => cmd_unlink(&cli, &args[2..]),
         "login" => cmd_login(&cli),
         "logout" => cmd_logout(&cli),
-        "outdated" => cmd_outdated(&cli).await,
-        "audit" => cmd_audit(&cli).await,
+        "outdated" => cmd_outdated(&cli, &args[2..]).await,
+        "audit" => cmd_audit(&cli, &args[2..]).await,
+        "verify" => cmd_verify(&cli),
+        "doctor" => cmd_doctor(&cli).await,
         // "script" => cmd_script(&cli, &args[2..]),
         "version" => cmd_version(),
         "help" => cmd_help(),
-        _ => {
-            eprintln!("stel: unknown command '{}'", args[1]);
-            eprintln!("Try 'stel help' for more information");
-            std::process::exit(1);
+        _ => CliError::Usage(format!("unknown command '{}'\nTry 'stel help' for more information", args[1])).exit("stel"),
+    }
+}
+
+/// Version of the documents `--format json` prints, bumped whenever a field
+/// changes meaning or goes away. New fields may appear without a bump.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// How a command reports its results: text for people, or one JSON document
+/// on stdout for CI systems and editors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    /// Take `--format <text|json>` and its shorthand `--json` out of a
+    /// command's arguments, returning the format and the other arguments.
+    fn from_args(command: &str, args: &[String]) -> (OutputFormat, Vec<String>) {
+        let mut format = OutputFormat::Text;
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--json" => "json",
+                "--format" => args.next().map(String::as_str).unwrap_or_else(|| CliError::Usage("--format expects text or json".to_string()).exit("stel")),
+                _ => match arg.strip_prefix("--format=") {
+                    Some(value) => value,
+                    None => {
+                        rest.push(arg.clone());
+                        continue;
+                    }
+                },
+            };
+            format = match value {
+                "text" => OutputFormat::Text,
+                "json" => OutputFormat::Json,
+                other => CliError::Usage(format!("unknown format '{}' for stel {} (expected text or json)", other, command)).exit("stel"),
+            };
         }
+        (format, rest)
+    }
+}
+
+/// Print a `--format json` document: the fields of `payload`, after the
+/// schema version.
+fn print_json(payload: serde_json::Value) {
+    let mut document = serde_json::Map::new();
+    document.insert("schema_version".to_string(), JSON_SCHEMA_VERSION.into());
+    if let serde_json::Value::Object(fields) = payload {
+        document.extend(fields);
+    }
+    match serde_json::to_string_pretty(&document) {
+        Ok(text) => println!("{}", text),
+        Err(e) => CliError::Other(format!("can't encode the JSON output: {}", e)).exit("stel"),
     }
 }
 
@@ -513,9 +1010,16 @@ fn cmd_init(cli: &StelCLI) {
             license: Some("MIT".to_string()),
             repository: None,
             keywords: Some(vec!["stellang".to_string()]),
+            features: Vec::new(),
+            build: None,
+            stellang: None,
+            edition: None,
         },
         dependencies: Some(HashMap::new()),
         dev_dependencies: Some(HashMap::new()),
+        native_dependencies: None,
+        bins: Vec::new(),
+        profile: None,
     };
 
     if let Err(e) = cli.write_manifest(&manifest) {
@@ -554,16 +1058,21 @@ fn main() {
     println!("  Run 'stel build' to build your project");
 }
 
-fn cmd_add(cli: &StelCLI, args: &[String]) {
+async fn cmd_add(cli: &StelCLI, args: &[String]) {
+    let is_native = args.iter().any(|arg| arg == "--native");
+    let args: Vec<String> = args.iter().filter(|arg| *arg != "--native").cloned().collect();
     if args.is_empty() {
         eprintln!("stel add: missing package name");
-        eprintln!("Usage: stel add <package> [version]");
+        eprintln!("Usage: stel add [--native] <package> [version|path]");
         std::process::exit(1);
     }
 
-    let package_name = &args[0];
-    let default_version = "*".to_string();
-    let version = args.get(1).unwrap_or(&default_version);
+    // Without a version, look the name up in the registry to pin the latest release
+    let (package_name, version) = match args.get(1) {
+        Some(version) => (args[0].clone(), version.clone()),
+        None if !is_native => choose_package(cli, &args[0]).await,
+        None => (args[0].clone(), "*".to_string()),
+    };
 
     let mut manifest = match cli.read_manifest() {
         Ok(m) => m,
@@ -573,7 +1082,11 @@ fn cmd_add(cli: &StelCLI, args: &[String]) {
         }
     };
 
-    let deps = manifest.dependencies.get_or_insert_with(HashMap::new);
+    let (deps, section) = if is_native {
+        (manifest.native_dependencies.get_or_insert_with(HashMap::new), "native_dependencies")
+    } else {
+        (manifest.dependencies.get_or_insert_with(HashMap::new), "dependencies")
+    };
     deps.insert(package_name.clone(), version.clone());
 
     if let Err(e) = cli.write_manifest(&manifest) {
@@ -581,64 +1094,106 @@ fn cmd_add(cli: &StelCLI, args: &[String]) {
         std::process::exit(1);
     }
 
-    println!("Added {} = \"{}\" to dependencies", package_name, version);
+    println!("Added {} = \"{}\" to {}", package_name, version, section);
     println!("Run 'stel install' to install the new dependency");
 }
 
-fn cmd_build(cli: &StelCLI) {
-    let manifest = match cli.read_manifest() {
-        Ok(m) => m,
+/// One package in the `stel add` picker: its newest version and downloads summed over all versions.
+struct PackageChoice {
+    name: String,
+    latest: Version,
+    description: Option<String>,
+    downloads: u64,
+}
+
+// Search the registry for `query` and return the package to add with a caret
+// requirement on its latest version. An exact name match or a single result is
+// taken as is; several results are listed for the user to pick from.
+async fn choose_package(cli: &StelCLI, query: &str) -> (String, String) {
+    let packages = match cli.search_registry(query).await {
+        Ok(packages) => packages,
         Err(e) => {
-            eprintln!("Failed to read stel.toml: {}", e);
-            std::process::exit(1);
+            eprintln!("Registry search failed ({}); adding {} = \"*\"", e, query);
+            return (query.to_string(), "*".to_string());
         }
     };
 
-    println!("Building {} v{}", manifest.package.name, manifest.package.version);
-
-    // Check if main.stel exists
-    let main_file = Path::new("src/main.stel");
-    if !main_file.exists() {
-        eprintln!("src/main.stel not found");
-        std::process::exit(1);
+    let mut choices: Vec<PackageChoice> = Vec::new();
+    for package in packages {
+        let Ok(version) = Version::parse(&package.version) else { continue };
+        match choices.iter_mut().find(|choice| choice.name == package.name) {
+            Some(choice) => {
+                choice.downloads += package.downloads;
+                if version > choice.latest {
+                    choice.latest = version;
+                    choice.description = package.description;
+                }
+            }
+            None => choices.push(PackageChoice {
+                name: package.name,
+                latest: version,
+                description: package.description,
+                downloads: package.downloads,
+            }),
+        }
     }
+    choices.sort_by(|a, b| b.downloads.cmp(&a.downloads).then_with(|| a.name.cmp(&b.name)));
 
-    // For now, just validate the syntax
-    let content = match fs::read_to_string(main_file) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to read main.stel: {}", e);
-            std::process::exit(1);
+    let chosen = match choices.iter().position(|choice| choice.name == query) {
+        Some(exact) => choices.swap_remove(exact),
+        None if choices.is_empty() => {
+            eprintln!("No packages found matching '{}'; adding {} = \"*\"", query, query);
+            return (query.to_string(), "*".to_string());
+        }
+        None if choices.len() == 1 => choices.remove(0),
+        None => {
+            let index = pick(query, &choices);
+            choices.swap_remove(index)
         }
     };
+    let requirement = format!("^{}", chosen.latest);
+    (chosen.name, requirement)
+}
 
-    // Basic syntax validation using the existing lexer/parser
-    let mut lexer = stellang::lang::lexer::Lexer::new(&content);
-    let mut tokens = Vec::new();
-    
-    loop {
-        match lexer.next_token() {
-            Ok(stellang::lang::lexer::Token::EOF) => break,
-            Ok(token) => tokens.push(token),
-            Err(e) => {
-                eprintln!("Lexer error: {:?}", e);
-                std::process::exit(1);
-            }
-        }
+// Print the choices as a numbered table and read the user's pick from stdin
+fn pick(query: &str, choices: &[PackageChoice]) -> usize {
+    let name_width = choices.iter().map(|choice| choice.name.len()).max().unwrap_or(0).max("PACKAGE".len());
+    println!("Several packages match '{}':", query);
+    println!();
+    println!("     {:<name_width$}  {:<10}  {:>9}  DESCRIPTION", "PACKAGE", "LATEST", "DOWNLOADS");
+    for (i, choice) in choices.iter().enumerate() {
+        println!(
+            "{:>3}) {:<name_width$}  {:<10}  {:>9}  {}",
+            i + 1,
+            choice.name,
+            choice.latest.to_string(),
+            choice.downloads,
+            choice.description.as_deref().unwrap_or(""),
+        );
     }
+    println!();
 
-    let mut parser = stellang::lang::parser::Parser::new(tokens);
-    match parser.parse() {
-        Ok(Some(_)) => println!("Build successful"),
-        Ok(None) => println!("Build successful (no expressions)"),
-        Err(e) => {
-            eprintln!("Parser error: {:?}", e);
-            std::process::exit(1);
+    let stdin = io::stdin();
+    loop {
+        print!("Add which package? [1-{}] ", choices.len());
+        if let Err(e) = io::stdout().flush() {
+            CliError::Stdout(e).exit("stel");
+        }
+        let mut answer = String::new();
+        match stdin.read_line(&mut answer) {
+            Ok(0) => CliError::Other("no package chosen".to_string()).exit("stel"),
+            Ok(_) => {}
+            Err(e) => CliError::Stdin(e).exit("stel"),
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=choices.len()).contains(&n) => return n - 1,
+            _ => println!("Enter a number from 1 to {}", choices.len()),
         }
     }
 }
 
-async fn cmd_install(cli: &StelCLI) {
+fn cmd_build(cli: &StelCLI, args: &[String]) {
+    let (format, args) = OutputFormat::from_args("build", args);
     let manifest = match cli.read_manifest() {
         Ok(m) => m,
         Err(e) => {
@@ -646,152 +1201,1118 @@ async fn cmd_install(cli: &StelCLI) {
             std::process::exit(1);
         }
     };
+    let profile = manifest.profile(args.iter().any(|arg| arg == "--release"));
+    require_toolchain(&manifest.package);
 
-    println!("Installing dependencies for {} v{}", manifest.package.name, manifest.package.version);
-
-    // Ensure config directory exists
-    if let Err(e) = cli.ensure_config_dir() {
-        eprintln!("Failed to create config directory: {}", e);
+    if format == OutputFormat::Text {
+        println!("Building {} v{} ({})", manifest.package.name, manifest.package.version, profile.describe());
+    }
+    if let Err(e) = run_build_script(&manifest.package, Path::new(".")) {
+        eprintln!("{}", e);
         std::process::exit(1);
     }
 
-    // Resolve dependencies
-    let lockfile = match cli.resolve_dependencies(&manifest).await {
-        Ok(l) => l,
-        Err(e) => {
-            eprintln!("Failed to resolve dependencies: {}", e);
-            std::process::exit(1);
+    // Building parses each entry point, optimizing and caching it as the profile says
+    let mut failed = false;
+    let mut targets = Vec::new();
+    for target in manifest.bin_targets() {
+        let result = load_program(Path::new(&target.path), &profile);
+        match (&result, format) {
+            (Ok(_), OutputFormat::Text) => println!("  ✓ {} ({})", target.name, target.path),
+            (Err(e), OutputFormat::Text) => eprintln!("  ✗ {} ({}): {}", target.name, target.path, e),
+            (_, OutputFormat::Json) => {}
         }
-    };
+        failed |= result.is_err();
+        targets.push(serde_json::json!({ "name": target.name, "path": target.path, "success": result.is_ok(), "error": result.err() }));
+    }
+    if format == OutputFormat::Json {
+        print_json(serde_json::json!({ "name": manifest.package.name, "version": manifest.package.version, "profile": profile.name, "success": !failed, "targets": targets }));
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    if format == OutputFormat::Text {
+        println!("Build successful");
+    }
+}
 
-    // Install each package
-    for (name, locked_package) in &lockfile.packages {
-        println!("Installing {}@{}", name, locked_package.version);
-        if let Err(e) = cli.install_package(name, &locked_package.version).await {
-            eprintln!("Failed to install {}@{}: {}", name, locked_package.version, e);
-            std::process::exit(1);
+/// How large `.stel/cache` may grow before `stel install` evicts the entries
+/// used least recently; `STEL_CACHE_MAX_SIZE` (such as `500MB`) overrides it.
+const DEFAULT_CACHE_MAX_SIZE: u64 = 1 << 30;
+
+/// One thing `.stel/cache` holds: a downloaded package directory or a file of
+/// the build cache. Entries are cleaned and evicted whole.
+struct CacheEntry {
+    path: PathBuf,
+    /// The package a `<name>-<version>` directory holds
+    package: Option<String>,
+    size: u64,
+    /// When the entry was written or last used
+    used: SystemTime,
+}
+
+impl StelCLI {
+    fn cache_entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        if !self.cache_dir.exists() {
+            return Ok(entries);
         }
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let path = entry?.path();
+            if path.is_dir() && path.file_name().is_some_and(|name| name == "build") {
+                collect_cache_files(&path, &mut entries)?;
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            // Package names may have dashes too, so the version is whatever after one parses as a version
+            let package = name.match_indices('-').find(|(at, _)| Version::parse(&name[at + 1..]).is_ok()).map(|(at, _)| name[..at].to_string());
+            let used = fs::symlink_metadata(&path)?.modified()?;
+            entries.push(CacheEntry { size: disk_size(&path)?, path, package, used });
+        }
+        Ok(entries)
     }
 
-    // Write lockfile
-    if let Err(e) = cli.write_lockfile(&lockfile) {
-        eprintln!("Failed to write lockfile: {}", e);
-        std::process::exit(1);
+    fn cache_max_size(&self) -> u64 {
+        match env::var("STEL_CACHE_MAX_SIZE") {
+            Ok(value) => parse_size(&value).unwrap_or_else(|| {
+                eprintln!("warning: ignoring STEL_CACHE_MAX_SIZE '{}' (expected a size such as 500MB or 2GB)", value);
+                DEFAULT_CACHE_MAX_SIZE
+            }),
+            Err(_) => DEFAULT_CACHE_MAX_SIZE,
+        }
     }
 
-    println!("All dependencies installed successfully!");
-    println!("Run 'stel build' to build your project");
+    /// Remove the least recently used entries until the cache fits its size
+    /// limit.
+    fn evict_cache(&self) -> io::Result<()> {
+        let limit = self.cache_max_size();
+        let mut entries = self.cache_entries()?;
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        entries.sort_by_key(|entry| entry.used);
+        for entry in &entries {
+            if total <= limit {
+                break;
+            }
+            remove_cache_entry(&entry.path)?;
+            total -= entry.size;
+            println!("Evicted {} from the cache ({} limit)", cache_entry_name(&self.cache_dir, entry), format_size(limit));
+        }
+        Ok(())
+    }
 }
 
-fn cmd_test(cli: &StelCLI) {
-    let manifest = match cli.read_manifest() {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("Failed to read stel.toml: {}", e);
-            std::process::exit(1);
+fn collect_cache_files(dir: &Path, entries: &mut Vec<CacheEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            collect_cache_files(&path, entries)?;
+        } else {
+            entries.push(CacheEntry { path, package: None, size: metadata.len(), used: metadata.modified()? });
         }
-    };
-
-    println!("Running tests for {} v{}", manifest.package.name, manifest.package.version);
+    }
+    Ok(())
+}
 
-    // Look for test files
-    let test_dir = Path::new("tests");
-    if !test_dir.exists() {
-        println!("No tests directory found");
-        return;
+/// Bytes in the files under `path`, not following symlinks.
+fn disk_size(path: &Path) -> io::Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += disk_size(&entry?.path())?;
     }
+    Ok(size)
+}
 
-    let mut test_count = 0;
-    let mut passed = 0;
+fn remove_cache_entry(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
 
-    if let Ok(entries) = fs::read_dir(test_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "stel") {
-                test_count += 1;
-                println!("Running test: {}", path.display());
-                
-                // Run the test file
-                let content = match fs::read_to_string(&path) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        eprintln!("Failed to read test file: {}", e);
-                        continue;
-                    }
-                };
+fn cache_entry_name(cache_dir: &Path, entry: &CacheEntry) -> String {
+    entry.path.strip_prefix(cache_dir).unwrap_or(&entry.path).display().to_string()
+}
 
-                let mut lexer = stellang::lang::lexer::Lexer::new(&content);
-                let mut tokens = Vec::new();
-                
-                loop {
-                    match lexer.next_token() {
-                        Ok(stellang::lang::lexer::Token::EOF) => break,
-                        Ok(token) => tokens.push(token),
-                        Err(e) => {
-                            eprintln!("Lexer error in test: {:?}", e);
-                            continue;
-                        }
-                    }
-                }
+/// `500MB`, `2GB`, `64k` or a plain number of bytes; units are powers of 1024.
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let scale: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * scale as f64) as u64)
+}
 
-                let mut parser = stellang::lang::parser::Parser::new(tokens);
-                match parser.parse() {
-                    Ok(Some(_)) => {
-                        println!("  ✓ Test passed");
-                        passed += 1;
-                    }
-                    Ok(None) => {
-                        println!("  ✓ Test passed (no expressions)");
-                        passed += 1;
-                    }
-                    Err(e) => {
-                        eprintln!("  ✗ Test failed: {:?}", e);
-                    }
-                }
-            }
-        }
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
     }
-
-    println!("\nTest Results: {} passed, {} failed", passed, test_count - passed);
-    if passed == test_count {
-        println!("All tests passed!");
-    } else {
-        std::process::exit(1);
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
     }
+    format!("{:.1} {}", size, UNITS[unit])
 }
 
-async fn cmd_update(cli: &StelCLI) {
-    let manifest = match cli.read_manifest() {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("Failed to read stel.toml: {}", e);
-            std::process::exit(1);
-        }
+/// `30d`, `12h`, `45m`, `90s` or `2w`.
+fn parse_age(text: &str) -> Option<Duration> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
     };
+    Some(Duration::from_secs(number * seconds))
+}
 
-    println!("Updating dependencies for {} v{}", manifest.package.name, manifest.package.version);
+/// How many packages `stel install` downloads at once unless `--jobs` says.
+const DEFAULT_DOWNLOAD_JOBS: usize = 4;
 
-    // Ensure config directory exists
-    if let Err(e) = cli.ensure_config_dir() {
-        eprintln!("Failed to create config directory: {}", e);
-        std::process::exit(1);
-    }
+/// Width of a download progress bar, in characters.
+const PROGRESS_BAR_WIDTH: usize = 24;
 
-    // Resolve dependencies (this will get latest versions)
-    let lockfile = match cli.resolve_dependencies(&manifest).await {
-        Ok(l) => l,
+/// Progress of the downloads of one install. On a terminal each download gets
+/// a bar on stderr, redrawn as data arrives; otherwise a line is printed as
+/// each one finishes.
+struct Progress {
+    state: Mutex<ProgressState>,
+    live: bool,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    bars: Vec<ProgressBar>,
+    /// Lines drawn last time, to move back over
+    drawn: usize,
+    last_draw: Option<Instant>,
+}
+
+struct ProgressBar {
+    label: String,
+    done: u64,
+    total: Option<u64>,
+    /// The registry that served the download, once it has finished
+    served_by: Option<String>,
+}
+
+impl Progress {
+    fn new() -> Progress {
+        Progress { state: Mutex::new(ProgressState::default()), live: io::stderr().is_terminal() }
+    }
+
+    /// Add a bar for a download that has `done` of `total` bytes already.
+    fn start(&self, label: &str, done: u64, total: Option<u64>) -> usize {
+        let mut state = self.lock();
+        state.bars.push(ProgressBar { label: label.to_string(), done, total, served_by: None });
+        self.draw(&mut state, true);
+        state.bars.len() - 1
+    }
+
+    fn update(&self, bar: usize, done: u64) {
+        let mut state = self.lock();
+        state.bars[bar].done = done;
+        self.draw(&mut state, false);
+    }
+
+    fn finish(&self, bar: usize, served_by: &str) {
+        let mut state = self.lock();
+        let bar = &mut state.bars[bar];
+        bar.served_by = Some(served_by.to_string());
+        bar.total = Some(bar.done);
+        if !self.live {
+            println!("Downloaded {} ({}) from {}", bar.label, format_size(bar.done), served_by);
+            return;
+        }
+        self.draw(&mut state, true);
+    }
+
+    // Redraw every bar in place, at most ten times a second unless `force`d
+    fn draw(&self, state: &mut ProgressState, force: bool) {
+        if !self.live || (!force && state.last_draw.is_some_and(|last| last.elapsed() < Duration::from_millis(100))) {
+            return;
+        }
+        let mut out = String::new();
+        if state.drawn > 0 {
+            out.push_str(&format!("\x1b[{}A", state.drawn));
+        }
+        for bar in &state.bars {
+            out.push_str("\r\x1b[2K");
+            out.push_str(&bar.line());
+            out.push('\n');
+        }
+        eprint!("{}", out);
+        state.drawn = state.bars.len();
+        state.last_draw = Some(Instant::now());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ProgressBar {
+    fn line(&self) -> String {
+        let fraction = match self.total {
+            Some(total) if total > 0 => (self.done as f64 / total as f64).min(1.0),
+            _ => 0.0,
+        };
+        let filled = (fraction * PROGRESS_BAR_WIDTH as f64) as usize;
+        let size = match self.total {
+            Some(total) if self.served_by.is_none() => format!("{} / {}", format_size(self.done), format_size(total)),
+            _ => format_size(self.done),
+        };
+        let mut line = format!("{:<24} [{}{}] {:>3}%  {}", self.label, "#".repeat(filled), "-".repeat(PROGRESS_BAR_WIDTH - filled), (fraction * 100.0) as u32, size);
+        if let Some(registry) = &self.served_by {
+            line.push_str(&format!("  from {}", registry));
+        }
+        line
+    }
+}
+
+/// A program in the build cache, valid while its source hashes the same.
+#[derive(Serialize, Deserialize)]
+struct CachedProgram {
+    source_hash: String,
+    program: Option<stellang::lang::ast::Expr>,
+}
+
+// Parse a source file the way `profile` asks: from the build cache when it is
+// enabled and up to date, through the optimizer when that is enabled
+fn load_program(path: &Path, profile: &Profile) -> Result<Option<stellang::lang::ast::Expr>, String> {
+    use sha2::Digest;
+    if !path.exists() {
+        return Err("file not found".to_string());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // The profile's settings change the optimized program, so they are part of the key
+    let source_hash = hex::encode(sha2::Sha256::digest(format!("{}\n{}", profile.describe(), content)));
+    let cache_file = Path::new(STEL_CACHE_DIR)
+        .join("build")
+        .join(profile.name)
+        .join(format!("{}.ast", path.to_string_lossy().replace(['/', '\\', ':'], "_")));
+    if profile.cache {
+        let cached = fs::read(&cache_file).ok().and_then(|bytes| bincode::deserialize::<CachedProgram>(&bytes).ok());
+        if let Some(cached) = cached.filter(|cached| cached.source_hash == source_hash) {
+            // Counts as a use, so eviction drops this entry after ones untouched for longer
+            let _ = fs::File::options().append(true).open(&cache_file).and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok(cached.program);
+        }
+    }
+
+    let mut program = parse_source(&content, path)?;
+    if profile.optimize {
+        if let Some(program) = &mut program {
+            stellang::lang::optimize::optimize(program, profile.debug_assertions);
+        }
+    }
+
+    if profile.cache {
+        // A cache that can't be written only costs a parse next time
+        let cached = CachedProgram { source_hash, program };
+        let written = bincode::serialize(&cached)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|bytes| {
+                fs::create_dir_all(cache_file.parent().unwrap_or(Path::new(".")))?;
+                fs::write(&cache_file, bytes)
+            });
+        if let Err(e) = written {
+            eprintln!("warning: can't write build cache {}: {}", cache_file.display(), e);
+        }
+        return Ok(cached.program);
+    }
+    Ok(program)
+}
+
+// Run the build script a package declares, unless its files are unchanged
+// since the last run. The script runs sandboxed: it may read the package's
+// files and write to its OUT_DIR, `.stel/out/<name>`, which starts out empty.
+fn run_build_script(package: &PackageInfo, dir: &Path) -> Result<(), String> {
+    let Some(script) = &package.build else {
+        return Ok(());
+    };
+    let failed = |e: &dyn std::fmt::Display| format!("Build script {} of {} failed: {}", script, package.name, e);
+    let out_dir = Path::new(STEL_OUT_DIR).join(&package.name);
+    let stamp = Path::new(STEL_OUT_DIR).join(format!("{}.inputs", package.name));
+    // Hidden files and installed dependencies are not the script's inputs
+    let inputs = hash_tree(dir, &|name| name.starts_with('.') || name == "dependencies").map_err(|e| failed(&e))?;
+    if out_dir.is_dir() && fs::read_to_string(&stamp).is_ok_and(|hash| hash == inputs) {
+        return Ok(());
+    }
+
+    let source = fs::read_to_string(dir.join(script)).map_err(|e| failed(&e))?;
+    let program = parse_source(&source, &dir.join(script)).map_err(|e| failed(&e))?;
+    if out_dir.exists() {
+        fs::remove_dir_all(&out_dir).map_err(|e| failed(&e))?;
+    }
+    fs::create_dir_all(&out_dir).map_err(|e| failed(&e))?;
+    let (dir, out_dir) = fs::canonicalize(dir).and_then(|dir| Ok((dir, fs::canonicalize(&out_dir)?))).map_err(|e| failed(&e))?;
+
+    // Like everything the script prints, on stderr, so that stdout stays the command's own
+    eprintln!("Running build script {} of {}", script, package.name);
+    let capabilities = stellang::lang::sandbox::Capabilities::sandboxed().allow_dir(&dir, false).allow_dir(&out_dir, true);
+    let mut interpreter = stellang::lang::interpreter::Interpreter::with_capabilities(capabilities);
+    interpreter.set_stdout(Box::new(io::stderr()));
+    interpreter.set_global("PACKAGE_DIR", Value::Str(dir.display().to_string()));
+    interpreter.set_global("OUT_DIR", Value::Str(out_dir.display().to_string()));
+    if let Some(program) = program {
+        interpreter.eval(&program).map_err(|e| failed(&e))?;
+    }
+    fs::write(&stamp, inputs).map_err(|e| failed(&e))
+}
+
+// sha256 over the relative paths and contents of the files under `dir`,
+// leaving out the files and directories whose names `skip` picks
+fn hash_tree(dir: &Path, skip: &dyn Fn(&str) -> bool) -> io::Result<String> {
+    use sha2::Digest;
+    fn collect(dir: &Path, skip: &dyn Fn(&str) -> bool, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if skip(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                collect(&entry.path(), skip, files)?;
+            } else {
+                files.push(entry.path());
+            }
+        }
+        Ok(())
+    }
+    let mut files = Vec::new();
+    collect(dir, skip, &mut files)?;
+    files.sort();
+    let mut hasher = sha2::Sha256::new();
+    for file in files {
+        hash_file(&mut hasher, file.strip_prefix(dir).unwrap_or(&file), &fs::read(&file)?);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// What hash_tree would give for the files a package archive unpacks to
+fn hash_archive(archive: &[u8]) -> io::Result<String> {
+    use sha2::Digest;
+    let mut files = BTreeMap::new();
+    for entry in tar::Archive::new(flate2::read::GzDecoder::new(archive)).entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        // Unpacking drops `.` components, and a later entry for the same path wins
+        let path: PathBuf = entry.path()?.components().filter(|component| matches!(component, std::path::Component::Normal(_))).collect();
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.insert(path, contents);
+    }
+    let mut hasher = sha2::Sha256::new();
+    for (path, contents) in &files {
+        hash_file(&mut hasher, path, contents);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn hash_file(hasher: &mut sha2::Sha256, path: &Path, contents: &[u8]) {
+    use sha2::Digest;
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(contents);
+    hasher.update([0]);
+}
+
+// Lex and parse the source of the file at `path`, keeping the file and line of
+// each statement for tracebacks
+fn parse_source(content: &str, path: &Path) -> Result<Option<stellang::lang::ast::Expr>, String> {
+    stellang::lang::parser::Parser::parse_file(content, &path.display().to_string()).map_err(|e| format!("Parse error: {}", e))
+}
+
+async fn cmd_install(cli: &StelCLI, args: &[String]) {
+    match args.first().map(String::as_str) {
+        Some("--script") => return cmd_install_script(&args[1..]),
+        Some("--global") => return cmd_install_global(cli),
+        _ => {}
+    }
+    // `--jobs <n>`: how many packages to download at once
+    let jobs = match args {
+        [] => Some(DEFAULT_DOWNLOAD_JOBS),
+        [flag, jobs] if flag == "--jobs" => jobs.parse().ok().filter(|&jobs| jobs > 0),
+        [flag] if flag.starts_with("--jobs=") => flag["--jobs=".len()..].parse().ok().filter(|&jobs| jobs > 0),
+        _ => None,
+    }
+    .unwrap_or_else(|| CliError::Usage("install expects --jobs <n> with n at least 1, --script <file> or --global".to_string()).exit("stel"));
+
+    let manifest = match cli.read_manifest() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to read stel.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    require_toolchain(&manifest.package);
+    println!("Installing dependencies for {} v{}", manifest.package.name, manifest.package.version);
+
+    // Ensure config directory exists
+    if let Err(e) = cli.ensure_config_dir() {
+        eprintln!("Failed to create config directory: {}", e);
+        std::process::exit(1);
+    }
+
+    // Resolve dependencies
+    let mut lockfile = match cli.resolve_dependencies(&manifest).await {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to resolve dependencies: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = cli.download_all(&lockfile, jobs).await {
+        eprintln!("Failed to download dependencies: {}", e);
+        std::process::exit(1);
+    }
+
+    // Install each package
+    for (name, locked_package) in lockfile.packages.iter_mut() {
+        println!("Installing {}@{}", name, locked_package.version);
+        match cli.install_locked(name, locked_package).await {
+            // A hash stel.lock already had was checked, never replaced
+            Ok(integrity) => locked_package.integrity = locked_package.integrity.take().or(integrity),
+            Err(e) => {
+                eprintln!("Failed to install {}@{}: {}", name, locked_package.version, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    for (name, source) in manifest.native_dependencies.iter().flatten() {
+        if let Err(e) = cli.install_native(name, source).await {
+            eprintln!("Failed to install native module {}: {}", name, e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Err(e) = cli.evict_cache() {
+        eprintln!("warning: can't trim {}: {}", STEL_CACHE_DIR, e);
+    }
+
+    // Write lockfile
+    if let Err(e) = cli.write_lockfile(&lockfile) {
+        eprintln!("Failed to write lockfile: {}", e);
+        std::process::exit(1);
+    }
+
+    println!("All dependencies installed successfully!");
+    println!("Run 'stel build' to build your project");
+}
+
+// Put a launcher for each bin target into the global bin directory. Launchers
+// run the project's sources in place, so imports relative to them keep working.
+fn cmd_install_global(cli: &StelCLI) {
+    let manifest = match cli.read_manifest() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to read stel.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let bin_dir = global_bin_dir();
+    if let Err(source) = fs::create_dir_all(&bin_dir) {
+        CliError::Write { path: bin_dir.display().to_string(), source }.exit("stel");
+    }
+
+    for target in manifest.bin_targets() {
+        let source = match fs::canonicalize(&target.path) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Failed to install {}: {}: {}", target.name, target.path, e);
+                std::process::exit(1);
+            }
+        };
+        match write_launcher(&bin_dir, &target.name, &source) {
+            Ok(launcher) => println!("Installed {} -> {}", launcher.display(), source.display()),
+            Err(e) => {
+                eprintln!("Failed to install {}: {}", target.name, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let on_path = env::var_os("PATH").is_some_and(|path| env::split_paths(&path).any(|dir| dir == bin_dir));
+    if !on_path {
+        println!("Add {} to your PATH to run them by name", bin_dir.display());
+    }
+}
+
+// $STEL_HOME, by default ~/.stel: state shared by every project of the user
+fn stel_home() -> PathBuf {
+    env::var_os("STEL_HOME").map(PathBuf::from).unwrap_or_else(|| {
+        let user_home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).unwrap_or_default();
+        PathBuf::from(user_home).join(STEL_CONFIG_DIR)
+    })
+}
+
+fn global_bin_dir() -> PathBuf {
+    stel_home().join("bin")
+}
+
+/// Hex digits of an archive's sha256 in the name of its package store entry.
+const STORE_HASH_LEN: usize = 16;
+
+// Unpacked packages, each kept once for every project and hard-linked into
+// their dependencies/ as `<name>-<version>-<hash of the archive>`, beside the
+// archive it was unpacked from as `<name>-<version>-<hash>.tar.gz`
+fn package_store() -> PathBuf {
+    stel_home().join("store")
+}
+
+/// The archive a store entry was unpacked from.
+fn store_archive(entry: &Path) -> PathBuf {
+    let mut archive = entry.as_os_str().to_owned();
+    archive.push(".tar.gz");
+    PathBuf::from(archive)
+}
+
+/// The store entry of a locked package, when the lockfile has its archive's
+/// sha256 and the store holds that archive already. An entry whose archive
+/// or files don't match is removed, so the package is downloaded again.
+fn stored_package(name: &str, locked: &LockedPackage) -> Option<PathBuf> {
+    let digest = locked_digest(locked)?;
+    let dir = package_store().join(format!("{}-{}-{}", name, locked.version, &digest[..STORE_HASH_LEN]));
+    if !dir.is_dir() {
+        return None;
+    }
+    match check_store_entry(&dir, &digest) {
+        Ok(()) => Some(dir),
+        Err(problem) => {
+            eprintln!("warning: removing {} from the package store: {}", dir.display(), problem);
+            let _ = remove_cache_entry(&dir);
+            let _ = fs::remove_file(store_archive(&dir));
+            None
+        }
+    }
+}
+
+// Whether a store entry still holds what the archive stel.lock names unpacks to
+fn check_store_entry(dir: &Path, digest: &str) -> Result<(), String> {
+    use sha2::Digest;
+    let archive = fs::read(store_archive(dir)).map_err(|e| format!("can't read its archive: {}", e))?;
+    if hex::encode(sha2::Sha256::digest(&archive)) != digest {
+        return Err("its archive doesn't match the checksum in stel.lock".to_string());
+    }
+    let expected = hash_archive(&archive).map_err(|e| format!("can't read its archive: {}", e))?;
+    if hash_tree(dir, &|_| false).map_err(|e| e.to_string())? != expected {
+        return Err("its files were modified".to_string());
+    }
+    Ok(())
+}
+
+/// The archive sha256 the lockfile records for a package, in lowercase hex.
+fn locked_digest(locked: &LockedPackage) -> Option<String> {
+    let digest = locked.checksum.as_deref()?.strip_prefix("sha256:")?;
+    (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+/// Unpack a downloaded archive with sha256 `digest` into the package store,
+/// unless the same archive was stored before, and return its store entry.
+fn store_package(name: &str, version: &str, archive: &Path, digest: &str) -> io::Result<PathBuf> {
+    let dir = package_store().join(format!("{}-{}-{}", name, version, &digest[..STORE_HASH_LEN]));
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+    // Unpacked beside the entry and renamed, so a half-unpacked package is never used
+    let staging = package_store().join(format!(".{}-{}-{}.{}", name, version, &digest[..STORE_HASH_LEN], std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)?;
+    if let Err(e) = unpack_package(archive, &staging) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    // Projects share these files through hard links, so none of them may change them
+    make_read_only(&staging)?;
+    // Kept to check the entry against stel.lock before it's used again
+    let kept = package_store().join(format!(".{}-{}-{}.tar.gz.{}", name, version, &digest[..STORE_HASH_LEN], std::process::id()));
+    fs::copy(archive, &kept)?;
+    make_read_only_file(&kept)?;
+    fs::rename(&kept, store_archive(&dir))?;
+    if let Err(e) = fs::rename(&staging, &dir) {
+        // Another install stored the same package meanwhile
+        fs::remove_dir_all(&staging)?;
+        if !dir.is_dir() {
+            return Err(e);
+        }
+    }
+    Ok(dir)
+}
+
+// Unpack a package archive into `dir`. Packages may only hold files and
+// directories: a symlink could lead whatever reads the package, such as its
+// sandboxed build script, out of it.
+fn unpack_package(archive: &Path, dir: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(fs::File::open(archive)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        if kind.is_pax_global_extensions() || kind.is_pax_local_extensions() {
+            continue;
+        }
+        if !kind.is_file() && !kind.is_dir() {
+            let message = format!("the archive has a link or special file at {}; packages may only hold files and directories", entry.path()?.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        entry.unpack_in(dir)?;
+    }
+    Ok(())
+}
+
+fn make_read_only(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            make_read_only(&entry.path())?;
+        } else {
+            make_read_only_file(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn make_read_only_file(path: &Path) -> io::Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(path, permissions)
+}
+
+/// Remove the package store entries that no project links to any more, and
+/// any an interrupted install left half unpacked. Returns how many went and
+/// the bytes they held.
+fn prune_store() -> io::Result<(usize, u64)> {
+    let store = package_store();
+    let (mut removed, mut freed) = (0, 0);
+    if !store.is_dir() {
+        return Ok((removed, freed));
+    }
+    for entry in fs::read_dir(&store)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        // An archive goes with its entry
+        let entry = name.strip_suffix(".tar.gz").map(|entry| store.join(entry)).unwrap_or_else(|| path.clone());
+        if !name.starts_with('.') && entry.is_dir() && is_linked(&entry)? {
+            continue;
+        }
+        let size = disk_size(&path)?;
+        remove_cache_entry(&path)?;
+        removed += usize::from(path == entry);
+        freed += size;
+    }
+    Ok((removed, freed))
+}
+
+// Whether some project's dependencies/ still links a file of a store entry
+#[cfg(unix)]
+fn is_linked(path: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.nlink() > 1);
+    }
+    for entry in fs::read_dir(path)? {
+        if is_linked(&entry?.path())? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Without link counts there's no telling, so every entry is kept
+#[cfg(not(unix))]
+fn is_linked(_path: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Recreate the directory `src` at `dst` with hard links to its files,
+/// copying those that can't be linked, such as across file systems.
+fn link_tree(src: &Path, dst: &Path) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let (from, to) = (entry.path(), dst.join(entry.file_name()));
+        if entry.file_type()?.is_dir() {
+            link_tree(&from, &to)?;
+        } else if fs::hard_link(&from, &to).is_err() {
+            fs::copy(&from, &to)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_launcher(bin_dir: &Path, name: &str, source: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+    let launcher = bin_dir.join(name);
+    // Single-quoted for sh, with any ' in the path closed, escaped and reopened
+    let quoted = source.display().to_string().replace('\'', "'\\''");
+    fs::write(&launcher, format!("#!/bin/sh\nexec stellang '{}' \"$@\"\n", quoted))?;
+    fs::set_permissions(&launcher, fs::Permissions::from_mode(0o755))?;
+    Ok(launcher)
+}
+
+#[cfg(not(unix))]
+fn write_launcher(bin_dir: &Path, name: &str, source: &Path) -> io::Result<PathBuf> {
+    let launcher = bin_dir.join(format!("{}.cmd", name));
+    fs::write(&launcher, format!("@stellang \"{}\" %*\r\n", source.display()))?;
+    Ok(launcher)
+}
+
+const SCRIPT_SHEBANG: &str = "#!/usr/bin/env stellang";
+
+// Make .stel files runnable as ./script.stel: give them a shebang line if they
+// lack one and set the executable bits
+fn cmd_install_script(files: &[String]) {
+    if files.is_empty() {
+        eprintln!("stel install --script: missing script file");
+        eprintln!("Usage: stel install --script <file.stel>...");
+        std::process::exit(1);
+    }
+
+    for file in files {
+        if let Err(e) = install_script(Path::new(file)) {
+            eprintln!("Failed to install script {}: {}", file, e);
+            std::process::exit(1);
+        }
+        println!("Installed script {}", file);
+    }
+}
+
+fn install_script(path: &Path) -> io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    if !content.starts_with("#!") {
+        fs::write(path, format!("{}\n{}", SCRIPT_SHEBANG, content))?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)?.permissions();
+        // Execute for everyone who can read it, like chmod +x
+        permissions.set_mode(permissions.mode() | (permissions.mode() & 0o444) >> 2);
+        fs::set_permissions(path, permissions)?;
+    }
+    Ok(())
+}
+
+fn cmd_test(cli: &StelCLI, args: &[String]) {
+    if args.iter().any(|arg| arg == "--watch") {
+        watch("test", args);
+    }
+    let (format, args) = OutputFormat::from_args("test", args);
+    let selection = TestSelection::from_args(&args);
+    let text = format == OutputFormat::Text;
+
+    let manifest = match cli.read_manifest() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to read stel.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if text {
+        println!("Running tests for {} v{}", manifest.package.name, manifest.package.version);
+    }
+
+    // Look for test files
+    let test_dir = Path::new("tests");
+    let mut summary = TestSummary::default();
+    if !test_dir.exists() {
+        match format {
+            OutputFormat::Text => println!("No tests directory found"),
+            OutputFormat::Json => summary.print_json(),
+        }
+        return;
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(test_dir).into_iter().flatten().flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "stel"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Failed to read test file: {}", e);
+                continue;
+            }
+        };
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let fixtures = fs::canonicalize(test_dir).unwrap_or_else(|_| test_dir.to_path_buf()).join("fixtures").join(&stem);
+
+        let program = match stellang::lang::parser::Parser::parse_file(&content, &path.display().to_string()) {
+            Ok(program) => program,
+            Err(e) => {
+                if selection.selects(&stem, &[]) {
+                    if text {
+                        println!("Running test: {}", path.display());
+                    }
+                    summary.record(TestOutcome { name: stem, tags: Vec::new(), error: Some(e.to_string()), output: String::new() }, format);
+                } else {
+                    summary.filtered += 1;
+                }
+                continue;
+            }
+        };
+        let statements = match program {
+            Some(stellang::lang::ast::Expr::Block(statements)) => statements,
+            Some(statement) => vec![statement],
+            None => Vec::new(),
+        };
+
+        // A file without test blocks is a single test. Otherwise each block is a
+        // test named file::block, run after the file's other statements
+        let (tests, setup): (Vec<_>, Vec<_>) = statements.into_iter().partition(|statement| matches!(statement.unlocated(), stellang::lang::ast::Expr::Test { .. }));
+        if tests.is_empty() {
+            if !selection.selects(&stem, &[]) {
+                summary.filtered += 1;
+                continue;
+            }
+            if text {
+                println!("Running test: {}", path.display());
+            }
+            let outcome = run_test_case(&stem, Vec::new(), &fixtures, &setup, None);
+            summary.record(outcome, format);
+            continue;
+        }
+        for test in &tests {
+            let stellang::lang::ast::Expr::Test { name, tags, body } = test.unlocated() else { continue };
+            let name = format!("{}::{}", stem, name);
+            if !selection.selects(&name, tags) {
+                summary.filtered += 1;
+                continue;
+            }
+            if text {
+                println!("Running test: {}", name);
+            }
+            let outcome = run_test_case(&name, tags.clone(), &fixtures, &setup, Some(body));
+            summary.record(outcome, format);
+        }
+    }
+
+    match format {
+        OutputFormat::Text => summary.print(),
+        OutputFormat::Json => summary.print_json(),
+    }
+    if summary.failed > 0 {
+        std::process::exit(1);
+    }
+    if text {
+        println!("All tests passed!");
+    }
+}
+
+/// Which tests `stel test` runs: those whose name contains the filter and none
+/// of the `--skip` patterns, carrying one of the `--tag`s (when any are given)
+/// and none of the `--exclude-tag`s.
+#[derive(Default)]
+struct TestSelection {
+    filter: Option<String>,
+    skip: Vec<String>,
+    tags: Vec<String>,
+    exclude_tags: Vec<String>,
+}
+
+impl TestSelection {
+    fn from_args(args: &[String]) -> Self {
+        let mut selection = TestSelection::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| args.next().cloned().unwrap_or_else(|| CliError::Usage(format!("{} expects a value", flag)).exit("stel"));
+            match arg.as_str() {
+                "--watch" => {}
+                "--skip" => selection.skip.push(value("--skip")),
+                "--tag" => selection.tags.push(value("--tag")),
+                "--exclude-tag" => selection.exclude_tags.push(value("--exclude-tag")),
+                option if option.starts_with("--") => CliError::Usage(format!("unknown option '{}' for stel test", option)).exit("stel"),
+                filter if selection.filter.is_none() => selection.filter = Some(filter.to_string()),
+                _ => CliError::Usage("stel test takes at most one name filter".to_string()).exit("stel"),
+            }
+        }
+        selection
+    }
+
+    fn selects(&self, name: &str, tags: &[String]) -> bool {
+        self.filter.as_ref().is_none_or(|filter| name.contains(filter.as_str()))
+            && !self.skip.iter().any(|pattern| name.contains(pattern.as_str()))
+            && (self.tags.is_empty() || tags.iter().any(|tag| self.tags.contains(tag)))
+            && !tags.iter().any(|tag| self.exclude_tags.contains(tag))
+    }
+}
+
+/// How one test went.
+#[derive(Serialize)]
+struct TestOutcome {
+    name: String,
+    tags: Vec<String>,
+    /// Why the test failed, if it did
+    error: Option<String>,
+    /// What a failing test printed
+    #[serde(skip_serializing_if = "String::is_empty")]
+    output: String,
+}
+
+/// Counts for the end of a `stel test` run, overall and by tag.
+#[derive(Default)]
+struct TestSummary {
+    passed: usize,
+    failed: usize,
+    filtered: usize,
+    by_tag: std::collections::BTreeMap<String, (usize, usize)>,
+    tests: Vec<TestOutcome>,
+}
+
+impl TestSummary {
+    /// Count a test that ran, reporting it right away in text mode.
+    fn record(&mut self, outcome: TestOutcome, format: OutputFormat) {
+        if format == OutputFormat::Text {
+            match &outcome.error {
+                None => println!("  ✓ Test passed"),
+                Some(error) => {
+                    eprintln!("  ✗ Test failed: {}", error);
+                    if !outcome.output.is_empty() {
+                        eprintln!("  ---- output ----");
+                        for line in outcome.output.lines() {
+                            eprintln!("  {}", line);
+                        }
+                    }
+                }
+            }
+        }
+        let counts = if outcome.error.is_none() { (1, 0) } else { (0, 1) };
+        self.passed += counts.0;
+        self.failed += counts.1;
+        for tag in &outcome.tags {
+            let tag_counts = self.by_tag.entry(tag.clone()).or_default();
+            tag_counts.0 += counts.0;
+            tag_counts.1 += counts.1;
+        }
+        self.tests.push(outcome);
+    }
+
+    fn print_json(&self) {
+        let by_tag: serde_json::Map<String, serde_json::Value> = self.by_tag.iter().map(|(tag, (passed, failed))| (tag.clone(), serde_json::json!({ "passed": passed, "failed": failed }))).collect();
+        let tests: Vec<_> = self.tests.iter().map(|test| {
+            let mut entry = serde_json::to_value(test).unwrap_or_default();
+            entry["passed"] = test.error.is_none().into();
+            entry
+        }).collect();
+        print_json(serde_json::json!({ "passed": self.passed, "failed": self.failed, "filtered": self.filtered, "by_tag": by_tag, "tests": tests }));
+    }
+
+    fn print(&self) {
+        print!("\nTest Results: {} passed, {} failed", self.passed, self.failed);
+        if self.filtered > 0 {
+            print!(", {} filtered out", self.filtered);
+        }
+        println!();
+        for (tag, (passed, failed)) in &self.by_tag {
+            println!("  [{}] {} passed, {} failed", tag, passed, failed);
+        }
+    }
+}
+
+// Run one test in a fresh interpreter and its own scratch directory, removed
+// afterwards, with fixture_path() pointing at tests/fixtures/<file>/. `setup`
+// is the whole file for a file-level test, or the statements around the test
+// blocks for a `test "name" { }` block
+fn run_test_case(name: &str, tags: Vec<String>, fixtures: &Path, setup: &[stellang::lang::ast::Expr], body: Option<&stellang::lang::ast::Expr>) -> TestOutcome {
+    let mut outcome = TestOutcome { name: name.to_string(), tags, error: None, output: String::new() };
+    let scratch = match stellang::lang::testing::ScratchDir::enter(name) {
+        Ok(scratch) => scratch,
+        Err(e) => {
+            outcome.error = Some(format!("cannot create a working directory: {}", e));
+            return outcome;
+        }
+    };
+    stellang::lang::testing::set_fixture_dir(Some(fixtures.to_path_buf()));
+    let mut interpreter = stellang::lang::interpreter::Interpreter::new();
+    let output = interpreter.capture_stdout();
+    let program = stellang::lang::ast::Expr::Block(setup.iter().chain(body).cloned().collect());
+    let result = interpreter.eval(&program);
+    stellang::lang::testing::set_fixture_dir(None);
+    drop(scratch);
+
+    // Run the file so assert_eq failures surface with their diff. What the
+    // test printed is only shown when it fails, to keep passing runs quiet
+    if let Err(e) = result {
+        outcome.error = Some(e.to_string());
+        outcome.output = output.contents();
+    }
+    outcome
+}
+
+async fn cmd_update(cli: &StelCLI) {
+    let manifest = match cli.read_manifest() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("Failed to read stel.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Updating dependencies for {} v{}", manifest.package.name, manifest.package.version);
+
+    // Ensure config directory exists
+    if let Err(e) = cli.ensure_config_dir() {
+        eprintln!("Failed to create config directory: {}", e);
+        std::process::exit(1);
+    }
+
+    // Resolve dependencies (this will get latest versions)
+    let mut lockfile = match cli.resolve_dependencies(&manifest).await {
+        Ok(l) => l,
         Err(e) => {
             eprintln!("Failed to resolve dependencies: {}", e);
             std::process::exit(1);
         }
     };
 
+    if let Err(e) = cli.download_all(&lockfile, DEFAULT_DOWNLOAD_JOBS).await {
+        eprintln!("Failed to download dependencies: {}", e);
+        std::process::exit(1);
+    }
+
     // Install updated packages
-    for (name, locked_package) in &lockfile.packages {
+    for (name, locked_package) in lockfile.packages.iter_mut() {
         println!("Updating {}@{}", name, locked_package.version);
-        if let Err(e) = cli.install_package(name, &locked_package.version).await {
-            eprintln!("Failed to update {}@{}: {}", name, locked_package.version, e);
-            std::process::exit(1);
+        match cli.install_locked(name, locked_package).await {
+            // A hash stel.lock already had was checked, never replaced
+            Ok(integrity) => locked_package.integrity = locked_package.integrity.take().or(integrity),
+            Err(e) => {
+                eprintln!("Failed to update {}@{}: {}", name, locked_package.version, e);
+                std::process::exit(1);
+            }
         }
     }
 
@@ -803,7 +2324,137 @@ async fn cmd_update(cli: &StelCLI) {
     println!("Dependencies updated successfully!");
 }
 
-async fn cmd_publish(cli: &StelCLI) {
+/// SPDX identifiers `license` may use, alone or combined with `OR`, `AND` and `WITH`.
+const SPDX_LICENSES: &[&str] = &[
+    "0BSD", "AFL-3.0", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-2.0", "Artistic-2.0", "BSD-2-Clause", "BSD-3-Clause", "BSD-3-Clause-Clear", "BSL-1.0",
+    "CC-BY-4.0", "CC-BY-SA-4.0", "CC0-1.0", "CDDL-1.0", "ECL-2.0", "EPL-1.0", "EPL-2.0", "EUPL-1.2", "GPL-2.0-only", "GPL-2.0-or-later", "GPL-3.0-only",
+    "GPL-3.0-or-later", "ISC", "LGPL-2.1-only", "LGPL-2.1-or-later", "LGPL-3.0-only", "LGPL-3.0-or-later", "MIT", "MIT-0", "MPL-2.0", "MS-PL", "NCSA",
+    "OFL-1.1", "OSL-3.0", "PostgreSQL", "Unicode-3.0", "Unlicense", "UPL-1.0", "WTFPL", "Zlib",
+];
+
+/// SPDX exceptions that may follow `WITH`.
+const SPDX_EXCEPTIONS: &[&str] = &["Classpath-exception-2.0", "GCC-exception-3.1", "LLVM-exception", "OpenSSL-exception"];
+
+/// The checks `stel publish` runs on `[package]`, by the name `--allow` takes.
+const PUBLISH_CHECKS: &[&str] = &["license", "description", "keywords", "repository", "readme", "stellang"];
+
+const MAX_DESCRIPTION_LENGTH: usize = 280;
+const MAX_KEYWORDS: usize = 5;
+const MAX_KEYWORD_LENGTH: usize = 20;
+
+/// Something `stel publish` found wrong with the package's metadata. Errors
+/// stop the upload; warnings are only printed.
+struct MetadataProblem {
+    check: &'static str,
+    error: bool,
+    message: String,
+}
+
+// Check what the registry will show for the package: its license, description,
+// keywords, repository, README and StelLang requirement, skipping the checks
+// named in `allowed`
+fn lint_metadata(package: &PackageInfo, readme: &Path, allowed: &[String]) -> Vec<MetadataProblem> {
+    let mut problems = Vec::new();
+    let mut report = |check: &'static str, error: bool, message: String| {
+        if !allowed.iter().any(|allow| allow == check) {
+            problems.push(MetadataProblem { check, error, message });
+        }
+    };
+
+    match package.license.as_deref().map(str::trim) {
+        None | Some("") => report("license", true, "no license; set an SPDX identifier such as license = \"MIT\"".to_string()),
+        Some(license) => {
+            if let Some(unknown) = unknown_license_terms(license).first() {
+                report("license", true, format!("'{}' is not a known SPDX license identifier (in license = \"{}\")", unknown, license));
+            }
+        }
+    }
+
+    match package.description.as_deref().map(str::trim) {
+        None | Some("") => report("description", false, "no description; search results show it under the package name".to_string()),
+        Some(description) if description.chars().count() > MAX_DESCRIPTION_LENGTH => {
+            report("description", true, format!("description is {} characters long; keep it to {}", description.chars().count(), MAX_DESCRIPTION_LENGTH));
+        }
+        Some(_) => {}
+    }
+
+    let keywords = package.keywords.as_deref().unwrap_or_default();
+    if keywords.len() > MAX_KEYWORDS {
+        report("keywords", true, format!("{} keywords; the registry indexes at most {}", keywords.len(), MAX_KEYWORDS));
+    }
+    for keyword in keywords {
+        let valid = !keyword.is_empty() && keyword.len() <= MAX_KEYWORD_LENGTH && keyword.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid {
+            report("keywords", true, format!("keyword '{}' must be 1 to {} lowercase letters, digits or dashes", keyword, MAX_KEYWORD_LENGTH));
+        }
+    }
+
+    match package.repository.as_deref().map(str::trim) {
+        None | Some("") => report("repository", false, "no repository URL; users can't find the source or report issues".to_string()),
+        Some(repository) => {
+            let valid = reqwest::Url::parse(repository).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some());
+            if !valid {
+                report("repository", true, format!("repository '{}' is not an http(s) URL", repository));
+            }
+        }
+    }
+
+    if !readme.is_file() {
+        report("readme", false, format!("no {}; the registry shows it as the package's page", readme.display()));
+    }
+
+    if let Some(requirement) = &package.stellang {
+        if let Err(e) = VersionReq::parse(requirement) {
+            report("stellang", true, format!("stellang = \"{}\" is not a version requirement: {}", requirement, e));
+        }
+    }
+    problems
+}
+
+/// The licenses and exceptions in an SPDX expression that aren't on the known lists.
+fn unknown_license_terms(expression: &str) -> Vec<String> {
+    let spaced = expression.replace('(', " ( ").replace(')', " ) ");
+    let mut unknown = Vec::new();
+    let mut after_with = false;
+    for term in spaced.split_whitespace() {
+        match term {
+            "(" | ")" | "OR" | "AND" => {}
+            "WITH" => {
+                after_with = true;
+                continue;
+            }
+            exception if after_with => {
+                if !SPDX_EXCEPTIONS.contains(&exception) {
+                    unknown.push(exception.to_string());
+                }
+            }
+            license => {
+                if !SPDX_LICENSES.contains(&license) {
+                    unknown.push(license.to_string());
+                }
+            }
+        }
+        after_with = false;
+    }
+    unknown
+}
+
+async fn cmd_publish(cli: &StelCLI, args: &[String]) {
+    // `--allow <check>` skips one of the metadata checks
+    let mut allowed = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let check = match arg.strip_prefix("--allow=") {
+            Some(check) => check.to_string(),
+            None if arg == "--allow" => args.next().cloned().unwrap_or_default(),
+            None => CliError::Usage(format!("unknown publish option '{}'", arg)).exit("stel"),
+        };
+        if !PUBLISH_CHECKS.contains(&check.as_str()) {
+            CliError::Usage(format!("--allow expects one of: {}", PUBLISH_CHECKS.join(", "))).exit("stel");
+        }
+        allowed.push(check);
+    }
+
     let manifest = match cli.read_manifest() {
         Ok(m) => m,
         Err(e) => {
@@ -812,6 +2463,16 @@ async fn cmd_publish(cli: &StelCLI) {
         }
     };
 
+    let problems = lint_metadata(&manifest.package, Path::new("README.md"), &allowed);
+    for problem in &problems {
+        eprintln!("{}: {}: {}", if problem.error { "error" } else { "warning" }, problem.check, problem.message);
+    }
+    let errors = problems.iter().filter(|problem| problem.error).count();
+    if errors > 0 {
+        eprintln!("Not publishing: {} metadata error(s) in stel.toml; fix them, or pass --allow <check> to publish anyway", errors);
+        std::process::exit(1);
+    }
+
     println!("Publishing {} v{}", manifest.package.name, manifest.package.version);
 
     // Check if we're logged in
@@ -916,9 +2577,16 @@ fn cmd_new(cli: &StelCLI, args: &[String]) {
             license: Some("MIT".to_string()),
             repository: None,
             keywords: Some(vec!["stellang".to_string()]),
+            features: Vec::new(),
+            build: None,
+            stellang: None,
+            edition: None,
         },
         dependencies: Some(HashMap::new()),
         dev_dependencies: Some(HashMap::new()),
+        native_dependencies: None,
+        bins: Vec::new(),
+        profile: None,
     };
 
     if let Err(e) = cli.write_manifest(&manifest) {
@@ -937,31 +2605,45 @@ fn cmd_new(cli: &StelCLI, args: &[String]) {
         "basic" => r#"// Basic StelLang project template
 
 fn main() {
-    print("Hello from {}!");
+    print("Hello from {{name}}!");
 }
 
 "#.to_string(),
-        "web" => r#"// Web application template
+        "web" => r#"# Web application template
+import http
 
-fn main() {
-    print("Starting web server...");
-    // TODO: Add web server implementation
+fn index(request) {
+    return http.response("<h1>Hello from {{name}}!</h1>", 200, {"Content-Type": "text/html; charset=utf-8"})
 }
 
-fn handle_request(request) {
-    return "Hello, World!";
+fn hello(request) {
+    return "Hello, " + request["params"]["name"] + "!"
 }
 
-"#.to_string(),
-        "cli" => r#"// Command-line application template
+# Serves until Ctrl-C
+http.serve("127.0.0.1:8000", {"GET /": "index", "GET /hello/:name": "hello"})
 
-fn main() {
-    let args = get_args();
-    if args.len() > 1 {
-        print("Hello, " + args[1] + "!");
-    } else {
-        print("Hello, World!");
-    }
+"#.to_string(),
+        "cli" => r#"# Command-line application template
+import argparse
+
+let args = argparse.parse({
+    "description": "Greet someone from the command line",
+    "arguments": [
+        {"name": "name", "nargs": "?", "default": "World", "help": "who to greet"},
+        {"name": "--count", "short": "-c", "type": "int", "default": 1, "help": "how many times to greet"},
+        {"name": "--loud", "flag": true, "help": "shout the greeting"}
+    ]
+})
+
+let greeting = "Hello, " + args["name"] + "!"
+if args["loud"] {
+    greeting = greeting.upper()
+}
+let i = 0
+while i < args["count"] {
+    print(greeting)
+    i = i + 1
 }
 
 "#.to_string(),
@@ -1041,6 +2723,15 @@ fn assert(condition, message) {
         }
     };
 
+    // Templates are rendered with the project's name as `{{name}}`
+    let data = Value::Dict(ValueMap::from_iter([(Value::Str("name".to_string()), Value::Str(project_name.clone()))]));
+    let main_content = match template::render(&main_content, &data, false) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to render template '{}': {}", template, e);
+            std::process::exit(1);
+        }
+    };
     let main_file = src_dir.join("main.stel");
     if let Err(e) = fs::write(&main_file, main_content) {
         eprintln!("Failed to create main.stel: {}", e);
@@ -1158,16 +2849,20 @@ fn cmd_template_install(_cli: &StelCLI, _args: &[String]) {
 }
 
 async fn cmd_search(cli: &StelCLI, args: &[String]) {
+    let (format, args) = OutputFormat::from_args("search", args);
     if args.is_empty() {
         eprintln!("stel search: missing search query");
-        eprintln!("Usage: stel search <query>");
+        eprintln!("Usage: stel search [--format json] <query>");
         std::process::exit(1);
     }
 
     let query = &args[0];
-    println!("Searching for packages matching '{}'...", query);
+    if format == OutputFormat::Text {
+        println!("Searching for packages matching '{}'...", query);
+    }
 
     match cli.search_registry(query).await {
+        Ok(packages) if format == OutputFormat::Json => print_json(serde_json::json!({ "query": query, "packages": packages })),
         Ok(packages) => {
             if packages.is_empty() {
                 println!("No packages found matching '{}'", query);
@@ -1187,9 +2882,74 @@ async fn cmd_search(cli: &StelCLI, args: &[String]) {
             }
         }
         Err(e) => {
-            eprintln!("Search failed: {}", e);
+            eprintln!("Search failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+async fn cmd_info(cli: &StelCLI, args: &[String]) {
+    let (format, args) = OutputFormat::from_args("info", args);
+    let Some(name) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("stel info: missing package name");
+        eprintln!("Usage: stel info [--format json] <package>");
+        std::process::exit(1);
+    };
+
+    let package = match cli.get_package_versions(name).await {
+        Ok(package) => package,
+        Err(e) => {
+            eprintln!("Failed to get package info: {}", e);
             std::process::exit(1);
         }
+    };
+
+    if format == OutputFormat::Json {
+        match serde_json::to_value(&package) {
+            Ok(payload) => print_json(payload),
+            Err(e) => CliError::Other(format!("can't encode package info: {}", e)).exit("stel"),
+        }
+        return;
+    }
+
+    let Some(latest) = package.versions.first() else {
+        println!("{} has no published versions", package.name);
+        return;
+    };
+    let downloads: u64 = package.versions.iter().map(|version| version.downloads).sum();
+    println!("📦 {}@{}", package.name, latest.version);
+    if let Some(desc) = &latest.description {
+        println!("   {}", desc);
+    }
+    println!();
+    if let Some(authors) = &latest.authors {
+        println!("Authors:    {}", authors.join(", "));
+    }
+    if let Some(license) = &latest.license {
+        println!("License:    {}", license);
+    }
+    println!("Downloads:  {}", downloads);
+
+    println!();
+    match latest.dependencies.as_ref().filter(|deps| !deps.is_empty()) {
+        Some(deps) => {
+            println!("Dependencies of {}:", latest.version);
+            let mut deps: Vec<_> = deps.iter().collect();
+            deps.sort();
+            for (dep, requirement) in deps {
+                println!("    {} = \"{}\"", dep, requirement);
+            }
+        }
+        None => println!("No dependencies"),
+    }
+
+    println!();
+    println!("Versions:");
+    let width = package.versions.iter().map(|version| version.version.len()).max().unwrap_or(0);
+    for version in &package.versions {
+        // Only the date part of the RFC 3339 upload time
+        let published = version.upload_date.as_deref().map_or("", |date| date.split('T').next().unwrap_or(date));
+        println!("    {:<width$}  {:<10}  {} downloads", version.version, published, version.downloads);
     }
 }
 
@@ -1228,6 +2988,10 @@ fn cmd_remove(cli: &StelCLI, args: &[String]) {
 }
 
 fn cmd_run(_cli: &StelCLI, _args: &[String]) {
+    if _args.iter().any(|arg| arg == "--watch") {
+        watch("run", _args);
+    }
+
     let manifest = match _cli.read_manifest() {
         Ok(m) => m,
         Err(e) => {
@@ -1236,59 +3000,189 @@ fn cmd_run(_cli: &StelCLI, _args: &[String]) {
         }
     };
 
-    println!("Running {} v{}", manifest.package.name, manifest.package.version);
-
-    let main_file = Path::new("src/main.stel");
-    if !main_file.exists() {
-        eprintln!("src/main.stel not found");
-        std::process::exit(1);
+    // `--bin <name>` picks the entry point and `--release` the profile; the rest are options for the run itself
+    let mut bin = None;
+    let mut release = false;
+    let mut run_args = Vec::new();
+    let mut args = _args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--bin" {
+            bin = Some(args.next().cloned().unwrap_or_else(|| {
+                CliError::Usage("--bin expects the name of a [[bin]] target".to_string()).exit("stel")
+            }));
+        } else if let Some(name) = arg.strip_prefix("--bin=") {
+            bin = Some(name.to_string());
+        } else if arg == "--release" {
+            release = true;
+        } else {
+            run_args.push(arg.clone());
+        }
     }
 
-    let content = match fs::read_to_string(main_file) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("Failed to read main.stel: {}", e);
+    let targets = manifest.bin_targets();
+    let names = || targets.iter().map(|target| target.name.as_str()).collect::<Vec<_>>().join(", ");
+    let target = match &bin {
+        Some(name) => targets.iter().find(|target| &target.name == name).unwrap_or_else(|| {
+            eprintln!("No bin target named '{}' in stel.toml (available: {})", name, names());
+            std::process::exit(1);
+        }),
+        None if targets.len() == 1 => &targets[0],
+        None => {
+            eprintln!("stel.toml declares several bin targets; choose one with --bin <name> (available: {})", names());
             std::process::exit(1);
         }
     };
 
-    // Create lexer and parser
-    let mut lexer = stellang::lang::lexer::Lexer::new(&content);
-    let mut tokens = Vec::new();
-    
-    loop {
-        match lexer.next_token() {
-            Ok(stellang::lang::lexer::Token::EOF) => break,
-            Ok(token) => tokens.push(token),
-            Err(e) => {
-                eprintln!("Lexer error: {:?}", e);
-                std::process::exit(1);
-            }
-        }
+    let profile = manifest.profile(release);
+    println!("Running {} v{} ({}, {})", manifest.package.name, manifest.package.version, target.name, profile.describe());
+    if let Err(e) = run_build_script(&manifest.package, Path::new(".")) {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
+    // Imports resolve next to the entry point, then among the installed packages and what build scripts generated
+    let entry_dir = Path::new(&target.path).parent().unwrap_or(Path::new(".")).to_path_buf();
+    let mut search_path = vec![entry_dir, PathBuf::from("dependencies")];
+    search_path.extend(fs::read_dir(STEL_OUT_DIR).into_iter().flatten().flatten().map(|entry| entry.path()).filter(|path| path.is_dir()));
+    stellang::lang::modules::set_search_path(search_path);
 
-    let mut parser = stellang::lang::parser::Parser::new(tokens);
-    let expr = match parser.parse() {
+    let expr = match load_program(Path::new(&target.path), &profile) {
         Ok(Some(e)) => e,
         Ok(None) => {
             println!("No expressions to run");
             return;
         }
         Err(e) => {
-            eprintln!("Parser error: {:?}", e);
+            eprintln!("{}: {}", target.path, e);
             std::process::exit(1);
         }
     };
 
     // Create interpreter and run
+    let stats_path = match run_args.as_slice() {
+        [flag, path, ..] if flag == "--stats" => Some(path.clone()),
+        [flag, ..] if flag.starts_with("--stats=") => Some(flag["--stats=".len()..].to_string()),
+        [flag] if flag == "--stats" => {
+            eprintln!("--stats expects a file to write the report to");
+            std::process::exit(1);
+        }
+        _ => None,
+    };
     let mut interpreter = stellang::lang::interpreter::Interpreter::new();
-    match interpreter.eval(&expr) {
+    interpreter.set_debug(profile.debug_assertions);
+    if stats_path.is_some() {
+        interpreter.enable_stats();
+    }
+    let result = interpreter.eval(&expr);
+    for e in interpreter.run_exit_handlers() {
+        eprintln!("Runtime error: {}", e);
+    }
+    if let (Some(path), Some(stats)) = (&stats_path, interpreter.stats()) {
+        let json = serde_json::to_string_pretty(&stats.to_json()).unwrap_or_default();
+        match fs::write(path, json + "\n") {
+            Ok(()) => println!("Execution stats written to {}", path),
+            Err(e) => eprintln!("Failed to write stats to {}: {}", path, e),
+        }
+    }
+    match result {
         Ok(_) => println!("Program completed successfully"),
-        Err(e) => {
-            eprintln!("Runtime error: {:?}", e);
-            std::process::exit(1);
+        // exit() and sys.exit() end the run with the status they were given
+        Err(e) => match e.exit_code() {
+            Some(code) => std::process::exit(code),
+            None => {
+                eprintln!("Runtime error: {}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+// How often --watch looks for changes, and how long the sources must stay
+// unchanged before a rerun, so that saving several files at once reruns once
+const WATCH_POLL: Duration = Duration::from_millis(200);
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+const WATCHED_DIRS: &[&str] = &["src", "tests"];
+
+// `stel run --watch` and `stel test --watch`: run the command in a child stel and
+// start it over whenever a source file changes. A child is used because both
+// commands exit the process on failure, and so a long-running program can be killed.
+fn watch(command: &str, args: &[String]) -> ! {
+    let exe = env::current_exe().unwrap_or_else(|e| CliError::Other(format!("can't find the stel executable: {}", e)).exit("stel"));
+    let args: Vec<&String> = args.iter().filter(|arg| *arg != "--watch").collect();
+    let clear = io::stdout().is_terminal();
+    let rules = IgnoreRules::load(Path::new(".")).unwrap_or_else(|e| CliError::Other(e).exit("stel"));
+    let mut sources = watched_sources(&rules);
+    loop {
+        if clear {
+            // Clear the screen and move the cursor home
+            print!("\x1b[2J\x1b[H");
+        }
+        println!("[watch] stel {}", command);
+        let mut child = match std::process::Command::new(&exe).arg(command).args(&args).spawn() {
+            Ok(child) => Some(child),
+            Err(e) => {
+                eprintln!("[watch] failed to start stel {}: {}", command, e);
+                None
+            }
+        };
+        loop {
+            if let Some(status) = child.as_mut().and_then(|c| c.try_wait().ok().flatten()) {
+                let outcome = if status.success() { "finished" } else { "failed" };
+                println!("[watch] {}; waiting for changes in {} (Ctrl-C to stop)", outcome, WATCHED_DIRS.join("/, ") + "/");
+                child = None;
+            }
+            std::thread::sleep(WATCH_POLL);
+            if sources_changed(&mut sources, &rules) {
+                break;
+            }
+        }
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Every .stel (or .stl) file under the watched directories that .stelignore
+// doesn't list, with its modification time
+fn watched_sources(rules: &IgnoreRules) -> HashMap<PathBuf, Option<SystemTime>> {
+    fn visit(dir: &Path, rules: &IgnoreRules, sources: &mut HashMap<PathBuf, Option<SystemTime>>) {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if rules.is_ignored(&path, path.is_dir()) {
+                continue;
+            }
+            if path.is_dir() {
+                visit(&path, rules, sources);
+            } else if path.extension().is_some_and(|ext| ext == "stel" || ext == "stl") {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                sources.insert(path, modified);
+            }
+        }
+    }
+    let mut sources = HashMap::new();
+    for dir in WATCHED_DIRS {
+        visit(Path::new(dir), rules, &mut sources);
+    }
+    sources
+}
+
+// Whether a file was added, removed or modified since `sources` was taken. On a
+// change, wait for the files to settle and update `sources` to the settled state.
+fn sources_changed(sources: &mut HashMap<PathBuf, Option<SystemTime>>, rules: &IgnoreRules) -> bool {
+    let mut current = watched_sources(rules);
+    if current == *sources {
+        return false;
+    }
+    loop {
+        std::thread::sleep(WATCH_DEBOUNCE);
+        let settled = watched_sources(rules);
+        if settled == current {
+            break;
         }
+        current = settled;
     }
+    *sources = current;
+    true
 }
 
 fn cmd_clean(_cli: &StelCLI) {
@@ -1310,7 +3204,78 @@ fn cmd_clean(_cli: &StelCLI) {
     println!("Clean completed");
 }
 
-fn cmd_tree(cli: &StelCLI) {
+fn cmd_cache(cli: &StelCLI, args: &[String]) {
+    match args.first().map(String::as_str) {
+        Some("dir") => {
+            let dir = env::current_dir().map(|cwd| cwd.join(&cli.cache_dir)).unwrap_or_else(|_| cli.cache_dir.clone());
+            println!("{}", dir.display());
+        }
+        Some("size") => cmd_cache_size(cli),
+        Some("clean") => cmd_cache_clean(cli, &args[1..]),
+        Some("prune") => cmd_cache_prune(),
+        Some(other) => CliError::Usage(format!("unknown cache subcommand '{}' (expected dir, size, clean or prune)", other)).exit("stel"),
+        None => CliError::Usage("cache expects a subcommand: dir, size, clean or prune".to_string()).exit("stel"),
+    }
+}
+
+fn cmd_cache_size(cli: &StelCLI) {
+    let entries = cli.cache_entries().unwrap_or_else(|e| CliError::Other(format!("can't read {}: {}", STEL_CACHE_DIR, e)).exit("stel"));
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    let packages = entries.iter().filter(|entry| entry.package.is_some()).count();
+    println!("{} in {} package(s) and {} build file(s) (limit {})", format_size(total), packages, entries.len() - packages, format_size(cli.cache_max_size()));
+}
+
+// `stel cache clean [--package <name>] [--older-than <age>]`; without options
+// the whole cache goes
+fn cmd_cache_clean(cli: &StelCLI, args: &[String]) {
+    let mut package = None;
+    let mut older_than = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next().cloned()).unwrap_or_else(|| CliError::Usage(format!("{} expects a value", flag)).exit("stel"));
+        match flag {
+            "--package" => package = Some(value()),
+            "--older-than" => {
+                let age = value();
+                older_than = Some(parse_age(&age).unwrap_or_else(|| CliError::Usage(format!("--older-than expects an age such as 30d, 12h or 2w, not '{}'", age)).exit("stel")));
+            }
+            _ => CliError::Usage(format!("unknown cache clean option '{}'", arg)).exit("stel"),
+        }
+    }
+
+    let entries = cli.cache_entries().unwrap_or_else(|e| CliError::Other(format!("can't read {}: {}", STEL_CACHE_DIR, e)).exit("stel"));
+    let now = SystemTime::now();
+    let (mut removed, mut freed) = (0, 0);
+    for entry in entries {
+        if package.as_ref().is_some_and(|name| entry.package.as_ref() != Some(name)) {
+            continue;
+        }
+        if older_than.is_some_and(|age| now.duration_since(entry.used).unwrap_or_default() < age) {
+            continue;
+        }
+        if let Err(e) = remove_cache_entry(&entry.path) {
+            eprintln!("Failed to remove {}: {}", entry.path.display(), e);
+            std::process::exit(1);
+        }
+        removed += 1;
+        freed += entry.size;
+    }
+    println!("Removed {} cache entr{} ({} freed)", removed, if removed == 1 { "y" } else { "ies" }, format_size(freed));
+}
+
+// `stel cache prune`: the package store is shared by every project, so what
+// goes is what none of them links to
+fn cmd_cache_prune() {
+    let (removed, freed) = prune_store().unwrap_or_else(|e| CliError::Other(format!("can't prune {}: {}", package_store().display(), e)).exit("stel"));
+    println!("Removed {} unused package{} from the store ({} freed)", removed, if removed == 1 { "" } else { "s" }, format_size(freed));
+}
+
+fn cmd_tree(cli: &StelCLI, args: &[String]) {
+    let (format, _) = OutputFormat::from_args("tree", args);
     let manifest = match cli.read_manifest() {
         Ok(m) => m,
         Err(e) => {
@@ -1319,6 +3284,26 @@ fn cmd_tree(cli: &StelCLI) {
         }
     };
 
+    if format == OutputFormat::Json {
+        let lockfile = cli.read_lockfile().unwrap_or_else(|e| {
+            eprintln!("Failed to read lockfile: {}", e);
+            std::process::exit(1);
+        });
+        let locked_packages = &lockfile.packages;
+        let dependencies = |deps: &Option<HashMap<String, String>>, dev: bool| {
+            let mut deps: Vec<_> = deps.iter().flatten().collect();
+            deps.sort();
+            deps.into_iter().map(move |(name, requirement)| {
+                let locked = locked_packages.get(name);
+                serde_json::json!({ "name": name, "requirement": requirement, "dev": dev, "locked": locked.map(|locked| &locked.version), "linked": locked.is_some_and(|locked| locked.source.starts_with(PATH_SOURCE_PREFIX)) })
+            }).collect::<Vec<_>>()
+        };
+        let mut all = dependencies(&manifest.dependencies, false);
+        all.extend(dependencies(&manifest.dev_dependencies, true));
+        print_json(serde_json::json!({ "name": manifest.package.name, "version": manifest.package.version, "dependencies": all }));
+        return;
+    }
+
     println!("{} v{}", manifest.package.name, manifest.package.version);
 
     if let Some(deps) = &manifest.dependencies {
@@ -1334,6 +3319,119 @@ fn cmd_tree(cli: &StelCLI) {
     }
 }
 
+// `stel link` in a library registers it under its package name; `stel link <name>`
+// in a project then uses that directory for the dependency instead of the registry
+fn cmd_link(cli: &StelCLI, args: &[String]) {
+    let links_path = stel_home().join("links.toml");
+    let mut links: Links = read_toml_or_default(&links_path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", links_path.display(), e);
+        std::process::exit(1);
+    });
+
+    let Some(name) = args.first() else {
+        let manifest = match cli.read_manifest() {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("Failed to read stel.toml: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let dir = env::current_dir().and_then(fs::canonicalize).unwrap_or_else(|e| {
+            eprintln!("Failed to resolve the current directory: {}", e);
+            std::process::exit(1);
+        });
+        links.links.insert(manifest.package.name.clone(), dir.display().to_string());
+        if let Err(e) = fs::create_dir_all(stel_home()).and_then(|_| write_toml(&links_path, &links)) {
+            eprintln!("Failed to write {}: {}", links_path.display(), e);
+            std::process::exit(1);
+        }
+        println!("Registered {} at {}", manifest.package.name, dir.display());
+        println!("Run 'stel link {}' in a project to use it", manifest.package.name);
+        return;
+    };
+
+    let Some(path) = links.links.get(name) else {
+        eprintln!("No linked package named '{}'", name);
+        eprintln!("Run 'stel link' in the package's directory first");
+        std::process::exit(1);
+    };
+    if let Err(e) = link_dependency(name, Path::new(path)) {
+        eprintln!("Failed to link {}: {}", name, e);
+        std::process::exit(1);
+    }
+    let mut overrides = cli.read_overrides().unwrap_or_default();
+    overrides.overrides.insert(name.clone(), path.clone());
+    if let Err(e) = cli.write_overrides(&overrides) {
+        eprintln!("Failed to write .stel/overrides.toml: {}", e);
+        std::process::exit(1);
+    }
+    println!("Linked dependencies/{} -> {}", name, path);
+    println!("Run 'stel unlink {}' to go back to the registry version", name);
+}
+
+fn cmd_unlink(cli: &StelCLI, args: &[String]) {
+    let Some(name) = args.first() else {
+        eprintln!("stel unlink: missing package name");
+        eprintln!("Usage: stel unlink <package>");
+        std::process::exit(1);
+    };
+
+    let mut overrides = match cli.read_overrides() {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            eprintln!("Failed to read .stel/overrides.toml: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if overrides.overrides.remove(name).is_none() {
+        eprintln!("{} is not linked", name);
+        std::process::exit(1);
+    }
+    if let Err(e) = cli.write_overrides(&overrides) {
+        eprintln!("Failed to write .stel/overrides.toml: {}", e);
+        std::process::exit(1);
+    }
+    // Only the symlink goes; the linked package itself is left alone
+    let target = Path::new("dependencies").join(name);
+    if target.is_symlink() {
+        let _ = fs::remove_file(&target).or_else(|_| fs::remove_dir(&target));
+    }
+    println!("Unlinked {}", name);
+    println!("Run 'stel install' to install it from the registry again");
+}
+
+// Point dependencies/<name> at a directory, replacing whatever is there
+fn link_dependency(name: &str, path: &Path) -> io::Result<()> {
+    if !path.join(STEL_MANIFEST_FILE).exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no stel.toml in {}", path.display())));
+    }
+    let deps_dir = Path::new("dependencies");
+    fs::create_dir_all(deps_dir)?;
+    let target = deps_dir.join(name);
+    if target.is_symlink() {
+        fs::remove_file(&target).or_else(|_| fs::remove_dir(&target))?;
+    } else if target.exists() {
+        fs::remove_dir_all(&target)?;
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(path, &target)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_dir(path, &target)?;
+    Ok(())
+}
+
+fn read_toml_or_default<T: Default + serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    toml::from_str(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_toml<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let content = toml::to_string_pretty(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(path, content)
+}
+
 fn cmd_login(cli: &StelCLI) {
     println!("Logging in to Stel registry...");
     
@@ -1343,7 +3441,9 @@ fn cmd_login(cli: &StelCLI) {
     }
 
     print!("Enter your registry token: ");
-    io::stdout().flush().unwrap();
+    if let Err(e) = io::stdout().flush() {
+        CliError::Stdout(e).exit("stel");
+    }
     
     let mut token = String::new();
     if let Err(e) = io::stdin().read_line(&mut token) {
@@ -1379,7 +3479,9 @@ fn cmd_logout(cli: &StelCLI) {
     }
 }
 
-async fn cmd_outdated(cli: &StelCLI) {
+async fn cmd_outdated(cli: &StelCLI, args: &[String]) {
+    let (format, _) = OutputFormat::from_args("outdated", args);
+    let text = format == OutputFormat::Text;
     let manifest = match cli.read_manifest() {
         Ok(m) => m,
         Err(e) => {
@@ -1396,54 +3498,81 @@ async fn cmd_outdated(cli: &StelCLI) {
         }
     };
 
-    println!("Checking for outdated dependencies...");
-    println!();
+    if text {
+        println!("Checking for outdated dependencies...");
+        println!();
+    }
 
     let mut outdated_count = 0;
+    // One entry per dependency for --format json
+    let mut report = Vec::new();
 
     if let Some(deps) = &manifest.dependencies {
         for (name, version_req) in deps {
             if let Some(locked_package) = lockfile.packages.get(name) {
+                if locked_package.source.starts_with(PATH_SOURCE_PREFIX) {
+                    if text {
+                        println!("{}: {} (linked)", name, locked_package.version);
+                    }
+                    report.push(serde_json::json!({ "name": name, "current": locked_package.version, "status": "linked" }));
+                    continue;
+                }
                 // Get latest version from registry
                 match cli.get_package_info(name, version_req).await {
                     Ok(latest_info) => {
-                        let current_version = Version::parse(&locked_package.version).unwrap();
-                        let latest_version = Version::parse(&latest_info.version).unwrap();
-                        
+                        let (current_version, latest_version) = match (Version::parse(&locked_package.version), Version::parse(&latest_info.version)) {
+                            (Ok(current), Ok(latest)) => (current, latest),
+                            (Err(e), _) | (_, Err(e)) => {
+                                eprintln!("{}: Invalid version: {}", name, e);
+                                report.push(serde_json::json!({ "name": name, "current": locked_package.version, "status": "error", "error": format!("Invalid version: {}", e) }));
+                                continue;
+                            }
+                        };
+
                         if latest_version > current_version {
-                            println!("{}: {} → {}", name, locked_package.version, latest_info.version);
-                            if let Some(desc) = latest_info.description {
-                                println!("   {}", desc);
+                            if text {
+                                println!("{}: {} → {}", name, locked_package.version, latest_info.version);
+                                if let Some(desc) = &latest_info.description {
+                                    println!("   {}", desc);
+                                }
+                                println!();
                             }
-                            println!();
+                            report.push(serde_json::json!({ "name": name, "current": locked_package.version, "latest": latest_info.version, "status": "outdated", "description": latest_info.description }));
                             outdated_count += 1;
                         } else {
-                            println!("{}: {} (up to date)", name, locked_package.version);
+                            if text {
+                                println!("{}: {} (up to date)", name, locked_package.version);
+                            }
+                            report.push(serde_json::json!({ "name": name, "current": locked_package.version, "latest": latest_info.version, "status": "up_to_date" }));
                         }
                     }
                     Err(e) => {
                         eprintln!("{}: Failed to check for updates: {}", name, e);
+                        report.push(serde_json::json!({ "name": name, "current": locked_package.version, "status": "error", "error": e.to_string() }));
                     }
                 }
             }
         }
     }
 
-    if outdated_count == 0 {
+    if !text {
+        report.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        print_json(serde_json::json!({ "dependencies": report, "outdated": outdated_count }));
+    } else if outdated_count == 0 {
         println!("All dependencies are up to date.");
     } else {
         println!("Found {} outdated dependencies. Run 'stel update' to update them.", outdated_count);
     }
 }
 
-async fn cmd_audit(cli: &StelCLI) {
-    let manifest = match cli.read_manifest() {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("Failed to read stel.toml: {}", e);
-            std::process::exit(1);
-        }
-    };
+async fn cmd_audit(cli: &StelCLI, args: &[String]) {
+    let (format, _) = OutputFormat::from_args("audit", args);
+    let text = format == OutputFormat::Text;
+    // Only audit inside a project, even though the lockfile says what to check
+    if let Err(e) = cli.read_manifest() {
+        eprintln!("Failed to read stel.toml: {}", e);
+        std::process::exit(1);
+    }
 
     let lockfile = match cli.read_lockfile() {
         Ok(l) => l,
@@ -1453,20 +3582,33 @@ async fn cmd_audit(cli: &StelCLI) {
         }
     };
 
-    println!("Checking for security vulnerabilities...");
-    println!();
+    if text {
+        println!("Checking for security vulnerabilities...");
+        println!();
+    }
 
     let mut vulnerabilities = 0;
+    // Every finding, for --format json: security issues and warnings alike
+    let mut issues = Vec::new();
+    let mut report = |severity: &str, name: &str, version: &str, message: String| {
+        if text {
+            println!("{}: {}@{} - {}", severity.to_uppercase(), name, version, message);
+        }
+        issues.push(serde_json::json!({ "severity": severity, "name": name, "version": version, "message": message }));
+    };
 
     for (name, locked_package) in &lockfile.packages {
         // total_packages += 1; // This line was removed as per the edit hint
+        if locked_package.source.starts_with(PATH_SOURCE_PREFIX) {
+            continue; // Linked packages are local code, not a registry release
+        }
         
         match cli.get_package_info(name, &locked_package.version).await {
             Ok(package_info) => {
                 if let Some(checksum) = &locked_package.checksum {
                     if let Some(package_checksum) = &package_info.checksum {
                         if checksum != package_checksum {
-                            println!("SECURITY: {}@{} - Checksum mismatch", name, locked_package.version);
+                            report("security", name, &locked_package.version, "Checksum mismatch".to_string());
                             vulnerabilities += 1;
                         }
                     }
@@ -1474,16 +3616,21 @@ async fn cmd_audit(cli: &StelCLI) {
                 
                 if let Some(desc) = &package_info.description {
                     if desc.to_lowercase().contains("deprecated") || desc.to_lowercase().contains("security") {
-                        println!("WARNING: {}@{} - {}", name, locked_package.version, desc);
+                        report("warning", name, &locked_package.version, desc.clone());
                     }
                 }
             }
             Err(e) => {
-                println!("WARNING: {}@{} - Failed to verify: {}", name, locked_package.version, e);
+                report("warning", name, &locked_package.version, format!("Failed to verify: {}", e));
             }
         }
     }
 
+    if !text {
+        issues.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        print_json(serde_json::json!({ "issues": issues, "vulnerabilities": vulnerabilities }));
+        return;
+    }
     println!();
     if vulnerabilities == 0 {
         println!("No security vulnerabilities found.");
@@ -1493,6 +3640,311 @@ async fn cmd_audit(cli: &StelCLI) {
     // println!("Audited {} packages.", total_packages); // This line was removed as per the edit hint
 }
 
+// `stel verify`: hash each installed registry package again and compare it with
+// the integrity hash stel.lock recorded when it was installed
+fn cmd_verify(cli: &StelCLI) {
+    let lockfile = match cli.read_lockfile() {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to read lockfile: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut packages: Vec<_> = lockfile.packages.iter().collect();
+    packages.sort_by_key(|(name, _)| name.as_str());
+    let mut checked = 0;
+    let mut failed = 0;
+    for (name, locked) in packages {
+        if let Some(path) = locked.source.strip_prefix(PATH_SOURCE_PREFIX) {
+            println!("  - {}: linked to {}, not verified", name, path);
+            continue;
+        }
+        let dir = Path::new("dependencies").join(name);
+        let problem = match &locked.integrity {
+            None => Some("stel.lock has no integrity hash for it; run 'stel install'".to_string()),
+            Some(_) if !dir.is_dir() => Some("not installed".to_string()),
+            Some(expected) => match hash_tree(&dir, &|_| false) {
+                Ok(actual) if actual == *expected => None,
+                Ok(_) => Some("files were modified or corrupted since it was installed".to_string()),
+                Err(e) => Some(format!("can't read {}: {}", dir.display(), e)),
+            },
+        };
+        checked += 1;
+        match problem {
+            None => println!("  ✓ {}@{}", name, locked.version),
+            Some(problem) => {
+                println!("  ✗ {}@{}: {}", name, locked.version, problem);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{} of {} package(s) failed verification", failed, checked);
+        std::process::exit(1);
+    }
+    println!("Verified {} package(s)", checked);
+}
+
+/// The interpreter's version, as `sys.version` reports it, which a package's
+/// `stellang` requirement is checked against.
+const STELLANG_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Editions of the language this release understands, oldest first. A
+/// package without an `edition` is written for the first.
+const EDITIONS: &[&str] = &["2025"];
+
+/// Why this StelLang can't use a package with the given `stellang`
+/// requirement and `edition`, phrased to follow the package's name.
+fn toolchain_mismatch(stellang: Option<&str>, edition: Option<&str>) -> Option<String> {
+    if let Some(requirement) = stellang {
+        match VersionReq::parse(requirement) {
+            Err(e) => return Some(format!("has an invalid stellang requirement '{}': {}", requirement, e)),
+            Ok(req) if !Version::parse(STELLANG_VERSION).is_ok_and(|version| req.matches(&version)) => {
+                return Some(format!("needs StelLang {}, but this is StelLang {}", requirement, STELLANG_VERSION));
+            }
+            Ok(_) => {}
+        }
+    }
+    edition.filter(|edition| !EDITIONS.contains(edition)).map(|edition| format!("is written for edition {}, which StelLang {} doesn't know (it knows {})", edition, STELLANG_VERSION, EDITIONS.join(", ")))
+}
+
+/// Exit when this StelLang can't build the project, before doing anything.
+fn require_toolchain(package: &PackageInfo) {
+    if let Some(reason) = toolchain_mismatch(package.stellang.as_deref(), package.edition.as_deref()) {
+        eprintln!("{} {}", package.name, reason);
+        std::process::exit(1);
+    }
+}
+
+/// Something `stel doctor` found wrong, and what to do about it.
+struct DoctorFinding {
+    /// Errors fail `stel doctor`; warnings don't
+    error: bool,
+    message: String,
+    fix: String,
+}
+
+impl DoctorFinding {
+    fn error(message: String, fix: &str) -> Self {
+        DoctorFinding { error: true, message, fix: fix.to_string() }
+    }
+
+    fn warning(message: String, fix: &str) -> Self {
+        DoctorFinding { error: false, message, fix: fix.to_string() }
+    }
+}
+
+/// The fix for findings that a fresh `stel install` takes care of; `stel
+/// doctor` offers to run it.
+const INSTALL_FIX: &str = "run 'stel install'";
+
+// `stel doctor`: check the project and its environment, printing each problem
+// with a suggested fix, and offer to run `stel install` when that fixes them
+async fn cmd_doctor(cli: &StelCLI) {
+    let mut checks: Vec<(&str, Vec<DoctorFinding>)> = Vec::new();
+
+    let manifest = match doctor_manifest() {
+        Ok((manifest, findings)) => {
+            checks.push((STEL_MANIFEST_FILE, findings));
+            Some(manifest)
+        }
+        Err(finding) => {
+            checks.push((STEL_MANIFEST_FILE, vec![finding]));
+            None
+        }
+    };
+    if let Some(manifest) = &manifest {
+        checks.push(("StelLang version", doctor_stellang_version(&manifest.package)));
+        checks.push((STEL_LOCK_FILE, doctor_lockfile(cli, manifest)));
+        checks.push(("dependencies/", doctor_dependencies(cli)));
+    }
+    checks.push(("cache", doctor_cache(cli)));
+    checks.push(("registry", doctor_registries(cli).await));
+
+    let mut errors = 0;
+    let mut install_fixes = false;
+    for (check, findings) in &checks {
+        if findings.is_empty() {
+            println!("  ✓ {}", check);
+        }
+        for finding in findings {
+            println!("  {} {}: {}", if finding.error { "✗" } else { "!" }, check, finding.message);
+            println!("      fix: {}", finding.fix);
+            errors += finding.error as usize;
+            install_fixes |= finding.fix == INSTALL_FIX;
+        }
+    }
+    let warnings = checks.iter().flat_map(|(_, findings)| findings).count() - errors;
+    println!();
+    if errors == 0 && warnings == 0 {
+        println!("No problems found");
+        return;
+    }
+    println!("{} error(s), {} warning(s)", errors, warnings);
+
+    if install_fixes && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        print!("Run 'stel install' now? [y/N] ");
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y") {
+            return cmd_install(cli, &[]).await;
+        }
+    }
+    if errors > 0 {
+        std::process::exit(1);
+    }
+}
+
+// stel.toml parses, and its version, dependency requirements and build script make sense
+fn doctor_manifest() -> Result<(PackageManifest, Vec<DoctorFinding>), DoctorFinding> {
+    let content = fs::read_to_string(STEL_MANIFEST_FILE)
+        .map_err(|e| DoctorFinding::error(format!("can't read {}: {}", STEL_MANIFEST_FILE, e), "run 'stel init' to create a project here, or run stel doctor in the project's directory"))?;
+    let manifest: PackageManifest = toml::from_str(&content).map_err(|e| DoctorFinding::error(format!("{} is not valid: {}", STEL_MANIFEST_FILE, e.to_string().trim_end()), "correct stel.toml where the error points"))?;
+
+    let mut findings = Vec::new();
+    if let Err(e) = Version::parse(&manifest.package.version) {
+        findings.push(DoctorFinding::error(format!("version '{}' is not a semantic version: {}", manifest.package.version, e), "use a version such as 0.1.0"));
+    }
+    for (name, requirement) in manifest.dependencies.iter().chain(&manifest.dev_dependencies).flatten() {
+        if let Err(e) = VersionReq::parse(requirement) {
+            findings.push(DoctorFinding::error(format!("dependency {} has an invalid version requirement '{}': {}", name, requirement, e), "use a requirement such as ^1.2 or 1.2.3"));
+        }
+    }
+    for feature in &manifest.package.features {
+        if let Err(e) = stellang::lang::features::enable(feature) {
+            findings.push(DoctorFinding::error(e.to_string(), "remove it from `features` in stel.toml"));
+        }
+    }
+    if let Some(script) = manifest.package.build.as_ref().filter(|script| !Path::new(script).is_file()) {
+        findings.push(DoctorFinding::error(format!("build script {} does not exist", script), "create it, or remove `build` from stel.toml"));
+    }
+    Ok((manifest, findings))
+}
+
+// The package's `stellang` requirement and `edition` admit this interpreter
+fn doctor_stellang_version(package: &PackageInfo) -> Vec<DoctorFinding> {
+    toolchain_mismatch(package.stellang.as_deref(), package.edition.as_deref())
+        .map(|reason| DoctorFinding::error(format!("{} {}", package.name, reason), "install a StelLang release that matches, or change `stellang` or `edition` in stel.toml"))
+        .into_iter()
+        .collect()
+}
+
+// stel.lock exists and agrees with stel.toml
+fn doctor_lockfile(cli: &StelCLI, manifest: &PackageManifest) -> Vec<DoctorFinding> {
+    let deps = manifest.dependencies.clone().unwrap_or_default();
+    if !Path::new(STEL_LOCK_FILE).exists() {
+        return if deps.is_empty() { Vec::new() } else { vec![DoctorFinding::error(format!("{} is missing", STEL_LOCK_FILE), INSTALL_FIX)] };
+    }
+    let lockfile = match cli.read_lockfile() {
+        Ok(lockfile) => lockfile,
+        Err(e) => return vec![DoctorFinding::error(format!("{} is not valid: {}", STEL_LOCK_FILE, e.to_string().trim_end()), INSTALL_FIX)],
+    };
+
+    let mut findings = Vec::new();
+    let mut names: Vec<_> = deps.keys().collect();
+    names.sort();
+    for name in names {
+        let requirement = &deps[name];
+        match lockfile.packages.get(name) {
+            None => findings.push(DoctorFinding::error(format!("{} is in {} but not in {}", name, STEL_MANIFEST_FILE, STEL_LOCK_FILE), INSTALL_FIX)),
+            Some(locked) if locked.source.starts_with(PATH_SOURCE_PREFIX) => {}
+            Some(locked) => {
+                let satisfied = VersionReq::parse(requirement).ok().zip(Version::parse(&locked.version).ok()).is_none_or(|(req, version)| req.matches(&version));
+                if !satisfied {
+                    findings.push(DoctorFinding::error(format!("{} has {} {}, which doesn't satisfy {} in {}", STEL_LOCK_FILE, name, locked.version, requirement, STEL_MANIFEST_FILE), INSTALL_FIX));
+                }
+            }
+        }
+    }
+
+    // Packages no dependency leads to any more, directly or through others
+    let mut needed: Vec<&String> = deps.keys().collect();
+    let mut reachable = std::collections::HashSet::new();
+    while let Some(name) = needed.pop() {
+        if reachable.insert(name.clone()) {
+            needed.extend(lockfile.packages.get(name).and_then(|locked| locked.dependencies.as_ref()).into_iter().flat_map(|deps| deps.keys()));
+        }
+    }
+    let mut unused: Vec<_> = lockfile.packages.keys().filter(|name| !reachable.contains(*name)).collect();
+    unused.sort();
+    for name in unused {
+        findings.push(DoctorFinding::warning(format!("{} still has {}, which nothing depends on", STEL_LOCK_FILE, name), INSTALL_FIX));
+    }
+    findings
+}
+
+// Every locked package is installed
+fn doctor_dependencies(cli: &StelCLI) -> Vec<DoctorFinding> {
+    let Ok(lockfile) = cli.read_lockfile() else {
+        return Vec::new();
+    };
+    let deps_dir = Path::new("dependencies");
+    if lockfile.packages.is_empty() {
+        return Vec::new();
+    }
+    if !deps_dir.is_dir() {
+        return vec![DoctorFinding::error("dependencies/ is missing".to_string(), INSTALL_FIX)];
+    }
+    let mut names: Vec<_> = lockfile.packages.keys().collect();
+    names.sort();
+    let mut findings = Vec::new();
+    for name in names {
+        let dir = deps_dir.join(name);
+        if dir.is_symlink() && !dir.exists() {
+            findings.push(DoctorFinding::error(format!("dependencies/{} links to a directory that no longer exists", name), "run 'stel link' again, or 'stel unlink' it"));
+        } else if !dir.is_dir() {
+            findings.push(DoctorFinding::error(format!("{} is not installed in dependencies/{}", name, name), INSTALL_FIX));
+        }
+    }
+    findings
+}
+
+// The project's cache and the package store can be read
+fn doctor_cache(cli: &StelCLI) -> Vec<DoctorFinding> {
+    let mut findings = Vec::new();
+    if let Err(e) = cli.cache_entries() {
+        findings.push(DoctorFinding::error(format!("can't read {}: {}", STEL_CACHE_DIR, e), "fix its permissions, or delete the directory; stel fills it again"));
+    }
+    let store = package_store();
+    if store.exists() {
+        if let Err(e) = fs::read_dir(&store) {
+            findings.push(DoctorFinding::error(format!("can't read the package store {}: {}", store.display(), e), "fix its permissions, or delete the directory and run 'stel install'"));
+        }
+    }
+    findings
+}
+
+// The registry and each mirror answer
+async fn doctor_registries(cli: &StelCLI) -> Vec<DoctorFinding> {
+    let mut unreachable = Vec::new();
+    for (i, registry) in cli.registries.iter().enumerate() {
+        let timeout = Duration::from_secs(registry.timeout.unwrap_or(DEFAULT_REGISTRY_TIMEOUT));
+        let answer = match reqwest::Client::builder().timeout(timeout).build() {
+            Ok(client) => client.get(format!("{}/api/search?q=stel", registry.url.trim_end_matches('/'))).header("User-Agent", "stel-cli/1.0").send().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match answer {
+            Ok(response) if response.status().is_server_error() => unreachable.push((i, registry, response.status().to_string())),
+            Ok(_) => {}
+            Err(e) => unreachable.push((i, registry, e)),
+        }
+    }
+    let all = unreachable.len() == cli.registries.len();
+    unreachable
+        .into_iter()
+        .map(|(i, registry, reason)| {
+            let message = format!("{} can't be reached: {}", registry.url, reason);
+            match (all, i) {
+                (true, _) => DoctorFinding::error(message, "check your network connection, or add a mirror under [registry] in ~/.stel/config.toml"),
+                (false, 0) => DoctorFinding::warning(message, "installs use the mirrors meanwhile; check STEL_REGISTRY if it is set"),
+                (false, _) => DoctorFinding::warning(message, "remove the mirror from ~/.stel/config.toml if it is gone for good"),
+            }
+        })
+        .collect()
+}
+
 fn cmd_version() {
     println!("stel 1.0.0");
     println!("StelLang Package Manager");
@@ -1517,13 +3969,19 @@ fn cmd_help() {
     println!("    install     Install dependencies");
     println!("    update      Update dependencies");
     println!("    clean       Clean build artifacts");
+    println!("    cache       Show the package cache's location and size, clean it or prune the store");
     println!("    tree        Show dependency tree");
     println!("    search      Search for packages");
+    println!("    info        Show a package's versions and metadata");
     println!("    publish     Publish package to registry");
+    println!("    link        Use a local package in place of the registry's");
+    println!("    unlink      Stop using a linked package");
     println!("    login       Log in to registry");
     println!("    logout      Log out from registry");
     println!("    outdated    Check for outdated dependencies");
     println!("    audit       Check for security vulnerabilities");
+    println!("    verify      Check installed dependencies against stel.lock");
+    println!("    doctor      Diagnose problems with the project and its environment");
     println!("    version     Show version information");
     println!("    help        Show this help message");
     println!();
@@ -1531,11 +3989,31 @@ fn cmd_help() {
     println!("    stel init                    # Initialize new project");
     println!("    stel new my-project          # Create new project");
     println!("    stel add some-package        # Add dependency");
+    println!("    stel add --native fastmath   # Add native extension module");
     println!("    stel build                   # Build project");
+    println!("    stel build --release         # Build with the release profile");
     println!("    stel run                     # Run project");
+    println!("    stel run --bin tool          # Run one of several [[bin]] targets");
+    println!("    stel run --stats out.json    # Run and write execution stats");
+    println!("    stel run --watch             # Rerun when a source file changes");
     println!("    stel test                    # Run tests");
+    println!("    stel test --watch            # Rerun tests when a source file changes");
+    println!("    stel test parser --skip slow # Tests named *parser*, except *slow*");
+    println!("    stel test --exclude-tag slow # Leave out tests tagged slow");
+    println!("    stel test --format json      # Test results as JSON for CI");
+    println!("    stel install --jobs 8        # Download up to 8 packages at once");
+    println!("    stel install --script t.stel # Make a script executable");
+    println!("    stel install --global        # Install the project's bins");
+    println!("    stel verify                  # Fail if an installed package was modified");
+    println!("    stel doctor                  # Find problems and how to fix them");
     println!("    stel search http             # Search for packages");
+    println!("    stel info http-client --json # Package details for scripts");
+    println!("    stel cache size              # Show how much the package cache holds");
+    println!("    stel cache prune             # Drop stored packages no project uses");
     println!("    stel publish                 # Publish to registry");
+    println!("    stel publish --allow readme  # Publish without the README check");
+    println!("    stel link                    # Register this library for linking");
+    println!("    stel link mylib              # Use the registered mylib here");
     println!();
     println!("For more information, visit: {}", STEL_REGISTRY_URL);
 }
@@ -0,0 +1,61 @@
+// Errors shared by the command-line tools: what to tell the user and which status to exit with
+
+use std::fmt;
+use std::io;
+
+/// A failure outside of the script itself that stops `stellang`, `stel` or
+/// `registry`. Script errors are exceptions and are reported as tracebacks instead.
+#[derive(Debug)]
+pub enum CliError {
+    /// Missing or malformed command-line arguments
+    Usage(String),
+    /// A file named on the command line could not be read
+    Read { path: String, source: io::Error },
+    /// A file the tool produces could not be written
+    Write { path: String, source: io::Error },
+    /// Reading from stdin failed
+    Stdin(io::Error),
+    /// Writing to stdout failed, as when a pipe's reader has gone away
+    Stdout(io::Error),
+    /// Anything else, already phrased for the user
+    Other(String),
+}
+
+impl CliError {
+    /// 2 for usage errors and unreadable input files, as Python and most Unix
+    /// tools do, and 1 for everything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) | CliError::Read { .. } => 2,
+            CliError::Write { .. } | CliError::Stdin(_) | CliError::Stdout(_) | CliError::Other(_) => 1,
+        }
+    }
+
+    /// Print `<program>: <message>` to stderr and exit with `exit_code()`.
+    pub fn exit(&self, program: &str) -> ! {
+        eprintln!("{}: {}", program, self);
+        std::process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Other(message) => f.write_str(message),
+            CliError::Read { path, source } => write!(f, "can't open file '{}': {}", path, source),
+            CliError::Write { path, source } => write!(f, "can't write file '{}': {}", path, source),
+            CliError::Stdin(source) => write!(f, "can't read stdin: {}", source),
+            CliError::Stdout(source) => write!(f, "can't write to stdout: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CliError::Read { source, .. } | CliError::Write { source, .. } | CliError::Stdin(source) | CliError::Stdout(source) => Some(source),
+            CliError::Usage(_) | CliError::Other(_) => None,
+        }
+    }
+}
+
@@ -0,0 +1,93 @@
+// Crash reports: when the interpreter itself panics, write what a maintainer
+// needs to reproduce it to a local file and tell the user where it is. Nothing
+// is sent anywhere; attaching the report to an issue is up to the user.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where reports go, relative to the project root (or the current directory
+/// outside a project).
+pub const CRASH_DIR: &str = ".stel/crash";
+
+/// Where the printed instructions send users with a report.
+pub const ISSUES_URL: &str = "https://github.com/MaheshDhingra/StelLang/issues/new";
+
+/// How much of the running script a report quotes.
+const SNIPPET_LINES: usize = 40;
+
+// The script being run, by name, for the report
+static SCRIPT: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Replace the default panic message with one that writes a report to `dir`
+/// and asks for it to be attached to an issue. `program` prefixes the
+/// message, as `CliError::exit` does.
+pub fn install(program: &'static str, dir: PathBuf) {
+    panic::set_hook(Box::new(move |info| {
+        let report = render(program, info, &Backtrace::force_capture());
+        eprintln!("{}: internal error: {}", program, panic_message(info));
+        match save(&dir, &report) {
+            Ok(path) => {
+                eprintln!("This is a bug in StelLang, not in your program. A crash report was written to {}.", path.display());
+                eprintln!("Please check it for anything private, then attach it to a new issue at {}", ISSUES_URL);
+            }
+            Err(e) => eprintln!("(couldn't write a crash report to {}: {})\n{}", dir.display(), e, report),
+        }
+    }));
+}
+
+/// Quote `source` in any report written from now on, under `name`.
+pub fn set_script(name: &str, source: &str) {
+    *SCRIPT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some((name.to_string(), source.to_string()));
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let message = payload.downcast_ref::<&str>().copied().or_else(|| payload.downcast_ref::<String>().map(String::as_str)).unwrap_or("(no message)");
+    match info.location() {
+        Some(location) => format!("{} at {}", message, location),
+        None => message.to_string(),
+    }
+}
+
+fn render(program: &str, info: &PanicHookInfo, backtrace: &Backtrace) -> String {
+    let mut report = String::from("# StelLang crash report\n\n");
+    let _ = writeln!(report, "version: {} {}", program, env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(report, "command: {}", std::env::args().collect::<Vec<_>>().join(" "));
+    let _ = writeln!(report, "thread: {}", std::thread::current().name().unwrap_or("(unnamed)"));
+    let _ = writeln!(report, "panic: {}", panic_message(info));
+
+    if let Some((name, source)) = SCRIPT.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).as_ref() {
+        let lines = source.lines().count();
+        let _ = writeln!(report, "\n## Script: {} ({} lines)\n", name, lines);
+        for (number, line) in source.lines().take(SNIPPET_LINES).enumerate() {
+            let _ = writeln!(report, "{:>4} | {}", number + 1, line);
+        }
+        if lines > SNIPPET_LINES {
+            let _ = writeln!(report, "     | ... {} more lines", lines - SNIPPET_LINES);
+        }
+    }
+    let _ = write!(report, "\n## Backtrace\n\n{}\n", backtrace);
+    report
+}
+
+// Write the report to a new file in `dir` and return its path. Panics on
+// several threads at once each get their own file.
+fn save(dir: &Path, report: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let stem = format!("crash-{}-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), std::process::id());
+    for attempt in 1.. {
+        let path = if attempt == 1 { dir.join(format!("{}.txt", stem)) } else { dir.join(format!("{}-{}.txt", stem, attempt)) };
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => return file.write_all(report.as_bytes()).map(|()| path),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    unreachable!("ran out of crash report names")
+}
@@ -0,0 +1,135 @@
+//! C ABI for embedding StelLang in C, C++, Python (ctypes/cffi) and other hosts.
+//!
+//! The matching declarations live in `include/stellang.h`. Every function is
+//! null-safe; failures are reported through `StelStatus` codes and the message
+//! is available from `stellang_get_error`.
+
+use crate::lang::{interpreter::{Interpreter, Value}, parser::Parser, exceptions::{Exception, ExceptionKind}};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+/// Result codes returned by `stellang_eval`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StelStatus {
+    Ok = 0,
+    /// A null pointer or non-UTF-8 source was passed in
+    InvalidArgument = 1,
+    /// The source failed to lex or parse
+    SyntaxError = 2,
+    /// Evaluation raised an exception
+    RuntimeError = 3,
+}
+
+/// Opaque interpreter handle owned by the host.
+pub struct StelInterpreter {
+    interpreter: Interpreter,
+    last_result: Value,
+    last_error: Option<Exception>,
+}
+
+/// Create a new interpreter. Release it with `stellang_free`.
+#[no_mangle]
+pub extern "C" fn stellang_new() -> *mut StelInterpreter {
+    let interpreter = Interpreter::new();
+    Box::into_raw(Box::new(StelInterpreter { interpreter, last_result: Value::None, last_error: None }))
+}
+
+/// Evaluate a NUL-terminated UTF-8 source string. State persists between calls.
+///
+/// # Safety
+/// `interp` must come from `stellang_new` and `source` must be a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn stellang_eval(interp: *mut StelInterpreter, source: *const c_char) -> c_int {
+    let Some(handle) = interp.as_mut() else {
+        return StelStatus::InvalidArgument as c_int;
+    };
+    if source.is_null() {
+        return handle.fail(StelStatus::InvalidArgument, Exception::new(ExceptionKind::ValueError, vec!["source is null".to_string()]));
+    }
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(s) => s,
+        Err(e) => return handle.fail(StelStatus::InvalidArgument, Exception::new(ExceptionKind::UnicodeDecodeError, vec![e.to_string()])),
+    };
+    let expr = match Parser::parse_source_with_lines(source) {
+        Ok(Some(expr)) => expr,
+        Ok(None) => {
+            handle.last_result = Value::None;
+            handle.last_error = None;
+            return StelStatus::Ok as c_int;
+        }
+        Err(exc) => return handle.fail(StelStatus::SyntaxError, exc),
+    };
+    match handle.interpreter.eval(&expr) {
+        Ok(value) => {
+            handle.last_result = value;
+            handle.last_error = None;
+            StelStatus::Ok as c_int
+        }
+        Err(exc) => handle.fail(StelStatus::RuntimeError, exc),
+    }
+}
+
+/// Display string of the last successful result. Free with `stellang_string_free`.
+///
+/// # Safety
+/// `interp` must be null or come from `stellang_new`.
+#[no_mangle]
+pub unsafe extern "C" fn stellang_get_string(interp: *const StelInterpreter) -> *mut c_char {
+    match interp.as_ref() {
+        Some(handle) => to_c_string(handle.last_result.to_display_string()),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// `"Kind: message"` for the last failed evaluation, followed by ` (line N)`
+/// when the line it came from is known, or null if it succeeded.
+/// Free with `stellang_string_free`.
+///
+/// # Safety
+/// `interp` must be null or come from `stellang_new`.
+#[no_mangle]
+pub unsafe extern "C" fn stellang_get_error(interp: *const StelInterpreter) -> *mut c_char {
+    match interp.as_ref().and_then(|h| h.last_error.as_ref()) {
+        Some(exc) => to_c_string(match exc.line {
+            Some(line) => format!("{:?}: {} (line {})", exc.kind, exc.args.join(", "), line),
+            None => format!("{:?}: {}", exc.kind, exc.args.join(", ")),
+        }),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Release a string returned by this library.
+///
+/// # Safety
+/// `s` must be null or a pointer returned by `stellang_get_string`/`stellang_get_error`.
+#[no_mangle]
+pub unsafe extern "C" fn stellang_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Destroy an interpreter created by `stellang_new`.
+///
+/// # Safety
+/// `interp` must be null or come from `stellang_new`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn stellang_free(interp: *mut StelInterpreter) {
+    if !interp.is_null() {
+        drop(Box::from_raw(interp));
+    }
+}
+
+impl StelInterpreter {
+    fn fail(&mut self, status: StelStatus, exc: Exception) -> c_int {
+        self.last_result = Value::None;
+        self.last_error = Some(exc);
+        status as c_int
+    }
+}
+
+// Interior NULs can't cross the C boundary, so they are dropped
+fn to_c_string(s: String) -> *mut c_char {
+    CString::new(s.replace('\0', "")).unwrap_or_default().into_raw()
+}
@@ -0,0 +1,91 @@
+// A project's .stelignore: paths, in gitignore syntax, that the tools leave out
+// of what they package and watch
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+use glob::{MatchOptions, Pattern};
+
+/// The ignore file, read from the project's root directory.
+pub const IGNORE_FILE: &str = ".stelignore";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions { case_sensitive: true, require_literal_separator: true, require_literal_leading_dot: false };
+
+/// One line of the ignore file.
+struct Rule {
+    pattern: Pattern,
+    /// `!pattern`: include what an earlier rule ignored
+    negated: bool,
+    /// `pattern/`: match directories only
+    dir_only: bool,
+    /// The pattern has a `/` before its end, so it matches paths from the
+    /// project root instead of names at any depth
+    anchored: bool,
+}
+
+/// The rules of an ignore file, in order; the last rule matching a path decides.
+#[derive(Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// The rules in `root`'s .stelignore, or none when it has no such file.
+    pub fn load(root: &Path) -> Result<IgnoreRules, String> {
+        let path = root.join(IGNORE_FILE);
+        match fs::read_to_string(&path) {
+            Ok(text) => IgnoreRules::parse(&text).map_err(|e| format!("{}:{}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(IgnoreRules::default()),
+            Err(e) => Err(format!("can't read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Parse gitignore syntax: one pattern per line, `#` comments, `!` to
+    /// negate, a trailing `/` for directories only, and `*`, `?`, `[...]` and
+    /// `**` wildcards. Errors name the line with the bad pattern.
+    pub fn parse(text: &str) -> Result<IgnoreRules, String> {
+        let mut rules = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                // `\#` and `\!` start patterns with those characters
+                None => (false, line.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!'])).unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let line = line.strip_prefix('/').unwrap_or(line);
+            let pattern = Pattern::new(line).map_err(|e| format!("{}: invalid pattern '{}': {}", number + 1, line, e.msg))?;
+            rules.push(Rule { pattern, negated, dir_only, anchored });
+        }
+        Ok(IgnoreRules { rules })
+    }
+
+    /// Whether `path`, relative to the project root, is ignored, either itself
+    /// or because a directory it is in is.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let components: Vec<&str> = path.components().filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        }).collect();
+        (1..components.len()).any(|end| self.decide(&components[..end], true)) || self.decide(&components, is_dir)
+    }
+
+    fn decide(&self, components: &[&str], is_dir: bool) -> bool {
+        let Some(name) = components.last() else {
+            return false;
+        };
+        let path = components.join("/");
+        let matching = self.rules.iter().rev().find(|rule| {
+            (is_dir || !rule.dir_only) && rule.pattern.matches_with(if rule.anchored { &path } else { name }, MATCH_OPTIONS)
+        });
+        matching.is_some_and(|rule| !rule.negated)
+    }
+}
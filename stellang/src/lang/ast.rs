@@ -1,10 +1,14 @@
 // AST definitions for StelLang
 
+use std::sync::Arc;
+
+use super::symbol::Symbol;
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Expr {
     Integer(i64),
     Float(f64), // f64 cannot implement Eq or Hash directly, will need manual impl for Expr
-    Ident(String),
+    Ident(Symbol),
     String(String),
     BinaryOp {
         left: Box<Expr>,
@@ -12,7 +16,7 @@ pub enum Expr {
         right: Box<Expr>,
     },
     Assign {
-        name: String,
+        name: Symbol,
         expr: Box<Expr>,
     },
     Block(Vec<Expr>),
@@ -24,19 +28,43 @@ pub enum Expr {
     While {
         cond: Box<Expr>,
         body: Box<Expr>,
+        label: Option<Symbol>,
     },
-    FnDef {
-        name: String,
-        params: Vec<String>,
+    /// `loop { ... }`, left only through break or return
+    Loop {
         body: Box<Expr>,
+        label: Option<Symbol>,
+    },
+    /// `do { ... } while cond`, which runs its body at least once
+    DoWhile {
+        body: Box<Expr>,
+        cond: Box<Expr>,
+        label: Option<Symbol>,
+    },
+    /// `fn name(params) { body }`. The body is shared with every function
+    /// value defined from it, so defining a function doesn't copy it.
+    FnDef {
+        name: Symbol,
+        params: Vec<Symbol>,
+        body: Arc<Expr>,
+    },
+    /// `@cache` or `@cache(128)` lines above a function definition, outermost first
+    Decorated {
+        decorators: Vec<Expr>,
+        def: Box<Expr>,
     },
     FnCall {
         callable: Box<Expr>,
         args: Vec<Expr>,
     },
+    /// `name=value` among the arguments of a call, after any positional ones
+    KeywordArg {
+        name: Symbol,
+        value: Box<Expr>,
+    },
     GetAttr {
         object: Box<Expr>,
-        name: String,
+        name: Symbol,
     },
     // === Added for arrays, maps, indexing, unary, and return ===
     ArrayLiteral(Vec<Expr>),
@@ -55,14 +83,16 @@ pub enum Expr {
         expr: Box<Expr>,
     },
     Return(Box<Expr>),
-    Break,
-    Continue,
+    /// `break` or `break 'label`
+    Break(Option<Symbol>),
+    /// `continue` or `continue 'label`
+    Continue(Option<Symbol>),
     Let {
-        name: String,
+        name: Symbol,
         expr: Box<Expr>,
     },
     Const {
-        name: String,
+        name: Symbol,
         expr: Box<Expr>,
     },
     Bool(bool),
@@ -73,57 +103,66 @@ pub enum Expr {
         arms: Vec<(Expr, Expr)>, // (pattern, result)
     },
     StructDef {
-        name: String,
-        fields: Vec<String>,
+        name: Symbol,
+        fields: Vec<Symbol>,
     },
     StructInit {
-        name: String,
-        fields: Vec<(String, Expr)>,
+        name: Symbol,
+        fields: Vec<(Symbol, Expr)>,
     },
     EnumDef {
-        name: String,
-        variants: Vec<String>,
+        name: Symbol,
+        variants: Vec<Symbol>,
     },
     EnumInit {
-        name: String,
-        variant: String,
+        name: Symbol,
+        variant: Symbol,
         value: Option<Box<Expr>>,
     },
+    /// `for x in items { }`, or `for (k, v) in pairs { }` unpacking each item into several names
     For {
-        var: String,
+        vars: Vec<Symbol>,
         iter: Box<Expr>,
         body: Box<Expr>,
+        label: Option<Symbol>,
     },
+    /// `try { } catch e { } finally { }`; either the catch or the finally may be left out
     TryCatch {
         try_block: Box<Expr>,
-        catch_var: Option<String>,
-        catch_block: Box<Expr>,
+        catch_var: Option<Symbol>,
+        catch_block: Option<Box<Expr>>,
+        finally_block: Option<Box<Expr>>,
+    },
+    Throw {
+        value: Box<Expr>,
+        cause: Option<Box<Expr>>,
     },
-    Throw(Box<Expr>),
     TupleLiteral(Vec<Expr>),
     Destructure {
-        names: Vec<String>,
+        names: Vec<Symbol>,
         expr: Box<Expr>,
     },
     Import(String),
+    ImportNative(String),
     LetTyped {
-        name: String,
-        ty: String,
+        name: Symbol,
+        ty: Symbol,
         expr: Box<Expr>,
     },
     ConstTyped {
-        name: String,
-        ty: String,
+        name: Symbol,
+        ty: Symbol,
         expr: Box<Expr>,
     },
     Global {
-        name: String,
+        name: Symbol,
         expr: Box<Expr>,
     },
     Static {
-        name: String,
+        name: Symbol,
         expr: Box<Expr>,
     },
+    /// `defer expr`: run `expr` when the enclosing block ends, however it ends
     Defer(Box<Expr>),
     Switch {
         expr: Box<Expr>,
@@ -131,22 +170,48 @@ pub enum Expr {
         default: Option<Box<Expr>>,
     },
     ClassDef {
-        name: String,
+        name: Symbol,
         bases: Vec<Expr>,
         body: Vec<Expr>,
     },
+    /// `abstract fn name(params)` in a class body: a method subclasses must define
+    AbstractFn {
+        name: Symbol,
+        params: Vec<Symbol>,
+    },
     ClassInit {
-        class_name: String,
+        class_name: Symbol,
         args: Vec<Expr>,
     },
     MethodCall {
         object: Box<Expr>,
-        method: String,
+        method: Symbol,
         args: Vec<Expr>,
     },
     FieldAccess {
         object: Box<Expr>,
-        field: String,
+        field: Symbol,
+    },
+    With {
+        resource: Box<Expr>,
+        alias: Option<Symbol>,
+        body: Box<Expr>,
+    },
+    /// `test "name" tags=["slow"] { ... }`: run by `stel test`, skipped otherwise
+    Test {
+        name: String,
+        tags: Vec<String>,
+        body: Box<Expr>,
+    },
+    /// A statement and the line it starts on in its source file, recorded by
+    /// `Parser::with_lines` so that tracebacks point at the code as written,
+    /// however passes such as the optimizer rewrite the statement. `file` is
+    /// the path of that file, when the parser was told it (`Parser::in_file`).
+    Located {
+        line: usize,
+        #[serde(default)]
+        file: Option<Arc<str>>,
+        expr: Box<Expr>,
     },
 }
 
@@ -174,19 +239,37 @@ impl Hash for Expr {
                 then_branch.hash(state);
                 else_branch.hash(state);
             },
-            Expr::While { cond, body } => {
+            Expr::While { cond, body, label } => {
                 cond.hash(state);
                 body.hash(state);
+                label.hash(state);
+            },
+            Expr::Loop { body, label } => {
+                body.hash(state);
+                label.hash(state);
+            },
+            Expr::DoWhile { body, cond, label } => {
+                body.hash(state);
+                cond.hash(state);
+                label.hash(state);
             },
             Expr::FnDef { name, params, body } => {
                 name.hash(state);
                 params.hash(state);
                 body.hash(state);
             },
+            Expr::Decorated { decorators, def } => {
+                decorators.hash(state);
+                def.hash(state);
+            },
             Expr::FnCall { callable, args } => {
                 callable.hash(state);
                 args.hash(state);
             },
+            Expr::KeywordArg { name, value } => {
+                name.hash(state);
+                value.hash(state);
+            },
             Expr::GetAttr { object, name } => {
                 object.hash(state);
                 name.hash(state);
@@ -212,8 +295,14 @@ impl Hash for Expr {
                 expr.hash(state);
             },
             Expr::Return(expr) => expr.hash(state),
-            Expr::Break => "Break".hash(state),
-            Expr::Continue => "Continue".hash(state),
+            Expr::Break(label) => {
+                "Break".hash(state);
+                label.hash(state);
+            },
+            Expr::Continue(label) => {
+                "Continue".hash(state);
+                label.hash(state);
+            },
             Expr::Let { name, expr } => {
                 name.hash(state);
                 expr.hash(state);
@@ -251,23 +340,32 @@ impl Hash for Expr {
                 variant.hash(state);
                 value.hash(state);
             },
-            Expr::For { var, iter, body } => {
-                var.hash(state);
+            Expr::For { vars, iter, body, label } => {
+                vars.hash(state);
                 iter.hash(state);
                 body.hash(state);
+                label.hash(state);
             },
-            Expr::TryCatch { try_block, catch_var, catch_block } => {
+            Expr::TryCatch { try_block, catch_var, catch_block, finally_block } => {
                 try_block.hash(state);
                 catch_var.hash(state);
                 catch_block.hash(state);
+                finally_block.hash(state);
+            },
+            Expr::Throw { value, cause } => {
+                value.hash(state);
+                cause.hash(state);
             },
-            Expr::Throw(expr) => expr.hash(state),
             Expr::TupleLiteral(items) => items.hash(state),
             Expr::Destructure { names, expr } => {
                 names.hash(state);
                 expr.hash(state);
             },
             Expr::Import(s) => s.hash(state),
+            Expr::ImportNative(s) => {
+                "native".hash(state);
+                s.hash(state);
+            },
             Expr::LetTyped { name, ty, expr } => {
                 name.hash(state);
                 ty.hash(state);
@@ -300,6 +398,10 @@ impl Hash for Expr {
                 bases.hash(state);
                 body.hash(state);
             },
+            Expr::AbstractFn { name, params } => {
+                name.hash(state);
+                params.hash(state);
+            },
             Expr::ClassInit { class_name, args } => {
                 class_name.hash(state);
                 args.hash(state);
@@ -313,6 +415,158 @@ impl Hash for Expr {
                 object.hash(state);
                 field.hash(state);
             },
+            Expr::With { resource, alias, body } => {
+                resource.hash(state);
+                alias.hash(state);
+                body.hash(state);
+            },
+            Expr::Test { name, tags, body } => {
+                name.hash(state);
+                tags.hash(state);
+                body.hash(state);
+            },
+            Expr::Located { line, file, expr } => {
+                line.hash(state);
+                file.hash(state);
+                expr.hash(state);
+            },
+        }
+    }
+}
+
+impl Expr {
+    /// This statement without the line a parser recorded for it.
+    pub fn unlocated(&self) -> &Expr {
+        match self {
+            Expr::Located { expr, .. } => expr,
+            expr => expr,
+        }
+    }
+
+    /// The expressions directly inside this one, for passes that only read the tree.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Integer(_) | Expr::Float(_) | Expr::Ident(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null
+            | Expr::Break(_) | Expr::Continue(_) | Expr::StructDef { .. } | Expr::EnumDef { .. }
+            | Expr::AbstractFn { .. } | Expr::Import(_) | Expr::ImportNative(_) => Vec::new(),
+            Expr::BinaryOp { left, right, .. } => vec![left, right],
+            Expr::Assign { expr, .. } | Expr::Let { expr, .. } | Expr::Const { expr, .. }
+            | Expr::LetTyped { expr, .. } | Expr::ConstTyped { expr, .. } | Expr::Global { expr, .. }
+            | Expr::Static { expr, .. } | Expr::Destructure { expr, .. } | Expr::UnaryOp { expr, .. }
+            | Expr::Return(expr) | Expr::Defer(expr) | Expr::Located { expr, .. } => vec![expr],
+            Expr::Block(exprs) | Expr::ArrayLiteral(exprs) | Expr::TupleLiteral(exprs)
+            | Expr::ClassInit { args: exprs, .. } => exprs.iter().collect(),
+            Expr::If { cond, then_branch, else_branch } => {
+                let mut children = vec![&**cond, &**then_branch];
+                children.extend(else_branch.as_deref());
+                children
+            }
+            Expr::While { cond, body, .. } | Expr::DoWhile { body, cond, .. } => vec![cond, body],
+            Expr::Loop { body, .. } => vec![body],
+            Expr::FnDef { body, .. } => vec![&**body],
+            Expr::Decorated { decorators, def } => decorators.iter().chain(std::iter::once(&**def)).collect(),
+            Expr::FnCall { callable, args } => std::iter::once(&**callable).chain(args.iter()).collect(),
+            Expr::KeywordArg { value, .. } => vec![value],
+            Expr::GetAttr { object, .. } | Expr::FieldAccess { object, .. } => vec![object],
+            Expr::MapLiteral(pairs) => pairs.iter().flat_map(|(k, v)| [k, v]).collect(),
+            Expr::Index { collection, index } => vec![collection, index],
+            Expr::AssignIndex { collection, index, expr } => vec![collection, index, expr],
+            Expr::Match { expr, arms } => std::iter::once(&**expr).chain(arms.iter().flat_map(|(pattern, result)| [pattern, result])).collect(),
+            Expr::StructInit { fields, .. } => fields.iter().map(|(_, expr)| expr).collect(),
+            Expr::EnumInit { value, .. } => value.as_deref().into_iter().collect(),
+            Expr::For { iter, body, .. } => vec![iter, body],
+            Expr::TryCatch { try_block, catch_block, finally_block, .. } => {
+                std::iter::once(&**try_block).chain(catch_block.as_deref()).chain(finally_block.as_deref()).collect()
+            }
+            Expr::Throw { value, cause } => std::iter::once(&**value).chain(cause.as_deref()).collect(),
+            Expr::Switch { expr, cases, default } => std::iter::once(&**expr)
+                .chain(cases.iter().flat_map(|(case, body)| [case, body]))
+                .chain(default.as_deref())
+                .collect(),
+            Expr::ClassDef { bases, body, .. } => bases.iter().chain(body.iter()).collect(),
+            Expr::MethodCall { object, args, .. } => std::iter::once(&**object).chain(args.iter()).collect(),
+            Expr::With { resource, body, .. } => vec![resource, body],
+            Expr::Test { body, .. } => vec![body],
         }
     }
+
+    /// The expressions directly inside this one, for passes that rewrite the tree.
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Integer(_) | Expr::Float(_) | Expr::Ident(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null
+            | Expr::Break(_) | Expr::Continue(_) | Expr::StructDef { .. } | Expr::EnumDef { .. }
+            | Expr::AbstractFn { .. } | Expr::Import(_) | Expr::ImportNative(_) => Vec::new(),
+            Expr::BinaryOp { left, right, .. } => vec![left, right],
+            Expr::Assign { expr, .. } | Expr::Let { expr, .. } | Expr::Const { expr, .. }
+            | Expr::LetTyped { expr, .. } | Expr::ConstTyped { expr, .. } | Expr::Global { expr, .. }
+            | Expr::Static { expr, .. } | Expr::Destructure { expr, .. } | Expr::UnaryOp { expr, .. }
+            | Expr::Return(expr) | Expr::Defer(expr) | Expr::Located { expr, .. } => vec![expr],
+            Expr::Block(exprs) | Expr::ArrayLiteral(exprs) | Expr::TupleLiteral(exprs)
+            | Expr::ClassInit { args: exprs, .. } => exprs.iter_mut().collect(),
+            Expr::If { cond, then_branch, else_branch } => {
+                let mut children = vec![&mut **cond, &mut **then_branch];
+                children.extend(else_branch.as_deref_mut());
+                children
+            }
+            Expr::While { cond, body, .. } | Expr::DoWhile { body, cond, .. } => vec![cond, body],
+            Expr::Loop { body, .. } => vec![body],
+            // Copies the body only if a defined function still shares it
+            Expr::FnDef { body, .. } => vec![Arc::make_mut(body)],
+            Expr::Decorated { decorators, def } => decorators.iter_mut().chain(std::iter::once(&mut **def)).collect(),
+            Expr::FnCall { callable, args } => std::iter::once(&mut **callable).chain(args.iter_mut()).collect(),
+            Expr::KeywordArg { value, .. } => vec![value],
+            Expr::GetAttr { object, .. } | Expr::FieldAccess { object, .. } => vec![object],
+            Expr::MapLiteral(pairs) => pairs.iter_mut().flat_map(|(k, v)| [k, v]).collect(),
+            Expr::Index { collection, index } => vec![collection, index],
+            Expr::AssignIndex { collection, index, expr } => vec![collection, index, expr],
+            Expr::Match { expr, arms } => std::iter::once(&mut **expr).chain(arms.iter_mut().flat_map(|(pattern, result)| [pattern, result])).collect(),
+            Expr::StructInit { fields, .. } => fields.iter_mut().map(|(_, expr)| expr).collect(),
+            Expr::EnumInit { value, .. } => value.as_deref_mut().into_iter().collect(),
+            Expr::For { iter, body, .. } => vec![iter, body],
+            Expr::TryCatch { try_block, catch_block, finally_block, .. } => {
+                std::iter::once(&mut **try_block).chain(catch_block.as_deref_mut()).chain(finally_block.as_deref_mut()).collect()
+            }
+            Expr::Throw { value, cause } => std::iter::once(&mut **value).chain(cause.as_deref_mut()).collect(),
+            Expr::Switch { expr, cases, default } => std::iter::once(&mut **expr)
+                .chain(cases.iter_mut().flat_map(|(case, body)| [case, body]))
+                .chain(default.as_deref_mut())
+                .collect(),
+            Expr::ClassDef { bases, body, .. } => bases.iter_mut().chain(body.iter_mut()).collect(),
+            Expr::MethodCall { object, args, .. } => std::iter::once(&mut **object).chain(args.iter_mut()).collect(),
+            Expr::With { resource, body, .. } => vec![resource, body],
+            Expr::Test { body, .. } => vec![body],
+        }
+    }
+}
+
+/// Read-only traversal of a syntax tree, for tools such as linters. Override
+/// `visit_expr` to look at the nodes of interest and call `walk_expr` from it to
+/// carry on into their children; the default visits everything.
+pub trait Visitor {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visit each child of `expr`.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    for child in expr.children() {
+        visitor.visit_expr(child);
+    }
+}
+
+/// In-place rewriting of a syntax tree, for instrumentation and code mods.
+/// Override `transform_expr` to replace or change nodes, calling
+/// `walk_expr_mut` first to rewrite bottom up or afterwards for top down.
+pub trait Transformer {
+    fn transform_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+}
+
+/// Transform each child of `expr`.
+pub fn walk_expr_mut<T: Transformer + ?Sized>(transformer: &mut T, expr: &mut Expr) {
+    for child in expr.children_mut() {
+        transformer.transform_expr(child);
+    }
 }
@@ -0,0 +1,48 @@
+// Programs as JSON, for tools that generate or rewrite StelLang code and hand
+// it back to the interpreter (`stellang --emit ast-json` / `--from ast-json`)
+
+use super::ast::Expr;
+use super::exceptions::{Exception, ExceptionKind};
+
+const FORMAT: &str = "stellang-ast";
+
+/// Version of the JSON layout of `Expr`. Every variant is an object keyed by
+/// its name (`{"Integer": 1}`, `{"Ident": "x"}`, `{"BinaryOp": {"left": ..,
+/// "op": "+", "right": ..}}`); renaming, removing or reshaping a variant or
+/// field bumps this, adding a variant does not.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Document {
+    format: String,
+    version: u32,
+    /// None for a program with nothing in it
+    program: Option<Expr>,
+}
+
+/// `program` wrapped in a document naming the format and schema version.
+pub fn to_json(program: Option<&Expr>) -> String {
+    let document = Document { format: FORMAT.to_string(), version: SCHEMA_VERSION, program: program.cloned() };
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// The program in a document written by `to_json`, or by any tool following
+/// the same schema. Documents from another schema version are refused rather
+/// than half understood.
+pub fn from_json(text: &str) -> Result<Option<Expr>, Exception> {
+    let header: serde_json::Value = serde_json::from_str(text).map_err(|e| invalid(format!("not valid JSON: {}", e)))?;
+    if header.get("format").and_then(|format| format.as_str()) != Some(FORMAT) {
+        return Err(invalid(format!("expected a document with \"format\": \"{}\"", FORMAT)));
+    }
+    match header.get("version").and_then(|version| version.as_u64()) {
+        Some(version) if version == SCHEMA_VERSION as u64 => {}
+        Some(version) => return Err(invalid(format!("unsupported schema version {} (this interpreter reads version {})", version, SCHEMA_VERSION))),
+        None => return Err(invalid("missing schema \"version\"".to_string())),
+    }
+    let document: Document = serde_json::from_value(header).map_err(|e| invalid(e.to_string()))?;
+    Ok(document.program)
+}
+
+fn invalid(message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![format!("invalid AST JSON: {}", message)])
+}
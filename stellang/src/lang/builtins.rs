@@ -0,0 +1,255 @@
+// Builtins that work across value types: len, min, max, sum, abs, round and enumerate
+
+use super::containers;
+use super::exceptions::{Exception, ExceptionKind};
+use super::interpreter::{raise, Value};
+use super::stdlib::decimal;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::cmp::Ordering;
+
+/// The builtins answered here rather than by the interpreter itself.
+pub const NAMES: &[&str] = &["len", "min", "max", "sum", "abs", "round", "enumerate"];
+
+/// Call the builtin `name`, one of `NAMES`, with evaluated arguments.
+pub fn call(name: &str, args: &[Value]) -> Result<Value, Exception> {
+    match name {
+        "len" => match args {
+            [value] => len(value),
+            _ => raise(ExceptionKind::TypeError, vec![format!("len() takes exactly one argument ({} given)", args.len())]),
+        },
+        "min" => extreme(name, args, Ordering::Less),
+        "max" => extreme(name, args, Ordering::Greater),
+        "sum" => match args {
+            [items] => sum(items, Value::Int(0)),
+            [items, start] => sum(items, start.clone()),
+            _ => raise(ExceptionKind::TypeError, vec![format!("sum() takes 1 or 2 arguments ({} given)", args.len())]),
+        },
+        "abs" => match args {
+            [value] => abs(value),
+            _ => raise(ExceptionKind::TypeError, vec![format!("abs() takes exactly one argument ({} given)", args.len())]),
+        },
+        "round" => match args {
+            [value] | [value, Value::None] => round(value, None),
+            [value, Value::Int(ndigits)] => round(value, Some(*ndigits)),
+            [_, other] => raise(ExceptionKind::TypeError, vec![format!("round() ndigits must be an int, not '{}'", other.type_name())]),
+            _ => raise(ExceptionKind::TypeError, vec![format!("round() takes 1 or 2 arguments ({} given)", args.len())]),
+        },
+        "enumerate" => match args {
+            [items] => enumerate(items, 0),
+            [items, Value::Int(start)] => enumerate(items, *start),
+            [_, other] => raise(ExceptionKind::TypeError, vec![format!("enumerate() start must be an int, not '{}'", other.type_name())]),
+            _ => raise(ExceptionKind::TypeError, vec![format!("enumerate() takes 1 or 2 arguments ({} given)", args.len())]),
+        },
+        _ => raise(ExceptionKind::NameError, vec![format!("name '{}' is not defined", name)]),
+    }
+}
+
+/// `enumerate(items, start)`: `(index, item)` pairs counting from `start` (0 by default).
+fn enumerate(items: &Value, start: i64) -> Result<Value, Exception> {
+    let pairs = containers::iter_values(items)?
+        .into_iter()
+        .zip(start..)
+        .map(|(item, i)| Value::Tuple(vec![Value::Int(i), item]))
+        .collect();
+    Ok(Value::List(pairs))
+}
+
+/// `len(x)`: characters of a str, bytes of a bytes-like value, items of a container.
+fn len(value: &Value) -> Result<Value, Exception> {
+    let n = match value {
+        Value::Str(s) => s.chars().count(),
+        Value::Bytes(b) | Value::ByteArray(b) | Value::MemoryView(b) => b.len(),
+        Value::List(items) | Value::Tuple(items) => items.len(),
+        Value::Set(items) | Value::FrozenSet(items) => items.len(),
+        Value::Dict(map) => map.len(),
+        Value::Range(_) => containers::iter_values(value)?.len(),
+        other => return raise(ExceptionKind::TypeError, vec![format!("object of type '{}' has no len()", other.type_name())]),
+    };
+    Ok(Value::Int(n as i64))
+}
+
+/// `min(iterable)`, `min(iterable, default)` or `min(a, b, ...)`, and the same
+/// for `max`. Without keyword arguments, a container followed by one more
+/// argument is read as the iterable and the default for when it is empty.
+fn extreme(name: &str, args: &[Value], wanted: Ordering) -> Result<Value, Exception> {
+    let (items, default) = match args {
+        [] => return raise(ExceptionKind::TypeError, vec![format!("{}() expected at least 1 argument, got 0", name)]),
+        [items] => (containers::iter_values(items)?, None),
+        [items, default] if is_iterable(items) => (containers::iter_values(items)?, Some(default)),
+        values => (values.to_vec(), None),
+    };
+    let mut items = items.into_iter();
+    let Some(mut best) = items.next() else {
+        return match default {
+            Some(default) => Ok(default.clone()),
+            None => raise(ExceptionKind::ValueError, vec![format!("{}() arg is an empty sequence", name)]),
+        };
+    };
+    for item in items {
+        if compare(&item, &best)? == wanted {
+            best = item;
+        }
+    }
+    Ok(best)
+}
+
+fn is_iterable(value: &Value) -> bool {
+    matches!(value, Value::List(_) | Value::Tuple(_) | Value::Set(_) | Value::FrozenSet(_) | Value::Dict(_) | Value::Range(_))
+}
+
+// Order two numbers or two strings, as `<` does. NaN compares equal to
+// everything, so it neither replaces nor is replaced by another candidate.
+fn compare(l: &Value, r: &Value) -> Result<Ordering, Exception> {
+    let ordering = match (l, r) {
+        (Value::Str(l), Value::Str(r)) => Some(l.cmp(r)),
+        (Value::Decimal(_), _) | (_, Value::Decimal(_)) => as_decimal(l).zip(as_decimal(r)).map(|(l, r)| l.cmp(&r)),
+        _ => as_float(l).zip(as_float(r)).map(|(l, r)| l.partial_cmp(&r).unwrap_or(Ordering::Equal)),
+    };
+    match ordering {
+        Some(ordering) => Ok(ordering),
+        None => raise(ExceptionKind::TypeError, vec![format!("'<' not supported between instances of '{}' and '{}'", l.type_name(), r.type_name())]),
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Bool(b) => Some(*b as i64 as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+fn as_decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Int(n) => Some(Decimal::from(*n)),
+        Value::Bool(b) => Some(Decimal::from(*b as i64)),
+        Value::Float(f) => Decimal::try_from(*f).ok(),
+        Value::Decimal(d) => Some(*d),
+        _ => None,
+    }
+}
+
+/// `sum(iterable, start)`: `start` (0 by default) plus every item, all numbers.
+fn sum(items: &Value, start: Value) -> Result<Value, Exception> {
+    if matches!(start, Value::Str(_)) {
+        return raise(ExceptionKind::TypeError, vec!["sum() can't sum strings (use ''.join(seq) instead)".to_string()]);
+    }
+    containers::iter_values(items)?.into_iter().try_fold(start, add)
+}
+
+fn add(l: Value, r: Value) -> Result<Value, Exception> {
+    match (&l, &r) {
+        (Value::Int(_) | Value::Bool(_), Value::Int(_) | Value::Bool(_)) => {
+            let (a, b) = (as_int(&l), as_int(&r));
+            match a.checked_add(b) {
+                Some(n) => Ok(Value::Int(n)),
+                None => raise(ExceptionKind::OverflowError, vec!["integer overflow in sum()".to_string()]),
+            }
+        }
+        (Value::Decimal(_), Value::Float(_)) | (Value::Float(_), Value::Decimal(_)) => unsupported(&l, &r),
+        (Value::Decimal(_), _) | (_, Value::Decimal(_)) => {
+            match as_decimal(&l).zip(as_decimal(&r)) {
+                Some((a, b)) => decimal::binary_op("+", a, b),
+                None => unsupported(&l, &r),
+            }
+        }
+        (Value::Complex(..), _) | (_, Value::Complex(..)) => match (as_complex(&l), as_complex(&r)) {
+            (Some((a, b)), Some((c, d))) => Ok(Value::Complex(a + c, b + d)),
+            _ => unsupported(&l, &r),
+        },
+        _ => match as_float(&l).zip(as_float(&r)) {
+            Some((a, b)) => Ok(Value::Float(a + b)),
+            None => unsupported(&l, &r),
+        },
+    }
+}
+
+fn as_int(value: &Value) -> i64 {
+    match value {
+        Value::Int(n) => *n,
+        Value::Bool(b) => *b as i64,
+        _ => 0,
+    }
+}
+
+fn as_complex(value: &Value) -> Option<(f64, f64)> {
+    match value {
+        Value::Complex(re, im) => Some((*re, *im)),
+        other => as_float(other).map(|re| (re, 0.0)),
+    }
+}
+
+fn unsupported(l: &Value, r: &Value) -> Result<Value, Exception> {
+    raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for +: '{}' and '{}'", l.type_name(), r.type_name())])
+}
+
+/// `abs(x)`: the magnitude of a number; for a complex number its modulus.
+fn abs(value: &Value) -> Result<Value, Exception> {
+    match value {
+        Value::Int(n) => match n.checked_abs() {
+            Some(n) => Ok(Value::Int(n)),
+            None => raise(ExceptionKind::OverflowError, vec!["integer overflow in abs()".to_string()]),
+        },
+        Value::Bool(b) => Ok(Value::Int(*b as i64)),
+        Value::Float(f) => Ok(Value::Float(f.abs())),
+        Value::Decimal(d) => Ok(Value::Decimal(d.abs())),
+        Value::Complex(re, im) => Ok(Value::Float(re.hypot(*im))),
+        other => raise(ExceptionKind::TypeError, vec![format!("bad operand type for abs(): '{}'", other.type_name())]),
+    }
+}
+
+/// `round(x)` rounds to the nearest int; `round(x, ndigits)` keeps the type of
+/// `x` and rounds to `ndigits` decimal places, or to tens, hundreds and so on
+/// when negative. Halves round to the even neighbour, as in Python.
+fn round(value: &Value, ndigits: Option<i64>) -> Result<Value, Exception> {
+    match (value, ndigits) {
+        (Value::Int(n), None) => Ok(Value::Int(*n)),
+        (Value::Bool(b), None) => Ok(Value::Int(*b as i64)),
+        (Value::Int(n), Some(digits)) => Ok(Value::Int(round_int(*n, digits))),
+        (Value::Bool(b), Some(digits)) => Ok(Value::Int(round_int(*b as i64, digits))),
+        (Value::Float(f), None) => {
+            let rounded = f.round_ties_even();
+            if !rounded.is_finite() {
+                let kind = if f.is_nan() { ExceptionKind::ValueError } else { ExceptionKind::OverflowError };
+                return raise(kind, vec![format!("cannot convert float {} to integer", f)]);
+            }
+            Ok(Value::Int(rounded as i64))
+        }
+        // Formatting rounds the exact binary value, so 2.675 (really 2.67499...) gives 2.67
+        (Value::Float(f), Some(digits)) if digits >= 0 => {
+            Ok(Value::Float(format!("{:.*}", digits.min(400) as usize, f).parse().unwrap_or(*f)))
+        }
+        (Value::Float(f), Some(digits)) => {
+            let scale = 10f64.powi(digits.clamp(-400, 400) as i32);
+            let rounded = (f * scale).round_ties_even() / scale;
+            Ok(Value::Float(if rounded.is_finite() { rounded } else { *f }))
+        }
+        (Value::Decimal(d), None) => match d.round_dp_with_strategy(0, RoundingStrategy::MidpointNearestEven).to_i64() {
+            Some(n) => Ok(Value::Int(n)),
+            None => raise(ExceptionKind::OverflowError, vec![format!("cannot convert Decimal {} to integer", d)]),
+        },
+        (Value::Decimal(d), Some(digits)) if digits >= 0 => {
+            Ok(Value::Decimal(d.round_dp_with_strategy(digits.min(28) as u32, RoundingStrategy::MidpointNearestEven)))
+        }
+        (Value::Decimal(d), Some(digits)) => {
+            let scale = Decimal::from(10i64.pow(digits.unsigned_abs().min(18) as u32));
+            Ok(Value::Decimal((d / scale).round_dp_with_strategy(0, RoundingStrategy::MidpointNearestEven) * scale))
+        }
+        (other, _) => raise(ExceptionKind::TypeError, vec![format!("type '{}' doesn't define __round__ method", other.type_name())]),
+    }
+}
+
+// Round an int to a multiple of 10^-digits; digits >= 0 leaves it unchanged
+fn round_int(n: i64, digits: i64) -> i64 {
+    if digits >= 0 {
+        return n;
+    }
+    let Some(scale) = 10i64.checked_pow(digits.unsigned_abs() as u32) else {
+        return 0;
+    };
+    let (quotient, remainder) = (n.div_euclid(scale), n.rem_euclid(scale));
+    let round_up = remainder * 2 > scale || (remainder * 2 == scale && quotient % 2 != 0);
+    (quotient + round_up as i64) * scale
+}
@@ -0,0 +1,80 @@
+// Memoization for functions decorated with @cache
+
+use super::interpreter::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The results cache of every `@cache` function, by function name. Shared with
+/// the sub-interpreters that run calls, so recursive calls hit the same cache.
+pub type Caches = Arc<Mutex<HashMap<String, FnCache>>>;
+
+pub(crate) fn lock(caches: &Caches) -> MutexGuard<'_, HashMap<String, FnCache>> {
+    caches.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// One function's results, keyed on its argument values. With a `maxsize`
+/// the least recently used result is evicted to make room for a new one.
+#[derive(Debug, Default)]
+#[allow(clippy::mutable_key_type)] // arguments are checked to be hashable first
+pub struct FnCache {
+    maxsize: Option<usize>,
+    entries: HashMap<Vec<Value>, (Value, u64)>,
+    // Keys by when they were last used, oldest first
+    recency: BTreeMap<u64, Vec<Value>>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl FnCache {
+    pub fn new(maxsize: Option<usize>) -> Self {
+        FnCache { maxsize, ..Self::default() }
+    }
+
+    /// The cached result for `args`, counting a hit or a miss.
+    pub fn get(&mut self, args: &[Value]) -> Option<Value> {
+        self.clock += 1;
+        let Some((result, used)) = self.entries.get_mut(args) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let key = self.recency.remove(used);
+        *used = self.clock;
+        self.recency.extend(key.map(|key| (self.clock, key)));
+        Some(result.clone())
+    }
+
+    pub fn insert(&mut self, args: Vec<Value>, result: Value) {
+        if self.maxsize == Some(0) {
+            return;
+        }
+        if self.maxsize.is_some_and(|maxsize| self.entries.len() >= maxsize) {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        if let Some((_, used)) = self.entries.insert(args.clone(), (result, self.clock)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(self.clock, args);
+    }
+
+    /// Forget every result and reset the statistics, as `fn.cache_clear()` does.
+    pub fn clear(&mut self) {
+        *self = FnCache::new(self.maxsize);
+    }
+
+    /// `fn.cache_info()`: hits, misses, maxsize (None when unbounded) and currsize.
+    pub fn info(&self) -> Value {
+        let maxsize = self.maxsize.map_or(Value::None, |maxsize| Value::Int(maxsize as i64));
+        let fields = [
+            ("hits", Value::Int(self.hits as i64)),
+            ("misses", Value::Int(self.misses as i64)),
+            ("maxsize", maxsize),
+            ("currsize", Value::Int(self.entries.len() as i64)),
+        ];
+        Value::Dict(fields.into_iter().map(|(key, value)| (Value::Str(key.to_string()), value)).collect())
+    }
+}
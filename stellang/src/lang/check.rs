@@ -0,0 +1,175 @@
+// Static checks behind `stellang check`: syntax errors, plus warnings about
+// code that parses but is probably a mistake
+
+use super::ast::{walk_expr, Expr, Visitor};
+use super::format;
+use super::exceptions::Exception;
+use super::lexer::{LexError, Lexer, Span, Token};
+use super::parser::Parser;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One problem found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    /// Where the problem is; None when the checker can't place it, as for
+    /// warnings about the syntax tree, which doesn't keep positions
+    pub range: Option<Span>,
+    pub severity: Severity,
+    /// The exception kind for errors (`SyntaxError`), the lint name for warnings
+    pub code: String,
+    pub message: String,
+    /// How the problem might be fixed, for some lexical errors
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    fn error(file: &str, range: Option<Span>, exc: Exception) -> Self {
+        Diagnostic { file: file.to_string(), range, severity: Severity::Error, code: format!("{:?}", exc.kind), message: exc.message(), hint: None }
+    }
+
+    fn lexical(file: &str, error: LexError) -> Self {
+        let hint = error.hint();
+        Diagnostic { hint, ..Diagnostic::error(file, Some(error.span), Exception::from(error)) }
+    }
+
+    /// The form `stellang check --output json` prints.
+    pub fn to_json(&self) -> serde_json::Value {
+        let position = |p: &super::lexer::Position| serde_json::json!({ "line": p.line, "column": p.column });
+        serde_json::json!({
+            "file": self.file,
+            "range": self.range.as_ref().map(|(start, end)| serde_json::json!({ "start": position(start), "end": position(end) })),
+            "severity": self.severity.name(),
+            "code": self.code,
+            "message": self.message,
+            "hint": self.hint,
+        })
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    /// `file:line:column: severity[code]: message`, like compilers print them.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some((start, _)) = &self.range {
+            write!(f, ":{}:{}", start.line, start.column)?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity.name(), self.code, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the source of `file`. Every lexical error is reported; otherwise a
+/// syntax error stops the check, so it is then the only diagnostic.
+pub fn check_source(file: &str, source: &str) -> Vec<Diagnostic> {
+    let (tokens, errors) = Lexer::new(source).tokenize();
+    if !errors.is_empty() {
+        return errors.into_iter().map(|error| Diagnostic::lexical(file, error)).collect();
+    }
+    let (tokens, spans): (Vec<Token>, Vec<Span>) = tokens.into_iter().unzip();
+    let mut parser = Parser::with_spans(tokens, spans);
+    let program = match parser.parse() {
+        Ok(Some(program)) => program,
+        Ok(None) => return Vec::new(),
+        Err(exc) => return vec![Diagnostic::error(file, parser.span(), exc)],
+    };
+    let mut linter = Linter { file, warnings: Vec::new() };
+    linter.visit_expr(&program);
+    linter.warnings
+}
+
+// Collects the warnings about every node of a program
+struct Linter<'a> {
+    file: &'a str,
+    warnings: Vec<Diagnostic>,
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        lint(expr, &mut |code, message| {
+            self.warnings.push(Diagnostic { file: self.file.to_string(), range: None, severity: Severity::Warning, code: code.to_string(), message, hint: None });
+        });
+        walk_expr(self, expr);
+    }
+}
+
+// Report suspicious code in `expr` itself
+fn lint(expr: &Expr, warn: &mut dyn FnMut(&str, String)) {
+    match expr {
+        Expr::Block(statements) => {
+            let exit = statements.iter().position(|statement| exit_keyword(statement).is_some());
+            if let Some(exit) = exit.filter(|&exit| exit + 1 < statements.len()) {
+                let keyword = exit_keyword(&statements[exit]).unwrap_or_default();
+                warn("unreachable-code", format!("code after '{}' is never run", keyword));
+            }
+            let mut defined = Vec::new();
+            for statement in statements.iter() {
+                if let Expr::FnDef { name, .. } = statement {
+                    if defined.contains(&name) {
+                        warn("redefined-function", format!("function '{}' is defined again in the same block", name));
+                    }
+                    defined.push(name);
+                }
+            }
+            // The last statement is the block's value, as in `fn double(x) { x * 2 }`
+            for statement in &statements[..statements.len().saturating_sub(1)] {
+                if !matches!(statement, Expr::String(_)) && has_no_effect(statement) {
+                    let hint = match statement {
+                        Expr::BinaryOp { op, .. } if op == "==" => " (did you mean '='?)",
+                        Expr::Ident(_) | Expr::GetAttr { .. } | Expr::FieldAccess { .. } => " (did you mean to call it?)",
+                        _ => "",
+                    };
+                    warn("unused-result", format!("'{}' has no effect{}", format::format_expr(statement), hint));
+                }
+            }
+        }
+        Expr::Assign { name, expr } if matches!(expr.as_ref(), Expr::Ident(value) if value == name) => {
+            warn("self-assignment", format!("'{}' is assigned to itself", name));
+        }
+        _ => {}
+    }
+}
+
+// Whether evaluating `expr` only computes a value: no call, assignment or
+// control flow anywhere in it. A string on its own is left alone, since those
+// are used as comments. Operators are assumed not to be overloaded
+fn has_no_effect(expr: &Expr) -> bool {
+    match expr {
+        Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null | Expr::Ident(_) => true,
+        Expr::BinaryOp { left, right, .. } => has_no_effect(left) && has_no_effect(right),
+        Expr::UnaryOp { expr, .. } => has_no_effect(expr),
+        Expr::GetAttr { object, .. } | Expr::FieldAccess { object, .. } => has_no_effect(object),
+        Expr::Index { collection, index } => has_no_effect(collection) && has_no_effect(index),
+        Expr::ArrayLiteral(items) | Expr::TupleLiteral(items) => items.iter().all(has_no_effect),
+        Expr::MapLiteral(pairs) => pairs.iter().all(|(key, value)| has_no_effect(key) && has_no_effect(value)),
+        _ => false,
+    }
+}
+
+// The keyword of a statement that always leaves its block
+fn exit_keyword(statement: &Expr) -> Option<&'static str> {
+    match statement {
+        Expr::Return(_) => Some("return"),
+        Expr::Break(_) => Some("break"),
+        Expr::Continue(_) => Some("continue"),
+        Expr::Throw { .. } => Some("throw"),
+        _ => None,
+    }
+}
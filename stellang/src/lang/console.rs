@@ -0,0 +1,160 @@
+// Reading the user's reply for input(): a line or a single key, optionally
+// without echo or within a time limit
+
+use super::exceptions::{Exception, ExceptionKind};
+use std::time::Duration;
+
+/// How `input()` reads, from its keyword arguments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputOptions {
+    /// Don't echo what is typed, for passwords (`hidden=true`)
+    pub hidden: bool,
+    /// Return after a single key instead of a full line (`key=true`)
+    pub key: bool,
+    /// Give up when nothing arrives in time (`timeout=seconds`)
+    pub timeout: Option<Duration>,
+}
+
+/// What reading stdin produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// A line without its line ending, or a single key
+    Text(String),
+    /// Stdin was closed before anything was read
+    Eof,
+    TimedOut,
+}
+
+/// Read a reply from the process's stdin. Bytes are read one at a time so
+/// nothing past the reply is buffered away from the next `input()`.
+#[cfg(unix)]
+pub fn read(options: &InputOptions) -> Result<Input, Exception> {
+    let _mode = TerminalMode::set(options)?;
+    let deadline = options.timeout.map(|timeout| std::time::Instant::now() + timeout);
+    let mut bytes = Vec::new();
+    loop {
+        if let Some(deadline) = deadline {
+            if !wait_readable(deadline.saturating_duration_since(std::time::Instant::now()))? {
+                return Ok(Input::TimedOut);
+            }
+        }
+        let Some(byte) = read_byte()? else {
+            return Ok(if bytes.is_empty() { Input::Eof } else { Input::Text(decode(bytes)) });
+        };
+        if !options.key && byte == b'\n' {
+            if bytes.last() == Some(&b'\r') {
+                bytes.pop();
+            }
+            return Ok(Input::Text(decode(bytes)));
+        }
+        bytes.push(byte);
+        if options.key && utf8_len(bytes[0]) <= bytes.len() {
+            return Ok(Input::Text(decode(bytes)));
+        }
+    }
+}
+
+/// Without termios only plain line input is available.
+#[cfg(not(unix))]
+pub fn read(options: &InputOptions) -> Result<Input, Exception> {
+    if options.hidden || options.key || options.timeout.is_some() {
+        return Err(Exception::new(ExceptionKind::NotImplementedError, vec!["input() hidden, key and timeout need a Unix terminal".to_string()]));
+    }
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) => Ok(Input::Eof),
+        Ok(_) => Ok(Input::Text(line.trim_end_matches(&['\r', '\n'][..]).to_string())),
+        Err(e) => Err(Exception::new(ExceptionKind::OSError, vec![e.to_string()])),
+    }
+}
+
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Bytes in the UTF-8 sequence starting with `lead`
+#[cfg(unix)]
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xF0.. => 4,
+        0xE0.. => 3,
+        0xC0.. => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(unix)]
+fn os_error(what: &str) -> Exception {
+    Exception::new(ExceptionKind::OSError, vec![format!("{}: {}", what, std::io::Error::last_os_error())])
+}
+
+#[cfg(unix)]
+fn read_byte() -> Result<Option<u8>, Exception> {
+    let mut byte = 0u8;
+    loop {
+        // Safety: reads at most one byte into a live local
+        let n = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+        match n {
+            1 => return Ok(Some(byte)),
+            0 => return Ok(None),
+            _ if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted => continue,
+            _ => return Err(os_error("cannot read stdin")),
+        }
+    }
+}
+
+// Whether stdin has something to read (or is closed) within `timeout`
+#[cfg(unix)]
+fn wait_readable(timeout: Duration) -> Result<bool, Exception> {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    // Safety: polls a single live pollfd
+    match unsafe { libc::poll(&mut fd, 1, millis) } {
+        n if n > 0 => Ok(true),
+        0 => Ok(false),
+        _ => Err(os_error("cannot wait for stdin")),
+    }
+}
+
+/// Terminal settings changed for hidden or single-key input, restored on drop.
+/// Nothing changes when stdin is not a terminal.
+#[cfg(unix)]
+struct TerminalMode(Option<libc::termios>);
+
+#[cfg(unix)]
+impl TerminalMode {
+    fn set(options: &InputOptions) -> Result<Self, Exception> {
+        use std::io::IsTerminal;
+        if !(options.hidden || options.key) || !std::io::stdin().is_terminal() {
+            return Ok(TerminalMode(None));
+        }
+        // Safety: termios is plain data, filled in by tcgetattr before use
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(os_error("cannot read terminal settings"));
+        }
+        let mut changed = original;
+        if options.hidden {
+            changed.c_lflag &= !libc::ECHO;
+        }
+        if options.key {
+            changed.c_lflag &= !libc::ICANON;
+            changed.c_cc[libc::VMIN] = 1;
+            changed.c_cc[libc::VTIME] = 0;
+        }
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &changed) } != 0 {
+            return Err(os_error("cannot change terminal settings"));
+        }
+        Ok(TerminalMode(Some(original)))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for TerminalMode {
+    fn drop(&mut self) {
+        if let Some(original) = &self.0 {
+            // Safety: restores settings read by tcgetattr
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original) };
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Operators between container values and conversions from one container type to
+// another, kept in one table so every unsupported combination fails the same way
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::hashing::{ValueMap, ValueSet};
+use super::interpreter::{raise, Value};
+
+/// Items a for loop visits, in order; also what the container constructors consume.
+pub fn iter_values(value: &Value) -> Result<Vec<Value>, Exception> {
+    match value {
+        Value::List(items) | Value::Tuple(items) => Ok(items.clone()),
+        Value::Str(s) => Ok(s.chars().map(|c| Value::Str(c.to_string())).collect()),
+        Value::Bytes(b) | Value::ByteArray(b) => Ok(b.iter().map(|&n| Value::Int(n as i64)).collect()),
+        Value::Range(r) if r.step > 0 => Ok((r.start..r.stop).step_by(r.step as usize).map(Value::Int).collect()),
+        Value::Range(r) if r.step < 0 => Ok((r.stop + 1..=r.start).rev().step_by(r.step.unsigned_abs() as usize).map(Value::Int).collect()),
+        Value::Dict(map) => Ok(map.keys().cloned().collect()),
+        Value::Set(items) | Value::FrozenSet(items) => Ok(items.iter().cloned().collect()),
+        other => raise(ExceptionKind::TypeError, vec![format!("'{}' object is not iterable", other.type_name())]),
+    }
+}
+
+/// The items of `value` for unpacking into `count` names, as in `for (k, v) in pairs`.
+pub fn unpack(value: &Value, count: usize) -> Result<Vec<Value>, Exception> {
+    let items = iter_values(value)
+        .map_err(|_| Exception::new(ExceptionKind::TypeError, vec![format!("cannot unpack non-iterable {} object", value.type_name())]))?;
+    match items.len() {
+        n if n < count => raise(ExceptionKind::ValueError, vec![format!("not enough values to unpack (expected {}, got {})", count, n)]),
+        n if n > count => raise(ExceptionKind::ValueError, vec![format!("too many values to unpack (expected {})", count)]),
+        _ => Ok(items),
+    }
+}
+
+/// `list(x)`, `tuple(x)`, `set(x)`, `frozenset(x)` and `dict(x)`: an empty
+/// container without an argument, otherwise one holding the items of `x`.
+/// `dict()` takes a dict or an iterable of key/value pairs.
+#[allow(clippy::mutable_key_type)] // keys and members are checked to be hashable
+pub fn construct(type_name: &str, args: &[Value]) -> Result<Value, Exception> {
+    let source = match args {
+        [] => None,
+        [source] => Some(source),
+        _ => return raise(ExceptionKind::TypeError, vec![format!("{} expected at most 1 argument, got {}", type_name, args.len())]),
+    };
+    let items = source.map(iter_values).transpose()?.unwrap_or_default();
+    match type_name {
+        "list" => Ok(Value::List(items)),
+        "tuple" => Ok(Value::Tuple(items)),
+        "set" | "frozenset" => {
+            items.iter().try_for_each(Value::check_hashable)?;
+            let members: ValueSet<Value> = items.into_iter().collect();
+            Ok(if type_name == "set" { Value::Set(members) } else { Value::FrozenSet(members) })
+        }
+        "dict" => {
+            if let Some(Value::Dict(map)) = source {
+                return Ok(Value::Dict(map.clone()));
+            }
+            let mut map = ValueMap::default();
+            for (i, item) in items.into_iter().enumerate() {
+                let (key, value) = match item {
+                    Value::List(pair) | Value::Tuple(pair) if pair.len() == 2 => {
+                        let mut pair = pair.into_iter();
+                        (pair.next().unwrap_or(Value::None), pair.next().unwrap_or(Value::None))
+                    }
+                    Value::List(pair) | Value::Tuple(pair) => {
+                        return raise(ExceptionKind::ValueError, vec![format!("dictionary update sequence element #{} has length {}; 2 is required", i, pair.len())]);
+                    }
+                    other => {
+                        return raise(ExceptionKind::TypeError, vec![format!("cannot convert dictionary update sequence element #{} ('{}') to a key/value pair", i, other.type_name())]);
+                    }
+                };
+                key.check_hashable()?;
+                map.insert(key, value);
+            }
+            Ok(Value::Dict(map))
+        }
+        _ => raise(ExceptionKind::NameError, vec![format!("name '{}' is not defined", type_name)]),
+    }
+}
+
+/// `l op r` where a container is involved: concatenation, repetition,
+/// equality and membership. Anything else is a TypeError naming both operand
+/// types, with the conversion to use when two container types were mixed.
+#[allow(clippy::mutable_key_type)]
+pub fn binary_op(op: &str, l: Value, r: Value) -> Result<Value, Exception> {
+    match (op, l, r) {
+        ("+", Value::List(mut l), Value::List(r)) => {
+            l.extend(r);
+            Ok(Value::List(l))
+        }
+        ("+", Value::Tuple(mut l), Value::Tuple(r)) => {
+            l.extend(r);
+            Ok(Value::Tuple(l))
+        }
+        ("*", Value::List(items), Value::Int(n)) | ("*", Value::Int(n), Value::List(items)) => Ok(Value::List(repeat(&items, n)?)),
+        ("*", Value::Tuple(items), Value::Int(n)) | ("*", Value::Int(n), Value::Tuple(items)) => Ok(Value::Tuple(repeat(&items, n)?)),
+        ("==", l, r) => Ok(Value::Bool(l == r)),
+        ("!=", l, r) => Ok(Value::Bool(l != r)),
+        ("in", item, container) => Ok(Value::Bool(contains(&container, &item)?)),
+        ("not in", item, container) => Ok(Value::Bool(!contains(&container, &item)?)),
+        (op, l, r) => {
+            let mut message = format!("unsupported operand type(s) for {}: '{}' and '{}'", op, l.type_name(), r.type_name());
+            if let Some(hint) = conversion_hint(&l, &r) {
+                message.push_str(&format!(" ({})", hint));
+            }
+            raise(ExceptionKind::TypeError, vec![message])
+        }
+    }
+}
+
+fn repeat(items: &[Value], count: i64) -> Result<Vec<Value>, Exception> {
+    if count < 0 {
+        return raise(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]);
+    }
+    Ok(std::iter::repeat_n(items, count as usize).flatten().cloned().collect())
+}
+
+// `item in container`
+fn contains(container: &Value, item: &Value) -> Result<bool, Exception> {
+    match container {
+        Value::List(items) | Value::Tuple(items) => Ok(items.contains(item)),
+        Value::Set(items) | Value::FrozenSet(items) => {
+            item.check_hashable()?;
+            Ok(items.contains(item))
+        }
+        Value::Dict(map) => {
+            item.check_hashable()?;
+            Ok(map.contains_key(item))
+        }
+        Value::Str(s) => match item {
+            Value::Str(sub) => Ok(s.contains(sub.as_str())),
+            other => raise(ExceptionKind::TypeError, vec![format!("'in <string>' requires string as left operand, not '{}'", other.type_name())]),
+        },
+        other => raise(ExceptionKind::TypeError, vec![format!("argument of type '{}' is not iterable", other.type_name())]),
+    }
+}
+
+// How to make two different container types work together: convert the right
+// operand to the left one's type
+fn conversion_hint(l: &Value, r: &Value) -> Option<String> {
+    let is_container = |value: &Value| matches!(value, Value::List(_) | Value::Tuple(_) | Value::Set(_) | Value::FrozenSet(_));
+    (is_container(l) && is_container(r) && l.type_name() != r.type_name())
+        .then(|| format!("convert the {} with {}() first", r.type_name(), l.type_name()))
+}
@@ -1,4 +1,7 @@
 // Python-style exception hierarchy for StelLang
+
+use std::sync::Arc;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum ExceptionKind {
     BaseException,
@@ -65,12 +68,57 @@ pub enum ExceptionKind {
     PermissionError,
     ProcessLookupError,
     TimeoutError,
-    Return,
-    Break,
-    Continue,
     // ...add more as needed
 }
 
+impl ExceptionKind {
+    /// Kind named `name` in a script, e.g. `ValueError`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    pub fn is_warning(&self) -> bool {
+        matches!(self,
+            ExceptionKind::Warning | ExceptionKind::UserWarning | ExceptionKind::DeprecationWarning |
+            ExceptionKind::PendingDeprecationWarning | ExceptionKind::SyntaxWarning | ExceptionKind::RuntimeWarning |
+            ExceptionKind::FutureWarning | ExceptionKind::ImportWarning | ExceptionKind::UnicodeWarning |
+            ExceptionKind::BytesWarning | ExceptionKind::ResourceWarning | ExceptionKind::EncodingWarning)
+    }
+}
+
+pub const CAUSE_SEPARATOR: &str = "The above exception was the direct cause of the following exception:";
+pub const CONTEXT_SEPARATOR: &str = "During handling of the above exception, another exception occurred:";
+
+/// A function an exception propagated through.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Frame {
+    pub function: String,
+    /// Line of the statement the exception left the function from, when the
+    /// function was parsed with lines
+    pub line: Option<u32>,
+    /// The file that line is in, when the parser knew it
+    #[serde(default)]
+    pub file: Option<Arc<str>>,
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in {}()", self.function)?;
+        match self.line {
+            Some(line) => write!(f, " at {}", location(self.file.as_deref(), line)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A line as tracebacks show it: `path:line` when its file is known.
+fn location(file: Option<&str>, line: u32) -> String {
+    match file {
+        Some(file) => format!("{}:{}", file, line),
+        None => format!("line {}", line),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Exception {
     pub kind: ExceptionKind,
@@ -79,6 +127,16 @@ pub struct Exception {
     pub cause: Option<Box<Exception>>,
     pub suppress_context: bool,
     pub notes: Vec<String>,
+    /// Functions the exception propagated through, innermost first
+    #[serde(default)]
+    pub traceback: Vec<Frame>,
+    /// Line of the statement the exception came from in the code it is still
+    /// in: the innermost function it hasn't left, or else the script
+    #[serde(default)]
+    pub line: Option<u32>,
+    /// The file `line` is in, when the parser knew it
+    #[serde(default)]
+    pub file: Option<Arc<str>>,
 }
 
 impl Exception {
@@ -90,6 +148,9 @@ impl Exception {
             cause: None,
             suppress_context: false,
             notes: vec![],
+            traceback: vec![],
+            line: None,
+            file: None,
         }
     }
     pub fn with_context(mut self, ctx: Exception) -> Self {
@@ -104,4 +165,93 @@ impl Exception {
     pub fn add_note(&mut self, note: String) {
         self.notes.push(note);
     }
+    /// Record that the exception left the function `frame`, from the line it
+    /// came from there.
+    pub fn add_frame(&mut self, frame: String) {
+        self.traceback.push(Frame { function: frame, line: self.line.take(), file: self.file.take() });
+    }
+
+    /// Where in the code it is still in the exception came from, as tracebacks
+    /// show it: `path:line`, or `line N` when the file is unknown.
+    pub fn location(&self) -> Option<String> {
+        self.line.map(|line| location(self.file.as_deref(), line))
+    }
+
+    /// Whether a report has a traceback to show: functions the exception
+    /// left, or the line of the script it came from.
+    pub fn has_traceback(&self) -> bool {
+        !self.traceback.is_empty() || self.line.is_some()
+    }
+
+    pub fn message(&self) -> String {
+        self.args.join(", ")
+    }
+
+    /// The exception printed above this one in a report: the explicit cause
+    /// (`throw X from Y`), or the exception being handled when this one was
+    /// raised unless that was suppressed with `from None`.
+    pub fn chained(&self) -> Option<(&Exception, &'static str)> {
+        if let Some(cause) = &self.cause {
+            Some((cause, CAUSE_SEPARATOR))
+        } else if self.suppress_context {
+            None
+        } else {
+            self.context.as_deref().map(|context| (context, CONTEXT_SEPARATOR))
+        }
+    }
+
+    /// Machine-readable form used by `--error-format=json`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kind": format!("{:?}", self.kind),
+            "severity": if self.kind.is_warning() { "warning" } else { "error" },
+            "message": self.message(),
+            "notes": self.notes,
+            "line": self.line,
+            "file": self.file,
+            "traceback": self.traceback.iter().rev().collect::<Vec<_>>(),
+            "cause": self.cause.as_ref().map(|cause| cause.to_json()),
+            "context": self.context.as_ref().filter(|_| !self.suppress_context).map(|context| context.to_json()),
+        })
+    }
+
+    /// Process exit status requested by `quit()`/`exit()`/`sys.exit()`, or None for any other exception.
+    /// Non-integer arguments exit with status 1, like Python.
+    pub fn exit_code(&self) -> Option<i32> {
+        if self.kind != ExceptionKind::SystemExit {
+            return None;
+        }
+        match self.args.first() {
+            Some(arg) => Some(arg.parse().unwrap_or(1)),
+            None => Some(0),
+        }
+    }
+}
+
+impl std::fmt::Display for Exception {
+    /// Python-style report: any chained exceptions first, then the traceback
+    /// (most recent call last), `Kind: message` and notes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((chained, separator)) = self.chained() {
+            write!(f, "{}\n\n{}\n\n", chained, separator)?;
+        }
+        if self.has_traceback() {
+            writeln!(f, "Traceback (most recent call last):")?;
+            if let Some(location) = self.location() {
+                writeln!(f, "  at {}", location)?;
+            }
+            for frame in self.traceback.iter().rev() {
+                writeln!(f, "  {}", frame)?;
+            }
+        }
+        if self.args.is_empty() {
+            write!(f, "{:?}", self.kind)?;
+        } else {
+            write!(f, "{:?}: {}", self.kind, self.message())?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note)?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,139 @@
+// Opt-in language features and deprecation warnings: new syntax lands behind a
+// feature name (`--enable-feature`) and old syntax warns before it goes away
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::lexer::Dialect;
+use super::suggest;
+use std::collections::BTreeSet;
+use std::sync::{Mutex, RwLock};
+
+/// A language feature that is still settling and has to be asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct Feature {
+    pub name: &'static str,
+    pub summary: &'static str,
+}
+
+/// Every feature `--enable-feature` accepts.
+pub const FEATURES: &[Feature] = &[Feature {
+    name: "type-annotations",
+    summary: "`let x: int = ...` and `const`, raising TypeError when the value has another type",
+}];
+
+/// Syntax or a builtin slated for change, with what to use instead and the
+/// dialect (`#lang` line) that no longer has it.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    pub name: &'static str,
+    pub syntax: &'static str,
+    pub replacement: &'static str,
+    pub removed_in: Dialect,
+}
+
+pub const DEPRECATIONS: &[Deprecation] = &[Deprecation {
+    name: "class-extends",
+    syntax: "'class Name extends Base'",
+    replacement: "write 'class Name(Base)', which also takes several bases",
+    removed_in: Dialect::Stel2,
+}];
+
+/// What using something deprecated does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeprecationPolicy {
+    /// Print a DeprecationWarning to stderr, once per deprecation per process
+    #[default]
+    Warn,
+    /// Raise the DeprecationWarning, for CI runs that must be clean
+    Error,
+    Ignore,
+}
+
+impl DeprecationPolicy {
+    /// Parse the value of `--deprecations`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "warn" => Some(DeprecationPolicy::Warn),
+            "error" => Some(DeprecationPolicy::Error),
+            "ignore" => Some(DeprecationPolicy::Ignore),
+            _ => None,
+        }
+    }
+}
+
+// Features turned on for every interpreter in the process
+static ENABLED: RwLock<BTreeSet<&'static str>> = RwLock::new(BTreeSet::new());
+
+static POLICY: RwLock<DeprecationPolicy> = RwLock::new(DeprecationPolicy::Warn);
+
+// Deprecations already warned about, so a loop doesn't repeat the warning
+static WARNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// Turn on the feature `name` for every interpreter in the process. An
+/// unknown name is an error listing the ones there are.
+pub fn enable(name: &str) -> Result<(), String> {
+    let Some(feature) = FEATURES.iter().find(|feature| feature.name == name) else {
+        let names = FEATURES.iter().map(|feature| feature.name);
+        let message = suggest::did_you_mean(format!("unknown feature '{}'", name), name, names.clone());
+        return Err(format!("{}; features are: {}", message, names.collect::<Vec<_>>().join(", ")));
+    };
+    ENABLED.write().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(feature.name);
+    Ok(())
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    ENABLED.read().unwrap_or_else(|poisoned| poisoned.into_inner()).contains(name)
+}
+
+/// Ok when the feature `name` is on, otherwise a SyntaxError saying that
+/// `what` needs it and how to turn it on.
+pub fn require(name: &str, what: &str) -> Result<(), Exception> {
+    if is_enabled(name) {
+        return Ok(());
+    }
+    let mut exc = Exception::new(ExceptionKind::SyntaxError, vec![format!("{} are an unstable feature", what)]);
+    exc.add_note(format!("enable them with --enable-feature {}", name));
+    Err(exc)
+}
+
+pub fn set_deprecation_policy(policy: DeprecationPolicy) {
+    *POLICY.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = policy;
+}
+
+pub fn deprecation_policy() -> DeprecationPolicy {
+    *POLICY.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Report a use of the deprecation `name` as the policy says: warn the first
+/// time, raise, or nothing at all.
+pub fn deprecated(name: &str) -> Result<(), Exception> {
+    let Some(deprecation) = DEPRECATIONS.iter().find(|deprecation| deprecation.name == name) else {
+        return Ok(());
+    };
+    let warning = || {
+        let mut exc = Exception::new(ExceptionKind::DeprecationWarning, vec![format!("{} is deprecated", deprecation.syntax)]);
+        exc.add_note(deprecation.replacement.to_string());
+        exc
+    };
+    match deprecation_policy() {
+        DeprecationPolicy::Ignore => Ok(()),
+        DeprecationPolicy::Error => Err(warning()),
+        DeprecationPolicy::Warn => {
+            if WARNED.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(deprecation.name) {
+                eprintln!("{}", warning());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The SyntaxError for using the deprecation `name` in a dialect without it.
+pub fn removed(name: &str) -> Exception {
+    let deprecation = DEPRECATIONS.iter().find(|deprecation| deprecation.name == name);
+    let Some(deprecation) = deprecation else {
+        return Exception::new(ExceptionKind::SyntaxError, vec![format!("'{}' is not supported", name)]);
+    };
+    let message = format!("{} is not part of #lang {}", deprecation.syntax, deprecation.removed_in.name());
+    let mut exc = Exception::new(ExceptionKind::SyntaxError, vec![message]);
+    exc.add_note(deprecation.replacement.to_string());
+    exc
+}
@@ -0,0 +1,143 @@
+// File I/O support for StelLang
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::output::Output;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// Shared handle to an open file. Clones of a file value refer to the same
+/// underlying handle, so closing one copy closes them all.
+#[derive(Debug, Clone, Default)]
+pub struct FileHandle(Arc<Mutex<Option<Stream>>>);
+
+/// What a file value reads from and writes to: a file on disk, or one of the
+/// standard streams exposed as `sys.stdin`, `sys.stdout` and `sys.stderr`.
+#[derive(Debug)]
+enum Stream {
+    File(File),
+    Stdin,
+    /// The interpreter's output sink, so `sys.stdout.write()` goes wherever `print()` does
+    Stdout(Output),
+    Stderr,
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::File(file) => file.read(buf),
+            Stream::Stdin => io::stdin().read(buf),
+            Stream::Stdout(_) | Stream::Stderr => Err(io::Error::new(io::ErrorKind::Unsupported, "not readable")),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::File(file) => file.write(buf),
+            Stream::Stdin => Err(io::Error::new(io::ErrorKind::Unsupported, "not writable")),
+            Stream::Stdout(output) => output.write(buf),
+            Stream::Stderr => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::File(file) => file.flush(),
+            Stream::Stdin => Ok(()),
+            Stream::Stdout(output) => output.flush(),
+            Stream::Stderr => io::stderr().flush(),
+        }
+    }
+}
+
+impl FileHandle {
+    fn new(stream: Stream) -> Self {
+        FileHandle(Arc::new(Mutex::new(Some(stream))))
+    }
+
+    /// A handle reading the process's stdin.
+    pub fn stdin() -> Self {
+        Self::new(Stream::Stdin)
+    }
+
+    /// A handle writing to `output`, an interpreter's stdout.
+    pub fn stdout(output: Output) -> Self {
+        Self::new(Stream::Stdout(output))
+    }
+
+    /// A handle writing to the process's stderr.
+    pub fn stderr() -> Self {
+        Self::new(Stream::Stderr)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.lock().map(|f| f.is_none()).unwrap_or(true)
+    }
+
+    pub fn close(&self) {
+        if let Ok(mut f) = self.0.lock() {
+            f.take();
+        }
+    }
+
+    pub fn same_handle(&self, other: &FileHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    fn with_stream<T>(&self, op: impl FnOnce(&mut Stream) -> std::io::Result<T>) -> Result<T, Exception> {
+        let mut guard = self.0.lock().map_err(|_| Exception::new(ExceptionKind::OSError, vec!["file handle poisoned".to_string()]))?;
+        match guard.as_mut() {
+            Some(stream) => op(stream).map_err(io_error),
+            None => Err(Exception::new(ExceptionKind::ValueError, vec!["I/O operation on closed file".to_string()])),
+        }
+    }
+}
+
+/// Open `path` with a Python-style mode string ("r", "w", "a", optionally with "+").
+pub fn open(path: &str, mode: &str) -> Result<FileHandle, Exception> {
+    let mut options = OpenOptions::new();
+    match mode.trim_end_matches('b') {
+        "r" => options.read(true),
+        "r+" => options.read(true).write(true),
+        "w" => options.write(true).create(true).truncate(true),
+        "w+" => options.read(true).write(true).create(true).truncate(true),
+        "a" => options.append(true).create(true),
+        "a+" => options.read(true).append(true).create(true),
+        _ => return Err(Exception::new(ExceptionKind::ValueError, vec![format!("invalid mode: '{}'", mode)])),
+    };
+    let file = options.open(path).map_err(io_error)?;
+    Ok(FileHandle::new(Stream::File(file)))
+}
+
+pub fn read(handle: &FileHandle) -> Result<String, Exception> {
+    handle.with_stream(|f| {
+        let mut s = String::new();
+        f.read_to_string(&mut s)?;
+        Ok(s)
+    })
+}
+
+pub fn write(handle: &FileHandle, data: &str) -> Result<usize, Exception> {
+    handle.with_stream(|f| {
+        f.write_all(data.as_bytes())?;
+        Ok(data.len())
+    })
+}
+
+pub fn flush(handle: &FileHandle) -> Result<(), Exception> {
+    handle.with_stream(|f| f.flush())
+}
+
+pub fn io_error(e: std::io::Error) -> Exception {
+    let kind = match e.kind() {
+        std::io::ErrorKind::NotFound => ExceptionKind::FileNotFoundError,
+        std::io::ErrorKind::PermissionDenied => ExceptionKind::PermissionError,
+        std::io::ErrorKind::AlreadyExists => ExceptionKind::FileExistsError,
+        std::io::ErrorKind::Interrupted => ExceptionKind::InterruptedError,
+        std::io::ErrorKind::TimedOut => ExceptionKind::TimeoutError,
+        _ => ExceptionKind::OSError,
+    };
+    Exception::new(kind, vec![e.to_string()])
+}
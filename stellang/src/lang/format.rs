@@ -0,0 +1,552 @@
+// Formatter for StelLang: prints an AST back out as source code
+
+use super::ast::Expr;
+use super::lexer::{Lexer, Token};
+use super::symbol::Symbol;
+
+const INDENT: &str = "    ";
+
+/// How tightly a unary operator binds: above every binary operator.
+const UNARY: u8 = 12;
+/// How tightly calls, indexing and attribute access bind.
+const POSTFIX: u8 = 13;
+/// Literals and names, which never need parentheses.
+const ATOM: u8 = 14;
+/// Any expression the parser reads without a statement keyword in front.
+const OPERAND: u8 = 1;
+
+/// Format a whole program, one statement per line, the way `Parser::parse`
+/// returns it: a top-level `Block` holds the program's statements.
+///
+/// The output parses back to the same AST. Comments and the original line
+/// breaks are not in the AST, so they are not preserved.
+pub fn format_program(program: &Expr) -> String {
+    let mut formatter = Formatter::default();
+    match program {
+        Expr::Block(stmts) => formatter.statements(stmts),
+        stmt => formatter.statements(std::slice::from_ref(stmt)),
+    }
+    formatter.out
+}
+
+/// Format a single expression or statement without a trailing newline.
+pub fn format_expr(expr: &Expr) -> String {
+    let mut formatter = Formatter::default();
+    formatter.statement(expr);
+    formatter.out
+}
+
+/// Binding power of a binary operator, matching the parser's precedence
+/// levels from `or` (loosest) to `**`. All of them associate to the left.
+fn binary_precedence(op: &str) -> u8 {
+    match op {
+        "or" => 1,
+        "and" => 2,
+        "==" | "!=" => 3,
+        "<" | ">" | "<=" | ">=" | "is" | "is not" | "in" | "not in" => 4,
+        "|" => 5,
+        "^" => 6,
+        "&" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" | "//" => 10,
+        "**" => 11,
+        _ => OPERAND,
+    }
+}
+
+/// Binding power of an expression; 0 for statements, which the parser only
+/// reads where a whole statement may appear or inside parentheses.
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::BinaryOp { op, .. } => binary_precedence(op),
+        Expr::UnaryOp { .. } => UNARY,
+        Expr::FnCall { .. } | Expr::Index { .. } | Expr::GetAttr { .. } | Expr::MethodCall { .. } | Expr::FieldAccess { .. } => POSTFIX,
+        Expr::Integer(_) | Expr::Float(_) | Expr::Ident(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null
+        | Expr::ArrayLiteral(_) | Expr::MapLiteral(_) | Expr::TupleLiteral(_)
+        | Expr::StructInit { .. } | Expr::EnumInit { .. } | Expr::ClassInit { .. } => ATOM,
+        _ => 0,
+    }
+}
+
+/// Whether `expr` is formatted with a leading `not`, which after `is` would
+/// read as the `is not` operator.
+fn starts_with_not(expr: &Expr) -> bool {
+    match expr {
+        Expr::UnaryOp { op, .. } => op == "not",
+        Expr::BinaryOp { left, op, .. } => precedence(left) >= binary_precedence(op) && starts_with_not(left),
+        _ => false,
+    }
+}
+
+/// `s` as a string literal, escaped so the lexer reads it back unchanged.
+fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Whether `name` lexes as a single identifier rather than a keyword or several tokens.
+fn is_ident(name: &str) -> bool {
+    let mut lexer = Lexer::new(name);
+    matches!(lexer.next_token(), Ok(Token::Ident(ident)) if ident == name) && lexer.next_token() == Ok(Token::EOF)
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    depth: usize,
+}
+
+impl Formatter {
+    fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn statements(&mut self, stmts: &[Expr]) {
+        for (i, stmt) in stmts.iter().enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            self.statement(stmt);
+        }
+    }
+
+    /// A statement on its own line, where nothing follows it but a line break.
+    fn statement(&mut self, stmt: &Expr) {
+        let stmt = stmt.unlocated();
+        match stmt {
+            Expr::Return(value) if **value == Expr::Null => self.push("return"),
+            Expr::Block(stmts) => self.braced(stmts),
+            _ => self.expr(stmt, 0),
+        }
+    }
+
+    /// `{`, the statements indented one level, then `}`.
+    fn braced(&mut self, stmts: &[Expr]) {
+        if stmts.is_empty() {
+            self.push("{}");
+            return;
+        }
+        self.push("{");
+        self.depth += 1;
+        self.newline();
+        self.statements(stmts);
+        self.depth -= 1;
+        self.newline();
+        self.push("}");
+    }
+
+    /// The body of an `if`, loop, function or `try`, which the parser always reads as a block.
+    fn body(&mut self, body: &Expr) {
+        match body {
+            Expr::Block(stmts) => self.braced(stmts),
+            stmt => self.braced(std::slice::from_ref(stmt)),
+        }
+    }
+
+    /// Comma-separated items of a call, list or dictionary. An assignment is
+    /// parenthesized, since `a, b = ...` after an earlier item reads as destructuring.
+    fn item(&mut self, item: &Expr) {
+        match item {
+            Expr::Assign { .. } | Expr::Destructure { .. } => self.expr(item, OPERAND),
+            _ => self.expr(item, 0),
+        }
+    }
+
+    fn items(&mut self, items: &[Expr]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.push(", ");
+            }
+            self.item(item);
+        }
+    }
+
+    /// The fields of a struct or the variants of an enum.
+    fn names(&mut self, names: &[Symbol]) {
+        if names.is_empty() {
+            self.push("{}");
+        } else {
+            self.push(&format!("{{ {} }}", names.join(", ")));
+        }
+    }
+
+    fn label(&mut self, label: &Option<Symbol>) {
+        if let Some(name) = label {
+            self.push(&format!("'{}: ", name));
+        }
+    }
+
+    /// The object before `.name`; a number there would swallow the dot as a decimal point.
+    fn receiver(&mut self, object: &Expr) {
+        match object {
+            Expr::Integer(_) | Expr::Float(_) => self.parenthesized(object),
+            _ => self.expr(object, POSTFIX),
+        }
+    }
+
+    fn parenthesized(&mut self, expr: &Expr) {
+        self.push("(");
+        self.expr(expr, 0);
+        self.push(")");
+    }
+
+    /// Format `expr` where the parser expects something binding at least as
+    /// tightly as `min`, adding parentheses when it does not.
+    fn expr(&mut self, expr: &Expr, min: u8) {
+        if precedence(expr) < min {
+            self.parenthesized(expr);
+            return;
+        }
+        match expr {
+            Expr::Integer(n) => self.push(&n.to_string()),
+            Expr::Float(f) => self.push(&format!("{:?}", f)),
+            Expr::Ident(name) => self.push(name),
+            Expr::String(s) => self.push(&quote(s)),
+            Expr::Bool(b) => self.push(if *b { "true" } else { "false" }),
+            Expr::Null => self.push("null"),
+            Expr::BinaryOp { left, op, right } => {
+                let prec = binary_precedence(op);
+                self.expr(left, prec);
+                self.push(&format!(" {} ", op));
+                if op == "is" && starts_with_not(right) {
+                    self.parenthesized(right);
+                } else {
+                    self.expr(right, prec + 1);
+                }
+            }
+            Expr::UnaryOp { op, expr } => {
+                self.push(op);
+                if op == "not" {
+                    self.push(" ");
+                }
+                self.expr(expr, UNARY);
+            }
+            Expr::FnCall { callable, args } => {
+                match &**callable {
+                    // `a.b(...)` would read as a method call
+                    Expr::GetAttr { .. } => self.parenthesized(callable),
+                    _ => self.expr(callable, POSTFIX),
+                }
+                self.push("(");
+                self.items(args);
+                self.push(")");
+            }
+            Expr::KeywordArg { name, value } => {
+                self.push(&format!("{}=", name));
+                self.item(value);
+            }
+            Expr::Index { collection, index } => {
+                self.expr(collection, POSTFIX);
+                self.push("[");
+                self.expr(index, 0);
+                self.push("]");
+            }
+            Expr::GetAttr { object, name } => {
+                self.receiver(object);
+                self.push(&format!(".{}", name));
+            }
+            Expr::FieldAccess { object, field } => {
+                self.receiver(object);
+                self.push(&format!(".{}", field));
+            }
+            Expr::MethodCall { object, method, args } => {
+                self.receiver(object);
+                self.push(&format!(".{}(", method));
+                self.items(args);
+                self.push(")");
+            }
+            Expr::ArrayLiteral(items) => {
+                self.push("[");
+                self.items(items);
+                self.push("]");
+            }
+            Expr::TupleLiteral(items) => {
+                self.push("(");
+                self.items(items);
+                self.push(if items.len() == 1 { ",)" } else { ")" });
+            }
+            Expr::MapLiteral(pairs) => {
+                self.push("{");
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    match key {
+                        // A bare name would be read back as a string key
+                        Expr::Ident(_) => self.parenthesized(key),
+                        _ => self.item(key),
+                    }
+                    self.push(": ");
+                    self.item(value);
+                }
+                self.push("}");
+            }
+            Expr::StructInit { name, fields } => {
+                self.push(&format!("{} {{", name));
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    self.push(if i > 0 { ", " } else { " " });
+                    self.push(&format!("{}: ", field));
+                    self.item(value);
+                }
+                self.push(if fields.is_empty() { "}" } else { " }" });
+            }
+            Expr::EnumInit { name, variant, value } => {
+                self.push(&format!("{}::{}", name, variant));
+                if let Some(value) = value {
+                    self.push("(");
+                    self.expr(value, 0);
+                    self.push(")");
+                }
+            }
+            Expr::ClassInit { class_name, args } => {
+                self.push(&format!("{}(", class_name));
+                self.items(args);
+                self.push(")");
+            }
+            Expr::Assign { name, expr } => {
+                self.push(&format!("{} = ", name));
+                match &**expr {
+                    Expr::Assign { .. } => self.expr(expr, 0),
+                    _ => self.expr(expr, OPERAND),
+                }
+            }
+            Expr::AssignIndex { collection, index, expr } => {
+                self.expr(collection, POSTFIX);
+                self.push("[");
+                self.expr(index, 0);
+                self.push("] = ");
+                self.expr(expr, 0);
+            }
+            Expr::Destructure { names, expr } => {
+                self.push(&format!("{} = ", names.join(", ")));
+                self.expr(expr, 0);
+            }
+            Expr::Let { name, expr } => {
+                self.push(&format!("let {} = ", name));
+                self.expr(expr, 0);
+            }
+            Expr::Const { name, expr } => {
+                self.push(&format!("const {} = ", name));
+                self.expr(expr, 0);
+            }
+            Expr::LetTyped { name, ty, expr } => {
+                self.push(&format!("let {}: {} = ", name, ty));
+                self.expr(expr, 0);
+            }
+            Expr::ConstTyped { name, ty, expr } => {
+                self.push(&format!("const {}: {} = ", name, ty));
+                self.expr(expr, 0);
+            }
+            Expr::Global { name, expr } => {
+                self.push(&format!("global {} = ", name));
+                self.expr(expr, 0);
+            }
+            Expr::Static { name, expr } => {
+                self.push(&format!("static {} = ", name));
+                self.expr(expr, 0);
+            }
+            Expr::Block(stmts) => self.braced(stmts),
+            Expr::If { cond, then_branch, else_branch } => {
+                self.push("if ");
+                self.expr(cond, OPERAND);
+                self.push(" ");
+                self.body(then_branch);
+                match else_branch.as_deref() {
+                    Some(elif @ Expr::If { .. }) => {
+                        self.push(" else ");
+                        self.expr(elif, 0);
+                    }
+                    Some(else_branch) => {
+                        self.push(" else ");
+                        self.body(else_branch);
+                    }
+                    None => {}
+                }
+            }
+            Expr::While { cond, body, label } => {
+                self.label(label);
+                self.push("while ");
+                self.expr(cond, OPERAND);
+                self.push(" ");
+                self.body(body);
+            }
+            Expr::Loop { body, label } => {
+                self.label(label);
+                self.push("loop ");
+                self.body(body);
+            }
+            Expr::DoWhile { body, cond, label } => {
+                self.label(label);
+                self.push("do ");
+                self.body(body);
+                self.push(" while ");
+                self.expr(cond, 0);
+            }
+            Expr::For { vars, iter, body, label } => {
+                self.label(label);
+                match vars.as_slice() {
+                    [var] => self.push(&format!("for {} in ", var)),
+                    vars => self.push(&format!("for ({}) in ", vars.join(", "))),
+                }
+                self.expr(iter, OPERAND);
+                self.push(" ");
+                self.body(body);
+            }
+            Expr::FnDef { name, params, body } => {
+                self.push(&format!("fn {}({}) ", name, params.join(", ")));
+                self.body(body);
+            }
+            Expr::AbstractFn { name, params } => self.push(&format!("abstract fn {}({})", name, params.join(", "))),
+            Expr::Decorated { decorators, def } => {
+                for decorator in decorators {
+                    self.push("@");
+                    self.expr(decorator, POSTFIX);
+                    self.newline();
+                }
+                self.statement(def);
+            }
+            Expr::Return(value) => {
+                self.push("return ");
+                self.expr(value, 0);
+            }
+            Expr::Break(label) | Expr::Continue(label) => {
+                self.push(if matches!(expr, Expr::Break(_)) { "break" } else { "continue" });
+                if let Some(name) = label {
+                    self.push(&format!(" '{}", name));
+                }
+            }
+            Expr::Match { expr, arms } => {
+                self.push("match ");
+                self.expr(expr, OPERAND);
+                self.push(" {");
+                self.depth += 1;
+                for (pattern, result) in arms {
+                    self.newline();
+                    self.expr(pattern, 0);
+                    self.push(" => ");
+                    self.expr(result, 0);
+                    self.push(",");
+                }
+                self.depth -= 1;
+                self.newline();
+                self.push("}");
+            }
+            Expr::Switch { expr, cases, default } => {
+                self.push("switch ");
+                self.expr(expr, OPERAND);
+                self.push(" {");
+                self.depth += 1;
+                for (case, body) in cases {
+                    self.newline();
+                    self.push("case ");
+                    self.expr(case, 0);
+                    self.push(" => ");
+                    self.body(body);
+                }
+                if let Some(default) = default {
+                    self.newline();
+                    self.push("default => ");
+                    self.body(default);
+                }
+                self.depth -= 1;
+                self.newline();
+                self.push("}");
+            }
+            Expr::StructDef { name, fields } => {
+                self.push(&format!("struct {} ", name));
+                self.names(fields);
+            }
+            Expr::EnumDef { name, variants } => {
+                self.push(&format!("enum {} ", name));
+                self.names(variants);
+            }
+            Expr::ClassDef { name, bases, body } => {
+                self.push(&format!("class {}", name));
+                if !bases.is_empty() {
+                    self.push("(");
+                    self.items(bases);
+                    self.push(")");
+                }
+                self.push(" ");
+                self.braced(body);
+            }
+            Expr::TryCatch { try_block, catch_var, catch_block, finally_block } => {
+                self.push("try ");
+                self.body(try_block);
+                if let Some(catch_block) = catch_block {
+                    self.push(" catch ");
+                    if let Some(var) = catch_var {
+                        self.push(&format!("{} ", var));
+                    }
+                    self.body(catch_block);
+                }
+                if let Some(finally_block) = finally_block {
+                    self.push(" finally ");
+                    self.body(finally_block);
+                }
+            }
+            Expr::Throw { value, cause } => {
+                self.push("throw ");
+                match cause {
+                    Some(cause) => {
+                        // A statement there would take the `from` for itself
+                        self.expr(value, OPERAND);
+                        self.push(" from ");
+                        self.expr(cause, 0);
+                    }
+                    None => self.expr(value, 0),
+                }
+            }
+            Expr::Import(module) => {
+                if is_ident(module) {
+                    self.push(&format!("import {}", module));
+                } else {
+                    self.push(&format!("import {}", quote(module)));
+                }
+            }
+            Expr::ImportNative(path) => self.push(&format!("import native {}", quote(path))),
+            Expr::With { resource, alias, body } => {
+                self.push("with ");
+                self.expr(resource, OPERAND);
+                if let Some(alias) = alias {
+                    self.push(&format!(" as {}", alias));
+                }
+                self.push(" ");
+                self.body(body);
+            }
+            Expr::Defer(expr) => {
+                self.push("defer ");
+                self.expr(expr, 0);
+            }
+            Expr::Test { name, tags, body } => {
+                self.push(&format!("test {} ", quote(name)));
+                if !tags.is_empty() {
+                    let tags: Vec<String> = tags.iter().map(|tag| quote(tag)).collect();
+                    self.push(&format!("tags=[{}] ", tags.join(", ")));
+                }
+                self.body(body);
+            }
+            Expr::Located { expr, .. } => self.expr(expr, min),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// How dicts and sets hash their keys: from a seed chosen at random once per
+// process, or a fixed one under `--deterministic`, so that iteration order (and
+// with it printed dicts and sets) is the same on every run and every machine.
+// The hash is SipHash-1-3 from the siphasher crate rather than std's
+// DefaultHasher, whose algorithm may change between Rust releases.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+
+use siphasher::sip::SipHasher13;
+
+/// The seed `--deterministic` uses.
+pub const DETERMINISTIC_SEED: u64 = 0;
+
+/// The second SipHash key, beside the seed.
+const SECOND_KEY: u64 = 0x5374_656c_4c61_6e67;
+
+static SEED: OnceLock<u64> = OnceLock::new();
+
+/// The entries of a `Value::Dict`.
+pub type ValueMap<K, V> = HashMap<K, V, SeededState>;
+
+/// The members of a `Value::Set` or `Value::FrozenSet`.
+pub type ValueSet<T> = HashSet<T, SeededState>;
+
+/// Hash every dict and set in the process with `seed`. Only takes effect
+/// before the first one is made; returns false when it is too late.
+pub fn set_seed(seed: u64) -> bool {
+    SEED.set(seed).is_ok() || SEED.get() == Some(&seed)
+}
+
+/// The process's seed, picking a random one if none was set.
+pub fn seed() -> u64 {
+    *SEED.get_or_init(|| RandomState::new().build_hasher().finish())
+}
+
+/// Builds hashers keyed by the process's seed. Each map remembers the seed it
+/// was made with, so maps made before and after `set_seed` still work.
+#[derive(Debug, Clone)]
+pub struct SeededState {
+    seed: u64,
+}
+
+impl Default for SeededState {
+    fn default() -> Self {
+        SeededState { seed: seed() }
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.seed, SECOND_KEY)
+    }
+}
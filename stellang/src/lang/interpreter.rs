@@ -1,7 +1,32 @@
 use super::ast::Expr;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use crate::lang::exceptions::{Exception, ExceptionKind};
-use std::time::{Instant, Duration};
+use crate::lang::fileio::{self, FileHandle};
+use crate::lang::process::ProcessHandle;
+use crate::lang::modules;
+use crate::lang::threads::{self, ChannelHandle, ThreadHandle};
+use crate::lang::sandbox::Capabilities;
+use crate::lang::native::{self, NativeHandle, NativeModule};
+use crate::lang::methods;
+use crate::lang::builtins;
+use crate::lang::console;
+use crate::lang::containers;
+use crate::lang::output::{Capture, Output};
+use crate::lang::testing;
+use crate::lang::interrupt;
+use crate::lang::stats::{self, Stats, StatsHandle};
+use crate::lang::cache::{self, Caches, FnCache};
+use crate::lang::stdlib::{self, debug, decimal, http};
+use crate::lang::stdlib::sys::RecursionLimit;
+use crate::lang::stdlib::config::{self, EnvVars};
+use crate::lang::format;
+use crate::lang::parser::Parser;
+use crate::lang::lexer::KEYWORDS;
+use crate::lang::symbol::Symbol;
+use crate::lang::suggest;
+use crate::lang::hashing::{ValueMap, ValueSet};
+use std::time::Instant;
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -9,6 +34,7 @@ pub enum Value {
     Int(i64),
     Float(f64),
     Complex(f64, f64),
+    Decimal(rust_decimal::Decimal),
     Bool(bool),
     Str(String),
     Bytes(Vec<u8>),
@@ -17,9 +43,9 @@ pub enum Value {
     List(Vec<Value>),
     Tuple(Vec<Value>),
     Range(RangeData),
-    Set(std::collections::HashSet<Value>),
-    FrozenSet(std::collections::HashSet<Value>),
-    Dict(std::collections::HashMap<Value, Value>),
+    Set(ValueSet<Value>),
+    FrozenSet(ValueSet<Value>),
+    Dict(ValueMap<Value, Value>),
     // Iterator(Box<dyn std::any::Any>), // Removed due to Clone trait issue
     // Generator(Box<dyn std::any::Any>), // Removed due to Clone trait issue
     None,
@@ -32,29 +58,282 @@ pub enum Value {
     },
     Class {
         name: String,
-        methods: HashMap<String, (Vec<String>, Expr)>,
+        methods: HashMap<String, Function>,
         fields: HashMap<String, Value>,
         base: Option<String>,
     },
     Instance {
         class_name: String,
         fields: HashMap<String, Value>,
+        /// Numbers instances in the order they were created, for display
+        id: u64,
+    },
+    File {
+        path: String,
+        mode: String,
+        #[serde(skip)]
+        handle: FileHandle,
+    },
+    Channel {
+        #[serde(skip)]
+        handle: ChannelHandle,
+    },
+    Thread {
+        #[serde(skip)]
+        handle: ThreadHandle,
+    },
+    Process {
+        #[serde(skip)]
+        handle: ProcessHandle,
+    },
+    /// A StelLang source file loaded by `import`: its global variables and functions
+    Module {
+        name: String,
+        path: String,
+        globals: HashMap<String, Value>,
+        functions: HashMap<String, Function>,
+    },
+    NativeFunction {
+        name: String,
+        #[serde(skip)]
+        handle: NativeHandle,
     },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct RangeData {
-    start: i64,
-    stop: i64,
-    step: i64,
+    pub(crate) start: i64,
+    pub(crate) stop: i64,
+    pub(crate) step: i64,
 }
 
+/// How evaluation of an expression ended when it did not produce a value:
+/// `return`, `break` and `continue` unwind to the enclosing function or loop
+/// (or the loop with the given label), errors unwind to the nearest `catch`.
+#[derive(Debug, Clone)]
+pub(crate) enum ControlFlow {
+    // Boxed so the signal stays no larger than an exception
+    Return(Box<Value>),
+    Break(Option<Symbol>),
+    Continue(Option<Symbol>),
+    Exception(Exception),
+}
+
+impl From<Exception> for ControlFlow {
+    fn from(exc: Exception) -> Self {
+        ControlFlow::Exception(exc)
+    }
+}
+
+impl ControlFlow {
+    /// Resolve a signal that reached the top of a function body or script:
+    /// `return` yields its value, a stray `break`/`continue` is an error.
+    fn finish(self) -> Result<Value, Exception> {
+        match self {
+            ControlFlow::Return(value) => Ok(*value),
+            ControlFlow::Break(None) => Err(Exception::new(ExceptionKind::SyntaxError, vec!["'break' outside loop".to_string()])),
+            ControlFlow::Continue(None) => Err(Exception::new(ExceptionKind::SyntaxError, vec!["'continue' not properly in loop".to_string()])),
+            ControlFlow::Break(Some(label)) | ControlFlow::Continue(Some(label)) => {
+                Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("no enclosing loop labeled '{}", label)]))
+            }
+            ControlFlow::Exception(exc) => Err(exc),
+        }
+    }
+
+    /// Note that an exception came from the statement on `line` (of `file`),
+    /// unless a statement inside that one already has.
+    fn at_line(self, line: usize, file: &Option<Arc<str>>) -> Self {
+        match self {
+            ControlFlow::Exception(mut exc) => {
+                // Kept as a u32 so `Exception` stays small enough to return by value
+                if let (None, Ok(line)) = (exc.line, u32::try_from(line)) {
+                    exc.line = Some(line);
+                    exc.file = file.clone();
+                }
+                ControlFlow::Exception(exc)
+            }
+            flow => flow,
+        }
+    }
+}
+
+/// The AST node name used as the key in stats.
+fn node_name(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Integer(_) => "Integer",
+        Expr::Float(_) => "Float",
+        Expr::String(_) => "String",
+        Expr::Ident(_) => "Ident",
+        Expr::ArrayLiteral(_) => "ArrayLiteral",
+        Expr::MapLiteral(_) => "MapLiteral",
+        Expr::Index { .. } => "Index",
+        Expr::AssignIndex { .. } => "AssignIndex",
+        Expr::BinaryOp { .. } => "BinaryOp",
+        Expr::UnaryOp { .. } => "UnaryOp",
+        Expr::Assign { .. } => "Assign",
+        Expr::Let { .. } => "Let",
+        Expr::Const { .. } => "Const",
+        Expr::Bool(_) => "Bool",
+        Expr::Null => "Null",
+        Expr::Block(_) => "Block",
+        Expr::If { .. } => "If",
+        Expr::While { .. } => "While",
+        Expr::Loop { .. } => "Loop",
+        Expr::DoWhile { .. } => "DoWhile",
+        Expr::FnDef { .. } => "FnDef",
+        Expr::Decorated { .. } => "Decorated",
+        Expr::FnCall { .. } => "FnCall",
+        Expr::KeywordArg { .. } => "KeywordArg",
+        Expr::Return(_) => "Return",
+        Expr::Break(_) => "Break",
+        Expr::Continue(_) => "Continue",
+        Expr::Match { .. } => "Match",
+        Expr::StructDef { .. } => "StructDef",
+        Expr::StructInit { .. } => "StructInit",
+        Expr::EnumDef { .. } => "EnumDef",
+        Expr::EnumInit { .. } => "EnumInit",
+        Expr::For { .. } => "For",
+        Expr::TryCatch { .. } => "TryCatch",
+        Expr::Throw { .. } => "Throw",
+        Expr::TupleLiteral(_) => "TupleLiteral",
+        Expr::Destructure { .. } => "Destructure",
+        Expr::Import(_) => "Import",
+        Expr::ImportNative(_) => "ImportNative",
+        Expr::LetTyped { .. } => "LetTyped",
+        Expr::ConstTyped { .. } => "ConstTyped",
+        Expr::Global { .. } => "Global",
+        Expr::Static { .. } => "Static",
+        Expr::Defer(_) => "Defer",
+        Expr::Switch { .. } => "Switch",
+        Expr::ClassDef { .. } => "ClassDef",
+        Expr::AbstractFn { .. } => "AbstractFn",
+        Expr::ClassInit { .. } => "ClassInit",
+        Expr::MethodCall { .. } => "MethodCall",
+        Expr::FieldAccess { .. } => "FieldAccess",
+        Expr::With { .. } => "With",
+        Expr::Test { .. } => "Test",
+        _ => "Other",
+    }
+}
+
+/// Raise a new exception from inside the evaluator.
+pub(crate) fn raise<T, E: From<Exception>>(kind: ExceptionKind, args: Vec<String>) -> Result<T, E> {
+    Err(Exception::new(kind, args).into())
+}
+
+/// Attributes of a caught exception, as resolved by `Interpreter::exception_attr`.
+const EXCEPTION_ATTRS: &[&str] = &["args", "cause", "code", "context", "kind", "message", "notes", "suppress_context"];
+
+thread_local! {
+    // Function calls currently running on this thread, across sub-interpreters,
+    // innermost last, each with the line of the statement it is running once
+    // known; what inspect.stack() reports
+    static CALL_STACK: std::cell::RefCell<Vec<(String, Option<usize>)>> = const { std::cell::RefCell::new(Vec::new()) };
+    // With stats on, when the time not yet added to them started
+    static CLOCK: std::cell::Cell<Option<Instant>> = const { std::cell::Cell::new(None) };
+}
+
+/// Add the time since the last call to the own time of the innermost function
+/// call, at the line it is running. Called whenever either of them changes.
+fn charge_time(stats: &StatsHandle) {
+    let now = Instant::now();
+    let Some(since) = CLOCK.with(|clock| clock.replace(Some(now))) else { return };
+    CALL_STACK.with(|stack| {
+        let stack = stack.borrow();
+        if !stack.is_empty() {
+            stats::lock(stats).record_time(&stack, now - since);
+        }
+    });
+}
+
+/// One running function call. Entering fails with RecursionError past the
+/// interpreter's recursion limit, before the Rust stack itself overflows. With
+/// stats on, the call's own time is added to them as it runs.
+struct CallFrame {
+    stats: Option<StatsHandle>,
+}
+
+impl CallFrame {
+    fn enter(function: &str, limit: &RecursionLimit, stats: Option<&StatsHandle>) -> Result<CallFrame, Exception> {
+        if CALL_STACK.with(|stack| stack.borrow().len()) >= limit.get() {
+            return Err(Exception::new(ExceptionKind::RecursionError, vec!["maximum recursion depth exceeded".to_string()]));
+        }
+        if let Some(stats) = stats {
+            charge_time(stats);
+        }
+        CALL_STACK.with(|stack| stack.borrow_mut().push((function.to_string(), None)));
+        Ok(CallFrame { stats: stats.cloned() })
+    }
+
+    /// Note that the innermost call has reached the statement at `line`. The
+    /// time it spent before its first statement counts toward that one.
+    fn at_line(line: usize, stats: &StatsHandle) {
+        CALL_STACK.with(|stack| {
+            if let Some((_, current @ None)) = stack.borrow_mut().last_mut() {
+                *current = Some(line);
+            }
+        });
+        charge_time(stats);
+        CALL_STACK.with(|stack| {
+            if let Some((_, current)) = stack.borrow_mut().last_mut() {
+                *current = Some(line);
+            }
+        });
+    }
+}
+
+impl Drop for CallFrame {
+    fn drop(&mut self) {
+        if let Some(stats) = &self.stats {
+            charge_time(stats);
+        }
+        CALL_STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// The functions running on this thread, outermost first.
+pub(crate) fn call_stack() -> Vec<String> {
+    CALL_STACK.with(|stack| stack.borrow().iter().map(|(function, _)| function.clone()).collect())
+}
+
+/// How one pass through a loop body ended.
+enum Iteration {
+    Completed(Value),
+    Continued,
+    Broken,
+}
+
+/// A user-defined function or method: its parameter names and its body. The
+/// body is shared, so copying the function table into the interpreter that runs
+/// a call, or a class into a value, copies handles rather than syntax trees.
+pub type Function = (Vec<Symbol>, Arc<Expr>);
+
+/// Supplies the display text of a value, or of a value nested in another
+/// (the flag), or None for the default; see `Value::display_with`.
+pub type DisplayHook<'a> = dyn FnMut(&Value, bool) -> Result<Option<String>, Exception> + 'a;
+
 pub struct Interpreter {
     pub env: HashMap<String, Value>,
-    pub functions: HashMap<String, (Vec<String>, Expr)>,
-    pub profile: Option<HashMap<&'static str, Duration>>,
+    pub functions: HashMap<String, Function>,
+    pub capabilities: Capabilities,
+    /// Where `print()` and `input()` prompts go; see `set_stdout`
+    pub(crate) stdout: Output,
+    /// Functions registered with `atexit()`, shared with the sub-interpreters
+    /// that run function calls so a handler registered anywhere is kept
+    pub(crate) exit_handlers: ExitHandlers,
+    /// Execution counters, collected only after `enable_stats`
+    pub(crate) stats: Option<StatsHandle>,
+    /// Results of `@cache` functions, shared with sub-interpreters like `exit_handlers`
+    pub(crate) caches: Caches,
+    /// How deeply calls may nest, shared with sub-interpreters like `exit_handlers`
+    pub(crate) recursion_limit: RecursionLimit,
+    /// Variables loaded by `config.load_env()`, shared with sub-interpreters and threads
+    pub(crate) env_vars: EnvVars,
 }
 
+/// Handlers queued by `atexit(fn, args...)`: function names and their arguments.
+pub(crate) type ExitHandlers = Arc<Mutex<Vec<(String, Vec<Value>)>>>;
+
 impl Interpreter {
     pub fn new() -> Self {
         let mut env = HashMap::new();
@@ -72,1656 +351,1937 @@ impl Interpreter {
         env.insert("copyright".to_string(), Value::Str("Copyright (c) StelLang contributors".to_string()));
         env.insert("credits".to_string(), Value::Str("Thanks to all StelLang contributors!".to_string()));
         env.insert("license".to_string(), Value::Str("Type license() to see the full license text".to_string()));
-        Self { env, functions: HashMap::new(), profile: Some(HashMap::new()) }
+        Self { env, functions: HashMap::new(), capabilities: Capabilities::all(), stdout: Output::stdout(), exit_handlers: ExitHandlers::default(), stats: None, caches: Caches::default(), recursion_limit: RecursionLimit::default(), env_vars: EnvVars::default() }
     }
 
-    pub fn eval(&mut self, expr: &Expr) -> Result<Value, Exception> {
-        let result = self.eval_inner(expr);
-        if let Some(profile) = &self.profile {
-            println!("--- Interpreter Profiling Summary ---");
-            let mut items: Vec<_> = profile.iter().collect();
-            items.sort_by(|a, b| b.1.cmp(a.1));
-            for (expr_type, duration) in items {
-                println!("{:20}: {:?}", expr_type, duration);
-            }
-            println!("-------------------------------------");
+    /// An interpreter for untrusted scripts: filesystem, stdin, OS and network
+    /// builtins are disabled until whitelisted with `allow_native`.
+    pub fn sandboxed() -> Self {
+        Self::with_capabilities(Capabilities::sandboxed())
+    }
+
+    pub fn with_capabilities(capabilities: Capabilities) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.capabilities = capabilities;
+        interpreter
+    }
+
+    /// Send `print()` output and `input()` prompts to `writer` instead of stdout.
+    /// Functions, `eval`/`exec` and spawned threads share the new sink.
+    pub fn set_stdout(&mut self, writer: Box<dyn std::io::Write + Send>) {
+        self.stdout = Output::new(writer);
+    }
+
+    /// Collect `print()` output in memory from now on and return a handle to read it.
+    pub fn capture_stdout(&mut self) -> Capture {
+        let capture = Capture::new();
+        self.set_stdout(Box::new(capture.clone()));
+        capture
+    }
+
+    /// Run the `atexit()` handlers, most recently registered first, and return the
+    /// errors they raised. Every handler runs once even if an earlier one fails.
+    /// Hosts call this before exiting the process; otherwise it happens when the
+    /// interpreter is dropped.
+    pub fn run_exit_handlers(&mut self) -> Vec<Exception> {
+        let mut errors = Vec::new();
+        loop {
+            // Pop under the lock but call without it, so handlers may register more
+            let next = self.exit_handlers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop();
+            let Some((name, args)) = next else { break };
+            let Some((params, body)) = self.functions.get(&name).cloned() else {
+                errors.push(Exception::new(ExceptionKind::NameError, vec![format!("name '{}' is not defined", name)]));
+                continue;
+            };
+            if let Err(exc) = self.run_method(name, &params, &body, None, args) {
+                errors.push(exc);
+            }
         }
-        result
+        errors
     }
 
-    fn profile_enter(&mut self, _expr_type: &'static str) -> Instant {
-        Instant::now()
+    /// Start counting expressions, allocations, calls and exceptions. Function
+    /// calls, `eval`/`exec` and threads started from now on add to the same totals.
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(StatsHandle::default());
     }
-    fn profile_exit(&mut self, expr_type: &'static str, start: Instant) {
-        if let Some(profile) = &mut self.profile {
-            let elapsed = start.elapsed();
-            *profile.entry(expr_type).or_insert(Duration::ZERO) += elapsed;
+
+    /// The counters so far, or None if `enable_stats` was never called.
+    pub fn stats(&self) -> Option<Stats> {
+        self.stats.as_ref().map(|stats| stats::lock(stats).clone())
+    }
+
+    fn record_call(&self, name: &str) {
+        if let Some(stats) = &self.stats {
+            stats::lock(stats).record_call(name);
         }
     }
 
-    fn eval_inner(&mut self, expr: &Expr) -> Result<Value, Exception> {
-        let expr_type = match expr {
-            Expr::Integer(_) => "Integer",
-            Expr::Float(_) => "Float",
-            Expr::String(_) => "String",
-            Expr::Ident(_) => "Ident",
-            Expr::ArrayLiteral(_) => "ArrayLiteral",
-            Expr::MapLiteral(_) => "MapLiteral",
-            Expr::Index { .. } => "Index",
-            Expr::AssignIndex { .. } => "AssignIndex",
-            Expr::BinaryOp { .. } => "BinaryOp",
-            Expr::UnaryOp { .. } => "UnaryOp",
-            Expr::Assign { .. } => "Assign",
-            Expr::Let { .. } => "Let",
-            Expr::Const { .. } => "Const",
-            Expr::Bool(_) => "Bool",
-            Expr::Null => "Null",
-            Expr::Block(_) => "Block",
-            Expr::If { .. } => "If",
-            Expr::While { .. } => "While",
-            Expr::FnDef { .. } => "FnDef",
-            Expr::FnCall { .. } => "FnCall",
-            Expr::Return(_) => "Return",
-            Expr::Break => "Break",
-            Expr::Continue => "Continue",
-            Expr::Match { .. } => "Match",
-            Expr::StructDef { .. } => "StructDef",
-            Expr::StructInit { .. } => "StructInit",
-            Expr::EnumDef { .. } => "EnumDef",
-            Expr::EnumInit { .. } => "EnumInit",
-            Expr::For { .. } => "For",
-            Expr::TryCatch { .. } => "TryCatch",
-            Expr::Throw(_) => "Throw",
-            Expr::TupleLiteral(_) => "TupleLiteral",
-            Expr::Destructure { .. } => "Destructure",
-            Expr::Import(_) => "Import",
-            Expr::LetTyped { .. } => "LetTyped",
-            Expr::ConstTyped { .. } => "ConstTyped",
-            Expr::Global { .. } => "Global",
-            Expr::Static { .. } => "Static",
-            Expr::Defer(_) => "Defer",
-            Expr::Switch { .. } => "Switch",
-            Expr::ClassDef { .. } => "ClassDef",
-            Expr::ClassInit { .. } => "ClassInit",
-            Expr::MethodCall { .. } => "MethodCall",
-            Expr::FieldAccess { .. } => "FieldAccess",
-            _ => "Other",
+    /// Turn debug mode on or off: what `__debug__` and `cfg("debug")` report to
+    /// the script. It is on by default; release profiles turn it off.
+    pub fn set_debug(&mut self, debug: bool) {
+        self.env.insert("__debug__".to_string(), Value::Bool(debug));
+    }
+
+    /// Bind a global variable before the script runs, such as a setting the
+    /// host passes in.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.env.insert(name.to_string(), value);
+    }
+
+    /// Permit a single native function even if its capability is disabled.
+    pub fn allow_native(&mut self, name: &str) {
+        self.capabilities.allowed_functions.insert(name.to_string());
+    }
+
+    /// Bind a module implemented by the host under its name, without a
+    /// shared library. Scripts use it exactly like an `import native` module.
+    pub fn add_native_module(&mut self, module: NativeModule) {
+        let name = module.name.clone();
+        self.env.insert(name, module.into_value(None));
+    }
+
+    pub fn eval(&mut self, expr: &Expr) -> Result<Value, Exception> {
+        self.eval_inner(expr).or_else(ControlFlow::finish)
+    }
+
+    fn eval_inner(&mut self, expr: &Expr) -> Result<Value, ControlFlow> {
+        // Not a node of its own to stats
+        if let Expr::Located { line, file, expr } = expr {
+            if let Some(stats) = &self.stats {
+                CallFrame::at_line(*line, stats);
+            }
+            return self.eval_inner(expr).map_err(|flow| flow.at_line(*line, file));
+        }
+        // Recorded here rather than in eval_expr, whose arms often return early
+        let Some(stats) = self.stats.clone() else {
+            return self.eval_expr(expr);
         };
-        let start = self.profile_enter(expr_type);
-        let result = {
-            match expr {
-                Expr::Integer(n) => Ok(Value::Int(*n)),
-                Expr::Float(f) => Ok(Value::Float(*f)),
-                Expr::String(s) => Ok(Value::Str(s.clone())),
-                Expr::Ident(name) => {
-                    // Support self.field access
-                    if let Some((obj_name, field_name)) = name.split_once('.') {
-                        if obj_name == "self" {
-                            if let Some(Value::Instance { class_name, fields }) = self.env.get("self") {
-                                if let Some(val) = fields.get(field_name) {
+        let result = self.eval_expr(expr);
+        let allocates = matches!(expr,
+            Expr::String(_) | Expr::ArrayLiteral(_) | Expr::MapLiteral(_) | Expr::TupleLiteral(_) |
+            Expr::BinaryOp { .. } | Expr::UnaryOp { .. } |
+            Expr::ClassInit { .. } | Expr::StructInit { .. } | Expr::EnumInit { .. });
+        stats::lock(&stats).record(node_name(expr), allocates, &result, self.env.len());
+        result
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, ControlFlow> {
+        interrupt::check()?;
+        match expr {
+            Expr::Integer(n) => Ok(Value::Int(*n)),
+            Expr::Float(f) => Ok(Value::Float(*f)),
+            Expr::String(s) => Ok(Value::Str(s.clone())),
+            Expr::Ident(name) => {
+                // Support self.field access
+                if let Some((obj_name, field_name)) = name.split_once('.') {
+                    if obj_name == "self" {
+                        if let Some(instance @ Value::Instance { class_name, fields, .. }) = self.env.get("self") {
+                            if let Some(val) = fields.get(field_name) {
+                                return Ok(val.clone());
+                            } else {
+                                // Look up in class hierarchy
+                                let (_, class_fields) = self.collect_class_hierarchy(class_name);
+                                if let Some(val) = class_fields.get(field_name) {
                                     return Ok(val.clone());
                                 } else {
-                                    // Look up in class hierarchy
-                                    let (_, class_fields) = self.collect_class_hierarchy(class_name);
-                                    if let Some(val) = class_fields.get(field_name) {
-                                        return Ok(val.clone());
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::AttributeError, vec![format!("'self' object has no attribute '{}'", field_name)]));
-                                    }
+                                    return Err(self.attribute_error(instance, "self", field_name).into());
                                 }
-                            } else {
-                                return Err(Exception::new(ExceptionKind::TypeError, vec!["'self' is not an instance".to_string()]));
                             }
+                        } else {
+                            return raise(ExceptionKind::TypeError, vec!["'self' is not an instance".to_string()]);
                         }
                     }
-                    Ok(self.env.get(name).cloned().unwrap_or(Value::None)) // Changed default to None
                 }
-                Expr::ArrayLiteral(items) => {
-                    let mut evaluated_items = Vec::new();
-                    for e in items {
-                        evaluated_items.push(self.eval_inner(e)?);
-                    }
-                    Ok(Value::List(evaluated_items))
+                match self.env.get(name.as_str()) {
+                    Some(value) => Ok(value.clone()),
+                    // Functions aren't values yet, so naming one still gives None
+                    None if self.is_function_name(name) => Ok(Value::None),
+                    None => Err(self.name_error(name).into()),
                 }
-                Expr::MapLiteral(pairs) => {
-                    let mut map = HashMap::new();
-                    for (k, v) in pairs {
-                        let key = self.eval_inner(k)?;
-                        let val = self.eval_inner(v)?;
-                        map.insert(key, val);
-                    }
-                    Ok(Value::Dict(map))
+            }
+            Expr::ArrayLiteral(items) => {
+                let mut evaluated_items = Vec::new();
+                for e in items {
+                    evaluated_items.push(self.eval_inner(e)?);
                 }
-                Expr::Index { collection, index } => {
-                    let coll = self.eval_inner(collection)?;
-                    let idx = self.eval_inner(index)?;
-                    match (coll, idx) {
-                        (Value::List(arr), Value::Int(n)) => {
-                            if n < 0 || n as usize >= arr.len() {
-                                Err(Exception::new(ExceptionKind::IndexError, vec![format!("list index {} out of range", n)]))
-                            } else {
-                                Ok(arr.get(n as usize).cloned().unwrap_or(Value::None))
-                            }
-                        }
-                        (Value::Dict(map), key) => {
-                            map.get(&key).cloned().ok_or_else(|| {
-                                Exception::new(ExceptionKind::KeyError, vec![key.to_display_string()])
-                            })
-                        }
-                        (Value::Str(s), Value::Int(n)) => {
-                            if n < 0 || n as usize >= s.len() {
-                                Err(Exception::new(ExceptionKind::IndexError, vec![format!("string index {} out of range", n)]))
-                            } else {
-                                Ok(s.chars().nth(n as usize).map(|c| Value::Str(c.to_string())).unwrap_or(Value::None))
-                            }
+                Ok(Value::List(evaluated_items))
+            }
+            Expr::MapLiteral(pairs) => {
+                let entries = pairs.iter().map(|(k, v)| {
+                    let key = self.eval_inner(k)?;
+                    key.check_hashable()?;
+                    Ok((key, self.eval_inner(v)?))
+                });
+                Ok(Value::Dict(entries.collect::<Result<ValueMap<_, _>, ControlFlow>>()?))
+            }
+            Expr::Index { collection, index } => {
+                let coll = self.eval_inner(collection)?;
+                let idx = self.eval_inner(index)?;
+                match (coll, idx) {
+                    (Value::List(arr), Value::Int(n)) => {
+                        if n < 0 || n as usize >= arr.len() {
+                            raise(ExceptionKind::IndexError, vec![format!("list index {} out of range", n)])
+                        } else {
+                            Ok(arr.get(n as usize).cloned().unwrap_or(Value::None))
                         }
-                        (Value::Bytes(b), Value::Int(n)) => {
-                            if n < 0 || n as usize >= b.len() {
-                                Err(Exception::new(ExceptionKind::IndexError, vec![format!("bytes index {} out of range", n)]))
-                            } else {
-                                Ok(b.get(n as usize).map(|&byte| Value::Int(byte as i64)).unwrap_or(Value::None))
-                            }
+                    }
+                    (Value::Dict(map), key) => {
+                        key.check_hashable()?;
+                        map.get(&key).cloned().ok_or_else(|| {
+                            Exception::new(ExceptionKind::KeyError, vec![key.to_display_string()]).into()
+                        })
+                    }
+                    // Indexes count characters, not UTF-8 bytes
+                    (Value::Str(s), Value::Int(n)) => match usize::try_from(n).ok().and_then(|n| s.chars().nth(n)) {
+                        Some(c) => Ok(Value::Str(c.to_string())),
+                        None => raise(ExceptionKind::IndexError, vec![format!("string index {} out of range", n)]),
+                    },
+                    (Value::Bytes(b), Value::Int(n)) => {
+                        if n < 0 || n as usize >= b.len() {
+                            raise(ExceptionKind::IndexError, vec![format!("bytes index {} out of range", n)])
+                        } else {
+                            Ok(b.get(n as usize).map(|&byte| Value::Int(byte as i64)).unwrap_or(Value::None))
                         }
-                        (Value::ByteArray(b), Value::Int(n)) => {
-                            if n < 0 || n as usize >= b.len() {
-                                Err(Exception::new(ExceptionKind::IndexError, vec![format!("bytearray index {} out of range", n)]))
-                            } else {
-                                Ok(b.get(n as usize).map(|&byte| Value::Int(byte as i64)).unwrap_or(Value::None))
-                            }
+                    }
+                    (Value::ByteArray(b), Value::Int(n)) => {
+                        if n < 0 || n as usize >= b.len() {
+                            raise(ExceptionKind::IndexError, vec![format!("bytearray index {} out of range", n)])
+                        } else {
+                            Ok(b.get(n as usize).map(|&byte| Value::Int(byte as i64)).unwrap_or(Value::None))
                         }
-                        (Value::Tuple(t), Value::Int(n)) => {
-                            if n < 0 || n as usize >= t.len() {
-                                Err(Exception::new(ExceptionKind::IndexError, vec![format!("tuple index {} out of range", n)]))
-                            } else {
-                                Ok(t.get(n as usize).cloned().unwrap_or(Value::None))
-                            }
+                    }
+                    (Value::Tuple(t), Value::Int(n)) => {
+                        if n < 0 || n as usize >= t.len() {
+                            raise(ExceptionKind::IndexError, vec![format!("tuple index {} out of range", n)])
+                        } else {
+                            Ok(t.get(n as usize).cloned().unwrap_or(Value::None))
                         }
-                        (coll, _) => Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object is not subscriptable", coll.type_name())]))
                     }
+                    (coll, _) => raise(ExceptionKind::TypeError, vec![format!("'{}' object is not subscriptable", coll.type_name())])
                 }
-                Expr::AssignIndex { collection, index, expr } => {
-                    let idx = self.eval_inner(index)?;
-                    let val = self.eval_inner(expr)?;
-                    
-                    // Special case: if collection is a variable reference, we need to update the environment
-                    if let Expr::Ident(name) = collection.as_ref() {
-                        if let Some(existing_value) = self.env.get_mut(name) {
-                            match existing_value {
-                                Value::List(arr) => {
-                                    if let Value::Int(n) = idx {
-                                        let i = n as usize;
-                                        if i < arr.len() {
-                                            arr[i] = val.clone();
-                                            Ok(val)
-                                        } else {
-                                            Err(Exception::new(ExceptionKind::IndexError, vec![format!("list assignment index {} out of range", n)]))
-                                        }
+            }
+            Expr::AssignIndex { collection, index, expr } => {
+                let idx = self.eval_inner(index)?;
+                let val = self.eval_inner(expr)?;
+                
+                // Special case: if collection is a variable reference, we need to update the environment
+                if let Expr::Ident(name) = collection.as_ref() {
+                    if let Some(existing_value) = self.env.get_mut(name.as_str()) {
+                        match existing_value {
+                            Value::List(arr) => {
+                                if let Value::Int(n) = idx {
+                                    let i = n as usize;
+                                    if i < arr.len() {
+                                        arr[i] = val.clone();
+                                        Ok(val)
                                     } else {
-                                        Err(Exception::new(ExceptionKind::TypeError, vec!["list indices must be integers".to_string()]))
+                                        raise(ExceptionKind::IndexError, vec![format!("list assignment index {} out of range", n)])
                                     }
+                                } else {
+                                    raise(ExceptionKind::TypeError, vec!["list indices must be integers".to_string()])
                                 }
-                                Value::Dict(map) => {
-                                    map.insert(idx, val.clone());
-                                    Ok(val)
-                                }
-                                Value::ByteArray(arr) => {
-                                    if let Value::Int(n) = idx {
-                                        let i = n as usize;
-                                        if i < arr.len() {
-                                            if let Value::Int(byte_val) = val {
-                                                if byte_val >= 0 && byte_val <= 255 {
-                                                    arr[i] = byte_val as u8;
-                                                    Ok(val)
-                                                } else {
-                                                    Err(Exception::new(ExceptionKind::ValueError, vec!["byte must be in range(0, 256)".to_string()]))
-                                                }
+                            }
+                            Value::Dict(map) => {
+                                idx.check_hashable()?;
+                                map.insert(idx, val.clone());
+                                Ok(val)
+                            }
+                            Value::ByteArray(arr) => {
+                                if let Value::Int(n) = idx {
+                                    let i = n as usize;
+                                    if i < arr.len() {
+                                        if let Value::Int(byte_val) = val {
+                                            if byte_val >= 0 && byte_val <= 255 {
+                                                arr[i] = byte_val as u8;
+                                                Ok(val)
                                             } else {
-                                                Err(Exception::new(ExceptionKind::TypeError, vec!["bytearray assignment must be an integer".to_string()]))
+                                                raise(ExceptionKind::ValueError, vec!["byte must be in range(0, 256)".to_string()])
                                             }
                                         } else {
-                                            Err(Exception::new(ExceptionKind::IndexError, vec![format!("bytearray assignment index {} out of range", n)]))
+                                            raise(ExceptionKind::TypeError, vec!["bytearray assignment must be an integer".to_string()])
                                         }
                                     } else {
-                                        Err(Exception::new(ExceptionKind::TypeError, vec!["bytearray indices must be integers".to_string()]))
+                                        raise(ExceptionKind::IndexError, vec![format!("bytearray assignment index {} out of range", n)])
                                     }
+                                } else {
+                                    raise(ExceptionKind::TypeError, vec!["bytearray indices must be integers".to_string()])
                                 }
-                                coll => Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object does not support item assignment", coll.type_name())]))
                             }
-                        } else {
-                            Err(Exception::new(ExceptionKind::NameError, vec![format!("name '{}' is not defined", name)]))
+                            coll => raise(ExceptionKind::TypeError, vec![format!("'{}' object does not support item assignment", coll.type_name())])
                         }
                     } else {
-                        // General case: evaluate collection and modify a copy
-                        let mut coll = self.eval_inner(collection)?;
-                        match (&mut coll, idx) {
-                            (Value::List(arr), Value::Int(n)) => {
-                                let i = n as usize;
-                                if i < arr.len() {
-                                    arr[i] = val.clone();
-                                    Ok(coll)
-                                } else {
-                                    Err(Exception::new(ExceptionKind::IndexError, vec![format!("list assignment index {} out of range", n)]))
-                                }
-                            }
-                            (Value::Dict(map), key) => {
-                                map.insert(key, val.clone());
+                        Err(self.name_error(name).into())
+                    }
+                } else {
+                    // General case: evaluate collection and modify a copy
+                    let mut coll = self.eval_inner(collection)?;
+                    match (&mut coll, idx) {
+                        (Value::List(arr), Value::Int(n)) => {
+                            let i = n as usize;
+                            if i < arr.len() {
+                                arr[i] = val.clone();
                                 Ok(coll)
+                            } else {
+                                raise(ExceptionKind::IndexError, vec![format!("list assignment index {} out of range", n)])
                             }
-                            (Value::ByteArray(arr), Value::Int(n)) => {
-                                let i = n as usize;
-                                if i < arr.len() {
-                                    if let Value::Int(byte_val) = val {
-                                        if byte_val >= 0 && byte_val <= 255 {
-                                            arr[i] = byte_val as u8;
-                                            Ok(coll)
-                                        } else {
-                                            Err(Exception::new(ExceptionKind::ValueError, vec!["byte must be in range(0, 256)".to_string()]))
-                                        }
+                        }
+                        (Value::Dict(map), key) => {
+                            key.check_hashable()?;
+                            map.insert(key, val.clone());
+                            Ok(coll)
+                        }
+                        (Value::ByteArray(arr), Value::Int(n)) => {
+                            let i = n as usize;
+                            if i < arr.len() {
+                                if let Value::Int(byte_val) = val {
+                                    if byte_val >= 0 && byte_val <= 255 {
+                                        arr[i] = byte_val as u8;
+                                        Ok(coll)
                                     } else {
-                                        Err(Exception::new(ExceptionKind::TypeError, vec!["bytearray assignment must be an integer".to_string()]))
+                                        raise(ExceptionKind::ValueError, vec!["byte must be in range(0, 256)".to_string()])
                                     }
                                 } else {
-                                    Err(Exception::new(ExceptionKind::IndexError, vec![format!("bytearray assignment index {} out of range", n)]))
+                                    raise(ExceptionKind::TypeError, vec!["bytearray assignment must be an integer".to_string()])
                                 }
+                            } else {
+                                raise(ExceptionKind::IndexError, vec![format!("bytearray assignment index {} out of range", n)])
                             }
-                            (coll, _) => Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object does not support item assignment", coll.type_name())]))
                         }
+                        (coll, _) => raise(ExceptionKind::TypeError, vec![format!("'{}' object does not support item assignment", coll.type_name())])
                     }
                 }
-                Expr::BinaryOp { left, op, right } => {
-                    let l = self.eval_inner(left)?;
-                    let r = self.eval_inner(right)?;
-                    match (l, r) {
-                        (Value::Int(l), Value::Int(r)) => match op.as_str() {
-                            "+" => Ok(Value::Int(l + r)),
-                            "-" => Ok(Value::Int(l - r)),
-                            "*" => Ok(Value::Int(l * r)),
-                            "/" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]));
-                                }
-                                Ok(Value::Float((l as f64) / (r as f64)))
-                            },
-                            "//" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["integer division by zero".to_string()]));
-                                }
-                                Ok(Value::Int(l / r))
-                            },
-                            "%" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["modulo by zero".to_string()]));
-                                }
-                                Ok(Value::Int(l % r))
-                            },
-                            "**" => Ok(Value::Float((l as f64).powf(r as f64))),
-                            "&" => Ok(Value::Int(l & r)),
-                            "|" => Ok(Value::Int(l | r)),
-                            "^" => Ok(Value::Int(l ^ r)),
-                            "<<" => Ok(Value::Int(l << r)),
-                            ">>" => Ok(Value::Int(l >> r)),
-                            "==" => Ok(Value::Bool(l == r)),
-                            "!=" => Ok(Value::Bool(l != r)),
-                            "<" => Ok(Value::Bool(l < r)),
-                            ">" => Ok(Value::Bool(l > r)),
-                            "<=" => Ok(Value::Bool(l <= r)),
-                            ">=" => Ok(Value::Bool(l >= r)),
-                            "and" => Ok(Value::Bool((l != 0) && (r != 0))),
-                            "or" => Ok(Value::Bool((l != 0) || (r != 0))),
-                            "is" => Ok(Value::Bool(l == r)), // For primitive types, 'is' is value equality
-                            "is not" => Ok(Value::Bool(l != r)),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'int' and 'int'", op)])),
-                        },
-                        (Value::Float(l), Value::Float(r)) => match op.as_str() {
-                            "+" => Ok(Value::Float(l + r)),
-                            "-" => Ok(Value::Float(l - r)),
-                            "*" => Ok(Value::Float(l * r)),
-                            "/" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]));
-                                }
-                                Ok(Value::Float(l / r))
-                            },
-                            "//" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]));
-                                }
-                                Ok(Value::Float((l / r).floor()))
-                            },
-                            "%" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]));
-                                }
-                                Ok(Value::Float(l % r))
-                            },
-                            "**" => Ok(Value::Float(l.powf(r))),
-                            "==" => Ok(Value::Bool(l == r)),
-                            "!=" => Ok(Value::Bool(l != r)),
-                            "<" => Ok(Value::Bool(l < r)),
-                            ">" => Ok(Value::Bool(l > r)),
-                            "<=" => Ok(Value::Bool(l <= r)),
-                            ">=" => Ok(Value::Bool(l >= r)),
-                            "and" => Ok(Value::Bool((l != 0.0) && (r != 0.0))),
-                            "or" => Ok(Value::Bool((l != 0.0) || (r != 0.0))),
-                            "is" => Ok(Value::Bool(l == r)),
-                            "is not" => Ok(Value::Bool(l != r)),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'float' and 'float'", op)])),
-                        },
-                        (Value::Int(l), Value::Float(r)) => match op.as_str() {
-                            "+" => Ok(Value::Float((l as f64) + r)),
-                            "-" => Ok(Value::Float((l as f64) - r)),
-                            "*" => Ok(Value::Float((l as f64) * r)),
-                            "/" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]));
-                                }
-                                Ok(Value::Float((l as f64) / r))
-                            },
-                            "//" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]));
-                                }
-                                Ok(Value::Float(((l as f64) / r).floor()))
-                            },
-                            "%" => {
-                                if r == 0.0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]));
-                                }
-                                Ok(Value::Float((l as f64) % r))
-                            },
-                            "**" => Ok(Value::Float((l as f64).powf(r))),
-                            "==" => Ok(Value::Bool((l as f64) == r)),
-                            "!=" => Ok(Value::Bool((l as f64) != r)),
-                            "<" => Ok(Value::Bool((l as f64) < r)),
-                            ">" => Ok(Value::Bool((l as f64) > r)),
-                            "<=" => Ok(Value::Bool((l as f64) <= r)),
-                            ">=" => Ok(Value::Bool((l as f64) >= r)),
-                            "and" => Ok(Value::Bool((l != 0) && (r != 0.0))),
-                            "or" => Ok(Value::Bool((l != 0) || (r != 0.0))),
-                            "is" => Ok(Value::Bool((l as f64) == r)),
-                            "is not" => Ok(Value::Bool((l as f64) != r)),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'int' and 'float'", op)])),
-                        },
-                        (Value::Float(l), Value::Int(r)) => match op.as_str() {
-                            "+" => Ok(Value::Float(l + (r as f64))),
-                            "-" => Ok(Value::Float(l - (r as f64))),
-                            "*" => Ok(Value::Float(l * (r as f64))),
-                            "/" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]));
-                                }
-                                Ok(Value::Float(l / (r as f64)))
-                            },
-                            "//" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]));
-                                }
-                                Ok(Value::Float((l / (r as f64)).floor()))
-                            },
-                            "%" => {
-                                if r == 0 {
-                                    return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]));
-                                }
-                                Ok(Value::Float(l % (r as f64)))
-                            },
-                            "**" => Ok(Value::Float(l.powf(r as f64))),
-                            "==" => Ok(Value::Bool(l == (r as f64))),
-                            "!=" => Ok(Value::Bool(l != (r as f64))),
-                            "<" => Ok(Value::Bool(l < (r as f64))),
-                            ">" => Ok(Value::Bool(l > (r as f64))),
-                            "<=" => Ok(Value::Bool(l <= (r as f64))),
-                            ">=" => Ok(Value::Bool(l >= (r as f64))),
-                            "and" => Ok(Value::Bool((l != 0.0) && (r != 0))),
-                            "or" => Ok(Value::Bool((l != 0.0) || (r != 0))),
-                            "is" => Ok(Value::Bool(l == (r as f64))),
-                            "is not" => Ok(Value::Bool(l != (r as f64))),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'float' and 'int'", op)])),
-                        },
-                        (Value::Str(l), Value::Str(r)) => match op.as_str() {
-                            "+" => Ok(Value::Str(l + &r)),
-                            "==" => Ok(Value::Bool(l == r)),
-                            "!=" => Ok(Value::Bool(l != r)),
-                            "<" => Ok(Value::Bool(l < r)),
-                            ">" => Ok(Value::Bool(l > r)),
-                            "<=" => Ok(Value::Bool(l <= r)),
-                            ">=" => Ok(Value::Bool(l >= r)),
-                            "is" => Ok(Value::Bool(l == r)),
-                            "is not" => Ok(Value::Bool(l != r)),
-                            "in" => Ok(Value::Bool(r.contains(&l))),
-                            "not in" => Ok(Value::Bool(!r.contains(&l))),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'str' and 'str'", op)])),
-                        },
-                        (Value::Str(l), Value::Int(r)) if op == "*" => {
-                            if r < 0 {
-                                return Err(Exception::new(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]));
+            }
+            Expr::BinaryOp { left, op, right } => {
+                let l = self.eval_inner(left)?;
+                let r = self.eval_inner(right)?;
+                match (l, r) {
+                    (Value::Int(l), Value::Int(r)) => match op.as_str() {
+                        "+" => Ok(Value::Int(l + r)),
+                        "-" => Ok(Value::Int(l - r)),
+                        "*" => Ok(Value::Int(l * r)),
+                        "/" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]);
                             }
-                            Ok(Value::Str(l.repeat(r as usize)))
+                            Ok(Value::Float((l as f64) / (r as f64)))
                         },
-                        (Value::Int(l), Value::Str(r)) if op == "*" => {
-                            if l < 0 {
-                                return Err(Exception::new(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]));
+                        "//" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["integer division by zero".to_string()]);
                             }
-                            Ok(Value::Str(r.repeat(l as usize)))
+                            Ok(Value::Int(l / r))
                         },
-                        (Value::Bool(l), Value::Bool(r)) => match op.as_str() {
-                            "and" => Ok(Value::Bool(l && r)),
-                            "or" => Ok(Value::Bool(l || r)),
-                            "==" => Ok(Value::Bool(l == r)),
-                            "!=" => Ok(Value::Bool(l != r)),
-                            "is" => Ok(Value::Bool(l == r)),
-                            "is not" => Ok(Value::Bool(l != r)),
-                            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'bool' and 'bool'", op)])),
+                        "%" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["modulo by zero".to_string()]);
+                            }
+                            Ok(Value::Int(l % r))
                         },
-                        (Value::List(l), Value::List(r)) if op == "+" => {
-                            let mut new_list = l.clone();
-                            new_list.extend(r.clone());
-                            Ok(Value::List(new_list))
+                        "**" => Ok(Value::Float((l as f64).powf(r as f64))),
+                        "&" => Ok(Value::Int(l & r)),
+                        "|" => Ok(Value::Int(l | r)),
+                        "^" => Ok(Value::Int(l ^ r)),
+                        "<<" => Ok(Value::Int(l << r)),
+                        ">>" => Ok(Value::Int(l >> r)),
+                        "==" => Ok(Value::Bool(l == r)),
+                        "!=" => Ok(Value::Bool(l != r)),
+                        "<" => Ok(Value::Bool(l < r)),
+                        ">" => Ok(Value::Bool(l > r)),
+                        "<=" => Ok(Value::Bool(l <= r)),
+                        ">=" => Ok(Value::Bool(l >= r)),
+                        "and" => Ok(Value::Bool((l != 0) && (r != 0))),
+                        "or" => Ok(Value::Bool((l != 0) || (r != 0))),
+                        "is" => Ok(Value::Bool(l == r)), // For primitive types, 'is' is value equality
+                        "is not" => Ok(Value::Bool(l != r)),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'int' and 'int'", op)]),
+                    },
+                    (Value::Float(l), Value::Float(r)) => match op.as_str() {
+                        "+" => Ok(Value::Float(l + r)),
+                        "-" => Ok(Value::Float(l - r)),
+                        "*" => Ok(Value::Float(l * r)),
+                        "/" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]);
+                            }
+                            Ok(Value::Float(l / r))
                         },
-                        (Value::List(l), Value::Int(r)) if op == "*" => {
-                            if r < 0 {
-                                return Err(Exception::new(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]));
+                        "//" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]);
                             }
-                            let mut new_list = Vec::new();
-                            for _ in 0..(r as usize) {
-                                new_list.extend_from_slice(&l);
+                            Ok(Value::Float((l / r).floor()))
+                        },
+                        "%" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]);
                             }
-                            Ok(Value::List(new_list))
+                            Ok(Value::Float(l % r))
                         },
-                        (Value::Int(l), Value::List(r)) if op == "*" => {
-                            if l < 0 {
-                                return Err(Exception::new(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]));
+                        "**" => Ok(Value::Float(l.powf(r))),
+                        "==" => Ok(Value::Bool(l == r)),
+                        "!=" => Ok(Value::Bool(l != r)),
+                        "<" => Ok(Value::Bool(l < r)),
+                        ">" => Ok(Value::Bool(l > r)),
+                        "<=" => Ok(Value::Bool(l <= r)),
+                        ">=" => Ok(Value::Bool(l >= r)),
+                        "and" => Ok(Value::Bool((l != 0.0) && (r != 0.0))),
+                        "or" => Ok(Value::Bool((l != 0.0) || (r != 0.0))),
+                        // Identity, unlike ==, holds for a NaN compared with itself
+                        "is" => Ok(Value::Bool(Value::Float(l) == Value::Float(r))),
+                        "is not" => Ok(Value::Bool(Value::Float(l) != Value::Float(r))),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'float' and 'float'", op)]),
+                    },
+                    (Value::Int(l), Value::Float(r)) => match op.as_str() {
+                        "+" => Ok(Value::Float((l as f64) + r)),
+                        "-" => Ok(Value::Float((l as f64) - r)),
+                        "*" => Ok(Value::Float((l as f64) * r)),
+                        "/" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]);
                             }
-                            let mut new_list = Vec::new();
-                            for _ in 0..(l as usize) {
-                                new_list.extend_from_slice(&r);
+                            Ok(Value::Float((l as f64) / r))
+                        },
+                        "//" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]);
                             }
-                            Ok(Value::List(new_list))
+                            Ok(Value::Float(((l as f64) / r).floor()))
                         },
-                        (Value::List(l), r_val) if op == "in" => {
-                            Ok(Value::Bool(l.contains(&r_val)))
+                        "%" => {
+                            if r == 0.0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]);
+                            }
+                            Ok(Value::Float((l as f64) % r))
                         },
-                        (Value::List(l), r_val) if op == "not in" => {
-                            Ok(Value::Bool(!l.contains(&r_val)))
+                        "**" => Ok(Value::Float((l as f64).powf(r))),
+                        "==" => Ok(Value::Bool((l as f64) == r)),
+                        "!=" => Ok(Value::Bool((l as f64) != r)),
+                        "<" => Ok(Value::Bool((l as f64) < r)),
+                        ">" => Ok(Value::Bool((l as f64) > r)),
+                        "<=" => Ok(Value::Bool((l as f64) <= r)),
+                        ">=" => Ok(Value::Bool((l as f64) >= r)),
+                        "and" => Ok(Value::Bool((l != 0) && (r != 0.0))),
+                        "or" => Ok(Value::Bool((l != 0) || (r != 0.0))),
+                        "is" => Ok(Value::Bool((l as f64) == r)),
+                        "is not" => Ok(Value::Bool((l as f64) != r)),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'int' and 'float'", op)]),
+                    },
+                    (Value::Float(l), Value::Int(r)) => match op.as_str() {
+                        "+" => Ok(Value::Float(l + (r as f64))),
+                        "-" => Ok(Value::Float(l - (r as f64))),
+                        "*" => Ok(Value::Float(l * (r as f64))),
+                        "/" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["division by zero".to_string()]);
+                            }
+                            Ok(Value::Float(l / (r as f64)))
                         },
-                        // Handle membership operators with item on left, container on right
-                        (l_val, Value::List(r)) if op == "in" => {
-                            Ok(Value::Bool(r.contains(&l_val)))
+                        "//" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float floor division by zero".to_string()]);
+                            }
+                            Ok(Value::Float((l / (r as f64)).floor()))
                         },
-                        (l_val, Value::List(r)) if op == "not in" => {
-                            Ok(Value::Bool(!r.contains(&l_val)))
+                        "%" => {
+                            if r == 0 {
+                                return raise(ExceptionKind::ZeroDivisionError, vec!["float modulo by zero".to_string()]);
+                            }
+                            Ok(Value::Float(l % (r as f64)))
                         },
-                        (Value::None, Value::None) if op == "is" => Ok(Value::Bool(true)),
-                        (Value::None, Value::None) if op == "is not" => Ok(Value::Bool(false)),
-                        (Value::None, _) if op == "is" => Ok(Value::Bool(false)),
-                        (Value::None, _) if op == "is not" => Ok(Value::Bool(true)),
-                        (_, Value::None) if op == "is" => Ok(Value::Bool(false)),
-                        (_, Value::None) if op == "is not" => Ok(Value::Bool(true)),
-                        (l_val, r_val) if op == "is" => Ok(Value::Bool(l_val == r_val)), // Fallback for other types
-                        (l_val, r_val) if op == "is not" => Ok(Value::Bool(l_val != r_val)), // Fallback for other types
-                        (l, r) => Err(Exception::new(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: '{}' and '{}'", op, l.type_name(), r.type_name())])),
-                    }
+                        "**" => Ok(Value::Float(l.powf(r as f64))),
+                        "==" => Ok(Value::Bool(l == (r as f64))),
+                        "!=" => Ok(Value::Bool(l != (r as f64))),
+                        "<" => Ok(Value::Bool(l < (r as f64))),
+                        ">" => Ok(Value::Bool(l > (r as f64))),
+                        "<=" => Ok(Value::Bool(l <= (r as f64))),
+                        ">=" => Ok(Value::Bool(l >= (r as f64))),
+                        "and" => Ok(Value::Bool((l != 0.0) && (r != 0))),
+                        "or" => Ok(Value::Bool((l != 0.0) || (r != 0))),
+                        "is" => Ok(Value::Bool(l == (r as f64))),
+                        "is not" => Ok(Value::Bool(l != (r as f64))),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'float' and 'int'", op)]),
+                    },
+                    (Value::Decimal(l), Value::Decimal(r)) => Ok(decimal::binary_op(op, l, r)?),
+                    (Value::Decimal(l), Value::Int(r)) => Ok(decimal::binary_op(op, l, r.into())?),
+                    (Value::Int(l), Value::Decimal(r)) => Ok(decimal::binary_op(op, l.into(), r)?),
+                    (Value::Str(l), Value::Str(r)) => match op.as_str() {
+                        "+" => Ok(Value::Str(l + &r)),
+                        "==" => Ok(Value::Bool(l == r)),
+                        "!=" => Ok(Value::Bool(l != r)),
+                        "<" => Ok(Value::Bool(l < r)),
+                        ">" => Ok(Value::Bool(l > r)),
+                        "<=" => Ok(Value::Bool(l <= r)),
+                        ">=" => Ok(Value::Bool(l >= r)),
+                        "is" => Ok(Value::Bool(l == r)),
+                        "is not" => Ok(Value::Bool(l != r)),
+                        "in" => Ok(Value::Bool(r.contains(&l))),
+                        "not in" => Ok(Value::Bool(!r.contains(&l))),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'str' and 'str'", op)]),
+                    },
+                    (Value::Str(l), Value::Int(r)) if op == "*" => {
+                        if r < 0 {
+                            return raise(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]);
+                        }
+                        Ok(Value::Str(l.repeat(r as usize)))
+                    },
+                    (Value::Int(l), Value::Str(r)) if op == "*" => {
+                        if l < 0 {
+                            return raise(ExceptionKind::ValueError, vec!["negative repetition count".to_string()]);
+                        }
+                        Ok(Value::Str(r.repeat(l as usize)))
+                    },
+                    (Value::Bool(l), Value::Bool(r)) => match op.as_str() {
+                        "and" => Ok(Value::Bool(l && r)),
+                        "or" => Ok(Value::Bool(l || r)),
+                        "==" => Ok(Value::Bool(l == r)),
+                        "!=" => Ok(Value::Bool(l != r)),
+                        "is" => Ok(Value::Bool(l == r)),
+                        "is not" => Ok(Value::Bool(l != r)),
+                        _ => raise(ExceptionKind::TypeError, vec![format!("unsupported operand type(s) for {}: 'bool' and 'bool'", op)]),
+                    },
+                    (Value::None, Value::None) if op == "is" => Ok(Value::Bool(true)),
+                    (Value::None, Value::None) if op == "is not" => Ok(Value::Bool(false)),
+                    (Value::None, _) if op == "is" => Ok(Value::Bool(false)),
+                    (Value::None, _) if op == "is not" => Ok(Value::Bool(true)),
+                    (_, Value::None) if op == "is" => Ok(Value::Bool(false)),
+                    (_, Value::None) if op == "is not" => Ok(Value::Bool(true)),
+                    (l_val, r_val) if op == "is" => Ok(Value::Bool(l_val == r_val)), // Fallback for other types
+                    (l_val, r_val) if op == "is not" => Ok(Value::Bool(l_val != r_val)), // Fallback for other types
+                    (l, r) => Ok(containers::binary_op(op, l, r)?),
                 }
-                Expr::UnaryOp { op, expr } => {
-                    let v = self.eval_inner(expr)?;
-                    match (op.as_str(), v) {
-                        ("-", Value::Int(n)) => Ok(Value::Int(-n)),
-                        ("-", Value::Float(n)) => Ok(Value::Float(-n)),
-                        ("not", Value::Bool(b)) => Ok(Value::Bool(!b)),
-                        ("not", Value::Int(n)) => Ok(Value::Bool(n == 0)),
-                        ("~", Value::Int(n)) => Ok(Value::Int(!n)),
-                        (_, v) => Err(Exception::new(ExceptionKind::TypeError, vec![format!("bad operand type for unary {}: '{}'", op, v.type_name())])),
-                    }
+            }
+            Expr::UnaryOp { op, expr } => {
+                let v = self.eval_inner(expr)?;
+                match (op.as_str(), v) {
+                    ("-", Value::Int(n)) => Ok(Value::Int(-n)),
+                    ("-", Value::Float(n)) => Ok(Value::Float(-n)),
+                    ("-", Value::Decimal(d)) => Ok(Value::Decimal(-d)),
+                    ("not", Value::Bool(b)) => Ok(Value::Bool(!b)),
+                    ("not", Value::Int(n)) => Ok(Value::Bool(n == 0)),
+                    ("~", Value::Int(n)) => Ok(Value::Int(!n)),
+                    (_, v) => raise(ExceptionKind::TypeError, vec![format!("bad operand type for unary {}: '{}'", op, v.type_name())]),
                 }
-                Expr::Assign { name, expr } => {
-                    // Support self.field assignment
-                    if let Some((obj_name, field_name)) = name.split_once('.') {
-                        if obj_name == "self" {
-                            let val = self.eval_inner(expr)?;
-                            // Update the field in the instance
-                            if let Some(Value::Instance { fields, .. }) = self.env.get_mut("self") {
-                                fields.insert(field_name.to_string(), val.clone());
-                                return Ok(val);
-                            } else {
-                                return Err(Exception::new(ExceptionKind::TypeError, vec!["'self' is not an instance".to_string()]));
-                            }
-                        }
-                    }
-                    if name == "True" || name == "False" || name == "None" || name == "__debug__" {
-                        Err(Exception::new(ExceptionKind::TypeError, vec!["Assignment to constant is not allowed".to_string()]))
-                    } else {
+            }
+            Expr::Assign { name, expr } => {
+                // Support self.field assignment
+                if let Some((obj_name, field_name)) = name.split_once('.') {
+                    if obj_name == "self" {
                         let val = self.eval_inner(expr)?;
-                        self.env.insert(name.clone(), val.clone());
-                        Ok(val)
+                        // Update the field in the instance
+                        if let Some(Value::Instance { fields, .. }) = self.env.get_mut("self") {
+                            fields.insert(field_name.to_string(), val.clone());
+                            return Ok(val);
+                        } else {
+                            return raise(ExceptionKind::TypeError, vec!["'self' is not an instance".to_string()]);
+                        }
                     }
                 }
-                Expr::Let { name, expr } => {
+                if name == "True" || name == "False" || name == "None" || name == "__debug__" {
+                    raise(ExceptionKind::TypeError, vec!["Assignment to constant is not allowed".to_string()])
+                } else {
                     let val = self.eval_inner(expr)?;
-                    self.env.insert(name.clone(), val.clone());
+                    self.env.insert(name.to_string(), val.clone());
                     Ok(val)
                 }
-                Expr::Const { name, expr } => {
-                    let val = self.eval_inner(expr)?;
-                    // For now, treat like let (no immutability enforcement yet)
-                    self.env.insert(name.clone(), val.clone());
-                    Ok(val)
+            }
+            Expr::Let { name, expr } => {
+                let val = self.eval_inner(expr)?;
+                self.env.insert(name.to_string(), val.clone());
+                Ok(val)
+            }
+            Expr::Const { name, expr } => {
+                let val = self.eval_inner(expr)?;
+                // For now, treat like let (no immutability enforcement yet)
+                self.env.insert(name.to_string(), val.clone());
+                Ok(val)
+            }
+            Expr::LetTyped { name, ty, expr } | Expr::ConstTyped { name, ty, expr } => {
+                let val = self.eval_inner(expr)?;
+                let actual = match &val {
+                    Value::Instance { class_name, .. } => class_name.as_str(),
+                    other => other.type_name(),
+                };
+                if actual != ty {
+                    return raise(ExceptionKind::TypeError, vec![format!("'{}' must be '{}', not '{}'", name, ty, actual)]);
                 }
-                Expr::Bool(b) => Ok(Value::Bool(*b)),
-                Expr::Null => Ok(Value::None),
-                Expr::Block(exprs) => {
-                    let mut last = Value::None;
-                    for e in exprs {
-                        last = self.eval_inner(e)?;
+                self.env.insert(name.to_string(), val.clone());
+                Ok(val)
+            }
+            Expr::Bool(b) => Ok(Value::Bool(*b)),
+            Expr::Null => Ok(Value::None),
+            Expr::Block(exprs) => {
+                let mut outcome = Ok(Value::None);
+                let mut deferred = Vec::new();
+                for e in exprs {
+                    if let Expr::Defer(action) = e.unlocated() {
+                        deferred.push(action);
+                        continue;
+                    }
+                    outcome = self.eval_inner(e);
+                    if outcome.is_err() {
+                        break;
                     }
-                    Ok(last)
                 }
-                Expr::If { cond, then_branch, else_branch } => {
-                    let cond_val = self.eval_inner(cond)?;
-                    let cond_bool = cond_val.is_truthy();
-                    if cond_bool {
-                        self.eval_inner(then_branch)
-                    } else if let Some(else_b) = else_branch {
-                        self.eval_inner(else_b)
-                    } else {
-                        Ok(Value::None)
+                // Most recently deferred first, even when the block is left early
+                for action in deferred.into_iter().rev() {
+                    outcome = self.clean_up(action, outcome);
+                }
+                outcome
+            }
+            // Outside a block there is nothing left to wait for
+            Expr::Defer(action) => self.clean_up(action, Ok(Value::None)),
+            Expr::If { cond, then_branch, else_branch } => {
+                let cond_val = self.eval_inner(cond)?;
+                let cond_bool = cond_val.is_truthy();
+                if cond_bool {
+                    self.eval_inner(then_branch)
+                } else if let Some(else_b) = else_branch {
+                    self.eval_inner(else_b)
+                } else {
+                    Ok(Value::None)
+                }
+            }
+            Expr::While { cond, body, label } => {
+                let mut last = Value::None;
+                loop {
+                    if !self.eval_inner(cond)?.is_truthy() {
+                        break;
+                    }
+                    match self.run_iteration(body, label)? {
+                        Iteration::Completed(v) => last = v,
+                        Iteration::Continued => {}
+                        Iteration::Broken => break,
                     }
                 }
-                Expr::While { cond, body } => {
-                    let mut last = Value::None;
-                    loop {
-                        if !self.eval_inner(cond)?.is_truthy() {
-                            break;
+                Ok(last)
+            }
+            Expr::Loop { body, label } => {
+                let mut last = Value::None;
+                loop {
+                    match self.run_iteration(body, label)? {
+                        Iteration::Completed(v) => last = v,
+                        Iteration::Continued => {}
+                        Iteration::Broken => break,
+                    }
+                }
+                Ok(last)
+            }
+            Expr::DoWhile { body, cond, label } => {
+                let mut last = Value::None;
+                loop {
+                    match self.run_iteration(body, label)? {
+                        Iteration::Completed(v) => last = v,
+                        Iteration::Continued => {}
+                        Iteration::Broken => break,
+                    }
+                    if !self.eval_inner(cond)?.is_truthy() {
+                        break;
+                    }
+                }
+                Ok(last)
+            }
+            Expr::For { vars, iter, body, label } => {
+                let items = containers::iter_values(&self.eval_inner(iter)?)?;
+                let mut last = Value::None;
+                for item in items {
+                    match vars.as_slice() {
+                        [var] => {
+                            self.env.insert(var.to_string(), item);
                         }
-                        match self.eval_inner(body) {
-                            Ok(v) => last = v,
-                            Err(exc) if exc.kind == ExceptionKind::Break => break,
-                            Err(exc) if exc.kind == ExceptionKind::Continue => continue,
-                            Err(exc) => return Err(exc),
+                        vars => {
+                            let values = containers::unpack(&item, vars.len())?;
+                            self.env.extend(vars.iter().map(Symbol::to_string).zip(values));
                         }
                     }
-                    Ok(last)
-                }
-                Expr::FnDef { name, params, body } => {
-                    self.functions.insert(name.clone(), (params.clone(), *body.clone()));
-                    Ok(Value::None)
+                    match self.run_iteration(body, label)? {
+                        Iteration::Completed(v) => last = v,
+                        Iteration::Continued => {}
+                        Iteration::Broken => break,
+                    }
                 }
-                Expr::Return(expr) => {
-                    let val = self.eval_inner(expr)?;
-                    return Err(Exception {
-                        kind: ExceptionKind::Return,
-                        args: vec![serde_json::to_string(&val).unwrap_or_default()],
-                        context: None,
-                        cause: None,
-                        suppress_context: false,
-                        notes: vec![],
-                    });
+                Ok(last)
+            }
+            Expr::FnDef { name, params, body } => {
+                self.functions.insert(name.to_string(), (params.clone(), body.clone()));
+                // A redefined function starts over without its decorators' caching
+                cache::lock(&self.caches).remove(name.as_str());
+                Ok(Value::None)
+            }
+            Expr::Decorated { decorators, def } => {
+                self.eval_inner(def)?;
+                let Expr::FnDef { name, .. } = def.as_ref() else {
+                    return raise(ExceptionKind::SyntaxError, vec!["decorators apply only to function definitions".to_string()]);
+                };
+                for decorator in decorators.iter().rev() {
+                    self.apply_decorator(name, decorator)?;
                 }
-                Expr::ClassDef { name, bases, body } => {
-                    let mut methods = HashMap::new();
-                    let mut fields = HashMap::new();
-                    
-                    for expr in body {
-                        match expr {
-                            Expr::FnDef { name: method_name, params, body } => {
-                                methods.insert(method_name.clone(), (params.clone(), *body.clone()));
-                            }
-                            Expr::Assign { name: field_name, expr } => {
-                                fields.insert(field_name.clone(), self.eval_inner(expr)?);
-                            }
-                            _ => {
-                                self.eval_inner(expr)?;
+                Ok(Value::None)
+            }
+            Expr::TryCatch { try_block, catch_var, catch_block, finally_block } => {
+                // SystemExit is caught like any exception, so a handler can veto
+                // quit()/exit() or re-throw it after cleaning up
+                let outcome = match (self.eval_inner(try_block), catch_block) {
+                    (Err(ControlFlow::Exception(caught)), Some(catch_block)) => {
+                        if let Some(name) = catch_var {
+                            self.env.insert(name.to_string(), Value::Exception(caught.clone()));
+                        }
+                        match self.eval_inner(catch_block) {
+                            // An error escaping the handler records the one it was handling
+                            Err(ControlFlow::Exception(mut exc)) if exc.context.is_none() && exc != caught => {
+                                exc.context = Some(Box::new(caught));
+                                Err(exc.into())
                             }
+                            other => other,
                         }
                     }
-                    
-                    let base = if !bases.is_empty() {
-                        if let Expr::Ident(base_name) = &bases[0] {
-                            Some(base_name.clone())
-                        } else {
-                            None
+                    (outcome, _) => outcome,
+                };
+                match finally_block {
+                    Some(finally_block) => self.clean_up(finally_block, outcome),
+                    None => outcome,
+                }
+            }
+            Expr::Throw { value, cause } => {
+                let mut exc = match self.eval_inner(value)? {
+                    Value::Exception(exc) => exc,
+                    other => Exception::new(ExceptionKind::Exception, vec![other.to_display_string()]),
+                };
+                if let Some(cause) = cause {
+                    match self.eval_inner(cause)? {
+                        Value::Exception(cause) => exc = exc.with_cause(cause),
+                        // `throw X from None` hides the exception being handled
+                        Value::None => exc.suppress_context = true,
+                        other => return raise(ExceptionKind::TypeError, vec![format!("exception causes must be exceptions or None, not '{}'", other.type_name())]),
+                    }
+                }
+                Err(exc.into())
+            }
+            Expr::Return(expr) => Err(ControlFlow::Return(Box::new(self.eval_inner(expr)?))),
+            Expr::Break(label) => Err(ControlFlow::Break(*label)),
+            Expr::Continue(label) => Err(ControlFlow::Continue(*label)),
+            Expr::ClassDef { name, bases, body } => {
+                let mut methods = HashMap::new();
+                let mut fields = HashMap::new();
+                
+                for expr in body {
+                    let statement = expr.unlocated();
+                    match statement {
+                        Expr::FnDef { name: method_name, params, body } => {
+                            methods.insert(method_name.to_string(), (params.clone(), body.clone()));
+                        }
+                        // Kept as the method's body, so an override replaces it
+                        Expr::AbstractFn { name: method_name, params } => {
+                            methods.insert(method_name.to_string(), (params.clone(), Arc::new(statement.clone())));
+                        }
+                        Expr::Assign { name: field_name, expr } => {
+                            fields.insert(field_name.to_string(), self.eval_inner(expr)?);
                         }
+                        _ => {
+                            self.eval_inner(expr)?;
+                        }
+                    }
+                }
+                
+                let base = if !bases.is_empty() {
+                    if let Expr::Ident(base_name) = &bases[0] {
+                        Some(base_name.to_string())
                     } else {
                         None
-                    };
-                    
-                    let class_value = Value::Class {
-                        name: name.clone(),
-                        methods,
-                        fields,
-                        base,
-                    };
-                    
-                    self.env.insert(name.clone(), class_value);
-                    Ok(Value::None)
+                    }
+                } else {
+                    None
+                };
+                
+                let class_value = Value::Class {
+                    name: name.to_string(),
+                    methods,
+                    fields,
+                    base,
+                };
+                
+                self.env.insert(name.to_string(), class_value);
+                Ok(Value::None)
+            }
+            Expr::AbstractFn { name, .. } => raise(ExceptionKind::NotImplementedError, vec![format!("abstract method '{}' is not implemented", name)]),
+            Expr::ClassInit { class_name, args } => {
+                if !matches!(self.env.get(class_name.as_str()), Some(Value::Class { .. })) {
+                    return raise(ExceptionKind::NameError, vec![format!("class '{}' is not defined", class_name)]);
                 }
-                Expr::ClassInit { class_name, args } => {
-                    let class_val = self.env.get(class_name.as_str()).cloned();
-                    if let Some(Value::Class { .. }) = class_val {
-                        let (methods, fields) = self.collect_class_hierarchy(class_name);
-                        let mut instance_fields = fields;
-                        if let Some(init_method) = methods.get("__init__") {
-                            let (params, body) = init_method;
-                            let mut new_env = self.env.clone();
-                            for (param, arg) in params.iter().zip(args.iter()) {
-                                new_env.insert(param.clone(), self.eval_inner(arg)?);
-                            }
-                            let mut sub_interpreter = Interpreter {
-                                env: new_env,
-                                functions: self.functions.clone(),
-                                profile: self.profile.clone(),
-                            };
-                            sub_interpreter.eval(body)?;
-                        }
-                        Ok(Value::Instance {
-                            class_name: class_name.clone(),
-                            fields: instance_fields,
-                        })
-                    } else {
-                        Err(Exception::new(ExceptionKind::NameError, vec![format!("class '{}' is not defined", class_name)]))
+                let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                Ok(self.instantiate(class_name, evaluated_args)?)
+            }
+            Expr::MethodCall { object, method, args } => {
+                if let Expr::Ident(name) = object.as_ref() {
+                    if !self.env.contains_key(name.as_str()) && cache::lock(&self.caches).contains_key(name.as_str()) {
+                        return self.call_cache_method(name, method, args);
                     }
                 }
-                Expr::MethodCall { object, method, args } => {
-                    let obj = self.eval_inner(object)?;
-                    if let Value::Instance { class_name, fields } = &obj {
-                        let (methods, _) = self.collect_class_hierarchy(class_name);
-                        if let Some((params, body)) = methods.get(method) {
-                            let mut new_env = self.env.clone();
-                            for (field_name, field_value) in fields {
-                                new_env.insert(field_name.clone(), field_value.clone());
-                            }
-                            // Bind 'self' to the instance
-                            new_env.insert("self".to_string(), obj.clone());
-                            for (param, arg) in params.iter().zip(args.iter()) {
-                                new_env.insert(param.clone(), self.eval_inner(arg)?);
-                            }
-                            let mut sub_interpreter = Interpreter {
-                                env: new_env,
-                                functions: self.functions.clone(),
-                                profile: self.profile.clone(),
-                            };
-                            match sub_interpreter.eval(body) {
-                                Err(exc) if exc.kind == ExceptionKind::Return => {
-                                    if let Some(arg) = exc.args.get(0) {
-                                        let val: Value = serde_json::from_str(arg).unwrap_or(Value::None);
-                                        Ok(val)
-                                    } else {
-                                        Ok(Value::None)
-                                    }
-                                }
-                                other => other,
-                            }
-                        } else {
-                            Err(Exception::new(ExceptionKind::AttributeError, vec![format!("'{}' object has no attribute '{}'", class_name, method)]))
-                        }
-                    } else {
-                        Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object has no attribute '{}'", obj.type_name(), method)]))
+                let mut obj = self.eval_inner(object)?;
+                if let Value::Dict(members) = &obj {
+                    if let Some(func) = members.get(&Value::Str(method.to_string())).and_then(native::interpreter_function) {
+                        return self.call_interpreter_function(func, args);
                     }
                 }
-                Expr::FieldAccess { object, field } => {
-                    let obj = self.eval_inner(object)?;
-                    if let Value::Instance { class_name, fields } = &obj {
-                        if let Some(value) = fields.get(field) {
-                            Ok(value.clone())
-                        } else {
-                            // Look up in class hierarchy
-                            let (_, class_fields) = self.collect_class_hierarchy(class_name);
-                            if let Some(val) = class_fields.get(field) {
-                                Ok(val.clone())
-                            } else {
-                                Err(Exception::new(ExceptionKind::AttributeError, vec![format!("'{}' object has no attribute '{}'", obj.type_name(), field)]))
-                            }
+                let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                let result = self.call_method(&mut obj, method, evaluated_args);
+                // Builtin methods like `items.append(x)` and class methods setting
+                // `self` fields mutate the receiver
+                self.store_back(object, obj);
+                Ok(result?)
+            }
+            Expr::FieldAccess { object, field } => {
+                let obj = self.eval_inner(object)?;
+                Ok(self.get_attr(&obj, field)?)
+            }
+            // Test blocks only run under `stel test`, which picks them out of the program
+            Expr::Test { .. } => Ok(Value::None),
+            Expr::With { resource, alias, body } => {
+                let resource = self.eval_inner(resource)?;
+                let entered = self.enter_context(&resource)?;
+                if let Some(name) = alias {
+                    self.env.insert(name.to_string(), entered);
+                }
+                let outcome = self.eval_inner(body);
+                self.exit_context(&resource, outcome)
+            }
+            Expr::Import(module_name) => {
+                // Standard library modules are built in; anything else is a
+                // source file on the module search path
+                let module_value = match stdlib::module(module_name) {
+                    Some(mut module) => {
+                        if module_name == "sys" {
+                            stdlib::sys::register_streams(&mut module, &self.stdout, &self.capabilities);
                         }
-                    } else {
-                        Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object has no attribute '{}'", obj.type_name(), field)]))
+                        module.into_value(None)
                     }
+                    None => self.import_source(module_name)?,
+                };
+                self.env.insert(module_name.clone(), module_value);
+                Ok(Value::None)
+            }
+            Expr::ImportNative(module_name) => {
+                if !self.capabilities.native_modules {
+                    return raise(ExceptionKind::PermissionError, vec![format!("import native \"{}\" is disabled in this sandbox", module_name)]);
                 }
-                Expr::Import(module_name) => {
-                    // For now, just create a placeholder module
-                    // In a real implementation, this would load the module from file
-                    let module_value = Value::Dict(HashMap::new());
-                    self.env.insert(module_name.clone(), module_value);
-                    Ok(Value::None)
-                }
-                Expr::GetAttr { object, name } => {
-                    let obj = self.eval_inner(object)?;
-                    Ok(Value::BuiltinMethod {
-                        object: Box::new(obj),
-                        method_name: name.clone(),
-                    })
+                let module = native::load(module_name)?;
+                self.env.insert(native::binding_name(module_name), module);
+                Ok(Value::None)
+            }
+            Expr::GetAttr { object, name } => {
+                let obj = self.eval_inner(object)?;
+                Ok(self.get_attr(&obj, name)?)
+            }
+            Expr::FnCall { callable, args } => {
+                // Evaluate the callable first. Bare names that aren't bound in the
+                // environment refer to builtins or user-defined functions by name.
+                // quit and exit are bound to help strings but are builtins when called.
+                let callable_val = match callable.as_ref() {
+                    Expr::Ident(name) if !self.env.contains_key(name.as_str()) || name == "quit" || name == "exit" => Value::Str(name.to_string()),
+                    other => self.eval_inner(other)?,
+                };
+                
+                // Only print() and input() take keyword arguments so far
+                if let Some(Expr::KeywordArg { name: keyword, .. }) = args.iter().find(|arg| matches!(arg, Expr::KeywordArg { .. })) {
+                    let func = match &callable_val {
+                        Value::Str(name) if name == "print" || name == "input" => None,
+                        Value::Str(name) | Value::NativeFunction { name, .. } => Some(name.clone()),
+                        other => Some(other.type_name().to_string()),
+                    };
+                    if let Some(func) = func {
+                        return raise(ExceptionKind::TypeError, vec![format!("{}() got an unexpected keyword argument '{}'", func, keyword)]);
+                    }
                 }
-                Expr::FnCall { callable, args } => {
-                    // Evaluate the callable first
-                    let callable_val = self.eval_inner(callable)?;
-                    
-                    // Handle built-in functions (e.g., print, input)
-                    if let Value::Str(name) = &callable_val {
-                        match name.as_str() {
-                            "print" => {
-                                let mut output = String::new();
-                                for (i, arg) in args.iter().enumerate() {
-                                    output.push_str(&self.eval_inner(arg)?.to_display_string());
-                                    if i < args.len() - 1 {
-                                        output.push(' ');
-                                    }
-                                }
-                                println!("{}", output);
-                                return Ok(Value::None);
+
+                // Handle built-in functions (e.g., print, input)
+                if let Value::Str(name) = &callable_val {
+                    // open() is checked once its path is known, since directories may be allowed
+                    if name != "open" {
+                        self.capabilities.check(name)?;
+                    }
+                    self.record_call(name);
+                    match name.as_str() {
+                        "print" => return self.call_print(args),
+                        "input" => return self.call_input(args),
+                        "open" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            let path = match evaluated_args.first() {
+                                Some(Value::Str(p)) => p.clone(),
+                                _ => return raise(ExceptionKind::TypeError, vec!["open() path must be a string".to_string()]),
+                            };
+                            let mode = match evaluated_args.get(1) {
+                                Some(Value::Str(m)) => m.clone(),
+                                None => "r".to_string(),
+                                _ => return raise(ExceptionKind::TypeError, vec!["open() mode must be a string".to_string()]),
+                            };
+                            self.capabilities.check_open(&path, &mode)?;
+                            let handle = fileio::open(&path, &mode)?;
+                            return Ok(Value::File { path, mode, handle });
+                        }
+                        "spawn" => {
+                            // The first argument names the function to run; remaining arguments are passed to it
+                            let func_name = match args.first() {
+                                Some(Expr::Ident(n)) if !self.env.contains_key(n.as_str()) => n.to_string(),
+                                Some(arg) => match self.eval_inner(arg)? {
+                                    Value::Str(n) => n,
+                                    other => return raise(ExceptionKind::TypeError, vec![format!("spawn() argument must be a function, not '{}'", other.type_name())]),
+                                },
+                                None => return raise(ExceptionKind::TypeError, vec!["spawn() missing required argument: 'fn'".to_string()]),
+                            };
+                            let evaluated_args = args[1..].iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            let handle = threads::spawn(self.env.clone(), self.functions.clone(), self.capabilities.clone(), self.stdout.clone(), self.stats.clone(), self.caches.clone(), self.recursion_limit.get(), self.env_vars.clone(), &func_name, evaluated_args)?;
+                            return Ok(Value::Thread { handle });
+                        }
+                        "atexit" => {
+                            // Same calling convention as spawn(): a function name, then its arguments
+                            let func_name = match args.first() {
+                                Some(Expr::Ident(n)) if !self.env.contains_key(n.as_str()) => n.to_string(),
+                                Some(arg) => match self.eval_inner(arg)? {
+                                    Value::Str(n) => n,
+                                    other => return raise(ExceptionKind::TypeError, vec![format!("atexit() argument must be a function, not '{}'", other.type_name())]),
+                                },
+                                None => return raise(ExceptionKind::TypeError, vec!["atexit() missing required argument: 'fn'".to_string()]),
+                            };
+                            let Some((params, _)) = self.functions.get(&func_name) else {
+                                return Err(self.name_error(&func_name).into());
+                            };
+                            if params.len() != args.len() - 1 {
+                                return raise(ExceptionKind::TypeError, vec![format!("{}() takes {} arguments but {} were given", func_name, params.len(), args.len() - 1)]);
                             }
-                            "input" => {
-                                let prompt = if !args.is_empty() {
-                                    self.eval_inner(&args[0])?.to_display_string()
-                                } else {
-                                    "".to_string()
-                                };
-                                print!("{}", prompt);
-                                use std::io::{self, Write};
-                                io::stdout().flush().map_err(|e| Exception::new(ExceptionKind::OSError, vec![e.to_string()]))?;
-                                let mut input = String::new();
-                                io::stdin().read_line(&mut input).map_err(|e| Exception::new(ExceptionKind::OSError, vec![e.to_string()]))?;
-                                return Ok(Value::Str(input.trim_end_matches(&['\r', '\n'][..]).to_string()));
+                            let evaluated_args = args[1..].iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            self.exit_handlers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push((func_name, evaluated_args));
+                            return Ok(Value::None);
+                        }
+                        "quit" | "exit" => {
+                            let code = match args.first() {
+                                Some(arg) => self.eval_inner(arg)?,
+                                None => Value::Int(0),
+                            };
+                            return raise(ExceptionKind::SystemExit, vec![code.to_display_string()]);
+                        }
+                        "chan" => {
+                            if !args.is_empty() {
+                                return raise(ExceptionKind::TypeError, vec!["chan() takes no arguments".to_string()]);
                             }
-                            _ => { /* continue to check for bytes/bytearray methods or user-defined functions */ }
+                            return Ok(Value::Channel { handle: ChannelHandle::new() });
                         }
-                    }
-                    
-                    // Handle built-in method calls
-                    if let Value::BuiltinMethod { object, method_name } = callable_val {
-                        let evaluated_args: Vec<Value> = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, Exception>>()?;
-                        
-                        match method_name.as_str() {
-                            // String methods
-                            "len" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Int(s.len() as i64)); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "upper" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Str(s.to_uppercase())); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "lower" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Str(s.to_lowercase())); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "strip" => {
-                                if let Value::Str(s) = *object { 
-                                    // Handle escape sequences by converting them to actual characters
-                                    let s = s.replace("\\n", "\n").replace("\\t", "\t").replace("\\r", "\r");
-                                    return Ok(Value::Str(s.trim().to_string())); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "split" => {
-                                if let Value::Str(s) = *object {
-                                    let sep = if !evaluated_args.is_empty() {
-                                        if let Value::Str(sep_str) = &evaluated_args[0] {
-                                            sep_str.as_str()
-                                        } else {
-                                            return Err(Exception::new(ExceptionKind::TypeError, vec!["Split separator must be a string".to_string()]));
-                                        }
-                                    } else {
-                                        " "
-                                    };
-                                    let parts: Vec<Value> = if sep == " " {
-                                        s.split_whitespace().map(|part| Value::Str(part.to_string())).collect()
-                                    } else {
-                                        s.split(sep).map(|part| Value::Str(part.to_string())).collect()
-                                    };
-                                    return Ok(Value::List(parts));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "join" => {
-                                if let Value::Str(sep) = *object {
-                                    if let Some(Value::List(items)) = evaluated_args.get(0) {
-                                        let strings: Vec<String> = items.iter().map(|item| item.to_display_string()).collect();
-                                        return Ok(Value::Str(strings.join(&sep)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Join expects a list argument".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "replace" => {
-                                if let Value::Str(s) = *object {
-                                    if evaluated_args.len() >= 2 {
-                                        let old = if let Value::Str(old_str) = &evaluated_args[0] { old_str } else {
-                                            return Err(Exception::new(ExceptionKind::TypeError, vec!["Replace arguments must be strings".to_string()]));
-                                        };
-                                        let new = if let Value::Str(new_str) = &evaluated_args[1] { new_str } else {
-                                            return Err(Exception::new(ExceptionKind::TypeError, vec!["Replace arguments must be strings".to_string()]));
-                                        };
-                                        let count = if evaluated_args.len() > 2 {
-                                            if let Value::Int(count_val) = evaluated_args[2] { count_val as usize } else {
-                                                return Err(Exception::new(ExceptionKind::TypeError, vec!["Replace count must be an integer".to_string()]));
-                                            }
-                                        } else {
-                                            usize::MAX
-                                        };
-                                        let result = if count == usize::MAX {
-                                            s.replace(old, new)
-                                        } else {
-                                            s.replacen(old, new, count)
-                                        };
-                                        return Ok(Value::Str(result));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Replace expects at least 2 arguments".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "find" => {
-                                if let Value::Str(s) = *object {
-                                    if let Some(Value::Str(sub)) = evaluated_args.get(0) {
-                                        match s.find(sub) {
-                                            Some(pos) => return Ok(Value::Int(pos as i64)),
-                                            None => return Ok(Value::Int(-1))
-                                        }
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Find expects a string argument".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "count" => {
-                                if let Value::Str(s) = *object {
-                                    if let Some(Value::Str(sub)) = evaluated_args.get(0) {
-                                        let count = s.matches(sub).count();
-                                        return Ok(Value::Int(count as i64));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Count expects a string argument".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "startswith" => {
-                                if let Value::Str(s) = *object {
-                                    if let Some(Value::Str(prefix)) = evaluated_args.get(0) {
-                                        return Ok(Value::Bool(s.starts_with(prefix)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Startswith expects a string argument".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "endswith" => {
-                                if let Value::Str(s) = *object {
-                                    if let Some(Value::Str(suffix)) = evaluated_args.get(0) {
-                                        return Ok(Value::Bool(s.ends_with(suffix)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["Endswith expects a string argument".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "isalnum" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_alphanumeric()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "isalpha" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_alphabetic()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "isdigit" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "islower" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_lowercase()) && s.chars().any(|c| c.is_alphabetic()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "isupper" => {
-                                if let Value::Str(s) = *object { 
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_uppercase()) && s.chars().any(|c| c.is_alphabetic()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "isspace" => {
-                                if let Value::Str(s) = *object { 
-                                    // Handle escape sequences by converting them to actual characters
-                                    let s = s.replace("\\n", "\n").replace("\\t", "\t").replace("\\r", "\r");
-                                    return Ok(Value::Bool(!s.is_empty() && s.chars().all(|c| c.is_whitespace()))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            "istitle" => {
-                                if let Value::Str(s) = *object { 
-                                    if s.is_empty() {
-                                        return Ok(Value::Bool(false));
-                                    }
-                                    // Check if each word starts with uppercase and the rest are lowercase
-                                    let words: Vec<&str> = s.split_whitespace().collect();
-                                    if words.is_empty() {
-                                        return Ok(Value::Bool(false));
-                                    }
-                                    // For istitle, we need at least one word and all words must be title case
-                                    // But according to the test, "Hello world" should be true
-                                    // So we check that the first word is title case and subsequent words are either title case or lowercase
-                                    if words.len() == 1 {
-                                        // Single word: must be title case
-                                        let word = words[0];
-                                        let mut chars = word.chars();
-                                        return Ok(Value::Bool(chars.next().map_or(false, |c| c.is_uppercase()) &&
-                                            chars.all(|c| c.is_lowercase())));
-                                    } else {
-                                        // Multiple words: first must be title case, others can be title case or lowercase
-                                        let first_word = words[0];
-                                        let mut first_chars = first_word.chars();
-                                        let first_is_title = first_chars.next().map_or(false, |c| c.is_uppercase()) &&
-                                            first_chars.all(|c| c.is_lowercase());
-                                        
-                                        if !first_is_title {
-                                            return Ok(Value::Bool(false));
-                                        }
-                                        
-                                        // Check that other words are either title case or lowercase
-                                        return Ok(Value::Bool(words[1..].iter().all(|word| {
-                                            let mut chars = word.chars();
-                                            chars.next().map_or(false, |c| c.is_uppercase() || c.is_lowercase()) &&
-                                            chars.all(|c| c.is_lowercase())
-                                        })));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected string object".to_string()])); 
-                                }
-                            },
-                            // List methods
-                            "list_append" => {
-                                if let Value::List(mut l) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["append() takes exactly one argument".to_string()]));
-                                    }
-                                    l.push(evaluated_args[0].clone());
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_pop" => {
-                                if let Value::List(mut l) = *object {
-                                    if !evaluated_args.is_empty() {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["pop() takes no arguments".to_string()]));
-                                    }
-                                    return l.pop().ok_or_else(|| Exception::new(ExceptionKind::IndexError, vec!["pop from empty list".to_string()]));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_extend" => {
-                                if let Value::List(mut l) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["extend() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::List(other) = &evaluated_args[0] {
-                                        l.extend(other.clone());
-                                        return Ok(Value::None);
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["extend() argument must be a list".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_insert" => {
-                                if let Value::List(mut l) = *object {
-                                    if evaluated_args.len() != 2 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["insert() takes exactly two arguments".to_string()]));
-                                    }
-                                    if let Value::Int(index) = &evaluated_args[0] {
-                                        let index = if *index < 0 { 
-                                            (l.len() as i64 + *index).max(0) as usize 
-                                        } else { 
-                                            (*index as usize).min(l.len()) 
-                                        };
-                                        l.insert(index, evaluated_args[1].clone());
-                                        return Ok(Value::None);
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["insert() index must be an integer".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_remove" => {
-                                if let Value::List(mut l) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["remove() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Some(pos) = l.iter().position(|x| x == &evaluated_args[0]) {
-                                        l.remove(pos);
-                                        return Ok(Value::None);
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::ValueError, vec!["list.remove(x): x not in list".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_clear" => {
-                                if let Value::List(mut l) = *object {
-                                    l.clear();
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_copy" => {
-                                if let Value::List(l) = *object {
-                                    return Ok(Value::List(l.clone()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_index" => {
-                                if let Value::List(l) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["index() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Some(pos) = l.iter().position(|x| x == &evaluated_args[0]) {
-                                        return Ok(Value::Int(pos as i64));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::ValueError, vec!["list.index(x): x not in list".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_count" => {
-                                if let Value::List(l) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["count() takes exactly one argument".to_string()]));
-                                    }
-                                    let count = l.iter().filter(|x| *x == &evaluated_args[0]).count();
-                                    return Ok(Value::Int(count as i64));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_reverse" => {
-                                if let Value::List(mut l) = *object {
-                                    l.reverse();
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
-                                }
-                            },
-                            "list_sort" => {
-                                if let Value::List(mut l) = *object {
-                                    l.sort_by(|a, b| a.to_display_string().cmp(&b.to_display_string()));
+                        "send" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            match evaluated_args.as_slice() {
+                                [Value::Channel { handle }, value] => {
+                                    handle.send(value.clone())?;
                                     return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected list object".to_string()])); 
                                 }
-                            },
-                            // Dict methods
-                            "dict_keys" => {
-                                if let Value::Dict(d) = *object {
-                                    return Ok(Value::List(d.keys().cloned().collect()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_values" => {
-                                if let Value::Dict(d) = *object {
-                                    return Ok(Value::List(d.values().cloned().collect()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_items" => {
-                                if let Value::Dict(d) = *object {
-                                    let items: Vec<Value> = d.iter().map(|(k, v)| Value::Tuple(vec![k.clone(), v.clone()])).collect();
-                                    return Ok(Value::List(items));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_get" => {
-                                if let Value::Dict(d) = *object {
-                                    if evaluated_args.len() < 1 || evaluated_args.len() > 2 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["get() takes 1 or 2 arguments".to_string()]));
-                                    }
-                                    let key = &evaluated_args[0];
-                                    if let Some(value) = d.get(key) {
-                                        return Ok(value.clone());
-                                    } else if evaluated_args.len() == 2 {
-                                        return Ok(evaluated_args[1].clone());
-                                    } else {
-                                        return Ok(Value::None);
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_pop" => {
-                                if let Value::Dict(mut d) = *object {
-                                    if evaluated_args.len() < 1 || evaluated_args.len() > 2 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["pop() takes 1 or 2 arguments".to_string()]));
-                                    }
-                                    let key = &evaluated_args[0];
-                                    if let Some(value) = d.remove(key) {
-                                        return Ok(value);
-                                    } else if evaluated_args.len() == 2 {
-                                        return Ok(evaluated_args[1].clone());
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::KeyError, vec![key.to_display_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_update" => {
-                                if let Value::Dict(mut d) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["update() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Dict(other) = &evaluated_args[0] {
-                                        d.extend(other.clone());
-                                        return Ok(Value::None);
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["update() argument must be a dictionary".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_clear" => {
-                                if let Value::Dict(mut d) = *object {
-                                    d.clear();
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            "dict_copy" => {
-                                if let Value::Dict(d) = *object {
-                                    return Ok(Value::Dict(d.clone()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected dict object".to_string()])); 
-                                }
-                            },
-                            // Set methods
-                            "set_add" => {
-                                if let Value::Set(mut s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["add() takes exactly one argument".to_string()]));
-                                    }
-                                    s.insert(evaluated_args[0].clone());
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_remove" => {
-                                if let Value::Set(mut s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["remove() takes exactly one argument".to_string()]));
-                                    }
-                                    if s.remove(&evaluated_args[0]) {
-                                        return Ok(Value::None);
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::KeyError, vec![evaluated_args[0].to_display_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_discard" => {
-                                if let Value::Set(mut s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["discard() takes exactly one argument".to_string()]));
-                                    }
-                                    s.remove(&evaluated_args[0]);
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_pop" => {
-                                if let Value::Set(mut s) = *object {
-                                    if !evaluated_args.is_empty() {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["pop() takes no arguments".to_string()]));
-                                    }
-                                    return s.drain().next().ok_or_else(|| Exception::new(ExceptionKind::KeyError, vec!["pop from an empty set".to_string()]));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_clear" => {
-                                if let Value::Set(mut s) = *object {
-                                    s.clear();
-                                    return Ok(Value::None);
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_union" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["union() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Set(s.union(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["union() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_intersection" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["intersection() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Set(s.intersection(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["intersection() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_difference" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["difference() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Set(s.difference(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["difference() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_symmetric_difference" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["symmetric_difference() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Set(s.symmetric_difference(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["symmetric_difference() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_issubset" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issubset() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_subset(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issubset() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_issuperset" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issuperset() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_superset(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issuperset() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_isdisjoint" => {
-                                if let Value::Set(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["isdisjoint() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Set(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_disjoint(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["isdisjoint() argument must be a set".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            "set_copy" => {
-                                if let Value::Set(s) = *object {
-                                    return Ok(Value::Set(s.clone()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected set object".to_string()])); 
-                                }
-                            },
-                            // FrozenSet methods (similar to set, but immutable)
-                            "frozenset_union" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["union() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::FrozenSet(s.union(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["union() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_intersection" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["intersection() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::FrozenSet(s.intersection(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["intersection() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_difference" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["difference() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::FrozenSet(s.difference(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["difference() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_symmetric_difference" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["symmetric_difference() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::FrozenSet(s.symmetric_difference(other).cloned().collect()));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["symmetric_difference() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_issubset" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issubset() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_subset(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issubset() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_issuperset" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issuperset() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_superset(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["issuperset() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_isdisjoint" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["isdisjoint() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::FrozenSet(other) = &evaluated_args[0] {
-                                        return Ok(Value::Bool(s.is_disjoint(other)));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["isdisjoint() argument must be a frozenset".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            "frozenset_copy" => {
-                                if let Value::FrozenSet(s) = *object {
-                                    return Ok(Value::FrozenSet(s.clone()));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected frozenset object".to_string()])); 
-                                }
-                            },
-                            // Bytes methods
-                            "bytes_len" => {
-                                if let Value::Bytes(b) = *object { 
-                                    return Ok(Value::Int(b.len() as i64)); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytes object".to_string()])); 
-                                }
-                            },
-                            "bytes_hex" => {
-                                if let Value::Bytes(b) = *object { 
-                                    return Ok(Value::Str(hex::encode(b))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytes object".to_string()])); 
-                                }
-                            },
-                            "bytes_decode" => {
-                                if let Value::Bytes(b) = *object {
-                                    let encoding = if evaluated_args.is_empty() {
-                                        "utf-8".to_string()
-                                    } else if let Value::Str(e) = &evaluated_args[0] {
-                                        e.clone()
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["decode() encoding must be string".to_string()]));
-                                    };
-                                    match encoding.as_str() {
-                                        "utf-8" => {
-                                            return String::from_utf8(b).map_or_else(
-                                                |e| Err(Exception::new(ExceptionKind::UnicodeDecodeError, vec![format!("'utf-8' codec can't decode byte: {}", e)])),
-                                                |s| Ok(Value::Str(s))
-                                            );
-                                        },
-                                        _ => return Err(Exception::new(ExceptionKind::Exception, vec![format!("unknown encoding: {}", encoding)])),
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytes object".to_string()])); 
-                                }
-                            },
-                            // ByteArray methods
-                            "bytearray_len" => {
-                                if let Value::ByteArray(b) = *object { 
-                                    return Ok(Value::Int(b.len() as i64)); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytearray object".to_string()])); 
-                                }
-                            },
-                            "bytearray_hex" => {
-                                if let Value::ByteArray(b) = *object { 
-                                    return Ok(Value::Str(hex::encode(b))); 
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytearray object".to_string()])); 
-                                }
-                            },
-                            "bytearray_decode" => {
-                                if let Value::ByteArray(b) = *object {
-                                    let encoding = if evaluated_args.is_empty() {
-                                        "utf-8".to_string()
-                                    } else if let Value::Str(e) = &evaluated_args[0] {
-                                        e.clone()
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["decode() encoding must be string".to_string()]));
-                                    };
-                                    match encoding.as_str() {
-                                        "utf-8" => {
-                                            return String::from_utf8(b).map_or_else(
-                                                |e| Err(Exception::new(ExceptionKind::UnicodeDecodeError, vec![format!("'utf-8' codec can't decode byte: {}", e)])),
-                                                |s| Ok(Value::Str(s))
-                                            );
-                                        },
-                                        _ => return Err(Exception::new(ExceptionKind::Exception, vec![format!("unknown encoding: {}", encoding)])),
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytearray object".to_string()])); 
-                                }
-                            },
-                            "bytearray_append" => {
-                                if let Value::ByteArray(mut b) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["append() takes exactly one argument".to_string()]));
-                                    }
-                                    if let Value::Int(byte_val) = &evaluated_args[0] {
-                                        if *byte_val >= 0 && *byte_val <= 255 {
-                                            b.push(*byte_val as u8);
-                                            return Ok(Value::None);
-                                        } else {
-                                            return Err(Exception::new(ExceptionKind::ValueError, vec!["byte must be in range(0, 256)".to_string()]));
-                                        }
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["an integer is required (got type {})".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytearray object".to_string()])); 
-                                }
-                            },
-                            "bytearray_pop" => {
-                                if let Value::ByteArray(mut b) = *object {
-                                    if !evaluated_args.is_empty() {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["pop() takes no arguments".to_string()]));
-                                    }
-                                    return b.pop().map_or(Err(Exception::new(ExceptionKind::IndexError, vec!["pop from empty bytearray".to_string()])), |byte| Ok(Value::Int(byte as i64)));
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected bytearray object".to_string()])); 
-                                }
-                            },
-                            // Tuple methods
-                            "tuple_count" => {
-                                if let Value::Tuple(t) = *object {
-                                    return Ok(Value::Int(t.iter().filter(|x| **x == evaluated_args[0]).count() as i64));
-                                } else {
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected tuple object".to_string()]));
-                                }
-                            },
-                            "tuple_index" => {
-                                if let Value::Tuple(t) = *object {
-                                    if evaluated_args.len() != 1 {
-                                        return Err(Exception::new(ExceptionKind::TypeError, vec!["index() takes exactly one argument".to_string()]));
-                                    }
-                                    let value_to_find = &evaluated_args[0];
-                                    if let Some(pos) = t.iter().position(|x| x == value_to_find) {
-                                        return Ok(Value::Int(pos as i64));
-                                    } else {
-                                        return Err(Exception::new(ExceptionKind::ValueError, vec!["'{}' is not in tuple".to_string()]));
-                                    }
-                                } else { 
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec!["Expected tuple object".to_string()])); 
-                                }
-                            },
-                            _ => return Err(Exception::new(ExceptionKind::AttributeError, vec![format!("Unknown builtin method: {}", method_name)])),
+                                [other, _] => return raise(ExceptionKind::TypeError, vec![format!("send() expects a channel, not '{}'", other.type_name())]),
+                                _ => return raise(ExceptionKind::TypeError, vec!["send() takes exactly two arguments".to_string()]),
+                            }
+                        }
+                        "recv" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            match evaluated_args.as_slice() {
+                                [Value::Channel { handle }] => return Ok(handle.recv()?),
+                                [other] => return raise(ExceptionKind::TypeError, vec![format!("recv() expects a channel, not '{}'", other.type_name())]),
+                                _ => return raise(ExceptionKind::TypeError, vec!["recv() takes exactly one argument".to_string()]),
+                            }
+                        }
+                        "join" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            match evaluated_args.as_slice() {
+                                [Value::Thread { handle }] => return Ok(handle.join()?),
+                                [other] => return raise(ExceptionKind::TypeError, vec![format!("join() expects a thread, not '{}'", other.type_name())]),
+                                _ => return raise(ExceptionKind::TypeError, vec!["join() takes exactly one argument".to_string()]),
+                            }
+                        }
+                        "copy" | "deepcopy" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return match evaluated_args.as_slice() {
+                                [value] if name == "copy" => Ok(value.clone()),
+                                [value] => Ok(Self::deep_copy(value)?),
+                                _ => raise(ExceptionKind::TypeError, vec![format!("{}() takes exactly one argument ({} given)", name, evaluated_args.len())]),
+                            };
                         }
-                    } else {
-                        // Handle user-defined function calls
-                        if let Value::Str(func_name) = &callable_val {
-                            if let Some((params, body)) = self.functions.get(func_name) {
-                                let params = params.clone();
-                                let body = body.clone();
-                                
-                                // Check argument count
-                                if args.len() != params.len() {
-                                    return Err(Exception::new(ExceptionKind::TypeError, vec![
-                                        format!("{}() takes {} arguments but {} were given", 
-                                            func_name, params.len(), args.len())
-                                    ]));
+                        "str" if !self.functions.contains_key("str") => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return match evaluated_args.as_slice() {
+                                [] => Ok(Value::Str(String::new())),
+                                [value] => Ok(Value::Str(self.display(value)?)),
+                                _ => raise(ExceptionKind::TypeError, vec![format!("str() takes at most one argument ({} given)", evaluated_args.len())]),
+                            };
+                        }
+                        "list" | "tuple" | "set" | "frozenset" | "dict" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return Ok(containers::construct(name, &evaluated_args)?);
+                        }
+                        builtin if builtins::NAMES.contains(&builtin) && !self.functions.contains_key(builtin) => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return Ok(builtins::call(builtin, &evaluated_args)?);
+                        }
+                        "cfg" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            let debug = self.env.get("__debug__").is_some_and(Value::is_truthy);
+                            return match evaluated_args.as_slice() {
+                                [Value::Str(option)] => Ok(Value::Bool(stdlib::sys::cfg(option, debug))),
+                                [other] => raise(ExceptionKind::TypeError, vec![format!("cfg() expects a str, not '{}'", other.type_name())]),
+                                _ => raise(ExceptionKind::TypeError, vec![format!("cfg() takes exactly one argument ({} given)", evaluated_args.len())]),
+                            };
+                        }
+                        "assert_eq" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return Ok(testing::assert_eq(&evaluated_args)?);
+                        }
+                        "fixture_path" => {
+                            let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            return Ok(testing::fixture_path(&evaluated_args)?);
+                        }
+                        "dir" | "getattr" | "setattr" | "hasattr" | "vars" => return self.call_reflection(name, args),
+                        "eval" | "exec" => return self.call_eval(name, args),
+                        kind_name if !self.functions.contains_key(kind_name) && ExceptionKind::from_name(kind_name).is_some() => {
+                            let kind = ExceptionKind::from_name(kind_name).unwrap_or(ExceptionKind::Exception);
+                            let args = args.iter().map(|arg| Ok(self.eval_inner(arg)?.to_display_string())).collect::<Result<Vec<String>, ControlFlow>>()?;
+                            return Ok(Value::Exception(Exception::new(kind, args)));
+                        }
+                        _ => { /* continue to check for bytes/bytearray methods or user-defined functions */ }
+                    }
+                }
+                
+                if let Some(func) = native::interpreter_function(&callable_val) {
+                    return self.call_interpreter_function(func, args);
+                }
+                if let Value::NativeFunction { name, handle } = &callable_val {
+                    self.capabilities.check(name)?;
+                    self.record_call(name);
+                    let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                    return Ok(handle.call(name, &evaluated_args)?);
+                }
+
+                // Calling a class constructs an instance of it
+                if let Value::Class { name, .. } = &callable_val {
+                    let evaluated_args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                    return Ok(self.instantiate(name, evaluated_args)?);
+                }
+
+                // Bound methods such as `f = items.append` are called like any other method
+                if let Value::BuiltinMethod { mut object, method_name } = callable_val {
+                    let evaluated_args: Vec<Value> = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                    Ok(self.call_method(&mut object, &method_name, evaluated_args)?)
+                } else {
+                    // Handle user-defined function calls
+                    if let Value::Str(func_name) = &callable_val {
+                        if let Some((params, body)) = self.functions.get(func_name) {
+                            let params = params.clone();
+                            let body = body.clone();
+                            
+                            // Check argument count
+                            if args.len() != params.len() {
+                                return raise(ExceptionKind::TypeError, vec![
+                                    format!("{}() takes {} arguments but {} were given", 
+                                        func_name, params.len(), args.len())
+                                ]);
+                            }
+                            
+                            let arg_values = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+                            let cached = cache::lock(&self.caches).contains_key(func_name);
+                            if cached {
+                                for value in &arg_values {
+                                    value.check_hashable()?;
                                 }
-                                
-                                // Create new environment for function call
-                                let mut new_env = self.env.clone();
-                                for (param, arg) in params.iter().zip(args.iter()) {
-                                    new_env.insert(param.clone(), self.eval_inner(arg)?);
+                                if let Some(result) = cache::lock(&self.caches).get_mut(func_name).and_then(|cache| cache.get(&arg_values)) {
+                                    return Ok(result);
                                 }
-                                
-                                let mut sub_interpreter = Interpreter {
-                                    env: new_env,
-                                    functions: self.functions.clone(),
-                                    profile: self.profile.clone(),
-                                };
-                                match sub_interpreter.eval(&body) {
-                                    Err(exc) if exc.kind == ExceptionKind::Return => {
-                                        if let Some(arg) = exc.args.get(0) {
-                                            let val: Value = serde_json::from_str(arg).unwrap_or(Value::None);
-                                            Ok(val)
-                                        } else {
-                                            Ok(Value::None)
-                                        }
-                                    }
-                                    other => other,
+                            }
+
+                            // Create new environment for function call
+                            let mut new_env = self.env.clone();
+                            for (param, arg) in params.iter().zip(arg_values.iter()) {
+                                new_env.insert(param.to_string(), arg.clone());
+                            }
+                            
+                            let mut sub_interpreter = Interpreter {
+                                env: new_env,
+                                functions: self.functions.clone(),
+                                capabilities: self.capabilities.clone(),
+                                stdout: self.stdout.clone(),
+                                exit_handlers: self.exit_handlers.clone(),
+                                stats: self.stats.clone(),
+                                caches: self.caches.clone(),
+                                recursion_limit: self.recursion_limit.clone(),
+                                env_vars: self.env_vars.clone(),
+                            };
+                            let _frame = CallFrame::enter(func_name, &self.recursion_limit, self.stats.as_ref())?;
+                            let result = sub_interpreter.eval(&body).map_err(|mut exc| {
+                                exc.add_frame(func_name.clone());
+                                ControlFlow::from(exc)
+                            })?;
+                            if cached {
+                                if let Some(cache) = cache::lock(&self.caches).get_mut(func_name) {
+                                    cache.insert(arg_values, result.clone());
                                 }
-                            } else {
-                                Err(Exception::new(ExceptionKind::NameError, vec![format!("name '{}' is not defined", func_name)]))
                             }
+                            Ok(result)
                         } else {
-                            Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object is not callable", callable_val.type_name())]))
+                            Err(self.name_error(func_name).into())
                         }
+                    } else {
+                        raise(ExceptionKind::TypeError, vec![format!("'{}' object is not callable", callable_val.type_name())])
                     }
                 }
-                Expr::GetAttr { object, name } => {
-                    let obj = self.eval_inner(object)?;
-                    Ok(Value::BuiltinMethod {
-                        object: Box::new(obj),
-                        method_name: name.clone(),
-                    })
+            }
+            Expr::KeywordArg { name, .. } => raise(ExceptionKind::TypeError, vec![format!("unexpected keyword argument '{}'", name)]),
+            expr => raise(ExceptionKind::NotImplementedError, vec![format!("Expression not implemented: {:?}", expr)]),
+        }
+    }
+
+    // Resolve `obj.method(args)`: methods of a class or instance, functions of a
+    // native module, then the methods of builtin values
+    fn call_method(&mut self, obj: &mut Value, method: &str, args: Vec<Value>) -> Result<Value, Exception> {
+        match &*obj {
+            Value::Instance { class_name, .. } | Value::Class { name: class_name, .. } => {
+                let class_name = class_name.clone();
+                let (methods, _) = self.collect_class_hierarchy(&class_name);
+                let Some((params, body)) = methods.get(method) else {
+                    return Err(self.attribute_error(obj, &class_name, method));
+                };
+                let receiver = matches!(obj, Value::Instance { .. }).then_some(obj);
+                self.run_method(format!("{}.{}", class_name, method), params, body, receiver, args)
+            }
+            Value::Dict(members) => match members.get(&Value::Str(method.to_string())) {
+                Some(Value::NativeFunction { name, handle }) => {
+                    self.capabilities.check(name)?;
+                    self.record_call(name);
+                    handle.call(name, &args)
+                }
+                _ => methods::call(obj, method, args),
+            },
+            // Module functions run against the module's own globals and functions
+            Value::Module { name, globals, functions, .. } => {
+                let Some((params, body)) = functions.get(method) else {
+                    return raise(ExceptionKind::AttributeError, vec![format!("module '{}' has no function '{}'", name, method)]);
+                };
+                let scope = Interpreter {
+                    env: globals.clone(),
+                    functions: functions.clone(),
+                    capabilities: self.capabilities.clone(),
+                    stdout: self.stdout.clone(),
+                    exit_handlers: self.exit_handlers.clone(),
+                    stats: self.stats.clone(),
+                    caches: self.caches.clone(),
+                    recursion_limit: self.recursion_limit.clone(),
+                    env_vars: self.env_vars.clone(),
+                };
+                scope.run_method(format!("{}.{}", name, method), params, body, None, args)
+            }
+            _ => methods::call(obj, method, args),
+        }
+    }
+
+    /// Load the source module `import name` refers to, running it the first time.
+    fn import_source(&self, name: &str) -> Result<Value, Exception> {
+        let Some(path) = modules::resolve(name) else {
+            let searched: Vec<String> = modules::search_path().iter().map(|dir| dir.display().to_string()).collect();
+            return raise(ExceptionKind::ModuleNotFoundError, vec![format!("No module named '{}' (searched {})", name, searched.join(", "))]);
+        };
+        if !self.capabilities.filesystem {
+            return raise(ExceptionKind::PermissionError, vec![format!("importing '{}' from {} is disabled in this sandbox", name, path.display())]);
+        }
+        let path = path.canonicalize().unwrap_or(path);
+        let _loading = modules::Loading::enter(name, &path)?;
+        if let Some(module) = modules::cached(&path) {
+            return Ok(module);
+        }
+        let source = std::fs::read_to_string(&path).map_err(fileio::io_error)?;
+        let builtins = Interpreter::new().env.clone();
+        let mut scope = Interpreter {
+            env: builtins.clone(),
+            functions: HashMap::new(),
+            capabilities: self.capabilities.clone(),
+            stdout: self.stdout.clone(),
+            exit_handlers: self.exit_handlers.clone(),
+            stats: self.stats.clone(),
+            caches: self.caches.clone(),
+            recursion_limit: self.recursion_limit.clone(),
+            env_vars: self.env_vars.clone(),
+        };
+        let frame = format!("<module {}>", name);
+        if let Some(program) = Parser::parse_file(&source, &path.display().to_string()).map_err(|mut exc| {
+            exc.add_frame(frame.clone());
+            exc
+        })? {
+            scope.eval(&program).map_err(|mut exc| {
+                exc.add_frame(frame);
+                exc
+            })?;
+        }
+        let mut globals = std::mem::take(&mut scope.env);
+        globals.retain(|name, value| builtins.get(name).is_none_or(|builtin| builtin != value));
+        let module = Value::Module { name: name.to_string(), path: path.display().to_string(), globals, functions: std::mem::take(&mut scope.functions) };
+        modules::store(&path, module.clone());
+        Ok(module)
+    }
+
+    // Run a method body. An instance receiver is bound to 'self' (an explicit leading
+    // 'self' parameter is implied) and its fields are visible as variables. Whatever
+    // the body does to 'self' is copied back into the receiver, even when it raises.
+    fn run_method(&self, frame: String, params: &[Symbol], body: &Expr, receiver: Option<&mut Value>, args: Vec<Value>) -> Result<Value, Exception> {
+        self.record_call(&frame);
+        let mut new_env = self.env.clone();
+        let mut params = params;
+        if let Some(instance @ Value::Instance { fields, .. }) = receiver.as_deref() {
+            for (field_name, field_value) in fields {
+                new_env.insert(field_name.clone(), field_value.clone());
+            }
+            new_env.insert("self".to_string(), instance.clone());
+            if let Some((first, rest)) = params.split_first() {
+                if first == "self" {
+                    params = rest;
                 }
-                expr => Err(Exception::new(ExceptionKind::NotImplementedError, vec![format!("Expression not implemented: {:?}", expr)])),
             }
+        }
+        for (param, arg) in params.iter().zip(args) {
+            new_env.insert(param.to_string(), arg);
+        }
+        let mut sub_interpreter = Interpreter {
+            env: new_env,
+            functions: self.functions.clone(),
+            capabilities: self.capabilities.clone(),
+            stdout: self.stdout.clone(),
+            exit_handlers: self.exit_handlers.clone(),
+            stats: self.stats.clone(),
+            caches: self.caches.clone(),
+            recursion_limit: self.recursion_limit.clone(),
+            env_vars: self.env_vars.clone(),
         };
-        self.profile_exit(expr_type, start);
+        let _frame = CallFrame::enter(&frame, &self.recursion_limit, self.stats.as_ref())?;
+        let result = sub_interpreter.eval(body).map_err(|mut exc| {
+            exc.add_frame(frame);
+            exc
+        });
+        if let (Some(receiver), Some(instance)) = (receiver, sub_interpreter.env.remove("self")) {
+            *receiver = instance;
+        }
         result
     }
 
+    // Construct an instance of `class_name`: start from the class fields (inherited
+    // ones included), then run `__init__` with 'self' bound to the new instance and
+    // keep the fields it sets. Abstract methods must all have been overridden.
+    fn instantiate(&self, class_name: &str, args: Vec<Value>) -> Result<Value, Exception> {
+        let (methods, fields) = self.collect_class_hierarchy(class_name);
+        let mut missing: Vec<&str> = methods.iter().filter(|(_, (_, body))| matches!(**body, Expr::AbstractFn { .. })).map(|(name, _)| name.as_str()).collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            return raise(ExceptionKind::TypeError, vec![format!("Can't instantiate abstract class {} with abstract methods {}", class_name, missing.join(", "))]);
+        }
+        let instance = Value::Instance { class_name: class_name.to_string(), fields, id: next_instance_id() };
+        let Some((params, body)) = methods.get("__init__") else {
+            if !args.is_empty() {
+                return raise(ExceptionKind::TypeError, vec![format!("{}() takes no arguments", class_name)]);
+            }
+            return Ok(instance);
+        };
+        let params = match params.split_first() {
+            Some((first, rest)) if first == "self" => rest,
+            _ => params,
+        };
+        if params.len() != args.len() {
+            return raise(ExceptionKind::TypeError, vec![format!("{}.__init__() takes {} arguments but {} were given", class_name, params.len(), args.len())]);
+        }
+        let frame = format!("{}.__init__", class_name);
+        self.record_call(&frame);
+        let mut new_env = self.env.clone();
+        new_env.insert("self".to_string(), instance);
+        new_env.extend(params.iter().map(Symbol::to_string).zip(args));
+        let mut sub_interpreter = Interpreter {
+            env: new_env,
+            functions: self.functions.clone(),
+            capabilities: self.capabilities.clone(),
+            stdout: self.stdout.clone(),
+            exit_handlers: self.exit_handlers.clone(),
+            stats: self.stats.clone(),
+            caches: self.caches.clone(),
+            recursion_limit: self.recursion_limit.clone(),
+            env_vars: self.env_vars.clone(),
+        };
+        let _frame = CallFrame::enter(&frame, &self.recursion_limit, self.stats.as_ref())?;
+        sub_interpreter.eval(body).map_err(|mut exc| {
+            exc.add_frame(frame);
+            exc
+        })?;
+        Ok(sub_interpreter.env.remove("self").unwrap_or(Value::None))
+    }
+
+    // Values are copied out of variables; store a mutated copy back when it came from
+    // one, or from a field of an instance or an element of a list or dict in one
+    // (`self.head.push(x)`, `items[0].bump()`). Other copies, like one assigned to
+    // a second variable, are left as they were.
+    fn store_back(&mut self, target: &Expr, value: Value) {
+        match target {
+            Expr::Ident(name) => {
+                if let Some(slot) = self.env.get_mut(name.as_str()) {
+                    *slot = value;
+                }
+            }
+            Expr::GetAttr { object, name } | Expr::FieldAccess { object, field: name } if Self::is_path(object) => {
+                if let Ok(mut parent @ Value::Instance { .. }) = self.eval_inner(object) {
+                    if let Value::Instance { fields, .. } = &mut parent {
+                        fields.insert(name.to_string(), value);
+                    }
+                    self.store_back(object, parent);
+                }
+            }
+            Expr::Index { collection, index } if Self::is_path(collection) && Self::is_path(index) => {
+                if let (Ok(mut parent), Ok(key)) = (self.eval_inner(collection), self.eval_inner(index)) {
+                    let slot = match (&mut parent, &key) {
+                        (Value::List(items), Value::Int(n)) => usize::try_from(*n).ok().and_then(|n| items.get_mut(n)),
+                        (Value::Dict(entries), key) => entries.get_mut(key),
+                        _ => None,
+                    };
+                    if let Some(slot) = slot {
+                        *slot = value;
+                        self.store_back(collection, parent);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A literal, a variable, or a chain of attributes and indexes on one, which
+    // evaluates without side effects
+    fn is_path(expr: &Expr) -> bool {
+        match expr {
+            Expr::Ident(_) | Expr::Integer(_) | Expr::String(_) => true,
+            Expr::GetAttr { object, .. } | Expr::FieldAccess { object, .. } => Self::is_path(object),
+            Expr::Index { collection, index } => Self::is_path(collection) && Self::is_path(index),
+            _ => false,
+        }
+    }
+
+    // Reflection builtins: dir, getattr, setattr, hasattr and vars
+    fn call_reflection(&mut self, builtin: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let values = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+        let is_missing = |exc: &Exception| exc.kind == ExceptionKind::AttributeError;
+        match (builtin, values.as_slice()) {
+            ("dir", []) => {
+                let mut names: Vec<String> = self.env.keys().chain(self.functions.keys()).cloned().collect();
+                names.sort();
+                names.dedup();
+                Ok(Value::List(names.into_iter().map(Value::Str).collect()))
+            }
+            ("dir", [obj]) => Ok(Value::List(self.attr_names(obj).into_iter().map(Value::Str).collect())),
+            ("getattr", [obj, Value::Str(name)]) => Ok(self.get_attr(obj, name)?),
+            ("getattr", [obj, Value::Str(name), default]) => match self.get_attr(obj, name) {
+                Err(exc) if is_missing(&exc) => Ok(default.clone()),
+                result => Ok(result?),
+            },
+            ("hasattr", [obj, Value::Str(name)]) => match self.get_attr(obj, name) {
+                Ok(_) => Ok(Value::Bool(true)),
+                Err(exc) if is_missing(&exc) => Ok(Value::Bool(false)),
+                Err(exc) => Err(exc.into()),
+            },
+            ("setattr", [obj, Value::Str(name), value]) => {
+                let mut obj = obj.clone();
+                Self::set_attr(&mut obj, name, value.clone())?;
+                self.store_back(&args[0], obj);
+                Ok(Value::None)
+            }
+            ("vars", [obj]) => Ok(Self::vars(obj)?),
+            ("getattr" | "hasattr" | "setattr", [_, name, ..]) if values.len() <= 3 => {
+                raise(ExceptionKind::TypeError, vec![format!("{}(): attribute name must be string, not '{}'", builtin, name.type_name())])
+            }
+            _ => {
+                let expected = match builtin {
+                    "dir" => "at most 1 argument",
+                    "getattr" => "2 or 3 arguments",
+                    "hasattr" => "exactly 2 arguments",
+                    "setattr" => "exactly 3 arguments",
+                    _ => "exactly 1 argument",
+                };
+                raise(ExceptionKind::TypeError, vec![format!("{}() takes {} ({} given)", builtin, expected, values.len())])
+            }
+        }
+    }
+
+    // Decorators are built in, since functions aren't values a user-defined one
+    // could wrap. `@cache` and `@cache(maxsize)` memoize the function's results.
+    fn apply_decorator(&mut self, func_name: &str, decorator: &Expr) -> Result<(), ControlFlow> {
+        let (name, args) = match decorator {
+            Expr::FnCall { callable, args } => (callable.as_ref(), Some(args)),
+            name => (name, None),
+        };
+        if *name != Expr::Ident(Symbol::intern("cache")) {
+            let name = format::format_expr(name);
+            return raise(ExceptionKind::NameError, vec![format!("'@{}' is not a built-in decorator", name)]);
+        }
+        let args = args.map_or(Ok(Vec::new()), |args| args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>())?;
+        let maxsize = match args.as_slice() {
+            [] | [Value::None] => None,
+            [Value::Int(maxsize)] if *maxsize >= 0 => Some(*maxsize as usize),
+            [Value::Int(_)] => return raise(ExceptionKind::ValueError, vec!["cache() maxsize must not be negative".to_string()]),
+            [other] => return raise(ExceptionKind::TypeError, vec![format!("cache() maxsize must be an int or None, not '{}'", other.type_name())]),
+            values => return raise(ExceptionKind::TypeError, vec![format!("cache() takes at most 1 argument ({} given)", values.len())]),
+        };
+        cache::lock(&self.caches).insert(func_name.to_string(), FnCache::new(maxsize));
+        Ok(())
+    }
+
+    // `fib.cache_clear()` and `fib.cache_info()` on a function decorated with @cache
+    fn call_cache_method(&mut self, func_name: &str, method: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        if !args.is_empty() {
+            return raise(ExceptionKind::TypeError, vec![format!("{}() takes no arguments ({} given)", method, args.len())]);
+        }
+        let mut caches = cache::lock(&self.caches);
+        let Some(cache) = caches.get_mut(func_name) else {
+            return raise(ExceptionKind::NameError, vec![format!("name '{}' is not defined", func_name)]);
+        };
+        match method {
+            "cache_clear" => {
+                cache.clear();
+                Ok(Value::None)
+            }
+            "cache_info" => Ok(cache.info()),
+            _ => raise(ExceptionKind::AttributeError, vec![format!("'function' object has no attribute '{}'", method)]),
+        }
+    }
+
+    // The native functions that need this interpreter's state; see
+    // `native::interpreter_function`. Their arguments are left unevaluated, as
+    // some take a function by its bare name.
+    fn call_interpreter_function(&mut self, func: &'static str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        self.capabilities.check(func)?;
+        self.record_call(func);
+        match func {
+            "inspect.signature" | "inspect.getsource" => self.call_inspect(func, args),
+            "debug.heap_stats" | "debug.track_allocations" => self.call_debug(func, args),
+            "http.serve" => self.call_http(func, args),
+            "sys.getrecursionlimit" | "sys.setrecursionlimit" => self.call_sys(func, args),
+            "config.load" | "config.load_env" => self.call_config(func, args),
+            _ => unreachable!("{} is not answered by the interpreter", func),
+        }
+    }
+
+    // sys.getrecursionlimit() and sys.setrecursionlimit(limit), for this
+    // interpreter and the ones running its calls
+    fn call_sys(&mut self, qualified: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+        match (qualified, args.as_slice()) {
+            ("sys.getrecursionlimit", []) => Ok(Value::Int(self.recursion_limit.get() as i64)),
+            ("sys.setrecursionlimit", [Value::Int(limit)]) => {
+                self.recursion_limit.set(*limit)?;
+                Ok(Value::None)
+            }
+            ("sys.setrecursionlimit", [other]) => raise(ExceptionKind::TypeError, vec![format!("{}() expects an int, not '{}'", qualified, other.type_name())]),
+            ("sys.setrecursionlimit", _) => raise(ExceptionKind::TypeError, vec![format!("{}() takes exactly 1 argument ({} given)", qualified, args.len())]),
+            _ => raise(ExceptionKind::TypeError, vec![format!("{}() takes no arguments ({} given)", qualified, args.len())]),
+        }
+    }
+
+    // config.load() and config.load_env(), which read and add to the variables
+    // loaded for this interpreter
+    fn call_config(&mut self, qualified: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let args = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+        let result = match qualified {
+            "config.load_env" => config::load_env(&args, &self.env_vars),
+            _ => config::load(&args, &self.env_vars),
+        };
+        result.map_err(ControlFlow::from)
+    }
+
+    // inspect.signature(fn) and inspect.getsource(fn). Like spawn() and atexit(),
+    // they take a function by name, bare or as a string.
+    fn call_inspect(&mut self, qualified: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let name = match args {
+            [Expr::Ident(name)] if !self.env.contains_key(name.as_str()) => name.to_string(),
+            [arg] => match self.eval_inner(arg)? {
+                Value::Str(name) => name,
+                other => return raise(ExceptionKind::TypeError, vec![format!("{}() expects a function, not '{}'", qualified, other.type_name())]),
+            },
+            _ => return raise(ExceptionKind::TypeError, vec![format!("{}() takes exactly 1 argument ({} given)", qualified, args.len())]),
+        };
+        let Some((params, body)) = self.functions.get(&name) else {
+            return Err(self.name_error(&name).into());
+        };
+        if qualified == "inspect.signature" {
+            let params = Value::List(params.iter().map(|param| Value::Str(param.to_string())).collect());
+            let fields = [("name", Value::Str(name)), ("params", params)];
+            return Ok(Value::Dict(fields.into_iter().map(|(key, value)| (Value::Str(key.to_string()), value)).collect()));
+        }
+        // Regenerated from the AST, so comments and the original layout are not kept
+        let definition = Expr::FnDef { name: Symbol::intern(&name), params: params.clone(), body: body.clone() };
+        Ok(Value::Str(format::format_expr(&definition) + "\n"))
+    }
+
+    // `debug.heap_stats()`: every value the variables in scope hold, by type,
+    // leaving out the constants every interpreter starts with.
+    // `debug.track_allocations(fn, args...)`: call a function, counting the values
+    // it creates and those still reachable from its result. Values are never
+    // shared, so the rest were freed when the call returned.
+    fn call_debug(&mut self, qualified: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        if qualified == "debug.heap_stats" {
+            if !args.is_empty() {
+                return raise(ExceptionKind::TypeError, vec![format!("{}() takes no arguments ({} given)", qualified, args.len())]);
+            }
+            let initial = Interpreter::new();
+            let mut tally = debug::Tally::default();
+            for (name, value) in &self.env {
+                if initial.env.get(name) != Some(value) {
+                    tally.add(value);
+                }
+            }
+            return Ok(tally.to_value());
+        }
+        let Some((function, rest)) = args.split_first() else {
+            return raise(ExceptionKind::TypeError, vec![format!("{}() expects a function to call", qualified)]);
+        };
+        let name = match function {
+            Expr::Ident(name) if !self.env.contains_key(name.as_str()) => name.to_string(),
+            arg => match self.eval_inner(arg)? {
+                Value::Str(name) => name,
+                other => return raise(ExceptionKind::TypeError, vec![format!("{}() expects a function, not '{}'", qualified, other.type_name())]),
+            },
+        };
+        let Some((params, body)) = self.functions.get(&name).cloned() else {
+            return Err(self.name_error(&name).into());
+        };
+        let call_args = rest.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+        // Counted apart from the program's own stats, then added to them
+        let outer = self.stats.replace(StatsHandle::default());
+        let result = self.run_method(name, &params, &body, None, call_args);
+        let tracked = std::mem::replace(&mut self.stats, outer).map(|tracked| stats::lock(&tracked).clone()).unwrap_or_default();
+        if let Some(outer) = &self.stats {
+            stats::lock(outer).merge(&tracked);
+        }
+        let result = result?;
+        let allocated = tracked.allocations.iter().map(|(kind, &count)| (Value::Str(kind.to_string()), Value::Int(count as i64)));
+        let mut retained = debug::Tally::default();
+        retained.add(&result);
+        Ok(debug::dict([("result", result), ("allocated", Value::Dict(allocated.collect())), ("retained", retained.counts())]))
+    }
+
+    // http.serve(addr, handler, max_requests=None): answer requests on `addr` with
+    // `handler`, a function or a dict of routes to function names, one request at a
+    // time until Ctrl-C or `max_requests` have been answered
+    fn call_http(&mut self, qualified: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let (addr, handler, limit) = match args {
+            [addr, handler] => (addr, handler, None),
+            [addr, handler, limit] => (addr, handler, Some(limit)),
+            _ => return raise(ExceptionKind::TypeError, vec![format!("{}() expects an address, a handler and an optional number of requests", qualified)]),
+        };
+        let addr = match self.eval_inner(addr)? {
+            Value::Str(addr) => addr,
+            other => return raise(ExceptionKind::TypeError, vec![format!("{}() address must be a str, not '{}'", qualified, other.type_name())]),
+        };
+        let routes = match handler {
+            Expr::Ident(name) if !self.env.contains_key(name.as_str()) => http::Routes::single(name.to_string()),
+            handler => match self.eval_inner(handler)? {
+                Value::Str(name) => http::Routes::single(name),
+                Value::Dict(routes) => http::Routes::from_dict(&routes)?,
+                other => return raise(ExceptionKind::TypeError, vec![format!("{}() handler must be a function or a dict of routes, not '{}'", qualified, other.type_name())]),
+            },
+        };
+        let limit = match limit.map(|limit| self.eval_inner(limit)).transpose()? {
+            None | Some(Value::None) => None,
+            Some(Value::Int(limit)) if limit >= 0 => Some(limit),
+            Some(other) => return raise(ExceptionKind::TypeError, vec![format!("{}() number of requests must be a non-negative int, not {}", qualified, other.to_display_string())]),
+        };
+        for name in routes.functions() {
+            match self.functions.get(name) {
+                None => return Err(self.name_error(name).into()),
+                Some((params, _)) if params.len() > 1 => {
+                    return raise(ExceptionKind::TypeError, vec![format!("{}() handler '{}' must take the request or nothing, not {} arguments", qualified, name, params.len())]);
+                }
+                Some(_) => {}
+            }
+        }
+        let listener = http::bind(&addr)?;
+        if let Ok(local) = listener.local_addr() {
+            eprintln!("Serving HTTP on http://{}", local);
+        }
+        let mut served = 0;
+        while limit.is_none_or(|limit| served < limit) {
+            let stream = http::accept(&listener)?;
+            let (method, response) = match http::Request::read(&stream) {
+                Err(status) => ("GET".to_string(), http::Response::error(status)),
+                Ok(request) => match routes.find(&request.method, &request.path) {
+                    Err(status) => (request.method, http::Response::error(status)),
+                    Ok((name, params)) => {
+                        let (param_names, body) = self.functions[name].clone();
+                        let call_args = if param_names.is_empty() { Vec::new() } else { vec![request.to_value(params)] };
+                        let result = self.run_method(name.to_string(), &param_names, &body, None, call_args).and_then(|value| http::Response::from_value(&value));
+                        match result {
+                            Ok(response) => (request.method, response),
+                            Err(exc) if matches!(exc.kind, ExceptionKind::KeyboardInterrupt | ExceptionKind::SystemExit) => return Err(exc.into()),
+                            // A failing handler fails its request, not the server
+                            Err(exc) => {
+                                eprintln!("{}", exc);
+                                (request.method, http::Response::error(500))
+                            }
+                        }
+                    }
+                },
+            };
+            response.write(&stream, &method);
+            served += 1;
+        }
+        Ok(Value::None)
+    }
+
+    // Run a finally block or deferred expression once `outcome` is known. An error
+    // raised by the cleanup replaces the outcome, recording any exception in flight.
+    fn clean_up(&mut self, action: &Expr, outcome: Result<Value, ControlFlow>) -> Result<Value, ControlFlow> {
+        match self.eval_inner(action) {
+            Ok(_) => outcome,
+            Err(ControlFlow::Exception(mut exc)) => {
+                if let Err(ControlFlow::Exception(pending)) = outcome {
+                    if exc.context.is_none() && exc != pending {
+                        exc.context = Some(Box::new(pending));
+                    }
+                }
+                Err(exc.into())
+            }
+            Err(jump) => Err(jump),
+        }
+    }
+
+    // print(values..., sep=" ", end="\n", file=None, flush=false). Without a file the
+    // text goes to the interpreter's stdout, which is flushed after every write anyway.
+    fn call_print(&mut self, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let mut values = Vec::new();
+        let (mut sep, mut end, mut file, mut flush) = (" ".to_string(), "\n".to_string(), None, false);
+        for arg in args {
+            let Expr::KeywordArg { name, value } = arg else {
+                let value = self.eval_inner(arg)?;
+                values.push(self.display(&value)?);
+                continue;
+            };
+            match (name.as_str(), self.eval_inner(value)?) {
+                ("sep", Value::Str(s)) => sep = s,
+                ("end", Value::Str(s)) => end = s,
+                ("sep" | "end" | "file", Value::None) => {}
+                ("sep" | "end", other) => return raise(ExceptionKind::TypeError, vec![format!("{} must be None or a string, not '{}'", name, other.type_name())]),
+                ("file", Value::File { handle, .. }) => file = Some(handle),
+                ("file", other) => return raise(ExceptionKind::TypeError, vec![format!("print() file must be a file, not '{}'", other.type_name())]),
+                ("flush", value) => flush = value.is_truthy(),
+                (other, _) => return raise(ExceptionKind::TypeError, vec![format!("print() got an unexpected keyword argument '{}'", other)]),
+            }
+        }
+        let text = values.join(&sep) + &end;
+        match file {
+            Some(handle) => {
+                fileio::write(&handle, &text)?;
+                if flush {
+                    fileio::flush(&handle)?;
+                }
+            }
+            None => self.stdout.write_str(&text)?,
+        }
+        Ok(Value::None)
+    }
+
+    // input(prompt="", hidden=false, key=false, timeout=None): one line, or one key,
+    // from stdin; None once stdin is closed, TimeoutError when nothing arrives in time
+    fn call_input(&mut self, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let mut prompt = String::new();
+        let mut options = console::InputOptions::default();
+        for (i, arg) in args.iter().enumerate() {
+            let Expr::KeywordArg { name, value } = arg else {
+                if i > 0 {
+                    return raise(ExceptionKind::TypeError, vec![format!("input() expected at most 1 positional argument, got {}", i + 1)]);
+                }
+                prompt = self.eval_inner(arg)?.to_display_string();
+                continue;
+            };
+            match (name.as_str(), self.eval_inner(value)?) {
+                ("hidden", value) => options.hidden = value.is_truthy(),
+                ("key", value) => options.key = value.is_truthy(),
+                ("timeout", Value::None) => options.timeout = None,
+                ("timeout", Value::Int(secs)) if secs >= 0 => options.timeout = Some(std::time::Duration::from_secs(secs as u64)),
+                ("timeout", Value::Float(secs)) if secs >= 0.0 && secs.is_finite() => options.timeout = Some(std::time::Duration::from_secs_f64(secs)),
+                ("timeout", other) => return raise(ExceptionKind::ValueError, vec![format!("input() timeout must be a non-negative number, not {}", other.to_display_string())]),
+                (other, _) => return raise(ExceptionKind::TypeError, vec![format!("input() got an unexpected keyword argument '{}'", other)]),
+            }
+        }
+        self.stdout.write_str(&prompt)?;
+        let input = console::read(&options)?;
+        // The Enter that ended a hidden reply was not echoed either
+        if options.hidden && !options.key && std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+            self.stdout.write_str("\n")?;
+        }
+        match input {
+            console::Input::Text(text) => Ok(Value::Str(text)),
+            console::Input::Eof => Ok(Value::None),
+            console::Input::TimedOut => raise(ExceptionKind::TimeoutError, vec!["input() timed out".to_string()]),
+        }
+    }
+
+    // eval(code) and exec(code) run source text in the current scope. Given a dict as
+    // well, the code sees only those variables and its assignments are stored back into it.
+    #[allow(clippy::mutable_key_type)] // scope keys are variable names
+    fn call_eval(&mut self, builtin: &str, args: &[Expr]) -> Result<Value, ControlFlow> {
+        let values = args.iter().map(|arg| self.eval_inner(arg)).collect::<Result<Vec<Value>, ControlFlow>>()?;
+        let (source, scope) = match values.as_slice() {
+            [Value::Str(source)] => (source, None),
+            [Value::Str(source), Value::Dict(scope)] => (source, Some(scope)),
+            [Value::Str(_), other] => return raise(ExceptionKind::TypeError, vec![format!("{}() scope must be a dict, not '{}'", builtin, other.type_name())]),
+            [other] | [other, _] => return raise(ExceptionKind::TypeError, vec![format!("{}() arg 1 must be a string, not '{}'", builtin, other.type_name())]),
+            _ => return raise(ExceptionKind::TypeError, vec![format!("{}() takes 1 or 2 arguments ({} given)", builtin, values.len())]),
+        };
+        let Some(program) = Parser::parse_source(source)? else {
+            return Ok(Value::None);
+        };
+        let result = match scope {
+            None => self.eval_inner(&program).or_else(ControlFlow::finish)?,
+            Some(scope) => {
+                let builtins = std::mem::take(&mut Interpreter::new().env);
+                let mut env = builtins.clone();
+                env.extend(scope.iter().filter_map(|(key, value)| match key {
+                    Value::Str(name) => Some((name.clone(), value.clone())),
+                    _ => None,
+                }));
+                let mut sub_interpreter = Interpreter {
+                    env,
+                    functions: self.functions.clone(),
+                    capabilities: self.capabilities.clone(),
+                    stdout: self.stdout.clone(),
+                    exit_handlers: self.exit_handlers.clone(),
+                    stats: self.stats.clone(),
+                    caches: self.caches.clone(),
+                    recursion_limit: self.recursion_limit.clone(),
+                    env_vars: self.env_vars.clone(),
+                };
+                let result = sub_interpreter.eval_inner(&program).or_else(ControlFlow::finish)?;
+                // Builtin constants stay out of the dict unless the code rebound them
+                let mut scope = scope.clone();
+                for (name, value) in std::mem::take(&mut sub_interpreter.env) {
+                    let key = Value::Str(name.clone());
+                    if scope.contains_key(&key) || builtins.get(&name) != Some(&value) {
+                        scope.insert(key, value);
+                    }
+                }
+                self.store_back(&args[1], Value::Dict(scope));
+                result
+            }
+        };
+        Ok(if builtin == "eval" { result } else { Value::None })
+    }
+
+    // Attribute names `dir(obj)` reports, sorted
+    /// NameError for `name`, suggesting a variable, function, builtin or keyword
+    /// it may be a typo of, preferred in that order.
+    fn name_error(&self, name: &str) -> Exception {
+        let mut defined: Vec<&str> = self.env.keys().chain(self.functions.keys()).map(String::as_str).collect();
+        defined.sort_unstable();
+        let candidates = defined.into_iter()
+            .chain(builtins::NAMES.iter().chain(suggest::INTERPRETER_BUILTINS).chain(KEYWORDS).copied());
+        let message = suggest::did_you_mean(format!("name '{}' is not defined", name), name, candidates);
+        Exception::new(ExceptionKind::NameError, vec![message])
+    }
+
+    // Whether calling `name` would reach a function: a user-defined one, a
+    // builtin or an exception constructor
+    fn is_function_name(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+            || builtins::NAMES.contains(&name)
+            || suggest::INTERPRETER_BUILTINS.contains(&name)
+            || ExceptionKind::from_name(name).is_some()
+    }
+
+    /// AttributeError for `name` on an instance or class of `type_name`,
+    /// suggesting one of its attributes.
+    fn attribute_error(&self, obj: &Value, type_name: &str, name: &str) -> Exception {
+        let names = self.attr_names(obj);
+        let message = format!("'{}' object has no attribute '{}'", type_name, name);
+        Exception::new(ExceptionKind::AttributeError, vec![suggest::did_you_mean(message, name, names.iter().map(String::as_str))])
+    }
+
+    fn attr_names(&self, obj: &Value) -> Vec<String> {
+        let mut names: Vec<String> = match obj {
+            Value::Instance { class_name, fields, .. } | Value::Class { name: class_name, fields, .. } => {
+                let (methods, class_fields) = self.collect_class_hierarchy(class_name);
+                fields.keys().chain(class_fields.keys()).chain(methods.keys()).cloned().collect()
+            }
+            Value::Dict(members) => members.keys()
+                .filter_map(|key| match key {
+                    Value::Str(name) => Some(name.clone()),
+                    _ => None,
+                })
+                .chain(methods::names(obj).iter().map(|name| name.to_string()))
+                .collect(),
+            Value::Exception(_) => EXCEPTION_ATTRS.iter().map(|name| name.to_string()).collect(),
+            _ => methods::names(obj).iter().map(|name| name.to_string()).collect(),
+        };
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn set_attr(obj: &mut Value, name: &str, value: Value) -> Result<(), Exception> {
+        match obj {
+            Value::Instance { fields, .. } | Value::Class { fields, .. } => {
+                fields.insert(name.to_string(), value);
+            }
+            Value::Dict(members) => {
+                members.insert(Value::Str(name.to_string()), value);
+            }
+            _ => return Err(Exception::new(ExceptionKind::AttributeError, vec![format!("cannot set attribute '{}' on '{}' object", name, obj.type_name())])),
+        }
+        Ok(())
+    }
+
+    // The fields `vars(obj)` returns: an instance's own fields, a class's fields, a module's members
+    fn vars(obj: &Value) -> Result<Value, Exception> {
+        match obj {
+            Value::Instance { fields, .. } | Value::Class { fields, .. } => {
+                Ok(Value::Dict(fields.iter().map(|(name, value)| (Value::Str(name.clone()), value.clone())).collect()))
+            }
+            Value::Dict(_) => Ok(obj.clone()),
+            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("vars() argument must be an instance, class or module, not '{}'", obj.type_name())])),
+        }
+    }
+
+    // Resolve `obj.name` without calling it. Methods come back bound to their object.
+    fn get_attr(&self, obj: &Value, name: &str) -> Result<Value, Exception> {
+        let bound = || Value::BuiltinMethod { object: Box::new(obj.clone()), method_name: name.to_string() };
+        match obj {
+            Value::Exception(exc) => Self::exception_attr(exc, name),
+            Value::Instance { class_name, fields, .. } | Value::Class { name: class_name, fields, .. } => {
+                if let Some(value) = fields.get(name) {
+                    return Ok(value.clone());
+                }
+                let (methods, class_fields) = self.collect_class_hierarchy(class_name);
+                match class_fields.get(name) {
+                    Some(value) => Ok(value.clone()),
+                    None if methods.contains_key(name) => Ok(bound()),
+                    None => Err(self.attribute_error(obj, class_name, name)),
+                }
+            }
+            // Module members shadow the dict methods
+            Value::Dict(members) => match members.get(&Value::Str(name.to_string())) {
+                Some(value) => Ok(value.clone()),
+                None if methods::names(obj).contains(&name) => Ok(bound()),
+                None => Err(methods::no_attribute(obj, name)),
+            },
+            Value::Module { name: module_name, globals, .. } => match globals.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => raise(ExceptionKind::AttributeError, vec![format!("module '{}' has no attribute '{}'", module_name, name)]),
+            },
+            _ if methods::names(obj).contains(&name) => Ok(bound()),
+            _ => Err(methods::no_attribute(obj, name)),
+        }
+    }
+
+    fn has_method(&self, obj: &Value, method: &str) -> bool {
+        match obj {
+            Value::Instance { class_name, .. } => self.collect_class_hierarchy(class_name).0.contains_key(method),
+            _ => false,
+        }
+    }
+
+    /// The text `print()` shows for a value. Instances use their `__str__`, or
+    /// `__repr__` without one; inside a container only `__repr__` is used.
+    pub fn display(&mut self, value: &Value) -> Result<String, Exception> {
+        value.display_with(&mut |value, nested| {
+            let hooks: &[&str] = if nested { &["__repr__"] } else { &["__str__", "__repr__"] };
+            let Some(hook) = hooks.iter().find(|hook| self.has_method(value, hook)) else {
+                return Ok(None);
+            };
+            match self.call_method(&mut value.clone(), hook, vec![])? {
+                Value::Str(text) => Ok(Some(text)),
+                other => raise(ExceptionKind::TypeError, vec![format!("{} returned non-string (type {})", hook, other.type_name())]),
+            }
+        })
+    }
+
+    // Context manager protocol: files are their own context, instances may define __enter__
+    fn enter_context(&mut self, resource: &Value) -> Result<Value, Exception> {
+        match resource {
+            Value::File { .. } => Ok(resource.clone()),
+            Value::Instance { .. } if self.has_method(resource, "__enter__") => self.call_method(&mut resource.clone(), "__enter__", vec![]),
+            Value::Instance { .. } if self.has_method(resource, "__exit__") || self.has_method(resource, "close") => Ok(resource.clone()),
+            _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' object does not support the context manager protocol", resource.type_name())])),
+        }
+    }
+
+    // Runs the exit hook even when the body raised; a truthy __exit__ result suppresses the error
+    fn exit_context(&mut self, resource: &Value, outcome: Result<Value, ControlFlow>) -> Result<Value, ControlFlow> {
+        match resource {
+            Value::File { handle, .. } => {
+                handle.close();
+                outcome
+            }
+            Value::Instance { .. } if self.has_method(resource, "__exit__") => {
+                let error = match &outcome {
+                    Err(ControlFlow::Exception(exc)) => Value::Exception(exc.clone()),
+                    _ => Value::None,
+                };
+                let suppress = self.call_method(&mut resource.clone(), "__exit__", vec![error])?.is_truthy();
+                match outcome {
+                    Err(ControlFlow::Exception(_)) if suppress => Ok(Value::None),
+                    other => other,
+                }
+            }
+            Value::Instance { .. } => {
+                self.call_method(&mut resource.clone(), "close", vec![])?;
+                outcome
+            }
+            _ => outcome,
+        }
+    }
+
+    // Run one pass of a loop body. Unlabeled break/continue apply to the
+    // innermost loop; labeled ones unwind until they reach the loop they name.
+    fn run_iteration(&mut self, body: &Expr, label: &Option<Symbol>) -> Result<Iteration, ControlFlow> {
+        match self.eval_inner(body) {
+            Ok(v) => Ok(Iteration::Completed(v)),
+            Err(ControlFlow::Break(target)) if target.is_none() || target == *label => Ok(Iteration::Broken),
+            Err(ControlFlow::Continue(target)) if target.is_none() || target == *label => Ok(Iteration::Continued),
+            Err(signal) => Err(signal),
+        }
+    }
+
+    // deepcopy(x) rebuilds containers and instances item by item; values backed by a live
+    // resource can't be duplicated. Values never share structure yet, so there are no
+    // cycles to remember.
+    fn deep_copy(value: &Value) -> Result<Value, Exception> {
+        let copy_all = |items: &[Value]| items.iter().map(Self::deep_copy).collect::<Result<Vec<Value>, Exception>>();
+        Ok(match value {
+            Value::List(items) => Value::List(copy_all(items)?),
+            Value::Tuple(items) => Value::Tuple(copy_all(items)?),
+            Value::Set(items) => Value::Set(items.iter().map(Self::deep_copy).collect::<Result<_, Exception>>()?),
+            Value::FrozenSet(items) => Value::FrozenSet(items.iter().map(Self::deep_copy).collect::<Result<_, Exception>>()?),
+            Value::Dict(map) => Value::Dict(map.iter().map(|(k, v)| Ok((Self::deep_copy(k)?, Self::deep_copy(v)?))).collect::<Result<_, Exception>>()?),
+            Value::Instance { class_name, fields, .. } => Value::Instance {
+                class_name: class_name.clone(),
+                fields: fields.iter().map(|(name, v)| Ok((name.clone(), Self::deep_copy(v)?))).collect::<Result<_, Exception>>()?,
+                id: next_instance_id(),
+            },
+            Value::BuiltinMethod { object, method_name } => Value::BuiltinMethod {
+                object: Box::new(Self::deep_copy(object)?),
+                method_name: method_name.clone(),
+            },
+            Value::File { .. } | Value::Channel { .. } | Value::Thread { .. } | Value::Process { .. } | Value::NativeFunction { .. } => {
+                return Err(Exception::new(ExceptionKind::TypeError, vec![format!("cannot deep-copy '{}' object", value.type_name())]));
+            }
+            other => other.clone(),
+        })
+    }
+
+    // Attributes scripts can read from a caught exception
+    fn exception_attr(exc: &Exception, name: &str) -> Result<Value, Exception> {
+        let chained = |link: &Option<Box<Exception>>| link.as_ref().map_or(Value::None, |e| Value::Exception((**e).clone()));
+        match name {
+            "kind" => Ok(Value::Str(format!("{:?}", exc.kind))),
+            "message" => Ok(Value::Str(exc.message())),
+            "args" => Ok(Value::List(exc.args.iter().cloned().map(Value::Str).collect())),
+            "notes" => Ok(Value::List(exc.notes.iter().cloned().map(Value::Str).collect())),
+            "cause" => Ok(chained(&exc.cause)),
+            "context" => Ok(chained(&exc.context)),
+            "suppress_context" => Ok(Value::Bool(exc.suppress_context)),
+            // The exit status a SystemExit asks for; None for other exceptions
+            "code" => Ok(exc.exit_code().map_or(Value::None, |code| Value::Int(code as i64))),
+            _ => Err(Exception::new(ExceptionKind::AttributeError, vec![format!("'{:?}' object has no attribute '{}'", exc.kind, name)])),
+        }
+    }
+
     // Helper for pattern matching
     fn pattern_match(val: &Value, pat: &Value) -> bool {
         match (val, pat) {
@@ -1739,7 +2299,7 @@ impl Interpreter {
     }
 
     // Helper to recursively collect fields and methods from base classes
-    fn collect_class_hierarchy(&self, class_name: &str) -> (HashMap<String, (Vec<String>, Expr)>, HashMap<String, Value>) {
+    fn collect_class_hierarchy(&self, class_name: &str) -> (HashMap<String, Function>, HashMap<String, Value>) {
         let mut methods = HashMap::new();
         let mut fields = HashMap::new();
         let mut current = self.env.get(class_name);
@@ -1760,26 +2320,54 @@ impl Interpreter {
     }
 }
 
+// Scripts that end without an explicit `run_exit_handlers()` still get their
+// `atexit()` handlers. Sub-interpreters share the list, so only the last holder runs it.
+impl Drop for Interpreter {
+    fn drop(&mut self) {
+        if Arc::strong_count(&self.exit_handlers) > 1 {
+            return;
+        }
+        for exc in self.run_exit_handlers() {
+            eprintln!("Exception ignored in atexit handler: {}", exc);
+        }
+    }
+}
+
+// Instances are numbered from 1 as they are created, so the same program shows
+// the same `<Point instance #3>` on every run
+fn next_instance_id() -> u64 {
+    static NEXT_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
 impl Value {
     pub fn to_display_string(&self) -> String {
-        match self {
+        self.display_with(&mut |_, _| Ok(None)).unwrap_or_default()
+    }
+
+    /// The display text, asking `custom` first for this value and each value
+    /// inside it (flagged as nested) so instances can supply their own text.
+    pub fn display_with(&self, custom: &mut DisplayHook) -> Result<String, Exception> {
+        if let Some(text) = custom(self, false)? {
+            return Ok(text);
+        }
+        let mut item = |value: &Value| match custom(value, true)? {
+            Some(text) => Ok(text),
+            None => value.display_with(&mut *custom),
+        };
+        Ok(match self {
             Value::Int(n) => {
                 format!("{}", *n)
             }
-            Value::Float(n) => {
-                if n.fract() == 0.0 {
-                    format!("{}", *n as i64)
-                } else {
-                    format!("{}", n)
-                }
-            }
+            Value::Float(n) => format_float(*n),
+            Value::Decimal(d) => d.to_string(),
             Value::Str(s) => s.clone(),
             Value::List(arr) => {
-                let items: Vec<String> = arr.iter().map(|v| v.to_display_string()).collect();
+                let items: Vec<String> = arr.iter().map(&mut item).collect::<Result<_, Exception>>()?;
                 format!("[{}]", items.join(", "))
             }
             Value::Dict(map) => {
-                let items: Vec<String> = map.iter().map(|(k, v)| format!("{}: {}", k.to_display_string(), v.to_display_string())).collect();
+                let items: Vec<String> = map.iter().map(|(k, v)| Ok(format!("{}: {}", item(k)?, item(v)?))).collect::<Result<_, Exception>>()?;
                 format!("{{{}}}", items.join(", "))
             }
             Value::Bool(b) => format!("{}", b),
@@ -1793,11 +2381,11 @@ impl Value {
             },
             Value::Range(r) => format!("range({}, {}, {})", r.start, r.stop, r.step),
             Value::Set(s) => {
-                let items: Vec<String> = s.iter().map(|v| v.to_display_string()).collect();
+                let items: Vec<String> = s.iter().map(&mut item).collect::<Result<_, Exception>>()?;
                 format!("{{{}}}", items.join(", "))
             }
             Value::FrozenSet(s) => {
-                let items: Vec<String> = s.iter().map(|v| v.to_display_string()).collect();
+                let items: Vec<String> = s.iter().map(&mut item).collect::<Result<_, Exception>>()?;
                 format!("frozenset({{{}}})", items.join(", "))
             }
             // Value::Iterator(_) => "<iterator object>".to_string(),
@@ -1806,20 +2394,52 @@ impl Value {
             Value::Ellipsis => "Ellipsis".to_string(),
             Value::Complex(r, i) => format!("({}{}{}j)", r, if *i >= 0.0 { "+" } else { "" }, i),
             Value::Tuple(t) => {
-                let items: Vec<String> = t.iter().map(|v| v.to_display_string()).collect();
+                let items: Vec<String> = t.iter().map(&mut item).collect::<Result<_, Exception>>()?;
                 format!("({})", items.join(", "))
             }
-            Value::Exception(e) => format!("<Exception: {:?}>", e), // More detailed exception display
+            Value::Exception(e) => {
+                let args: Vec<String> = e.args.iter().map(|arg| format!("{:?}", arg)).collect();
+                format!("{:?}({})", e.kind, args.join(", "))
+            }
             Value::BuiltinMethod { object, method_name } => {
-                format!("<method object {} of {}>", method_name, object.to_display_string())
+                format!("<method object {} of {}>", method_name, item(object)?)
             },
             Value::Class { name, .. } => {
                 format!("<class '{}'>", name)
             },
-            Value::Instance { class_name, fields } => {
-                format!("<{} instance at {:p}>", class_name, std::ptr::addr_of!(fields))
-            },
+            Value::Instance { class_name, id, .. } => format!("<{} instance #{}>", class_name, id),
             Value::MemoryView(_) => "<memoryview object>".to_string(),
+            Value::File { path, mode, handle } => {
+                let state = if handle.is_closed() { "closed" } else { "open" };
+                format!("<{} file '{}', mode '{}'>", state, path, mode)
+            },
+            Value::Channel { handle } => {
+                let state = if handle.is_closed() { "closed" } else { "open" };
+                format!("<{} channel>", state)
+            },
+            Value::Thread { handle } => {
+                let state = if handle.is_joined() { "joined" } else { "running" };
+                format!("<{} thread>", state)
+            },
+            Value::Process { handle } => match handle.poll() {
+                Ok(Some(code)) => format!("<exited process '{}' ({})>", handle.command(), code),
+                Ok(None) => format!("<running process '{}' {}>", handle.command(), handle.pid().unwrap_or_default()),
+                Err(_) => "<detached process>".to_string(),
+            },
+            Value::Module { name, path, .. } => format!("<module '{}' from '{}'>", name, path),
+            Value::NativeFunction { name, .. } => format!("<native function {}>", name),
+        })
+    }
+
+    /// Dict keys and set members must not change once stored, so mutable
+    /// containers (and tuples holding one) are rejected.
+    pub fn check_hashable(&self) -> Result<(), Exception> {
+        match self {
+            Value::List(_) | Value::Dict(_) | Value::Set(_) | Value::ByteArray(_) => {
+                Err(Exception::new(ExceptionKind::TypeError, vec![format!("unhashable type: '{}'", self.type_name())]))
+            }
+            Value::Tuple(items) => items.iter().try_for_each(Value::check_hashable),
+            _ => Ok(()),
         }
     }
 
@@ -1828,6 +2448,7 @@ impl Value {
             Value::Int(_) => "int",
             Value::Float(_) => "float",
             Value::Complex(_, _) => "complex",
+            Value::Decimal(_) => "Decimal",
             Value::Bool(_) => "bool",
             Value::Str(_) => "str",
             Value::Bytes(_) => "bytes",
@@ -1848,6 +2469,12 @@ impl Value {
             Value::BuiltinMethod { .. } => "builtin_method",
             Value::Class { name, .. } => "class",
             Value::Instance { class_name, .. } => "instance",
+            Value::File { .. } => "file",
+            Value::Channel { .. } => "channel",
+            Value::Thread { .. } => "thread",
+            Value::Process { .. } => "process",
+            Value::Module { .. } => "module",
+            Value::NativeFunction { .. } => "native_function",
         }
     }
 
@@ -1855,6 +2482,7 @@ impl Value {
         match self {
             Value::Int(n) => *n != 0,
             Value::Float(f) => *f != 0.0 && !f.is_nan(),
+            Value::Decimal(d) => !d.is_zero(),
             Value::Str(s) => !s.is_empty(),
             Value::List(l) => !l.is_empty(),
             Value::Tuple(t) => !t.is_empty(),
@@ -1868,12 +2496,36 @@ impl Value {
     }
 }
 
+/// Shortest text that reads back as the same float, in the form Python prints:
+/// `2.0`, `0.1`, `1e+16`, `1.5e-07`, `nan`, `-inf`.
+pub fn format_float(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    // Debug already picks the shortest round-trip digits and switches to an exponent
+    // at the same magnitudes Python does; only the exponent is spelled differently
+    let repr = format!("{:?}", f);
+    match repr.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let (sign, digits) = exponent.strip_prefix('-').map_or(('+', exponent), |digits| ('-', digits));
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => repr,
+    }
+}
+
+// Value equality is an equivalence relation so dict keys and set members behave:
+// NaN equals itself here, while the `==` operator follows IEEE and reports nan != nan.
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Value::Int(a), Value::Int(b)) => a == b,
-            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
             (Value::Complex(ar, ai), Value::Complex(br, bi)) => ar == br && ai == bi,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
             (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::Str(a), Value::Str(b)) => a == b,
             (Value::Bytes(a), Value::Bytes(b)) => a == b,
@@ -1887,9 +2539,17 @@ impl PartialEq for Value {
             (Value::None, Value::None) => true,
             (Value::NotImplemented, Value::NotImplemented) => true,
             (Value::Ellipsis, Value::Ellipsis) => true,
+            (Value::File { handle: a, .. }, Value::File { handle: b, .. }) => a.same_handle(b),
+            (Value::Channel { handle: a }, Value::Channel { handle: b }) => a.same_handle(b),
+            (Value::Thread { handle: a }, Value::Thread { handle: b }) => a.same_handle(b),
+            (Value::Process { handle: a }, Value::Process { handle: b }) => a.same_handle(b),
+            (Value::Module { path: a, .. }, Value::Module { path: b, .. }) => a == b,
+            (Value::NativeFunction { name: a, handle: ha }, Value::NativeFunction { name: b, handle: hb }) => a == b && ha.same_handle(hb),
             // Allow comparison between Int and Float
             (Value::Int(a), Value::Float(b)) => (*a as f64) == *b,
             (Value::Float(a), Value::Int(b)) => *a == (*b as f64),
+            (Value::Int(a), Value::Decimal(b)) => rust_decimal::Decimal::from(*a) == *b,
+            (Value::Decimal(a), Value::Int(b)) => *a == rust_decimal::Decimal::from(*b),
             // Allow comparison between Int/Float and Bool
             (Value::Int(a), Value::Bool(b)) => (*a != 0) == *b,
             (Value::Bool(a), Value::Int(b)) => *a == (*b != 0),
@@ -1907,12 +2567,20 @@ impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
             Value::Int(i) => i.hash(state),
-            Value::Float(f) => f.to_bits().hash(state), // Hash float bits
+            // Whole floats and bools equal the matching int, so they must hash like it
+            Value::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => (*f as i64).hash(state),
+            Value::Float(f) if f.is_nan() => f64::NAN.to_bits().hash(state),
+            Value::Float(f) => f.to_bits().hash(state),
+            // Likewise whole decimals; otherwise 1.5 and 1.50 are equal and must hash alike
+            Value::Decimal(d) => match d.fract().is_zero().then(|| i64::try_from(*d).ok()).flatten() {
+                Some(i) => i.hash(state),
+                None => d.normalize().hash(state),
+            },
             Value::Complex(r, i) => {
                 r.to_bits().hash(state);
                 i.to_bits().hash(state);
             },
-            Value::Bool(b) => b.hash(state),
+            Value::Bool(b) => (*b as i64).hash(state),
             Value::Str(s) => s.hash(state),
             Value::Bytes(b) => b.hash(state),
             Value::ByteArray(b) => b.hash(state),
@@ -1960,6 +2628,12 @@ impl Hash for Value {
                 // Hash the type name for instance
                 "instance".hash(state);
             },
+            Value::File { path, .. } => path.hash(state),
+            Value::Channel { .. } => "channel".hash(state),
+            Value::Thread { .. } => "thread".hash(state),
+            Value::Process { .. } => "process".hash(state),
+            Value::Module { path, .. } => path.hash(state),
+            Value::NativeFunction { name, .. } => name.hash(state),
         }
     }
 }
@@ -0,0 +1,36 @@
+// Ctrl-C delivery: a process-wide flag the interpreter polls between expressions
+
+use super::exceptions::{Exception, ExceptionKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+fn pending() -> &'static Arc<AtomicBool> {
+    static PENDING: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    PENDING.get_or_init(Arc::default)
+}
+
+/// The flag a signal handler sets on SIGINT. Hosts register it with their
+/// signal library; the interpreter turns it into a KeyboardInterrupt.
+pub fn flag() -> Arc<AtomicBool> {
+    Arc::clone(pending())
+}
+
+/// Ask whichever interpreter evaluates next to raise KeyboardInterrupt.
+pub fn interrupt() {
+    pending().store(true, Ordering::SeqCst);
+}
+
+/// Clear a pending interrupt, returning whether there was one. The REPL uses
+/// this to drop a line that Ctrl-C was pressed on.
+pub fn take() -> bool {
+    pending().swap(false, Ordering::SeqCst)
+}
+
+/// The checkpoint run before each expression: raise KeyboardInterrupt once per Ctrl-C,
+/// so try/catch handlers and `with` blocks still get to clean up.
+pub(crate) fn check() -> Result<(), Exception> {
+    if take() {
+        return Err(Exception::new(ExceptionKind::KeyboardInterrupt, vec![]));
+    }
+    Ok(())
+}
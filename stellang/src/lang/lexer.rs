@@ -1,13 +1,16 @@
 // Lexer for StelLang
 
 use super::exceptions::{Exception, ExceptionKind};
+use super::symbol::Symbol;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Integer(i64),
     Float(f64),
-    Ident(String),
+    Ident(Symbol),
     String(String),
+    LoopLabel(Symbol), // 'name
+    Newline,           // end of a line; consecutive blank lines collapse into one
     Assign,
     Plus,
     Minus,
@@ -21,6 +24,7 @@ pub enum Token {
     Semicolon,
     If,
     Else,
+    Elif,
     While,
     Fn,
     Return,
@@ -87,8 +91,10 @@ pub enum Token {
     Try,
     Catch,
     Finally,
+    Defer,
     With,
     Do,
+    Loop,
     For,
     Foreach,
     Of,
@@ -175,9 +181,144 @@ pub enum Token {
     RParenTok,
 }
 
+/// Words `read_ident` turns into keyword tokens rather than identifiers.
+pub const KEYWORDS: &[&str] = &[
+    "if", "else", "elif", "while", "loop", "do", "fn", "return", "break", "continue", "and", "or", "not", "let",
+    "const", "true", "false", "null", "print", "input", "match", "case", "struct", "class", "abstract", "extends",
+    "enum", "for", "in", "is", "try", "catch", "finally", "defer", "throw", "from", "import", "with", "as",
+];
+
+/// The version of the grammar a file is written in, chosen with a `#lang`
+/// line at the top (after the `#!` line, if there is one).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Files without a `#lang` line: the grammar as it has always been,
+    /// deprecated forms included
+    #[default]
+    Stel1,
+    /// `#lang stel2`: deprecated syntax is gone, and `extends` is an ordinary name
+    Stel2,
+}
+
+impl Dialect {
+    pub const ALL: &[Dialect] = &[Dialect::Stel1, Dialect::Stel2];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Stel1 => "stel1",
+            Dialect::Stel2 => "stel2",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|dialect| dialect.name() == name)
+    }
+}
+
+/// A place in the source, as a line and a column counted in characters, both from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Where a token starts and where it ends (just past its last character).
+pub type Span = (Position, Position);
+
+/// What made a piece of source unreadable as a token.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    /// A backslash in a string followed by a character that isn't an escape
+    BadEscape(char),
+    /// A string still open at the end of the source. Notes whether it ran on
+    /// past the line it starts on, and the last `\"` on that line, which may have
+    /// been meant to close it
+    UnterminatedString { multiline: bool, escaped_quote: Option<Position> },
+    /// A character that can't start any token
+    InvalidCharacter(char),
+    /// A `'` not followed by a loop label name
+    MissingLabel,
+    /// Digits that don't fit the number type, with the parser's complaint
+    InvalidNumber { float: bool, reason: String },
+    /// A `#lang` line naming a dialect there isn't
+    UnknownDialect(String),
+}
+
+/// A lexical error and the source it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl LexError {
+    pub fn message(&self) -> String {
+        match &self.kind {
+            LexErrorKind::BadEscape(ch) => format!("Invalid escape sequence '\\{}'", ch),
+            LexErrorKind::UnterminatedString { .. } => "Unterminated string literal".to_string(),
+            LexErrorKind::InvalidCharacter(ch) => format!("Unexpected character: {}", ch),
+            LexErrorKind::MissingLabel => "Expected label name after '\''".to_string(),
+            LexErrorKind::InvalidNumber { float: true, reason } => format!("Invalid float literal: {}", reason),
+            LexErrorKind::InvalidNumber { float: false, reason } => format!("Invalid integer literal: {}", reason),
+            LexErrorKind::UnknownDialect(name) => format!("Unknown dialect '{}' in #lang line", name),
+        }
+    }
+
+    /// A suggestion for fixing the error, when there's a likely one.
+    pub fn hint(&self) -> Option<String> {
+        match &self.kind {
+            LexErrorKind::BadEscape(_) => Some("write '\\\\' for a backslash".to_string()),
+            LexErrorKind::UnterminatedString { escaped_quote: Some(quote), .. } => {
+                Some(format!("the '\"' at column {} is escaped by the backslash before it; write '\\\\' for a backslash", quote.column))
+            }
+            LexErrorKind::UnterminatedString { multiline: true, .. } => {
+                Some(format!("the string runs from line {} to the end of the file; is its closing '\"' missing?", self.span.0.line))
+            }
+            LexErrorKind::UnknownDialect(_) => {
+                let names: Vec<&str> = Dialect::ALL.iter().map(|dialect| dialect.name()).collect();
+                Some(format!("the dialects are {}", names.join(", ")))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for LexError {
+    /// `line:column: message`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.span.0.line, self.span.0.column, self.message())
+    }
+}
+
+impl From<LexError> for Exception {
+    /// A SyntaxError, or a ValueError for a number literal out of range, with
+    /// a note saying where it is.
+    fn from(error: LexError) -> Self {
+        let kind = match error.kind {
+            LexErrorKind::InvalidNumber { .. } => ExceptionKind::ValueError,
+            _ => ExceptionKind::SyntaxError,
+        };
+        let mut exception = Exception::new(kind, vec![error.message()]);
+        exception.add_note(format!("at line {}, column {}", error.span.0.line, error.span.0.column));
+        if let Some(hint) = error.hint() {
+            exception.add_note(format!("hint: {}", hint));
+        }
+        exception
+    }
+}
+
 pub struct Lexer {
     input: Vec<char>,
     pos: usize,
+    // No Newline is emitted before the first token or right after another Newline
+    at_line_start: bool,
+    line: usize,
+    // Index of the first character of the current line
+    line_start: usize,
+    token_start: Position,
+    dialect: Dialect,
+    // The word being read, reused so that only names never seen before allocate
+    spelling: String,
 }
 
 impl Lexer {
@@ -185,9 +326,32 @@ impl Lexer {
         Self {
             input: input.chars().collect(),
             pos: 0,
+            at_line_start: true,
+            line: 1,
+            line_start: 0,
+            token_start: Position { line: 1, column: 1 },
+            dialect: Dialect::default(),
+            // Room for all but unusually long names, so reading words doesn't grow it
+            spelling: String::with_capacity(32),
         }
     }
 
+    /// The dialect the source declared, as far as it has been read; the
+    /// `#lang` line is read with the first token.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    fn position(&self) -> Position {
+        Position { line: self.line, column: self.pos - self.line_start + 1 }
+    }
+
+    /// Where the token last returned by `next_token` lies, or the text read so
+    /// far of the one it failed on.
+    pub fn span(&self) -> Span {
+        (self.token_start, self.position())
+    }
+
     fn peek(&self) -> Option<char> {
         self.input.get(self.pos).copied()
     }
@@ -201,21 +365,71 @@ impl Lexer {
         if ch.is_some() {
             self.pos += 1;
         }
+        if ch == Some('\n') {
+            self.line += 1;
+            self.line_start = self.pos;
+        }
         ch
     }
 
-    fn skip_whitespace(&mut self) {
+    /// Skip whitespace and comments, reporting whether a line break was crossed.
+    /// A `#!/usr/bin/env stellang` line is a comment like any other.
+    fn skip_whitespace(&mut self) -> bool {
+        let mut newline = false;
         while let Some(ch) = self.peek() {
-            if ch.is_whitespace() {
+            if ch == '#' {
+                while let Some(ch) = self.peek() {
+                    if ch == '\n' { break; }
+                    self.advance();
+                }
+            } else if ch.is_whitespace() {
+                newline |= ch == '\n';
                 self.advance();
             } else {
                 break;
             }
         }
+        newline
     }
 
-    fn read_number(&mut self) -> Result<Token, Exception> {
-        let mut num = String::new();
+    // `#lang <dialect>` on the first line, or on the second after a `#!` line.
+    // Only read before the first token, so later `#lang` lines are comments
+    fn read_dialect(&mut self) -> Result<(), LexError> {
+        if self.looking_at("#!") {
+            while self.advance().is_some_and(|ch| ch != '\n') {}
+        }
+        if !self.looking_at("#lang ") {
+            return Ok(());
+        }
+        for _ in 0.."#lang".len() {
+            self.advance();
+        }
+        while self.peek().is_some_and(|ch| ch == ' ' || ch == '\t') {
+            self.advance();
+        }
+        self.token_start = self.position();
+        let mut name = String::new();
+        while let Some(ch) = self.peek().filter(|ch| !ch.is_whitespace()) {
+            name.push(ch);
+            self.advance();
+        }
+        self.dialect = Dialect::parse(&name).ok_or_else(|| self.error(LexErrorKind::UnknownDialect(name)))?;
+        Ok(())
+    }
+
+    fn looking_at(&self, text: &str) -> bool {
+        let rest = &self.input[self.pos.min(self.input.len())..];
+        rest.iter().copied().take(text.chars().count()).eq(text.chars())
+    }
+
+    fn error(&self, kind: LexErrorKind) -> LexError {
+        LexError { kind, span: self.span() }
+    }
+
+    fn read_number(&mut self) -> Result<Token, LexError> {
+        // Spelled out in the word buffer, handed back once parsed
+        let mut num = std::mem::take(&mut self.spelling);
+        num.clear();
         let mut is_float = false;
         while let Some(ch) = self.peek() {
             if ch.is_ascii_digit() {
@@ -229,27 +443,61 @@ impl Lexer {
                 break;
             }
         }
-        if is_float {
-            num.parse::<f64>().map(Token::Float).map_err(|e| Exception::new(ExceptionKind::ValueError, vec![format!("Invalid float literal: {}", e)]))
+        // Exponent: 1e16, 2.5E-3, 1e+07
+        if let Some('e' | 'E') = self.peek() {
+            let sign = matches!(self.peek_next(), Some('+' | '-'));
+            let first_digit = self.input.get(self.pos + 1 + sign as usize).copied();
+            if first_digit.is_some_and(|ch| ch.is_ascii_digit()) {
+                is_float = true;
+                num.push('e');
+                self.advance();
+                if sign {
+                    num.push(self.advance().unwrap_or('+'));
+                }
+                while let Some(ch) = self.peek().filter(char::is_ascii_digit) {
+                    num.push(ch);
+                    self.advance();
+                }
+            }
+        }
+        let parsed = match is_float {
+            true => num.parse::<f64>().map(Token::Float).map_err(|e| e.to_string()),
+            false => num.parse::<i64>().map(Token::Integer).map_err(|e| e.to_string()),
+        };
+        self.spelling = num;
+        parsed.map_err(|reason| self.error(LexErrorKind::InvalidNumber { float: is_float, reason }))
+    }
+
+    fn read_loop_label(&mut self) -> Result<Token, LexError> {
+        self.advance(); // consume '\''
+        let name = self.read_word();
+        if name.is_empty() {
+            Err(self.error(LexErrorKind::MissingLabel))
         } else {
-            num.parse::<i64>().map(Token::Integer).map_err(|e| Exception::new(ExceptionKind::ValueError, vec![format!("Invalid integer literal: {}", e)]))
+            Ok(Token::LoopLabel(Symbol::intern(name)))
         }
     }
 
-    fn read_ident(&mut self) -> Token {
-        let mut ident = String::new();
-        while let Some(ch) = self.peek() {
-            if ch.is_alphanumeric() || ch == '_' {
-                ident.push(ch);
-                self.advance();
-            } else {
-                break;
-            }
+    // Read letters, digits and underscores into `spelling`
+    fn read_word(&mut self) -> &str {
+        self.spelling.clear();
+        while let Some(ch) = self.peek().filter(|&ch| ch.is_alphanumeric() || ch == '_') {
+            self.spelling.push(ch);
+            self.advance();
         }
-        match ident.as_str() {
+        &self.spelling
+    }
+
+    fn read_ident(&mut self) -> Token {
+        let dialect = self.dialect;
+        let word = self.read_word();
+        match word {
             "if" => Token::If,
             "else" => Token::Else,
+            "elif" => Token::Elif,
             "while" => Token::While,
+            "loop" => Token::Loop,
+            "do" => Token::Do,
             "fn" => Token::Fn,
             "return" => Token::Return,
             "break" => Token::Break,
@@ -267,50 +515,132 @@ impl Lexer {
             "match" => Token::Match,
             "case" => Token::Case,
             "struct" => Token::Struct,
+            "class" => Token::Class,
+            "abstract" => Token::Abstract,
+            "extends" if dialect == Dialect::Stel1 => Token::Extends,
             "enum" => Token::Enum,
             "for" => Token::For,
             "in" => Token::In,
             "is" => Token::Is,
             "try" => Token::Try,
             "catch" => Token::Catch,
+            "finally" => Token::Finally,
+            "defer" => Token::Defer,
             "throw" => Token::Throw,
+            "from" => Token::From,
             "import" => Token::Import,
-            _ => Token::Ident(ident),
+            "with" => Token::With,
+            "as" => Token::As,
+            _ => Token::Ident(Symbol::intern(word)),
         }
     }
 
-    fn read_string(&mut self) -> Result<Token, Exception> {
+    // A string literal. The whole literal is read even past a bad escape, which is
+    // then reported with the span of just the escape. An unterminated one is
+    // reported at its opening quote, and lexing resumes at the end of that line so
+    // the rest of the file isn't swallowed into the string
+    fn read_string(&mut self) -> Result<Token, LexError> {
         let mut s = String::new();
+        let quote = self.position();
         self.advance(); // skip opening quote
-        let mut closed = false;
-        while let Some(ch) = self.peek() {
-            if ch == '"' {
-                self.advance();
-                closed = true;
-                break;
+        let mut bad_escape = None;
+        let mut escaped_quote = None;
+        // Where the first line of the string ends: position, line start and line
+        let mut first_line_end = None;
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.advance();
+                    break;
+                }
+                None => {
+                    let kind = LexErrorKind::UnterminatedString { multiline: first_line_end.is_some(), escaped_quote };
+                    if let Some((pos, line_start, line)) = first_line_end {
+                        (self.pos, self.line_start, self.line) = (pos, line_start, line);
+                    }
+                    return Err(LexError { kind, span: (quote, Position { column: quote.column + 1, ..quote }) });
+                }
+                Some('\\') => {
+                    let start = self.position();
+                    self.advance();
+                    let Some(ch) = self.peek() else { continue };
+                    if ch == '\n' {
+                        first_line_end.get_or_insert((self.pos, self.line_start, self.line));
+                    }
+                    self.advance();
+                    match ch {
+                        'n' => s.push('\n'),
+                        't' => s.push('\t'),
+                        'r' => s.push('\r'),
+                        '0' => s.push('\0'),
+                        '"' if first_line_end.is_none() => {
+                            escaped_quote = Some(Position { column: start.column + 1, ..start });
+                            s.push('"');
+                        }
+                        '"' => {
+                            s.push('"');
+                        }
+                        // A backslash at the end of a line joins it to the next
+                        '\n' => {}
+                        '\\' | '\'' => s.push(ch),
+                        ch => {
+                            bad_escape.get_or_insert(LexError { kind: LexErrorKind::BadEscape(ch), span: (start, self.position()) });
+                        }
+                    }
+                }
+                Some(ch) => {
+                    if ch == '\n' {
+                        first_line_end.get_or_insert((self.pos, self.line_start, self.line));
+                    }
+                    s.push(ch);
+                    self.advance();
+                }
             }
-            s.push(ch);
-            self.advance();
         }
-        if closed {
-            Ok(Token::String(s))
-        } else {
-            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Unterminated string literal".to_string()]))
+        match bad_escape {
+            Some(error) => Err(error),
+            None => Ok(Token::String(s)),
         }
     }
 
     pub fn next_token(&mut self) -> Result<Token, Exception> {
-        self.skip_whitespace();
-        // Skip comments
-        if let Some('#') = self.peek() {
-            while let Some(ch) = self.peek() {
-                if ch == '\n' { break; }
-                self.advance();
+        self.read_token().map_err(Exception::from)
+    }
+
+    /// Every token with its span, and every lexical error. After an error the
+    /// lexer skips to the next whitespace and carries on, so one bad character
+    /// doesn't hide the problems after it.
+    pub fn tokenize(&mut self) -> (Vec<(Token, Span)>, Vec<LexError>) {
+        let (mut tokens, mut errors) = (Vec::new(), Vec::new());
+        loop {
+            match self.read_token() {
+                Ok(Token::EOF) => break,
+                Ok(token) => tokens.push((token, self.span())),
+                Err(error) => {
+                    errors.push(error);
+                    while self.peek().is_some_and(|ch| !ch.is_whitespace()) {
+                        self.advance();
+                    }
+                }
             }
-            self.skip_whitespace();
         }
+        (tokens, errors)
+    }
+
+    fn read_token(&mut self) -> Result<Token, LexError> {
+        if self.pos == 0 {
+            self.read_dialect()?;
+        }
+        let newline = self.skip_whitespace();
+        self.token_start = self.position();
+        if newline && !self.at_line_start && self.peek().is_some() {
+            self.at_line_start = true;
+            return Ok(Token::Newline);
+        }
+        self.at_line_start = false;
         match self.peek() {
             Some('"') => self.read_string(),
+            Some('\'') => self.read_loop_label(),
             Some('=') => {
                 self.advance();
                 if let Some('=') = self.peek() {
@@ -385,12 +715,16 @@ impl Lexer {
             Some('{') => { self.advance(); Ok(Token::LBrace) },
             Some('}') => { self.advance(); Ok(Token::RBrace) },
             Some(',') => { self.advance(); Ok(Token::Comma) },
+            Some('@') => { self.advance(); Ok(Token::At) },
             Some(';') => { self.advance(); Ok(Token::Semicolon) },
             Some('.') => { self.advance(); Ok(Token::Dot) }, // Added for attribute access
             Some(':') => { self.advance(); Ok(Token::Colon) }, // Added for dictionary literals
             Some(ch) if ch.is_ascii_digit() => self.read_number(),
             Some(ch) if ch.is_alphabetic() || ch == '_' => Ok(self.read_ident()),
-            Some(ch) => Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Unexpected character: {}", ch)])),
+            Some(ch) => {
+                self.advance();
+                Err(self.error(LexErrorKind::InvalidCharacter(ch)))
+            }
             None => Ok(Token::EOF),
         }
     }
@@ -0,0 +1,98 @@
+// Modules written in StelLang: `import name` finds `name.stel` on the module
+// search path. Each file runs once per process; later imports of it (from any
+// interpreter) share the loaded module until the file changes.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::interpreter::Value;
+
+/// The extension of StelLang source files.
+pub const EXTENSION: &str = "stel";
+
+static SEARCH_PATH: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+
+static LOADED: Mutex<Option<HashMap<PathBuf, Loaded>>> = Mutex::new(None);
+
+/// A loaded module and the modification time of its file when it was loaded.
+struct Loaded {
+    modified: Option<SystemTime>,
+    module: Value,
+}
+
+thread_local! {
+    // Modules being loaded on this thread, outermost first, to catch import cycles
+    static LOADING: RefCell<Vec<(String, PathBuf)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Directories searched, in order, for `import`ed source modules. An empty
+/// search path means the current directory.
+pub fn set_search_path(dirs: Vec<PathBuf>) {
+    *SEARCH_PATH.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = dirs;
+}
+
+pub fn search_path() -> Vec<PathBuf> {
+    let dirs = SEARCH_PATH.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    if dirs.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        dirs
+    }
+}
+
+/// The source file `import name` loads: `name.stel` in the first search path
+/// directory that has one, or an installed package's `name/src/main.stel`.
+pub fn resolve(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    search_path().into_iter().find_map(|dir| {
+        let file = dir.join(format!("{}.{}", name, EXTENSION));
+        let package = dir.join(name).join("src").join(format!("main.{}", EXTENSION));
+        [file, package].into_iter().find(|candidate| candidate.is_file())
+    })
+}
+
+/// A module loaded earlier from `path`, unless the file has changed since.
+pub(crate) fn cached(path: &Path) -> Option<Value> {
+    let loaded = LOADED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let loaded = loaded.as_ref()?.get(path)?;
+    (loaded.modified == modified_time(path)).then(|| loaded.module.clone())
+}
+
+pub(crate) fn store(path: &Path, module: Value) {
+    let mut loaded = LOADED.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    loaded.get_or_insert_with(HashMap::new).insert(path.to_path_buf(), Loaded { modified: modified_time(path), module });
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Marks a module as loading until dropped. Entering one that is already
+/// loading on this thread raises ImportError naming the cycle.
+pub(crate) struct Loading;
+
+impl Loading {
+    pub(crate) fn enter(name: &str, path: &Path) -> Result<Loading, Exception> {
+        LOADING.with(|loading| {
+            let mut loading = loading.borrow_mut();
+            if let Some(start) = loading.iter().position(|(_, loaded)| loaded == path) {
+                let cycle: Vec<&str> = loading[start..].iter().map(|(name, _)| name.as_str()).chain([name]).collect();
+                return Err(Exception::new(ExceptionKind::ImportError, vec![format!("circular import: {}", cycle.join(" -> "))]));
+            }
+            loading.push((name.to_string(), path.to_path_buf()));
+            Ok(Loading)
+        })
+    }
+}
+
+impl Drop for Loading {
+    fn drop(&mut self) {
+        LOADING.with(|loading| loading.borrow_mut().pop());
+    }
+}
@@ -0,0 +1,266 @@
+// Native extension modules: Rust functions exposed to StelLang scripts
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::hashing::ValueMap;
+use super::interpreter::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Bumped whenever `NativeModule` or `NativeFn` change shape. Plugins built
+/// against a different version are refused instead of being called.
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the registration function every plugin library exports.
+pub const INIT_SYMBOL: &[u8] = b"stellang_module_init";
+/// Name of the `u32` static holding the ABI version a plugin was built with.
+pub const ABI_SYMBOL: &[u8] = b"STELLANG_ABI_VERSION";
+/// Name of the `u64` static holding the `ABI_FINGERPRINT` a plugin was built with.
+pub const FINGERPRINT_SYMBOL: &[u8] = b"STELLANG_ABI_FINGERPRINT";
+
+/// Plugins share Rust types such as `Value` and `NativeModule` with the
+/// interpreter, and Rust makes no promise about their layout between compiler
+/// versions or builds of this crate. A plugin is only called when it was built
+/// by the same compiler, against the same StelLang version, and sees the same
+/// sizes and alignments for the types it is handed.
+pub const ABI_FINGERPRINT: u64 = {
+    let mut hash = fnv1a(FNV_OFFSET, &ABI_VERSION.to_le_bytes());
+    hash = fnv1a(hash, env!("STELLANG_RUSTC_VERSION").as_bytes());
+    hash = fnv1a(hash, env!("CARGO_PKG_VERSION").as_bytes());
+    let layouts = [
+        std::mem::size_of::<Value>(), std::mem::align_of::<Value>(),
+        std::mem::size_of::<Exception>(), std::mem::align_of::<Exception>(),
+        std::mem::size_of::<NativeModule>(), std::mem::align_of::<NativeModule>(),
+        std::mem::size_of::<NativeFn>(),
+        std::mem::size_of::<String>(),
+        std::mem::size_of::<Vec<Value>>(),
+        std::mem::size_of::<HashMap<String, Value>>(),
+    ];
+    let mut i = 0;
+    while i < layouts.len() {
+        hash = fnv1a(hash, &(layouts[i] as u64).to_le_bytes());
+        i += 1;
+    }
+    hash
+};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+const fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Directory, relative to the project root, where `stel install` places native libraries.
+pub const NATIVE_DEPS_DIR: &str = "dependencies/native";
+
+/// Signature of a function implemented in Rust and callable from scripts.
+pub type NativeFn = fn(&[Value]) -> Result<Value, Exception>;
+
+/// Signature of `stellang_module_init`.
+pub type ModuleInit = unsafe extern "C" fn(&mut NativeModule);
+
+/// Native functions the interpreter answers itself, by their
+/// `module.function` names, because they need its state, such as its function
+/// table. Modules register them with `NativeModule::register_interpreter_fn`.
+const INTERPRETER_FUNCTIONS: &[&str] = &["config.load", "config.load_env", "inspect.signature", "inspect.getsource", "debug.heap_stats", "debug.track_allocations", "http.serve", "sys.getrecursionlimit", "sys.setrecursionlimit"];
+
+/// Which of the functions the interpreter answers itself `callable` is, if any.
+pub(crate) fn interpreter_function(callable: &Value) -> Option<&'static str> {
+    match callable {
+        Value::NativeFunction { name, .. } => INTERPRETER_FUNCTIONS.iter().copied().find(|func| func == name),
+        _ => None,
+    }
+}
+
+/// Functions and values a plugin registers under its module name.
+#[derive(Debug, Default)]
+pub struct NativeModule {
+    pub name: String,
+    functions: HashMap<String, NativeFn>,
+    values: HashMap<String, Value>,
+}
+
+impl NativeModule {
+    pub fn new(name: &str) -> Self {
+        NativeModule { name: name.to_string(), ..Default::default() }
+    }
+
+    /// Expose `func` to scripts as `module.name(...)`.
+    pub fn register_fn(&mut self, name: &str, func: NativeFn) {
+        self.functions.insert(name.to_string(), func);
+    }
+
+    /// Expose `name`, one of `INTERPRETER_FUNCTIONS`, as `module.name`.
+    pub(crate) fn register_interpreter_fn(&mut self, name: &str) {
+        // Never run: `NativeHandle::call` refuses these by name
+        self.register_fn(name, |_| Ok(Value::None));
+    }
+
+    /// Expose a constant, class or other value as `module.name`.
+    pub fn register_value(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Build the dict a script sees after `import native`. Functions keep the
+    /// library alive so it is not unloaded while a script can still call into it.
+    // Dict keys are plain strings, never values with interior mutability
+    #[allow(clippy::mutable_key_type)]
+    pub(crate) fn into_value(self, library: Option<Arc<Library>>) -> Value {
+        let mut members = ValueMap::default();
+        for (name, func) in self.functions {
+            let handle = NativeHandle { func: Some(func), library: library.clone() };
+            members.insert(Value::Str(name.clone()), Value::NativeFunction { name: format!("{}.{}", self.name, name), handle });
+        }
+        for (name, value) in self.values {
+            members.insert(Value::Str(name), value);
+        }
+        Value::Dict(members)
+    }
+}
+
+/// Export a plugin's registration function with the symbols the loader expects:
+///
+/// ```ignore
+/// fn init(module: &mut stellang::lang::native::NativeModule) {
+///     module.register_fn("square", |args| match args {
+///         [Value::Int(n)] => Ok(Value::Int(n * n)),
+///         _ => Err(Exception::new(ExceptionKind::TypeError, vec!["square() expects an int".to_string()])),
+///     });
+/// }
+/// stellang::native_module!(init);
+/// ```
+#[macro_export]
+macro_rules! native_module {
+    ($init:path) => {
+        #[no_mangle]
+        pub static STELLANG_ABI_VERSION: u32 = $crate::lang::native::ABI_VERSION;
+
+        #[no_mangle]
+        pub static STELLANG_ABI_FINGERPRINT: u64 = $crate::lang::native::ABI_FINGERPRINT;
+
+        #[no_mangle]
+        pub extern "C" fn stellang_module_init(module: &mut $crate::lang::native::NativeModule) {
+            $init(module)
+        }
+    };
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use libloading::Library;
+
+/// Dynamic loading is unavailable in the browser build.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug)]
+pub struct Library;
+
+/// Callable handle stored in `Value::NativeFunction`.
+#[derive(Debug, Clone, Default)]
+pub struct NativeHandle {
+    func: Option<NativeFn>,
+    #[allow(dead_code)]
+    library: Option<Arc<Library>>,
+}
+
+impl NativeHandle {
+    pub fn call(&self, name: &str, args: &[Value]) -> Result<Value, Exception> {
+        // Called some other way than directly by a script, where the interpreter can't answer
+        if INTERPRETER_FUNCTIONS.contains(&name) {
+            return Err(Exception::new(ExceptionKind::RuntimeError, vec![format!("{}() can only be called from a script", name)]));
+        }
+        match self.func {
+            Some(func) => func(args),
+            // Handles are dropped by snapshots; the module must be imported again
+            None => Err(Exception::new(ExceptionKind::RuntimeError, vec![format!("native function '{}' is no longer loaded", name)])),
+        }
+    }
+
+    pub fn same_handle(&self, other: &NativeHandle) -> bool {
+        self.func.map(|f| f as usize) == other.func.map(|f| f as usize)
+    }
+}
+
+/// Locate the shared library for `import native "name"`. Explicit paths are
+/// used as-is; bare names are looked up as `libname.so` (or the platform
+/// equivalent) in `dependencies/native` and every directory in
+/// `STELLANG_NATIVE_PATH`.
+pub fn resolve(name: &str) -> Option<PathBuf> {
+    let direct = Path::new(name);
+    if direct.components().count() > 1 || direct.extension().is_some() {
+        return direct.exists().then(|| direct.to_path_buf());
+    }
+    let file_name = library_filename(name);
+    search_path().into_iter().map(|dir| dir.join(&file_name)).find(|path| path.exists())
+}
+
+/// The directories bare `import native` names are looked up in, in order;
+/// scripts see them as `sys.path`.
+pub fn search_path() -> Vec<PathBuf> {
+    // Not the current directory: a library dropped where a script happens to
+    // run would be loaded, and run, in place of the one meant
+    let mut dirs = vec![PathBuf::from(NATIVE_DEPS_DIR)];
+    if let Some(extra) = std::env::var_os("STELLANG_NATIVE_PATH") {
+        dirs.extend(std::env::split_paths(&extra));
+    }
+    dirs
+}
+
+/// Variable a module is bound to: `import native "build/libfast.so"` is used as `fast`.
+pub fn binding_name(spec: &str) -> String {
+    let stem = Path::new(spec).file_stem().and_then(|s| s.to_str()).unwrap_or(spec);
+    if stem == spec {
+        return stem.to_string();
+    }
+    stem.strip_prefix(std::env::consts::DLL_PREFIX).filter(|s| !s.is_empty()).unwrap_or(stem).to_string()
+}
+
+/// Platform file name for a native library called `name`.
+pub fn library_filename(name: &str) -> String {
+    format!("{}{}{}", std::env::consts::DLL_PREFIX, name, std::env::consts::DLL_SUFFIX)
+}
+
+/// Load a plugin library and run its registration function.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(name: &str) -> Result<Value, Exception> {
+    let path = resolve(name).ok_or_else(|| {
+        Exception::new(ExceptionKind::ModuleNotFoundError, vec![format!("no native module named '{}'", name)])
+    })?;
+    // Safety: loading runs the library's initializers; native modules are trusted code,
+    // which is why sandboxed interpreters cannot import them
+    let library = unsafe { Library::new(&path) }.map_err(|e| import_error(&path, e))?;
+    let version = unsafe { library.get::<*const u32>(ABI_SYMBOL) }.map_err(|e| import_error(&path, e))?;
+    let version = unsafe { **version };
+    if version != ABI_VERSION {
+        return Err(Exception::new(ExceptionKind::ImportError, vec![format!(
+            "{} was built for native ABI {}, but this interpreter uses ABI {}", path.display(), version, ABI_VERSION
+        )]));
+    }
+    let fingerprint = unsafe { library.get::<*const u64>(FINGERPRINT_SYMBOL) }.map_err(|e| import_error(&path, e))?;
+    if unsafe { **fingerprint } != ABI_FINGERPRINT {
+        return Err(Exception::new(ExceptionKind::ImportError, vec![format!(
+            "{} was built with a different Rust compiler or StelLang version than this interpreter ({}, stellang {}); rebuild it",
+            path.display(), env!("STELLANG_RUSTC_VERSION"), env!("CARGO_PKG_VERSION")
+        )]));
+    }
+    let mut module = NativeModule::new(&binding_name(name));
+    unsafe {
+        let init = library.get::<ModuleInit>(INIT_SYMBOL).map_err(|e| import_error(&path, e))?;
+        init(&mut module);
+    }
+    Ok(module.into_value(Some(Arc::new(library))))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(name: &str) -> Result<Value, Exception> {
+    Err(Exception::new(ExceptionKind::ImportError, vec![format!("cannot load native module '{}' on this platform", name)]))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_error(path: &Path, e: libloading::Error) -> Exception {
+    Exception::new(ExceptionKind::ImportError, vec![format!("cannot load {}: {}", path.display(), e)])
+}
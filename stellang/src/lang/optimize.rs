@@ -0,0 +1,119 @@
+// Optimizer passes over the AST, run by `stel build --release` and other release-profile runs
+
+use std::cell::RefCell;
+
+use super::ast::Expr;
+use super::interpreter::{Interpreter, Value};
+use super::stdlib::sys;
+
+/// Folded strings longer than this stay as expressions, so that `"-" * 100000`
+/// does not turn into a huge literal.
+const MAX_FOLDED_STRING: usize = 256;
+
+thread_local! {
+    // Evaluates the operators being folded, so folding can never disagree
+    // with the interpreter; literals need nothing else from it
+    static SCRATCH: RefCell<Interpreter> = RefCell::new(Interpreter::new());
+}
+
+/// Every pass, in one walk: guards are resolved, constants folded and decided
+/// branches pruned, so that `if not cfg("windows") { ... }` is settled too.
+pub fn optimize(expr: &mut Expr, debug: bool) {
+    for child in expr.children_mut() {
+        optimize(child, debug);
+    }
+    resolve_cfg_call(expr, debug);
+    fold(expr);
+    prune_if(expr);
+}
+
+/// Replace operators whose operands are all literals with their result, bottom up,
+/// so `60 * 60 * 24` becomes `86400`. Operations that raise, like `1 / 0`, are
+/// left in place to raise when they run.
+pub fn fold_constants(expr: &mut Expr) {
+    for child in expr.children_mut() {
+        fold_constants(child);
+    }
+    fold(expr);
+}
+
+/// Settle platform and mode guards when the program is loaded: `cfg("windows")`
+/// becomes `true` or `false` for this machine and `debug`, and an `if` whose
+/// condition is now a literal is replaced by the branch that would run.
+pub fn resolve_cfg(expr: &mut Expr, debug: bool) {
+    for child in expr.children_mut() {
+        resolve_cfg(child, debug);
+    }
+    resolve_cfg_call(expr, debug);
+    prune_if(expr);
+}
+
+fn fold(expr: &mut Expr) {
+    let foldable = match expr {
+        Expr::BinaryOp { left, op, right } => match (left.as_ref(), right.as_ref()) {
+            (Expr::Integer(l), Expr::Integer(r)) => !overflows(op, *l, *r),
+            (left, right) => is_literal(left) && is_literal(right),
+        },
+        Expr::UnaryOp { op, expr: operand } => match operand.as_ref() {
+            Expr::Integer(n) => !(op == "-" && n.checked_neg().is_none()),
+            operand => is_literal(operand),
+        },
+        _ => false,
+    };
+    if !foldable {
+        return;
+    }
+    if let Some(folded) = SCRATCH.with(|scratch| scratch.borrow_mut().eval(expr)).ok().and_then(literal) {
+        *expr = folded;
+    }
+}
+
+// Whether the interpreter's integer arithmetic would overflow, which is left
+// to happen when the program runs rather than while it's built
+fn overflows(op: &str, l: i64, r: i64) -> bool {
+    let shift = u32::try_from(r).ok();
+    match op {
+        "+" => l.checked_add(r).is_none(),
+        "-" => l.checked_sub(r).is_none(),
+        "*" => l.checked_mul(r).is_none(),
+        "//" => l.checked_div(r).is_none(),
+        "%" => l.checked_rem(r).is_none(),
+        "<<" => shift.and_then(|shift| l.checked_shl(shift)).is_none(),
+        ">>" => shift.and_then(|shift| l.checked_shr(shift)).is_none(),
+        _ => false,
+    }
+}
+
+fn resolve_cfg_call(expr: &mut Expr, debug: bool) {
+    if let Expr::FnCall { callable, args } = expr {
+        if let (Expr::Ident(name), [Expr::String(option)]) = (callable.as_ref(), args.as_slice()) {
+            if name == "cfg" {
+                *expr = Expr::Bool(sys::cfg(option, debug));
+            }
+        }
+    }
+}
+
+fn prune_if(expr: &mut Expr) {
+    if let Expr::If { cond, then_branch, else_branch } = expr {
+        if let Expr::Bool(taken) = **cond {
+            let branch = if taken { Some(std::mem::replace(then_branch, Box::new(Expr::Null))) } else { else_branch.take() };
+            *expr = branch.map_or(Expr::Null, |branch| *branch);
+        }
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(expr, Expr::Integer(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null)
+}
+
+fn literal(value: Value) -> Option<Expr> {
+    match value {
+        Value::Int(n) => Some(Expr::Integer(n)),
+        Value::Float(f) => Some(Expr::Float(f)),
+        Value::Str(s) if s.len() <= MAX_FOLDED_STRING => Some(Expr::String(s)),
+        Value::Bool(b) => Some(Expr::Bool(b)),
+        Value::None => Some(Expr::Null),
+        _ => None,
+    }
+}
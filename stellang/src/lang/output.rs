@@ -0,0 +1,78 @@
+// Output sinks: where `print()` writes, so embedders and the test runner can capture it
+
+use super::exceptions::{Exception, ExceptionKind};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// A destination for script output. Clones share the same writer, so function
+/// calls and spawned threads write to the sink of the interpreter that started them.
+#[derive(Clone)]
+pub struct Output(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Output {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Output(Arc::new(Mutex::new(writer)))
+    }
+
+    /// The process's real stdout; what `Interpreter::new()` uses.
+    pub fn stdout() -> Self {
+        Self::new(Box::new(std::io::stdout()))
+    }
+
+    /// Write `text` and flush, so prompts appear before `input()` blocks.
+    pub(crate) fn write_str(&self, text: &str) -> Result<(), Exception> {
+        let mut writer = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writer.write_all(text.as_bytes())
+            .and_then(|_| writer.flush())
+            .map_err(|e| Exception::new(ExceptionKind::OSError, vec![format!("cannot write output: {}", e)]))
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).flush()
+    }
+}
+
+impl std::fmt::Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Output")
+    }
+}
+
+/// An in-memory writer whose contents can be read back while or after a script
+/// runs. Pass a clone to `Interpreter::set_stdout` and keep the other.
+#[derive(Debug, Clone, Default)]
+pub struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far, with invalid UTF-8 replaced.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())).into_owned()
+    }
+
+    /// Everything written so far, leaving the capture empty.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
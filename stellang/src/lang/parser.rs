@@ -1,48 +1,170 @@
 // Parser for StelLang
 
-use super::lexer::Token;
+use super::lexer::{Lexer, Span, Token};
 use super::ast::Expr;
+use super::symbol::Symbol;
 use super::exceptions::{Exception, ExceptionKind};
+use super::features;
+use std::sync::Arc;
 
 /// The Parser struct parses a vector of tokens into an AST expression.
+///
+/// Statements are separated by newlines or semicolons. A newline does not end
+/// a statement while inside `()`, `[]` or a dictionary literal, after a binary
+/// operator, or before a `.` continuing a method chain.
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// How many brackets we are inside; newlines are insignificant when nonzero
+    nesting: usize,
+    /// How many expressions we are recursively parsing, bounded by `MAX_DEPTH`
+    depth: usize,
+    /// Where each token is in the source, when the caller knows
+    spans: Vec<Span>,
+    /// Wrap statements in `Expr::Located`
+    lines: bool,
+    /// The file the tokens come from, for `Expr::Located`
+    file: Option<Arc<str>>,
 }
 
+/// Deepest expression nesting accepted before reporting a syntax error rather
+/// than overflowing the stack.
+const MAX_DEPTH: usize = 100;
+
 impl Parser {
     /// Create a new parser from a vector of tokens.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self { tokens, pos: 0, nesting: 0, depth: 0, spans: Vec::new(), lines: false, file: None }
+    }
+
+    /// A parser that can say where in the source it stopped, given the span of
+    /// each token as `Lexer::span` reported it.
+    pub fn with_spans(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
+        Self { spans, ..Self::new(tokens) }
+    }
+
+    /// A parser that also records the line each statement starts on, as
+    /// `Expr::Located`, for programs that are run rather than only read.
+    pub fn with_lines(tokens: Vec<Token>, spans: Vec<Span>) -> Self {
+        Self { lines: true, ..Self::with_spans(tokens, spans) }
+    }
+
+    /// Record `path` as the file each statement comes from, along with its
+    /// line, so tracebacks can name it.
+    pub fn in_file(self, path: &str) -> Self {
+        Self { file: Some(path.into()), ..self }
     }
 
-    /// Peek at the current token without advancing.
+    /// The span of the token the parser is looking at, which after a syntax
+    /// error is the one it could not accept. None without spans.
+    pub fn span(&self) -> Option<Span> {
+        let next = (self.pos..self.tokens.len()).find(|&i| self.tokens[i] != Token::Newline);
+        next.and_then(|i| self.spans.get(i)).or_else(|| self.spans.last()).copied()
+    }
+
+    /// Peek at the current token without advancing, looking past line breaks.
     fn peek(&self) -> &Token {
-        self.tokens.get(self.pos).unwrap_or(&Token::EOF)
+        self.tokens[self.pos.min(self.tokens.len())..].iter()
+            .find(|tok| **tok != Token::Newline)
+            .unwrap_or(&Token::EOF)
     }
 
-    /// Advance to the next token and return the previous one.
-    fn advance(&mut self) -> &Token {
-        if self.pos < self.tokens.len() {
+    /// Advance to the next token and return the previous one. The parser never
+    /// looks back, so the token is moved out rather than its names copied.
+    fn advance(&mut self) -> Token {
+        while let Some(Token::Newline) = self.tokens.get(self.pos) {
             self.pos += 1;
         }
-        self.tokens.get(self.pos - 1).unwrap_or(&Token::EOF)
+        let Some(token) = self.tokens.get_mut(self.pos) else {
+            return Token::EOF;
+        };
+        self.pos += 1;
+        std::mem::replace(token, Token::EOF)
+    }
+
+    /// Consume the next token if it is an identifier, returning its name.
+    fn take_ident(&mut self) -> Option<Symbol> {
+        match self.peek() {
+            Token::Ident(_) => match self.advance() {
+                Token::Ident(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Consume the next token if it is a string literal, returning its text.
+    fn take_string(&mut self) -> Option<String> {
+        match self.peek() {
+            Token::String(_) => match self.advance() {
+                Token::String(text) => Some(text),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether a significant line break separates the previous token from the next one.
+    fn at_line_break(&self) -> bool {
+        self.nesting == 0 && matches!(self.tokens.get(self.pos), Some(Token::Newline))
+    }
+
+    /// Peek at a token that would continue the current expression: an
+    /// operator, call, index or attribute access. Returns `Newline` when the
+    /// expression ends at a line break, except before a leading `.`.
+    fn peek_continuation(&self) -> &Token {
+        match self.peek() {
+            Token::Dot => &Token::Dot,
+            tok if !self.at_line_break() => tok,
+            _ => &Token::Newline,
+        }
+    }
+
+    /// Consume the separator after a statement: a semicolon, a line break, or
+    /// nothing before a closing brace or the end of input.
+    fn end_statement(&mut self) -> Result<(), Exception> {
+        let mut separated = self.at_line_break() || matches!(self.peek(), Token::RBrace | Token::EOF);
+        while let Token::Semicolon = self.peek() {
+            self.advance();
+            separated = true;
+        }
+        if separated {
+            Ok(())
+        } else {
+            Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Expected newline or ';' before {:?}.", self.peek())]))
+        }
+    }
+
+    /// Parse the tokens inside a pair of brackets, where newlines are insignificant.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Exception>) -> Result<T, Exception> {
+        self.nesting += 1;
+        let result = parse(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Parse a recursive sub-expression, failing once `MAX_DEPTH` is exceeded.
+    fn descend<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Exception>) -> Result<T, Exception> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expression is nested too deeply.".to_string()]));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     /// Parse an expression from the token stream.
     pub fn parse(&mut self) -> Result<Option<Expr>, Exception> {
         let mut exprs = Vec::new();
-        while self.pos < self.tokens.len() {
+        while !matches!(self.peek(), Token::EOF) {
             // Accept any top-level statement, not just blocks
-            if let Some(expr) = self.parse_expr()? {
+            if let Some(expr) = self.parse_located()? {
                 exprs.push(expr);
             } else {
                 break;
             }
-            // Skip optional semicolons between top-level statements
-            while let Token::Semicolon = self.peek() {
-                self.advance();
-            }
+            self.end_statement()?;
         }
         if exprs.len() == 1 {
             Ok(Some(exprs.remove(0)))
@@ -53,22 +175,53 @@ impl Parser {
         }
     }
 
+    /// Tokenize and parse a whole program. `None` means the source had no statements.
+    pub fn parse_source(source: &str) -> Result<Option<Expr>, Exception> {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            match lexer.next_token()? {
+                Token::EOF => break,
+                tok => tokens.push(tok),
+            }
+        }
+        Parser::new(tokens).parse()
+    }
+
+    /// Like `parse_source`, recording the line of each statement as `with_lines` does.
+    pub fn parse_source_with_lines(source: &str) -> Result<Option<Expr>, Exception> {
+        Self::lex_with_lines(source)?.parse()
+    }
+
+    /// Like `parse_source_with_lines`, for the source of the file at `path`.
+    pub fn parse_file(source: &str, path: &str) -> Result<Option<Expr>, Exception> {
+        Self::lex_with_lines(source)?.in_file(path).parse()
+    }
+
+    fn lex_with_lines(source: &str) -> Result<Parser, Exception> {
+        let mut lexer = Lexer::new(source);
+        let (mut tokens, mut spans) = (Vec::new(), Vec::new());
+        loop {
+            match lexer.next_token()? {
+                Token::EOF => break,
+                tok => {
+                    tokens.push(tok);
+                    spans.push(lexer.span());
+                }
+            }
+        }
+        Ok(Parser::with_lines(tokens, spans))
+    }
+
     fn parse_block(&mut self) -> Result<Option<Expr>, Exception> {
-        let mut exprs = Vec::new();
         if let Token::LBrace = self.peek() {
             self.advance();
-            while !matches!(self.peek(), Token::RBrace | Token::EOF) {
-                if let Some(expr) = self.parse_expr()? {
-                    exprs.push(expr);
-                } else {
-                    // If parse_expr returns None, advance to avoid infinite loop
-                    self.advance();
-                }
-                // Accept optional semicolons between statements
-                while let Token::Semicolon = self.peek() {
-                    self.advance();
-                }
-            }
+            // Statements inside a block are line-sensitive again, even within brackets.
+            // Restore the depth before any error propagates, since `nested` decrements it.
+            let nesting = std::mem::take(&mut self.nesting);
+            let exprs = self.parse_statements();
+            self.nesting = nesting;
+            let exprs = exprs?;
             if let Token::RBrace = self.peek() {
                 self.advance();
             } else {
@@ -79,31 +232,100 @@ impl Parser {
         Ok(None)
     }
 
+    /// The statements of a block, up to its closing brace.
+    fn parse_statements(&mut self) -> Result<Vec<Expr>, Exception> {
+        let mut exprs = Vec::new();
+        while !matches!(self.peek(), Token::RBrace | Token::EOF) {
+            if let Some(expr) = self.parse_located()? {
+                exprs.push(expr);
+                self.end_statement()?;
+            } else {
+                // If parse_expr returns None, advance to avoid infinite loop
+                self.advance();
+            }
+        }
+        Ok(exprs)
+    }
+
+    // A statement, wrapped in its line when the parser records them
+    fn parse_located(&mut self) -> Result<Option<Expr>, Exception> {
+        let line = self.span().filter(|_| self.lines).map(|(start, _)| start.line);
+        let expr = self.parse_expr()?;
+        Ok(match line {
+            Some(line) => expr.map(|expr| Expr::Located { line, file: self.file.clone(), expr: Box::new(expr) }),
+            None => expr,
+        })
+    }
+
     fn parse_expr(&mut self) -> Result<Option<Expr>, Exception> {
+        self.descend(Self::parse_statement)
+    }
+
+    fn parse_statement(&mut self) -> Result<Option<Expr>, Exception> {
         match self.peek() {
             Token::Let => self.parse_let(),
             Token::Const => self.parse_const(),
             Token::Match => self.parse_match(),
             Token::Struct => self.parse_struct(),
+            Token::Class => self.parse_class(),
             Token::Enum => self.parse_enum(),
             Token::For => self.parse_for(),
             Token::Try => self.parse_try_catch(),
             Token::Throw => self.parse_throw(),
+            Token::Defer => self.parse_defer(),
             Token::Import => self.parse_import(),
+            Token::With => self.parse_with(),
             Token::If => self.parse_if(),
             Token::While => self.parse_while(),
+            Token::Loop => self.parse_loop(),
+            Token::Do => self.parse_do_while(),
             Token::Fn => self.parse_fn_def(),
+            Token::At => self.parse_decorated(),
             Token::Return => self.parse_return(),
-            Token::Break => { self.advance(); Ok(Some(Expr::Break)) },
-            Token::Continue => { self.advance(); Ok(Some(Expr::Continue)) },
+            Token::LoopLabel(name) => { let name = *name; self.parse_labeled_loop(name) },
+            Token::Break => { self.advance(); Ok(Some(Expr::Break(self.parse_jump_label()))) },
+            Token::Continue => { self.advance(); Ok(Some(Expr::Continue(self.parse_jump_label()))) },
+            Token::Ident(name) if name == "test" && self.is_test_block_ahead() => self.parse_test(),
             _ => self.parse_assignment().map(Some),
         }
     }
 
+    // 'name: while ... { } or 'name: for ... { }
+    fn parse_labeled_loop(&mut self, name: Symbol) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume the label
+        if let Token::Colon = self.peek() {
+            self.advance();
+        } else {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Expected ':' after label '{}.", name)]));
+        }
+        let mut expr = match self.peek() {
+            Token::While => self.parse_while()?,
+            Token::For => self.parse_for()?,
+            Token::Loop => self.parse_loop()?,
+            Token::Do => self.parse_do_while()?,
+            _ => return Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Label '{} must be followed by a loop.", name)])),
+        };
+        if let Some(Expr::While { label, .. } | Expr::For { label, .. } | Expr::Loop { label, .. } | Expr::DoWhile { label, .. }) = &mut expr {
+            *label = Some(name);
+        }
+        Ok(expr)
+    }
+
+    // Optional target of break/continue
+    fn parse_jump_label(&mut self) -> Option<Symbol> {
+        match self.peek_continuation() {
+            Token::LoopLabel(_) => match self.advance() {
+                Token::LoopLabel(name) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     fn parse_return(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'return'
         // Allow return without an expression (for void returns)
-        if matches!(self.peek(), Token::Semicolon | Token::RBrace | Token::EOF) {
+        if self.at_line_break() || matches!(self.peek(), Token::Semicolon | Token::RBrace | Token::EOF) {
             Ok(Some(Expr::Return(Box::new(Expr::Null))))
         } else {
             let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'return'.".to_string()]))?;
@@ -113,49 +335,116 @@ impl Parser {
 
     fn parse_import(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'import'
-        if let Token::String(s) = self.peek() {
-            let s = s.clone();
+        if let Token::Ident(kw) = self.peek() {
+            if kw == "native" {
+                self.advance();
+                return match self.take_string() {
+                    Some(path) => Ok(Some(Expr::ImportNative(path))),
+                    None => Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected string literal after 'import native'.".to_string()])),
+                };
+            }
+        }
+        // `import math` and `import "math"` are equivalent
+        match self.take_string().or_else(|| self.take_ident().map(String::from)) {
+            Some(module) => Ok(Some(Expr::Import(module))),
+            None => Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected module name after 'import'.".to_string()])),
+        }
+    }
+
+    fn parse_with(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'with'
+        let resource = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'with'.".to_string()]))?;
+        let alias = if let Token::As = self.peek() {
             self.advance();
-            Ok(Some(Expr::Import(s)))
+            let Some(name) = self.take_ident() else {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'as'.".to_string()]));
+            };
+            Some(name)
         } else {
-            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected string literal after 'import'.".to_string()]))
+            None
+        };
+        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'with' header.".to_string()]))?;
+        Ok(Some(Expr::With {
+            resource: Box::new(resource),
+            alias,
+            body: Box::new(body),
+        }))
+    }
+
+    // test "name" tags=["a", "b"] { ... }; `test` stays an ordinary name elsewhere
+    fn parse_test(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'test'
+        let name = match self.advance() {
+            Token::String(name) => name,
+            _ => return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected test name after 'test'.".to_string()])),
+        };
+        let mut tags = Vec::new();
+        if matches!(self.peek(), Token::Ident(n) if n == "tags") {
+            self.advance();
+            if !matches!(self.advance(), Token::Assign) || !matches!(self.advance(), Token::LBracket) {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '=[' after 'tags'.".to_string()]));
+            }
+            while let Some(tag) = self.take_string() {
+                tags.push(tag);
+                if let Token::Comma = self.peek() {
+                    self.advance();
+                }
+            }
+            if !matches!(self.advance(), Token::RBracket) {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Test tags must be a list of strings.".to_string()]));
+            }
         }
+        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec![format!("Expected block after test \"{}\".", name)]))?;
+        Ok(Some(Expr::Test { name, tags, body: Box::new(body) }))
     }
 
     fn parse_let(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'let'
-        let name = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
-            self.advance();
-            n
-        } else {
+        let Some(name) = self.take_ident() else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'let'.".to_string()]));
         };
+        let ty = self.parse_type_annotation()?;
         if let Token::Assign = self.peek() {
             self.advance();
         } else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '=' after identifier in 'let' statement.".to_string()]));
         }
         let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after '=' in 'let' statement.".to_string()]))?;
-        Ok(Some(Expr::Let { name, expr: Box::new(expr) }))
+        Ok(Some(match ty {
+            Some(ty) => Expr::LetTyped { name, ty, expr: Box::new(expr) },
+            None => Expr::Let { name, expr: Box::new(expr) },
+        }))
     }
 
     fn parse_const(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'const'
-        let name = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
-            self.advance();
-            n
-        } else {
+        let Some(name) = self.take_ident() else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'const'.".to_string()]));
         };
+        let ty = self.parse_type_annotation()?;
         if let Token::Assign = self.peek() {
             self.advance();
         } else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '=' after identifier in 'const' statement.".to_string()]));
         }
         let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after '=' in 'const' statement.".to_string()]))?;
-        Ok(Some(Expr::Const { name, expr: Box::new(expr) }))
+        Ok(Some(match ty {
+            Some(ty) => Expr::ConstTyped { name, ty, expr: Box::new(expr) },
+            None => Expr::Const { name, expr: Box::new(expr) },
+        }))
+    }
+
+    // `: type` after the name in a let or const, behind the type-annotations feature
+    fn parse_type_annotation(&mut self) -> Result<Option<Symbol>, Exception> {
+        if !matches!(self.peek(), Token::Colon) {
+            return Ok(None);
+        }
+        features::require("type-annotations", "Type annotations")?;
+        self.advance();
+        let Some(ty) = self.take_ident() else {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected a type name after ':'.".to_string()]));
+        };
+        Ok(Some(ty))
     }
 
     fn parse_match(&mut self) -> Result<Option<Expr>, Exception> {
@@ -189,13 +478,74 @@ impl Parser {
         Ok(Some(Expr::Match { expr: Box::new(expr), arms }))
     }
 
+    // class Name { ... }, with a base class as `class Name(Base)` or `class Name extends Base`
+    fn parse_class(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'class'
+        let Some(name) = self.take_ident() else {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'class'.".to_string()]));
+        };
+        let bases = match self.peek() {
+            Token::LParen => {
+                self.advance();
+                self.nested(Self::parse_call_args)?
+            }
+            // Only a keyword in stel1
+            Token::Ident(word) if word == "extends" => return Err(features::removed("class-extends")),
+            Token::Extends => {
+                features::deprecated("class-extends")?;
+                self.advance();
+                let Some(base) = self.take_ident() else {
+                    return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected base class after 'extends'.".to_string()]));
+                };
+                vec![Expr::Ident(base)]
+            }
+            _ => Vec::new(),
+        };
+        if !matches!(self.peek(), Token::LBrace) {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Expected '{{' after class name '{}'.", name)]));
+        }
+        self.advance();
+        let nesting = std::mem::take(&mut self.nesting);
+        let body = self.parse_class_body();
+        self.nesting = nesting;
+        let body = body?;
+        if !matches!(self.peek(), Token::RBrace) {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '}' after class body.".to_string()]));
+        }
+        self.advance();
+        Ok(Some(Expr::ClassDef { name, bases, body }))
+    }
+
+    // The statements of a class body, which may also declare `abstract fn name(params)`
+    fn parse_class_body(&mut self) -> Result<Vec<Expr>, Exception> {
+        let mut body = Vec::new();
+        while !matches!(self.peek(), Token::RBrace | Token::EOF) {
+            let statement = if let Token::Abstract = self.peek() {
+                self.advance();
+                if !matches!(self.peek(), Token::Fn) {
+                    return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected 'fn' after 'abstract'.".to_string()]));
+                }
+                let (name, params) = self.parse_fn_signature()?;
+                Some(Expr::AbstractFn { name, params })
+            } else {
+                self.parse_expr()?
+            };
+            match statement {
+                Some(statement) => {
+                    body.push(statement);
+                    self.end_statement()?;
+                }
+                None => {
+                    self.advance();
+                }
+            }
+        }
+        Ok(body)
+    }
+
     fn parse_struct(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'struct'
-        let name = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
-            self.advance();
-            n
-        } else {
+        let Some(name) = self.take_ident() else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'struct'.".to_string()]));
         };
         if let Token::LBrace = self.peek() {
@@ -204,9 +554,8 @@ impl Parser {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '{' after struct name.".to_string()]));
         }
         let mut fields = Vec::new();
-        while let Token::Ident(field) = self.peek() {
-            fields.push(field.clone());
-            self.advance();
+        while let Some(field) = self.take_ident() {
+            fields.push(field);
             if let Token::Comma = self.peek() {
                 self.advance();
             } else {
@@ -223,11 +572,7 @@ impl Parser {
 
     fn parse_enum(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'enum'
-        let name = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
-            self.advance();
-            n
-        } else {
+        let Some(name) = self.take_ident() else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'enum'.".to_string()]));
         };
         if let Token::LBrace = self.peek() {
@@ -236,9 +581,8 @@ impl Parser {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '{' after enum name.".to_string()]));
         }
         let mut variants = Vec::new();
-        while let Token::Ident(variant) = self.peek() {
-            variants.push(variant.clone());
-            self.advance();
+        while let Some(variant) = self.take_ident() {
+            variants.push(variant);
             if let Token::Comma = self.peek() {
                 self.advance();
             } else {
@@ -255,13 +599,28 @@ impl Parser {
 
     fn parse_for(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'for'
-        let var = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
+        // `for x in`, or `for (k, v) in` / `for k, v in` to unpack each item
+        let parenthesized = matches!(self.peek(), Token::LParen);
+        if parenthesized {
             self.advance();
-            n
-        } else {
-            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'for'.".to_string()]));
-        };
+        }
+        let mut vars = Vec::new();
+        loop {
+            let Some(var) = self.take_ident() else {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after 'for'.".to_string()]));
+            };
+            vars.push(var);
+            if !matches!(self.peek(), Token::Comma) {
+                break;
+            }
+            self.advance();
+        }
+        if parenthesized {
+            if !matches!(self.peek(), Token::RParen) {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ')' after for loop variables.".to_string()]));
+            }
+            self.advance();
+        }
         if let Token::In = self.peek() {
             self.advance();
         } else {
@@ -269,45 +628,67 @@ impl Parser {
         }
         let iter = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected iterable expression after 'in'.".to_string()]))?;
         let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after for loop header.".to_string()]))?;
-        Ok(Some(Expr::For { var, iter: Box::new(iter), body: Box::new(body) }))
+        Ok(Some(Expr::For { vars, iter: Box::new(iter), body: Box::new(body), label: None }))
     }
 
     fn parse_try_catch(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'try'
         let try_block = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'try'.".to_string()]))?;
         let mut catch_var = None;
+        let mut catch_block = None;
         if let Token::Catch = self.peek() {
             self.advance();
-            if let Token::Ident(var) = self.peek() {
-                catch_var = Some(var.clone());
-                self.advance();
-            }
-            let catch_block = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'catch'.".to_string()]))?;
-            Ok(Some(Expr::TryCatch {
-                try_block: Box::new(try_block),
-                catch_var,
-                catch_block: Box::new(catch_block),
-            }))
-        } else {
-            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected 'catch' after 'try' block.".to_string()]))
+            catch_var = self.take_ident();
+            let block = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'catch'.".to_string()]))?;
+            catch_block = Some(Box::new(block));
+        }
+        let mut finally_block = None;
+        if let Token::Finally = self.peek() {
+            self.advance();
+            let block = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'finally'.".to_string()]))?;
+            finally_block = Some(Box::new(block));
         }
+        if catch_block.is_none() && finally_block.is_none() {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected 'catch' or 'finally' after 'try' block.".to_string()]));
+        }
+        Ok(Some(Expr::TryCatch { try_block: Box::new(try_block), catch_var, catch_block, finally_block }))
+    }
+
+    fn parse_defer(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'defer'
+        let action = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'defer'.".to_string()]))?;
+        Ok(Some(Expr::Defer(Box::new(action))))
     }
 
     fn parse_throw(&mut self) -> Result<Option<Expr>, Exception> {
         self.advance(); // consume 'throw'
-        let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'throw'.".to_string()]))?;
-        Ok(Some(Expr::Throw(Box::new(expr))))
+        let value = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'throw'.".to_string()]))?;
+        let mut cause = None;
+        if let Token::From = self.peek_continuation() {
+            self.advance();
+            let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression after 'from'.".to_string()]))?;
+            cause = Some(Box::new(expr));
+        }
+        Ok(Some(Expr::Throw { value: Box::new(value), cause }))
     }
 
     fn parse_if(&mut self) -> Result<Option<Expr>, Exception> {
-        self.advance(); // consume 'if'
+        self.advance(); // consume 'if' (or 'elif' when continuing a chain)
         let cond = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected condition after 'if'.".to_string()]))?;
         let then_branch = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'if' condition.".to_string()]))?;
-        let else_branch = if let Token::Else = self.peek() {
-            self.advance();
-            Some(Box::new(self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'else'.".to_string()]))?))
-        } else {
-            None
+        // `elif c { }` and `else if c { }` hang the next If directly off the
+        // else branch, so a chain is a flat run of Ifs rather than nested blocks
+        let else_branch = match self.peek() {
+            Token::Elif => self.parse_if()?.map(Box::new),
+            Token::Else => {
+                self.advance();
+                if let Token::If = self.peek() {
+                    self.parse_if()?.map(Box::new)
+                } else {
+                    Some(Box::new(self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'else'.".to_string()]))?))
+                }
+            }
+            _ => None,
         };
         Ok(Some(Expr::If {
             cond: Box::new(cond),
@@ -323,16 +704,71 @@ impl Parser {
         Ok(Some(Expr::While {
             cond: Box::new(cond),
             body: Box::new(body),
+            label: None,
         }))
     }
 
-    fn parse_fn_def(&mut self) -> Result<Option<Expr>, Exception> {
-        self.advance(); // consume 'fn'
-        let name = if let Token::Ident(n) = self.peek() {
-            let n = n.clone();
+    fn parse_loop(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'loop'
+        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'loop'.".to_string()]))?;
+        Ok(Some(Expr::Loop {
+            body: Box::new(body),
+            label: None,
+        }))
+    }
+
+    fn parse_do_while(&mut self) -> Result<Option<Expr>, Exception> {
+        self.advance(); // consume 'do'
+        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after 'do'.".to_string()]))?;
+        if let Token::While = self.peek() {
             self.advance();
-            n
         } else {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected 'while' after 'do' block.".to_string()]));
+        }
+        let cond = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected condition after 'while'.".to_string()]))?;
+        Ok(Some(Expr::DoWhile {
+            body: Box::new(body),
+            cond: Box::new(cond),
+            label: None,
+        }))
+    }
+
+    // One or more `@decorator` lines, each a name or a call, then the function they apply to
+    fn parse_decorated(&mut self) -> Result<Option<Expr>, Exception> {
+        let mut decorators = Vec::new();
+        while let Token::At = self.peek() {
+            self.advance();
+            let decorator = self.parse_call_or_index()?;
+            if !matches!(decorator, Expr::Ident(_) | Expr::FnCall { .. }) {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected a decorator name or call after '@'.".to_string()]));
+            }
+            decorators.push(decorator);
+        }
+        if self.peek() != &Token::Fn {
+            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected a function definition after decorator.".to_string()]));
+        }
+        let def = self.parse_fn_def()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected a function definition after decorator.".to_string()]))?;
+        Ok(Some(Expr::Decorated { decorators, def: Box::new(def) }))
+    }
+
+    fn parse_fn_def(&mut self) -> Result<Option<Expr>, Exception> {
+        let (name, params) = self.parse_fn_signature()?;
+        // Accept optional semicolons before the block
+        while let Token::Semicolon = self.peek() {
+            self.advance();
+        }
+        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after function definition.".to_string()]))?;
+        Ok(Some(Expr::FnDef {
+            name,
+            params,
+            body: Arc::new(body),
+        }))
+    }
+
+    // `fn name(params)`, up to where the body starts
+    fn parse_fn_signature(&mut self) -> Result<(Symbol, Vec<Symbol>), Exception> {
+        self.advance(); // consume 'fn'
+        let Some(name) = self.take_ident() else {
             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected function name after 'fn'.".to_string()]));
         };
         if let Token::LParen = self.peek() {
@@ -345,14 +781,15 @@ impl Parser {
             self.advance();
         } else {
             loop {
-                if let Token::Ident(n) = self.peek() {
-                    params.push(n.clone());
-                    self.advance();
-                } else {
+                let Some(param) = self.take_ident() else {
                     return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected parameter name or ')'.".to_string()]));
-                }
+                };
+                params.push(param);
                 if let Token::Comma = self.peek() {
                     self.advance();
+                    if let Token::RParen = self.peek() {
+                        break;
+                    }
                 } else {
                     break;
                 }
@@ -363,29 +800,27 @@ impl Parser {
                 return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ')' after function parameters.".to_string()]));
             }
         }
-        // Accept optional semicolons before the block
-        while let Token::Semicolon = self.peek() {
-            self.advance();
-        }
-        let body = self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block after function definition.".to_string()]))?;
-        Ok(Some(Expr::FnDef {
-            name,
-            params,
-            body: Box::new(body),
-        }))
+        Ok((name, params))
     }
 
     fn parse_assignment(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_logical_or()?;
-        if let Token::Assign = self.peek() {
+        if let Token::Assign = self.peek_continuation() {
             // Check if the left side is a valid assignment target
             match &node {
+                // `self.field = value` inside a method sets a field of the instance
+                Expr::GetAttr { object, name } if matches!(object.as_ref(), Expr::Ident(obj) if obj == "self") => {
+                    let name = Symbol::intern(&format!("self.{}", name));
+                    self.advance(); // consume '='
+                    let value = self.parse_assignment()?;
+                    node = Expr::Assign { name, expr: Box::new(value) };
+                }
                 Expr::Ident(_) | Expr::Index { .. } => {
                     self.advance(); // consume '='
                     let value = self.parse_assignment()?;
                     node = Expr::Assign {
                         name: match &node {
-                            Expr::Ident(name) => name.clone(),
+                            Expr::Ident(name) => *name,
                             _ => return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Invalid assignment target".to_string()])),
                         },
                         expr: Box::new(value),
@@ -399,7 +834,7 @@ impl Parser {
 
     fn parse_logical_or(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_logical_and()?;
-        while let Token::Or = self.peek() {
+        while let Token::Or = self.peek_continuation() {
             self.advance();
             let right = self.parse_logical_and()?;
             node = Expr::BinaryOp {
@@ -413,7 +848,7 @@ impl Parser {
 
     fn parse_logical_and(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_equality()?;
-        while let Token::And = self.peek() {
+        while let Token::And = self.peek_continuation() {
             self.advance();
             let right = self.parse_equality()?;
             node = Expr::BinaryOp {
@@ -428,7 +863,7 @@ impl Parser {
     fn parse_equality(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_comparison()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::Eq => {
                     self.advance();
                     let right = self.parse_comparison()?;
@@ -456,7 +891,7 @@ impl Parser {
     fn parse_comparison(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_bitwise_or()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::Lt => {
                     self.advance();
                     let right = self.parse_bitwise_or()?;
@@ -539,7 +974,7 @@ impl Parser {
 
     fn parse_bitwise_or(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_bitwise_xor()?;
-        while let Token::BitOr = self.peek() {
+        while let Token::BitOr = self.peek_continuation() {
             self.advance();
             let right = self.parse_bitwise_xor()?;
             node = Expr::BinaryOp {
@@ -553,7 +988,7 @@ impl Parser {
 
     fn parse_bitwise_xor(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_bitwise_and()?;
-        while let Token::BitXor = self.peek() {
+        while let Token::BitXor = self.peek_continuation() {
             self.advance();
             let right = self.parse_bitwise_and()?;
             node = Expr::BinaryOp {
@@ -567,7 +1002,7 @@ impl Parser {
 
     fn parse_bitwise_and(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_shift()?;
-        while let Token::BitAnd = self.peek() {
+        while let Token::BitAnd = self.peek_continuation() {
             self.advance();
             let right = self.parse_shift()?;
             node = Expr::BinaryOp {
@@ -582,7 +1017,7 @@ impl Parser {
     fn parse_shift(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_term()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::Shl => {
                     self.advance();
                     let right = self.parse_term()?;
@@ -610,7 +1045,7 @@ impl Parser {
     fn parse_term(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_factor()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::Plus => {
                     self.advance();
                     let right = self.parse_factor()?;
@@ -638,7 +1073,7 @@ impl Parser {
     fn parse_factor(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_power()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::Star => {
                     self.advance();
                     let right = self.parse_power()?;
@@ -683,7 +1118,7 @@ impl Parser {
 
     fn parse_power(&mut self) -> Result<Expr, Exception> {
         let mut node = self.parse_unary()?;
-        while let Token::Pow = self.peek() {
+        while let Token::Pow = self.peek_continuation() {
             self.advance();
             let right = self.parse_unary()?;
             node = Expr::BinaryOp {
@@ -699,17 +1134,17 @@ impl Parser {
         match self.peek() {
             Token::Not => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                let expr = self.descend(Self::parse_unary)?;
                 Ok(Expr::UnaryOp { op: "not".into(), expr: Box::new(expr) })
             }
             Token::Minus => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                let expr = self.descend(Self::parse_unary)?;
                 Ok(Expr::UnaryOp { op: "-".into(), expr: Box::new(expr) })
             }
             Token::BitNot => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                let expr = self.descend(Self::parse_unary)?;
                 Ok(Expr::UnaryOp { op: "~".into(), expr: Box::new(expr) })
             }
             _ => self.parse_call_or_index(),
@@ -719,32 +1154,15 @@ impl Parser {
     fn parse_call_or_index(&mut self) -> Result<Expr, Exception> {
         let mut expr = self.parse_primary()?;
         loop {
-            match self.peek() {
+            match self.peek_continuation() {
                 Token::LParen => {
                     self.advance();
-                    let mut args = Vec::new();
-                    if let Token::RParen = self.peek() {
-                        self.advance();
-                    } else {
-                        loop {
-                            args.push(self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in function call arguments.".to_string()]))?);
-                            if let Token::Comma = self.peek() {
-                                self.advance();
-                            } else {
-                                break;
-                            }
-                        }
-                        if let Token::RParen = self.peek() {
-                            self.advance();
-                        } else {
-                            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ')' after function call arguments.".to_string()]));
-                        }
-                    }
+                    let args = self.nested(Self::parse_call_args)?;
                     expr = Expr::FnCall { callable: Box::new(expr), args };
                 }
                 Token::LBracket => {
                     self.advance();
-                    let index_expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected index expression inside brackets.".to_string()]))?;
+                    let index_expr = self.nested(Self::parse_expr)?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected index expression inside brackets.".to_string()]))?;
                     if let Token::RBracket = self.peek() {
                         self.advance();
                     } else {
@@ -768,10 +1186,14 @@ impl Parser {
                 }
                 Token::Dot => {
                     self.advance(); // consume '.'
-                    if let Token::Ident(name) = self.peek() {
-                        let name = name.clone();
-                        self.advance();
-                        expr = Expr::GetAttr { object: Box::new(expr), name };
+                    if let Some(name) = self.take_ident() {
+                        if let Token::LParen = self.peek_continuation() {
+                            self.advance();
+                            let args = self.nested(Self::parse_call_args)?;
+                            expr = Expr::MethodCall { object: Box::new(expr), method: name, args };
+                        } else {
+                            expr = Expr::GetAttr { object: Box::new(expr), name };
+                        }
                     } else {
                         return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier after '.' for attribute access.".to_string()]));
                     }
@@ -782,6 +1204,125 @@ impl Parser {
         Ok(expr)
     }
 
+    // Arguments of a call, after the opening '(' up to and including ')'
+    fn parse_call_args(&mut self) -> Result<Vec<Expr>, Exception> {
+        let mut args = Vec::new();
+        if let Token::RParen = self.peek() {
+            self.advance();
+            return Ok(args);
+        }
+        loop {
+            let arg = match self.peek_pair() {
+                (Token::Ident(_), Token::Assign) => {
+                    let name = self.take_ident().unwrap_or_default();
+                    self.advance(); // consume '='
+                    if args.iter().any(|arg| matches!(arg, Expr::KeywordArg { name: other, .. } if *other == name)) {
+                        return Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Keyword argument repeated: {}", name)]));
+                    }
+                    let value = self.parse_call_arg()?;
+                    args.push(Expr::KeywordArg { name, value: Box::new(value) });
+                    None
+                }
+                _ => Some(self.parse_call_arg()?),
+            };
+            if let Some(arg) = arg {
+                if matches!(args.last(), Some(Expr::KeywordArg { .. })) {
+                    return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Positional argument follows keyword argument.".to_string()]));
+                }
+                args.push(arg);
+            }
+            if let Token::Comma = self.peek() {
+                self.advance();
+                // A trailing comma: f(a, b,)
+                if let Token::RParen = self.peek() {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        if let Token::RParen = self.peek() {
+            self.advance();
+            Ok(args)
+        } else {
+            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ')' after function call arguments.".to_string()]))
+        }
+    }
+
+    // One argument value. A name followed by a comma is not the start of a
+    // destructuring `a, b = ...`: `f(a, b=1)` passes b by keyword.
+    fn parse_call_arg(&mut self) -> Result<Expr, Exception> {
+        if let (Token::Ident(_), Token::Comma) = self.peek_pair() {
+            return Ok(Expr::Ident(self.take_ident().unwrap_or_default()));
+        }
+        self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in function call arguments.".to_string()]))
+    }
+
+    // Entries of a dictionary literal after the first key, up to and including '}'
+    fn parse_dict_entries(&mut self, first_key: Expr) -> Result<Expr, Exception> {
+        self.advance(); // consume ':'
+        let value = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected value in dictionary literal.".to_string()]))?;
+        let mut pairs = vec![(first_key, value)];
+
+        while let Token::Comma = self.peek() {
+            self.advance(); // consume ','
+            if let Token::RBrace = self.peek() {
+                break;
+            }
+            let key = self.parse_dict_key()?;
+
+            if let Token::Colon = self.peek() {
+                self.advance(); // consume ':'
+                let value = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected value in dictionary literal.".to_string()]))?;
+                pairs.push((key, value));
+            } else {
+                return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ':' after key in dictionary literal.".to_string()]));
+            }
+        }
+
+        if let Token::RBrace = self.peek() {
+            self.advance(); // consume '}'
+            Ok(Expr::MapLiteral(pairs))
+        } else {
+            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '}' at end of dictionary literal.".to_string()]))
+        }
+    }
+
+    // A key after the first in a dictionary literal. A bare name is the string
+    // of that name, as in `{name: "x"}`; `{(name): "x"}` uses its value.
+    fn parse_dict_key(&mut self) -> Result<Expr, Exception> {
+        if let (Token::Ident(_), Token::Colon) = self.peek_pair() {
+            return Ok(Expr::String(self.take_ident().map(String::from).unwrap_or_default()));
+        }
+        self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected key in dictionary literal.".to_string()]))
+    }
+
+    // Items of a list literal, after the opening '[' up to and including ']'
+    fn parse_list_items(&mut self) -> Result<Expr, Exception> {
+        let mut items = Vec::new();
+        if let Token::RBracket = self.peek() {
+            self.advance();
+            return Ok(Expr::ArrayLiteral(items));
+        }
+        loop {
+            items.push(self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in array literal.".to_string()]))?);
+            if let Token::Comma = self.peek() {
+                self.advance();
+                if let Token::RBracket = self.peek() {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        if let Token::RBracket = self.peek() {
+            self.advance();
+            Ok(Expr::ArrayLiteral(items))
+        } else {
+            Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ']' after array literal.".to_string()]))
+        }
+    }
+
     fn parse_primary(&mut self) -> Result<Expr, Exception> {
         match self.peek() {
             Token::LBrace => {
@@ -792,72 +1333,48 @@ impl Parser {
                     self.advance();
                     return Ok(Expr::MapLiteral(vec![]));
                 }
+                // A name and ':' can only start a dictionary, keyed by the name
+                if let (Token::Ident(_), Token::Colon) = self.peek_pair() {
+                    let key = Expr::String(self.take_ident().map(String::from).unwrap_or_default());
+                    return self.nested(|parser| parser.parse_dict_entries(key));
+                }
                 
-                // Look ahead to see if we have key-value pairs (key: value)
-                // We need to peek ahead to see if the next token after the first expression is a colon
-                let start_pos = self.pos;
-                let first_expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in block or dictionary.".to_string()]))?;
-                
+                // Parse the first entry once as a statement of a block, then
+                // a following ':' makes it the first key of a dictionary instead
+                let nesting = std::mem::take(&mut self.nesting);
+                let first_expr = self.parse_expr();
+                self.nesting = nesting;
+                let first_expr = first_expr?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in block or dictionary.".to_string()]))?;
+
                 if let Token::Colon = self.peek() {
                     // This is a dictionary literal
-                    self.advance(); // consume ':'
-                    let value = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected value in dictionary literal.".to_string()]))?;
-                    let mut pairs = vec![(first_expr, value)];
-                    
-                    while let Token::Comma = self.peek() {
-                        self.advance(); // consume ','
-                        let key = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected key in dictionary literal.".to_string()]))?;
-                        
-                        if let Token::Colon = self.peek() {
-                            self.advance(); // consume ':'
-                            let value = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected value in dictionary literal.".to_string()]))?;
-                            pairs.push((key, value));
-                        } else {
-                            return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ':' after key in dictionary literal.".to_string()]));
-                        }
-                    }
-                    
+                    self.nested(|parser| parser.parse_dict_entries(first_expr))
+                } else {
+                    // This is a block, not a dictionary
+                    let nesting = std::mem::take(&mut self.nesting);
+                    let rest = self.end_statement().and_then(|()| self.parse_statements());
+                    self.nesting = nesting;
+                    let mut exprs = vec![first_expr];
+                    exprs.extend(rest?);
                     if let Token::RBrace = self.peek() {
-                        self.advance(); // consume '}'
-                        return Ok(Expr::MapLiteral(pairs));
+                        self.advance();
+                        Ok(Expr::Block(exprs))
                     } else {
-                        return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '}' at end of dictionary literal.".to_string()]));
+                        Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected '}' after block.".to_string()]))
                     }
-                } else {
-                    // This is a block, not a dictionary
-                    self.pos = start_pos - 1; // Reset to before the '{' so parse_block can consume it
-                    return self.parse_block()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected block expression.".to_string()]));
                 }
             },
             Token::LBracket => {
                 self.advance();
-                let mut items = Vec::new();
-                if let Token::RBracket = self.peek() {
-                    self.advance();
-                    return Ok(Expr::ArrayLiteral(items));
-                }
-                loop {
-                    items.push(self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression in array literal.".to_string()]))?);
-                    if let Token::Comma = self.peek() {
-                        self.advance();
-                    } else {
-                        break;
-                    }
-                }
-                if let Token::RBracket = self.peek() {
-                    self.advance();
-                } else {
-                    return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ']' after array literal.".to_string()]));
-                }
-                Ok(Expr::ArrayLiteral(items))
+                self.nested(Self::parse_list_items)
             }
             Token::Print => {
                 self.advance();
-                Ok(Expr::Ident("print".to_string()))
+                Ok(Expr::Ident(Symbol::intern("print")))
             }
             Token::Input => {
                 self.advance();
-                Ok(Expr::Ident("input".to_string()))
+                Ok(Expr::Ident(Symbol::intern("input")))
             }
             Token::True => { self.advance(); Ok(Expr::Bool(true)) }
             Token::False => { self.advance(); Ok(Expr::Bool(false)) }
@@ -872,14 +1389,10 @@ impl Parser {
                 self.advance();
                 Ok(Expr::Float(f))
             }
-            Token::String(s) => {
-                let s = s.clone();
-                self.advance();
-                Ok(Expr::String(s))
-            }
+            Token::String(_) => Ok(Expr::String(self.take_string().unwrap_or_default())),
             Token::LParen => {
                 self.advance();
-                let expr = self.parse_expr()?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression inside parentheses.".to_string()]))?;
+                let expr = self.nested(Self::parse_expr)?.ok_or_else(|| Exception::new(ExceptionKind::SyntaxError, vec!["Expected expression inside parentheses.".to_string()]))?;
                 if let Token::RParen = self.peek() {
                     self.advance();
                     Ok(expr)
@@ -887,20 +1400,21 @@ impl Parser {
                     Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected ')' after expression in parentheses.".to_string()]))
                 }
             }
-            Token::Ident(name) => {
-                let name = name.clone();
-                self.advance();
+            Token::Ident(_) => {
+                let name = self.take_ident().unwrap_or_default();
                 if let Token::Comma = self.peek() {
+                    if !self.is_destructure_ahead() {
+                        // A comma-separated argument or element list, not a destructuring target
+                        return Ok(Expr::Ident(name));
+                    }
                     // Destructuring assignment: (a, b) = ...
-                    let mut names = vec![name.clone()];
+                    let mut names = vec![name];
                     while let Token::Comma = self.peek() {
                         self.advance();
-                        if let Token::Ident(n) = self.peek() {
-                            names.push(n.clone());
-                            self.advance();
-                        } else {
+                        let Some(name) = self.take_ident() else {
                             return Err(Exception::new(ExceptionKind::SyntaxError, vec!["Expected identifier in destructuring assignment.".to_string()]));
-                        }
+                        };
+                        names.push(name);
                     }
                     if let Token::Assign = self.peek() {
                         self.advance();
@@ -915,6 +1429,32 @@ impl Parser {
             _ => Err(Exception::new(ExceptionKind::SyntaxError, vec![format!("Unexpected token: {:?}", self.peek())])),
         }
     }
+
+    /// The next two tokens, skipping line breaks as `peek` does.
+    fn peek_pair(&self) -> (&Token, &Token) {
+        let mut tokens = self.tokens[self.pos.min(self.tokens.len())..].iter().filter(|tok| **tok != Token::Newline);
+        (tokens.next().unwrap_or(&Token::EOF), tokens.next().unwrap_or(&Token::EOF))
+    }
+
+    /// Check whether the next `test` is followed by a name on the same line,
+    /// starting a test block rather than using a variable called `test`.
+    fn is_test_block_ahead(&self) -> bool {
+        let start = (self.pos..self.tokens.len()).find(|&i| self.tokens[i] != Token::Newline);
+        matches!(start.and_then(|i| self.tokens.get(i + 1)), Some(Token::String(_)))
+    }
+
+    /// Check whether the tokens after an identifier form `, ident, ... =`.
+    fn is_destructure_ahead(&self) -> bool {
+        let mut i = self.pos;
+        while let Some(Token::Comma) = self.tokens.get(i) {
+            if let Some(Token::Ident(_)) = self.tokens.get(i + 1) {
+                i += 2;
+            } else {
+                return false;
+            }
+        }
+        matches!(self.tokens.get(i), Some(Token::Assign))
+    }
 }
 
 #[cfg(test)]
@@ -1053,6 +1593,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_elif_chain() {
+        let mut lexer = Lexer::new("if a { 1 } elif b { 2 } else if c { 3 } else { 4 }");
+        let mut tokens = Vec::new();
+        loop {
+            let tok = lexer.next_token();
+            if tok == Ok(Token::EOF) {
+                break;
+            }
+            tokens.push(tok.expect("Failed to tokenize"));
+        }
+        let mut parser = Parser::new(tokens);
+        let mut expr = parser.parse().unwrap().unwrap();
+        let mut conds = Vec::new();
+        while let Expr::If { cond, else_branch, .. } = expr {
+            conds.push(*cond);
+            expr = *else_branch.expect("Expected else branch");
+        }
+        assert_eq!(conds, vec![Expr::Ident("a".into()), Expr::Ident("b".into()), Expr::Ident("c".into())]);
+        assert_eq!(expr, Expr::Block(vec![Expr::Integer(4)]));
+    }
+
     #[test]
     fn test_parse_while() {
         let mut lexer = Lexer::new("while x { y = 1; }");
@@ -1067,8 +1629,9 @@ mod tests {
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap().unwrap();
         match ast {
-            Expr::While { cond, body } => {
+            Expr::While { cond, body, label } => {
                 assert_eq!(*cond, Expr::Ident("x".into()));
+                assert_eq!(label, None);
                 if let Expr::Block(exprs) = *body {
                     assert_eq!(exprs.len(), 1);
                     if let Expr::Assign { name, .. } = &exprs[0] {
@@ -1084,6 +1647,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_labeled_loop() {
+        let mut lexer = Lexer::new("'outer: while x { break 'outer; }");
+        let mut tokens = Vec::new();
+        loop {
+            let tok = lexer.next_token();
+            if tok == Ok(Token::EOF) {
+                break;
+            }
+            tokens.push(tok.expect("Failed to tokenize"));
+        }
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().unwrap().unwrap();
+        match ast {
+            Expr::While { body, label, .. } => {
+                assert_eq!(label.as_deref(), Some("outer"));
+                assert_eq!(*body, Expr::Block(vec![Expr::Break(Some("outer".into()))]));
+            }
+            _ => panic!("Expected while expression"),
+        }
+    }
+
     #[test]
     fn test_parse_fn_def() {
         let mut lexer = Lexer::new("fn add(x, y) { return x + y; }");
@@ -1103,7 +1688,7 @@ mod tests {
                 assert_eq!(params.len(), 2);
                 assert_eq!(params[0], "x");
                 assert_eq!(params[1], "y");
-                if let Expr::Block(exprs) = *body {
+                if let Expr::Block(exprs) = &*body {
                     assert_eq!(exprs.len(), 1);
                     if let Expr::Return(ref expr) = &exprs[0] {
                         if let Expr::BinaryOp { ref op, .. } = **expr {
@@ -1121,4 +1706,75 @@ mod tests {
             _ => panic!("Expected function definition"),
         }
     }
+
+    fn parse_source(source: &str) -> Result<Option<Expr>, Exception> {
+        let mut lexer = Lexer::new(source);
+        let mut tokens = Vec::new();
+        loop {
+            let tok = lexer.next_token();
+            if tok == Ok(Token::EOF) {
+                break;
+            }
+            tokens.push(tok.expect("Failed to tokenize"));
+        }
+        Parser::new(tokens).parse()
+    }
+
+    fn statement_count(source: &str) -> usize {
+        match parse_source(source).unwrap() {
+            Some(Expr::Block(exprs)) => exprs.len(),
+            Some(_) => 1,
+            None => 0,
+        }
+    }
+
+    #[test]
+    fn test_newline_ends_statement() {
+        // Without the line break these would be a call and a subtraction
+        assert_eq!(statement_count("x = a\n(-1)"), 2);
+        assert_eq!(statement_count("x\n-1"), 2);
+        assert_eq!(statement_count("# comment\n\nx = 1\n\n# another\n# comment\ny = 2\n"), 2);
+        assert!(parse_source("x = 1 y = 2").is_err());
+        assert_eq!(statement_count("x = 1; y = 2"), 2);
+    }
+
+    #[test]
+    fn test_newline_continues_expression() {
+        // Inside brackets, after a binary operator, and before a leading '.'
+        assert_eq!(statement_count("total = 1 +\n    2"), 1);
+        assert_eq!(statement_count("f(1,\n  2\n)"), 1);
+        assert_eq!(statement_count("xs = [\n  1,\n  2\n]"), 1);
+        assert_eq!(statement_count("d = {\n  \"a\": 1,\n  \"b\": 2\n}"), 1);
+        assert_eq!(statement_count("ok = (a\n  and b)"), 1);
+        assert_eq!(statement_count("s\n  .strip()\n  .upper()"), 1);
+        // Blocks inside brackets are line-sensitive again
+        assert_eq!(statement_count("f({\n  a = 1\n  b = 2\n})"), 1);
+    }
+
+    #[test]
+    fn test_bare_return_at_line_end() {
+        let ast = parse_source("fn f() {\n  return\n  5\n}").unwrap().unwrap();
+        if let Expr::FnDef { body, .. } = ast {
+            assert_eq!(*body, Expr::Block(vec![Expr::Return(Box::new(Expr::Null)), Expr::Integer(5)]));
+        } else {
+            panic!("Expected function definition");
+        }
+    }
+
+    #[test]
+    fn test_names_in_lists_are_not_destructuring() {
+        // Arguments and elements that start with names, unlike `a, b = ...`
+        let names = || vec![Expr::Ident("a".into()), Expr::Ident("b".into())];
+        assert_eq!(parse_source("f(a, b)").unwrap(), Some(Expr::FnCall { callable: Box::new(Expr::Ident("f".into())), args: names() }));
+        assert_eq!(parse_source("[a, b]").unwrap(), Some(Expr::ArrayLiteral(names())));
+        assert_eq!(parse_source("a, b = pair").unwrap(), Some(Expr::Destructure { names: vec!["a".into(), "b".into()], expr: Box::new(Expr::Ident("pair".into())) }));
+    }
+    #[test]
+    fn test_defined_function_shares_its_body_with_the_ast() {
+        let ast = parse_source("fn add(x, y) {\n    return x + y\n}").unwrap().unwrap();
+        let mut interpreter = crate::lang::interpreter::Interpreter::new();
+        interpreter.eval(&ast).unwrap();
+        let Expr::FnDef { body, .. } = &ast else { panic!("Expected function definition") };
+        assert!(std::sync::Arc::ptr_eq(body, &interpreter.functions["add"].1));
+    }
 }
@@ -0,0 +1,212 @@
+// Child processes started by `proc.spawn()`: piped output is collected by
+// background threads, so a child never blocks on a full pipe while the script
+// is busy elsewhere
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ExitStatus};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::interrupt;
+
+/// How often blocked reads and waits look for a Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Handle to a child process. Handles restored from a snapshot are detached
+/// from any process.
+#[derive(Clone, Default)]
+pub struct ProcessHandle(Option<Arc<Process>>);
+
+struct Process {
+    pid: u32,
+    command: String,
+    child: Mutex<Child>,
+    stdin: Mutex<Option<ChildStdin>>,
+    stdout: Option<Arc<Pipe>>,
+    stderr: Option<Arc<Pipe>>,
+}
+
+/// Output read so far from one of the child's pipes, and whether it has closed.
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<(Vec<u8>, bool)>,
+    changed: Condvar,
+}
+
+impl fmt::Debug for ProcessHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(process) => write!(f, "ProcessHandle({})", process.pid),
+            None => f.write_str("ProcessHandle(detached)"),
+        }
+    }
+}
+
+impl ProcessHandle {
+    /// Take ownership of a spawned child, collecting whichever of its output
+    /// streams were piped.
+    pub(crate) fn new(command: String, mut child: Child) -> ProcessHandle {
+        let stdout = child.stdout.take().map(collect);
+        let stderr = child.stderr.take().map(collect);
+        let stdin = Mutex::new(child.stdin.take());
+        ProcessHandle(Some(Arc::new(Process { pid: child.id(), command, child: Mutex::new(child), stdin, stdout, stderr })))
+    }
+
+    fn process(&self) -> Result<&Process, Exception> {
+        self.0.as_deref().ok_or_else(|| Exception::new(ExceptionKind::RuntimeError, vec!["process handle is no longer attached to a process".to_string()]))
+    }
+
+    pub fn pid(&self) -> Result<u32, Exception> {
+        Ok(self.process()?.pid)
+    }
+
+    /// The program name the process was started with, for display.
+    pub fn command(&self) -> &str {
+        self.0.as_deref().map_or("", |process| &process.command)
+    }
+
+    /// Send `data` to the child's stdin.
+    pub fn write(&self, data: &[u8]) -> Result<usize, Exception> {
+        let process = self.process()?;
+        let mut stdin = lock(&process.stdin);
+        let Some(pipe) = stdin.as_mut() else {
+            return Err(Exception::new(ExceptionKind::ValueError, vec!["process stdin is not a pipe, or was closed".to_string()]));
+        };
+        pipe.write_all(data).and_then(|_| pipe.flush()).map_err(|err| io_error("cannot write to process", err))?;
+        Ok(data.len())
+    }
+
+    /// Close the child's stdin so it sees end of input.
+    pub fn close_stdin(&self) -> Result<(), Exception> {
+        lock(&self.process()?.stdin).take();
+        Ok(())
+    }
+
+    /// Everything the child writes to stdout (or stderr) until it closes it.
+    pub fn read(&self, stderr: bool) -> Result<Vec<u8>, Exception> {
+        self.pipe(stderr)?.read_all()
+    }
+
+    /// The next line of stdout, with its newline; empty once it is closed.
+    pub fn read_line(&self) -> Result<Vec<u8>, Exception> {
+        self.pipe(false)?.read_line()
+    }
+
+    fn pipe(&self, stderr: bool) -> Result<&Pipe, Exception> {
+        let process = self.process()?;
+        let (pipe, name) = if stderr { (&process.stderr, "stderr") } else { (&process.stdout, "stdout") };
+        pipe.as_deref().ok_or_else(|| Exception::new(ExceptionKind::ValueError, vec![format!("process {} is not a pipe; spawn it with {{\"{}\": \"pipe\"}}", name, name)]))
+    }
+
+    /// The exit code once the process has finished, without waiting.
+    pub fn poll(&self) -> Result<Option<i64>, Exception> {
+        let process = self.process()?;
+        let status = lock(&process.child).try_wait().map_err(|err| io_error("cannot check process", err))?;
+        Ok(status.map(exit_code))
+    }
+
+    /// Wait for the process to finish and return its exit code, raising
+    /// TimeoutError if it is still running after `timeout` seconds.
+    pub fn wait(&self, timeout: Option<f64>) -> Result<i64, Exception> {
+        let deadline = timeout.map(|seconds| Instant::now() + Duration::from_secs_f64(seconds.max(0.0)));
+        loop {
+            if let Some(code) = self.poll()? {
+                return Ok(code);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(Exception::new(ExceptionKind::TimeoutError, vec![format!("process {} still running after {} seconds", self.command(), timeout.unwrap_or_default())]));
+            }
+            interrupt::check()?;
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Stop the process. Killing one that already finished does nothing.
+    pub fn kill(&self) -> Result<(), Exception> {
+        let process = self.process()?;
+        let mut child = lock(&process.child);
+        if child.try_wait().map_err(|err| io_error("cannot check process", err))?.is_none() {
+            child.kill().map_err(|err| io_error("cannot kill process", err))?;
+            child.wait().map_err(|err| io_error("cannot wait for process", err))?;
+        }
+        Ok(())
+    }
+
+    pub fn same_handle(&self, other: &ProcessHandle) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Pipe {
+    fn read_all(&self) -> Result<Vec<u8>, Exception> {
+        let mut state = self.wait_until(|(_, closed)| *closed)?;
+        Ok(std::mem::take(&mut state.0))
+    }
+
+    fn read_line(&self) -> Result<Vec<u8>, Exception> {
+        let mut state = self.wait_until(|(buffer, closed)| *closed || buffer.contains(&b'\n'))?;
+        let (buffer, _) = &mut *state;
+        let end = buffer.iter().position(|&byte| byte == b'\n').map_or(buffer.len(), |newline| newline + 1);
+        Ok(buffer.drain(..end).collect())
+    }
+
+    /// The pipe's state once `ready` holds, raising KeyboardInterrupt on Ctrl-C while waiting.
+    fn wait_until(&self, ready: impl Fn(&(Vec<u8>, bool)) -> bool) -> Result<MutexGuard<'_, (Vec<u8>, bool)>, Exception> {
+        let mut state = lock(&self.state);
+        while !ready(&state) {
+            interrupt::check()?;
+            state = self.changed.wait_timeout(state, POLL_INTERVAL).map(|(state, _)| state).unwrap_or_else(|poisoned| poisoned.into_inner().0);
+        }
+        Ok(state)
+    }
+}
+
+/// Read `source` into a pipe buffer on a background thread until it closes.
+fn collect(mut source: impl Read + Send + 'static) -> Arc<Pipe> {
+    let pipe = Arc::new(Pipe::default());
+    let sink = Arc::clone(&pipe);
+    std::thread::spawn(move || {
+        let mut chunk = [0; 8192];
+        loop {
+            let read = source.read(&mut chunk).unwrap_or(0);
+            let mut state = lock(&sink.state);
+            if read == 0 {
+                state.1 = true;
+            } else {
+                state.0.extend_from_slice(&chunk[..read]);
+            }
+            sink.changed.notify_all();
+            if read == 0 {
+                break;
+            }
+        }
+    });
+    pipe
+}
+
+/// The exit code, or minus the signal number for a process killed by a signal.
+fn exit_code(status: ExitStatus) -> i64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return -(signal as i64);
+        }
+    }
+    status.code().unwrap_or(-1) as i64
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn io_error(context: &str, err: std::io::Error) -> Exception {
+    let mut exc = super::fileio::io_error(err);
+    exc.args = vec![format!("{}: {}", context, exc.args.first().cloned().unwrap_or_default())];
+    exc
+}
@@ -0,0 +1,74 @@
+// Error reporting for the command-line tools
+
+use super::exceptions::Exception;
+use std::io::IsTerminal;
+
+/// How uncaught exceptions are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Traceback and message, colorized when stderr is a terminal
+    #[default]
+    Human,
+    /// One JSON object per error, for editors and other tooling
+    Json,
+}
+
+impl ErrorFormat {
+    /// Parse the value of `--error-format`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const CYAN: &str = "\x1b[36m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Render `exc` for a human, with ANSI colors if `color` is set: errors in
+/// red, warnings in yellow, the traceback dimmed and notes in cyan.
+pub fn render(exc: &Exception, color: bool) -> String {
+    if !color {
+        return exc.to_string();
+    }
+    let mut out = String::new();
+    if let Some((chained, separator)) = exc.chained() {
+        out.push_str(&format!("{}\n\n{}\n\n", render(chained, color), separator));
+    }
+    if exc.has_traceback() {
+        out.push_str(&format!("{}Traceback (most recent call last):\n", DIM));
+        if let Some(location) = exc.location() {
+            out.push_str(&format!("  at {}\n", location));
+        }
+        for frame in exc.traceback.iter().rev() {
+            out.push_str(&format!("  {}\n", frame));
+        }
+        out.push_str(RESET);
+    }
+    let severity = if exc.kind.is_warning() { YELLOW } else { RED };
+    out.push_str(&format!("{}{:?}{}", severity, exc.kind, RESET));
+    if !exc.args.is_empty() {
+        out.push_str(&format!(": {}", exc.message()));
+    }
+    for note in &exc.notes {
+        out.push_str(&format!("\n  {}note:{} {}", CYAN, RESET, note));
+    }
+    out
+}
+
+/// Write an uncaught exception to stderr in the requested format. Colors are
+/// only used when stderr is a terminal and `NO_COLOR` is unset.
+pub fn report(exc: &Exception, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Json => eprintln!("{}", exc.to_json()),
+        ErrorFormat::Human => {
+            let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            eprintln!("{}", render(exc, color));
+        }
+    }
+}
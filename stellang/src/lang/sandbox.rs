@@ -0,0 +1,161 @@
+// Capability flags for embedding StelLang with restricted builtins
+
+use super::exceptions::{Exception, ExceptionKind};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// Which classes of native builtins an interpreter may call. Hosts running
+/// untrusted scripts start from `Capabilities::sandboxed()` and whitelist the
+/// individual functions a plugin actually needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `open()`, file handles, the filesystem queries in the `path` module and `log.set_file()`
+    pub filesystem: bool,
+    /// `input()` and `sys.stdin`
+    pub stdin: bool,
+    /// `print()`, `sys.stdout` and `sys.stderr`
+    pub stdout: bool,
+    /// `spawn()`, `sys.setrecursionlimit()` and other OS-level resources
+    pub os: bool,
+    /// HTTP and socket builtins
+    pub network: bool,
+    /// `import native` of shared-library extension modules
+    pub native_modules: bool,
+    /// `eval()` and `exec()` of source text built at runtime
+    pub dynamic_code: bool,
+    /// Native functions allowed regardless of the flags above
+    pub allowed_functions: HashSet<String>,
+    /// Directories whose files `open()` may use even without `filesystem`
+    pub allowed_dirs: Vec<AllowedDir>,
+}
+
+/// A directory opened up to a sandboxed script, such as the one a build
+/// script writes its output to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowedDir {
+    pub path: PathBuf,
+    /// Whether files in it may be opened for writing too
+    pub writable: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl Capabilities {
+    /// Everything enabled; what `Interpreter::new()` uses.
+    pub fn all() -> Self {
+        Capabilities {
+            filesystem: true,
+            stdin: true,
+            stdout: true,
+            os: true,
+            network: true,
+            native_modules: true,
+            dynamic_code: true,
+            allowed_functions: HashSet::new(),
+            allowed_dirs: Vec::new(),
+        }
+    }
+
+    /// No filesystem, stdin, OS, network, native module or `eval()`/`exec()` access. Output
+    /// through `print()` stays enabled so plugins can still log.
+    pub fn sandboxed() -> Self {
+        Capabilities {
+            filesystem: false,
+            stdin: false,
+            stdout: true,
+            os: false,
+            network: false,
+            native_modules: false,
+            dynamic_code: false,
+            allowed_functions: HashSet::new(),
+            allowed_dirs: Vec::new(),
+        }
+    }
+
+    /// Whitelist a single native function by name.
+    pub fn allow(mut self, name: &str) -> Self {
+        self.allowed_functions.insert(name.to_string());
+        self
+    }
+
+    /// Let `open()` use the files under `path`, for reading only unless
+    /// `writable`. A relative path is taken from the current directory.
+    pub fn allow_dir(mut self, path: impl AsRef<Path>, writable: bool) -> Self {
+        let path = std::env::current_dir().map(|cwd| cwd.join(path.as_ref())).unwrap_or_else(|_| path.as_ref().to_path_buf());
+        let path = resolve_links(&path).unwrap_or(path);
+        self.allowed_dirs.push(AllowedDir { path, writable });
+        self
+    }
+
+    /// Fail with a PermissionError unless `open(path, mode)` is permitted,
+    /// either outright or because `path` is in one of the allowed directories.
+    pub fn check_open(&self, path: &str, mode: &str) -> Result<(), Exception> {
+        let denied = match self.check("open") {
+            Ok(()) => return Ok(()),
+            Err(denied) => denied,
+        };
+        let writes = mode.contains(['w', 'a', 'x', '+']);
+        let path = Path::new(path);
+        // `..` could climb out of an allowed directory
+        if self.allowed_dirs.is_empty() || path.components().any(|component| component == Component::ParentDir) {
+            return Err(denied);
+        }
+        let path = std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf());
+        // Compared with symlinks resolved, so a link in an allowed directory can't lead out of it
+        let Some(path) = resolve_links(&path) else {
+            return Err(Exception::new(ExceptionKind::PermissionError, vec![format!("open() can't reach '{}' in this sandbox", path.display())]));
+        };
+        let mut dirs = self.allowed_dirs.iter().filter(|dir| path.starts_with(&dir.path)).peekable();
+        if dirs.peek().is_none() {
+            return Err(Exception::new(ExceptionKind::PermissionError, vec![format!("open() can't reach '{}' in this sandbox", path.display())]));
+        }
+        if writes && !dirs.any(|dir| dir.writable) {
+            return Err(Exception::new(ExceptionKind::PermissionError, vec![format!("open() can't write to '{}' in this sandbox", path.display())]));
+        }
+        Ok(())
+    }
+
+    /// Fail with a PermissionError if calling the builtin `name` is not permitted.
+    pub fn check(&self, name: &str) -> Result<(), Exception> {
+        if self.allowed_functions.contains(name) {
+            return Ok(());
+        }
+        let permitted = match name {
+            "open" | "path.absolute" | "path.exists" | "path.isfile" | "path.isdir" | "path.glob" | "log.set_file" => self.filesystem,
+            "archive.create" | "archive.extract" | "archive.list" => self.filesystem,
+            "input" => self.stdin,
+            "print" => self.stdout,
+            // A higher recursion limit lets a script take more of the native stack
+            "spawn" | "proc.spawn" | "sys.setrecursionlimit" => self.os,
+            // Both read a file and the environment, which may hold secrets
+            "config.load" | "config.load_env" => self.filesystem && self.os,
+            "http.serve" => self.network,
+            "eval" | "exec" => self.dynamic_code,
+            _ => true,
+        };
+        if permitted {
+            Ok(())
+        } else {
+            Err(Exception::new(ExceptionKind::PermissionError, vec![format!("{}() is disabled in this sandbox", name)]))
+        }
+    }
+}
+
+// `path` with every symlink resolved. The part of it that doesn't exist yet,
+// such as a file about to be created, is kept as it is; a dangling symlink
+// gives None, since opening it could create a file anywhere.
+fn resolve_links(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while std::fs::symlink_metadata(existing).is_err() {
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+    let mut resolved = std::fs::canonicalize(existing).ok()?;
+    resolved.extend(missing.iter().rev());
+    Some(resolved)
+}
@@ -0,0 +1,49 @@
+// Session snapshots: persist an interpreter's globals and functions
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::interpreter::{Function, Interpreter, Value};
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"STELIMG";
+const FORMAT_VERSION: u8 = 1;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Snapshot {
+    env: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+}
+
+/// Whether `bytes` look like a session image rather than source code.
+pub fn is_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+impl Interpreter {
+    /// Serialize the global environment and function table. Open files,
+    /// channels and threads are not persisted and come back closed.
+    pub fn snapshot(&self) -> Result<Vec<u8>, Exception> {
+        let snapshot = Snapshot { env: self.env.clone(), functions: self.functions.clone() };
+        let body = bincode::serialize(&snapshot).map_err(|e| Exception::new(ExceptionKind::ValueError, vec![format!("cannot snapshot session: {}", e)]))?;
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + body.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend(body);
+        Ok(bytes)
+    }
+
+    /// Replace the environment and functions with those from `snapshot()`.
+    /// Capabilities are kept, so restoring into a sandbox stays sandboxed.
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), Exception> {
+        let body = bytes.strip_prefix(MAGIC).ok_or_else(|| Exception::new(ExceptionKind::ValueError, vec!["not a StelLang session image".to_string()]))?;
+        match body.split_first() {
+            Some((&FORMAT_VERSION, rest)) => {
+                let snapshot: Snapshot = bincode::deserialize(rest).map_err(|e| Exception::new(ExceptionKind::ValueError, vec![format!("corrupt session image: {}", e)]))?;
+                self.env = snapshot.env;
+                self.functions = snapshot.functions;
+                Ok(())
+            }
+            Some((version, _)) => Err(Exception::new(ExceptionKind::ValueError, vec![format!("unsupported session image version {}", version)])),
+            None => Err(Exception::new(ExceptionKind::ValueError, vec!["truncated session image".to_string()])),
+        }
+    }
+}
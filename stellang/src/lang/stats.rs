@@ -0,0 +1,107 @@
+// Execution statistics: counters collected while a script runs, for `--stats` reports
+
+use super::interpreter::{ControlFlow, Value};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Counters for one run. Function calls, `eval`/`exec` and spawned threads add to
+/// the same `Stats` as the interpreter that started them, so the totals cover the program.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    /// Expressions evaluated, by AST node
+    pub expressions: BTreeMap<&'static str, u64>,
+    /// Values created by literals, operators and constructors, by type
+    pub allocations: BTreeMap<&'static str, u64>,
+    /// Calls to builtins, functions and methods, by name
+    pub function_calls: BTreeMap<String, u64>,
+    /// Exceptions raised, by kind, whether or not something caught them
+    pub exceptions: BTreeMap<String, u64>,
+    /// The most variables a single scope held at once
+    pub peak_env_size: usize,
+    /// Time spent in each function's own code, leaving out the functions it
+    /// called, by call stack and line (`outer:3;inner:7`, or `outer;inner` for
+    /// code parsed without lines); see `collapsed_stacks`
+    #[serde(skip)]
+    pub self_time: BTreeMap<String, Duration>,
+    /// Set while an exception propagates, so each one is counted where it
+    /// was raised rather than at every expression it passes through
+    #[serde(skip)]
+    unwinding: bool,
+}
+
+/// Shared between an interpreter and the sub-interpreters it starts.
+pub(crate) type StatsHandle = Arc<Mutex<Stats>>;
+
+pub(crate) fn lock(stats: &StatsHandle) -> MutexGuard<'_, Stats> {
+    stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Stats {
+    /// The report written by `--stats`.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    /// Count one evaluated expression. `allocates` says whether a value it
+    /// returns is newly created rather than read from somewhere.
+    pub(crate) fn record(&mut self, expr_type: &'static str, allocates: bool, result: &Result<Value, ControlFlow>, env_size: usize) {
+        *self.expressions.entry(expr_type).or_insert(0) += 1;
+        self.peak_env_size = self.peak_env_size.max(env_size);
+        match result {
+            Ok(value) => {
+                self.unwinding = false;
+                if allocates {
+                    *self.allocations.entry(value.type_name()).or_insert(0) += 1;
+                }
+            }
+            Err(ControlFlow::Exception(exc)) if !self.unwinding => {
+                self.unwinding = true;
+                *self.exceptions.entry(format!("{:?}", exc.kind)).or_insert(0) += 1;
+            }
+            Err(_) => {}
+        }
+    }
+
+    pub(crate) fn record_call(&mut self, name: &str) {
+        *self.function_calls.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    /// Add the counters of `other`, collected separately, to these.
+    pub(crate) fn merge(&mut self, other: &Stats) {
+        for (kind, count) in &other.expressions {
+            *self.expressions.entry(kind).or_insert(0) += count;
+        }
+        for (kind, count) in &other.allocations {
+            *self.allocations.entry(kind).or_insert(0) += count;
+        }
+        for (name, count) in &other.function_calls {
+            *self.function_calls.entry(name.clone()).or_insert(0) += count;
+        }
+        for (kind, count) in &other.exceptions {
+            *self.exceptions.entry(kind.clone()).or_insert(0) += count;
+        }
+        for (stack, time) in &other.self_time {
+            *self.self_time.entry(stack.clone()).or_default() += *time;
+        }
+        self.peak_env_size = self.peak_env_size.max(other.peak_env_size);
+    }
+
+    /// Add `time` spent in the innermost function of `stack` itself. Each call
+    /// on it comes with the line it is running, if known.
+    pub(crate) fn record_time(&mut self, stack: &[(String, Option<usize>)], time: Duration) {
+        let frames: Vec<String> = stack.iter().map(|(function, line)| match line {
+            Some(line) => format!("{}:{}", function, line),
+            None => function.clone(),
+        }).collect();
+        *self.self_time.entry(frames.join(";")).or_default() += time;
+    }
+
+    /// `self_time` in the collapsed-stack format that flamegraph tools
+    /// (`inferno-flamegraph`, `flamegraph.pl`) read: an `outer:3;inner:7 micros`
+    /// line per call stack.
+    pub fn collapsed_stacks(&self) -> String {
+        self.self_time.iter().map(|(stack, time)| format!("{} {}\n", stack, time.as_micros())).collect()
+    }
+}
@@ -0,0 +1,474 @@
+// archive: tar, tar.gz and zip archives, and gzip-compressed bytes, for
+// packaging and deployment scripts
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::{Compression, Crc};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::fileio;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("archive");
+    module.register_fn("create", create);
+    module.register_fn("extract", extract);
+    module.register_fn("list", |args| match args {
+        [Value::Str(path)] => Ok(names(read_entries(path, None)?)),
+        _ => Err(type_error("archive.list() expects the archive's path".to_string())),
+    });
+    module.register_fn("gzip", gzip);
+    module.register_fn("gunzip", |args| {
+        let [data] = args else {
+            return Err(type_error("archive.gunzip() expects bytes".to_string()));
+        };
+        let mut out = Vec::new();
+        GzDecoder::new(data_bytes("gunzip", data)?).read_to_end(&mut out).map_err(|err| value_error(format!("archive.gunzip() data is not gzip: {}", err)))?;
+        Ok(Value::Bytes(out))
+    });
+    module
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl Format {
+    fn of(path: &str) -> Result<Format, Exception> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Ok(Format::TarGz)
+        } else if lower.ends_with(".tar") {
+            Ok(Format::Tar)
+        } else if lower.ends_with(".zip") {
+            Ok(Format::Zip)
+        } else {
+            Err(value_error(format!("archive can't tell the format of '{}' (expected .tar, .tar.gz, .tgz or .zip)", path)))
+        }
+    }
+}
+
+/// A file or directory to put in an archive, under `name`.
+struct Source {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// `create(path, files)`: write the archive at `path` (its format chosen by
+/// extension) holding `files`, a list of paths or a dict of names in the
+/// archive to paths. Directories are added with everything in them. Returns
+/// the names of the entries written.
+fn create(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(path), files] = args else {
+        return Err(type_error("archive.create() expects the archive's path and a list or dict of files".to_string()));
+    };
+    let format = Format::of(path)?;
+    let mut sources = Vec::new();
+    match files {
+        Value::List(files) => {
+            for file in files {
+                let Value::Str(file) = file else {
+                    return Err(type_error(format!("archive.create() file paths must be strs, not '{}'", file.type_name())));
+                };
+                collect(&archive_name(file), Path::new(file), &mut sources)?;
+            }
+        }
+        Value::Dict(files) => {
+            let mut files: Vec<(String, String)> = files.iter().map(|(name, file)| (name.to_display_string(), file.to_display_string())).collect();
+            files.sort();
+            for (name, file) in files {
+                collect(&archive_name(&name), Path::new(&file), &mut sources)?;
+            }
+        }
+        other => return Err(type_error(format!("archive.create() files must be a list or dict, not '{}'", other.type_name()))),
+    }
+    let file = File::create(path).map_err(|err| io_error("cannot create", path, err))?;
+    let written = match format {
+        Format::Tar => write_tar(file, &sources).map(drop),
+        Format::TarGz => write_tar(GzEncoder::new(file, Compression::default()), &sources).and_then(GzEncoder::finish).map(drop),
+        Format::Zip => zip::write(io::BufWriter::new(file), &sources),
+    };
+    written.map_err(|err| io_error("cannot write", path, err))?;
+    Ok(Value::List(sources.into_iter().map(|source| Value::Str(source.name)).collect()))
+}
+
+/// `extract(path, dest=".")`: unpack the archive at `path` into `dest`,
+/// creating it if needed, and return the names of its entries. Entries that
+/// would land outside `dest` are refused before anything is written.
+fn extract(args: &[Value]) -> Result<Value, Exception> {
+    let (path, dest) = match args {
+        [Value::Str(path)] => (path, "."),
+        [Value::Str(path), Value::Str(dest)] => (path, dest.as_str()),
+        _ => return Err(type_error("archive.extract() expects the archive's path and an optional destination directory".to_string())),
+    };
+    // Every name is checked before anything is written
+    for name in read_entries(path, None)? {
+        entry_path(Path::new(dest), &name)?;
+    }
+    fs::create_dir_all(dest).map_err(|err| io_error("cannot create", dest, err))?;
+    Ok(names(read_entries(path, Some(Path::new(dest)))?))
+}
+
+/// `gzip(data, level=6)`: `data` (bytes, or a str as UTF-8) gzip-compressed,
+/// at a level from 0 (none) to 9 (smallest).
+fn gzip(args: &[Value]) -> Result<Value, Exception> {
+    let (data, level) = match args {
+        [data] => (data, 6),
+        [data, Value::Int(level)] if (0..=9).contains(level) => (data, *level as u32),
+        [_, Value::Int(level)] => return Err(value_error(format!("archive.gzip() level must be from 0 to 9, not {}", level))),
+        _ => return Err(type_error("archive.gzip() expects bytes or a str and an optional level".to_string())),
+    };
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(data_bytes("gzip", data)?).map_err(fileio::io_error)?;
+    Ok(Value::Bytes(encoder.finish().map_err(fileio::io_error)?))
+}
+
+fn data_bytes<'a>(func: &str, data: &'a Value) -> Result<&'a [u8], Exception> {
+    match data {
+        Value::Bytes(bytes) | Value::ByteArray(bytes) => Ok(bytes),
+        Value::Str(text) => Ok(text.as_bytes()),
+        other => Err(type_error(format!("archive.{}() expects bytes or a str, not '{}'", func, other.type_name()))),
+    }
+}
+
+/// The name a path gets in an archive: relative, with `/` separators and no
+/// `.` or `..` parts.
+fn archive_name(path: &str) -> String {
+    let parts: Vec<String> = Path::new(path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    parts.join("/")
+}
+
+/// `path` under `name`, and everything inside it when it's a directory, in
+/// name order.
+fn collect(name: &str, path: &Path, sources: &mut Vec<Source>) -> Result<(), Exception> {
+    let display = path.to_string_lossy();
+    let metadata = fs::metadata(path).map_err(|err| io_error("cannot read", &display, err))?;
+    if name.is_empty() {
+        return Err(value_error(format!("archive.create() can't name an entry for '{}'", display)));
+    }
+    sources.push(Source { name: name.to_string(), path: path.to_path_buf(), is_dir: metadata.is_dir() });
+    if metadata.is_dir() {
+        let mut children: Vec<PathBuf> = fs::read_dir(path)
+            .and_then(|entries| entries.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<_>>())
+            .map_err(|err| io_error("cannot read", &display, err))?;
+        children.sort();
+        for child in children {
+            let child_name = format!("{}/{}", name, child.file_name().unwrap_or_default().to_string_lossy());
+            collect(&child_name, &child, sources)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_tar<W: Write>(out: W, sources: &[Source]) -> io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+    for source in sources {
+        if source.is_dir {
+            builder.append_dir(&source.name, &source.path)?;
+        } else {
+            builder.append_path_with_name(&source.path, &source.name)?;
+        }
+    }
+    builder.into_inner()
+}
+
+/// A name that is safe to create under the destination directory.
+fn entry_path(dest: &Path, name: &str) -> Result<PathBuf, Exception> {
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+        return Err(value_error(format!("archive entry '{}' would be extracted outside the destination", name)));
+    }
+    Ok(dest.join(relative))
+}
+
+/// The entries of the archive at `path`, extracting them into `dest` if given.
+fn read_entries(path: &str, dest: Option<&Path>) -> Result<Vec<String>, Exception> {
+    let format = Format::of(path)?;
+    let file = File::open(path).map_err(|err| io_error("cannot open", path, err))?;
+    let malformed = |err: io::Error| value_error(format!("'{}' is not a valid archive: {}", path, err));
+    match format {
+        Format::Tar => read_tar(file, dest, &malformed),
+        Format::TarGz => read_tar(GzDecoder::new(file), dest, &malformed),
+        Format::Zip => {
+            let mut data = Vec::new();
+            io::BufReader::new(file).read_to_end(&mut data).map_err(|err| io_error("cannot read", path, err))?;
+            let entries = zip::entries(&data).map_err(malformed)?;
+            if let Some(dest) = dest {
+                for entry in &entries {
+                    let target = entry_path(dest, &entry.name)?;
+                    zip::extract(&data, entry, &target).map_err(|err| match err.kind() {
+                        io::ErrorKind::InvalidData => malformed(err),
+                        _ => io_error("cannot write", &target.to_string_lossy(), err),
+                    })?;
+                }
+            }
+            Ok(entries.into_iter().map(|entry| entry.name).collect())
+        }
+    }
+}
+
+fn read_tar(input: impl Read, dest: Option<&Path>, malformed: &dyn Fn(io::Error) -> Exception) -> Result<Vec<String>, Exception> {
+    let mut archive = tar::Archive::new(input);
+    let mut names = Vec::new();
+    for entry in archive.entries().map_err(malformed)? {
+        let mut entry = entry.map_err(malformed)?;
+        let name = entry.path().map_err(malformed)?.to_string_lossy().trim_end_matches('/').to_string();
+        if let Some(dest) = dest {
+            let target = entry_path(dest, &name)?;
+            entry.unpack_in(dest).map_err(|err| io_error("cannot write", &target.to_string_lossy(), err))?;
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn names(entries: Vec<String>) -> Value {
+    Value::List(entries.into_iter().map(Value::Str).collect())
+}
+
+/// Zip archives without zip64 extensions: entries stored or deflated.
+mod zip {
+    use super::*;
+
+    const LOCAL_HEADER: u32 = 0x04034b50;
+    const CENTRAL_HEADER: u32 = 0x02014b50;
+    const END_OF_DIRECTORY: u32 = 0x06054b50;
+    const STORED: u16 = 0;
+    const DEFLATED: u16 = 8;
+    /// Names are UTF-8
+    const UTF8_FLAG: u16 = 0x0800;
+    /// Version 2.0, made on Unix so the mode in the external attributes counts
+    const VERSION: u16 = 20;
+    const MADE_BY_UNIX: u16 = 3 << 8 | VERSION;
+    /// Most bytes reserved up front for an entry, whatever size it claims
+    const MAX_PREALLOCATION: usize = 1 << 20;
+
+    pub(super) struct Entry {
+        pub(super) name: String,
+        method: u16,
+        crc: u32,
+        compressed_size: u32,
+        size: u32,
+        mode: u32,
+        offset: u32,
+    }
+
+    pub(super) fn write(mut out: impl Write, sources: &[Source]) -> io::Result<()> {
+        let mut central = Vec::new();
+        let mut offset = 0u32;
+        for source in sources {
+            let metadata = fs::metadata(&source.path)?;
+            let (time, date) = dos_time(metadata.modified().ok());
+            let (name, method, data, size, crc) = if source.is_dir {
+                (format!("{}/", source.name), STORED, Vec::new(), 0, 0)
+            } else {
+                let contents = fs::read(&source.path)?;
+                let mut crc = Crc::new();
+                crc.update(&contents);
+                let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&contents)?;
+                (source.name.clone(), DEFLATED, encoder.finish()?, size_u32(contents.len())?, crc.sum())
+            };
+            let mode = unix_mode(&metadata);
+            let compressed_size = size_u32(data.len())?;
+            let mut local = Vec::new();
+            put32(&mut local, LOCAL_HEADER);
+            for field in [VERSION, UTF8_FLAG, method, time, date] {
+                put16(&mut local, field);
+            }
+            for field in [crc, compressed_size, size] {
+                put32(&mut local, field);
+            }
+            put16(&mut local, name.len() as u16);
+            put16(&mut local, 0);
+            local.extend_from_slice(name.as_bytes());
+            out.write_all(&local)?;
+            out.write_all(&data)?;
+            put32(&mut central, CENTRAL_HEADER);
+            for field in [MADE_BY_UNIX, VERSION, UTF8_FLAG, method, time, date] {
+                put16(&mut central, field);
+            }
+            for field in [crc, compressed_size, size] {
+                put32(&mut central, field);
+            }
+            // Name length, then no extra field, comment, disk number or internal attributes
+            for field in [name.len() as u16, 0, 0, 0, 0] {
+                put16(&mut central, field);
+            }
+            put32(&mut central, mode << 16 | if source.is_dir { 0x10 } else { 0 });
+            put32(&mut central, offset);
+            central.extend_from_slice(name.as_bytes());
+            offset = offset.checked_add(size_u32(local.len() + data.len())?).ok_or_else(too_large)?;
+        }
+        let count = u16::try_from(sources.len()).map_err(|_| too_large())?;
+        let mut end = Vec::new();
+        put32(&mut end, END_OF_DIRECTORY);
+        for field in [0, 0, count, count] {
+            put16(&mut end, field);
+        }
+        put32(&mut end, size_u32(central.len())?);
+        put32(&mut end, offset);
+        put16(&mut end, 0);
+        out.write_all(&central)?;
+        out.write_all(&end)?;
+        out.flush()
+    }
+
+    /// The entries listed in the archive's central directory.
+    pub(super) fn entries(data: &[u8]) -> io::Result<Vec<Entry>> {
+        // The end record is the last 22 bytes, before a comment of up to 64 KiB
+        let search_from = data.len().saturating_sub(22 + 0xFFFF);
+        let end = (search_from..=data.len().saturating_sub(22)).rev().find(|&at| get32(data, at) == Some(END_OF_DIRECTORY)).ok_or_else(|| invalid("no end of central directory"))?;
+        let count = get16(data, end + 10).ok_or_else(|| invalid("truncated end record"))?;
+        let mut at = get32(data, end + 16).ok_or_else(|| invalid("truncated end record"))? as usize;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            if get32(data, at) != Some(CENTRAL_HEADER) {
+                return Err(invalid("bad central directory entry"));
+            }
+            let field16 = |offset: usize| get16(data, at + offset).ok_or_else(|| invalid("truncated central directory"));
+            let field32 = |offset: usize| get32(data, at + offset).ok_or_else(|| invalid("truncated central directory"));
+            let (name_len, extra_len, comment_len) = (field16(28)? as usize, field16(30)? as usize, field16(32)? as usize);
+            let name = data.get(at + 46..at + 46 + name_len).ok_or_else(|| invalid("truncated central directory"))?;
+            // Directory names end in `/`, whatever mode the archiver recorded
+            let mode = field32(38)? >> 16;
+            let mode = if name.ends_with(b"/") { mode & 0o7777 | 0o40000 } else { mode };
+            let entry = Entry {
+                name: String::from_utf8_lossy(name).trim_end_matches('/').to_string(),
+                method: field16(10)?,
+                crc: field32(16)?,
+                compressed_size: field32(20)?,
+                size: field32(24)?,
+                mode,
+                offset: field32(42)?,
+            };
+            if entry.offset == u32::MAX || entry.size == u32::MAX || entry.compressed_size == u32::MAX {
+                return Err(invalid("zip64 archives are not supported"));
+            }
+            entries.push(entry);
+            at += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(entries)
+    }
+
+    pub(super) fn extract(data: &[u8], entry: &Entry, target: &Path) -> io::Result<()> {
+        if entry.mode & 0o170000 == 0o40000 {
+            return fs::create_dir_all(target);
+        }
+        let at = entry.offset as usize;
+        if get32(data, at) != Some(LOCAL_HEADER) {
+            return Err(invalid("bad local header"));
+        }
+        let name_len = get16(data, at + 26).ok_or_else(|| invalid("truncated local header"))? as usize;
+        let extra_len = get16(data, at + 28).ok_or_else(|| invalid("truncated local header"))? as usize;
+        let start = at + 30 + name_len + extra_len;
+        let compressed = data.get(start..start + entry.compressed_size as usize).ok_or_else(|| invalid("truncated entry"))?;
+        let contents = match entry.method {
+            STORED => compressed.to_vec(),
+            DEFLATED => {
+                // Inflating stops one byte past the declared size, so a zip bomb can't fill memory
+                let mut contents = Vec::with_capacity((entry.size as usize).min(MAX_PREALLOCATION));
+                DeflateDecoder::new(compressed).take(entry.size as u64 + 1).read_to_end(&mut contents)?;
+                if contents.len() > entry.size as usize {
+                    return Err(invalid(&format!("'{}' is larger than the archive says", entry.name)));
+                }
+                contents
+            }
+            method => return Err(invalid(&format!("'{}' uses unsupported compression method {}", entry.name, method))),
+        };
+        let mut crc = Crc::new();
+        crc.update(&contents);
+        if crc.sum() != entry.crc || contents.len() != entry.size as usize {
+            return Err(invalid(&format!("'{}' is corrupt", entry.name)));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, contents)?;
+        // Only archives made on Unix record a regular file's permissions
+        #[cfg(unix)]
+        if entry.mode & 0o170000 == 0o100000 {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(target, fs::Permissions::from_mode(entry.mode & 0o777))?;
+        }
+        Ok(())
+    }
+
+    /// MS-DOS time and date fields, which start in 1980.
+    fn dos_time(modified: Option<std::time::SystemTime>) -> (u16, u16) {
+        use chrono::{Datelike, Timelike};
+        let Some(time) = modified.map(chrono::DateTime::<chrono::Local>::from).filter(|time| time.year() >= 1980) else {
+            return (0, 1 << 5 | 1);
+        };
+        let dos_time = (time.hour() << 11 | time.minute() << 5 | (time.second() / 2)) as u16;
+        let dos_date = (((time.year() - 1980) as u32) << 9 | time.month() << 5 | time.day()) as u16;
+        (dos_time, dos_date)
+    }
+
+    #[cfg(unix)]
+    fn unix_mode(metadata: &fs::Metadata) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode()
+    }
+
+    #[cfg(not(unix))]
+    fn unix_mode(metadata: &fs::Metadata) -> u32 {
+        if metadata.is_dir() { 0o40755 } else { 0o100644 }
+    }
+
+    fn size_u32(size: usize) -> io::Result<u32> {
+        u32::try_from(size).map_err(|_| too_large())
+    }
+
+    fn too_large() -> io::Error {
+        io::Error::other("archive is too large for zip without zip64")
+    }
+
+    fn invalid(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+    }
+
+    fn put16(out: &mut Vec<u8>, value: u16) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put32(out: &mut Vec<u8>, value: u32) {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn get16(data: &[u8], at: usize) -> Option<u16> {
+        Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+    }
+
+    fn get32(data: &[u8], at: usize) -> Option<u32> {
+        Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+    }
+}
+
+fn io_error(action: &str, path: &str, err: io::Error) -> Exception {
+    let message = format!("{} '{}': {}", action, path, err);
+    Exception::new(fileio::io_error(err).kind, vec![message])
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
+
+fn value_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![message])
+}
@@ -0,0 +1,426 @@
+// argparse: command-line parsing for scripts, from a dict describing the
+// arguments, with --help generated from the same description
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::hashing::ValueMap;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+use crate::lang::stdlib::sys;
+use crate::lang::suggest;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("argparse");
+    module.register_fn("parse", parse);
+    module.register_fn("help", |args| match args {
+        [spec] => Ok(Value::Str(Parser::from_spec(spec)?.help())),
+        _ => Err(type_error(format!("argparse.help() takes exactly 1 argument ({} given)", args.len()))),
+    });
+    module
+}
+
+/// `parse(spec, argv=sys.argv[1:])`: the values of the arguments `spec`
+/// declares, in a dict keyed by their names without dashes (`--dry-run` is
+/// `dry_run`). `-h`/`--help` prints the help and exits with status 0; bad
+/// arguments print the usage and the problem to stderr and exit with status 2,
+/// or raise ValueError when the spec sets `"exit_on_error": false`.
+///
+/// The spec has an optional `prog` and `description` and a list of
+/// `arguments`, each a dict with a `name` ("--count" for an option, "file" for
+/// a positional) and optionally `short` ("-c"), `help`, `type` ("str", "int",
+/// "float" or "bool"), `default`, `choices`, `required` (options), `flag`
+/// (an option without a value, true when given) and `nargs` ("?", "*" or "+",
+/// positionals).
+fn parse(args: &[Value]) -> Result<Value, Exception> {
+    let (spec, argv) = match args {
+        [spec] => (spec, sys::argv().into_iter().skip(1).collect()),
+        [spec, Value::List(argv)] => {
+            let argv = argv.iter().map(|arg| match arg {
+                Value::Str(arg) => Ok(arg.clone()),
+                other => Err(type_error(format!("argparse.parse() argv must hold str, not '{}'", other.type_name()))),
+            });
+            (spec, argv.collect::<Result<Vec<String>, Exception>>()?)
+        }
+        [_, other] => return Err(type_error(format!("argparse.parse() argv must be a list, not '{}'", other.type_name()))),
+        _ => return Err(type_error(format!("argparse.parse() takes 1 or 2 arguments ({} given)", args.len()))),
+    };
+    let parser = Parser::from_spec(spec)?;
+    match parser.parse(&argv) {
+        Ok(Some(values)) => Ok(Value::Dict(values)),
+        Ok(None) => {
+            println!("{}", parser.help());
+            Err(Exception::new(ExceptionKind::SystemExit, vec!["0".to_string()]))
+        }
+        Err(message) if parser.exit_on_error => {
+            eprintln!("{}\n{}: error: {}", parser.usage(), parser.prog, message);
+            Err(Exception::new(ExceptionKind::SystemExit, vec!["2".to_string()]))
+        }
+        Err(message) => Err(Exception::new(ExceptionKind::ValueError, vec![message])),
+    }
+}
+
+/// What a value on the command line is converted to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Str,
+    Int,
+    Float,
+    Bool,
+}
+
+impl Kind {
+    fn convert(self, text: &str) -> Option<Value> {
+        match self {
+            Kind::Str => Some(Value::Str(text.to_string())),
+            Kind::Int => text.parse().ok().map(Value::Int),
+            Kind::Float => text.parse().ok().map(Value::Float),
+            Kind::Bool => match text.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Str => "str",
+            Kind::Int => "int",
+            Kind::Float => "float",
+            Kind::Bool => "bool",
+        }
+    }
+}
+
+/// How many values a positional takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Nargs {
+    One,
+    Optional,
+    Any,
+    AtLeastOne,
+}
+
+impl Nargs {
+    fn min(self) -> usize {
+        match self {
+            Nargs::One | Nargs::AtLeastOne => 1,
+            Nargs::Optional | Nargs::Any => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Argument {
+    /// Key in the result
+    dest: String,
+    /// `--name` for options, `name` for positionals
+    name: String,
+    short: Option<String>,
+    help: Option<String>,
+    kind: Kind,
+    default: Value,
+    choices: Option<Vec<Value>>,
+    required: bool,
+    flag: bool,
+    nargs: Nargs,
+}
+
+impl Argument {
+    fn is_option(&self) -> bool {
+        self.name.starts_with('-')
+    }
+
+    /// `-c/--count`, as errors name options
+    fn display_name(&self) -> String {
+        match &self.short {
+            Some(short) => format!("{}/{}", short, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// `COUNT`, the placeholder for an option's value in the help
+    fn metavar(&self) -> String {
+        self.dest.to_uppercase()
+    }
+
+    fn convert(&self, text: &str) -> Result<Value, String> {
+        let value = self.kind.convert(text).ok_or_else(|| format!("argument {}: invalid {} value: '{}'", self.display_name(), self.kind.name(), text))?;
+        match &self.choices {
+            Some(choices) if !choices.contains(&value) => {
+                let choices: Vec<String> = choices.iter().map(|choice| format!("'{}'", choice.to_display_string())).collect();
+                Err(format!("argument {}: invalid choice: '{}' (choose from {})", self.display_name(), text, choices.join(", ")))
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
+struct Parser {
+    prog: String,
+    description: Option<String>,
+    arguments: Vec<Argument>,
+    exit_on_error: bool,
+}
+
+impl Parser {
+    fn from_spec(spec: &Value) -> Result<Self, Exception> {
+        let Value::Dict(spec) = spec else {
+            return Err(type_error(format!("argparse spec must be a dict, not '{}'", spec.type_name())));
+        };
+        let prog = match field(spec, "prog") {
+            Some(Value::Str(prog)) => prog.clone(),
+            _ => sys::argv().first().map_or_else(|| "script".to_string(), |path| path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()),
+        };
+        let description = text_field(spec, "spec", "description")?;
+        let exit_on_error = !matches!(field(spec, "exit_on_error"), Some(Value::Bool(false)));
+        let arguments = match field(spec, "arguments") {
+            None => Vec::new(),
+            Some(Value::List(arguments)) => arguments.iter().map(argument).collect::<Result<_, _>>()?,
+            Some(other) => return Err(type_error(format!("argparse spec 'arguments' must be a list, not '{}'", other.type_name()))),
+        };
+        Ok(Parser { prog, description, arguments, exit_on_error })
+    }
+
+    /// The values by name, or None when help was asked for, or the message
+    /// for what is wrong with `argv`.
+    #[allow(clippy::mutable_key_type)] // keys are argument names
+    fn parse(&self, argv: &[String]) -> Result<Option<ValueMap<Value, Value>>, String> {
+        let mut values = ValueMap::default();
+        let mut positionals: Vec<&str> = Vec::new();
+        let mut unknown: Vec<&str> = Vec::new();
+        let mut args = argv.iter();
+        let mut only_positionals = false;
+        while let Some(arg) = args.next() {
+            if only_positionals || !arg.starts_with('-') || arg == "-" || arg.parse::<f64>().is_ok() {
+                positionals.push(arg);
+                continue;
+            }
+            if arg == "--" {
+                only_positionals = true;
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                return Ok(None);
+            }
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => (name, Some(value)),
+                // `-c5` is `-c 5`
+                _ if !arg.starts_with("--") && arg.len() > 2 => (&arg[..2], Some(&arg[2..])),
+                _ => (arg.as_str(), None),
+            };
+            let Some(option) = self.arguments.iter().find(|option| option.is_option() && (option.name == name || option.short.as_deref() == Some(name))) else {
+                unknown.push(arg);
+                continue;
+            };
+            let value = if option.flag {
+                if inline.is_some() {
+                    return Err(format!("argument {}: ignored explicit argument '{}'", option.display_name(), inline.unwrap_or_default()));
+                }
+                Value::Bool(true)
+            } else {
+                let text = match inline {
+                    Some(text) => text,
+                    None => match args.next() {
+                        Some(next) if !next.starts_with('-') || next.parse::<f64>().is_ok() => next.as_str(),
+                        _ => return Err(format!("argument {}: expected one argument", option.display_name())),
+                    },
+                };
+                option.convert(text)?
+            };
+            values.insert(Value::Str(option.dest.clone()), value);
+        }
+        if let Some(first) = unknown.first() {
+            let options = self.arguments.iter().filter(|option| option.is_option()).map(|option| option.name.as_str());
+            let message = format!("unrecognized arguments: {}", unknown.join(" "));
+            return Err(suggest::did_you_mean(message, first.split('=').next().unwrap_or(first), options));
+        }
+        let mut missing = Vec::new();
+        let declared: Vec<&Argument> = self.arguments.iter().filter(|argument| !argument.is_option()).collect();
+        let mut rest = positionals.as_slice();
+        for (i, positional) in declared.iter().enumerate() {
+            let later: usize = declared[i + 1..].iter().map(|later| later.nargs.min()).sum();
+            let available = rest.len().saturating_sub(later);
+            let taken = match positional.nargs {
+                Nargs::One | Nargs::Optional => available.min(1),
+                Nargs::Any | Nargs::AtLeastOne => available,
+            };
+            if taken < positional.nargs.min() {
+                missing.push(positional.name.clone());
+                continue;
+            }
+            let (mine, others) = rest.split_at(taken);
+            rest = others;
+            let value = match positional.nargs {
+                Nargs::Any | Nargs::AtLeastOne => Value::List(mine.iter().map(|text| positional.convert(text)).collect::<Result<_, _>>()?),
+                _ => match mine.first() {
+                    Some(text) => positional.convert(text)?,
+                    None => positional.default.clone(),
+                },
+            };
+            values.insert(Value::Str(positional.dest.clone()), value);
+        }
+        for option in self.arguments.iter().filter(|option| option.is_option()) {
+            if values.contains_key(&Value::Str(option.dest.clone())) {
+                continue;
+            }
+            if option.required {
+                missing.push(option.display_name());
+            }
+            values.insert(Value::Str(option.dest.clone()), option.default.clone());
+        }
+        if !missing.is_empty() {
+            return Err(format!("the following arguments are required: {}", missing.join(", ")));
+        }
+        if !rest.is_empty() {
+            return Err(format!("unrecognized arguments: {}", rest.join(" ")));
+        }
+        Ok(Some(values))
+    }
+
+    /// `usage: prog [-h] [-c COUNT] [--loud] name`
+    fn usage(&self) -> String {
+        let mut parts = vec![format!("usage: {} [-h]", self.prog)];
+        for option in self.arguments.iter().filter(|option| option.is_option()) {
+            let name = option.short.as_ref().unwrap_or(&option.name);
+            let usage = if option.flag { name.clone() } else { format!("{} {}", name, option.metavar()) };
+            parts.push(if option.required { usage } else { format!("[{}]", usage) });
+        }
+        for positional in self.arguments.iter().filter(|argument| !argument.is_option()) {
+            parts.push(match positional.nargs {
+                Nargs::One => positional.name.clone(),
+                Nargs::Optional => format!("[{}]", positional.name),
+                Nargs::Any => format!("[{} ...]", positional.name),
+                Nargs::AtLeastOne => format!("{} [{} ...]", positional.name, positional.name),
+            });
+        }
+        parts.join(" ")
+    }
+
+    /// The usage, the description and a line per argument, laid out like
+    /// Python's argparse.
+    fn help(&self) -> String {
+        const COLUMN: usize = 24;
+        let entry = |invocation: String, help: String| {
+            if help.is_empty() {
+                format!("  {}", invocation)
+            } else if invocation.len() + 2 < COLUMN - 1 {
+                format!("  {:<width$}{}", invocation, help, width = COLUMN - 2)
+            } else {
+                format!("  {}\n{:width$}{}", invocation, "", help, width = COLUMN)
+            }
+        };
+        let described = |argument: &Argument| {
+            let help = argument.help.clone().unwrap_or_default();
+            match &argument.default {
+                Value::None | Value::Bool(false) => help,
+                Value::List(items) if items.is_empty() => help,
+                default => format!("{} (default: {})", help, default.to_display_string()).trim_start().to_string(),
+            }
+        };
+        let mut out = self.usage();
+        if let Some(description) = &self.description {
+            out.push_str(&format!("\n\n{}", description));
+        }
+        let positionals: Vec<&Argument> = self.arguments.iter().filter(|argument| !argument.is_option()).collect();
+        if !positionals.is_empty() {
+            out.push_str("\n\npositional arguments:");
+            for positional in positionals {
+                out.push_str(&format!("\n{}", entry(positional.name.clone(), described(positional))));
+            }
+        }
+        out.push_str("\n\noptions:");
+        out.push_str(&format!("\n{}", entry("-h, --help".to_string(), "show this help message and exit".to_string())));
+        for option in self.arguments.iter().filter(|option| option.is_option()) {
+            let names: Vec<String> = option.short.iter().chain([&option.name]).map(|name| {
+                if option.flag { name.clone() } else { format!("{} {}", name, option.metavar()) }
+            }).collect();
+            out.push_str(&format!("\n{}", entry(names.join(", "), described(option))));
+        }
+        out
+    }
+}
+
+/// One entry of the spec's `arguments`.
+fn argument(spec: &Value) -> Result<Argument, Exception> {
+    let Value::Dict(spec) = spec else {
+        return Err(type_error(format!("argparse arguments must be dicts, not '{}'", spec.type_name())));
+    };
+    let Some(Value::Str(name)) = field(spec, "name") else {
+        return Err(type_error("argparse argument needs a str 'name'".to_string()));
+    };
+    let option = name.starts_with('-');
+    let dest = name.trim_start_matches('-').replace('-', "_");
+    if dest.is_empty() || (option && !name.starts_with("--")) {
+        return Err(value_error(format!("invalid argument name '{}' (expected '--name' or 'name')", name)));
+    }
+    let short = text_field(spec, name, "short")?;
+    if let Some(short) = &short {
+        if !option || short.len() != 2 || !short.starts_with('-') || short == "-h" {
+            return Err(value_error(format!("invalid short name '{}' for '{}' (expected a dash and one letter, other than -h)", short, name)));
+        }
+    }
+    let flag = matches!(field(spec, "flag"), Some(Value::Bool(true)));
+    let kind = match field(spec, "type") {
+        None => Kind::Str,
+        Some(Value::Str(kind)) => [Kind::Str, Kind::Int, Kind::Float, Kind::Bool]
+            .into_iter()
+            .find(|known| known.name() == kind)
+            .ok_or_else(|| value_error(format!("unknown type '{}' for '{}' (expected 'str', 'int', 'float' or 'bool')", kind, name)))?,
+        Some(other) => return Err(type_error(format!("argparse 'type' must be a str, not '{}'", other.type_name()))),
+    };
+    let nargs = match field(spec, "nargs") {
+        None => Nargs::One,
+        Some(Value::Str(nargs)) if !option => match nargs.as_str() {
+            "?" => Nargs::Optional,
+            "*" => Nargs::Any,
+            "+" => Nargs::AtLeastOne,
+            _ => return Err(value_error(format!("invalid nargs '{}' for '{}' (expected '?', '*' or '+')", nargs, name))),
+        },
+        Some(_) => return Err(value_error(format!("nargs is for positionals, and must be '?', '*' or '+' ('{}')", name))),
+    };
+    let default = match field(spec, "default") {
+        Some(default) => default.clone(),
+        None if flag => Value::Bool(false),
+        None if nargs == Nargs::Any => Value::List(Vec::new()),
+        None => Value::None,
+    };
+    let choices = match field(spec, "choices") {
+        None => None,
+        Some(Value::List(choices)) => Some(choices.clone()),
+        Some(other) => return Err(type_error(format!("argparse 'choices' must be a list, not '{}'", other.type_name()))),
+    };
+    Ok(Argument {
+        dest,
+        name: name.clone(),
+        short,
+        help: text_field(spec, name, "help")?,
+        kind,
+        default,
+        choices,
+        required: option && matches!(field(spec, "required"), Some(Value::Bool(true))),
+        flag: option && flag,
+        nargs,
+    })
+}
+
+#[allow(clippy::mutable_key_type)]
+fn field<'a>(spec: &'a ValueMap<Value, Value>, key: &str) -> Option<&'a Value> {
+    spec.get(&Value::Str(key.to_string())).filter(|value| **value != Value::None)
+}
+
+#[allow(clippy::mutable_key_type)]
+fn text_field(spec: &ValueMap<Value, Value>, owner: &str, key: &str) -> Result<Option<String>, Exception> {
+    match field(spec, key) {
+        None => Ok(None),
+        Some(Value::Str(text)) => Ok(Some(text.clone())),
+        Some(other) => Err(type_error(format!("argparse '{}' of '{}' must be a str, not '{}'", key, owner, other.type_name()))),
+    }
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
+
+fn value_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![message])
+}
@@ -0,0 +1,295 @@
+// config: application settings layered from defaults, a config file and the
+// environment, and `.env` files loaded over the environment
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::fileio;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("config");
+    module.register_interpreter_fn("load_env");
+    module.register_interpreter_fn("load");
+    module
+}
+
+/// The variables `load_env()` loaded, laid over the process environment.
+/// Kept by the interpreter and shared with the ones running its calls and
+/// threads, rather than set with `std::env::set_var`: that races with other
+/// threads reading the environment, and would leak into every other
+/// interpreter the host runs.
+#[derive(Debug, Clone, Default)]
+pub struct EnvVars(Arc<Mutex<HashMap<String, String>>>);
+
+impl EnvVars {
+    /// `name` as loaded, or else as the process environment has it.
+    fn get(&self, name: &str) -> Option<String> {
+        let loaded = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(name).cloned();
+        loaded.or_else(|| std::env::var(name).ok())
+    }
+
+    fn set(&self, name: &str, value: &str) {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(name.to_string(), value.to_string());
+    }
+}
+
+/// `load_env(path=".env", override=false)`: load the variables in a `.env`
+/// file over the environment `load()` reads, leaving ones already set alone
+/// unless `override` is true, and return them as a dict. The process
+/// environment itself is not changed.
+///
+/// Lines are `KEY=value`, optionally after `export`; `#` starts a comment.
+/// Values may be 'single quoted' (taken as is) or "double quoted" (with `\n`
+/// style escapes, and spanning lines), and `${NAME}` in unquoted and double
+/// quoted values is replaced by an earlier variable or the environment's.
+pub(crate) fn load_env(args: &[Value], env: &EnvVars) -> Result<Value, Exception> {
+    let (path, replace) = match args {
+        [] => (".env", false),
+        [Value::Str(path)] => (path.as_str(), false),
+        [Value::Str(path), Value::Bool(replace)] => (path.as_str(), *replace),
+        _ => return Err(type_error("config.load_env() expects a path and an optional override flag".to_string())),
+    };
+    let variables = parse_env(path, &read(path)?, env)?;
+    for (name, value) in &variables {
+        if replace || env.get(name).is_none() {
+            env.set(name, value);
+        }
+    }
+    Ok(Value::Dict(variables.into_iter().map(|(name, value)| (Value::Str(name), Value::Str(value))).collect()))
+}
+
+/// `load(defaults, path=None, prefix="")`: `defaults` with the values in the
+/// file at `path` (TOML, YAML, JSON or `.env`, by extension) laid over it, then
+/// environment variables, including ones `load_env()` loaded, named `prefix` +
+/// the key in upper case, with `__` between the keys of nested dicts
+/// (`APP_DB__PORT` for `db.port`).
+///
+/// Values from the file and the environment are cast to the type of the
+/// default they replace, so `PORT=8080` gives an int where the default is one
+/// and `HOSTS=a,b` a list where the default is a list. `.env` files and the
+/// environment only set keys the defaults declare.
+pub(crate) fn load(args: &[Value], env: &EnvVars) -> Result<Value, Exception> {
+    let (defaults, path, prefix) = match args {
+        [defaults] => (defaults, None, ""),
+        [defaults, Value::None] => (defaults, None, ""),
+        [defaults, Value::Str(path)] => (defaults, Some(path.as_str()), ""),
+        [defaults, Value::None, Value::Str(prefix)] => (defaults, None, prefix.as_str()),
+        [defaults, Value::Str(path), Value::Str(prefix)] => (defaults, Some(path.as_str()), prefix.as_str()),
+        _ => return Err(type_error("config.load() expects a dict of defaults, an optional path and an optional prefix".to_string())),
+    };
+    let Value::Dict(_) = defaults else {
+        return Err(type_error(format!("config.load() defaults must be a dict, not '{}'", defaults.type_name())));
+    };
+    let mut settings = defaults.clone();
+    if let Some(path) = path {
+        let text = read(path)?;
+        if path.ends_with(".env") || path.rsplit(['/', '\\']).next().is_some_and(|name| name.starts_with(".env")) {
+            let variables: HashMap<String, String> = parse_env(path, &text, env)?.into_iter().collect();
+            apply_names(&mut settings, &mut Vec::new(), prefix, &|name| variables.get(name).map(|value| (value.clone(), path.to_string())))?;
+        } else {
+            let loaded = if path.ends_with(".toml") {
+                super::toml::loads(&[Value::Str(text)])?
+            } else if [".yaml", ".yml", ".json"].iter().any(|extension| path.ends_with(extension)) {
+                // JSON is YAML too
+                super::yaml::loads(&[Value::Str(text)])?
+            } else {
+                return Err(value_error(format!("config.load() can't tell the format of '{}' (expected .toml, .yaml, .yml, .json or .env)", path)));
+            };
+            merge(&mut settings, loaded, &mut Vec::new(), path)?;
+        }
+    }
+    apply_names(&mut settings, &mut Vec::new(), prefix, &|name| env.get(name).map(|value| (value, name.to_string())))?;
+    Ok(settings)
+}
+
+/// Lay `value` over `setting`: dicts key by key, anything else replacing the
+/// setting after casting it to the setting's type.
+#[allow(clippy::mutable_key_type)] // keys are setting names
+fn merge(setting: &mut Value, value: Value, key: &mut Vec<String>, source: &str) -> Result<(), Exception> {
+    match (setting, value) {
+        (Value::Dict(settings), Value::Dict(values)) => {
+            for (name, value) in values {
+                key.push(name.to_display_string());
+                match settings.get_mut(&name) {
+                    Some(setting) => merge(setting, value, key, source)?,
+                    None => {
+                        settings.insert(name, value);
+                    }
+                }
+                key.pop();
+            }
+            Ok(())
+        }
+        (setting, value) => {
+            *setting = cast(setting, value, key, source)?;
+            Ok(())
+        }
+    }
+}
+
+/// Set every non-dict setting under `settings` that `lookup` has a value for,
+/// asking for it by its upper-case name after `prefix`.
+#[allow(clippy::mutable_key_type)] // keys are setting names
+fn apply_names(settings: &mut Value, key: &mut Vec<String>, prefix: &str, lookup: &dyn Fn(&str) -> Option<(String, String)>) -> Result<(), Exception> {
+    let Value::Dict(entries) = settings else {
+        let name = format!("{}{}", prefix, key.join("__").to_uppercase());
+        if let Some((text, source)) = lookup(&name) {
+            *settings = cast(settings, Value::Str(text), key, &source)?;
+        }
+        return Ok(());
+    };
+    for (name, setting) in entries.iter_mut() {
+        key.push(name.to_display_string());
+        apply_names(setting, key, prefix, lookup)?;
+        key.pop();
+    }
+    Ok(())
+}
+
+/// `value` as the type of `current`, parsing it when it is text.
+fn cast(current: &Value, value: Value, key: &[String], source: &str) -> Result<Value, Exception> {
+    let invalid = |expected: &str, value: &Value| {
+        value_error(format!("config '{}' expects {}, got {} from {}", key.join("."), expected, value.to_display_string(), source))
+    };
+    Ok(match (current, value) {
+        (Value::Int(_), Value::Str(text)) => Value::Int(text.trim().parse().map_err(|_| invalid("an int", &Value::Str(text.clone())))?),
+        (Value::Float(_), Value::Str(text)) => Value::Float(text.trim().parse().map_err(|_| invalid("a float", &Value::Str(text.clone())))?),
+        (Value::Float(_), Value::Int(n)) => Value::Float(n as f64),
+        (Value::Bool(_), Value::Str(text)) => match text.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Value::Bool(true),
+            "false" | "no" | "off" | "0" | "" => Value::Bool(false),
+            _ => return Err(invalid("a bool", &Value::Str(text))),
+        },
+        (Value::List(items), Value::Str(text)) => {
+            let element = items.first().cloned().unwrap_or(Value::Str(String::new()));
+            let parts = text.split(',').map(str::trim).filter(|part| !part.is_empty());
+            Value::List(parts.map(|part| cast(&element, Value::Str(part.to_string()), key, source)).collect::<Result<_, _>>()?)
+        }
+        (Value::Str(_), value @ (Value::Int(_) | Value::Float(_) | Value::Bool(_))) => Value::Str(value.to_display_string()),
+        (Value::Dict(_), value) if !matches!(value, Value::Dict(_)) => return Err(invalid("a table", &value)),
+        (Value::Int(_), value @ Value::Int(_))
+        | (Value::Float(_), value @ Value::Float(_))
+        | (Value::Bool(_), value @ Value::Bool(_))
+        | (Value::List(_), value @ Value::List(_))
+        | (Value::Str(_), value @ Value::Str(_))
+        | (Value::None, value) => value,
+        (current, value) => return Err(invalid(&format!("a {}", current.type_name()), &value)),
+    })
+}
+
+/// The variables in `.env` text, in order.
+fn parse_env(path: &str, text: &str, env: &EnvVars) -> Result<Vec<(String, String)>, Exception> {
+    let mut variables: Vec<(String, String)> = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+        let error = |message: &str| value_error(format!("{}:{}: {}", path, number + 1, message));
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(error("expected NAME=value"));
+        };
+        let name = name.trim();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err(error(&format!("invalid variable name '{}'", name)));
+        }
+        let value = value.trim_start();
+        let value = if let Some(quoted) = value.strip_prefix('\'') {
+            let Some((value, _)) = quoted.split_once('\'') else {
+                return Err(error("unterminated single quote"));
+            };
+            value.to_string()
+        } else if let Some(quoted) = value.strip_prefix('"') {
+            // A double-quoted value runs to the closing quote, on this line or a later one
+            let mut raw = quoted.to_string();
+            while !has_closing_quote(&raw) {
+                let Some((_, next)) = lines.next() else {
+                    return Err(error("unterminated double quote"));
+                };
+                raw.push('\n');
+                raw.push_str(next);
+            }
+            expand(&unescape(&raw), &variables, env)
+        } else {
+            let value = match value.find(" #") {
+                Some(comment) => &value[..comment],
+                None => value,
+            };
+            expand(value.trim_end(), &variables, env)
+        };
+        variables.retain(|(known, _)| known != name);
+        variables.push((name.to_string(), value));
+    }
+    Ok(variables)
+}
+
+fn has_closing_quote(raw: &str) -> bool {
+    let mut escaped = false;
+    for c in raw.chars() {
+        match c {
+            '"' if !escaped => return true,
+            '\\' => escaped = !escaped,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+/// The text up to the closing quote with its escapes resolved.
+fn unescape(raw: &str) -> String {
+    let mut out = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `${NAME}` replaced by an earlier variable in the file or the environment's,
+/// or nothing when neither has it.
+fn expand(value: &str, variables: &[(String, String)], env: &EnvVars) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else { break };
+        out.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + end];
+        match variables.iter().rev().find(|(known, _)| known == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&env.get(name).unwrap_or_default()),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn read(path: &str) -> Result<String, Exception> {
+    std::fs::read_to_string(path).map_err(|err| {
+        let message = format!("cannot read '{}': {}", path, err);
+        Exception::new(fileio::io_error(err).kind, vec![message])
+    })
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
+
+fn value_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![message])
+}
@@ -0,0 +1,57 @@
+// debug: what a program's variables hold in memory and what a call allocates,
+// for finding where memory goes
+
+use std::collections::BTreeMap;
+
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+use crate::lang::stdlib::sys;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("debug");
+    // Both read the calling interpreter's variables or run its functions
+    module.register_interpreter_fn("heap_stats");
+    module.register_interpreter_fn("track_allocations");
+    module
+}
+
+/// Values by type: how many, and their bytes as `sys.getsizeof()` counts them.
+#[derive(Debug, Default)]
+pub(crate) struct Tally(BTreeMap<&'static str, (i64, i64)>);
+
+impl Tally {
+    /// Count `value` and everything inside it.
+    pub(crate) fn add(&mut self, value: &Value) {
+        let (count, bytes) = self.0.entry(value.type_name()).or_default();
+        *count += 1;
+        *bytes += sys::getsizeof(value) as i64;
+        match value {
+            Value::List(items) | Value::Tuple(items) => items.iter().for_each(|item| self.add(item)),
+            Value::Set(items) | Value::FrozenSet(items) => items.iter().for_each(|item| self.add(item)),
+            Value::Dict(entries) => entries.iter().for_each(|(key, value)| {
+                self.add(key);
+                self.add(value);
+            }),
+            Value::Instance { fields, .. } => fields.values().for_each(|field| self.add(field)),
+            _ => {}
+        }
+    }
+
+    /// `{"list": {"count": 2, "bytes": 96}, ...}`
+    pub(crate) fn to_value(&self) -> Value {
+        let kinds = self.0.iter().map(|(kind, &(count, bytes))| {
+            let fields = [("count", Value::Int(count)), ("bytes", Value::Int(bytes))];
+            (Value::Str(kind.to_string()), dict(fields))
+        });
+        Value::Dict(kinds.collect())
+    }
+
+    /// Just the counts: `{"list": 2, ...}`
+    pub(crate) fn counts(&self) -> Value {
+        Value::Dict(self.0.iter().map(|(kind, &(count, _))| (Value::Str(kind.to_string()), Value::Int(count))).collect())
+    }
+}
+
+pub(crate) fn dict<const N: usize>(fields: [(&str, Value); N]) -> Value {
+    Value::Dict(fields.into_iter().map(|(key, value)| (Value::Str(key.to_string()), value)).collect())
+}
@@ -0,0 +1,178 @@
+// decimal: exact base-10 numbers for money and other values floats cannot represent
+
+use std::cell::Cell;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::{format_float, Value};
+use crate::lang::native::NativeModule;
+
+/// Most significant digits a `Decimal` can hold, and the default precision.
+pub const MAX_PRECISION: u32 = 28;
+
+/// Rounding mode names as Python spells them, with the strategy each one selects.
+const ROUNDING_MODES: &[(&str, RoundingStrategy)] = &[
+    ("ROUND_HALF_EVEN", RoundingStrategy::MidpointNearestEven),
+    ("ROUND_HALF_UP", RoundingStrategy::MidpointAwayFromZero),
+    ("ROUND_HALF_DOWN", RoundingStrategy::MidpointTowardZero),
+    ("ROUND_UP", RoundingStrategy::AwayFromZero),
+    ("ROUND_DOWN", RoundingStrategy::ToZero),
+    ("ROUND_CEILING", RoundingStrategy::ToPositiveInfinity),
+    ("ROUND_FLOOR", RoundingStrategy::ToNegativeInfinity),
+];
+
+// Like Python's decimal context, precision and rounding are per thread, so a
+// spawned thread starts from the defaults.
+thread_local! {
+    static PRECISION: Cell<u32> = const { Cell::new(MAX_PRECISION) };
+    static ROUNDING: Cell<usize> = const { Cell::new(0) };
+}
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("decimal");
+    for (name, _) in ROUNDING_MODES {
+        module.register_value(name, Value::Str(name.to_string()));
+    }
+    module.register_fn("Decimal", construct);
+    module.register_fn("getprecision", |_| Ok(Value::Int(PRECISION.get() as i64)));
+    module.register_fn("setprecision", setprecision);
+    module.register_fn("getrounding", |_| Ok(Value::Str(ROUNDING_MODES[ROUNDING.get()].0.to_string())));
+    module.register_fn("setrounding", |args| {
+        ROUNDING.set(rounding_mode("setrounding", args.first())?);
+        Ok(Value::None)
+    });
+    module
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
+
+/// `Decimal(value)`: strings are read exactly, floats through the digits they print as.
+fn construct(args: &[Value]) -> Result<Value, Exception> {
+    let parsed = match args {
+        [] => Ok(Decimal::ZERO),
+        [Value::Decimal(d)] => Ok(*d),
+        [Value::Int(n)] => Ok(Decimal::from(*n)),
+        [Value::Bool(b)] => Ok(Decimal::from(*b as i64)),
+        [Value::Str(s)] => parse(s.trim()).ok_or_else(|| format!("invalid literal for Decimal(): '{}'", s)),
+        [Value::Float(f)] if f.is_finite() => parse(&format_float(*f)).ok_or_else(|| format!("cannot convert {} to Decimal", format_float(*f))),
+        [Value::Float(f)] => Err(format!("cannot convert {} to Decimal", format_float(*f))),
+        [other] => return Err(type_error(format!("conversion from {} to Decimal is not supported", other.type_name()))),
+        _ => return Err(type_error(format!("Decimal() takes at most 1 argument ({} given)", args.len()))),
+    };
+    parsed.map(Value::Decimal).map_err(|message| Exception::new(ExceptionKind::ValueError, vec![message]))
+}
+
+fn parse(text: &str) -> Option<Decimal> {
+    if text.contains(['e', 'E']) {
+        Decimal::from_scientific(text).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn setprecision(args: &[Value]) -> Result<Value, Exception> {
+    match args {
+        [Value::Int(n)] if (1..=MAX_PRECISION as i64).contains(n) => {
+            PRECISION.set(*n as u32);
+            Ok(Value::None)
+        }
+        [Value::Int(n)] => Err(Exception::new(ExceptionKind::ValueError, vec![format!("precision must be between 1 and {}, not {}", MAX_PRECISION, n)])),
+        _ => Err(type_error("setprecision() expects one int".to_string())),
+    }
+}
+
+/// Index into `ROUNDING_MODES` for a mode name such as `"ROUND_HALF_UP"`.
+fn rounding_mode(func: &str, mode: Option<&Value>) -> Result<usize, Exception> {
+    match mode {
+        Some(Value::Str(name)) => ROUNDING_MODES.iter().position(|(known, _)| known == name)
+            .ok_or_else(|| Exception::new(ExceptionKind::ValueError, vec![format!("unknown rounding mode '{}'", name)])),
+        Some(other) => Err(type_error(format!("{}() expects a rounding mode name, not '{}'", func, other.type_name()))),
+        None => Err(type_error(format!("{}() missing rounding mode", func))),
+    }
+}
+
+/// Round `d` to the current precision. Values that already fit keep their
+/// exponent, so `1.10 + 2.20` stays `3.30`.
+fn apply_context(d: Decimal) -> Result<Decimal, Exception> {
+    let precision = PRECISION.get();
+    let digits = d.mantissa().unsigned_abs().checked_ilog10().map_or(1, |log| log + 1);
+    if digits <= precision {
+        return Ok(d);
+    }
+    d.round_sf_with_strategy(precision, ROUNDING_MODES[ROUNDING.get()].1).ok_or_else(overflow)
+}
+
+fn overflow() -> Exception {
+    Exception::new(ExceptionKind::OverflowError, vec!["decimal result out of range".to_string()])
+}
+
+/// Arithmetic and comparison between two decimals (ints are converted by the caller).
+pub(crate) fn binary_op(op: &str, l: Decimal, r: Decimal) -> Result<Value, Exception> {
+    let zero_division = || Exception::new(ExceptionKind::ZeroDivisionError, vec!["decimal division by zero".to_string()]);
+    let result = match op {
+        "+" => l.checked_add(r).ok_or_else(overflow)?,
+        "-" => l.checked_sub(r).ok_or_else(overflow)?,
+        "*" => l.checked_mul(r).ok_or_else(overflow)?,
+        "/" if r.is_zero() => return Err(zero_division()),
+        "/" => l.checked_div(r).ok_or_else(overflow)?,
+        // Python's Decimal truncates toward zero here, unlike int and float
+        "//" if r.is_zero() => return Err(zero_division()),
+        "//" => l.checked_div(r).ok_or_else(overflow)?.trunc(),
+        "%" if r.is_zero() => return Err(zero_division()),
+        "%" => l.checked_rem(r).ok_or_else(overflow)?,
+        "**" => power(l, r)?,
+        "==" | "is" => return Ok(Value::Bool(l == r)),
+        "!=" | "is not" => return Ok(Value::Bool(l != r)),
+        "<" => return Ok(Value::Bool(l < r)),
+        ">" => return Ok(Value::Bool(l > r)),
+        "<=" => return Ok(Value::Bool(l <= r)),
+        ">=" => return Ok(Value::Bool(l >= r)),
+        _ => return Err(type_error(format!("unsupported operand type(s) for {}: 'Decimal' and 'Decimal'", op))),
+    };
+    apply_context(result).map(Value::Decimal)
+}
+
+fn power(base: Decimal, exponent: Decimal) -> Result<Decimal, Exception> {
+    let Some(mut remaining) = exponent.abs().to_u64().filter(|_| exponent.fract().is_zero()) else {
+        return Err(Exception::new(ExceptionKind::ValueError, vec!["Decimal powers need a whole exponent".to_string()]));
+    };
+    // Square-and-multiply, rounding each step the way Python's context would
+    let (mut result, mut square) = (Decimal::ONE, base);
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = apply_context(result.checked_mul(square).ok_or_else(overflow)?)?;
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = apply_context(square.checked_mul(square).ok_or_else(overflow)?)?;
+        }
+    }
+    if exponent.is_sign_negative() {
+        if result.is_zero() {
+            return Err(Exception::new(ExceptionKind::ZeroDivisionError, vec!["0 cannot be raised to a negative power".to_string()]));
+        }
+        result = Decimal::ONE.checked_div(result).ok_or_else(overflow)?;
+    }
+    Ok(result)
+}
+
+/// `d.quantize(exp, rounding=context)`: round `d` to the exponent of `exp`,
+/// e.g. `d.quantize(Decimal("0.01"))` for cents.
+pub(crate) fn quantize(d: Decimal, args: &[Value]) -> Result<Value, Exception> {
+    let exp = match args.first() {
+        Some(Value::Decimal(exp)) => *exp,
+        Some(other) => return Err(type_error(format!("quantize() expects a Decimal, not '{}'", other.type_name()))),
+        None => return Err(type_error("quantize() missing exponent".to_string())),
+    };
+    let mode = match args.get(1) {
+        Some(mode) => rounding_mode("quantize", Some(mode))?,
+        None => ROUNDING.get(),
+    };
+    let mut rounded = d.round_dp_with_strategy(exp.scale(), ROUNDING_MODES[mode].1);
+    rounded.rescale(exp.scale());
+    Ok(Value::Decimal(rounded))
+}
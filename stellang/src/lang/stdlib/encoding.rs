@@ -0,0 +1,44 @@
+// base64 and hex: text encodings for binary data
+
+use ::base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use ::base64::Engine;
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn base64_module() -> NativeModule {
+    let mut module = NativeModule::new("base64");
+    module.register_fn("encode", |args| Ok(Value::Str(STANDARD.encode(data("base64.encode", args)?))));
+    module.register_fn("decode", |args| base64_decode(&STANDARD, "base64.decode", args));
+    module.register_fn("urlsafe_encode", |args| Ok(Value::Str(URL_SAFE.encode(data("base64.urlsafe_encode", args)?))));
+    module.register_fn("urlsafe_decode", |args| base64_decode(&URL_SAFE, "base64.urlsafe_decode", args));
+    module
+}
+
+pub fn hex_module() -> NativeModule {
+    let mut module = NativeModule::new("hex");
+    module.register_fn("encode", |args| Ok(Value::Str(::hex::encode(data("hex.encode", args)?))));
+    module.register_fn("decode", |args| {
+        ::hex::decode(data("hex.decode", args)?)
+            .map(Value::Bytes)
+            .map_err(|err| Exception::new(ExceptionKind::ValueError, vec![format!("invalid hex: {}", err)]))
+    });
+    module
+}
+
+/// The single bytes-like argument of `func`; a str stands for its UTF-8 encoding.
+pub(crate) fn data<'a>(func: &str, args: &'a [Value]) -> Result<&'a [u8], Exception> {
+    match args {
+        [Value::Bytes(b) | Value::ByteArray(b)] => Ok(b),
+        [Value::Str(s)] => Ok(s.as_bytes()),
+        [other] => Err(Exception::new(ExceptionKind::TypeError, vec![format!("{}() expects bytes or str, not '{}'", func, other.type_name())])),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("{}() takes exactly 1 argument ({} given)", func, args.len())])),
+    }
+}
+
+fn base64_decode(engine: &impl Engine, func: &str, args: &[Value]) -> Result<Value, Exception> {
+    engine.decode(data(func, args)?)
+        .map(Value::Bytes)
+        .map_err(|err| Exception::new(ExceptionKind::ValueError, vec![format!("invalid base64: {}", err)]))
+}
@@ -0,0 +1,24 @@
+// hash: hex digests for verifying downloads and signing requests
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use super::encoding::data;
+use crate::lang::exceptions::Exception;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("hash");
+    module.register_fn("md5", |args| digest::<Md5>("hash.md5", args));
+    module.register_fn("sha1", |args| digest::<Sha1>("hash.sha1", args));
+    module.register_fn("sha256", |args| digest::<Sha256>("hash.sha256", args));
+    module.register_fn("sha512", |args| digest::<Sha512>("hash.sha512", args));
+    module
+}
+
+/// Lowercase hex digest of the bytes (or UTF-8 text) passed to `func`.
+fn digest<D: Digest>(func: &str, args: &[Value]) -> Result<Value, Exception> {
+    Ok(Value::Str(hex::encode(D::digest(data(func, args)?))))
+}
@@ -0,0 +1,330 @@
+// http: a small HTTP/1.1 server that hands each request to a script function,
+// one at a time, for web apps and local tools
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::hashing::ValueMap;
+use crate::lang::interpreter::Value;
+use crate::lang::interrupt;
+use crate::lang::native::NativeModule;
+use crate::lang::stdlib::url;
+
+/// Largest request head (request line and headers) and body read.
+const MAX_HEAD: usize = 64 * 1024;
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// How long a connected client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often `accept` looks for a connection or a Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+const REASONS: &[(u16, &str)] = &[
+    (200, "OK"),
+    (201, "Created"),
+    (204, "No Content"),
+    (301, "Moved Permanently"),
+    (302, "Found"),
+    (304, "Not Modified"),
+    (400, "Bad Request"),
+    (401, "Unauthorized"),
+    (403, "Forbidden"),
+    (404, "Not Found"),
+    (405, "Method Not Allowed"),
+    (413, "Content Too Large"),
+    (500, "Internal Server Error"),
+];
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("http");
+    // Runs the script's handler functions
+    module.register_interpreter_fn("serve");
+    module.register_fn("response", response);
+    module
+}
+
+/// `response(body, status=200, headers=None)`: the dict a handler returns to
+/// answer with something other than a 200 text response.
+#[allow(clippy::mutable_key_type)] // keys are field names
+fn response(args: &[Value]) -> Result<Value, Exception> {
+    let (body, status, headers) = match args {
+        [body] => (body, 200, Value::Dict(ValueMap::default())),
+        [body, Value::Int(status)] => (body, *status, Value::Dict(ValueMap::default())),
+        [body, Value::Int(status), Value::None] => (body, *status, Value::Dict(ValueMap::default())),
+        [body, Value::Int(status), headers @ Value::Dict(_)] => (body, *status, headers.clone()),
+        _ => return Err(type_error("http.response() expects a body, an optional status and an optional dict of headers".to_string())),
+    };
+    let fields = [("body", body.clone()), ("status", Value::Int(status)), ("headers", headers)];
+    Ok(Value::Dict(fields.into_iter().map(|(name, value)| (Value::Str(name.to_string()), value)).collect()))
+}
+
+/// Where requests go: every request to one function, or by method and path
+/// to the function of the first matching route.
+pub(crate) struct Routes(Vec<Route>);
+
+struct Route {
+    /// None matches any method
+    method: Option<String>,
+    /// Path segments; `:name` matches any one segment and captures it
+    segments: Vec<String>,
+    function: String,
+}
+
+impl Routes {
+    pub(crate) fn single(function: String) -> Routes {
+        Routes(vec![Route { method: None, segments: vec!["*".to_string()], function }])
+    }
+
+    /// Routes from a dict like `{"GET /": "index", "/users/:id": "user"}`:
+    /// keys are a path, optionally after a method, and values function names.
+    #[allow(clippy::mutable_key_type)] // keys are routes
+    pub(crate) fn from_dict(routes: &ValueMap<Value, Value>) -> Result<Routes, Exception> {
+        let mut parsed = Vec::new();
+        for (key, function) in routes {
+            let (Value::Str(key), Value::Str(function)) = (key, function) else {
+                return Err(type_error("http.serve() routes map \"METHOD /path\" strs to function name strs".to_string()));
+            };
+            let (method, path) = match key.trim().split_once(' ') {
+                Some((method, path)) => (Some(method.to_ascii_uppercase()), path.trim()),
+                None => (None, key.trim()),
+            };
+            if !path.starts_with('/') {
+                return Err(Exception::new(ExceptionKind::ValueError, vec![format!("http.serve() route path must start with '/', got '{}'", key)]));
+            }
+            parsed.push(Route { method, segments: segments(path).map(str::to_string).collect(), function: function.clone() });
+        }
+        // More specific routes first: literal segments over `:name` ones, then ones naming a method
+        parsed.sort_by_key(|route| {
+            let captures: Vec<bool> = route.segments.iter().map(|segment| segment.starts_with(':')).collect();
+            (captures, route.method.is_none(), route.segments.join("/"))
+        });
+        Ok(Routes(parsed))
+    }
+
+    pub(crate) fn functions(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|route| route.function.as_str())
+    }
+
+    /// The function for a request and the path parameters it captured, or
+    /// the status to answer with when there is none: 404, or 405 when the path
+    /// matches but only for other methods.
+    #[allow(clippy::mutable_key_type)] // keys are parameter names
+    pub(crate) fn find(&self, method: &str, path: &str) -> Result<(&str, ValueMap<Value, Value>), u16> {
+        let requested: Vec<&str> = segments(path).collect();
+        let mut status = 404;
+        for route in &self.0 {
+            if route.segments == ["*"] {
+                return Ok((&route.function, ValueMap::default()));
+            }
+            if route.segments.len() != requested.len() {
+                continue;
+            }
+            let mut params = ValueMap::default();
+            let matched = route.segments.iter().zip(&requested).all(|(segment, part)| match segment.strip_prefix(':') {
+                Some(name) => {
+                    params.insert(Value::Str(name.to_string()), Value::Str(url::decode(part, false)));
+                    true
+                }
+                None => segment == part,
+            });
+            if !matched {
+                continue;
+            }
+            match &route.method {
+                Some(allowed) if allowed != method && !(allowed == "GET" && method == "HEAD") => status = 405,
+                _ => return Ok((&route.function, params)),
+            }
+        }
+        Err(status)
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// A listener on `addr` (`"127.0.0.1:8000"`, or `":8000"` for every interface).
+pub(crate) fn bind(addr: &str) -> Result<TcpListener, Exception> {
+    let addr = if addr.starts_with(':') { format!("0.0.0.0{}", addr) } else { addr.to_string() };
+    let listener = TcpListener::bind(&addr).map_err(|err| os_error(format!("cannot listen on '{}': {}", addr, err)))?;
+    // Polled, so Ctrl-C can stop a server that's waiting for a connection
+    listener.set_nonblocking(true).map_err(|err| os_error(format!("cannot listen on '{}': {}", addr, err)))?;
+    Ok(listener)
+}
+
+/// The next connection, raising KeyboardInterrupt on Ctrl-C while waiting.
+pub(crate) fn accept(listener: &TcpListener) -> Result<TcpStream, Exception> {
+    loop {
+        interrupt::check()?;
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let _ = stream.set_nonblocking(false);
+                let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+                return Ok(stream);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(os_error(format!("cannot accept a connection: {}", err))),
+        }
+    }
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Read a request from `stream`, or the status to answer a malformed one with.
+    pub(crate) fn read(stream: &TcpStream) -> Result<Request, u16> {
+        let mut reader = BufReader::new(stream);
+        let mut head = 0;
+        let mut line = String::new();
+        let mut next_line = |line: &mut String| {
+            line.clear();
+            match reader.read_line(line) {
+                Ok(0) | Err(_) => Err(400_u16),
+                Ok(read) => {
+                    head += read;
+                    if head > MAX_HEAD {
+                        Err(413)
+                    } else {
+                        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+                    }
+                }
+            }
+        };
+        let request_line = next_line(&mut line)?;
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(400);
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(400);
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut headers = Vec::new();
+        loop {
+            let header = next_line(&mut line)?;
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                return Err(400);
+            };
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+        let length = match headers.iter().find(|(name, _)| name == "content-length") {
+            Some((_, length)) => length.parse::<usize>().map_err(|_| 400_u16)?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(413);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).map_err(|_| 400_u16)?;
+        Ok(Request { method: method.to_ascii_uppercase(), path: path.to_string(), query: query.to_string(), headers, body })
+    }
+
+    /// The dict handlers receive: `method`, `path`, `query` (a dict, as
+    /// `url.parse_query` gives), `params` (from the route), `headers` (with
+    /// lower-case names) and `body` (a str).
+    #[allow(clippy::mutable_key_type)] // keys are field and header names
+    pub(crate) fn to_value(&self, params: ValueMap<Value, Value>) -> Value {
+        let text = |text: &str| Value::Str(text.to_string());
+        let query = url::parse_query(&[text(&self.query)]).unwrap_or(Value::Dict(ValueMap::default()));
+        let headers = self.headers.iter().map(|(name, value)| (text(name), text(value))).collect();
+        let fields = [
+            ("method", text(&self.method)),
+            ("path", text(&self.path)),
+            ("query", query),
+            ("params", Value::Dict(params)),
+            ("headers", Value::Dict(headers)),
+            ("body", Value::Str(String::from_utf8_lossy(&self.body).into_owned())),
+        ];
+        Value::Dict(fields.into_iter().map(|(name, value)| (text(name), value)).collect())
+    }
+}
+
+pub(crate) struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// A handler's return value as a response: a str is a 200 text response,
+    /// None a 204, and a dict like `http.response()` gives sets any of
+    /// `status`, `headers` and `body`.
+    pub(crate) fn from_value(value: &Value) -> Result<Response, Exception> {
+        let (status, headers, body) = match value {
+            Value::None => return Ok(Response { status: 204, headers: Vec::new(), body: Vec::new() }),
+            Value::Dict(fields) => {
+                let field = |name: &str| fields.get(&Value::Str(name.to_string()));
+                let status = match field("status") {
+                    None => 200,
+                    Some(Value::Int(status)) if (100..=599).contains(status) => *status as u16,
+                    Some(other) => return Err(type_error(format!("http response status must be an int from 100 to 599, not {}", other.to_display_string()))),
+                };
+                let headers = match field("headers") {
+                    None | Some(Value::None) => Vec::new(),
+                    Some(Value::Dict(headers)) => headers.iter().map(|(name, value)| (name.to_display_string(), value.to_display_string())).collect(),
+                    Some(other) => return Err(type_error(format!("http response headers must be a dict, not '{}'", other.type_name()))),
+                };
+                (status, headers, field("body").cloned().unwrap_or(Value::None))
+            }
+            body => (200, Vec::new(), body.clone()),
+        };
+        let (content_type, body) = match body {
+            Value::None => (None, Vec::new()),
+            Value::Bytes(bytes) | Value::ByteArray(bytes) => (Some("application/octet-stream"), bytes),
+            body => (Some("text/plain; charset=utf-8"), body.to_display_string().into_bytes()),
+        };
+        let mut headers = headers;
+        if let Some(content_type) = content_type {
+            if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type")) {
+                headers.push(("Content-Type".to_string(), content_type.to_string()));
+            }
+        }
+        Ok(Response { status, headers, body })
+    }
+
+    /// The plain text response for an error status the server answers itself.
+    pub(crate) fn error(status: u16) -> Response {
+        let headers = vec![("Content-Type".to_string(), "text/plain; charset=utf-8".to_string())];
+        Response { status, headers, body: reason(status).as_bytes().to_vec() }
+    }
+
+    /// Send the response to a `method` request and close the connection. A
+    /// client that hung up is not an error.
+    pub(crate) fn write(&self, mut stream: &TcpStream, method: &str) {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in self.headers.iter().filter(|(name, _)| !name.eq_ignore_ascii_case("content-length") && !name.eq_ignore_ascii_case("connection")) {
+            head.push_str(&format!("{}: {}\r\n", name, value.replace(['\r', '\n'], " ")));
+        }
+        head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.len()));
+        let _ = stream.write_all(head.as_bytes());
+        if method != "HEAD" {
+            let _ = stream.write_all(&self.body);
+        }
+        let _ = stream.flush();
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    REASONS.iter().find(|(known, _)| *known == status).map_or("", |(_, reason)| reason)
+}
+
+fn os_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::OSError, vec![message])
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
@@ -0,0 +1,28 @@
+// inspect: function metadata and the live call stack, for debuggers and logging written in StelLang
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::{self, Value};
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("inspect");
+    // signature() and getsource() read the calling interpreter's function table
+    module.register_interpreter_fn("signature");
+    module.register_interpreter_fn("getsource");
+    module.register_fn("stack", |args| match args {
+        [] => Ok(stack()),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("inspect.stack() takes no arguments ({} given)", args.len())])),
+    });
+    module
+}
+
+/// `inspect.stack()`: one dict per running call, innermost first, ending with
+/// the top level of the program as `<module>`.
+fn stack() -> Value {
+    let mut frames = interpreter::call_stack();
+    frames.reverse();
+    frames.push("<module>".to_string());
+    Value::List(frames.into_iter().map(|function| {
+        Value::Dict([(Value::Str("function".to_string()), Value::Str(function))].into_iter().collect())
+    }).collect())
+}
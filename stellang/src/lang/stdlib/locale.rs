@@ -0,0 +1,288 @@
+// locale: numbers, amounts of money and dates written the way a given country
+// writes them, for scripts that produce reports
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+use crate::lang::suggest;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("locale");
+    module.register_value("locales", Value::List(LOCALES.iter().map(|locale| Value::Str(locale.name.to_string())).collect()));
+    module.register_fn("format_number", format_number);
+    module.register_fn("format_currency", format_currency);
+    module.register_fn("format_date", format_date);
+    module
+}
+
+/// How one locale writes numbers, money and dates.
+struct Locale {
+    name: &'static str,
+    group: &'static str,
+    decimal: &'static str,
+    /// Digits the integer part needs before it is grouped at all; Spanish
+    /// writes 1234 but 12.345
+    min_grouping: usize,
+    /// `{amount}` and `{symbol}` in the order this locale puts them
+    currency: &'static str,
+    /// `{d}`/`{dd}` day, `{M}`/`{MM}` month number, `{MMMM}` month name, `{yyyy}` year
+    short_date: &'static str,
+    long_date: &'static str,
+    months: [&'static str; 12],
+}
+
+const ENGLISH_MONTHS: [&str; 12] =
+    ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"];
+
+const LOCALES: &[Locale] = &[
+    Locale {
+        name: "en-US",
+        group: ",",
+        decimal: ".",
+        min_grouping: 1,
+        currency: "{symbol}{amount}",
+        short_date: "{M}/{d}/{yyyy}",
+        long_date: "{MMMM} {d}, {yyyy}",
+        months: ENGLISH_MONTHS,
+    },
+    Locale {
+        name: "en-GB",
+        group: ",",
+        decimal: ".",
+        min_grouping: 1,
+        currency: "{symbol}{amount}",
+        short_date: "{dd}/{MM}/{yyyy}",
+        long_date: "{d} {MMMM} {yyyy}",
+        months: ENGLISH_MONTHS,
+    },
+    Locale {
+        name: "de-DE",
+        group: ".",
+        decimal: ",",
+        min_grouping: 1,
+        currency: "{amount}\u{a0}{symbol}",
+        short_date: "{dd}.{MM}.{yyyy}",
+        long_date: "{d}. {MMMM} {yyyy}",
+        months: ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+    },
+    Locale {
+        name: "fr-FR",
+        group: "\u{202f}",
+        decimal: ",",
+        min_grouping: 1,
+        currency: "{amount}\u{a0}{symbol}",
+        short_date: "{dd}/{MM}/{yyyy}",
+        long_date: "{d} {MMMM} {yyyy}",
+        months: ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+    },
+    Locale {
+        name: "es-ES",
+        group: ".",
+        decimal: ",",
+        min_grouping: 2,
+        currency: "{amount}\u{a0}{symbol}",
+        short_date: "{d}/{M}/{yyyy}",
+        long_date: "{d} de {MMMM} de {yyyy}",
+        months: ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+    },
+    Locale {
+        name: "pt-BR",
+        group: ".",
+        decimal: ",",
+        min_grouping: 1,
+        currency: "{symbol}\u{a0}{amount}",
+        short_date: "{dd}/{MM}/{yyyy}",
+        long_date: "{d} de {MMMM} de {yyyy}",
+        months: ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+    },
+    Locale {
+        name: "ja-JP",
+        group: ",",
+        decimal: ".",
+        min_grouping: 1,
+        currency: "{symbol}{amount}",
+        short_date: "{yyyy}/{MM}/{dd}",
+        long_date: "{yyyy}年{M}月{d}日",
+        months: ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    },
+];
+
+/// Symbols for the common currencies, and how many decimals they are written
+/// with; any other code is written as itself with two decimals.
+const CURRENCIES: &[(&str, &str, u32)] =
+    &[("USD", "$", 2), ("EUR", "€", 2), ("GBP", "£", 2), ("JPY", "¥", 0), ("BRL", "R$", 2), ("CHF", "CHF", 2), ("CNY", "¥", 2), ("INR", "₹", 2)];
+
+/// `format_number(x, locale="en-US", decimals=None)`: `x` with the locale's
+/// grouping and decimal separators. Without `decimals`, ints have none and
+/// floats and decimals keep the digits they print with.
+fn format_number(args: &[Value]) -> Result<Value, Exception> {
+    let (number, rest) = number_arg("format_number", args)?;
+    let (locale, rest) = locale_arg("format_number", rest)?;
+    let decimals = match rest {
+        [] | [Value::None] => None,
+        [Value::Int(n)] if *n >= 0 => Some(*n as u32),
+        [other] => return Err(type_error(format!("locale.format_number() decimals must be a non-negative int, not '{}'", other.type_name()))),
+        _ => return Err(type_error(format!("locale.format_number() takes at most 3 arguments ({} given)", args.len()))),
+    };
+    Ok(Value::Str(localize(&number.digits(decimals)?, locale)))
+}
+
+/// `format_currency(x, currency, locale="en-US")`: an amount of `currency`
+/// (an ISO 4217 code such as "EUR") with its symbol placed as the locale does.
+fn format_currency(args: &[Value]) -> Result<Value, Exception> {
+    let (number, rest) = number_arg("format_currency", args)?;
+    let (code, rest) = match rest {
+        [Value::Str(code), rest @ ..] => (code.to_ascii_uppercase(), rest),
+        [other, ..] => return Err(type_error(format!("locale.format_currency() currency must be a str, not '{}'", other.type_name()))),
+        [] => return Err(type_error("locale.format_currency() missing the currency code".to_string())),
+    };
+    let (locale, rest) = locale_arg("format_currency", rest)?;
+    if !rest.is_empty() {
+        return Err(type_error(format!("locale.format_currency() takes at most 3 arguments ({} given)", args.len())));
+    }
+    let (symbol, decimals) = match CURRENCIES.iter().find(|(known, _, _)| *known == code) {
+        Some(&(_, symbol, decimals)) => (symbol.to_string(), decimals),
+        None => (code, 2),
+    };
+    let digits = number.digits(Some(decimals))?;
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits.as_str()),
+    };
+    let amount = locale.currency.replace("{amount}", &localize(digits, locale)).replace("{symbol}", &symbol);
+    Ok(Value::Str(format!("{}{}", sign, amount)))
+}
+
+/// `format_date(date, locale="en-US", style="short")`: a date as the locale
+/// writes it, `short` in numbers or `long` with the month's name. `date` is an
+/// ISO 8601 string ("2024-03-05", optionally with a time, which is ignored) or
+/// a Unix timestamp, taken as UTC.
+fn format_date(args: &[Value]) -> Result<Value, Exception> {
+    let date = match args.first() {
+        Some(Value::Str(text)) => parse_date(text)?,
+        Some(Value::Int(seconds)) => timestamp_date(*seconds)?,
+        Some(Value::Float(seconds)) if seconds.is_finite() => timestamp_date(seconds.floor() as i64)?,
+        Some(other) => return Err(type_error(format!("locale.format_date() expects an ISO date str or a timestamp, not '{}'", other.type_name()))),
+        None => return Err(type_error("locale.format_date() missing the date".to_string())),
+    };
+    let (locale, rest) = locale_arg("format_date", &args[1..])?;
+    let pattern = match rest {
+        [] => locale.short_date,
+        [Value::Str(style)] if style == "short" => locale.short_date,
+        [Value::Str(style)] if style == "long" => locale.long_date,
+        [Value::Str(style)] => return Err(value_error(format!("unknown date style '{}' (expected 'short' or 'long')", style))),
+        [other] => return Err(type_error(format!("locale.format_date() style must be a str, not '{}'", other.type_name()))),
+        _ => return Err(type_error(format!("locale.format_date() takes at most 3 arguments ({} given)", args.len()))),
+    };
+    let formatted = pattern
+        .replace("{dd}", &format!("{:02}", date.day()))
+        .replace("{d}", &date.day().to_string())
+        .replace("{MMMM}", locale.months[date.month0() as usize])
+        .replace("{MM}", &format!("{:02}", date.month()))
+        .replace("{M}", &date.month().to_string())
+        .replace("{yyyy}", &date.year().to_string());
+    Ok(Value::Str(formatted))
+}
+
+fn parse_date(text: &str) -> Result<NaiveDate, Exception> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").map(|datetime| datetime.date()))
+        .or_else(|_| DateTime::parse_from_rfc3339(text).map(|datetime| datetime.date_naive()))
+        .map_err(|_| value_error(format!("invalid ISO date '{}' (expected YYYY-MM-DD)", text)))
+}
+
+fn timestamp_date(seconds: i64) -> Result<NaiveDate, Exception> {
+    DateTime::from_timestamp(seconds, 0).map(|datetime| datetime.date_naive()).ok_or_else(|| value_error(format!("timestamp {} is out of range", seconds)))
+}
+
+/// A number argument, kept exact when it is an int or a decimal.
+enum Number {
+    Int(i64),
+    Float(f64),
+    Decimal(Decimal),
+}
+
+impl Number {
+    /// Plain digits with `.` for the point and `-` for the sign, rounded to
+    /// `decimals` places if given.
+    fn digits(&self, decimals: Option<u32>) -> Result<String, Exception> {
+        Ok(match (self, decimals) {
+            (Number::Int(n), None) => n.to_string(),
+            (Number::Int(n), Some(places)) => format!("{}.{}", n, "0".repeat(places as usize)).trim_end_matches('.').to_string(),
+            (Number::Float(x), _) if !x.is_finite() => return Err(value_error(format!("cannot format {} as a number", x))),
+            (Number::Float(x), None) => x.to_string(),
+            (Number::Float(x), Some(places)) => format!("{:.*}", places as usize, x),
+            (Number::Decimal(d), None) => d.to_string(),
+            (Number::Decimal(d), Some(places)) => format!("{:.*}", places as usize, d.round_dp(places)),
+        })
+    }
+}
+
+fn number_arg<'a>(func: &str, args: &'a [Value]) -> Result<(Number, &'a [Value]), Exception> {
+    let number = match args.first() {
+        Some(Value::Int(n)) => Number::Int(*n),
+        Some(Value::Float(x)) => Number::Float(*x),
+        Some(Value::Decimal(d)) => Number::Decimal(*d),
+        Some(other) => return Err(type_error(format!("locale.{}() expects a number, not '{}'", func, other.type_name()))),
+        None => return Err(type_error(format!("locale.{}() missing the number to format", func))),
+    };
+    Ok((number, &args[1..]))
+}
+
+/// The locale named by the next argument, en-US when there is none. Names are
+/// matched ignoring case and `_` for `-`, and a bare language ("de") picks
+/// that language's first locale.
+fn locale_arg<'a>(func: &str, args: &'a [Value]) -> Result<(&'static Locale, &'a [Value]), Exception> {
+    let name = match args.first() {
+        None => return Ok((&LOCALES[0], args)),
+        Some(Value::Str(name)) => name.replace('_', "-"),
+        Some(other) => return Err(type_error(format!("locale.{}() locale must be a str, not '{}'", func, other.type_name()))),
+    };
+    let found = LOCALES
+        .iter()
+        .find(|locale| locale.name.eq_ignore_ascii_case(&name))
+        .or_else(|| LOCALES.iter().find(|locale| locale.name.split('-').next().is_some_and(|language| language.eq_ignore_ascii_case(&name))));
+    match found {
+        Some(locale) => Ok((locale, &args[1..])),
+        None => {
+            let names = LOCALES.iter().map(|locale| locale.name);
+            let message = suggest::did_you_mean(format!("unknown locale '{}'", name), &name, names.clone());
+            Err(value_error(format!("{}; locales are: {}", message, names.collect::<Vec<_>>().join(", "))))
+        }
+    }
+}
+
+/// `digits` (as `Number::digits` writes them) with the locale's separators.
+fn localize(digits: &str, locale: &Locale) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits),
+    };
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let mut grouped = String::new();
+    let grouping = whole.len() >= 3 + locale.min_grouping;
+    for (i, digit) in whole.chars().enumerate() {
+        if grouping && i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push_str(locale.group);
+        }
+        grouped.push(digit);
+    }
+    match fraction {
+        Some(fraction) => format!("{}{}{}{}", sign, grouped, locale.decimal, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
+
+fn value_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![message])
+}
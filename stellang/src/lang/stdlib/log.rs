@@ -0,0 +1,122 @@
+// log: leveled, timestamped messages on stderr or in a log file
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+/// Environment variable holding the initial level, e.g. `STELLANG_LOG=debug`.
+pub const LEVEL_ENV: &str = "STELLANG_LOG";
+
+const LEVELS: &[&str] = &["debug", "info", "warn", "error"];
+const DEFAULT_LEVEL: usize = 1;
+
+/// Where messages go. Shared by every interpreter in the process, threads included,
+/// so one `set_file` call redirects the whole application.
+struct Logger {
+    level: usize,
+    file: Option<(String, File)>,
+}
+
+fn logger() -> &'static Mutex<Logger> {
+    static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
+    LOGGER.get_or_init(|| {
+        let level = std::env::var(LEVEL_ENV).ok()
+            .and_then(|name| level_index(&name))
+            .unwrap_or(DEFAULT_LEVEL);
+        Mutex::new(Logger { level, file: None })
+    })
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Logger> {
+    // A thread that panicked mid-write leaves nothing half-updated worth refusing
+    logger().lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn level_index(name: &str) -> Option<usize> {
+    let name = name.trim().to_ascii_lowercase();
+    let name = if name == "warning" { "warn" } else { name.as_str() };
+    LEVELS.iter().position(|level| *level == name)
+}
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("log");
+    module.register_fn("debug", |args| write(0, "log.debug", args));
+    module.register_fn("info", |args| write(1, "log.info", args));
+    module.register_fn("warn", |args| write(2, "log.warn", args));
+    module.register_fn("error", |args| write(3, "log.error", args));
+    module.register_fn("level", |_| Ok(Value::Str(LEVELS[lock().level].to_string())));
+    module.register_fn("set_level", set_level);
+    module.register_fn("set_file", set_file);
+    module
+}
+
+fn set_level(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(name)] = args else {
+        return Err(Exception::new(ExceptionKind::TypeError, vec!["log.set_level() expects a level name".to_string()]));
+    };
+    let level = level_index(name).ok_or_else(|| {
+        Exception::new(ExceptionKind::ValueError, vec![format!("unknown log level '{}', expected one of {}", name, LEVELS.join(", "))])
+    })?;
+    lock().level = level;
+    Ok(Value::None)
+}
+
+/// `set_file(path)` appends messages to `path` instead of stderr;
+/// `set_file(None)` goes back to stderr.
+fn set_file(args: &[Value]) -> Result<Value, Exception> {
+    let file = match args {
+        [Value::None] => None,
+        [Value::Str(path)] => {
+            let file = OpenOptions::new().create(true).append(true).open(path)
+                .map_err(|err| Exception::new(ExceptionKind::OSError, vec![format!("cannot open log file '{}': {}", path, err)]))?;
+            Some((path.clone(), file))
+        }
+        _ => return Err(Exception::new(ExceptionKind::TypeError, vec!["log.set_file() expects a path or None".to_string()])),
+    };
+    lock().file = file;
+    Ok(Value::None)
+}
+
+/// Write `message field...` at `level` if it passes the filter. Dict fields are
+/// spelled `key=value` in key order; anything else is appended as displayed.
+fn write(level: usize, func: &str, args: &[Value]) -> Result<Value, Exception> {
+    let Some((message, fields)) = args.split_first() else {
+        return Err(Exception::new(ExceptionKind::TypeError, vec![format!("{}() missing message", func)]));
+    };
+    let mut logger = lock();
+    if level < logger.level {
+        return Ok(Value::None);
+    }
+    let mut line = format!(
+        "{} {:<5} {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        LEVELS[level].to_ascii_uppercase(),
+        message.to_display_string()
+    );
+    for field in fields {
+        match field {
+            Value::Dict(map) => {
+                let mut pairs: Vec<String> = map.iter().map(|(k, v)| format!("{}={}", k.to_display_string(), v.to_display_string())).collect();
+                pairs.sort();
+                for pair in pairs {
+                    line.push(' ');
+                    line.push_str(&pair);
+                }
+            }
+            other => {
+                line.push(' ');
+                line.push_str(&other.to_display_string());
+            }
+        }
+    }
+    match &mut logger.file {
+        Some((path, file)) => writeln!(file, "{}", line)
+            .map_err(|err| Exception::new(ExceptionKind::OSError, vec![format!("cannot write log file '{}': {}", path, err)]))?,
+        None => eprintln!("{}", line),
+    }
+    Ok(Value::None)
+}
@@ -0,0 +1,52 @@
+// math: float constants and classification helpers
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("math");
+    module.register_value("pi", Value::Float(std::f64::consts::PI));
+    module.register_value("e", Value::Float(std::f64::consts::E));
+    module.register_value("tau", Value::Float(std::f64::consts::TAU));
+    module.register_value("inf", Value::Float(f64::INFINITY));
+    module.register_value("nan", Value::Float(f64::NAN));
+    module.register_fn("isclose", isclose);
+    module.register_fn("isnan", |args| Ok(Value::Bool(number("isnan", args, 0)?.is_nan())));
+    module.register_fn("isinf", |args| Ok(Value::Bool(number("isinf", args, 0)?.is_infinite())));
+    module.register_fn("isfinite", |args| Ok(Value::Bool(number("isfinite", args, 0)?.is_finite())));
+    module
+}
+
+fn number(func: &str, args: &[Value], index: usize) -> Result<f64, Exception> {
+    match args.get(index) {
+        Some(Value::Int(n)) => Ok(*n as f64),
+        Some(Value::Float(f)) => Ok(*f),
+        Some(Value::Bool(b)) => Ok(*b as i64 as f64),
+        Some(other) => Err(Exception::new(ExceptionKind::TypeError, vec![format!("math.{}() expects a number, not '{}'", func, other.type_name())])),
+        None => Err(Exception::new(ExceptionKind::TypeError, vec![format!("math.{}() missing argument {}", func, index + 1)])),
+    }
+}
+
+/// `isclose(a, b, rel_tol=1e-09, abs_tol=0.0)`: whether `a` and `b` differ by at most
+/// `rel_tol` times the larger magnitude, or by `abs_tol`. NaN is close to nothing,
+/// and an infinity only to itself.
+fn isclose(args: &[Value]) -> Result<Value, Exception> {
+    if !(2..=4).contains(&args.len()) {
+        return Err(Exception::new(ExceptionKind::TypeError, vec![format!("math.isclose() takes 2 to 4 arguments ({} given)", args.len())]));
+    }
+    let (a, b) = (number("isclose", args, 0)?, number("isclose", args, 1)?);
+    let rel_tol = if args.len() > 2 { number("isclose", args, 2)? } else { 1e-9 };
+    let abs_tol = if args.len() > 3 { number("isclose", args, 3)? } else { 0.0 };
+    if rel_tol < 0.0 || abs_tol < 0.0 {
+        return Err(Exception::new(ExceptionKind::ValueError, vec!["tolerances must be non-negative".to_string()]));
+    }
+    if a == b {
+        return Ok(Value::Bool(true));
+    }
+    if a.is_infinite() || b.is_infinite() {
+        return Ok(Value::Bool(false));
+    }
+    let diff = (a - b).abs();
+    Ok(Value::Bool(diff <= rel_tol * a.abs().max(b.abs()) || diff <= abs_tol))
+}
@@ -0,0 +1,54 @@
+// Standard library modules that ship with the interpreter and load with `import name`
+
+use super::native::NativeModule;
+
+mod archive;
+mod argparse;
+pub mod config;
+pub(crate) mod debug;
+pub(crate) mod decimal;
+mod encoding;
+mod hash;
+pub(crate) mod http;
+pub(crate) mod inspect;
+mod locale;
+mod log;
+mod math;
+mod path;
+mod proc;
+pub mod sys;
+pub mod template;
+mod term;
+mod toml;
+pub(crate) mod url;
+mod uuid;
+mod yaml;
+
+/// The built-in module called `name`, if there is one.
+pub fn module(name: &str) -> Option<NativeModule> {
+    match name {
+        "archive" => Some(archive::module()),
+        "argparse" => Some(argparse::module()),
+        "base64" => Some(encoding::base64_module()),
+        "config" => Some(config::module()),
+        "debug" => Some(debug::module()),
+        "decimal" => Some(decimal::module()),
+        "hash" => Some(hash::module()),
+        "hex" => Some(encoding::hex_module()),
+        "http" => Some(http::module()),
+        "inspect" => Some(inspect::module()),
+        "locale" => Some(locale::module()),
+        "log" => Some(log::module()),
+        "math" => Some(math::module()),
+        "path" => Some(path::module()),
+        "proc" => Some(proc::module()),
+        "sys" => Some(sys::module()),
+        "template" => Some(template::module()),
+        "term" => Some(term::module()),
+        "toml" => Some(toml::module()),
+        "url" => Some(url::module()),
+        "uuid" => Some(uuid::module()),
+        "yaml" => Some(yaml::module()),
+        _ => None,
+    }
+}
@@ -0,0 +1,111 @@
+// path: portable path manipulation and filesystem queries
+
+use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("path");
+    module.register_value("sep", Value::Str(MAIN_SEPARATOR_STR.to_string()));
+    module.register_fn("join", join);
+    module.register_fn("split", |args| {
+        let path = Path::new(text("split", args)?);
+        Ok(Value::Tuple(vec![Value::Str(dirname(path)), Value::Str(basename(path))]))
+    });
+    module.register_fn("dirname", |args| Ok(Value::Str(dirname(Path::new(text("dirname", args)?)))));
+    module.register_fn("basename", |args| Ok(Value::Str(basename(Path::new(text("basename", args)?)))));
+    module.register_fn("extension", |args| {
+        let extension = Path::new(text("extension", args)?).extension();
+        Ok(Value::Str(extension.map_or_else(String::new, |ext| ext.to_string_lossy().into_owned())))
+    });
+    module.register_fn("normalize", |args| Ok(Value::Str(normalize(text("normalize", args)?))));
+    // The functions below look at the filesystem, so sandboxes gate them like open()
+    module.register_fn("absolute", |args| {
+        let absolute = std::path::absolute(text("absolute", args)?).map_err(io_error)?;
+        Ok(Value::Str(normalize(&absolute.to_string_lossy())))
+    });
+    module.register_fn("exists", |args| Ok(Value::Bool(Path::new(text("exists", args)?).exists())));
+    module.register_fn("isfile", |args| Ok(Value::Bool(Path::new(text("isfile", args)?).is_file())));
+    module.register_fn("isdir", |args| Ok(Value::Bool(Path::new(text("isdir", args)?).is_dir())));
+    module.register_fn("glob", glob);
+    module
+}
+
+fn text<'a>(func: &str, args: &'a [Value]) -> Result<&'a str, Exception> {
+    match args {
+        [Value::Str(s)] => Ok(s),
+        [other] => Err(Exception::new(ExceptionKind::TypeError, vec![format!("path.{}() expects a str, not '{}'", func, other.type_name())])),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("path.{}() takes exactly 1 argument ({} given)", func, args.len())])),
+    }
+}
+
+fn io_error(err: std::io::Error) -> Exception {
+    Exception::new(ExceptionKind::OSError, vec![err.to_string()])
+}
+
+/// `join(a, b, ...)`: the parts joined with the platform separator. An absolute
+/// part discards everything before it, as in Python's `os.path.join`.
+fn join(args: &[Value]) -> Result<Value, Exception> {
+    let mut joined = PathBuf::new();
+    for arg in args {
+        let Value::Str(part) = arg else {
+            return Err(Exception::new(ExceptionKind::TypeError, vec![format!("path.join() expects str parts, not '{}'", arg.type_name())]));
+        };
+        joined.push(part);
+    }
+    Ok(Value::Str(joined.to_string_lossy().into_owned()))
+}
+
+fn dirname(path: &Path) -> String {
+    path.parent().map_or_else(String::new, |parent| parent.to_string_lossy().into_owned())
+}
+
+fn basename(path: &Path) -> String {
+    path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// Collapse `.`, `..` and repeated separators without touching the filesystem.
+/// Both `/` and `\` count as separators so Windows-style paths written in a
+/// script work everywhere; the result uses the platform separator.
+fn normalize(path: &str) -> String {
+    let (prefix, rest) = match path.split_once(':') {
+        // Windows drive letter such as `C:`
+        Some((drive, rest)) if drive.len() == 1 && drive.chars().all(|c| c.is_ascii_alphabetic()) => (format!("{}:", drive), rest),
+        _ => (String::new(), path),
+    };
+    let rooted = rest.starts_with(['/', '\\']);
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|last| *last != "..") => {
+                parts.pop();
+            }
+            // `..` at the root stays at the root
+            ".." if rooted => {}
+            _ => parts.push(part),
+        }
+    }
+    let root = if rooted { MAIN_SEPARATOR_STR } else { "" };
+    let joined = parts.join(MAIN_SEPARATOR_STR);
+    if prefix.is_empty() && !rooted && joined.is_empty() {
+        return ".".to_string();
+    }
+    format!("{}{}{}", prefix, root, joined)
+}
+
+/// `glob(pattern)`: sorted paths matching a pattern such as `src/**/*.stl`.
+fn glob(args: &[Value]) -> Result<Value, Exception> {
+    let pattern = text("glob", args)?;
+    let entries = ::glob::glob(pattern)
+        .map_err(|err| Exception::new(ExceptionKind::ValueError, vec![format!("invalid glob pattern: {}", err)]))?;
+    let mut matches = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| io_error(err.into()))?;
+        matches.push(path.to_string_lossy().into_owned());
+    }
+    matches.sort();
+    Ok(Value::List(matches.into_iter().map(Value::Str).collect()))
+}
@@ -0,0 +1,83 @@
+// proc: running other programs alongside a script, talking to them through
+// pipes, and waiting for or stopping them
+
+use std::process::{Command, Stdio};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::fileio;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+use crate::lang::process::ProcessHandle;
+
+const OPTIONS: &[&str] = &["stdin", "stdout", "stderr", "cwd", "env"];
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("proc");
+    module.register_fn("spawn", spawn);
+    module
+}
+
+/// `spawn(cmd, args=[], options={})`: start the program `cmd` with `args` and
+/// return its process. Options:
+///
+/// - `stdin`, `stdout`, `stderr`: `"inherit"` (the default) shares the
+///   script's stream, `"pipe"` connects it to the process's `write()`,
+///   `read()`/`read_line()` or `read_err()` methods, and `"null"` discards it.
+/// - `cwd`: the directory to run in.
+/// - `env`: a dict of variables to set on top of the script's environment.
+fn spawn(args: &[Value]) -> Result<Value, Exception> {
+    let (cmd, arguments, options) = match args {
+        [Value::Str(cmd)] => (cmd, &[][..], None),
+        [Value::Str(cmd), Value::List(arguments)] => (cmd, arguments.as_slice(), None),
+        [Value::Str(cmd), Value::List(arguments), Value::Dict(options)] => (cmd, arguments.as_slice(), Some(options)),
+        _ => return Err(type_error("proc.spawn() expects a command str, an optional list of arguments and an optional dict of options".to_string())),
+    };
+    let mut command = Command::new(cmd);
+    command.args(arguments.iter().map(Value::to_display_string));
+    command.stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::inherit());
+    for (name, value) in options.into_iter().flatten() {
+        let name = name.to_display_string();
+        match (name.as_str(), value) {
+            ("stdin", Value::Str(mode)) => {
+                command.stdin(stdio("stdin", mode)?);
+            }
+            ("stdout", Value::Str(mode)) => {
+                command.stdout(stdio("stdout", mode)?);
+            }
+            ("stderr", Value::Str(mode)) => {
+                command.stderr(stdio("stderr", mode)?);
+            }
+            ("cwd", Value::Str(dir)) => {
+                command.current_dir(dir);
+            }
+            ("env", Value::Dict(variables)) => {
+                command.envs(variables.iter().map(|(name, value)| (name.to_display_string(), value.to_display_string())));
+            }
+            (name, value) if OPTIONS.contains(&name) => {
+                return Err(type_error(format!("proc.spawn() option '{}' can't be '{}'", name, value.type_name())));
+            }
+            (name, _) => {
+                return Err(Exception::new(ExceptionKind::ValueError, vec![format!("proc.spawn() got an unknown option '{}'; options are: {}", name, OPTIONS.join(", "))]));
+            }
+        }
+    }
+    let child = command.spawn().map_err(|err| {
+        let exc = fileio::io_error(err);
+        let message = format!("cannot run '{}': {}", cmd, exc.args.first().cloned().unwrap_or_default());
+        Exception::new(exc.kind, vec![message])
+    })?;
+    Ok(Value::Process { handle: ProcessHandle::new(cmd.clone(), child) })
+}
+
+fn stdio(stream: &str, mode: &str) -> Result<Stdio, Exception> {
+    match mode {
+        "inherit" => Ok(Stdio::inherit()),
+        "pipe" => Ok(Stdio::piped()),
+        "null" => Ok(Stdio::null()),
+        _ => Err(Exception::new(ExceptionKind::ValueError, vec![format!("proc.spawn() {} must be 'inherit', 'pipe' or 'null', not '{}'", stream, mode)])),
+    }
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
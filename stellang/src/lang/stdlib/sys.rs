@@ -0,0 +1,143 @@
+// sys: the platform the interpreter runs on, its command line and runtime limits,
+// and the cfg() options derived from them
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::fileio::FileHandle;
+use crate::lang::interpreter::Value;
+use crate::lang::native::{self, NativeModule};
+use crate::lang::output::Output;
+use crate::lang::sandbox::Capabilities;
+
+// What `sys.argv` reports; set by the host before the script runs
+static ARGV: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Deepest nesting of function calls before RecursionError, as in Python.
+pub const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+/// Stack for a thread running scripts, which the stellang binary and
+/// `spawn()` give theirs. It is address space reserved up front; pages are only
+/// touched as calls nest.
+pub const STACK_SIZE: usize = 1 << 30;
+
+// Native stack one call of a script function may take, with room for bodies
+// that nest expressions deeply; unoptimized builds take far more
+const STACK_PER_CALL: usize = if cfg!(debug_assertions) { 1 << 20 } else { 64 << 10 };
+
+/// The highest limit `sys.setrecursionlimit()` takes: as deep as calls can
+/// nest in STACK_SIZE before the native stack overflows.
+pub const MAX_RECURSION_LIMIT: usize = STACK_SIZE / STACK_PER_CALL;
+
+/// An interpreter's recursion limit, shared with the sub-interpreters that run
+/// its calls so `sys.setrecursionlimit()` inside a function lasts.
+#[derive(Debug, Clone)]
+pub struct RecursionLimit(Arc<AtomicUsize>);
+
+impl RecursionLimit {
+    pub fn new(limit: usize) -> Self {
+        RecursionLimit(Arc::new(AtomicUsize::new(limit)))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Change the limit, which must be from 1 to MAX_RECURSION_LIMIT.
+    pub fn set(&self, limit: i64) -> Result<(), Exception> {
+        if limit < 1 {
+            return Err(Exception::new(ExceptionKind::ValueError, vec!["recursion limit must be greater or equal than 1".to_string()]));
+        }
+        if limit as u64 > MAX_RECURSION_LIMIT as u64 {
+            return Err(Exception::new(ExceptionKind::ValueError, vec![format!("recursion limit {} is more than the stack can hold (at most {})", limit, MAX_RECURSION_LIMIT)]));
+        }
+        self.0.store(limit as usize, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Default for RecursionLimit {
+    fn default() -> Self {
+        RecursionLimit::new(DEFAULT_RECURSION_LIMIT)
+    }
+}
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("sys");
+    module.register_value("platform", Value::Str(std::env::consts::OS.to_string()));
+    module.register_value("arch", Value::Str(std::env::consts::ARCH.to_string()));
+    module.register_value("version", Value::Str(env!("CARGO_PKG_VERSION").to_string()));
+    let argv = ARGV.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    module.register_value("argv", Value::List(argv.iter().cloned().map(Value::Str).collect()));
+    let path = native::search_path().into_iter().map(|dir| Value::Str(dir.to_string_lossy().into_owned()));
+    module.register_value("path", Value::List(path.collect()));
+    module.register_fn("exit", |args| match args {
+        [] => Err(Exception::new(ExceptionKind::SystemExit, vec!["0".to_string()])),
+        [code] => Err(Exception::new(ExceptionKind::SystemExit, vec![code.to_display_string()])),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("sys.exit() takes at most 1 argument ({} given)", args.len())])),
+    });
+    module.register_fn("getsizeof", |args| match args {
+        [value] => Ok(Value::Int(getsizeof(value) as i64)),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec![format!("sys.getsizeof() takes exactly 1 argument ({} given)", args.len())])),
+    });
+    // The limit belongs to the calling interpreter
+    module.register_interpreter_fn("getrecursionlimit");
+    module.register_interpreter_fn("setrecursionlimit");
+    module
+}
+
+/// Add `sys.stdin`, `sys.stdout` and `sys.stderr`, file values for the
+/// process's stdin, the interpreter's output and the process's stderr. They
+/// depend on the importing interpreter, so they are bound at import time rather
+/// than in `module()`, and only where its sandbox permits `input()` (stdin) or
+/// `print()` (stdout and stderr).
+pub(crate) fn register_streams(module: &mut NativeModule, stdout: &Output, capabilities: &Capabilities) {
+    if capabilities.check("input").is_ok() {
+        module.register_value("stdin", Value::File { path: "<stdin>".to_string(), mode: "r".to_string(), handle: FileHandle::stdin() });
+    }
+    if capabilities.check("print").is_ok() {
+        module.register_value("stdout", Value::File { path: "<stdout>".to_string(), mode: "w".to_string(), handle: FileHandle::stdout(stdout.clone()) });
+        module.register_value("stderr", Value::File { path: "<stderr>".to_string(), mode: "w".to_string(), handle: FileHandle::stderr() });
+    }
+}
+
+/// Set what scripts see as `sys.argv`: conventionally the script's path
+/// followed by its arguments.
+pub fn set_argv(args: Vec<String>) {
+    *ARGV.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = args;
+}
+
+/// What `sys.argv` reports.
+pub(crate) fn argv() -> Vec<String> {
+    ARGV.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// Bytes held by `value` itself: the value plus the buffers it owns directly.
+/// Like Python's, elements of a container are not counted.
+pub(crate) fn getsizeof(value: &Value) -> usize {
+    use std::mem::size_of;
+    let owned = match value {
+        Value::Str(s) => s.capacity(),
+        Value::Bytes(b) | Value::ByteArray(b) | Value::MemoryView(b) => b.capacity(),
+        Value::List(items) | Value::Tuple(items) => items.capacity() * size_of::<Value>(),
+        Value::Set(items) | Value::FrozenSet(items) => items.capacity() * size_of::<Value>(),
+        Value::Dict(entries) => entries.capacity() * 2 * size_of::<Value>(),
+        Value::Instance { class_name, fields, .. } => class_name.capacity() + fields.capacity() * (size_of::<String>() + size_of::<Value>()),
+        _ => 0,
+    };
+    size_of::<Value>() + owned
+}
+
+/// Whether `cfg(option)` holds: `"debug"` or `"release"` for the interpreter's
+/// mode, or the running OS (`"linux"`, `"windows"`, `"macos"`), OS family
+/// (`"unix"`, `"windows"`) or architecture (`"x86_64"`, `"aarch64"`). Like
+/// Rust's cfg, anything else is false.
+pub fn cfg(option: &str, debug: bool) -> bool {
+    use std::env::consts::{ARCH, FAMILY, OS};
+    match option {
+        "debug" => debug,
+        "release" => !debug,
+        _ => [OS, FAMILY, ARCH].contains(&option),
+    }
+}
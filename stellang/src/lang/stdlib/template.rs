@@ -0,0 +1,226 @@
+// template: mustache-style text templates, for generated code, HTML responses
+// and project templates
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("template");
+    module.register_fn("render", |args| {
+        let (text, data, escape) = match args {
+            [Value::Str(text)] => (text, &Value::None, true),
+            [Value::Str(text), data] => (text, data, true),
+            [Value::Str(text), data, Value::Str(escape)] => match escape.as_str() {
+                "html" => (text, data, true),
+                "none" => (text, data, false),
+                other => return Err(Exception::new(ExceptionKind::ValueError, vec![format!("template.render() escape must be 'html' or 'none', not '{}'", other)])),
+            },
+            _ => return Err(type_error("template.render() expects a template str, the data and an optional escape mode".to_string())),
+        };
+        Ok(Value::Str(render(text, data, escape)?))
+    });
+    module.register_fn("escape", |args| match args {
+        [Value::Str(text)] => Ok(Value::Str(escape_html(text))),
+        _ => Err(type_error("template.escape() expects one str".to_string())),
+    });
+    module
+}
+
+/// Render `template` with the values in `data`:
+///
+/// - `{{name}}` is the value of `name`, HTML-escaped when `escape` is set;
+///   `{{{name}}}` and `{{& name}}` are never escaped. `a.b` looks up `b` in
+///   `a` and `.` is the current item.
+/// - `{{#name}}...{{/name}}` renders its contents once for each item of a
+///   list, once inside a dict or instance, once for any other true value, and
+///   not at all for false, None or empty values.
+/// - `{{^name}}...{{/name}}` renders its contents only when `name` is missing
+///   or false.
+/// - `{{! comment}}` renders nothing.
+///
+/// Names are looked up from the innermost section out, and missing ones
+/// render as nothing. Section and comment tags alone on a line take the line
+/// with them, so templates can be laid out like the text they produce.
+pub fn render(template: &str, data: &Value, escape: bool) -> Result<String, Exception> {
+    let nodes = parse(template)?;
+    let mut out = String::new();
+    render_nodes(&nodes, &mut vec![data], escape, &mut out);
+    Ok(out)
+}
+
+enum Node {
+    Text(String),
+    Variable { name: String, escape: bool },
+    Section { name: String, inverted: bool, children: Vec<Node> },
+}
+
+/// A tag's kind: the character after `{{`, or a space for a variable.
+struct Tag<'a> {
+    kind: char,
+    name: &'a str,
+    /// Byte offsets of the tag, widened to its whole line when it stands alone
+    start: usize,
+    end: usize,
+}
+
+fn parse(template: &str) -> Result<Vec<Node>, Exception> {
+    // Open sections: the name, whether inverted, the line it opened on and the nodes before it
+    let mut open: Vec<(String, bool, usize, Vec<Node>)> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut position = 0;
+    while let Some(tag) = next_tag(template, position)? {
+        if tag.start > position {
+            nodes.push(Node::Text(template[position..tag.start].to_string()));
+        }
+        position = tag.end;
+        let name = tag.name.to_string();
+        match tag.kind {
+            '!' => {}
+            '#' | '^' => {
+                let line = line_number(template, tag.start);
+                open.push((name, tag.kind == '^', line, std::mem::take(&mut nodes)));
+            }
+            '/' => {
+                let Some((opened, inverted, _, before)) = open.pop() else {
+                    return Err(syntax_error(template, tag.start, format!("'{{{{/{}}}}}' closes no section", name)));
+                };
+                if opened != name {
+                    return Err(syntax_error(template, tag.start, format!("'{{{{/{}}}}}' closes section '{}'", name, opened)));
+                }
+                let children = std::mem::replace(&mut nodes, before);
+                nodes.push(Node::Section { name, inverted, children });
+            }
+            '{' | '&' => nodes.push(Node::Variable { name, escape: false }),
+            _ => nodes.push(Node::Variable { name, escape: true }),
+        }
+    }
+    if let Some((name, _, line, _)) = open.pop() {
+        return Err(Exception::new(ExceptionKind::ValueError, vec![format!("template line {}: section '{}' is never closed", line, name)]));
+    }
+    if position < template.len() {
+        nodes.push(Node::Text(template[position..].to_string()));
+    }
+    Ok(nodes)
+}
+
+/// The first tag at or after `from`, if any.
+fn next_tag(template: &str, from: usize) -> Result<Option<Tag<'_>>, Exception> {
+    let Some(offset) = template[from..].find("{{") else {
+        return Ok(None);
+    };
+    let start = from + offset;
+    let inner = start + 2;
+    let (kind, close) = match template[inner..].chars().next() {
+        Some('{') => ('{', "}}}"),
+        Some(c @ ('#' | '^' | '/' | '!' | '&')) => (c, "}}"),
+        _ => (' ', "}}"),
+    };
+    let name_start = if kind == ' ' { inner } else { inner + 1 };
+    let Some(length) = template[name_start..].find(close) else {
+        return Err(syntax_error(template, start, format!("tag is never closed with '{}'", close)));
+    };
+    let end = name_start + length + close.len();
+    let name = template[name_start..name_start + length].trim();
+    if name.is_empty() && kind != '!' {
+        return Err(syntax_error(template, start, "tag has no name".to_string()));
+    }
+    let mut tag = Tag { kind, name, start, end };
+    // A section or comment tag with only whitespace around it on its line
+    // leaves no blank line behind
+    if matches!(kind, '#' | '^' | '/' | '!') {
+        let line_start = template[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = template[end..].find('\n').map_or(template.len(), |newline| end + newline + 1);
+        if template[line_start..start].trim().is_empty() && template[end..line_end].trim().is_empty() && line_start >= from {
+            tag.start = line_start;
+            tag.end = line_end;
+        }
+    }
+    Ok(Some(tag))
+}
+
+fn render_nodes<'a>(nodes: &'a [Node], context: &mut Vec<&'a Value>, escape: bool, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Variable { name, escape: escaped } => {
+                let text = match lookup(context, name) {
+                    None | Some(Value::None) => String::new(),
+                    Some(value) => value.to_display_string(),
+                };
+                out.push_str(&if escape && *escaped { escape_html(&text) } else { text });
+            }
+            Node::Section { name, inverted: true, children } => {
+                if !lookup(context, name).is_some_and(Value::is_truthy) {
+                    render_nodes(children, context, escape, out);
+                }
+            }
+            Node::Section { name, inverted: false, children } => match lookup(context, name) {
+                Some(Value::List(items) | Value::Tuple(items)) => {
+                    for item in items {
+                        context.push(item);
+                        render_nodes(children, context, escape, out);
+                        context.pop();
+                    }
+                }
+                Some(value) if value.is_truthy() => {
+                    context.push(value);
+                    render_nodes(children, context, escape, out);
+                    context.pop();
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+/// `name` in the innermost context that has its first part, then each later
+/// part of a dotted name inside that.
+fn lookup<'a>(context: &[&'a Value], name: &str) -> Option<&'a Value> {
+    if name == "." {
+        return context.last().copied();
+    }
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+    let mut value = context.iter().rev().find_map(|value| member(value, first))?;
+    for part in parts {
+        value = member(value, part)?;
+    }
+    Some(value)
+}
+
+fn member<'a>(value: &'a Value, name: &str) -> Option<&'a Value> {
+    match value {
+        Value::Dict(entries) => entries.get(&Value::Str(name.to_string())),
+        Value::Instance { fields, .. } => fields.get(name),
+        Value::List(items) | Value::Tuple(items) => items.get(name.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn line_number(template: &str, offset: usize) -> usize {
+    template[..offset].matches('\n').count() + 1
+}
+
+fn syntax_error(template: &str, offset: usize, message: String) -> Exception {
+    Exception::new(ExceptionKind::ValueError, vec![format!("template line {}: {}", line_number(template, offset), message)])
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
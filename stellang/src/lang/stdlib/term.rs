@@ -0,0 +1,165 @@
+// term: colors, text styles, cursor movement and progress bars for command-line
+// tools, as ANSI escape sequences to print
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+const COLORS: &[(&str, u8)] =
+    &[("black", 30), ("red", 31), ("green", 32), ("yellow", 33), ("blue", 34), ("magenta", 35), ("cyan", 36), ("white", 37), ("gray", 90)];
+
+// Whether escapes are written: unset follows the terminal, or set_enabled()
+const AUTO: u8 = 0;
+const ON: u8 = 1;
+const OFF: u8 = 2;
+static ENABLED: AtomicU8 = AtomicU8::new(AUTO);
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("term");
+    module.register_fn("color", color);
+    module.register_fn("bold", |args| style("bold", 1, args));
+    module.register_fn("dim", |args| style("dim", 2, args));
+    module.register_fn("italic", |args| style("italic", 3, args));
+    module.register_fn("underline", |args| style("underline", 4, args));
+    module.register_fn("clear", |args| control("clear", args, "\x1b[2J\x1b[H"));
+    module.register_fn("clear_line", |args| control("clear_line", args, "\r\x1b[2K"));
+    module.register_fn("hide_cursor", |args| control("hide_cursor", args, "\x1b[?25l"));
+    module.register_fn("show_cursor", |args| control("show_cursor", args, "\x1b[?25h"));
+    module.register_fn("move_to", move_to);
+    module.register_fn("up", |args| step("up", 'A', args));
+    module.register_fn("down", |args| step("down", 'B', args));
+    module.register_fn("right", |args| step("right", 'C', args));
+    module.register_fn("left", |args| step("left", 'D', args));
+    module.register_fn("size", |args| {
+        expect_no_args("size", args)?;
+        let (columns, rows) = size();
+        Ok(Value::Tuple(vec![Value::Int(columns as i64), Value::Int(rows as i64)]))
+    });
+    module.register_fn("progress", progress);
+    module.register_fn("enabled", |args| {
+        expect_no_args("enabled", args)?;
+        Ok(Value::Bool(enabled()))
+    });
+    module.register_fn("set_enabled", set_enabled);
+    module
+}
+
+/// Escapes are written when stdout is a terminal and `NO_COLOR` is unset,
+/// unless a script decided otherwise with `term.set_enabled()`. Otherwise
+/// every function returns its text unchanged, so piped output stays plain.
+fn enabled() -> bool {
+    match ENABLED.load(Ordering::Relaxed) {
+        ON => true,
+        OFF => false,
+        _ => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// `set_enabled(flag)`: force escapes on or off; `None` goes back to following the terminal.
+fn set_enabled(args: &[Value]) -> Result<Value, Exception> {
+    let state = match args {
+        [Value::Bool(true)] => ON,
+        [Value::Bool(false)] => OFF,
+        [Value::None] => AUTO,
+        _ => return Err(type_error("term.set_enabled() expects true, false or None".to_string())),
+    };
+    ENABLED.store(state, Ordering::Relaxed);
+    Ok(Value::None)
+}
+
+/// `color(name, text)`: `text` in one of the eight terminal colors or gray.
+fn color(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(name), text] = args else {
+        return Err(type_error("term.color() expects a color name and the text".to_string()));
+    };
+    let Some(&(_, code)) = COLORS.iter().find(|(known, _)| known.eq_ignore_ascii_case(name)) else {
+        let names: Vec<&str> = COLORS.iter().map(|(known, _)| *known).collect();
+        return Err(Exception::new(ExceptionKind::ValueError, vec![format!("unknown color '{}'; colors are: {}", name, names.join(", "))]));
+    };
+    Ok(Value::Str(wrap(code, &text.to_display_string())))
+}
+
+fn style(func: &str, code: u8, args: &[Value]) -> Result<Value, Exception> {
+    let [text] = args else {
+        return Err(type_error(format!("term.{}() takes exactly 1 argument ({} given)", func, args.len())));
+    };
+    Ok(Value::Str(wrap(code, &text.to_display_string())))
+}
+
+fn wrap(code: u8, text: &str) -> String {
+    if enabled() { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_string() }
+}
+
+fn control(func: &str, args: &[Value], escape: &str) -> Result<Value, Exception> {
+    expect_no_args(func, args)?;
+    Ok(Value::Str(if enabled() { escape.to_string() } else { String::new() }))
+}
+
+/// `move_to(row, column)`: put the cursor there, counting from 1 at the top left.
+fn move_to(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Int(row), Value::Int(column)] = args else {
+        return Err(type_error("term.move_to() expects a row and a column".to_string()));
+    };
+    Ok(Value::Str(if enabled() { format!("\x1b[{};{}H", row.max(&1), column.max(&1)) } else { String::new() }))
+}
+
+/// `up(n=1)` and friends: move the cursor `n` cells.
+fn step(func: &str, direction: char, args: &[Value]) -> Result<Value, Exception> {
+    let cells = match args {
+        [] => 1,
+        [Value::Int(n)] if *n >= 0 => *n,
+        _ => return Err(type_error(format!("term.{}() expects a number of cells", func))),
+    };
+    Ok(Value::Str(if enabled() && cells > 0 { format!("\x1b[{}{}", cells, direction) } else { String::new() }))
+}
+
+/// `progress(done, total, width=30)`: a bar such as `[#######-------]  50%`,
+/// to print after `term.clear_line()` with `end=""` so it redraws in place.
+fn progress(args: &[Value]) -> Result<Value, Exception> {
+    let number = |value: &Value| match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Float(x) => Some(*x),
+        _ => None,
+    };
+    let (done, total, width) = match args {
+        [done, total] => (number(done), number(total), 30),
+        [done, total, Value::Int(width)] if *width > 0 => (number(done), number(total), *width as usize),
+        _ => (None, None, 0),
+    };
+    let (Some(done), Some(total)) = (done, total) else {
+        return Err(type_error("term.progress() expects done and total numbers and an optional width".to_string()));
+    };
+    let fraction = if total > 0.0 { (done / total).clamp(0.0, 1.0) } else { 1.0 };
+    let filled = (fraction * width as f64).round() as usize;
+    Ok(Value::Str(format!("[{}{}] {:>3}%", "#".repeat(filled), "-".repeat(width - filled), (fraction * 100.0).floor() as u32)))
+}
+
+/// The terminal's columns and rows, from stdout or else `COLUMNS`/`LINES`,
+/// falling back to 80x24 when output is not a terminal.
+fn size() -> (u16, u16) {
+    #[cfg(unix)]
+    {
+        // Safety: winsize is plain data that ioctl fills in on success
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+            return (size.ws_col, size.ws_row);
+        }
+    }
+    let from_env = |name: &str| std::env::var(name).ok().and_then(|value| value.parse().ok());
+    (from_env("COLUMNS").unwrap_or(80), from_env("LINES").unwrap_or(24))
+}
+
+fn expect_no_args(func: &str, args: &[Value]) -> Result<(), Exception> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(type_error(format!("term.{}() takes no arguments ({} given)", func, args.len())))
+    }
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
@@ -0,0 +1,72 @@
+// toml: read and write TOML documents such as stel.toml
+
+use ::toml::Value as Toml;
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("toml");
+    module.register_fn("loads", loads);
+    module.register_fn("dumps", dumps);
+    module
+}
+
+/// `loads(text)`: the document as a dict of tables, arrays and scalars.
+/// Dates and times come back as their TOML text.
+pub(super) fn loads(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(text)] = args else {
+        return Err(Exception::new(ExceptionKind::TypeError, vec!["toml.loads() expects one str".to_string()]));
+    };
+    let table: ::toml::Table = text.parse()
+        .map_err(|err: ::toml::de::Error| Exception::new(ExceptionKind::ValueError, vec![format!("invalid TOML: {}", err.to_string().trim_end())]))?;
+    Ok(from_toml(Toml::Table(table)))
+}
+
+// Table keys are strings, which hash by content
+#[allow(clippy::mutable_key_type)]
+fn from_toml(value: Toml) -> Value {
+    match value {
+        Toml::String(s) => Value::Str(s),
+        Toml::Integer(n) => Value::Int(n),
+        Toml::Float(f) => Value::Float(f),
+        Toml::Boolean(b) => Value::Bool(b),
+        Toml::Datetime(dt) => Value::Str(dt.to_string()),
+        Toml::Array(items) => Value::List(items.into_iter().map(from_toml).collect()),
+        Toml::Table(table) => Value::Dict(table.into_iter().map(|(k, v)| (Value::Str(k), from_toml(v))).collect()),
+    }
+}
+
+/// `dumps(dict)`: TOML text for a dict with string keys. TOML has no null, so
+/// None values are rejected rather than dropped.
+fn dumps(args: &[Value]) -> Result<Value, Exception> {
+    let [value @ Value::Dict(_)] = args else {
+        return Err(Exception::new(ExceptionKind::TypeError, vec!["toml.dumps() expects one dict".to_string()]));
+    };
+    let Toml::Table(table) = to_toml(value)? else { unreachable!("dicts convert to tables") };
+    ::toml::to_string(&table)
+        .map(Value::Str)
+        .map_err(|err| Exception::new(ExceptionKind::ValueError, vec![format!("cannot write TOML: {}", err)]))
+}
+
+fn to_toml(value: &Value) -> Result<Toml, Exception> {
+    Ok(match value {
+        Value::Str(s) => Toml::String(s.clone()),
+        Value::Int(n) => Toml::Integer(*n),
+        Value::Float(f) => Toml::Float(*f),
+        Value::Bool(b) => Toml::Boolean(*b),
+        Value::List(items) | Value::Tuple(items) => Toml::Array(items.iter().map(to_toml).collect::<Result<_, _>>()?),
+        Value::Dict(map) => {
+            let mut table = ::toml::Table::new();
+            for (key, item) in map {
+                let Value::Str(key) = key else {
+                    return Err(Exception::new(ExceptionKind::TypeError, vec![format!("TOML keys must be str, not '{}'", key.type_name())]));
+                };
+                table.insert(key.clone(), to_toml(item)?);
+            }
+            Toml::Table(table)
+        }
+        other => return Err(Exception::new(ExceptionKind::TypeError, vec![format!("'{}' values cannot be written as TOML", other.type_name())])),
+    })
+}
@@ -0,0 +1,229 @@
+// url: splitting URLs into their parts and back, percent-encoding, and query
+// strings, for scripts that talk to web services
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::hashing::ValueMap;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+const PARTS: &[&str] = &["scheme", "username", "password", "host", "port", "path", "query", "fragment"];
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("url");
+    module.register_fn("parse", parse);
+    module.register_fn("build", build);
+    module.register_fn("encode", encode);
+    module.register_fn("decode", |args| match args {
+        [Value::Str(text)] => Ok(Value::Str(decode(text, false))),
+        _ => Err(type_error("url.decode() expects one str".to_string())),
+    });
+    module.register_fn("parse_query", parse_query);
+    module.register_fn("encode_query", encode_query);
+    module
+}
+
+/// `parse(url)`: a dict of the URL's `scheme`, `username`, `password`,
+/// `host`, `port` (an int), `path`, `query` and `fragment`, as written (still
+/// percent-encoded). Parts the URL doesn't have are `""`, or None for `port`.
+#[allow(clippy::mutable_key_type)] // keys are part names
+fn parse(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(url)] = args else {
+        return Err(type_error("url.parse() expects one str".to_string()));
+    };
+    let (rest, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    // A scheme is a letter followed by letters, digits, `+`, `-` or `.`
+    let (scheme, rest) = match rest.split_once(':') {
+        Some((scheme, rest))
+            if scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) =>
+        {
+            (scheme.to_ascii_lowercase(), rest)
+        }
+        _ => (String::new(), rest),
+    };
+    let (authority, path) = match rest.strip_prefix("//") {
+        Some(rest) => rest.split_at(rest.find('/').unwrap_or(rest.len())),
+        None => ("", rest),
+    };
+    let (userinfo, hostport) = match authority.rsplit_once('@') {
+        Some((userinfo, hostport)) => (userinfo, hostport),
+        None => ("", authority),
+    };
+    let (username, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+    // `[::1]:8080`: the port follows the closing bracket of an IPv6 address
+    let port_start = match hostport.rfind(':') {
+        Some(colon) if !hostport[colon..].contains(']') => Some(colon),
+        _ => None,
+    };
+    let (host, port) = match port_start {
+        Some(colon) => (&hostport[..colon], &hostport[colon + 1..]),
+        None => (hostport, ""),
+    };
+    let port = match port {
+        "" => Value::None,
+        port => match port.parse::<u16>() {
+            Ok(port) => Value::Int(port as i64),
+            Err(_) => return Err(Exception::new(ExceptionKind::ValueError, vec![format!("invalid port '{}' in URL '{}'", port, url)])),
+        },
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    let values = [Value::Str(scheme), text(username), text(password), Value::Str(host), port, text(path), text(query), text(fragment)];
+    Ok(Value::Dict(PARTS.iter().map(|part| Value::Str(part.to_string())).zip(values).collect()))
+}
+
+/// `build(parts)`: the URL for a dict like the ones `parse` returns; missing
+/// parts are left out.
+#[allow(clippy::mutable_key_type)] // keys are part names
+fn build(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Dict(parts)] = args else {
+        return Err(type_error("url.build() expects a dict of URL parts".to_string()));
+    };
+    let part = |name: &str| match parts.get(&Value::Str(name.to_string())) {
+        None | Some(Value::None) => String::new(),
+        Some(value) => value.to_display_string(),
+    };
+    let mut url = String::new();
+    if !part("scheme").is_empty() {
+        url.push_str(&format!("{}:", part("scheme")));
+    }
+    let host = part("host");
+    let has_host = !host.is_empty();
+    if has_host {
+        url.push_str("//");
+        if !part("username").is_empty() {
+            url.push_str(&part("username"));
+            if !part("password").is_empty() {
+                url.push_str(&format!(":{}", part("password")));
+            }
+            url.push('@');
+        }
+        url.push_str(&if host.contains(':') { format!("[{}]", host) } else { host });
+        if !part("port").is_empty() {
+            url.push_str(&format!(":{}", part("port")));
+        }
+    }
+    let path = part("path");
+    if has_host && !path.is_empty() && !path.starts_with('/') {
+        url.push('/');
+    }
+    url.push_str(&path);
+    for (separator, name) in [('?', "query"), ('#', "fragment")] {
+        if !part(name).is_empty() {
+            url.push(separator);
+            url.push_str(&part(name));
+        }
+    }
+    Ok(Value::Str(url))
+}
+
+/// `encode(text, safe="/")`: `text` percent-encoded as UTF-8, leaving letters,
+/// digits, `-._~` and the characters in `safe` as they are.
+fn encode(args: &[Value]) -> Result<Value, Exception> {
+    let (text, safe) = match args {
+        [Value::Str(text)] => (text, "/"),
+        [Value::Str(text), Value::Str(safe)] => (text, safe.as_str()),
+        _ => return Err(type_error("url.encode() expects a str and an optional str of safe characters".to_string())),
+    };
+    Ok(Value::Str(percent_encode(text, safe)))
+}
+
+fn percent_encode(text: &str, safe: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || "-._~".contains(c) || safe.contains(c) {
+            out.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                out.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    out
+}
+
+/// `%XX` escapes decoded, and `+` as a space in query strings. Malformed
+/// escapes are kept as written and invalid UTF-8 becomes U+FFFD.
+pub(super) fn decode(text: &str, plus_is_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() => {
+                let hex = |digit: u8| (digit as char).to_digit(16).unwrap_or(0) as u8;
+                out.push(hex(bytes[i + 1]) * 16 + hex(bytes[i + 2]));
+                i += 3;
+                continue;
+            }
+            b'+' if plus_is_space => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// `parse_query(query)`: `a=1&b=x+y` as `{"a": "1", "b": "x y"}`, with a key
+/// that appears more than once holding a list of its values. A leading `?`
+/// is ignored.
+#[allow(clippy::mutable_key_type)] // keys are strings
+pub(super) fn parse_query(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(query)] = args else {
+        return Err(type_error("url.parse_query() expects one str".to_string()));
+    };
+    let mut fields: ValueMap<Value, Value> = ValueMap::default();
+    for pair in query.trim_start_matches('?').split(['&', ';']).filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (key, value) = (Value::Str(decode(key, true)), Value::Str(decode(value, true)));
+        match fields.get_mut(&key) {
+            Some(Value::List(values)) => values.push(value),
+            Some(first) => *first = Value::List(vec![first.clone(), value]),
+            None => {
+                fields.insert(key, value);
+            }
+        }
+    }
+    Ok(Value::Dict(fields))
+}
+
+/// `encode_query(fields)`: a dict, or a list of `[key, value]` pairs to keep
+/// their order, as `a=1&b=x+y`. A list value repeats its key; dict keys are
+/// sorted so the same dict always gives the same string.
+fn encode_query(args: &[Value]) -> Result<Value, Exception> {
+    let pairs: Vec<(Value, Value)> = match args {
+        [Value::Dict(fields)] => {
+            let mut pairs: Vec<(Value, Value)> = fields.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+            pairs.sort_by_key(|(key, _)| key.to_display_string());
+            pairs
+        }
+        [Value::List(items)] => items
+            .iter()
+            .map(|item| match item {
+                Value::List(pair) | Value::Tuple(pair) if pair.len() == 2 => Ok((pair[0].clone(), pair[1].clone())),
+                _ => Err(type_error("url.encode_query() list items must be [key, value] pairs".to_string())),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err(type_error("url.encode_query() expects a dict or a list of [key, value] pairs".to_string())),
+    };
+    let field = |key: &Value, value: &Value| {
+        format!("{}={}", percent_encode(&key.to_display_string(), " ").replace(' ', "+"), percent_encode(&value.to_display_string(), " ").replace(' ', "+"))
+    };
+    let mut fields = Vec::new();
+    for (key, value) in &pairs {
+        match value {
+            Value::List(values) => fields.extend(values.iter().map(|value| field(key, value))),
+            value => fields.push(field(key, value)),
+        }
+    }
+    Ok(Value::Str(fields.join("&")))
+}
+
+fn text(part: &str) -> Value {
+    Value::Str(part.to_string())
+}
+
+fn type_error(message: String) -> Exception {
+    Exception::new(ExceptionKind::TypeError, vec![message])
+}
@@ -0,0 +1,70 @@
+// uuid: random identifiers for files, sessions and uploads, as UUIDs or
+// short URL-safe ids
+
+use ::uuid::Uuid;
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+/// Letters, digits, `_` and `-`: 64 characters safe in URLs and file names.
+const ID_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+
+/// Characters in a `short()` id by default; as unlikely to collide as a UUID v4.
+const ID_SIZE: i64 = 21;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("uuid");
+    module.register_fn("v4", |args| {
+        no_args("v4", args)?;
+        Ok(Value::Str(Uuid::new_v4().to_string()))
+    });
+    module.register_fn("v7", |args| {
+        no_args("v7", args)?;
+        Ok(Value::Str(Uuid::now_v7().to_string()))
+    });
+    module.register_fn("short", short);
+    module.register_fn("is_valid", |args| match args {
+        [Value::Str(text)] => Ok(Value::Bool(Uuid::try_parse(text).is_ok())),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec!["uuid.is_valid() expects one str".to_string()])),
+    });
+    module
+}
+
+/// `short(size=21, alphabet=...)`: a random id of `size` characters drawn
+/// evenly from `alphabet`, URL-safe letters, digits, `_` and `-` by default.
+fn short(args: &[Value]) -> Result<Value, Exception> {
+    let (size, alphabet) = match args {
+        [] => (ID_SIZE, ID_ALPHABET),
+        [Value::Int(size)] => (*size, ID_ALPHABET),
+        [Value::Int(size), Value::Str(alphabet)] => (*size, alphabet.as_str()),
+        _ => return Err(Exception::new(ExceptionKind::TypeError, vec!["uuid.short() expects a size and an optional alphabet str".to_string()])),
+    };
+    let alphabet: Vec<char> = alphabet.chars().collect();
+    if !(1..=256).contains(&alphabet.len()) {
+        return Err(Exception::new(ExceptionKind::ValueError, vec!["uuid.short() alphabet must have 1 to 256 characters".to_string()]));
+    }
+    if !(1..=1024).contains(&size) {
+        return Err(Exception::new(ExceptionKind::ValueError, vec![format!("uuid.short() size must be from 1 to 1024, not {}", size)]));
+    }
+    // Bytes past the largest multiple of the alphabet's length are skipped, so
+    // every character is equally likely
+    let limit = 256 - 256 % alphabet.len();
+    let mut id = String::new();
+    let mut bytes = [0u8; 64];
+    while id.chars().count() < size as usize {
+        getrandom::fill(&mut bytes).map_err(|err| Exception::new(ExceptionKind::OSError, vec![format!("no randomness available: {}", err)]))?;
+        for &byte in bytes.iter().filter(|&&byte| (byte as usize) < limit).take(size as usize - id.chars().count()) {
+            id.push(alphabet[byte as usize % alphabet.len()]);
+        }
+    }
+    Ok(Value::Str(id))
+}
+
+fn no_args(func: &str, args: &[Value]) -> Result<(), Exception> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(Exception::new(ExceptionKind::TypeError, vec![format!("uuid.{}() takes no arguments ({} given)", func, args.len())]))
+    }
+}
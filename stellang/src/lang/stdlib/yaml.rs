@@ -0,0 +1,47 @@
+// yaml: read YAML configuration files
+
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::lang::exceptions::{Exception, ExceptionKind};
+use crate::lang::hashing::ValueMap;
+use crate::lang::interpreter::Value;
+use crate::lang::native::NativeModule;
+
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new("yaml");
+    module.register_fn("loads", loads);
+    module
+}
+
+/// `loads(text)`: the first document in `text`, or None for an empty one.
+pub(super) fn loads(args: &[Value]) -> Result<Value, Exception> {
+    let [Value::Str(text)] = args else {
+        return Err(Exception::new(ExceptionKind::TypeError, vec!["yaml.loads() expects one str".to_string()]));
+    };
+    let documents = YamlLoader::load_from_str(text)
+        .map_err(|err| Exception::new(ExceptionKind::ValueError, vec![format!("invalid YAML: {}", err)]))?;
+    documents.into_iter().next().map_or(Ok(Value::None), from_yaml)
+}
+
+// Mapping keys are scalars, which hash by content
+#[allow(clippy::mutable_key_type)]
+fn from_yaml(value: Yaml) -> Result<Value, Exception> {
+    Ok(match value {
+        Yaml::Real(ref text) => Value::Float(value.as_f64().ok_or_else(|| Exception::new(ExceptionKind::ValueError, vec![format!("invalid YAML float '{}'", text)]))?),
+        Yaml::Integer(n) => Value::Int(n),
+        Yaml::String(s) => Value::Str(s),
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Array(items) => Value::List(items.into_iter().map(from_yaml).collect::<Result<_, _>>()?),
+        Yaml::Hash(hash) => {
+            let mut map = ValueMap::default();
+            for (key, item) in hash {
+                let key = from_yaml(key)?;
+                key.check_hashable()?;
+                map.insert(key, from_yaml(item)?);
+            }
+            Value::Dict(map)
+        }
+        Yaml::Null => Value::None,
+        Yaml::Alias(_) | Yaml::BadValue => return Err(Exception::new(ExceptionKind::ValueError, vec!["YAML aliases are not supported".to_string()])),
+    })
+}
@@ -0,0 +1,56 @@
+// "Did you mean ...?" hints for NameError and AttributeError, picked by edit
+// distance from the names that do exist
+
+/// Functions the interpreter itself answers when called by name; the rest of
+/// the builtins are `builtins::NAMES`.
+pub const INTERPRETER_BUILTINS: &[&str] = &[
+    "print", "input", "open", "spawn", "atexit", "quit", "exit", "chan", "send", "recv", "join", "copy", "deepcopy",
+    "str", "list", "tuple", "set", "frozenset", "dict", "cfg", "assert_eq", "fixture_path", "dir", "getattr",
+    "setattr", "hasattr", "vars", "eval", "exec",
+];
+
+/// `message` with ` (did you mean 'x'?)` appended when one of `candidates` is
+/// close enough to `name` to be what was meant.
+pub fn did_you_mean<'a>(message: String, name: &str, candidates: impl IntoIterator<Item = &'a str>) -> String {
+    match closest(name, candidates) {
+        Some(suggestion) => format!("{} (did you mean '{}'?)", message, suggestion),
+        None => message,
+    }
+}
+
+/// The candidate nearest to `name`, at most a third of its length in edits
+/// away (but at least one). Ties go to the earliest, so list the likeliest
+/// candidates first.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edits to turn `a` into `b`: inserting, deleting or replacing a character,
+/// or swapping two neighbouring ones (`conut` is one edit from `count`).
+pub fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // rows[i][j]: edits between the first i characters of a and the first j of b
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = replace.min(rows[i - 1][j] + 1).min(rows[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
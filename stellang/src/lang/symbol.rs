@@ -0,0 +1,202 @@
+// Interned identifiers. The lexer turns every name in the source into a
+// `Symbol`, a small id into a table shared by the whole process, so a name
+// that appears many times is stored once and tokens and AST nodes carry a
+// copyable u32 instead of a String of their own. The table only grows: the
+// text of a symbol lives as long as the process, which is what lets
+// `as_str` hand out a `&'static str`. Symbols serialize and hash as their
+// text, so the AST JSON format, snapshots and cached builds are the same as
+// when names were Strings, whatever ids a process happens to assign.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Mutex, OnceLock};
+
+/// An interned name; two symbols are equal exactly when their text is.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Symbol(u32);
+
+// The symbol of each name seen so far
+fn ids() -> &'static Mutex<HashMap<&'static str, Symbol>> {
+    static IDS: OnceLock<Mutex<HashMap<&'static str, Symbol>>> = OnceLock::new();
+    IDS.get_or_init(Default::default)
+}
+
+// The text of each symbol, by id, in chunks of 64, 128, 256, ... slots that
+// are never moved, so that reading a name never waits on the lock writers take
+const CHUNKS: usize = 26;
+static NAMES: [OnceLock<Box<[OnceLock<&'static str>]>>; CHUNKS] = [const { OnceLock::new() }; CHUNKS];
+
+fn slot(id: u32) -> &'static OnceLock<&'static str> {
+    let id = id as usize;
+    let chunk = (id / 64 + 1).ilog2() as usize;
+    let slots = NAMES[chunk].get_or_init(|| (0..64 << chunk).map(|_| OnceLock::new()).collect());
+    &slots[id - 64 * ((1 << chunk) - 1)]
+}
+
+impl Symbol {
+    /// The symbol for `name`, adding it to the table the first time it is seen.
+    pub fn intern(name: &str) -> Symbol {
+        let mut ids = ids().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&symbol) = ids.get(name) {
+            return symbol;
+        }
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(ids.len() as u32);
+        let _ = slot(symbol.0).set(name);
+        ids.insert(name, symbol);
+        symbol
+    }
+
+    /// The text of this symbol.
+    pub fn as_str(self) -> &'static str {
+        slot(self.0).get().copied().unwrap_or_default()
+    }
+}
+
+// The empty name
+impl Default for Symbol {
+    fn default() -> Self {
+        Symbol::intern("")
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// By text rather than id, to agree with `Borrow<str>` and to give the same
+// hash in every process
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.as_str().to_string()
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl serde::Serialize for Symbol {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Symbol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SymbolVisitor;
+
+        impl serde::de::Visitor<'_> for SymbolVisitor {
+            type Value = Symbol;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Symbol, E> {
+                Ok(Symbol::intern(name))
+            }
+        }
+
+        deserializer.deserialize_str(SymbolVisitor)
+    }
+}
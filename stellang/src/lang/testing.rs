@@ -0,0 +1,159 @@
+// Testing builtins: assert_eq and the structural diff it reports on failure, and
+// the fixture directory and scratch working directory `stel test` gives each test
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::interpreter::Value;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Differences listed before the rest are summarised as a count.
+const MAX_DIFF_LINES: usize = 20;
+
+// `tests/fixtures/<name>` of the test being run, as an absolute path
+static FIXTURE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Set the directory `fixture_path()` resolves against; None outside a test run.
+pub fn set_fixture_dir(dir: Option<PathBuf>) {
+    *FIXTURE_DIR.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = dir;
+}
+
+/// `fixture_path(name?)`: the current test's fixture directory, or the file
+/// `name` inside it. Either must exist, so a typo fails where it was made.
+pub fn fixture_path(args: &[Value]) -> Result<Value, Exception> {
+    let dir = FIXTURE_DIR.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    let Some(dir) = dir else {
+        return Err(Exception::new(ExceptionKind::RuntimeError, vec!["fixture_path() is only available in tests run by stel test".to_string()]));
+    };
+    let path = match args {
+        [] => dir,
+        [Value::Str(name)] => dir.join(name),
+        [other] => return Err(Exception::new(ExceptionKind::TypeError, vec![format!("fixture_path() expects a str, not '{}'", other.type_name())])),
+        _ => return Err(Exception::new(ExceptionKind::TypeError, vec![format!("fixture_path() takes at most 1 argument ({} given)", args.len())])),
+    };
+    if !path.exists() {
+        return Err(Exception::new(ExceptionKind::FileNotFoundError, vec![format!("no such fixture: '{}'", path.display())]));
+    }
+    Ok(Value::Str(path.to_string_lossy().into_owned()))
+}
+
+/// A fresh, empty directory made the working directory while one test runs.
+/// Dropping it restores the previous working directory and deletes it with
+/// everything the test wrote there.
+pub struct ScratchDir {
+    path: PathBuf,
+    previous: PathBuf,
+}
+
+impl ScratchDir {
+    /// Create a scratch directory for the test `name` and change into it.
+    /// Characters other than letters, digits and `-` become `_` in its path.
+    pub fn enter(name: &str) -> std::io::Result<Self> {
+        let previous = std::env::current_dir()?;
+        let name: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect();
+        let path = std::env::temp_dir().join(format!("stel-test-{}-{}", name, std::process::id()));
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        std::env::set_current_dir(&path)?;
+        Ok(ScratchDir { path, previous })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.previous);
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// `assert_eq(left, right, message?)`: raise an AssertionError unless the values
+/// are equal. Lists, tuples and dicts are compared element by element so the
+/// error names the indices and keys that differ instead of printing both values.
+pub fn assert_eq(args: &[Value]) -> Result<Value, Exception> {
+    let (left, right, message) = match args {
+        [left, right] => (left, right, None),
+        [left, right, message] => (left, right, Some(message.to_display_string())),
+        _ => return Err(Exception::new(ExceptionKind::TypeError, vec![format!("assert_eq() takes 2 or 3 arguments ({} given)", args.len())])),
+    };
+    if left == right {
+        return Ok(Value::None);
+    }
+    let mut report = match message {
+        Some(message) => format!("assert_eq failed: {}", message),
+        None => "assert_eq failed".to_string(),
+    };
+    let lines = diff(left, right);
+    if is_container(left) && is_container(right) {
+        report.push_str(" (- left only, + right only)");
+        for line in lines.iter().take(MAX_DIFF_LINES) {
+            report.push_str("\n  ");
+            report.push_str(line);
+        }
+        if lines.len() > MAX_DIFF_LINES {
+            report.push_str(&format!("\n  ... and {} more differences", lines.len() - MAX_DIFF_LINES));
+        }
+    } else {
+        report.push_str(&format!(": {} != {}", repr(left), repr(right)));
+    }
+    Err(Exception::new(ExceptionKind::AssertionError, vec![report]))
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(value, Value::List(_) | Value::Tuple(_) | Value::Dict(_))
+}
+
+/// Strings are quoted so `1` and `"1"` can be told apart in a diff.
+fn repr(value: &Value) -> String {
+    match value {
+        Value::Str(s) => format!("{:?}", s),
+        other => other.to_display_string(),
+    }
+}
+
+/// One line per difference between `left` and `right`, each prefixed with the
+/// path to it: `  [2]: 3 != 4`, `- ["key"]: 1` (left only), `+ [5]: 9` (right only).
+pub fn diff(left: &Value, right: &Value) -> Vec<String> {
+    let mut lines = Vec::new();
+    diff_into(left, right, String::new(), &mut lines);
+    lines
+}
+
+fn diff_into(left: &Value, right: &Value, path: String, lines: &mut Vec<String>) {
+    match (left, right) {
+        (Value::List(l), Value::List(r)) | (Value::Tuple(l), Value::Tuple(r)) => {
+            for (i, (a, b)) in l.iter().zip(r).enumerate() {
+                diff_into(a, b, format!("{}[{}]", path, i), lines);
+            }
+            for (i, extra) in l.iter().enumerate().skip(r.len()) {
+                lines.push(format!("- {}[{}]: {}", path, i, repr(extra)));
+            }
+            for (i, extra) in r.iter().enumerate().skip(l.len()) {
+                lines.push(format!("+ {}[{}]: {}", path, i, repr(extra)));
+            }
+        }
+        (Value::Dict(l), Value::Dict(r)) => {
+            // Keys are visited in display order so the report is stable between runs
+            let mut keys: Vec<&Value> = l.keys().chain(r.keys().filter(|k| !l.contains_key(*k))).collect();
+            keys.sort_by_cached_key(|k| repr(k));
+            for key in keys {
+                let key_path = format!("{}[{}]", path, repr(key));
+                match (l.get(key), r.get(key)) {
+                    (Some(a), Some(b)) => diff_into(a, b, key_path, lines),
+                    (Some(a), None) => lines.push(format!("- {}: {}", key_path, repr(a))),
+                    (None, Some(b)) => lines.push(format!("+ {}: {}", key_path, repr(b))),
+                    (None, None) => {}
+                }
+            }
+        }
+        (a, b) if a != b => {
+            let path = if path.is_empty() { "value".to_string() } else { path };
+            lines.push(format!("  {}: {} != {}", path, repr(a), repr(b)));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,145 @@
+// Threading and message passing support for StelLang
+
+use super::exceptions::{Exception, ExceptionKind};
+use super::interpreter::{Function, Interpreter, Value};
+use super::cache::Caches;
+use super::output::Output;
+use super::sandbox::Capabilities;
+use super::stats::StatsHandle;
+use super::stdlib::config::EnvVars;
+use super::stdlib::sys::{self, RecursionLimit};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+/// Shared handle to a channel. Every clone of a channel value refers to the
+/// same queue, which is how spawned interpreters talk to each other.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelHandle(Arc<(Mutex<ChannelState>, Condvar)>);
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    queue: VecDeque<Value>,
+    closed: bool,
+}
+
+impl ChannelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&self, value: Value) -> Result<(), Exception> {
+        let (lock, cvar) = &*self.0;
+        let mut state = lock.lock().map_err(|_| poisoned("channel"))?;
+        if state.closed {
+            return Err(Exception::new(ExceptionKind::ValueError, vec!["send on closed channel".to_string()]));
+        }
+        state.queue.push_back(value);
+        cvar.notify_one();
+        Ok(())
+    }
+
+    /// Block until a value is available. Returns None once the channel is
+    /// closed and drained.
+    pub fn recv(&self) -> Result<Value, Exception> {
+        let (lock, cvar) = &*self.0;
+        let mut state = lock.lock().map_err(|_| poisoned("channel"))?;
+        loop {
+            if let Some(value) = state.queue.pop_front() {
+                return Ok(value);
+            }
+            if state.closed {
+                return Ok(Value::None);
+            }
+            state = cvar.wait(state).map_err(|_| poisoned("channel"))?;
+        }
+    }
+
+    pub fn close(&self) {
+        let (lock, cvar) = &*self.0;
+        if let Ok(mut state) = lock.lock() {
+            state.closed = true;
+            cvar.notify_all();
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.0.lock().map(|s| s.closed).unwrap_or(true)
+    }
+
+    pub fn same_handle(&self, other: &ChannelHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+type ThreadResult = Result<Value, Exception>;
+
+/// Handle to a spawned StelLang function running on its own OS thread.
+#[derive(Debug, Clone, Default)]
+pub struct ThreadHandle(Arc<Mutex<Option<JoinHandle<ThreadResult>>>>);
+
+impl ThreadHandle {
+    /// Wait for the thread to finish and return the function's result.
+    /// Exceptions raised in the thread are re-raised in the joining thread.
+    pub fn join(&self) -> ThreadResult {
+        let handle = self.0.lock().map_err(|_| poisoned("thread"))?.take();
+        match handle {
+            Some(h) => h.join().unwrap_or_else(|_| Err(Exception::new(ExceptionKind::RuntimeError, vec!["spawned thread panicked".to_string()]))),
+            None => Err(Exception::new(ExceptionKind::RuntimeError, vec!["thread has already been joined".to_string()])),
+        }
+    }
+
+    pub fn is_joined(&self) -> bool {
+        self.0.lock().map(|h| h.is_none()).unwrap_or(true)
+    }
+
+    pub fn same_handle(&self, other: &ThreadHandle) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Run a user-defined function on a new OS thread. The thread gets its own
+/// interpreter seeded with a snapshot of the caller's globals, functions and
+/// capabilities, so the only shared state is whatever channels were passed in
+/// the output sink, any stats counters, the results of `@cache` functions and
+/// the variables `config.load_env()` loaded.
+/// `atexit()` handlers registered on the thread run when it finishes.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn(
+    env: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+    capabilities: Capabilities,
+    stdout: Output,
+    stats: Option<StatsHandle>,
+    caches: Caches,
+    recursion_limit: usize,
+    env_vars: EnvVars,
+    func_name: &str,
+    args: Vec<Value>,
+) -> Result<ThreadHandle, Exception> {
+    let (params, body) = functions.get(func_name).cloned().ok_or_else(|| {
+        Exception::new(ExceptionKind::NameError, vec![format!("name '{}' is not defined", func_name)])
+    })?;
+    if args.len() != params.len() {
+        return Err(Exception::new(ExceptionKind::TypeError, vec![
+            format!("{}() takes {} arguments but {} were given", func_name, params.len(), args.len())
+        ]));
+    }
+    let handle = std::thread::Builder::new()
+        .name(format!("stellang-{}", func_name))
+        .stack_size(sys::STACK_SIZE)
+        .spawn(move || {
+            let mut env = env;
+            for (param, arg) in params.into_iter().zip(args) {
+                env.insert(param.to_string(), arg);
+            }
+            let mut interpreter = Interpreter { env, functions, capabilities, stdout, exit_handlers: Default::default(), stats, caches, recursion_limit: RecursionLimit::new(recursion_limit), env_vars };
+            interpreter.eval(&body)
+        })
+        .map_err(|e| Exception::new(ExceptionKind::RuntimeError, vec![format!("can't start new thread: {}", e)]))?;
+    Ok(ThreadHandle(Arc::new(Mutex::new(Some(handle)))))
+}
+
+fn poisoned(what: &str) -> Exception {
+    Exception::new(ExceptionKind::RuntimeError, vec![format!("{} lock poisoned", what)])
+}
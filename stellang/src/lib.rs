@@ -1,7 +1,41 @@
 pub mod lang {
     pub mod lexer;
+    pub mod symbol;
     pub mod parser;
     pub mod ast;
+    pub mod ast_json;
     pub mod interpreter;
     pub mod exceptions;
+    pub mod features;
+    pub mod hashing;
+    pub mod fileio;
+    pub mod threads;
+    pub mod process;
+    pub mod modules;
+    pub mod sandbox;
+    pub mod snapshot;
+    pub mod native;
+    pub mod methods;
+    pub mod containers;
+    pub mod builtins;
+    pub mod stdlib;
+    pub mod report;
+    pub mod output;
+    pub mod console;
+    pub mod testing;
+    pub mod interrupt;
+    pub mod stats;
+    pub mod cache;
+    pub mod format;
+    pub mod check;
+    pub mod suggest;
+    pub mod optimize;
 }
+
+pub mod ffi;
+pub mod cli;
+pub mod ignore;
+pub mod crash;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,67 +1,530 @@
 use std::io::{self, Write};
 use std::fs;
-use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::Interpreter};
-use stellang::lang::lexer::Token;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+use stellang::lang::{parser::Parser, interpreter::{Interpreter, Value}, ast_json, exceptions::{Exception, ExceptionKind}, interrupt, modules, snapshot, stdlib};
+use stellang::lang::report::{self, ErrorFormat};
+use stellang::lang::check::{self, Diagnostic, Severity};
+use stellang::lang::features::{self, DeprecationPolicy};
+use stellang::lang::hashing;
+use stellang::cli::CliError;
+use stellang::crash;
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    
-    if args.len() > 1 {
-        // File mode
-        let filename = &args[1];
-        let content = std::fs::read_to_string(filename).expect("Failed to read file");
-        
-        let mut lexer = Lexer::new(&content);
-        let mut tokens = Vec::new();
-        
-        loop {
-            let tok = lexer.next_token();
-            if tok == Ok(Token::EOF) { break; }
-            tokens.push(tok.expect("Lexer error"));
+// Prefix for messages about the command line itself, as opposed to script errors
+const PROGRAM: &str = "stellang";
+
+// Where `--stats <file>` writes the execution report when the process exits
+static STATS_PATH: OnceLock<String> = OnceLock::new();
+
+// Where `--flamegraph <file>` writes the time spent per call stack
+static FLAMEGRAPH_PATH: OnceLock<String> = OnceLock::new();
+
+/// What the command line asked for. Anything that is not a flag names the script to
+/// run, and every argument after the script is passed to it in `sys.argv`.
+struct Options {
+    error_format: ErrorFormat,
+    /// `--emit ast-json`: print the parsed program as JSON instead of running it
+    emit_ast: bool,
+    /// `--from ast-json`: the script is a program in that JSON rather than source
+    from_ast: bool,
+    script: Option<Script>,
+    script_args: Vec<String>,
+}
+
+/// Where the program to run comes from; without one the REPL starts.
+enum Script {
+    /// A path on the command line
+    File(String),
+    /// `-`: the whole of stdin, as in `cat prog.stel | stellang -`
+    Stdin,
+    /// `-e <code>`
+    Inline(String),
+}
+
+impl Script {
+    /// `sys.argv[0]`, as Python reports it
+    fn name(&self) -> &str {
+        match self {
+            Script::File(path) => path,
+            Script::Stdin => "-",
+            Script::Inline(_) => "-e",
+        }
+    }
+
+    /// The file the script is in, for tracebacks
+    fn path(&self) -> Option<&str> {
+        match self {
+            Script::File(path) => Some(path),
+            Script::Stdin | Script::Inline(_) => None,
         }
-        let mut parser = Parser::new(tokens);
-        if let Ok(Some(ast)) = parser.parse() {
-            let mut interpreter = Interpreter::new();
-            match interpreter.eval(&ast) {
-                Ok(result) => println!("{}", result.to_display_string()),
-                Err(e) => eprintln!("Error: {:?}", e),
+    }
+
+    fn read(self) -> Result<String, CliError> {
+        match self {
+            Script::File(path) => fs::read_to_string(&path).map_err(|source| CliError::Read { path, source }),
+            Script::Stdin => io::read_to_string(io::stdin()).map_err(CliError::Stdin),
+            Script::Inline(code) => Ok(code),
+        }
+    }
+}
+
+/// Parse the flags and script of a command line. `stellang run` passes its
+/// project's entry point as `project`; the first argument that is not a flag
+/// then starts the script's own arguments instead of naming the script.
+fn parse_args(mut args: impl Iterator<Item = String>, mut project: Option<String>) -> Result<Options, CliError> {
+    let mut options = Options { error_format: ErrorFormat::Human, emit_ast: false, from_ast: false, script: None, script_args: Vec::new() };
+    while options.script.is_none() {
+        let Some(arg) = args.next() else { break };
+        let format = if let Some(value) = arg.strip_prefix("--error-format=") {
+            value.to_string()
+        } else if arg == "--error-format" {
+            args.next().unwrap_or_default()
+        } else if let Some(path) = arg.strip_prefix("--stats=") {
+            let _ = STATS_PATH.set(path.to_string());
+            continue;
+        } else if arg == "--stats" {
+            let path = args.next().ok_or_else(|| CliError::Usage("--stats expects a file to write the report to".to_string()))?;
+            let _ = STATS_PATH.set(path);
+            continue;
+        } else if arg == "--enable-feature" || arg.starts_with("--enable-feature=") {
+            let name = match arg.split_once('=') {
+                Some((_, name)) => name.to_string(),
+                None => args.next().ok_or_else(|| CliError::Usage("--enable-feature expects a feature name".to_string()))?,
+            };
+            features::enable(&name).map_err(CliError::Usage)?;
+            continue;
+        } else if arg == "--deprecations" || arg.starts_with("--deprecations=") {
+            let policy = match arg.split_once('=') {
+                Some((_, policy)) => policy.to_string(),
+                None => args.next().unwrap_or_default(),
+            };
+            let policy = DeprecationPolicy::parse(&policy)
+                .ok_or_else(|| CliError::Usage(format!("unknown --deprecations '{}' (expected 'warn', 'error' or 'ignore')", policy)))?;
+            features::set_deprecation_policy(policy);
+            continue;
+        } else if arg == "--emit" || arg == "--from" || arg.starts_with("--emit=") || arg.starts_with("--from=") {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), args.next()),
+            };
+            match value.as_deref() {
+                Some("ast-json") if flag == "--emit" => options.emit_ast = true,
+                Some("ast-json") => options.from_ast = true,
+                Some(other) => return Err(CliError::Usage(format!("unknown {} '{}' (expected 'ast-json')", flag, other))),
+                None => return Err(CliError::Usage(format!("{} expects a format (ast-json)", flag))),
             }
+            continue;
+        } else if let Some(path) = arg.strip_prefix("--flamegraph=") {
+            let _ = FLAMEGRAPH_PATH.set(path.to_string());
+            continue;
+        } else if arg == "--flamegraph" {
+            let path = args.next().ok_or_else(|| CliError::Usage("--flamegraph expects a file to write the stacks to".to_string()))?;
+            let _ = FLAMEGRAPH_PATH.set(path);
+            continue;
+        } else if arg == "--deterministic" || arg.starts_with("--deterministic=") {
+            let seed = match arg.split_once('=') {
+                Some((_, seed)) => seed.parse().map_err(|_| CliError::Usage(format!("--deterministic expects a whole number seed, not '{}'", seed)))?,
+                None => hashing::DETERMINISTIC_SEED,
+            };
+            hashing::set_seed(seed);
+            continue;
+        } else if arg == "-e" {
+            let code = args.next().ok_or_else(|| CliError::Usage("-e expects code to run".to_string()))?;
+            options.script = Some(Script::Inline(code));
+            continue;
+        } else if arg == "-" {
+            options.script = Some(Script::Stdin);
+            continue;
         } else {
-            eprintln!("Failed to parse file");
+            match project.take() {
+                Some(entry) => {
+                    options.script = Some(Script::File(entry));
+                    options.script_args.push(arg);
+                }
+                None => options.script = Some(Script::File(arg)),
+            }
+            continue;
+        };
+        options.error_format = ErrorFormat::parse(&format)
+            .ok_or_else(|| CliError::Usage(format!("unknown --error-format '{}' (expected 'human' or 'json')", format)))?;
+    }
+    if let Some(entry) = project {
+        if options.script.is_some() {
+            return Err(CliError::Usage("run takes the project's src/main.stel; '-' and -e can't be used with it".to_string()));
         }
+        options.script = Some(Script::File(entry));
+    }
+    if (options.emit_ast || options.from_ast) && options.script.is_none() {
+        return Err(CliError::Usage("--emit and --from need a script, '-' or -e".to_string()));
+    }
+    options.script_args.extend(args);
+    Ok(options)
+}
+
+/// `stellang run`: the entry point of the project whose stel.toml is in the
+/// current directory or the nearest parent that has one.
+fn project_entry() -> Result<String, CliError> {
+    let root = find_project(Path::new(".")).ok_or_else(|| CliError::Usage("run expects a stel.toml in this directory or a parent".to_string()))?;
+    let entry = root.join("src").join(format!("main.{}", modules::EXTENSION));
+    if !entry.is_file() {
+        return Err(CliError::Usage(format!("run expects the project's entry point at {}", entry.display())));
+    }
+    Ok(entry.display().to_string())
+}
+
+fn find_project(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors().find(|dir| dir.join("stel.toml").is_file()).map(Path::to_path_buf)
+}
+
+/// Where `import` finds source modules: next to the script (or in the current
+/// directory for stdin, -e and the REPL), then among the packages `stel`
+/// installed for the project it is part of.
+fn module_search_path(script: Option<&Script>) -> Vec<PathBuf> {
+    let dir = match script {
+        Some(Script::File(path)) => Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")),
+        _ => Path::new("."),
+    };
+    let mut dirs = vec![dir.to_path_buf()];
+    dirs.extend(find_project(dir).map(|root| root.join("dependencies")));
+    dirs
+}
+
+/// `stellang check [--output human|json] [--max-warnings N] FILE...`
+struct CheckOptions {
+    json: bool,
+    max_warnings: Option<usize>,
+    files: Vec<String>,
+}
+
+fn parse_check_args(mut args: impl Iterator<Item = String>) -> Result<CheckOptions, CliError> {
+    let mut options = CheckOptions { json: false, max_warnings: None, files: Vec::new() };
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg.clone(), None),
+        };
+        let mut value = || inline.clone().or_else(|| args.next()).ok_or_else(|| CliError::Usage(format!("{} expects a value", flag)));
+        match flag.as_str() {
+            "--output" => {
+                options.json = match value()?.as_str() {
+                    "json" => true,
+                    "human" => false,
+                    other => return Err(CliError::Usage(format!("unknown --output '{}' (expected 'human' or 'json')", other))),
+                }
+            }
+            "--max-warnings" => {
+                let limit = value()?;
+                let limit = limit.parse().map_err(|_| CliError::Usage(format!("--max-warnings expects a number, not '{}'", limit)))?;
+                options.max_warnings = Some(limit);
+            }
+            _ => options.files.push(arg),
+        }
+    }
+    if options.files.is_empty() {
+        return Err(CliError::Usage("check expects one or more files".to_string()));
+    }
+    Ok(options)
+}
+
+// Print the diagnostics of every file and return the exit status: 1 when there
+// are errors or more warnings than --max-warnings allows, 0 otherwise
+fn run_check(options: CheckOptions) -> Result<i32, CliError> {
+    let mut diagnostics = Vec::new();
+    for path in &options.files {
+        let source = fs::read_to_string(path).map_err(|source| CliError::Read { path: path.clone(), source })?;
+        diagnostics.extend(check::check_source(path, &source));
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.len() - errors;
+    let output = if options.json {
+        serde_json::to_string_pretty(&diagnostics.iter().map(Diagnostic::to_json).collect::<Vec<_>>()).unwrap_or_default()
+    } else {
+        let lines = diagnostics.iter().map(|d| d.to_string() + "\n").collect::<String>();
+        format!("{}{} file(s) checked: {} error(s), {} warning(s)", lines, options.files.len(), errors, warnings)
+    };
+    writeln!(std::io::stdout(), "{}", output).map_err(CliError::Stdout)?;
+    if let Some(limit) = options.max_warnings.filter(|&limit| warnings > limit) {
+        eprintln!("{}: {} warning(s) exceed --max-warnings {}", PROGRAM, warnings, limit);
+        return Ok(1);
+    }
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+fn main() {
+    let root = find_project(Path::new(".")).unwrap_or_else(|| PathBuf::from("."));
+    crash::install(PROGRAM, root.join(crash::CRASH_DIR));
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("check") {
+        args.next();
+        let options = parse_check_args(args).unwrap_or_else(|e| e.exit(PROGRAM));
+        std::process::exit(run_check(options).unwrap_or_else(|e| e.exit(PROGRAM)));
+    }
+    let project = match args.peek().map(String::as_str) {
+        Some("run") => {
+            args.next();
+            Some(project_entry().unwrap_or_else(|e| e.exit(PROGRAM)))
+        }
+        _ => None,
+    };
+    let options = parse_args(args, project).unwrap_or_else(|e| e.exit(PROGRAM));
+    let runner = std::thread::Builder::new().name("main".to_string()).stack_size(stdlib::sys::STACK_SIZE).spawn(move || run(options));
+    match runner.map(|handle| handle.join()) {
+        Ok(Ok(())) => {}
+        // The panic message and where the crash report went have already been printed
+        Ok(Err(_)) => std::process::exit(101),
+        Err(e) => CliError::Other(format!("can't start the interpreter thread: {}", e)).exit(PROGRAM),
+    }
+}
+
+fn run(Options { error_format, emit_ast, from_ast, script, script_args }: Options) {
+    install_interrupt_handler();
+
+    let argv0 = script.as_ref().map_or("", Script::name).to_string();
+    stdlib::sys::set_argv(std::iter::once(argv0).chain(script_args).collect());
+    modules::set_search_path(module_search_path(script.as_ref()));
+
+    if let Some(script) = script {
+        // Script mode: a file, stdin or -e
+        let name = script.name().to_string();
+        let path = script.path().map(str::to_string);
+        let content = script.read().unwrap_or_else(|e| e.exit(PROGRAM));
+        crash::set_script(&name, &content);
+        let mut interpreter = new_interpreter();
+        let program = match &path {
+            _ if from_ast => ast_json::from_json(&content),
+            Some(path) => Parser::parse_file(&content, path),
+            None => Parser::parse_source_with_lines(&content),
+        };
+        if emit_ast {
+            match &program {
+                Ok(program) => println!("{}", ast_json::to_json(program.as_ref())),
+                Err(e) => {
+                    report::report(e, error_format);
+                    std::process::exit(1);
+                }
+            }
+            std::process::exit(0);
+        }
+        let result = program.and_then(|program| program.map_or(Ok(Value::None), |expr| interpreter.eval(&expr)));
+        match result {
+            Ok(result) => print_result(&mut interpreter, &result, error_format),
+            Err(e) => {
+                let code = if e.kind == ExceptionKind::KeyboardInterrupt { 130 } else { 1 };
+                exit_or_report(&mut interpreter, e, error_format);
+                exit(&mut interpreter, code, error_format);
+            }
+        }
+        exit(&mut interpreter, 0, error_format);
     } else {
         // REPL mode
-        println!("StelLang REPL (Press Ctrl+C to exit)");
+        println!("StelLang REPL (type :help for commands, :quit or Ctrl-D to exit)");
+        let mut interpreter = new_interpreter();
+        let mut results = 0;
         
         loop {
             print!(">>> ");
-            std::io::stdout().flush().unwrap();
+            if let Err(e) = std::io::stdout().flush() {
+                fail(&mut interpreter, CliError::Stdout(e), error_format);
+            }
             
             let mut input = String::new();
-            std::io::stdin().read_line(&mut input).expect("Failed to read input");
+            match std::io::stdin().read_line(&mut input) {
+                // EOF (Ctrl-D or end of piped input)
+                Ok(0) => break,
+                Ok(_) => {}
+                // A line that is not UTF-8 is thrown away rather than ending the session
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    eprintln!("{}: {}", PROGRAM, CliError::Stdin(e));
+                    continue;
+                }
+                Err(e) => fail(&mut interpreter, CliError::Stdin(e), error_format),
+            }
+            if interrupt::take() {
+                // Ctrl-C while typing throws the line away instead of running it
+                println!("KeyboardInterrupt");
+                continue;
+            }
             
             if input.trim().is_empty() {
                 continue;
             }
+            if let Some(command) = input.trim().strip_prefix(':') {
+                run_repl_command(&mut interpreter, command, error_format);
+                continue;
+            }
             
-            let mut lexer = Lexer::new(&input);
-            let mut tokens = Vec::new();
-            
-            loop {
-                let tok = lexer.next_token();
-                if tok == Ok(Token::EOF) { break; }
-                tokens.push(tok.expect("Lexer error"));
-            }
-            let mut parser = Parser::new(tokens);
-            if let Ok(Some(expr)) = parser.parse() {
-                let mut interpreter = Interpreter::new();
-                match interpreter.eval(&expr) {
-                    Ok(result) => println!("{}", result.to_display_string()),
-                    Err(e) => eprintln!("Error: {:?}", e),
+            crash::set_script("<stdin>", &input);
+            match eval_source(&mut interpreter, &input) {
+                Ok(result) => {
+                    print_result(&mut interpreter, &result, error_format);
+                    remember_result(&mut interpreter, &mut results, result);
                 }
-            } else {
-                eprintln!("Failed to parse input");
+                Err(e) => exit_or_report(&mut interpreter, e, error_format),
+            }
+        }
+        exit(&mut interpreter, 0, error_format);
+    }
+}
+
+fn new_interpreter() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if STATS_PATH.get().is_some() || FLAMEGRAPH_PATH.get().is_some() {
+        interpreter.enable_stats();
+    }
+    interpreter
+}
+
+// Ctrl-C raises KeyboardInterrupt at the interpreter's next checkpoint. A second
+// Ctrl-C before that one is handled (say, while blocked in input() or join())
+// ends the process outright with the conventional 128 + SIGINT status.
+fn install_interrupt_handler() {
+    #[cfg(unix)]
+    {
+        use signal_hook::{consts::SIGINT, flag};
+        let pending = interrupt::flag();
+        let installed = flag::register_conditional_shutdown(SIGINT, 130, pending.clone())
+            .and_then(|_| flag::register(SIGINT, pending));
+        if let Err(e) = installed {
+            eprintln!("warning: could not install Ctrl-C handler: {}", e);
+        }
+    }
+}
+
+// quit()/exit() surface as SystemExit; anything else is reported and the session goes on
+fn exit_or_report(interpreter: &mut Interpreter, e: Exception, format: ErrorFormat) {
+    if let Some(code) = e.exit_code() {
+        exit(interpreter, code, format);
+    }
+    report::report(&e, format);
+}
+
+// Leave the process once the script's atexit() handlers have run and the
+// --stats report, which counts them too, is written
+fn exit(interpreter: &mut Interpreter, code: i32, format: ErrorFormat) -> ! {
+    for exc in interpreter.run_exit_handlers() {
+        report::report(&exc, format);
+    }
+    let reports = [
+        (STATS_PATH.get(), interpreter.stats().map(|stats| serde_json::to_string_pretty(&stats.to_json()).unwrap_or_default() + "\n")),
+        (FLAMEGRAPH_PATH.get(), interpreter.stats().map(|stats| stats.collapsed_stacks())),
+    ];
+    for (path, report) in reports {
+        if let (Some(path), Some(report)) = (path, report) {
+            if let Err(source) = fs::write(path, report) {
+                eprintln!("{}: {}", PROGRAM, CliError::Write { path: path.clone(), source });
+            }
+        }
+    }
+    std::process::exit(code);
+}
+
+// Report a failure of the CLI itself, then leave through exit() so atexit()
+// handlers and --stats still run
+fn fail(interpreter: &mut Interpreter, e: CliError, format: ErrorFormat) -> ! {
+    eprintln!("{}: {}", PROGRAM, e);
+    exit(interpreter, e.exit_code(), format);
+}
+
+// Echo a result; a closed stdout ends the run instead of panicking in println!
+fn print_result(interpreter: &mut Interpreter, value: &Value, format: ErrorFormat) {
+    if let Err(e) = writeln!(std::io::stdout(), "{}", value.to_display_string()) {
+        fail(interpreter, CliError::Stdout(e), format);
+    }
+}
+
+// Keep a REPL result for later lines as `_` and as `_1`, `_2`, ... in the order
+// they came; None results (statements, mostly) are skipped as in Python
+fn remember_result(interpreter: &mut Interpreter, results: &mut usize, value: Value) {
+    if matches!(value, Value::None) {
+        return;
+    }
+    *results += 1;
+    interpreter.env.insert(format!("_{}", results), value.clone());
+    interpreter.env.insert("_".to_string(), value);
+}
+
+fn eval_source(interpreter: &mut Interpreter, source: &str) -> Result<Value, Exception> {
+    match Parser::parse_source(source)? {
+        Some(expr) => interpreter.eval(&expr),
+        None => Ok(Value::None),
+    }
+}
+
+// Instances report their class rather than the generic "instance"
+fn describe_type(value: &Value) -> String {
+    match value {
+        Value::Instance { class_name, .. } => class_name.clone(),
+        other => other.type_name().to_string(),
+    }
+}
+
+const REPL_HELP: &str = "\
+_, _1, _2, ...      The last result, and every result by number
+:help               Show this message
+:type <expr>        Evaluate an expression and print its type
+:time <expr>        Evaluate an expression and report how long it took
+:funcs              List the functions defined in this session
+:load <file>        Run a .stel file, or restore a session saved with :save
+:save <file>        Save variables and functions to a session image
+:quit               Leave the REPL (quit() and exit() work too)";
+
+// REPL commands start with ':' and operate on the session rather than evaluating code
+fn run_repl_command(interpreter: &mut Interpreter, command: &str, error_format: ErrorFormat) {
+    let (name, arg) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let arg = arg.trim();
+    match name {
+        "help" | "h" | "?" => println!("{}", REPL_HELP),
+        "quit" | "q" | "exit" => exit(interpreter, 0, error_format),
+        "type" if !arg.is_empty() => match eval_source(interpreter, arg) {
+            Ok(value) => println!("{}", describe_type(&value)),
+            Err(e) => exit_or_report(interpreter, e, error_format),
+        },
+        "time" if !arg.is_empty() => {
+            let start = Instant::now();
+            let result = eval_source(interpreter, arg);
+            let elapsed = start.elapsed();
+            match result {
+                Ok(value) => println!("{}", value.to_display_string()),
+                Err(e) => exit_or_report(interpreter, e, error_format),
+            }
+            println!("Elapsed: {:?}", elapsed);
+        }
+        "funcs" => {
+            let mut funcs: Vec<String> = interpreter.functions.iter()
+                .map(|(name, (params, _))| format!("{}({})", name, params.join(", ")))
+                .collect();
+            funcs.sort();
+            if funcs.is_empty() {
+                println!("No functions defined");
+            }
+            for f in funcs {
+                println!("{}", f);
             }
         }
+        "save" if !arg.is_empty() => match interpreter.snapshot() {
+            Ok(bytes) => match fs::write(arg, bytes) {
+                Ok(()) => println!("Session saved to {}", arg),
+                Err(e) => eprintln!("Error: could not write {}: {}", arg, e),
+            },
+            Err(e) => report::report(&e, error_format),
+        },
+        "load" if !arg.is_empty() => match fs::read(arg) {
+            Ok(bytes) if snapshot::is_snapshot(&bytes) => match interpreter.restore(&bytes) {
+                Ok(()) => println!("Session loaded from {}", arg),
+                Err(e) => report::report(&e, error_format),
+            },
+            // A file, unlike a typed line, has lines worth naming in tracebacks
+            Ok(bytes) => match Parser::parse_file(&String::from_utf8_lossy(&bytes), arg).and_then(|program| program.map_or(Ok(Value::None), |expr| interpreter.eval(&expr))) {
+                Ok(_) => println!("Loaded {}", arg),
+                Err(e) => exit_or_report(interpreter, e, error_format),
+            },
+            Err(e) => eprintln!("Error: could not read {}: {}", arg, e),
+        },
+        "save" => eprintln!("Usage: :save <file.stelimg>"),
+        "load" => eprintln!("Usage: :load <file.stel|file.stelimg>"),
+        "type" | "time" => eprintln!("Usage: :{} <expr>", name),
+        _ => eprintln!("Unknown command ':{}' (try :help)", name),
     }
 }
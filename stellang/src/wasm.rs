@@ -0,0 +1,74 @@
+//! JS bindings for running StelLang in the browser.
+//!
+//! Build with `cargo build --lib --target wasm32-unknown-unknown --no-default-features --features wasm`
+//! and post-process with `wasm-bindgen`.
+
+use crate::lang::{interpreter::{Interpreter, Value}, parser::Parser, exceptions::Exception, sandbox::Capabilities};
+use wasm_bindgen::prelude::*;
+
+/// Evaluate `source` in a fresh interpreter and return the result as a JS value.
+/// StelLang exceptions are thrown as JS `Error`s.
+#[wasm_bindgen]
+pub fn eval(source: &str) -> Result<JsValue, JsValue> {
+    Session::new().eval(source)
+}
+
+/// A persistent interpreter, so playground cells can share variables and functions.
+#[wasm_bindgen]
+pub struct Session {
+    interpreter: Interpreter,
+}
+
+#[wasm_bindgen]
+impl Session {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Session {
+        // The browser has no filesystem, stdin or threads
+        let interpreter = Interpreter::with_capabilities(Capabilities::sandboxed());
+        Session { interpreter }
+    }
+
+    pub fn eval(&mut self, source: &str) -> Result<JsValue, JsValue> {
+        run(&mut self.interpreter, source).map(|v| to_js(&v)).map_err(|e| to_js_error(&e))
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run(interpreter: &mut Interpreter, source: &str) -> Result<Value, Exception> {
+    match Parser::parse_source_with_lines(source)? {
+        Some(expr) => interpreter.eval(&expr),
+        None => Ok(Value::None),
+    }
+}
+
+fn to_js(value: &Value) -> JsValue {
+    match value {
+        Value::Int(n) => JsValue::from_f64(*n as f64),
+        Value::Float(f) => JsValue::from_f64(*f),
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Str(s) => JsValue::from_str(s),
+        Value::None => JsValue::NULL,
+        Value::List(items) | Value::Tuple(items) => items.iter().map(to_js).collect::<js_sys::Array>().into(),
+        Value::Dict(map) => {
+            let obj = js_sys::Object::new();
+            for (k, v) in map {
+                let _ = js_sys::Reflect::set(&obj, &JsValue::from_str(&k.to_display_string()), &to_js(v));
+            }
+            obj.into()
+        }
+        other => JsValue::from_str(&other.to_display_string()),
+    }
+}
+
+fn to_js_error(exc: &Exception) -> JsValue {
+    let message = match exc.line {
+        Some(line) => format!("{:?}: {} (line {})", exc.kind, exc.args.join(", "), line),
+        None => format!("{:?}: {}", exc.kind, exc.args.join(", ")),
+    };
+    js_sys::Error::new(&message).into()
+}
@@ -0,0 +1,131 @@
+// archive module tests: creating, listing and extracting tar, tar.gz and zip
+// archives, and gzip
+
+use std::fs;
+use std::path::PathBuf;
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+use stellang::lang::sandbox::Capabilities;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+/// A fresh directory holding `site/index.html` and `site/css/main.css`.
+fn fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("site/css")).unwrap();
+    fs::write(dir.join("site/index.html"), "<h1>hi</h1>\n".repeat(100)).unwrap();
+    fs::write(dir.join("site/css/main.css"), "body {}\n").unwrap();
+    dir
+}
+
+fn strs(items: &[&str]) -> Value {
+    Value::List(items.iter().map(|item| Value::Str(item.to_string())).collect())
+}
+
+fn round_trip(extension: &str) {
+    let dir = fixture(&format!("stellang_archive_{}", extension.replace('.', "_")));
+    let root = dir.display();
+    let code = format!(
+        "import archive
+let written = archive.create(\"{root}/out.{extension}\", {{\"site\": \"{root}/site\"}})
+let extracted = archive.extract(\"{root}/out.{extension}\", \"{root}/copy\")
+[written, archive.list(\"{root}/out.{extension}\"), extracted]"
+    );
+    let names = strs(&["site", "site/css", "site/css/main.css", "site/index.html"]);
+    assert_eq!(eval(&code).unwrap(), Value::List(vec![names.clone(), names.clone(), names]));
+    assert_eq!(fs::read_to_string(dir.join("copy/site/index.html")).unwrap(), "<h1>hi</h1>\n".repeat(100));
+    assert_eq!(fs::read_to_string(dir.join("copy/site/css/main.css")).unwrap(), "body {}\n");
+}
+
+#[test]
+fn test_tar_round_trip() {
+    round_trip("tar");
+}
+
+#[test]
+fn test_tar_gz_round_trip() {
+    round_trip("tar.gz");
+}
+
+#[test]
+fn test_zip_round_trip() {
+    round_trip("zip");
+}
+
+#[test]
+fn test_list_of_paths_keeps_relative_names() {
+    let dir = fixture("stellang_archive_names");
+    let root = dir.display();
+    let code = format!("import archive\narchive.create(\"{root}/out.zip\", [\"{root}/site/css/main.css\"])");
+    let expected = format!("{}/site/css/main.css", dir.to_string_lossy().trim_start_matches('/'));
+    assert_eq!(eval(&code).unwrap(), strs(&[&expected]));
+}
+
+#[test]
+fn test_gzip() {
+    assert_eq!(eval("import archive\narchive.gunzip(archive.gzip(\"hello \" * 100)).decode()").unwrap(), Value::Str("hello ".repeat(100)));
+    assert_eq!(eval("import archive\nlen(archive.gzip(\"hello \" * 100, 9)) < 100").unwrap(), Value::Bool(true));
+    let err = eval("import archive\narchive.gunzip(\"not gzip\".bytes())").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import archive\narchive.gzip(\"x\", 10)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_archive_errors() {
+    let dir = fixture("stellang_archive_errors");
+    let root = dir.display();
+    let err = eval(&format!("import archive\narchive.create(\"{root}/out.rar\", [\"{root}/site\"])")).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval(&format!("import archive\narchive.create(\"{root}/out.zip\", [\"{root}/missing\"])")).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::FileNotFoundError);
+    fs::write(dir.join("bad.zip"), "not a zip").unwrap();
+    let err = eval(&format!("import archive\narchive.list(\"{root}/bad.zip\")")).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_zip_entry_larger_than_declared() {
+    let dir = fixture("stellang_archive_zip_size");
+    let root = dir.display();
+    eval(&format!("import archive\narchive.create(\"{root}/out.zip\", [\"{root}/site/index.html\"])")).unwrap();
+    // The central directory claims 12 bytes for the 1200 the entry inflates to
+    let mut zip = fs::read(dir.join("out.zip")).unwrap();
+    let central = zip.windows(4).position(|window| window == b"PK\x01\x02").unwrap();
+    zip[central + 24..central + 28].copy_from_slice(&12u32.to_le_bytes());
+    fs::write(dir.join("out.zip"), zip).unwrap();
+    let err = eval(&format!("import archive\narchive.extract(\"{root}/out.zip\", \"{root}/copy\")")).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert!(err.args[0].contains("larger than the archive says"), "{:?}", err.args);
+}
+
+#[test]
+fn test_extract_refuses_paths_outside_destination() {
+    let dir = fixture("stellang_archive_traversal");
+    let mut builder = tar::Builder::new(fs::File::create(dir.join("evil.tar")).unwrap());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(4);
+    header.set_mode(0o644);
+    // set_path refuses `..`, so the name is written into the header directly
+    header.as_old_mut().name[..9].copy_from_slice(b"../escape");
+    header.set_cksum();
+    builder.append(&header, &b"evil"[..]).unwrap();
+    builder.into_inner().unwrap();
+    let root = dir.display();
+    let err = eval(&format!("import archive\narchive.extract(\"{root}/evil.tar\", \"{root}/out\")")).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert!(!dir.join("escape").exists() && !dir.join("out").exists());
+}
+
+#[test]
+fn test_archive_needs_filesystem_capability() {
+    let expr = Parser::parse_source("import archive\narchive.list(\"x.zip\")").unwrap().unwrap();
+    let err = Interpreter::with_capabilities(Capabilities::sandboxed()).eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
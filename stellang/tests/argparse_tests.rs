@@ -0,0 +1,98 @@
+// argparse module tests: options, positionals, errors and generated help
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+const SPEC: &str = "import argparse
+let spec = {
+    \"prog\": \"greet\",
+    \"description\": \"Greet someone\",
+    \"exit_on_error\": false,
+    \"arguments\": [
+        {\"name\": \"name\", \"help\": \"who to greet\"},
+        {\"name\": \"--count\", \"short\": \"-c\", \"type\": \"int\", \"default\": 1, \"help\": \"how many times\"},
+        {\"name\": \"--dry-run\", \"flag\": true},
+        {\"name\": \"--mode\", \"choices\": [\"plain\", \"fancy\"]},
+        {\"name\": \"extra\", \"nargs\": \"*\"}
+    ]
+}
+";
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(&format!("{}{}", SPEC, code)).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn parsed(argv: &str) -> Vec<Value> {
+    let code = format!("let a = argparse.parse(spec, {})\n[a[\"name\"], a[\"count\"], a[\"dry_run\"], a[\"mode\"], a[\"extra\"]]", argv);
+    match eval(&code).unwrap() {
+        Value::List(values) => values,
+        other => panic!("expected a list, got {:?}", other),
+    }
+}
+
+fn string(value: &str) -> Value {
+    Value::Str(value.to_string())
+}
+
+fn parse_error(argv: &str) -> String {
+    let err = eval(&format!("argparse.parse(spec, {})", argv)).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    err.message()
+}
+
+#[test]
+fn test_defaults() {
+    assert_eq!(parsed("[\"Ann\"]"), [string("Ann"), Value::Int(1), Value::Bool(false), Value::None, Value::List(vec![])]);
+}
+
+#[test]
+fn test_options_and_positionals() {
+    let values = parsed("[\"-c\", \"3\", \"Ann\", \"--dry-run\", \"--mode=fancy\", \"a\", \"b\"]");
+    assert_eq!(values, [string("Ann"), Value::Int(3), Value::Bool(true), string("fancy"), Value::List(vec![string("a"), string("b")])]);
+    // Attached short values, negative numbers and `--` ending the options
+    assert_eq!(parsed("[\"-c5\", \"--\", \"--dry-run\"]")[..3], [string("--dry-run"), Value::Int(5), Value::Bool(false)]);
+    assert_eq!(parsed("[\"--count\", \"-2\", \"Ann\"]")[1], Value::Int(-2));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(parse_error("[]"), "the following arguments are required: name");
+    assert_eq!(parse_error("[\"Ann\", \"--count\", \"x\"]"), "argument -c/--count: invalid int value: 'x'");
+    assert_eq!(parse_error("[\"Ann\", \"--count\"]"), "argument -c/--count: expected one argument");
+    assert_eq!(parse_error("[\"Ann\", \"--mode\", \"loud\"]"), "argument --mode: invalid choice: 'loud' (choose from 'plain', 'fancy')");
+    assert_eq!(parse_error("[\"Ann\", \"--cuont\", \"2\"]"), "unrecognized arguments: --cuont (did you mean '--count'?)");
+}
+
+#[test]
+fn test_help_and_exit() {
+    let help = eval("argparse.help(spec)").unwrap();
+    let expected = "usage: greet [-h] [-c COUNT] [--dry-run] [--mode MODE] name [extra ...]
+
+Greet someone
+
+positional arguments:
+  name                  who to greet
+  extra
+
+options:
+  -h, --help            show this help message and exit
+  -c COUNT, --count COUNT
+                        how many times (default: 1)
+  --dry-run
+  --mode MODE";
+    assert_eq!(help, string(expected));
+    let err = eval("argparse.parse(spec, [\"--help\"])").unwrap_err();
+    assert_eq!(err.exit_code(), Some(0));
+    // Without exit_on_error: false, bad arguments exit with status 2
+    let err = eval("spec[\"exit_on_error\"] = true\nargparse.parse(spec, [])").unwrap_err();
+    assert_eq!(err.exit_code(), Some(2));
+}
+
+#[test]
+fn test_invalid_spec() {
+    assert_eq!(eval("argparse.parse({\"arguments\": [{\"name\": \"-x\"}]}, [])").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("argparse.parse({\"arguments\": [{\"name\": \"--x\", \"type\": \"list\"}]}, [])").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("argparse.parse([], [])").unwrap_err().kind, ExceptionKind::TypeError);
+}
@@ -0,0 +1,84 @@
+// assert_eq and structural diff tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, testing::{diff, set_fixture_dir, ScratchDir}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn failure(code: &str) -> String {
+    let err = eval(code).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::AssertionError);
+    err.message()
+}
+
+#[test]
+fn test_assert_eq_passes() {
+    assert_eq!(eval("assert_eq([1, 2], [1, 2])").unwrap(), Value::None);
+    assert_eq!(eval("assert_eq(1, 1.0)").unwrap(), Value::None);
+    assert_eq!(eval("assert_eq(1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_scalar_failure() {
+    assert_eq!(failure("assert_eq(1, \"1\")"), "assert_eq failed: 1 != \"1\"");
+    assert_eq!(failure("assert_eq(2, 3, \"sum\")"), "assert_eq failed: sum: 2 != 3");
+}
+
+#[test]
+fn test_structural_diff() {
+    let message = failure("assert_eq({\"name\": \"a\", \"tags\": [1, 2], \"gone\": 1}, {\"name\": \"b\", \"tags\": [1, 2, 5], \"new\": \"x\"})");
+    assert_eq!(message, [
+        "assert_eq failed (- left only, + right only)",
+        "  - [\"gone\"]: 1",
+        "    [\"name\"]: \"a\" != \"b\"",
+        "  + [\"new\"]: \"x\"",
+        "  + [\"tags\"][2]: 5",
+    ].join("\n"));
+}
+
+#[test]
+fn test_long_diffs_are_truncated() {
+    let left = Value::List((0..30).map(Value::Int).collect());
+    let right = Value::List((0..30).map(|n| Value::Int(n + 1)).collect());
+    assert_eq!(diff(&left, &right).len(), 30);
+    let message = failure("assert_eq([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1])");
+    assert!(message.ends_with("\n  ... and 2 more differences"), "{}", message);
+    assert_eq!(message.lines().count(), 22);
+}
+
+#[test]
+fn test_fixture_path_and_scratch_dir() {
+    assert_eq!(eval("fixture_path()").unwrap_err().kind, ExceptionKind::RuntimeError);
+
+    let fixtures = std::env::temp_dir().join("stellang_assert_fixtures");
+    std::fs::create_dir_all(&fixtures).unwrap();
+    std::fs::write(fixtures.join("data.txt"), "x").unwrap();
+    set_fixture_dir(Some(fixtures.clone()));
+    assert_eq!(eval("fixture_path()").unwrap(), Value::Str(fixtures.to_string_lossy().into_owned()));
+    assert_eq!(eval("fixture_path(\"data.txt\")").unwrap(), Value::Str(fixtures.join("data.txt").to_string_lossy().into_owned()));
+    assert_eq!(eval("fixture_path(\"other.txt\")").unwrap_err().kind, ExceptionKind::FileNotFoundError);
+    assert_eq!(eval("fixture_path(1)").unwrap_err().kind, ExceptionKind::TypeError);
+    set_fixture_dir(None);
+
+    let before = std::env::current_dir().unwrap();
+    let scratch = ScratchDir::enter("assert_tests").unwrap();
+    let path = scratch.path().to_path_buf();
+    std::fs::write("left_behind.txt", "x").unwrap();
+    assert!(path.join("left_behind.txt").exists());
+    drop(scratch);
+    assert_eq!(std::env::current_dir().unwrap(), before);
+    assert!(!path.exists());
+    let _ = std::fs::remove_dir_all(&fixtures);
+}
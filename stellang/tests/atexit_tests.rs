@@ -0,0 +1,74 @@
+// atexit handler tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+const HANDLERS: &str = r#"
+    fn say(word) {
+        print(word)
+    }
+    fn fail() {
+        throw ValueError("cleanup failed")
+    }
+"#;
+
+#[test]
+fn test_handlers_run_last_registered_first() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, HANDLERS).unwrap();
+    eval_in(&mut interpreter, "atexit(say, \"first\")\natexit(say, \"second\")\nprint(\"main\")").unwrap();
+    assert!(interpreter.run_exit_handlers().is_empty());
+    assert_eq!(output.contents(), "main\nsecond\nfirst\n");
+    // Each handler runs once
+    assert!(interpreter.run_exit_handlers().is_empty());
+    assert_eq!(output.contents(), "main\nsecond\nfirst\n");
+}
+
+#[test]
+fn test_handlers_run_on_drop() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, HANDLERS).unwrap();
+    // Registered from inside a function call, which runs in a sub-interpreter
+    eval_in(&mut interpreter, "fn setup() {\n atexit(say, \"bye\")\n}\nsetup()").unwrap();
+    assert_eq!(output.contents(), "");
+    drop(interpreter);
+    assert_eq!(output.contents(), "bye\n");
+}
+
+#[test]
+fn test_failing_handler_does_not_stop_others() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, HANDLERS).unwrap();
+    eval_in(&mut interpreter, "atexit(say, \"still runs\")\natexit(fail)").unwrap();
+    let errors = interpreter.run_exit_handlers();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, ExceptionKind::ValueError);
+    assert_eq!(output.contents(), "still runs\n");
+}
+
+#[test]
+fn test_registration_errors() {
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, HANDLERS).unwrap();
+    assert_eq!(eval_in(&mut interpreter, "atexit(missing)").unwrap_err().kind, ExceptionKind::NameError);
+    assert_eq!(eval_in(&mut interpreter, "atexit(say)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval_in(&mut interpreter, "atexit(1)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert!(interpreter.run_exit_handlers().is_empty());
+}
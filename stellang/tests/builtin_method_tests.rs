@@ -200,6 +200,31 @@ fn test_iteration() {
     assert_eq!(eval_code("''.join([str(x) for x in [1,2,3]])"), Ok(stellang::lang::interpreter::Value::Str("123".to_string())));
 }
 
+#[test]
+fn test_mutating_methods_update_variable() {
+    assert_eq!(eval_code("let items = [1, 2]\nitems.append(3)\nitems.insert(0, 0)\nitems"), Ok(stellang::lang::interpreter::Value::List(vec![
+        stellang::lang::interpreter::Value::Int(0), stellang::lang::interpreter::Value::Int(1),
+        stellang::lang::interpreter::Value::Int(2), stellang::lang::interpreter::Value::Int(3),
+    ])));
+    assert_eq!(eval_code("let d = {\"a\": 1}\nd.update({\"b\": 2})\nd.pop(\"a\")\nd.keys()"), Ok(stellang::lang::interpreter::Value::List(vec![
+        stellang::lang::interpreter::Value::Str("b".to_string()),
+    ])));
+}
+
+#[test]
+fn test_bound_method() {
+    assert_eq!(eval_code("let up = \"abc\".upper\nup()"), Ok(stellang::lang::interpreter::Value::Str("ABC".to_string())));
+}
+
+#[test]
+fn test_unknown_attribute() {
+    for code in ["\"abc\".shout()", "\"abc\".shout", "[1].upper()"] {
+        let err = eval_code(code).unwrap_err();
+        assert_eq!(err.kind, stellang::lang::exceptions::ExceptionKind::AttributeError, "{}", code);
+    }
+    assert_eq!(eval_code("\"abc\".shout").unwrap_err().args, vec!["'str' object has no attribute 'shout'".to_string()]);
+}
+
 // Helper to convert Lexer output to Vec<Token>
 trait LexerExt {
     fn next_token_stream(&mut self) -> Vec<stellang::lang::lexer::Token>;
@@ -0,0 +1,65 @@
+// len, min, max, sum, abs and round as top-level builtins
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(&expr)
+}
+
+fn error_kind(code: &str) -> ExceptionKind {
+    eval(code).unwrap_err().kind
+}
+
+#[test]
+fn test_len() {
+    assert_eq!(eval("len(\"héllo\")"), Ok(Value::Int(5)));
+    assert_eq!(eval("len([1, 2, 3])"), Ok(Value::Int(3)));
+    assert_eq!(eval("len(tuple([1]))"), Ok(Value::Int(1)));
+    assert_eq!(eval("len({\"a\": 1, \"b\": 2})"), Ok(Value::Int(2)));
+    assert_eq!(eval("len(set([1, 1, 2]))"), Ok(Value::Int(2)));
+    assert_eq!(error_kind("len(5)"), ExceptionKind::TypeError);
+    assert_eq!(eval("len(5)").unwrap_err().args, vec!["object of type 'int' has no len()".to_string()]);
+}
+
+#[test]
+fn test_min_max() {
+    assert_eq!(eval("min([3, 1, 2])"), Ok(Value::Int(1)));
+    assert_eq!(eval("max(3, 7, 5)"), Ok(Value::Int(7)));
+    assert_eq!(eval("max([1, 2.5])"), Ok(Value::Float(2.5)));
+    assert_eq!(eval("min(\"pear\", \"apple\")"), Ok(Value::Str("apple".to_string())));
+    assert_eq!(eval("max([], 0)"), Ok(Value::Int(0)));
+    assert_eq!(error_kind("min([])"), ExceptionKind::ValueError);
+    assert_eq!(error_kind("max([1, \"a\"])"), ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_sum() {
+    assert_eq!(eval("sum([1, 2, 3])"), Ok(Value::Int(6)));
+    assert_eq!(eval("sum(tuple([1, 2]), 10)"), Ok(Value::Int(13)));
+    assert_eq!(eval("sum([0.5, 1])"), Ok(Value::Float(1.5)));
+    assert_eq!(eval("sum([])"), Ok(Value::Int(0)));
+    assert_eq!(error_kind("sum([\"a\"])"), ExceptionKind::TypeError);
+    assert_eq!(error_kind("sum([\"a\"], \"\")"), ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_abs_and_round() {
+    assert_eq!(eval("abs(-3)"), Ok(Value::Int(3)));
+    assert_eq!(eval("abs(-2.5)"), Ok(Value::Float(2.5)));
+    assert_eq!(eval("round(2.5)"), Ok(Value::Int(2)));
+    assert_eq!(eval("round(3.5)"), Ok(Value::Int(4)));
+    assert_eq!(eval("round(2.675, 2)"), Ok(Value::Float(2.67)));
+    assert_eq!(eval("round(1250, -2)"), Ok(Value::Int(1200)));
+    assert_eq!(eval("round(7)"), Ok(Value::Int(7)));
+    assert_eq!(error_kind("abs(\"x\")"), ExceptionKind::TypeError);
+    assert_eq!(error_kind("round(1.5, \"x\")"), ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_user_function_shadows_builtin() {
+    assert_eq!(eval("fn max(a, b) { \"mine\" }\nmax(1, 2)"), Ok(Value::Str("mine".to_string())));
+}
@@ -0,0 +1,65 @@
+// @cache decorator tests: memoized results, cache_info(), cache_clear() and maxsize eviction
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+// Each nested call takes several interpreter frames, more than a test thread's stack holds
+fn with_large_stack<T: Send + 'static>(test: impl FnOnce() -> T + Send + 'static) -> T {
+    std::thread::Builder::new().stack_size(1 << 30).spawn(test).unwrap().join().unwrap()
+}
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    eval_in(&mut Interpreter::new(), code)
+}
+
+fn info(hits: i64, misses: i64, maxsize: Value, currsize: i64) -> Value {
+    let fields = [("hits", Value::Int(hits)), ("misses", Value::Int(misses)), ("maxsize", maxsize), ("currsize", Value::Int(currsize))];
+    Value::Dict(fields.into_iter().map(|(key, value)| (Value::Str(key.to_string()), value)).collect())
+}
+
+const FIB: &str = "@cache\nfn fib(n) {\n    if n < 2 { return n }\n    return fib(n - 1) + fib(n - 2)\n}\n";
+
+#[test]
+fn test_recursive_function_is_memoized() {
+    with_large_stack(|| {
+        let mut interpreter = Interpreter::new();
+        // Without the cache this would take on the order of 2^80 calls
+        assert_eq!(eval_in(&mut interpreter, &format!("{}fib(80)", FIB)).unwrap(), Value::Int(23416728348467685));
+        assert_eq!(eval_in(&mut interpreter, "fib.cache_info()").unwrap(), info(78, 81, Value::None, 81));
+        eval_in(&mut interpreter, "fib.cache_clear()").unwrap();
+        assert_eq!(eval_in(&mut interpreter, "fib.cache_info()").unwrap(), info(0, 0, Value::None, 0));
+    });
+}
+
+#[test]
+fn test_maxsize_evicts_least_recently_used() {
+    let code = "@cache(2)\nfn square(n) {\n    print(n)\n    return n * n\n}\nsquare(1)\nsquare(2)\nsquare(1)\nsquare(3)\nsquare(1)\nsquare(2)\nsquare.cache_info()";
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    assert_eq!(eval_in(&mut interpreter, code).unwrap(), info(2, 4, Value::Int(2), 2));
+    // 2 was evicted when 3 was added, since 1 had been used more recently
+    assert_eq!(output.contents(), "1\n2\n3\n2\n");
+}
+
+#[test]
+fn test_redefinition_drops_cache() {
+    let code = "@cache\nfn double(n) {\n    return n * 2\n}\ndouble(5)\nfn double(n) {\n    return n + n\n}\ndouble.cache_info()";
+    assert_eq!(eval(code).unwrap_err().kind, ExceptionKind::AttributeError);
+}
+
+#[test]
+fn test_decorator_errors() {
+    let err = eval(&format!("{}fib([1])", FIB)).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    let err = eval("@memoize\nfn f() {\n    return 1\n}").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::NameError);
+    let err = eval("@cache(-1)\nfn f() {\n    return 1\n}").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert!(Parser::parse_source("@cache\nlet x = 1").is_err());
+}
@@ -0,0 +1,78 @@
+// `stellang check`: where syntax errors are reported and which warnings fire
+
+use stellang::lang::check::{check_source, Severity};
+use stellang::lang::lexer::Position;
+
+fn codes(source: &str) -> Vec<String> {
+    check_source("test.stel", source).into_iter().map(|d| d.code).collect()
+}
+
+#[test]
+fn test_syntax_error_has_a_range() {
+    let diagnostics = check_source("main.stel", "let a = 1\n\n# comment\nlet b = (2 +\n");
+    assert_eq!(diagnostics.len(), 1);
+    let error = &diagnostics[0];
+    assert_eq!((error.severity, error.code.as_str()), (Severity::Error, "SyntaxError"));
+    assert_eq!(error.range.map(|(start, _)| start.line), Some(4));
+
+    let error = &check_source("main.stel", "print(1)\n  x = \"open")[0];
+    assert_eq!(error.range.map(|(start, _)| start), Some(Position { line: 2, column: 7 }));
+    assert_eq!(error.to_string(), "main.stel:2:7: error[SyntaxError]: Unterminated string literal");
+}
+
+#[test]
+fn test_every_lexical_error_is_reported() {
+    let diagnostics = check_source("main.stel", "let a = 1 $ 2\nlet b = \"tab\\q\"\nlet c = ' + \"open");
+    let reported: Vec<(usize, usize, &str)> = diagnostics
+        .iter()
+        .map(|d| (d.range.unwrap().0.line, d.range.unwrap().0.column, d.message.as_str()))
+        .collect();
+    assert_eq!(reported, [
+        (1, 11, "Unexpected character: $"),
+        (2, 13, "Invalid escape sequence '\\q'"),
+        (3, 9, "Expected label name after '''"),
+        (3, 13, "Unterminated string literal"),
+    ]);
+    // An invalid escape covers just its two characters
+    assert_eq!(diagnostics[1].range.unwrap().1, Position { line: 2, column: 15 });
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Error && d.code == "SyntaxError"));
+}
+
+#[test]
+fn test_unterminated_string_points_at_its_opening_quote() {
+    // Lexing picks up again after the line the string starts on
+    let diagnostics = check_source("main.stel", "let a = \"no end\nlet b = 1 $");
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].range, Some((Position { line: 1, column: 9 }, Position { line: 1, column: 10 })));
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "main.stel:1:9: error[SyntaxError]: Unterminated string literal\n  hint: the string runs from line 1 to the end of the file; is its closing '\"' missing?"
+    );
+    assert_eq!(diagnostics[1].message, "Unexpected character: $");
+
+    let error = &check_source("main.stel", "print(\"C:\\dir\\\")\n")[0];
+    assert_eq!(error.message, "Unterminated string literal");
+    assert_eq!(error.to_json()["hint"], "the '\"' at column 15 is escaped by the backslash before it; write '\\\\' for a backslash");
+    assert_eq!(check_source("main.stel", "x = \"open")[0].hint, None);
+}
+
+#[test]
+fn test_warnings() {
+    assert!(codes("fn f(x) {\n return x * 2\n}\nf(2)").is_empty());
+    assert_eq!(codes("fn f(x) {\n return x\n print(x)\n}"), ["unreachable-code"]);
+    assert_eq!(codes("while true {\n break\n x = 1\n}"), ["unreachable-code"]);
+    assert_eq!(codes("fn f() { 1 }\nfn g() { 2 }\nfn f() { 3 }"), ["redefined-function"]);
+    assert_eq!(codes("x = 1\nx = x"), ["self-assignment"]);
+    assert_eq!(codes("x = 1\nx == 5; print(x)"), ["unused-result"]);
+    assert_eq!(codes("fn f() { 1 }\nf\n[f(), 2]\n-x\nf()"), ["unused-result", "unused-result"]);
+
+    // A block's last statement is its value, and a lone string is a comment
+    assert!(codes("fn double(x) {\n \"twice x\"\n x * 2\n}\ndouble(2) == 4").is_empty());
+    let messages: Vec<String> = check_source("a.stel", "x == 5\nitems.pop\nx + 1\nx").into_iter().map(|d| d.message).collect();
+    assert_eq!(messages, ["'x == 5' has no effect (did you mean '='?)", "'items.pop' has no effect (did you mean to call it?)", "'x + 1' has no effect"]);
+
+    let warning = &check_source("lib.stel", "x = 1\nx = x")[0];
+    assert_eq!((warning.severity, warning.range), (Severity::Warning, None));
+    assert_eq!(warning.to_json()["message"], "'x' is assigned to itself");
+    assert_eq!(warning.to_json()["range"], serde_json::Value::Null);
+}
@@ -0,0 +1,249 @@
+// Classes: construction, __init__, inherited fields, methods that update self,
+// how instances print and abstract methods
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+const ANIMALS: &str = "
+class Animal {
+    sound = \"...\"
+    legs = 4
+    fn __init__(self, name) {
+        self.name = name
+    }
+    fn describe(self) {
+        return self.name + \" says \" + self.sound
+    }
+}
+class Dog extends Animal {
+    sound = \"woof\"
+}
+class Bird(Animal) {
+    fn __init__(self, name, legs) {
+        self.name = name
+        self.legs = legs
+    }
+}
+";
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    eval_in(&mut Interpreter::new(), code)
+}
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let source = format!("{}\n{}", ANIMALS, code);
+    let expr = Parser::parse_source(&source).expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn printed(code: &str) -> String {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, code).unwrap();
+    output.take()
+}
+
+#[test]
+fn test_init_sets_fields() {
+    assert_eq!(eval("a = Animal(\"Generic\")\na.name"), Ok(Value::Str("Generic".to_string())));
+    assert_eq!(eval("a = Animal(\"Generic\")\na.legs"), Ok(Value::Int(4)));
+    assert_eq!(eval("b = Bird(\"Tweety\", 2)\nb.legs"), Ok(Value::Int(2)));
+    assert_eq!(eval("b = Bird(\"Tweety\", 2)\nb.name"), Ok(Value::Str("Tweety".to_string())));
+}
+
+#[test]
+fn test_inherited_init_and_fields() {
+    assert_eq!(eval("d = Dog(\"Rex\")\nd.name"), Ok(Value::Str("Rex".to_string())));
+    assert_eq!(eval("d = Dog(\"Rex\")\nd.describe()"), Ok(Value::Str("Rex says woof".to_string())));
+}
+
+#[test]
+fn test_constructor_arity() {
+    let error = eval("Dog()").unwrap_err();
+    assert_eq!(error.kind, ExceptionKind::TypeError);
+    assert_eq!(error.args, vec!["Dog.__init__() takes 1 arguments but 0 were given".to_string()]);
+    assert_eq!(eval("Bird(\"Tweety\")").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_class_without_init() {
+    assert_eq!(eval("class Point { x = 0 }\np = Point()\np.x"), Ok(Value::Int(0)));
+    let error = eval("class Point { x = 0 }\nPoint(1)").unwrap_err();
+    assert_eq!(error.kind, ExceptionKind::TypeError);
+    assert_eq!(error.args, vec!["Point() takes no arguments".to_string()]);
+}
+
+const COUNTER: &str = "
+class Counter {
+    count = 0
+    fn increment(self) {
+        self.count = self.count + 1
+        return self.count
+    }
+    fn add(self, n) {
+        self.count = self.count + n
+    }
+    fn twice(self) {
+        self.increment()
+        self.increment()
+    }
+    fn fail(self) {
+        self.count = 100
+        throw ValueError(\"failed\")
+    }
+}
+";
+
+#[test]
+fn test_method_mutations_persist() {
+    let run = |code: &str| eval(&format!("{}\n{}", COUNTER, code));
+    assert_eq!(run("c = Counter()\nc.increment()\nc.increment()\nc.count"), Ok(Value::Int(2)));
+    assert_eq!(run("c = Counter()\nc.increment()\nc.increment()"), Ok(Value::Int(2)));
+    assert_eq!(run("c = Counter()\nc.add(5)\nc.add(2)\nc.count"), Ok(Value::Int(7)));
+    assert_eq!(run("c = Counter()\nc.twice()\nc.twice()\nc.count"), Ok(Value::Int(4)));
+    assert_eq!(run("c = Counter()\nd = Counter()\nc.increment()\nd.count"), Ok(Value::Int(0)));
+    assert_eq!(run("c = Counter()\ntry { c.fail() } catch e { }\nc.count"), Ok(Value::Int(100)));
+}
+
+#[test]
+fn test_method_mutations_through_indexes_persist() {
+    let run = |code: &str| eval(&format!("{}\n{}", COUNTER, code));
+    assert_eq!(run("b = [Counter(), Counter()]\nb[0].increment()\nb[0].increment()\n[b[0].count, b[1].count]"), Ok(Value::List(vec![Value::Int(2), Value::Int(0)])));
+    assert_eq!(run("d = {\"k\": Counter()}\nd[\"k\"].add(3)\nd[\"k\"].count"), Ok(Value::Int(3)));
+    assert_eq!(run("k = \"k\"\nd = {\"k\": [Counter()]}\nd[k][0].increment()\nd[k][0].count"), Ok(Value::Int(1)));
+    assert_eq!(run("i = 1\nb = [Counter(), Counter()]\nb[i].twice()\nb[1].count"), Ok(Value::Int(2)));
+    // Built-in methods on elements too
+    assert_eq!(eval("rows = [[1], [2]]\nrows[1].append(3)\nrows"), Ok(Value::List(vec![Value::List(vec![Value::Int(1)]), Value::List(vec![Value::Int(2), Value::Int(3)])])));
+}
+
+#[test]
+fn test_aliases_are_copies() {
+    // Assigning an instance copies it, so a method called through one name
+    // leaves the other as it was
+    let run = |code: &str| eval(&format!("{}\n{}", COUNTER, code));
+    assert_eq!(run("a = Counter()\nb = a\nb.increment()\n[a.count, b.count]"), Ok(Value::List(vec![Value::Int(0), Value::Int(1)])));
+    assert_eq!(run("a = Counter()\nb = [a]\nb[0].increment()\na.count"), Ok(Value::Int(0)));
+}
+
+#[test]
+fn test_linked_structure_mutations_persist() {
+    let code = "
+class Node {
+    fn __init__(self, value, rest) {
+        self.value = value
+        self.rest = rest
+    }
+    fn bump(self) {
+        self.value = self.value + 10
+    }
+}
+class Stack {
+    head = None
+    size = 0
+    fn push(self, value) {
+        self.head = Node(value, self.head)
+        self.size = self.size + 1
+    }
+    fn total(self) {
+        acc = 0
+        node = self.head
+        while node != None {
+            acc = acc + node.value
+            node = node.rest
+        }
+        return acc
+    }
+}
+s = Stack()
+s.push(1)
+s.push(2)
+s.push(3)
+s.head.bump()
+";
+    assert_eq!(eval(&format!("{}\ns.size", code)), Ok(Value::Int(3)));
+    assert_eq!(eval(&format!("{}\ns.total()", code)), Ok(Value::Int(16)));
+    assert_eq!(eval(&format!("{}\ns.head.rest.value", code)), Ok(Value::Int(2)));
+}
+
+#[test]
+fn test_str_and_repr() {
+    let code = "
+class Point {
+    fn __init__(self, x, y) {
+        self.x = x
+        self.y = y
+    }
+    fn __str__(self) {
+        return \"(\" + str(self.x) + \", \" + str(self.y) + \")\"
+    }
+    fn __repr__(self) {
+        return \"Point\"
+    }
+}
+class Tag {
+    fn __repr__(self) {
+        return \"<tag>\"
+    }
+}
+p = Point(1, 2)
+print(p)
+print([p, Tag()])
+print(Tag())
+print(str(p) + \"!\")
+";
+    assert_eq!(printed(code), "(1, 2)\n[Point, <tag>]\n<tag>\n(1, 2)!\n");
+}
+
+#[test]
+fn test_default_display_is_stable() {
+    let first = printed("a = Animal(\"a\")\nb = Animal(\"b\")\nprint(a, b, a)");
+    let shown: Vec<&str> = first.trim_end().split("> ").collect();
+    assert!(shown[0].starts_with("<Animal instance #"), "{}", first);
+    assert_ne!(shown[0], shown[1]);
+    assert_eq!(format!("{}>", shown[0]), shown[2]);
+
+    let error = eval("class Bad {\n fn __str__(self) { return 1 }\n}\nprint(Bad())").unwrap_err();
+    assert_eq!(error.kind, ExceptionKind::TypeError);
+    assert_eq!(error.args, vec!["__str__ returned non-string (type int)".to_string()]);
+}
+
+const SHAPES: &str = "
+class Shape {
+    abstract fn area(self)
+    abstract fn name(self)
+    fn describe(self) {
+        return self.name() + \" of area \" + str(self.area())
+    }
+}
+class Square(Shape) {
+    fn __init__(self, side) {
+        self.side = side
+    }
+    fn area(self) {
+        return self.side * self.side
+    }
+    fn name(self) {
+        return \"square\"
+    }
+}
+class Unnamed(Shape) {
+    fn area(self) {
+        return 0
+    }
+}
+";
+
+#[test]
+fn test_abstract_methods() {
+    let run = |code: &str| eval(&format!("{}\n{}", SHAPES, code));
+    assert_eq!(run("Square(3).describe()"), Ok(Value::Str("square of area 9".to_string())));
+
+    let error = run("Shape()").unwrap_err();
+    assert_eq!(error.kind, ExceptionKind::TypeError);
+    assert_eq!(error.args, vec!["Can't instantiate abstract class Shape with abstract methods area, name".to_string()]);
+    assert_eq!(run("Unnamed()").unwrap_err().args, vec!["Can't instantiate abstract class Unnamed with abstract methods name".to_string()]);
+
+    assert_eq!(Parser::parse_source("abstract fn area(self)").unwrap_err().kind, ExceptionKind::SyntaxError);
+    assert_eq!(Parser::parse_source("class A { abstract area }").unwrap_err().kind, ExceptionKind::SyntaxError);
+}
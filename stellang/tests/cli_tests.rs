@@ -0,0 +1,438 @@
+// Command-line failure tests: bad arguments and unreadable or unwritable files
+// must end with a message and an exit status, never a panic
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stellang"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start stellang");
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().expect("stellang did not exit")
+}
+
+fn temp_file(name: &str, contents: &[u8]) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+fn assert_no_panic(output: &Output) {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("panicked") && !stderr.contains("internal error"), "{}", stderr);
+}
+
+#[test]
+fn test_missing_file() {
+    let output = run(&["/nonexistent/stellang_cli_test.stel"], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("stellang: can't open file '/nonexistent/stellang_cli_test.stel'"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_file_that_is_not_utf8() {
+    let path = temp_file("stellang_cli_invalid_utf8.stel", b"print(\"\xff\")\n");
+    let output = run(&[&path], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can't open file"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_usage_errors() {
+    for args in [&["--stats"][..], &["--error-format=xml", "main.stel"][..], &["--emit", "xml", "main.stel"][..], &["--from=ast-json"][..], &["--enable-feature", "nope", "main.stel"][..], &["--deprecations=loud", "main.stel"][..], &["--flamegraph"][..], &["--deterministic=seven", "main.stel"][..]] {
+        let output = run(args, b"");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("stellang: "), "{:?}", args);
+        assert_no_panic(&output);
+    }
+}
+
+#[test]
+fn test_ast_json_round_trip() {
+    let output = run(&["--emit", "ast-json", "-e", "let x = 4\nprint(x * 2.5)"], b"");
+    assert_eq!(output.status.code(), Some(0));
+    let json = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(json.contains("\"format\": \"stellang-ast\"") && json.contains("\"version\": 1"), "{}", json);
+    assert!(json.contains("\"Float\": 2.5"), "{}", json);
+
+    // A tool's rewrite is run as it comes back
+    let rewritten = json.replace("\"Integer\": 4", "\"Integer\": 6");
+    let output = run(&["--from", "ast-json", "-"], rewritten.as_bytes());
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("15.0\n"));
+
+    let output = run(&["--from=ast-json", "-"], json.replace("\"version\": 1", "\"version\": 99").as_bytes());
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unsupported schema version 99"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_syntax_error_in_file() {
+    let path = temp_file("stellang_cli_syntax_error.stel", b"let x = \"unterminated\n");
+    let output = run(&[&path], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("SyntaxError"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_repl_skips_line_that_is_not_utf8() {
+    let output = run(&[], b"print(\"\xff\")\nprint(\"after\")\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("after"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_flamegraph_writes_collapsed_stacks() {
+    let script = temp_file("stellang_cli_flamegraph.stel", b"fn inner() { 1 }\nfn outer() { inner() + inner() }\nprint(outer())\n");
+    let folded = std::env::temp_dir().join("stellang_cli_flamegraph.folded");
+    let output = run(&["--flamegraph", &folded.display().to_string(), &script], b"");
+    assert_eq!(output.status.code(), Some(0));
+    // The time per node kind table is gone from the output
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\nNone\n");
+    let stacks = std::fs::read_to_string(&folded).unwrap();
+    let stacks: Vec<&str> = stacks.lines().map(|line| line.rsplit_once(' ').unwrap().0).collect();
+    // Each frame is the function and the line it was running
+    assert_eq!(stacks, ["outer:2", "outer:2;inner:1"]);
+}
+
+#[test]
+fn test_deterministic_runs_print_the_same() {
+    let code = "print({\"a\": 1, \"b\": 2, \"c\": 3, \"d\": 4, \"e\": 5})\nprint(set([\"x\", \"y\", \"z\", 1, 2]))";
+    let first = run(&["--deterministic", "-e", code], b"");
+    assert_eq!(first.status.code(), Some(0));
+    // Pinned, so a change to the hash shows up here rather than in users' output
+    assert!(String::from_utf8_lossy(&first.stdout).starts_with("{a: 1, d: 4, b: 2, c: 3, e: 5}\n{x, 2, y, z, 1}\n"), "{}", String::from_utf8_lossy(&first.stdout));
+    for _ in 0..3 {
+        assert_eq!(run(&["--deterministic", "-e", code], b"").stdout, first.stdout);
+    }
+    assert_eq!(run(&["--deterministic=7", "-e", code], b"").stdout, run(&["--deterministic=7", "-e", code], b"").stdout);
+}
+
+#[test]
+fn test_unwritable_stats_file_keeps_exit_status() {
+    let path = temp_file("stellang_cli_stats.stel", b"exit(3)\n");
+    let output = run(&["--stats", "/nonexistent/stats.json", &path], b"");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("stellang: can't write file '/nonexistent/stats.json'"));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_inline_expression() {
+    let output = run(&["-e", "print(\"sum\", 1 + 2)"], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("sum 3"));
+
+    let output = run(&["-e", "[1][5]"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("IndexError"));
+
+    let output = run(&["-e"], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_script_arguments_in_sys_argv() {
+    let path = temp_file("stellang_cli_argv.stel", b"import sys\nprint(sys.argv)\n");
+    // Flags after the script belong to the script, not to stellang
+    let output = run(&[&path, "first", "--error-format=json"], b"");
+    assert_eq!(output.status.code(), Some(0));
+    let expected = format!("[{}, first, --error-format=json]", path);
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&expected), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = run(&["-e", "import sys\nif sys.argv[0] == \"-e\" and sys.argv[2] == \"b\" { sys.exit(3) }", "a", "b"], b"");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_deep_recursion_raises_recursion_error() {
+    let output = run(&["-e", "fn down(n) {\n    return down(n + 1)\n}\ndown(0)"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("RecursionError"));
+    assert_no_panic(&output);
+
+    // The highest limit sys.setrecursionlimit() takes still fits the stack
+    let code = format!("import sys\nsys.setrecursionlimit({})\nfn down(n) {{\n    return down(n + 1)\n}}\ndown(0)", stellang::lang::stdlib::sys::MAX_RECURSION_LIMIT);
+    let output = run(&["-e", &code], b"");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("RecursionError"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_no_panic(&output);
+}
+
+#[test]
+fn test_traceback_shows_lines() {
+    let code = "fn inner(d) {\n    let x = 1\n    return d[\"missing\"]\n}\n\nfn outer() {\n    inner({})\n}\n\nouter()";
+    let output = run(&["-e", code], b"");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Traceback (most recent call last):\n  at line 10\n  in outer() at line 7\n  in inner() at line 3\nKeyError"), "{}", stderr);
+
+    let output = run(&["--error-format=json", "-e", "let a = 1\na / 0"], b"");
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(report["line"], 2);
+
+    // Lines in files, the script's and a module's, come with the file's path
+    let dir = std::env::temp_dir().join("stellang_cli_traceback_files");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("mod1.stel"), "fn helper() {\n    return 1\n}\n\nfn boom() {\n    return 1 / 0\n}\n").unwrap();
+    let script = dir.join("main.stel");
+    std::fs::write(&script, "import mod1\n\nmod1.boom()\n").unwrap();
+    let module = dir.join("mod1.stel").canonicalize().unwrap();
+    let output = run(&[&script.display().to_string()], b"");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!("  at {}:3\n  in mod1.boom() at {}:6\nZeroDivisionError", script.display(), module.display());
+    assert!(stderr.contains(&expected), "{}", stderr);
+    let output = run(&["--error-format=json", &script.display().to_string()], b"");
+    let report: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!((report["file"].as_str(), report["traceback"][0]["line"].as_u64()), (Some(script.display().to_string().as_str()), Some(6)));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_program_from_stdin() {
+    let output = run(&["-"], b"fn double(n) {\n    return n * 2\n}\nprint(\"piped\", double(21))\nexit(4)\n");
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("piped 42"), "{}", stdout);
+    // The whole of stdin is one program, not REPL input
+    assert!(!stdout.contains(">>>"), "{}", stdout);
+}
+
+#[test]
+fn test_input_lines_keys_and_eof() {
+    let program = "print(input(\"name? \"))\nprint(input(key=true), input(hidden=true))\nprint(input() == None)";
+    let output = run(&["-e", program], b"ada\r\nyes\n");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // key=true reads one key; the rest of that line is the next reply
+    assert!(stdout.starts_with("name? ada\ny es\ntrue\n"), "{}", stdout);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_input_timeout() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stellang"))
+        .args(["-e", "input(\"> \", timeout=0.2)"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start stellang");
+    // Keep stdin open, so only the timeout can end the read
+    let stdin = child.stdin.take();
+    let output = child.wait_with_output().expect("stellang did not exit");
+    drop(stdin);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("TimeoutError"));
+}
+
+#[test]
+fn test_exit_unwinds_through_finally() {
+    let output = run(&["-e", "try { exit(3) } finally { print(\"cleanup\") }\nprint(\"not reached\")"], b"");
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cleanup") && !stdout.contains("not reached"), "{}", stdout);
+}
+
+#[test]
+fn test_shebang_line_is_skipped() {
+    let path = temp_file("stellang_cli_shebang.stel", b"#!/usr/bin/env stellang\nprint(\"from script\")\n");
+    let output = run(&[&path], b"");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("from script"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_installed_script_runs_directly() {
+    let dir = std::env::temp_dir().join("stellang_cli_install_script");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("hello.stel");
+    std::fs::write(&script, "print(\"hello\", 6 * 7)\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(["install", "--script"]).arg(&script).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(std::fs::read_to_string(&script).unwrap().starts_with("#!/usr/bin/env stellang\n"));
+
+    // The shebang finds stellang on PATH, so put the freshly built binary first
+    let bin_dir = std::path::Path::new(env!("CARGO_BIN_EXE_stellang")).parent().unwrap();
+    let mut paths = vec![bin_dir.to_path_buf()];
+    paths.extend(std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()));
+    let path = std::env::join_paths(paths).unwrap();
+    let output = Command::new(&script).env("PATH", path).output().expect("script is not executable");
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("hello 42"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_run_watch_reruns_on_change() {
+    use std::io::{BufRead, BufReader};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join("stellang_cli_watch");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let stel = env!("CARGO_BIN_EXE_stel");
+    assert!(Command::new(stel).arg("init").current_dir(&dir).output().unwrap().status.success());
+    std::fs::write(dir.join("src/main.stel"), "print(\"version one\")\n").unwrap();
+
+    let mut child = Command::new(stel)
+        .args(["run", "--watch"])
+        .current_dir(&dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let (lines, received) = mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = lines.send(line);
+        }
+    });
+    let wait_for = |text: &str| loop {
+        match received.recv_timeout(Duration::from_secs(20)) {
+            Ok(line) if line.contains(text) => break true,
+            Ok(_) => continue,
+            Err(_) => break false,
+        }
+    };
+
+    let first = wait_for("waiting for changes");
+    std::fs::write(dir.join("src/main.stel"), "print(\"version two\")\n").unwrap();
+    let second = wait_for("version two");
+    let _ = child.kill();
+    let _ = child.wait();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(first, "the first run never finished");
+    assert!(second, "no rerun after main.stel changed");
+}
+
+#[test]
+fn test_check_output_and_exit_status() {
+    let clean = temp_file("stellang_check_clean.stel", b"fn f(x) {\n    return x\n}\n");
+    let warned = temp_file("stellang_check_warned.stel", b"x = 1\nx = x\n");
+    let broken = temp_file("stellang_check_broken.stel", b"let a = (1 +\n");
+
+    let output = run(&["check", &clean, &warned], b"");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("{}: warning[self-assignment]: 'x' is assigned to itself", warned)), "{}", stdout);
+    assert!(stdout.ends_with("2 file(s) checked: 0 error(s), 1 warning(s)\n"), "{}", stdout);
+
+    let output = run(&["check", "--max-warnings", "0", &warned], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 warning(s) exceed --max-warnings 0"));
+
+    let output = run(&["check", "--output=json", &broken, &clean], b"");
+    assert_eq!(output.status.code(), Some(1));
+    let diagnostics: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diagnostics.as_array().map(Vec::len), Some(1));
+    assert_eq!(diagnostics[0]["file"], broken.as_str());
+    assert_eq!(diagnostics[0]["severity"], "error");
+    assert_eq!(diagnostics[0]["code"], "SyntaxError");
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    let output = run(&["check", "--output", "xml", &clean], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert_no_panic(&output);
+    for path in [clean, warned, broken] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[test]
+fn test_stel_test_fixtures_and_scratch_dir() {
+    let dir = std::env::temp_dir().join("stellang_cli_fixtures");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let stel = env!("CARGO_BIN_EXE_stel");
+    assert!(Command::new(stel).arg("init").current_dir(&dir).output().unwrap().status.success());
+    std::fs::create_dir_all(dir.join("tests/fixtures/copy")).unwrap();
+    std::fs::write(dir.join("tests/fixtures/copy/input.txt"), "from fixture").unwrap();
+    std::fs::write(
+        dir.join("tests/copy.stel"),
+        r#"let text = with open(fixture_path("input.txt")) as fh { fh.read() }
+with open("output.txt", "w") as fh { fh.write(text) }
+assert_eq(with open("output.txt") as fh { fh.read() }, "from fixture")
+"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join("tests/missing.stel"), "fixture_path(\"nothing.txt\")\n").unwrap();
+
+    let output = Command::new(stel).arg("test").current_dir(&dir).output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("FileNotFoundError: no such fixture"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("passed, 1 failed"));
+    // What the test wrote went to its scratch directory, not the project
+    assert!(!dir.join("output.txt").exists());
+    assert!(!dir.join("tests/output.txt").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_stel_test_selects_by_name_and_tag() {
+    let dir = std::env::temp_dir().join("stellang_cli_test_selection");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let stel = env!("CARGO_BIN_EXE_stel");
+    assert!(Command::new(stel).arg("init").current_dir(&dir).output().unwrap().status.success());
+    std::fs::create_dir_all(dir.join("tests")).unwrap();
+    std::fs::write(dir.join("tests/smoke.stel"), "assert_eq(1 + 1, 2)\n").unwrap();
+    std::fs::write(
+        dir.join("tests/parser.stel"),
+        r#"fn parse_all(text) { text.split(",") }
+
+test "splits on commas" {
+    assert_eq(parse_all("a,b"), ["a", "b"])
+}
+
+test "slow parser" tags=["slow"] {
+    assert_eq(len(parse_all("x," * 100)), 101)
+}
+
+test "broken" tags=["slow", "wip"] {
+    assert_eq(parse_all("a"), [])
+}
+"#,
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(stel).arg("test").args(args).current_dir(&dir).output().unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    let (code, stdout) = run(&["parser", "--exclude-tag", "wip"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(stdout.contains("Running test: parser::splits on commas"));
+    assert!(stdout.contains("Test Results: 2 passed, 0 failed, 2 filtered out"), "{}", stdout);
+
+    let (code, stdout) = run(&["--tag", "slow"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains("Test Results: 1 passed, 1 failed, 2 filtered out"), "{}", stdout);
+    assert!(stdout.contains("  [slow] 1 passed, 1 failed"));
+    assert!(stdout.contains("  [wip] 0 passed, 1 failed"));
+
+    let (code, stdout) = run(&["--skip", "broken", "--skip", "smoke"]);
+    assert_eq!(code, Some(0), "{}", stdout);
+    assert!(!stdout.contains("Running test: parser::broken"));
+    assert!(stdout.contains("Test Results: 2 passed, 0 failed, 2 filtered out"), "{}", stdout);
+
+    let (code, _) = run(&["--tag"]);
+    assert_eq!(code, Some(2));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,114 @@
+// toml, yaml and config module tests for StelLang
+
+use stellang::lang::hashing::ValueMap;
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn s(text: &str) -> Value {
+    Value::Str(text.to_string())
+}
+
+#[test]
+fn test_toml_loads() {
+    let doc = eval("import toml\ntoml.loads(\"name = 'app'\n[server]\nport = 8080\nhosts = ['a', 'b']\")").unwrap();
+    let server = Value::Dict(ValueMap::from_iter([
+        (s("port"), Value::Int(8080)),
+        (s("hosts"), Value::List(vec![s("a"), s("b")])),
+    ]));
+    assert_eq!(doc, Value::Dict(ValueMap::from_iter([(s("name"), s("app")), (s("server"), server)])));
+    assert_eq!(eval("import toml\ntoml.loads(\"day = 1979-05-27\")[\"day\"]").unwrap(), s("1979-05-27"));
+    assert_eq!(eval("import toml\ntoml.loads(\"a = \")").unwrap_err().kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_toml_dumps_round_trip() {
+    let text = eval("import toml\ntoml.dumps({\"name\": \"app\", \"server\": {\"port\": 80}})").unwrap();
+    assert_eq!(text, s("name = \"app\"\n\n[server]\nport = 80\n"));
+    let round_trip = eval("import toml\ntoml.loads(toml.dumps({\"a\": [1, 2], \"b\": {\"c\": true}}))").unwrap();
+    assert_eq!(round_trip, Value::Dict(ValueMap::from_iter([
+        (s("a"), Value::List(vec![Value::Int(1), Value::Int(2)])),
+        (s("b"), Value::Dict(ValueMap::from_iter([(s("c"), Value::Bool(true))]))),
+    ])));
+    assert_eq!(eval("import toml\ntoml.dumps({\"a\": None})").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("import toml\ntoml.dumps({1: 2})").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_yaml_loads() {
+    let doc = eval("import yaml\nyaml.loads(\"name: app\nports:\n  - 80\n  - 443\nratio: 0.5\nextra: ~\")").unwrap();
+    assert_eq!(doc, Value::Dict(ValueMap::from_iter([
+        (s("name"), s("app")),
+        (s("ports"), Value::List(vec![Value::Int(80), Value::Int(443)])),
+        (s("ratio"), Value::Float(0.5)),
+        (s("extra"), Value::None),
+    ])));
+    assert_eq!(eval("import yaml\nyaml.loads(\"\")").unwrap(), Value::None);
+    assert_eq!(eval("import yaml\nyaml.loads(\"a: [1, 2\")").unwrap_err().kind, ExceptionKind::ValueError);
+}
+
+fn temp_file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path.display().to_string().replace('\\', "/")
+}
+
+#[test]
+fn test_load_env() {
+    std::env::set_var("STELLANG_ENV_TEST_KEPT", "from the environment");
+    let path = temp_file("stellang_load_env_test.env", "# settings\nexport STELLANG_ENV_TEST_HOST=example.org # the host\nSTELLANG_ENV_TEST_URL=\"https://${STELLANG_ENV_TEST_HOST}/\\tx\"\nSTELLANG_ENV_TEST_RAW='${STELLANG_ENV_TEST_HOST}'\nSTELLANG_ENV_TEST_KEPT=from the file\n");
+    let loaded = eval(&format!("import config\nconfig.load_env(\"{}\")", path)).unwrap();
+    assert_eq!(loaded, Value::Dict(ValueMap::from_iter([
+        (s("STELLANG_ENV_TEST_HOST"), s("example.org")),
+        (s("STELLANG_ENV_TEST_URL"), s("https://example.org/\tx")),
+        (s("STELLANG_ENV_TEST_RAW"), s("${STELLANG_ENV_TEST_HOST}")),
+        (s("STELLANG_ENV_TEST_KEPT"), s("from the file")),
+    ])));
+    // Loaded for config.load() in this interpreter and its threads, not into the process environment
+    let read = "config.load({\"host\": \"\", \"kept\": \"\"}, None, \"STELLANG_ENV_TEST_\")";
+    let code = format!("import config\nconfig.load_env(\"{}\")\nfn settings() {{ return {} }}\n[settings(), join(spawn(settings))]", path, read);
+    let settings = Value::Dict(ValueMap::from_iter([(s("host"), s("example.org")), (s("kept"), s("from the environment"))]));
+    assert_eq!(eval(&code).unwrap(), Value::List(vec![settings.clone(), settings]));
+    assert!(std::env::var_os("STELLANG_ENV_TEST_HOST").is_none());
+    assert_eq!(eval(&format!("import config\n{}", read)).unwrap(), Value::Dict(ValueMap::from_iter([(s("host"), s("")), (s("kept"), s("from the environment"))])));
+    // Variables already set win unless asked to override
+    let code = format!("import config\nconfig.load_env(\"{}\", true)\n{}[\"kept\"]", path, read);
+    assert_eq!(eval(&code).unwrap(), s("from the file"));
+    assert_eq!(std::env::var("STELLANG_ENV_TEST_KEPT").unwrap(), "from the environment");
+    let bad = temp_file("stellang_load_env_bad.env", "OK=1\nnot a variable\n");
+    let err = eval(&format!("import config\nconfig.load_env(\"{}\")", bad)).unwrap_err();
+    assert!(err.message().ends_with(":2: expected NAME=value"), "{}", err.message());
+    assert_eq!(eval("import config\nconfig.load_env(\"/nonexistent/.env\")").unwrap_err().kind, ExceptionKind::FileNotFoundError);
+}
+
+#[test]
+fn test_load_layers_defaults_file_and_environment() {
+    let path = temp_file("stellang_config_layers.toml", "port = \"8081\"\ndebug = true\n[db]\nhost = \"db.local\"\n");
+    std::env::set_var("STELLANG_LAYERS_DB__PORT", "6000");
+    std::env::set_var("STELLANG_LAYERS_HOSTS", "a, b");
+    let code = format!("import config\nconfig.load({{\"port\": 8000, \"debug\": false, \"hosts\": [\"localhost\"], \"db\": {{\"host\": \"localhost\", \"port\": 5432}}}}, \"{}\", \"STELLANG_LAYERS_\")", path);
+    assert_eq!(eval(&code).unwrap(), Value::Dict(ValueMap::from_iter([
+        (s("port"), Value::Int(8081)),
+        (s("debug"), Value::Bool(true)),
+        (s("hosts"), Value::List(vec![s("a"), s("b")])),
+        (s("db"), Value::Dict(ValueMap::from_iter([(s("host"), s("db.local")), (s("port"), Value::Int(6000))]))),
+    ])));
+    std::env::set_var("STELLANG_BAD_PORT", "eighty");
+    let err = eval("import config\nconfig.load({\"port\": 80}, None, \"STELLANG_BAD_\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert_eq!(err.message(), "config 'port' expects an int, got eighty from STELLANG_BAD_PORT");
+    assert_eq!(eval("import config\nconfig.load({}, \"settings.ini\")").unwrap_err().kind, ExceptionKind::FileNotFoundError);
+}
@@ -0,0 +1,84 @@
+// Container constructors and operators between container types
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn ints(values: &[i64]) -> Vec<Value> {
+    values.iter().copied().map(Value::Int).collect()
+}
+
+fn type_error(code: &str) -> String {
+    let err = eval(code).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError, "{}", code);
+    err.args.join("")
+}
+
+#[test]
+fn test_constructors() {
+    assert_eq!(eval("list(tuple([1, 2]))"), Ok(Value::List(ints(&[1, 2]))));
+    assert_eq!(eval("tuple([1, 2])"), Ok(Value::Tuple(ints(&[1, 2]))));
+    assert_eq!(eval("set([1, 2, 1])"), Ok(Value::Set(ints(&[1, 2]).into_iter().collect())));
+    assert_eq!(eval("frozenset(\"aa\")"), Ok(Value::FrozenSet([Value::Str("a".to_string())].into_iter().collect())));
+    assert_eq!(eval("list(\"ab\")"), Ok(Value::List(vec![Value::Str("a".to_string()), Value::Str("b".to_string())])));
+    assert_eq!(eval("list()"), Ok(Value::List(Vec::new())));
+    let pairs = [(Value::Str("a".to_string()), Value::Int(1)), (Value::Str("b".to_string()), Value::Int(2))];
+    assert_eq!(eval("dict([[\"a\", 1], tuple([\"b\", 2])])"), Ok(Value::Dict(pairs.into_iter().collect())));
+    assert_eq!(eval("dict({\"a\": 1})"), Ok(Value::Dict([(Value::Str("a".to_string()), Value::Int(1))].into_iter().collect())));
+}
+
+#[test]
+fn test_constructor_errors() {
+    assert_eq!(eval("dict([[1, 2, 3]])").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("dict([1])").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("set([[1]])").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("list(5)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("list([1], [2])").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_tuple_operators() {
+    assert_eq!(eval("tuple([1]) + tuple([2, 3])"), Ok(Value::Tuple(ints(&[1, 2, 3]))));
+    assert_eq!(eval("tuple([1, 2]) * 2"), Ok(Value::Tuple(ints(&[1, 2, 1, 2]))));
+    assert_eq!(eval("2 * tuple([0])"), Ok(Value::Tuple(ints(&[0, 0]))));
+    assert_eq!(eval("tuple([1, 2]) == tuple([1, 2])"), Ok(Value::Bool(true)));
+    assert_eq!(eval("2 in tuple([1, 2])"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_container_equality_and_membership() {
+    assert_eq!(eval("[1, [2]] == [1, [2]]"), Ok(Value::Bool(true)));
+    assert_eq!(eval("{\"a\": 1} != {\"a\": 2}"), Ok(Value::Bool(true)));
+    assert_eq!(eval("[1] in [[1], [2]]"), Ok(Value::Bool(true)));
+    assert_eq!(eval("\"a\" in {\"a\": 1}"), Ok(Value::Bool(true)));
+    assert_eq!(eval("3 not in set([1, 2])"), Ok(Value::Bool(true)));
+}
+
+#[test]
+fn test_mixed_containers_name_both_types() {
+    assert_eq!(type_error("[1] + tuple([2])"), "unsupported operand type(s) for +: 'list' and 'tuple' (convert the tuple with list() first)");
+    assert_eq!(type_error("tuple([1]) + [2]"), "unsupported operand type(s) for +: 'tuple' and 'list' (convert the list with tuple() first)");
+    assert_eq!(type_error("[1] - [1]"), "unsupported operand type(s) for -: 'list' and 'list'");
+    assert_eq!(type_error("{\"a\": 1} + 1"), "unsupported operand type(s) for +: 'dict' and 'int'");
+    assert_eq!(type_error("\"a\" in 5"), "argument of type 'int' is not iterable");
+}
+
+#[test]
+fn test_identifier_keys_and_trailing_commas() {
+    let str = |s: &str| Value::Str(s.to_string());
+    let config = Value::Dict([(str("name"), str("x")), (str("age"), Value::Int(3))].into_iter().collect());
+    assert_eq!(eval("let name = \"ignored\"\n{name: \"x\", age: 3}"), Ok(config.clone()));
+    assert_eq!(eval("{\n    name: \"x\",\n    \"age\": 3,\n}"), Ok(config));
+    // Parentheses make a key an expression again
+    assert_eq!(eval("let k = \"a\"\n{(k): 1}"), Ok(Value::Dict([(str("a"), Value::Int(1))].into_iter().collect())));
+    assert_eq!(eval("[1, 2,]"), Ok(Value::List(ints(&[1, 2]))));
+    assert_eq!(eval("fn add(a, b,) {\n    return a + b\n}\nadd(\n    1,\n    2,\n)"), Ok(Value::Int(3)));
+    for code in ["[,]", "{,}", "print(,)", "[1,,]"] {
+        assert!(Parser::parse_source(code).is_err(), "{}", code);
+    }
+}
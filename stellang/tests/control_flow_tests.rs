@@ -0,0 +1,206 @@
+// return/break/continue tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, native::NativeModule};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    eval_in(&mut Interpreter::new(), code)
+}
+
+#[test]
+fn test_return_from_loop() {
+    let code = r#"
+        fn find(limit) {
+            let i = 0
+            while i < limit {
+                i = i + 1
+                if i == 4 { return i * 10 }
+            }
+            return -1
+        }
+        find(10) + find(2)
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Int(39));
+}
+
+#[test]
+fn test_break_and_continue() {
+    let code = r#"
+        let total = 0
+        let i = 0
+        while True {
+            i = i + 1
+            if i % 2 == 0 { continue }
+            if i > 7 { break }
+            total = total + i
+        }
+        total
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Int(1 + 3 + 5 + 7));
+}
+
+#[test]
+fn test_return_keeps_native_functions() {
+    fn double(args: &[Value]) -> Result<Value, Exception> {
+        match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Ok(Value::None),
+        }
+    }
+    let mut module = NativeModule::new("ops");
+    module.register_fn("double", double);
+    let mut interpreter = Interpreter::new();
+    interpreter.add_native_module(module);
+    // Returned values are passed through as-is, so the native handle survives
+    let code = r#"
+        fn pick() { return ops.double }
+        let f = pick()
+        f(21)
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code).unwrap(), Value::Int(42));
+}
+
+#[test]
+fn test_break_outside_loop() {
+    let err = eval("break").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+    let err = eval("fn f() { continue }\nf()").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+}
+
+#[test]
+fn test_labeled_break_leaves_outer_loop() {
+    let code = r#"
+        let hits = 0
+        let i = 0
+        'outer: while i < 5 {
+            i = i + 1
+            let j = 0
+            while j < 5 {
+                j = j + 1
+                if j == 2 { continue 'outer }
+                if i == 3 { break 'outer }
+                hits = hits + 1
+            }
+        }
+        hits * 10 + i
+    "#;
+    // Each pass of the outer loop counts one hit before `continue 'outer`, until i == 3
+    assert_eq!(eval(code).unwrap(), Value::Int(23));
+}
+
+#[test]
+fn test_labeled_for_loop() {
+    let code = r#"
+        let found = None
+        'rows: for row in [[1, 2], [3, 4], [5, 6]] {
+            for cell in row {
+                if cell % 2 == 0 and cell > 2 {
+                    found = cell
+                    break 'rows
+                }
+            }
+        }
+        found
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Int(4));
+}
+
+#[test]
+fn test_unknown_label() {
+    let err = eval("while True { break 'nowhere }").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+    assert!(err.message().contains("'nowhere"));
+}
+
+#[test]
+fn test_loop_runs_until_break() {
+    let code = r#"
+        let n = 1
+        loop {
+            n = n * 2
+            if n > 100 { break }
+        }
+        n
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Int(128));
+}
+
+#[test]
+fn test_do_while_runs_body_first() {
+    let code = r#"
+        let runs = 0
+        do {
+            runs = runs + 1
+        } while False
+        let i = 0
+        'count: do {
+            i = i + 1
+            if i < 5 { continue 'count }
+        } while i < 10
+        runs * 100 + i
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Int(110));
+}
+
+#[test]
+fn test_elif_chain() {
+    let code = r#"
+        fn grade(score) {
+            if score >= 90 {
+                return "A"
+            } elif score >= 80 {
+                return "B"
+            } else if score >= 70 {
+                return "C"
+            } else {
+                return "F"
+            }
+        }
+        grade(95) + grade(85) + grade(75) + grade(10)
+    "#;
+    assert_eq!(eval(code).unwrap(), Value::Str("ABCF".to_string()));
+}
+
+#[test]
+fn test_for_unpacks_enumerate_and_items() {
+    let code = r#"
+        let names = ""
+        for (i, name) in enumerate(["ada", "bob"]) {
+            names = names + str(i) + name
+        }
+        for i, name in enumerate(["cy"], 1) {
+            names = names + str(i) + name
+        }
+        let total = 0
+        for (key, value) in {"a": 1, "b": 2}.items() {
+            total = total + value
+        }
+        [names, total]
+    "#;
+    assert_eq!(eval(code), Ok(Value::List(vec![Value::Str("0ada1bob1cy".to_string()), Value::Int(3)])));
+}
+
+#[test]
+fn test_for_unpack_errors() {
+    let error = eval("for (a, b) in [tuple([1, 2, 3])] { a }").unwrap_err();
+    assert_eq!((error.kind, error.args), (ExceptionKind::ValueError, vec!["too many values to unpack (expected 2)".to_string()]));
+    let error = eval("for (a, b) in [[1]] { a }").unwrap_err();
+    assert_eq!((error.kind, error.args), (ExceptionKind::ValueError, vec!["not enough values to unpack (expected 2, got 1)".to_string()]));
+    let error = eval("for (a, b) in [1, 2] { a }").unwrap_err();
+    assert_eq!((error.kind, error.args), (ExceptionKind::TypeError, vec!["cannot unpack non-iterable int object".to_string()]));
+}
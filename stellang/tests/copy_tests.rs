@@ -0,0 +1,40 @@
+// copy()/deepcopy() tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+#[test]
+fn test_copies_are_independent() {
+    let code = r#"
+        let original = {"items": [1, 2]}
+        let shallow = copy(original)
+        let deep = deepcopy(original)
+        deep["items"] = [3]
+        [original, shallow, deep]
+    "#;
+    assert_eq!(eval(code).unwrap(), eval(r#"[{"items": [1, 2]}, {"items": [1, 2]}, {"items": [3]}]"#).unwrap());
+}
+
+#[test]
+fn test_deepcopy_refuses_live_resources() {
+    let err = eval("deepcopy([chan()])").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    assert_eq!(err.args, vec!["cannot deep-copy 'channel' object".to_string()]);
+    // A shallow copy shares the channel instead
+    assert_eq!(eval("let ch = chan()\nlet c = copy([ch])\nsend(c[0], 1)\nrecv(ch)").unwrap(), Value::Int(1));
+    assert_eq!(eval("copy()").unwrap_err().kind, ExceptionKind::TypeError);
+}
@@ -0,0 +1,30 @@
+// Crash reports: a panic writes a local report instead of only a message
+
+use std::fs;
+
+use stellang::crash;
+
+#[test]
+fn test_panic_writes_a_crash_report() {
+    let dir = std::env::temp_dir().join("stellang_crash_report");
+    let _ = fs::remove_dir_all(&dir);
+    crash::install("stellang", dir.clone());
+    let script: String = (1..=50).map(|n| format!("print({})\n", n)).collect();
+    crash::set_script("main.stel", &script);
+
+    let worker = std::thread::Builder::new().name("main".to_string()).spawn(|| panic!("scope stack is empty")).unwrap();
+    assert!(worker.join().is_err());
+    let _ = std::panic::take_hook();
+
+    let reports: Vec<_> = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(reports.len(), 1, "{:?}", reports);
+    let name = reports[0].file_name().unwrap().to_str().unwrap().to_string();
+    assert!(name.starts_with("crash-") && name.ends_with(".txt"), "{}", name);
+    let report = fs::read_to_string(&reports[0]).unwrap();
+    assert!(report.contains(&format!("version: stellang {}", env!("CARGO_PKG_VERSION"))), "{}", report);
+    assert!(report.contains(&format!("os: {} {}", std::env::consts::OS, std::env::consts::ARCH)));
+    assert!(report.contains("thread: main") && report.contains("panic: scope stack is empty at tests/crash_tests.rs:"), "{}", report);
+    // The script is quoted up to a limit, with line numbers
+    assert!(report.contains("## Script: main.stel (50 lines)") && report.contains("  40 | print(40)") && !report.contains("print(41)"), "{}", report);
+    assert!(report.contains("| ... 10 more lines") && report.contains("## Backtrace"), "{}", report);
+}
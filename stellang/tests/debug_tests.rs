@@ -0,0 +1,48 @@
+// debug module tests: heap_stats() and track_allocations()
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+#[test]
+fn test_heap_stats_counts_variables_by_type() {
+    let counts = eval("import debug\nlet xs = [1, 2, 3]\nlet stats = debug.heap_stats()\n[stats[\"list\"][\"count\"], stats[\"int\"][\"count\"], stats[\"int\"][\"bytes\"] > 0]").unwrap();
+    assert_eq!(counts, Value::List(vec![Value::Int(1), Value::Int(3), Value::Bool(true)]));
+    // The constants every interpreter starts with are not the program's
+    assert_eq!(eval("import debug\n\"bool\" in debug.heap_stats()").unwrap(), Value::Bool(false));
+}
+
+#[test]
+fn test_track_allocations() {
+    let code = "import debug
+fn build(n) {
+    let out = []
+    let i = 0
+    while i < n {
+        out = out + [i]
+        i = i + 1
+    }
+    return out
+}
+let report = debug.track_allocations(build, 3)
+[report[\"result\"], report[\"retained\"], report[\"allocated\"][\"list\"] >= 4]";
+    let retained = [(Value::Str("list".to_string()), Value::Int(1)), (Value::Str("int".to_string()), Value::Int(3))];
+    let expected = vec![Value::List(vec![Value::Int(0), Value::Int(1), Value::Int(2)]), Value::Dict(retained.into_iter().collect()), Value::Bool(true)];
+    assert_eq!(eval(code).unwrap(), Value::List(expected));
+}
+
+#[test]
+fn test_track_allocations_errors() {
+    let err = eval("import debug\ndebug.track_allocations(missing)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::NameError);
+    let err = eval("import debug\ndebug.track_allocations()").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    // Exceptions from the function propagate
+    let err = eval("import debug\nfn fail() { throw ValueError(\"bad\") }\ndebug.track_allocations(fail)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+}
@@ -0,0 +1,63 @@
+// decimal module tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(&format!("import decimal\nlet D = decimal.Decimal\n{}", code));
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn display(code: &str) -> String {
+    eval(code).unwrap().to_display_string()
+}
+
+#[test]
+fn test_exact_arithmetic() {
+    assert_eq!(eval("D(\"0.1\") + D(\"0.2\") == D(\"0.3\")").unwrap(), Value::Bool(true));
+    assert_eq!(display("D(\"1.10\") + D(\"2.20\")"), "3.30");
+    assert_eq!(display("D(\"19.99\") * 3 - D(\"0.97\")"), "59.00");
+    assert_eq!(display("D(\"-7\") // D(\"2\")"), "-3");
+    assert_eq!(display("D(\"-7\") % D(\"2\")"), "-1");
+    assert_eq!(display("D(2) ** -2"), "0.25");
+    assert_eq!(display("D(0.1)"), "0.1");
+    assert_eq!(eval("D(1) / 0").unwrap_err().kind, ExceptionKind::ZeroDivisionError);
+}
+
+#[test]
+fn test_precision_and_rounding() {
+    assert_eq!(display("D(1) / D(3)"), "0.3333333333333333333333333333");
+    assert_eq!(display("decimal.setprecision(5)\nD(2) / D(3)"), "0.66667");
+    assert_eq!(display("decimal.setprecision(5)\ndecimal.setrounding(decimal.ROUND_DOWN)\nD(2) / D(3)"), "0.66666");
+    assert_eq!(display("decimal.setprecision(3)\ndecimal.getprecision()"), "3");
+    assert_eq!(display("D(\"2.665\").quantize(D(\"0.01\"))"), "2.66");
+    assert_eq!(display("D(\"2.665\").quantize(D(\"0.01\"), decimal.ROUND_HALF_UP)"), "2.67");
+    assert_eq!(display("D(\"5\").quantize(D(\"0.01\"))"), "5.00");
+    assert_eq!(eval("decimal.setprecision(0)").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("decimal.setrounding(\"ROUND_SIDEWAYS\")").unwrap_err().kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_comparisons_and_keys() {
+    assert_eq!(eval("D(\"1.50\") == D(\"1.5\")").unwrap(), Value::Bool(true));
+    assert_eq!(eval("D(3) == 3").unwrap(), Value::Bool(true));
+    assert_eq!(eval("-D(\"5.5\") < 0").unwrap(), Value::Bool(true));
+    assert_eq!(eval("let m = {D(\"1.0\"): \"a\"}\nm[1]").unwrap(), Value::Str("a".to_string()));
+    assert_eq!(display("D(\"1.2300\").normalize()"), "1.23");
+}
+
+#[test]
+fn test_conversion_errors() {
+    assert_eq!(eval("D(\"abc\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("D([1])").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("D(\"1.5\") + 1.5").unwrap_err().kind, ExceptionKind::TypeError);
+}
@@ -0,0 +1,47 @@
+// base64, hex and hash module tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn s(text: &str) -> Value {
+    Value::Str(text.to_string())
+}
+
+#[test]
+fn test_base64() {
+    assert_eq!(eval("import base64\nbase64.encode(\"hello?\")").unwrap(), s("aGVsbG8/"));
+    assert_eq!(eval("import base64\nbase64.urlsafe_encode(\"hello?\")").unwrap(), s("aGVsbG8_"));
+    assert_eq!(eval("import base64\nbase64.decode(\"aGVsbG8/\")").unwrap(), Value::Bytes(b"hello?".to_vec()));
+    assert_eq!(eval("import base64\nbase64.decode(base64.encode(\"hi\")).decode()").unwrap(), s("hi"));
+    assert_eq!(eval("import base64\nbase64.decode(\"!!\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("import base64\nbase64.encode(1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_hex() {
+    assert_eq!(eval("import hex\nhex.encode(\"AZ\")").unwrap(), s("415a"));
+    assert_eq!(eval("import hex\nhex.decode(\"415a\")").unwrap(), Value::Bytes(b"AZ".to_vec()));
+    assert_eq!(eval("import hex\nhex.decode(\"4\")").unwrap_err().kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_hash_digests() {
+    assert_eq!(eval("import hash\nhash.md5(\"abc\")").unwrap(), s("900150983cd24fb0d6963f7d28e17f72"));
+    assert_eq!(eval("import hash\nhash.sha1(\"abc\")").unwrap(), s("a9993e364706816aba3e25717850c26c9cd0d89d"));
+    assert_eq!(eval("import hash\nhash.sha256(\"abc\")").unwrap(), s("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+    assert_eq!(eval("import hash\nimport hex\nhash.sha256(hex.decode(\"616263\"))").unwrap(), eval("import hash\nhash.sha256(\"abc\")").unwrap());
+}
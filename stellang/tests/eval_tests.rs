@@ -0,0 +1,61 @@
+// eval()/exec() tests for StelLang
+
+use stellang::lang::hashing::ValueMap;
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    eval_in(&mut Interpreter::new(), code)
+}
+
+#[test]
+fn test_eval_uses_current_scope() {
+    assert_eq!(eval("let x = 4\neval(\"x * 2 + 1\")").unwrap(), Value::Int(9));
+    assert_eq!(eval("eval(\"\")").unwrap(), Value::None);
+}
+
+#[test]
+fn test_exec_runs_statements() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_in(&mut interpreter, "exec(\"let y = 1; fn inc(n) { return n + 1 }\")").unwrap(), Value::None);
+    assert_eq!(eval_in(&mut interpreter, "inc(y)").unwrap(), Value::Int(2));
+}
+
+#[test]
+fn test_scope_dict() {
+    let mut interpreter = Interpreter::new();
+    // The dict is the only scope the code sees
+    eval_in(&mut interpreter, "let secret = 1\nexec(\"let seen = dir()\", {})").unwrap();
+    assert!(!interpreter.env.contains_key("seen"));
+    assert_eq!(eval_in(&mut interpreter, "let scope = {}\nexec(\"let seen = dir()\", scope)\n\"secret\" in scope[\"seen\"]").unwrap(), Value::Bool(false));
+    assert_eq!(eval_in(&mut interpreter, "eval(\"a + b\", {\"a\": 1, \"b\": 2})").unwrap(), Value::Int(3));
+    // Assignments land in the dict, not in the caller's variables
+    eval_in(&mut interpreter, "let config = {\"base\": 10}\nexec(\"let port = base + 80; base = 0\", config)").unwrap();
+    assert_eq!(eval_in(&mut interpreter, "config").unwrap(), Value::Dict(ValueMap::from_iter([
+        (Value::Str("base".to_string()), Value::Int(0)),
+        (Value::Str("port".to_string()), Value::Int(90)),
+    ])));
+    assert!(!interpreter.env.contains_key("port"));
+}
+
+#[test]
+fn test_eval_errors() {
+    assert_eq!(eval("eval(1)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("eval(\"1\", 2)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("eval(\"1 +\")").unwrap_err().kind, ExceptionKind::SyntaxError);
+    assert_eq!(eval("eval(\"break\")").unwrap_err().kind, ExceptionKind::SyntaxError);
+}
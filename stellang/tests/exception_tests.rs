@@ -2,6 +2,18 @@
 
 use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::Interpreter, exceptions::{Exception, ExceptionKind}};
 
+fn run(code: &str) -> Result<stellang::lang::interpreter::Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+    loop {
+        let tok = lexer.next_token().expect("Lexer error");
+        if tok == stellang::lang::lexer::Token::EOF { break; }
+        tokens.push(tok);
+    }
+    let expr = Parser::new(tokens).parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
 #[test]
 fn test_division_by_zero_exception() {
     let mut lexer = Lexer::new("10 / 0");
@@ -57,3 +69,87 @@ fn test_exception_chaining() {
     assert!(main.cause.is_some());
     assert_eq!(main.cause.unwrap().kind, ExceptionKind::ValueError);
 }
+
+#[test]
+fn test_exception_display() {
+    let mut exc = Exception::new(ExceptionKind::KeyError, vec!["'missing'".to_string()]);
+    exc.add_note("while loading config".to_string());
+    exc.add_frame("inner".to_string());
+    exc.add_frame("outer".to_string());
+    assert_eq!(
+        exc.to_string(),
+        "Traceback (most recent call last):\n  in outer()\n  in inner()\nKeyError: 'missing'\n  note: while loading config"
+    );
+    assert!(stellang::lang::report::render(&exc, true).contains("\x1b[1;31mKeyError"));
+    let warning = Exception::new(ExceptionKind::DeprecationWarning, vec![]);
+    assert_eq!(warning.to_json()["severity"], "warning");
+}
+
+#[test]
+fn test_throw_from_sets_cause() {
+    let err = run(r#"
+        try {
+            throw KeyError("port")
+        } catch e {
+            throw ValueError("bad config") from e
+        }
+    "#).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert_eq!(err.cause.as_ref().unwrap().kind, ExceptionKind::KeyError);
+    assert!(err.suppress_context);
+    let report = err.to_string();
+    assert!(report.starts_with("KeyError: port\n\nThe above exception was the direct cause"));
+    assert!(report.ends_with("ValueError: bad config"));
+}
+
+#[test]
+fn test_error_in_catch_records_context() {
+    let err = run("try { throw TypeError(\"inner\") } catch e { 1 / 0 }").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ZeroDivisionError);
+    assert_eq!(err.context.as_ref().unwrap().kind, ExceptionKind::TypeError);
+    assert!(err.to_string().contains("During handling of the above exception, another exception occurred:"));
+
+    // `from None` keeps the context but leaves it out of the report
+    let err = run("try { throw TypeError(\"inner\") } catch e { throw ValueError(\"outer\") from None }").unwrap_err();
+    assert!(err.context.is_some());
+    assert_eq!(err.to_string(), "ValueError: outer");
+}
+
+#[test]
+fn test_caught_exception_attributes() {
+    let result = run("try { throw ValueError(\"bad\") } catch e { e.kind + \": \" + e.message }").unwrap();
+    assert_eq!(result, stellang::lang::interpreter::Value::Str("ValueError: bad".to_string()));
+}
+
+#[test]
+fn test_finally_runs_after_try_and_catch() {
+    use stellang::lang::interpreter::Value;
+    assert_eq!(run("try { 1 } finally { 2 }"), Ok(Value::Int(1)));
+    assert_eq!(run("let x = 0\ntry { throw ValueError(\"v\") } catch e { x = 1 } finally { x = x + 10 }\nx"), Ok(Value::Int(11)));
+    let err = run("let x = 0\ntry { throw ValueError(\"v\") } finally { x = 1 }").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    // An error in the finally block replaces the one in flight and records it
+    let err = run("try { throw ValueError(\"v\") } finally { throw TypeError(\"t\") }").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    assert_eq!(err.context.as_ref().unwrap().kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_defer_runs_when_the_block_ends() {
+    use stellang::lang::interpreter::Value;
+    let strs = |items: &[&str]| Value::List(items.iter().map(|s| Value::Str(s.to_string())).collect());
+    assert_eq!(run("let out = []\nif true {\n defer out.append(\"first\")\n defer out.append(\"second\")\n out.append(\"body\")\n}\nout"), Ok(strs(&["body", "second", "first"])));
+    let code = "let out = []\ntry {\n if true {\n  defer out.append(\"deferred\")\n  throw ValueError(\"v\")\n }\n} catch e { out.append(e.kind) }\nout";
+    assert_eq!(run(code), Ok(strs(&["deferred", "ValueError"])));
+}
+
+#[test]
+fn test_system_exit_is_catchable() {
+    use stellang::lang::interpreter::Value;
+    assert_eq!(run("try { exit(3) } catch e { e.code }"), Ok(Value::Int(3)));
+    assert_eq!(run("import sys\ntry { sys.exit() } catch e { e.kind }"), Ok(Value::Str("SystemExit".to_string())));
+    assert_eq!(run("try { throw ValueError(\"v\") } catch e { e.code }"), Ok(Value::None));
+    // Uncaught, it unwinds through finally blocks to the caller with its status
+    let err = run("let x = 0\ntry { exit(4) } finally { x = 1 }").unwrap_err();
+    assert_eq!(err.exit_code(), Some(4));
+}
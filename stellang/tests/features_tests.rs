@@ -0,0 +1,86 @@
+// Opt-in language features and deprecation warnings. Both are process-wide,
+// so each test owns one feature or setting and checks it from start to finish.
+
+use stellang::lang::exceptions::ExceptionKind;
+use stellang::lang::features::{self, DeprecationPolicy};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::lexer::{Dialect, LexErrorKind, Lexer};
+use stellang::lang::parser::Parser;
+
+fn run(code: &str) -> Result<Value, stellang::lang::exceptions::Exception> {
+    let expr = Parser::parse_source(code)?.expect("No expression");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_type_annotations_need_the_feature() {
+    let err = run("let n: int = 3").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+    assert_eq!(err.message(), "Type annotations are an unstable feature");
+    assert_eq!(err.notes, ["enable them with --enable-feature type-annotations"]);
+
+    features::enable("type-annotations").unwrap();
+    assert!(features::is_enabled("type-annotations"));
+    assert_eq!(run("let n: int = 3\nconst name: str = \"a\"\nn").unwrap(), Value::Int(3));
+    assert_eq!(run("class Point { }\nlet p: Point = Point()\n1").unwrap(), Value::Int(1));
+    let err = run("let n: int = 1.5").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    assert_eq!(err.message(), "'n' must be 'int', not 'float'");
+}
+
+#[test]
+fn test_unknown_feature() {
+    let err = features::enable("type-anotations").unwrap_err();
+    assert_eq!(err, "unknown feature 'type-anotations' (did you mean 'type-annotations'?); features are: type-annotations");
+}
+
+#[test]
+fn test_deprecation_policy() {
+    let code = "class Animal { }\nclass Dog extends Animal { }\n1";
+    assert_eq!(features::deprecation_policy(), DeprecationPolicy::Warn);
+    assert_eq!(run(code).unwrap(), Value::Int(1));
+
+    features::set_deprecation_policy(DeprecationPolicy::Error);
+    let err = run(code).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::DeprecationWarning);
+    assert_eq!(err.message(), "'class Name extends Base' is deprecated");
+    assert_eq!(err.notes, ["write 'class Name(Base)', which also takes several bases"]);
+    // The replacement is accepted under any policy
+    assert_eq!(run("class Animal { }\nclass Dog(Animal) { }\n1").unwrap(), Value::Int(1));
+
+    features::set_deprecation_policy(DeprecationPolicy::Ignore);
+    assert_eq!(run(code).unwrap(), Value::Int(1));
+    assert_eq!(DeprecationPolicy::parse("error"), Some(DeprecationPolicy::Error));
+    assert_eq!(DeprecationPolicy::parse("loud"), None);
+}
+
+#[test]
+fn test_lang_line_selects_the_dialect() {
+    let dialect = |source: &str| {
+        let mut lexer = Lexer::new(source);
+        lexer.tokenize();
+        lexer.dialect()
+    };
+    assert_eq!(dialect("print(1)"), Dialect::Stel1);
+    assert_eq!(dialect("#lang stel2\nprint(1)"), Dialect::Stel2);
+    assert_eq!(dialect("#!/usr/bin/env stellang\n#lang stel2\nprint(1)"), Dialect::Stel2);
+    // Only the top of the file declares a dialect
+    assert_eq!(dialect("print(1)\n#lang stel2"), Dialect::Stel1);
+
+    let (_, errors) = Lexer::new("#lang stel9\nprint(1)").tokenize();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].kind, LexErrorKind::UnknownDialect("stel9".to_string()));
+    assert_eq!(errors[0].hint().unwrap(), "the dialects are stel1, stel2");
+}
+
+#[test]
+fn test_stel2_drops_deprecated_syntax() {
+    // `extends` is an ordinary name, so old-style subclasses don't parse
+    assert_eq!(run("#lang stel2\nlet extends = 2\nextends").unwrap(), Value::Int(2));
+    assert_eq!(run("#lang stel2\nclass Animal { }\nclass Dog(Animal) { }\n1").unwrap(), Value::Int(1));
+    let err = run("#lang stel2\nclass Animal { }\nclass Dog extends Animal { }").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+    assert_eq!(err.message(), "'class Name extends Base' is not part of #lang stel2");
+    assert_eq!(err.notes, ["write 'class Name(Base)', which also takes several bases"]);
+}
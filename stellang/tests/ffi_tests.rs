@@ -0,0 +1,66 @@
+use stellang::ffi::{stellang_eval, stellang_free, stellang_get_error, stellang_get_string, stellang_new, stellang_string_free, StelStatus};
+use std::ffi::{CStr, CString};
+
+fn take_string(ptr: *mut std::os::raw::c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    let s = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+    unsafe { stellang_string_free(ptr) };
+    Some(s)
+}
+
+#[test]
+fn test_eval_and_get_string() {
+    let interp = stellang_new();
+    let source = CString::new("let x = 40\nx + 2").unwrap();
+    assert_eq!(unsafe { stellang_eval(interp, source.as_ptr()) }, StelStatus::Ok as i32);
+    assert_eq!(take_string(unsafe { stellang_get_string(interp) }), Some("42".to_string()));
+    assert_eq!(take_string(unsafe { stellang_get_error(interp) }), None);
+    unsafe { stellang_free(interp) };
+}
+
+#[test]
+fn test_state_persists_between_calls() {
+    let interp = stellang_new();
+    let define = CString::new("fn double(n) { return n * 2 }").unwrap();
+    let call = CString::new("double(21)").unwrap();
+    assert_eq!(unsafe { stellang_eval(interp, define.as_ptr()) }, StelStatus::Ok as i32);
+    assert_eq!(unsafe { stellang_eval(interp, call.as_ptr()) }, StelStatus::Ok as i32);
+    assert_eq!(take_string(unsafe { stellang_get_string(interp) }), Some("42".to_string()));
+    unsafe { stellang_free(interp) };
+}
+
+#[test]
+fn test_error_codes() {
+    let interp = stellang_new();
+    let runtime = CString::new("1 / 0").unwrap();
+    assert_eq!(unsafe { stellang_eval(interp, runtime.as_ptr()) }, StelStatus::RuntimeError as i32);
+    let message = take_string(unsafe { stellang_get_error(interp) }).unwrap();
+    assert!(message.starts_with("ZeroDivisionError"), "{}", message);
+    let second_line = CString::new("let a = 1\na / 0").unwrap();
+    assert_eq!(unsafe { stellang_eval(interp, second_line.as_ptr()) }, StelStatus::RuntimeError as i32);
+    let message = take_string(unsafe { stellang_get_error(interp) }).unwrap();
+    assert_eq!(message, "ZeroDivisionError: division by zero (line 2)");
+
+    let syntax = CString::new("let = 1").unwrap();
+    assert_eq!(unsafe { stellang_eval(interp, syntax.as_ptr()) }, StelStatus::SyntaxError as i32);
+    assert_eq!(unsafe { stellang_eval(interp, std::ptr::null()) }, StelStatus::InvalidArgument as i32);
+    unsafe { stellang_free(interp) };
+}
+
+#[test]
+fn test_null_interpreter_is_rejected() {
+    let source = CString::new("1").unwrap();
+    assert_eq!(unsafe { stellang_eval(std::ptr::null_mut(), source.as_ptr()) }, StelStatus::InvalidArgument as i32);
+    assert!(unsafe { stellang_get_string(std::ptr::null()) }.is_null());
+    unsafe { stellang_free(std::ptr::null_mut()) };
+}
+
+#[test]
+fn test_header_declares_exports() {
+    let header = include_str!("../include/stellang.h");
+    for name in ["stellang_new", "stellang_eval", "stellang_get_string", "stellang_get_error", "stellang_string_free", "stellang_free"] {
+        assert!(header.contains(&format!("{}(", name)), "{} missing from stellang.h", name);
+    }
+}
@@ -0,0 +1,56 @@
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir().join(format!("stellang_{}_{}", std::process::id(), name)).to_string_lossy().replace('\\', "/")
+}
+
+#[test]
+fn test_with_open_write_then_read() {
+    let path = temp_path("with_rw.txt");
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, &format!(r#"with open("{}", "w") as fh {{ fh.write("hello") }}"#, path)).unwrap();
+    let result = eval_in(&mut interpreter, &format!(r#"with open("{}") as fh {{ fh.read() }}"#, path));
+    assert_eq!(result, Ok(Value::Str("hello".to_string())));
+    assert_eq!(eval_in(&mut interpreter, "fh.closed()"), Ok(Value::Bool(true)));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_with_closes_file_when_body_raises() {
+    let path = temp_path("with_err.txt");
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, &format!(r#"let f = open("{}", "w")"#, path)).unwrap();
+    let result = eval_in(&mut interpreter, "with f { f.write(\"partial\"); 1 / 0 }");
+    assert_eq!(result.unwrap_err().kind, ExceptionKind::ZeroDivisionError);
+    assert_eq!(eval_in(&mut interpreter, "f.closed()"), Ok(Value::Bool(true)));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_open_missing_file() {
+    let mut interpreter = Interpreter::new();
+    let result = eval_in(&mut interpreter, &format!(r#"open("{}")"#, temp_path("missing.txt")));
+    assert_eq!(result.unwrap_err().kind, ExceptionKind::FileNotFoundError);
+}
+
+#[test]
+fn test_with_rejects_plain_values() {
+    let mut interpreter = Interpreter::new();
+    let result = eval_in(&mut interpreter, "with 42 as x { x }");
+    assert_eq!(result.unwrap_err().kind, ExceptionKind::TypeError);
+}
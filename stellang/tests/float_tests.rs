@@ -0,0 +1,63 @@
+// Float formatting, special values and the math module for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value, format_float}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+#[test]
+fn test_float_display() {
+    assert_eq!(format_float(2.0), "2.0");
+    assert_eq!(format_float(-0.0), "-0.0");
+    assert_eq!(format_float(0.1 + 0.2), "0.30000000000000004");
+    assert_eq!(format_float(1e16), "1e+16");
+    assert_eq!(format_float(1.5e-7), "1.5e-07");
+    assert_eq!(format_float(f64::NAN), "nan");
+    assert_eq!(format_float(f64::NEG_INFINITY), "-inf");
+    assert_eq!(eval("4.0 / 2").unwrap().to_display_string(), "2.0");
+}
+
+#[test]
+fn test_exponent_literals() {
+    assert_eq!(eval("1e3").unwrap(), Value::Float(1000.0));
+    assert_eq!(eval("2.5E-3").unwrap(), Value::Float(0.0025));
+    assert_eq!(eval("1e+2").unwrap(), Value::Float(100.0));
+}
+
+#[test]
+fn test_nan_semantics() {
+    assert_eq!(eval("import math\nmath.nan == math.nan").unwrap(), Value::Bool(false));
+    assert_eq!(eval("import math\nmath.nan != math.nan").unwrap(), Value::Bool(true));
+    assert_eq!(eval("import math\nmath.nan < 1.0").unwrap(), Value::Bool(false));
+    assert_eq!(eval("import math\nlet x = math.nan\nx is x").unwrap(), Value::Bool(true));
+    assert_eq!(eval("import math\nmath.nan.is_nan()").unwrap(), Value::Bool(true));
+    assert_eq!(eval("import math\n(-math.inf).is_inf()").unwrap(), Value::Bool(true));
+    assert_eq!(eval("1.5.is_nan()").unwrap(), Value::Bool(false));
+}
+
+#[test]
+fn test_whole_float_keys_match_ints() {
+    assert_eq!(eval("let d = {1: \"a\"}\nd[1.0] = \"b\"\nd[1]").unwrap(), Value::Str("b".to_string()));
+}
+
+#[test]
+fn test_math_module() {
+    assert_eq!(eval("import math\nmath.isclose(0.1 + 0.2, 0.3)").unwrap(), Value::Bool(true));
+    assert_eq!(eval("import math\nmath.isclose(1.0, 1.1)").unwrap(), Value::Bool(false));
+    assert_eq!(eval("import math\nmath.isclose(1.0, 1.1, 0.2)").unwrap(), Value::Bool(true));
+    assert_eq!(eval("import math\nmath.isfinite(math.inf)").unwrap(), Value::Bool(false));
+    assert_eq!(eval("import math\nmath.isclose(1.0, 1.0, -1.0)").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("import math\nmath.isnan(\"x\")").unwrap_err().kind, ExceptionKind::TypeError);
+}
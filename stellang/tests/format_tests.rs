@@ -0,0 +1,208 @@
+// Formatter tests for StelLang, including property-based round trips through the parser
+
+use proptest::prelude::*;
+use stellang::lang::{ast::Expr, format::{format_expr, format_program}, interpreter::Interpreter, parser::Parser, symbol::Symbol};
+
+fn parse(source: &str) -> Expr {
+    Parser::parse_source(source).expect("Parse error").expect("No expression")
+}
+
+fn format_source(source: &str) -> String {
+    format_program(&parse(source))
+}
+
+#[test]
+fn test_formatted_program_is_unchanged() {
+    let source = "fn fib(n) {\n    if n < 2 {\n        return n\n    }\n    return fib(n - 1) + fib(n - 2)\n}\nfib(15)";
+    assert_eq!(format_source(source), source);
+}
+
+#[test]
+fn test_normalizes_spacing_and_separators() {
+    assert_eq!(format_source("let x=1;x=x*2;print( x )"), "let x = 1\nx = x * 2\nprint(x)");
+    assert_eq!(format_source("while true { break }"), "while true {\n    break\n}");
+    assert_eq!(format_source("if a { 1 } elif b { 2 } else { 3 }"), "if a {\n    1\n} else if b {\n    2\n} else {\n    3\n}");
+    assert_eq!(format_source("'outer: for i in xs { continue 'outer }"), "'outer: for i in xs {\n    continue 'outer\n}");
+    assert_eq!(format_source("for k, v in d.items() { k }"), "for (k, v) in d.items() {\n    k\n}");
+    assert_eq!(format_source("@cache(128)  fn f(n) { n }"), "@cache(128)\nfn f(n) {\n    n\n}");
+    assert_eq!(format_source("try { a } finally { defer b }"), "try {\n    a\n} finally {\n    defer b\n}");
+    assert_eq!(format_source("class Shape { abstract fn area(self)\n sides = 0 }"), "class Shape {\n    abstract fn area(self)\n    sides = 0\n}");
+    assert_eq!(format_source("test \"slow\"  tags=[\"a\",\"b\"] { 1 }"), "test \"slow\" tags=[\"a\", \"b\"] {\n    1\n}");
+}
+
+#[test]
+fn test_test_blocks_are_skipped_outside_stel_test() {
+    assert_eq!(evaluate(&parse("let x = 1\ntest \"never\" { x = 2 }\nx")), "Ok(Int(1))");
+    // `test` is still an ordinary name, even before a string on the next line
+    assert_eq!(evaluate(&parse("let test = 3\ntest\n\"s\"\ntest * 2")), "Ok(Int(6))");
+}
+
+#[test]
+fn test_parentheses_follow_precedence() {
+    assert_eq!(format_source("(1 + 2) * 3"), "(1 + 2) * 3");
+    assert_eq!(format_source("1 + (2 * 3)"), "1 + 2 * 3");
+    assert_eq!(format_source("1 - (2 - 3)"), "1 - (2 - 3)");
+    assert_eq!(format_source("(a or b) and not c"), "(a or b) and not c");
+    // `**` associates to the left like every other binary operator
+    assert_eq!(format_source("(2 ** 3) ** 2"), "2 ** 3 ** 2");
+    assert_eq!(format_source("2 ** (3 ** 2)"), "2 ** (3 ** 2)");
+    assert_eq!(format_source("-(2 ** 2)"), "-(2 ** 2)");
+}
+
+#[test]
+fn test_keeps_parentheses_that_change_the_parse() {
+    assert_eq!(format_source("a is (not b)"), "a is (not b)");
+    assert_eq!(format_source("a is not b"), "a is not b");
+    assert_eq!(format_source("(a.b)(1)"), "(a.b)(1)");
+    assert_eq!(format_source("(1).real"), "(1).real");
+    assert_eq!(format_source("f(a, (b = 1))"), "f(a, (b = 1))");
+    assert_eq!(format_source("print(a, sep = \", \")"), "print(a, sep=\", \")");
+    assert_eq!(format_source("x = (if c { 1 })"), "x = (if c {\n    1\n})");
+    assert_eq!(format_source("{(k): 1, k: 2,}"), "{(k): 1, \"k\": 2}");
+}
+
+#[test]
+fn test_format_expr() {
+    assert_eq!(format_expr(&parse("return")), "return");
+    assert_eq!(format_expr(&parse("[1,2][0]")), "[1, 2][0]");
+    assert_eq!(format_expr(&parse("{\"a\":1}")), "{\"a\": 1}");
+}
+
+const IDENTS: &[&str] = &["a", "b", "xs", "total", "outer"];
+
+const BINARY_OPS: &[&str] = &[
+    "or", "and", "==", "!=", "<", ">", "<=", ">=", "is", "is not", "in", "not in",
+    "|", "^", "&", "<<", ">>", "+", "-", "*", "/", "%", "//", "**",
+];
+
+fn ident() -> impl Strategy<Value = Symbol> {
+    prop::sample::select(IDENTS).prop_map(Symbol::intern)
+}
+
+fn literal() -> impl Strategy<Value = Expr> {
+    prop_oneof![
+        (0..1000i64).prop_map(Expr::Integer),
+        prop::sample::select(&[0.5, 1.0, 2.25, 1e16, 1.5e-7][..]).prop_map(Expr::Float),
+        "[a-z \"\\\\\n]{0,4}".prop_map(Expr::String),
+        any::<bool>().prop_map(Expr::Bool),
+        Just(Expr::Null),
+        ident().prop_map(Expr::Ident),
+    ]
+}
+
+fn label() -> impl Strategy<Value = Option<Symbol>> {
+    prop::option::of(ident())
+}
+
+/// Any expression or statement in the form the parser produces it, so that
+/// formatting it and parsing the result must give it back unchanged.
+fn node() -> impl Strategy<Value = Expr> {
+    literal().prop_recursive(4, 48, 3, |inner| {
+        let boxed = inner.clone().prop_map(Box::new);
+        let body = prop::collection::vec(inner.clone(), 0..3).prop_map(|stmts| Box::new(Expr::Block(stmts)));
+        let args = prop::collection::vec(inner.clone(), 0..3);
+        prop_oneof![
+            // Operators are weighted up since precedence is where formatting goes wrong
+            8 => (boxed.clone(), prop::sample::select(BINARY_OPS), boxed.clone())
+                .prop_map(|(left, op, right)| Expr::BinaryOp { left, op: op.to_string(), right }),
+            4 => (prop::sample::select(&["-", "not", "~"][..]), boxed.clone())
+                .prop_map(|(op, expr)| Expr::UnaryOp { op: op.to_string(), expr }),
+            1 => (boxed.clone(), args.clone()).prop_map(|(callable, args)| Expr::FnCall { callable, args }),
+            1 => (boxed.clone(), boxed.clone()).prop_map(|(collection, index)| Expr::Index { collection, index }),
+            1 => (boxed.clone(), ident()).prop_map(|(object, name)| Expr::GetAttr { object, name }),
+            1 => (boxed.clone(), ident(), args.clone()).prop_map(|(object, method, args)| Expr::MethodCall { object, method, args }),
+            1 => args.clone().prop_map(Expr::ArrayLiteral),
+            1 => prop::collection::vec((inner.clone(), inner.clone()), 0..3).prop_map(Expr::MapLiteral),
+            1 => (ident(), boxed.clone()).prop_map(|(name, expr)| Expr::Assign { name, expr }),
+            1 => (boxed.clone(), boxed.clone(), boxed.clone())
+                .prop_map(|(collection, index, expr)| Expr::AssignIndex { collection, index, expr }),
+            1 => (prop::collection::vec(ident(), 2..4), boxed.clone()).prop_map(|(names, expr)| Expr::Destructure { names, expr }),
+            1 => (ident(), boxed.clone()).prop_map(|(name, expr)| Expr::Let { name, expr }),
+            1 => (ident(), boxed.clone()).prop_map(|(name, expr)| Expr::Const { name, expr }),
+            1 => prop::collection::vec(inner.clone(), 1..3).prop_map(Expr::Block),
+            1 => (boxed.clone(), body.clone(), prop::option::of(body.clone()))
+                .prop_map(|(cond, then_branch, else_branch)| Expr::If { cond, then_branch, else_branch }),
+            1 => (boxed.clone(), body.clone(), boxed.clone(), body.clone()).prop_map(|(cond, then_branch, elif, elif_branch)| Expr::If {
+                cond,
+                then_branch,
+                else_branch: Some(Box::new(Expr::If { cond: elif, then_branch: elif_branch, else_branch: None })),
+            }),
+            1 => (boxed.clone(), body.clone(), label()).prop_map(|(cond, body, label)| Expr::While { cond, body, label }),
+            1 => (body.clone(), label()).prop_map(|(body, label)| Expr::Loop { body, label }),
+            1 => (body.clone(), boxed.clone(), label()).prop_map(|(body, cond, label)| Expr::DoWhile { body, cond, label }),
+            1 => (prop::collection::vec(ident(), 1..3), boxed.clone(), body.clone(), label()).prop_map(|(vars, iter, body, label)| Expr::For { vars, iter, body, label }),
+            1 => (ident(), prop::collection::vec(ident(), 0..3), body.clone()).prop_map(|(name, params, body)| Expr::FnDef { name, params, body: (*body).into() }),
+            1 => boxed.clone().prop_map(Expr::Return),
+            1 => label().prop_map(Expr::Break),
+            1 => label().prop_map(Expr::Continue),
+            1 => (body.clone(), prop::option::of((prop::option::of(ident()), body.clone())), prop::option::of(body.clone()))
+                .prop_map(|(try_block, catch, finally_block)| {
+                    // A try needs a catch, a finally or both
+                    let finally_block = finally_block.or_else(|| catch.is_none().then(|| try_block.clone()));
+                    let (catch_var, catch_block) = catch.map_or((None, None), |(var, block)| (var, Some(block)));
+                    Expr::TryCatch { try_block, catch_var, catch_block, finally_block }
+                }),
+            1 => boxed.clone().prop_map(Expr::Defer),
+            1 => (boxed.clone(), prop::option::of(boxed.clone())).prop_map(|(value, cause)| Expr::Throw { value, cause }),
+            1 => (boxed.clone(), prop::option::of(ident()), body.clone()).prop_map(|(resource, alias, body)| Expr::With { resource, alias, body }),
+            1 => (ident(), prop::collection::vec(ident(), 0..3)).prop_map(|(name, fields)| Expr::StructDef { name, fields }),
+            1 => (ident(), prop::collection::vec(ident(), 0..3)).prop_map(|(name, variants)| Expr::EnumDef { name, variants }),
+            1 => prop_oneof![ident().prop_map(String::from), Just("lib/util.stel".to_string()), Just("if".to_string())].prop_map(Expr::Import),
+            1 => Just(Expr::ImportNative("./libdemo.so".to_string())),
+            1 => (ident(), prop::collection::vec(ident(), 0..3), body.clone()).prop_map(|(name, tags, body)| Expr::Test { name: name.into(), tags: tags.into_iter().map(String::from).collect(), body }),
+        ]
+    })
+}
+
+/// A program as `Parser::parse` returns it: one statement, or a `Block` of several.
+fn program() -> impl Strategy<Value = Expr> {
+    prop::collection::vec(node(), 1..4).prop_filter_map("a lone block reads as the program itself", |mut stmts| {
+        if stmts.len() > 1 {
+            Some(Expr::Block(stmts))
+        } else {
+            Some(stmts.remove(0)).filter(|stmt| !matches!(stmt, Expr::Block(_)))
+        }
+    })
+}
+
+/// Source text for arithmetic and logic on constants, written with whatever
+/// spacing and parentheses the strategy picks, leaving precedence to the parser.
+/// Shifts are left out since they overflow on large operands.
+fn arithmetic_source() -> impl Strategy<Value = String> {
+    let atom = prop_oneof![
+        (0..10i64).prop_map(|n| n.to_string()),
+        prop::sample::select(&["0.5", "2.5", "true", "false", "null"][..]).prop_map(str::to_string),
+    ];
+    atom.prop_recursive(4, 16, 2, |inner| {
+        let ops = BINARY_OPS.iter().copied().filter(|op| !matches!(*op, "<<" | ">>")).collect::<Vec<_>>();
+        prop_oneof![
+            (inner.clone(), prop::sample::select(ops), inner.clone()).prop_map(|(left, op, right)| format!("{} {} {}", left, op, right)),
+            (prop::sample::select(&["-", "not ", "~"][..]), inner.clone()).prop_map(|(op, expr)| format!("{}{}", op, expr)),
+            inner.prop_map(|expr| format!("({})", expr)),
+        ]
+    })
+}
+
+/// The value or exception kind a program evaluates to, in a fresh interpreter.
+fn evaluate(program: &Expr) -> String {
+    format!("{:?}", Interpreter::new().eval(program).map_err(|exc| exc.kind))
+}
+
+proptest! {
+    #[test]
+    fn prop_parse_inverts_format(program in program()) {
+        let source = format_program(&program);
+        let reparsed = Parser::parse_source(&source);
+        prop_assert_eq!(reparsed.as_ref().ok().cloned().flatten(), Some(program), "formatted as:\n{}\nparse result: {:?}", source, reparsed.err());
+    }
+
+    #[test]
+    fn prop_format_is_stable(source in arithmetic_source()) {
+        let parsed = parse(&source);
+        let formatted = format_program(&parsed);
+        let reparsed = parse(&formatted);
+        prop_assert_eq!(&reparsed, &parsed, "{} formatted as {}", source, formatted);
+        prop_assert_eq!(format_program(&reparsed), formatted.clone(), "{} first formatted as {}", source, formatted);
+        prop_assert_eq!(evaluate(&reparsed), evaluate(&parsed), "{} formatted as {}", source, formatted);
+    }
+}
@@ -0,0 +1,50 @@
+// Regression tests for inputs the lexer and parser fuzz targets found
+
+use stellang::lang::{exceptions::ExceptionKind, parser::Parser};
+
+/// Minimized crashers from `cargo fuzz run parser`; each once panicked.
+const CRASHERS: &[&str] = &[
+    // A block inside brackets failing to parse left the bracket depth unbalanced
+    "[{x~",
+    "({x~",
+    "f({x ~",
+    "lambda[{lambda~",
+];
+
+#[test]
+fn test_crashers_are_syntax_errors() {
+    for source in CRASHERS {
+        let err = Parser::parse_source(source).expect_err(source);
+        assert_eq!(err.kind, ExceptionKind::SyntaxError, "{}", source);
+    }
+}
+
+#[test]
+fn test_deep_nesting_is_a_syntax_error() {
+    // Parse on a thread with the main thread's stack size, as the CLI does
+    let result = std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(|| {
+            for (open, close) in [("(", ")"), ("[", "]"), ("{", "}"), ("-", ""), ("not ", "")] {
+                let nested = |depth: usize| format!("{}1{}", open.repeat(depth), close.repeat(depth));
+                assert!(Parser::parse_source(&nested(50)).is_ok(), "{}", open);
+                let err = Parser::parse_source(&nested(10_000)).expect_err(open);
+                assert_eq!(err.kind, ExceptionKind::SyntaxError);
+            }
+        })
+        .unwrap()
+        .join();
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_nested_blocks_parse_in_linear_time() {
+    // Each `{` used to be parsed twice, once as a dictionary key and again as a block
+    let source = format!("{}1{}", "{".repeat(40), "}".repeat(40));
+    let result = std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(move || Parser::parse_source(&source).is_ok())
+        .unwrap()
+        .join();
+    assert!(result.unwrap());
+}
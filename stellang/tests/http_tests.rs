@@ -0,0 +1,126 @@
+// http module tests: serve() with a handler function and with routes, and response()
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+use stellang::lang::sandbox::Capabilities;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Run `script` (with `PORT` replaced) while a client sends `requests` to it,
+/// returning the script's result and the raw responses.
+fn serve(script: &str, requests: &[&str]) -> (Result<Value, Exception>, Vec<String>) {
+    let port = free_port();
+    let requests: Vec<String> = requests.iter().map(|request| request.to_string()).collect();
+    let client = thread::spawn(move || {
+        requests
+            .iter()
+            .map(|request| {
+                let mut stream = loop {
+                    match TcpStream::connect(("127.0.0.1", port)) {
+                        Ok(stream) => break stream,
+                        Err(_) => thread::sleep(Duration::from_millis(20)),
+                    }
+                };
+                stream.write_all(request.as_bytes()).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            })
+            .collect::<Vec<String>>()
+    });
+    let result = eval(&script.replace("PORT", &port.to_string()));
+    (result, client.join().unwrap())
+}
+
+#[test]
+fn test_serve_with_a_handler_function() {
+    let script = "import http
+fn handle(request) {
+    return request[\"method\"] + \" \" + request[\"path\"] + \" \" + request[\"query\"][\"q\"] + \" \" + request[\"headers\"][\"x-name\"] + \" \" + request[\"body\"]
+}
+http.serve(\"127.0.0.1:PORT\", handle, 1)";
+    let (result, responses) = serve(script, &["POST /search?q=a+b HTTP/1.1\r\nX-Name: stel\r\nContent-Length: 5\r\n\r\nhello"]);
+    assert_eq!(result.unwrap(), Value::None);
+    assert!(responses[0].starts_with("HTTP/1.1 200 OK\r\n"), "{}", responses[0]);
+    assert!(responses[0].contains("Content-Type: text/plain; charset=utf-8\r\n"));
+    assert!(responses[0].contains("Content-Length: 27\r\n"));
+    assert!(responses[0].ends_with("\r\n\r\nPOST /search a b stel hello"));
+}
+
+#[test]
+fn test_serve_with_routes() {
+    let script = "import http
+fn index() {
+    return http.response(\"<h1>Home</h1>\", 200, {\"Content-Type\": \"text/html\"})
+}
+fn user(request) {
+    return \"user \" + request[\"params\"][\"id\"]
+}
+fn create(request) {
+    return http.response(\"created\", 201)
+}
+fn fail(request) {
+    throw ValueError(\"broken\")
+}
+http.serve(\"127.0.0.1:PORT\", {\"GET /\": \"index\", \"/users/:id\": \"user\", \"POST /users\": \"create\", \"/fail\": \"fail\"}, 6)";
+    let (result, responses) = serve(
+        script,
+        &[
+            "GET / HTTP/1.1\r\n\r\n",
+            "GET /users/a%20b HTTP/1.1\r\n\r\n",
+            "POST /users HTTP/1.1\r\n\r\n",
+            "GET /users HTTP/1.1\r\n\r\n",
+            "GET /missing HTTP/1.1\r\n\r\n",
+            "GET /fail HTTP/1.1\r\n\r\n",
+        ],
+    );
+    assert_eq!(result.unwrap(), Value::None);
+    assert!(responses[0].contains("Content-Type: text/html\r\n") && responses[0].ends_with("<h1>Home</h1>"), "{}", responses[0]);
+    assert!(responses[1].ends_with("user a b"), "{}", responses[1]);
+    assert!(responses[2].starts_with("HTTP/1.1 201 Created\r\n"), "{}", responses[2]);
+    assert!(responses[3].starts_with("HTTP/1.1 405 Method Not Allowed\r\n"), "{}", responses[3]);
+    assert!(responses[4].starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", responses[4]);
+    // A handler that throws answers 500 and the server keeps going
+    assert!(responses[5].starts_with("HTTP/1.1 500 Internal Server Error\r\n"), "{}", responses[5]);
+}
+
+#[test]
+fn test_malformed_request_is_a_bad_request() {
+    let (result, responses) = serve("import http\nfn handle() {\n    return \"ok\"\n}\nhttp.serve(\"127.0.0.1:PORT\", \"handle\", 1)", &["nonsense\r\n\r\n"]);
+    assert_eq!(result.unwrap(), Value::None);
+    assert!(responses[0].starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", responses[0]);
+}
+
+#[test]
+fn test_serve_errors() {
+    let err = eval("import http\nhttp.serve(\"127.0.0.1:0\", missing, 0)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::NameError);
+    let err = eval("import http\nfn handle(a, b) {\n    return a\n}\nhttp.serve(\"127.0.0.1:0\", handle, 0)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+    let err = eval("import http\nfn handle() {\n    return 1\n}\nhttp.serve(\"127.0.0.1:0\", {\"users\": \"handle\"}, 0)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = taken.local_addr().unwrap().port();
+    let err = eval(&format!("import http\nfn handle() {{\n    return 1\n}}\nhttp.serve(\"127.0.0.1:{}\", handle, 0)", port)).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::OSError);
+}
+
+#[test]
+fn test_serve_needs_network_capability() {
+    let expr = Parser::parse_source("import http\nfn handle() {\n    return 1\n}\nhttp.serve(\"127.0.0.1:0\", handle, 0)").unwrap().unwrap();
+    let err = Interpreter::with_capabilities(Capabilities::sandboxed()).eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
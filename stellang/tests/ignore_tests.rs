@@ -0,0 +1,43 @@
+// .stelignore matching: gitignore syntax, relative to the project root
+
+use std::path::Path;
+
+use stellang::ignore::IgnoreRules;
+
+fn ignored(rules: &IgnoreRules, path: &str) -> bool {
+    rules.is_ignored(Path::new(path), path.ends_with('/'))
+}
+
+#[test]
+fn test_ignore_patterns() {
+    let rules = IgnoreRules::parse("# secrets\n*.key\n!public.key\nbuild/\n/src/generated.stel\ndocs/**/*.tmp\n\\#notes\n").unwrap();
+    assert!(ignored(&rules, "server.key") && ignored(&rules, "src/deep/server.key"));
+    assert!(!ignored(&rules, "src/public.key"));
+    // A directory pattern covers everything in the directory, at any depth
+    assert!(ignored(&rules, "src/build/") && ignored(&rules, "src/build/out.stel"));
+    assert!(!ignored(&rules, "src/build"));
+    // Patterns with a slash match from the root only
+    assert!(ignored(&rules, "src/generated.stel") && !ignored(&rules, "lib/src/generated.stel"));
+    assert!(ignored(&rules, "docs/a.tmp") && ignored(&rules, "docs/a/b/c.tmp") && !ignored(&rules, "src/a.tmp"));
+    assert!(ignored(&rules, "#notes") && !ignored(&rules, "main.stel"));
+}
+
+#[test]
+fn test_negation_cannot_reach_into_an_ignored_directory() {
+    let rules = IgnoreRules::parse("secrets/\n!secrets/keep.stel\n").unwrap();
+    assert!(ignored(&rules, "secrets/keep.stel"));
+    let rules = IgnoreRules::parse("*.stel\n!keep.stel\n").unwrap();
+    assert!(!ignored(&rules, "src/keep.stel") && ignored(&rules, "src/drop.stel"));
+}
+
+#[test]
+fn test_invalid_pattern_names_its_line() {
+    let err = IgnoreRules::parse("*.log\n\na**b\n").err().unwrap();
+    assert!(err.starts_with("3: invalid pattern 'a**b'"), "{}", err);
+}
+
+#[test]
+fn test_missing_ignore_file_ignores_nothing() {
+    let rules = IgnoreRules::load(&std::env::temp_dir().join("stel_no_ignore_file")).unwrap();
+    assert!(!ignored(&rules, "src/main.stel"));
+}
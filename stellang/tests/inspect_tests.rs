@@ -0,0 +1,50 @@
+// inspect module tests: signature(), getsource() and stack()
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn string(value: &str) -> Value {
+    Value::Str(value.to_string())
+}
+
+fn frames(names: &[&str]) -> Value {
+    Value::List(names.iter().map(|name| Value::Dict([(string("function"), string(name))].into_iter().collect())).collect())
+}
+
+const GREET: &str = "import inspect\nfn greet(name, punctuation) {\n    return \"Hello, \" + name + punctuation\n}\n";
+
+#[test]
+fn test_signature() {
+    let signature = eval(&format!("{}inspect.signature(greet)", GREET)).unwrap();
+    let expected = [(string("name"), string("greet")), (string("params"), Value::List(vec![string("name"), string("punctuation")]))];
+    assert_eq!(signature, Value::Dict(expected.into_iter().collect()));
+    // A string names the function too, and the function can be bound to a variable first
+    assert_eq!(eval(&format!("{}let sig = inspect.signature\nsig(\"greet\")", GREET)).unwrap(), signature);
+}
+
+#[test]
+fn test_getsource() {
+    let source = eval(&format!("{}inspect.getsource(greet)", GREET)).unwrap();
+    assert_eq!(source, string("fn greet(name, punctuation) {\n    return \"Hello, \" + name + punctuation\n}\n"));
+}
+
+#[test]
+fn test_unknown_function() {
+    let err = eval("import inspect\ninspect.signature(missing)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::NameError);
+    let err = eval("import inspect\ninspect.getsource(42)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_stack() {
+    assert_eq!(eval("import inspect\ninspect.stack()").unwrap(), frames(&["<module>"]));
+    let code = "import inspect\nfn inner() {\n    return inspect.stack()\n}\nfn outer() {\n    return inner()\n}\nouter()";
+    assert_eq!(eval(code).unwrap(), frames(&["inner", "outer", "<module>"]));
+}
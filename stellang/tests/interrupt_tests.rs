@@ -0,0 +1,60 @@
+// Ctrl-C (KeyboardInterrupt) delivery tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, interrupt};
+use std::time::Duration;
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+// The pending flag is process-wide, so every scenario lives in this one test
+// rather than racing other tests in the same binary.
+#[test]
+fn test_keyboard_interrupt() {
+    // An interrupt stops a busy loop at the next checkpoint
+    let mut interpreter = Interpreter::new();
+    let interrupter = std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(50));
+        interrupt::interrupt();
+    });
+    let err = eval_in(&mut interpreter, "while True { 1 }").unwrap_err();
+    interrupter.join().unwrap();
+    assert_eq!(err.kind, ExceptionKind::KeyboardInterrupt);
+
+    // It is raised once, then the interpreter carries on
+    assert!(!interrupt::take());
+    assert_eq!(eval_in(&mut interpreter, "1 + 1").unwrap(), Value::Int(2));
+
+    // Scripts can catch it and clean up
+    let code = r#"
+        cleaned = False
+        try {
+            while True { 1 }
+        } catch e {
+            cleaned = True
+        }
+        cleaned
+    "#;
+    let interrupter = std::thread::spawn(|| {
+        std::thread::sleep(Duration::from_millis(50));
+        interrupt::interrupt();
+    });
+    assert_eq!(eval_in(&mut interpreter, code).unwrap(), Value::Bool(true));
+    interrupter.join().unwrap();
+
+    // take() clears a pending interrupt without raising, as the REPL does on a cancelled line
+    interrupt::interrupt();
+    assert!(interrupt::take());
+    assert_eq!(eval_in(&mut interpreter, "2").unwrap(), Value::Int(2));
+}
@@ -0,0 +1,63 @@
+// Lexer allocation tests: keywords and numbers are read without allocating,
+// names are interned so only the first sight of each allocates, and string
+// literals allocate once each, for the text the AST keeps
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use stellang::lang::lexer::{Lexer, Token};
+
+struct CountingAllocator;
+
+thread_local! {
+    // Counted per thread, so tests running alongside don't disturb the count
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Allocations made while lexing `source` to the end, after the lexer is built
+fn allocations_lexing(source: &str) -> usize {
+    let mut lexer = Lexer::new(source);
+    let before = ALLOCATIONS.with(Cell::get);
+    while lexer.next_token().expect("Lexer error") != Token::EOF {}
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_keywords_and_numbers_do_not_allocate() {
+    assert_eq!(allocations_lexing("while true { if 1 < 2.5e3 { break } else { continue } }"), 0);
+}
+
+#[test]
+fn test_names_seen_before_do_not_allocate() {
+    let source = "fn seen_before(a, b) { return a + b + \"!\" }";
+    allocations_lexing(source);
+    // Only the string
+    assert_eq!(allocations_lexing(source), 1);
+}
+
+#[test]
+fn test_repeated_names_allocate_once() {
+    // One name, however often it appears, costs the same few allocations
+    let once = allocations_lexing("repeated_once");
+    assert!(once > 0);
+    let often = allocations_lexing(&"repeated_often = repeated_often + 1\n".repeat(1000));
+    assert!(often <= once, "{often} > {once}");
+}
@@ -0,0 +1,60 @@
+// locale module tests: numbers, currency and dates per locale
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(&format!("import locale\n{}", code)).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn formatted(code: &str) -> String {
+    match eval(code).unwrap() {
+        Value::Str(s) => s,
+        other => panic!("expected a str, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_format_number() {
+    assert_eq!(formatted("locale.format_number(1234567.89, \"de-DE\")"), "1.234.567,89");
+    assert_eq!(formatted("locale.format_number(1234567.89)"), "1,234,567.89");
+    assert_eq!(formatted("locale.format_number(1234567.891, \"fr-FR\", 2)"), "1\u{202f}234\u{202f}567,89");
+    assert_eq!(formatted("locale.format_number(-1234, \"en_gb\", 2)"), "-1,234.00");
+    // Decimals keep their exact digits
+    assert_eq!(formatted("import decimal\nlocale.format_number(decimal.Decimal(\"9876543.210\"), \"pt-BR\")"), "9.876.543,210");
+}
+
+#[test]
+fn test_minimum_grouping() {
+    // Spanish leaves four-digit numbers ungrouped
+    assert_eq!(formatted("locale.format_number(1234, \"es-ES\")"), "1234");
+    assert_eq!(formatted("locale.format_number(12345, \"es\")"), "12.345");
+}
+
+#[test]
+fn test_format_currency() {
+    assert_eq!(formatted("locale.format_currency(1234.5, \"EUR\", \"de-DE\")"), "1.234,50\u{a0}€");
+    assert_eq!(formatted("locale.format_currency(-1234.5, \"usd\")"), "-$1,234.50");
+    assert_eq!(formatted("locale.format_currency(1234.5, \"JPY\", \"ja-JP\")"), "¥1,234");
+    assert_eq!(formatted("locale.format_currency(3, \"SEK\", \"en-US\")"), "SEK3.00");
+}
+
+#[test]
+fn test_format_date() {
+    assert_eq!(formatted("locale.format_date(\"2024-03-05\", \"en-US\")"), "3/5/2024");
+    assert_eq!(formatted("locale.format_date(\"2024-03-05\", \"en-GB\")"), "05/03/2024");
+    assert_eq!(formatted("locale.format_date(\"2024-03-05T10:30:00\", \"de-DE\", \"long\")"), "5. März 2024");
+    assert_eq!(formatted("locale.format_date(0, \"ja-JP\", \"long\")"), "1970年1月1日");
+}
+
+#[test]
+fn test_errors() {
+    let err = eval("locale.format_number(1, \"de-CH\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert!(err.to_string().contains("locales are: en-US"), "{}", err);
+    assert_eq!(eval("locale.format_number(\"12\")").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("locale.format_date(\"03/05/2024\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("locale.format_date(\"2024-03-05\", \"en-US\", \"medium\")").unwrap_err().kind, ExceptionKind::ValueError);
+}
@@ -0,0 +1,52 @@
+// log module tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+// The logger is shared by the whole process, so everything touching its level
+// or sink runs in this one test
+#[test]
+fn test_levels_and_file_sink() {
+    let path = std::env::temp_dir().join(format!("stellang_{}_app.log", std::process::id()));
+    let path_text = path.to_string_lossy().replace('\\', "/");
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, &format!("import log\nlog.set_file(\"{}\")\nlog.set_level(\"info\")", path_text)).unwrap();
+    eval_in(&mut interpreter, "log.debug(\"hidden\")").unwrap();
+    eval_in(&mut interpreter, "log.info(\"started\", {\"port\": 80, \"host\": \"x\"})").unwrap();
+    eval_in(&mut interpreter, "log.set_level(\"WARNING\")").unwrap();
+    assert_eq!(eval_in(&mut interpreter, "log.level()").unwrap(), Value::Str("warn".to_string()));
+    eval_in(&mut interpreter, "log.info(\"filtered\")").unwrap();
+    eval_in(&mut interpreter, "log.error(\"failed\", 3)").unwrap();
+    eval_in(&mut interpreter, "log.set_file(None)\nlog.set_level(\"info\")").unwrap();
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 2, "unexpected log contents: {}", written);
+    assert!(lines[0].ends_with(" INFO  started host=x port=80"), "{}", lines[0]);
+    assert!(lines[1].ends_with(" ERROR failed 3"), "{}", lines[1]);
+
+    assert_eq!(eval_in(&mut interpreter, "log.set_level(\"loud\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval_in(&mut interpreter, "log.info()").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_sandbox_blocks_log_file() {
+    let mut interpreter = Interpreter::sandboxed();
+    let err = eval_in(&mut interpreter, "import log\nlog.set_file(\"app.log\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
@@ -0,0 +1,99 @@
+// Source module tests: `import` of .stel files next to the script and in a
+// project's dependencies, and `stellang run` for a project
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use stellang::lang::exceptions::ExceptionKind;
+use stellang::lang::interpreter::Interpreter;
+use stellang::lang::modules;
+use stellang::lang::parser::Parser;
+use stellang::lang::sandbox::Capabilities;
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stellang")).args(args).current_dir(dir).output().expect("failed to start stellang")
+}
+
+/// A fresh project with `src/main.stel`, a `src/util.stel` module and an
+/// installed `greet` package.
+fn project(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::create_dir_all(root.join("dependencies/greet/src")).unwrap();
+    fs::write(root.join("stel.toml"), "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+    fs::write(
+        root.join("src/util.stel"),
+        "let unit = \"cm\"
+fn scale(x) {
+    return x * 2
+}
+fn measure(items) {
+    let out = []
+    for item in items {
+        out.append(str(scale(item)) + unit)
+    }
+    return out
+}
+print(\"util loaded\")
+",
+    )
+    .unwrap();
+    fs::write(root.join("dependencies/greet/src/main.stel"), "fn hello(name) {\n    return \"hello \" + name\n}\n").unwrap();
+    fs::write(root.join("src/main.stel"), "import util\nimport greet\nimport sys\nimport util\nprint(util.measure([1, 2]), util.unit, greet.hello(\"ada\"), sys.argv[1])\n").unwrap();
+    root
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_run_finds_the_project_from_a_subdirectory() {
+    let root = project("stellang_modules_run");
+    fs::create_dir_all(root.join("docs")).unwrap();
+    let output = run(&root.join("docs"), &["run", "--deterministic", "fast"]);
+    assert_eq!(stdout(&output), "util loaded\n[2cm, 4cm] cm hello ada fast\nNone\n");
+}
+
+#[test]
+fn test_script_imports_from_its_own_directory() {
+    let root = project("stellang_modules_script");
+    let output = run(&std::env::temp_dir(), &[root.join("src/main.stel").to_str().unwrap(), "slow"]);
+    assert_eq!(stdout(&output), "util loaded\n[2cm, 4cm] cm hello ada slow\nNone\n");
+}
+
+#[test]
+fn test_import_errors() {
+    let root = project("stellang_modules_errors");
+    fs::write(root.join("src/a.stel"), "import b\n").unwrap();
+    fs::write(root.join("src/b.stel"), "import a\n").unwrap();
+    let output = run(&root.join("src"), &["-e", "import a"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ImportError: circular import: a -> b -> a"));
+
+    let output = run(&root.join("src"), &["-e", "import missing"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("ModuleNotFoundError: No module named 'missing'"));
+
+    let output = run(&root.join("src"), &["-e", "import util\nutil.nope()"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("AttributeError: module 'util' has no function 'nope'"));
+}
+
+#[test]
+fn test_run_outside_a_project() {
+    let output = run(Path::new("/"), &["run"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("stellang: run expects a stel.toml"));
+}
+
+#[test]
+fn test_source_import_needs_filesystem_capability() {
+    let root = project("stellang_modules_sandbox");
+    modules::set_search_path(vec![root.join("src")]);
+    let expr = Parser::parse_source("import util").unwrap().unwrap();
+    let err = Interpreter::with_capabilities(Capabilities::sandboxed()).eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
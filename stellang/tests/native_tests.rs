@@ -0,0 +1,81 @@
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, native::{self, NativeModule}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn square(args: &[Value]) -> Result<Value, Exception> {
+    match args {
+        [Value::Int(n)] => Ok(Value::Int(n * n)),
+        _ => Err(Exception::new(ExceptionKind::TypeError, vec!["square() expects an int".to_string()])),
+    }
+}
+
+fn fastmath() -> NativeModule {
+    let mut module = NativeModule::new("fastmath");
+    module.register_fn("square", square);
+    module.register_value("answer", Value::Int(42));
+    module
+}
+
+#[test]
+fn test_host_registered_module() {
+    let mut interpreter = Interpreter::new();
+    interpreter.add_native_module(fastmath());
+    assert_eq!(eval_in(&mut interpreter, "fastmath.square(7)"), Ok(Value::Int(49)));
+    assert_eq!(eval_in(&mut interpreter, r#"fastmath["answer"]"#), Ok(Value::Int(42)));
+    let err = eval_in(&mut interpreter, r#"fastmath.square("x")"#).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_native_function_value_is_callable() {
+    let mut interpreter = Interpreter::new();
+    interpreter.add_native_module(fastmath());
+    let code = r#"
+        let sq = fastmath["square"]
+        sq(5)
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code), Ok(Value::Int(25)));
+}
+
+#[test]
+fn test_import_missing_native_module() {
+    let mut interpreter = Interpreter::new();
+    let err = eval_in(&mut interpreter, r#"import native "does_not_exist""#).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ModuleNotFoundError);
+}
+
+#[test]
+fn test_sandbox_blocks_native_import() {
+    let mut interpreter = Interpreter::sandboxed();
+    let err = eval_in(&mut interpreter, r#"import native "fastmath""#).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_binding_name() {
+    assert_eq!(native::binding_name("fastmath"), "fastmath");
+    assert_eq!(native::binding_name(&format!("build/{}", native::library_filename("fastmath"))), "fastmath");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_library_without_abi_symbols_is_refused() {
+    // Any shared library that isn't a plugin: it exports no ABI version or fingerprint
+    let Some(libc) = ["/lib/x86_64-linux-gnu/libc.so.6", "/lib/aarch64-linux-gnu/libc.so.6", "/usr/lib/libc.so.6", "/lib64/libc.so.6"]
+        .into_iter().find(|path| std::path::Path::new(path).exists()) else { return };
+    let err = native::load(libc).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ImportError);
+}
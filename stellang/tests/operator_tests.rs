@@ -236,6 +236,18 @@ fn test_index_assignment() {
     assert_eq!(interpreter.env.get("my_dict").unwrap().clone(), stellang::lang::interpreter::Value::Dict(vec![("a".to_string(), stellang::lang::interpreter::Value::Int(10)), ("b".to_string(), stellang::lang::interpreter::Value::Int(2))].into_iter().map(|(k,v)| (stellang::lang::interpreter::Value::Str(k),v)).collect()));
 }
 
+#[test]
+fn test_unhashable_dict_keys() {
+    for code in ["{[1]: 2}", "{}[{}]", "let d = {}\nd[[1]] = 2", "{\"a\": 1}.get([1])"] {
+        let err = eval_code(code).unwrap_err();
+        assert_eq!(err.kind, stellang::lang::exceptions::ExceptionKind::TypeError, "{}", code);
+    }
+    assert_eq!(eval_code("{[1]: 2}").unwrap_err().args, vec!["unhashable type: 'list'".to_string()]);
+    let nested = stellang::lang::interpreter::Value::Tuple(vec![stellang::lang::interpreter::Value::Int(1), stellang::lang::interpreter::Value::List(vec![])]);
+    assert!(nested.check_hashable().is_err());
+    assert!(stellang::lang::interpreter::Value::Tuple(vec![stellang::lang::interpreter::Value::Int(1)]).check_hashable().is_ok());
+}
+
 // Helper to convert Lexer output to Vec<Token>
 trait LexerExt {
     fn next_token_stream(&mut self) -> Vec<stellang::lang::lexer::Token>;
@@ -0,0 +1,116 @@
+// Optimizer and build profile tests: constant folding, cfg() and guard resolution
+
+use stellang::lang::{ast::Expr, interpreter::{Interpreter, Value}, optimize::{fold_constants, optimize, resolve_cfg}, parser::Parser};
+
+fn parse(source: &str) -> Expr {
+    Parser::parse_source(source).expect("Parse error").expect("No expression")
+}
+
+fn folded(source: &str) -> Expr {
+    let mut program = parse(source);
+    fold_constants(&mut program);
+    program
+}
+
+fn run(program: &Expr, debug: bool) -> Value {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_debug(debug);
+    interpreter.eval(program).expect("Runtime error")
+}
+
+#[test]
+fn test_folds_literal_operators() {
+    assert_eq!(folded("60 * 60 * 24"), Expr::Integer(86400));
+    assert_eq!(folded("-(2 ** 10)"), Expr::Float(-1024.0));
+    assert_eq!(folded("-(7 // 2)"), Expr::Integer(-3));
+    assert_eq!(folded("\"ab\" + \"cd\""), Expr::String("abcd".to_string()));
+    assert_eq!(folded("not (1 < 2)"), Expr::Bool(false));
+    assert_eq!(folded("1.5 * 2"), Expr::Float(3.0));
+}
+
+#[test]
+fn test_folds_inside_other_expressions() {
+    assert_eq!(folded("x + 2 * 3"), parse("x + 6"));
+    assert_eq!(folded("fn f(n) { return n * (60 * 60) }"), parse("fn f(n) { return n * 3600 }"));
+    assert_eq!(folded("[1 + 1, {\"k\": 2 * 2}]"), parse("[2, {\"k\": 4}]"));
+}
+
+#[test]
+fn test_leaves_what_it_cannot_fold() {
+    // Errors are raised when the code runs, not when it is optimized
+    assert_eq!(folded("1 / 0"), parse("1 / 0"));
+    assert_eq!(folded("\"a\" - 1"), parse("\"a\" - 1"));
+    assert_eq!(folded("x * 2"), parse("x * 2"));
+    assert_eq!(folded("[1] + [2]"), parse("[1] + [2]"));
+    assert_eq!(folded("\"-\" * 1000"), parse("\"-\" * 1000"));
+    // Nor does integer overflow happen at build time
+    for overflowing in ["9223372036854775807 * 2", "9223372036854775807 + 1", "-(-9223372036854775807 - 1)", "1 << 64", "(-9223372036854775807 - 1) // -1"] {
+        assert_eq!(folded(overflowing), fold_all_but_top(overflowing), "{}", overflowing);
+    }
+    let mut program = parse("if False { print(9223372036854775807 * 2) }\n1");
+    optimize(&mut program, false);
+    assert_eq!(run(&program, false), Value::Int(1));
+}
+
+// `source` with its operands folded but not its outermost operator
+fn fold_all_but_top(source: &str) -> Expr {
+    let mut program = parse(source);
+    for child in program.children_mut() {
+        fold_constants(child);
+    }
+    program
+}
+
+#[test]
+fn test_folded_program_gives_same_result() {
+    let source = "fn area(r) { return 3.5 * r * r }\nlet total = 0\nfor i in [1, 2 + 1] { total = total + area(i) * (10 - 4) }\ntotal";
+    assert_eq!(run(&folded(source), true), run(&parse(source), true));
+}
+
+#[test]
+fn test_cfg_debug() {
+    let program = parse("fn mode() {\n    if cfg(\"debug\") { return \"debug\" }\n    return \"release\"\n}\n[mode(), cfg(\"release\"), __debug__, cfg(\"unknown\")]");
+    let expected = |mode: &str, release: bool| Value::List(vec![Value::Str(mode.to_string()), Value::Bool(release), Value::Bool(!release), Value::Bool(false)]);
+    assert_eq!(run(&program, true), expected("debug", false));
+    assert_eq!(run(&program, false), expected("release", true));
+}
+
+#[test]
+fn test_cfg_rejects_non_strings() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval(&parse("cfg(1)")).is_err());
+}
+
+#[test]
+fn test_resolves_cfg_when_loaded() {
+    let other_os = if cfg!(windows) { "linux" } else { "windows" };
+    let mut program = parse(&format!("if cfg(\"{}\") {{\n    a()\n}} else {{\n    b()\n}}", other_os));
+    resolve_cfg(&mut program, true);
+    assert_eq!(program, parse("{\n    b()\n}"));
+
+    let mut program = parse("if cfg(\"debug\") { log() }");
+    resolve_cfg(&mut program, false);
+    assert_eq!(program, Expr::Null);
+
+    // Only literal options are known ahead of time
+    let mut program = parse("cfg(name)");
+    resolve_cfg(&mut program, true);
+    assert_eq!(program, parse("cfg(name)"));
+}
+
+#[test]
+fn test_optimize_settles_negated_guards() {
+    let mut program = parse("if not cfg(\"release\") { check() } else { fast() }");
+    optimize(&mut program, true);
+    assert_eq!(program, parse("{\n    check()\n}"));
+}
+
+#[test]
+fn test_optimized_program_keeps_statement_lines() {
+    // The folded and pruned statements still raise from the lines they were written on
+    let source = "let hour = 60 * 60\nif cfg(\"debug\") {\n    let zero = 1 - 1\n    hour / zero\n}";
+    let mut program = Parser::parse_source_with_lines(source).expect("Parse error").expect("No expression");
+    optimize(&mut program, true);
+    let err = Interpreter::new().eval(&program).unwrap_err();
+    assert_eq!(err.line, Some(4));
+}
@@ -0,0 +1,97 @@
+// stdout capture tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, output::Capture};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_capture_print() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, "print(\"hello\", 1, [2])").unwrap();
+    eval_in(&mut interpreter, "print()").unwrap();
+    assert_eq!(output.contents(), "hello 1 [2]\n\n");
+    assert_eq!(output.take(), "hello 1 [2]\n\n");
+    assert_eq!(output.contents(), "");
+}
+
+#[test]
+fn test_capture_reaches_functions_exec_and_threads() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    let code = r#"
+        fn shout(word) {
+            print(word)
+        }
+        shout("called")
+        exec("print(123)")
+        join(spawn(shout, "threaded"))
+    "#;
+    eval_in(&mut interpreter, code).unwrap();
+    assert_eq!(output.contents(), "called\n123\nthreaded\n");
+}
+
+#[test]
+fn test_print_keyword_options() {
+    let mut interpreter = Interpreter::new();
+    let output = interpreter.capture_stdout();
+    eval_in(&mut interpreter, "print(1, 2, 3, sep=\", \", end=\"\")\nprint(\"!\", end=\"\", flush=true)\nprint(\"a\", \"b\", sep=\"\")").unwrap();
+    assert_eq!(output.contents(), "1, 2, 3!ab\n");
+    let path = std::env::temp_dir().join(format!("stel_print_file_{}.txt", std::process::id()));
+    let code = format!("let f = open(\"{}\", \"w\")\nprint(\"to file\", file=f, flush=true)\nf.close()", path.display());
+    eval_in(&mut interpreter, &code).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "to file\n");
+    std::fs::remove_file(&path).unwrap();
+    eval_in(&mut interpreter, "import sys\nprint(\"via sys\", file=sys.stdout)").unwrap();
+    assert_eq!(output.take(), "1, 2, 3!ab\nvia sys\n");
+}
+
+#[test]
+fn test_print_keyword_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_stdout();
+    for code in ["print(1, sep=2)", "print(1, file=3)", "print(1, color=\"red\")", "len(\"a\", sep=\"\")"] {
+        assert_eq!(eval_in(&mut interpreter, code).unwrap_err().kind, ExceptionKind::TypeError, "{}", code);
+    }
+    let mut lexer = Lexer::new("print(sep=\"\", 1)");
+    let tokens = std::iter::from_fn(|| Some(lexer.next_token().unwrap())).take_while(|tok| *tok != stellang::lang::lexer::Token::EOF).collect();
+    assert_eq!(Parser::new(tokens).parse().unwrap_err().kind, ExceptionKind::SyntaxError);
+}
+
+#[test]
+fn test_set_stdout_with_custom_writer() {
+    let capture = Capture::new();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_stdout(Box::new(capture.clone()));
+    eval_in(&mut interpreter, "print(\"into the sink\")").unwrap();
+    assert_eq!(capture.contents(), "into the sink\n");
+}
+
+#[test]
+fn test_failed_write_raises() {
+    struct Broken;
+    impl std::io::Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pipe closed"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut interpreter = Interpreter::new();
+    interpreter.set_stdout(Box::new(Broken));
+    assert_eq!(eval_in(&mut interpreter, "print(1)").unwrap_err().kind, ExceptionKind::OSError);
+}
@@ -5,9 +5,10 @@ use std::fs;
 fn test_project_init() {
     let test_dir = "test_stel_project";
     let _ = fs::remove_dir_all(test_dir); // Clean up if exists
+    fs::create_dir(test_dir).unwrap();
     let output = Command::new("cargo")
         .args(["run", "--bin", "stel", "--", "init"])
-        .current_dir("./")
+        .current_dir(test_dir)
         .output()
         .expect("failed to run stel init");
     assert!(output.status.success(), "stel init failed: {}", String::from_utf8_lossy(&output.stderr));
@@ -60,4 +61,839 @@ fn test_publish_error_without_auth() {
         .expect("failed to run stel publish");
     assert!(!output.status.success(), "stel publish should fail without auth");
     let _ = fs::remove_dir_all(test_dir);
-} 
\ No newline at end of file
+} 
+// Answer one request with `body` as JSON, standing in for the registry
+fn serve_once(body: &'static str) -> String {
+    serve_once_with_status("200 OK", body)
+}
+
+fn serve_once_with_status(status: &'static str, body: &'static str) -> String {
+    use std::io::{BufRead, BufReader, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+    url
+}
+
+#[test]
+fn test_add_picks_from_search_results() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let test_dir = std::env::temp_dir().join("stel_add_picker");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).unwrap();
+    let stel = env!("CARGO_BIN_EXE_stel");
+    Command::new(stel).arg("init").current_dir(&test_dir).output().unwrap();
+
+    let registry = serve_once(r#"{"total": 3, "packages": [
+        {"name": "http-client", "version": "1.2.0", "description": "Old", "downloads": 40},
+        {"name": "http-client", "version": "1.10.1", "description": "HTTP requests", "downloads": 60},
+        {"name": "http-server", "version": "0.3.0", "description": "Serve HTTP", "downloads": 7}
+    ]}"#);
+    let mut child = Command::new(stel)
+        .args(["add", "http"])
+        .env("STEL_REGISTRY", &registry)
+        .current_dir(&test_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // An answer out of range is asked again
+    child.stdin.take().unwrap().write_all(b"9\n2\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    // Sorted by downloads, with the latest version and its description
+    let listing: Vec<&str> = stdout.lines().filter(|line| line.contains(") http-")).collect();
+    assert!(listing[0].contains("http-client") && listing[0].contains("1.10.1") && listing[0].contains("100"), "{}", stdout);
+    assert!(listing[0].contains("HTTP requests"), "{}", stdout);
+    assert!(stdout.contains("Enter a number from 1 to 2"), "{}", stdout);
+
+    let manifest = fs::read_to_string(test_dir.join("stel.toml")).unwrap();
+    assert!(manifest.contains("http-server = \"^0.3.0\""), "{}", manifest);
+    let _ = fs::remove_dir_all(&test_dir);
+}
+
+#[test]
+fn test_add_exact_match_pins_latest_version() {
+    let test_dir = std::env::temp_dir().join("stel_add_exact");
+    let _ = fs::remove_dir_all(&test_dir);
+    fs::create_dir_all(&test_dir).unwrap();
+    let stel = env!("CARGO_BIN_EXE_stel");
+    Command::new(stel).arg("init").current_dir(&test_dir).output().unwrap();
+
+    let registry = serve_once(r#"{"total": 2, "packages": [
+        {"name": "json", "version": "2.0.0", "downloads": 1},
+        {"name": "json-schema", "version": "0.1.0", "downloads": 9}
+    ]}"#);
+    let output = Command::new(stel).args(["add", "json"]).env("STEL_REGISTRY", &registry).current_dir(&test_dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let manifest = fs::read_to_string(test_dir.join("stel.toml")).unwrap();
+    assert!(manifest.contains("json = \"^2.0.0\""), "{}", manifest);
+    let _ = fs::remove_dir_all(&test_dir);
+}
+
+const HTTP_CLIENT_VERSIONS: &str = r#"{"name": "http-client", "versions": [
+    {"name": "http-client", "version": "1.10.1", "description": "HTTP requests", "authors": ["Ada"], "license": "MIT",
+     "dependencies": {"url": "^2.0", "json": "^1.1"}, "checksum": "sha256:ab", "downloads": 60, "upload_date": "2026-03-04T10:00:00+00:00"},
+    {"name": "http-client", "version": "1.2.0", "description": "Old", "authors": ["Ada"], "license": "MIT",
+     "dependencies": null, "checksum": "sha256:cd", "downloads": 40, "upload_date": "2025-11-20T08:30:00+00:00"}
+]}"#;
+
+#[test]
+fn test_info_shows_versions_and_metadata() {
+    let output = Command::new(env!("CARGO_BIN_EXE_stel"))
+        .args(["info", "http-client"])
+        .env("STEL_REGISTRY", serve_once(HTTP_CLIENT_VERSIONS))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("http-client@1.10.1"), "{}", stdout);
+    assert!(stdout.contains("HTTP requests") && stdout.contains("Ada") && stdout.contains("MIT"), "{}", stdout);
+    assert!(stdout.contains("Downloads:  100"), "{}", stdout);
+    assert!(stdout.contains("json = \"^1.1\"") && stdout.contains("url = \"^2.0\""), "{}", stdout);
+    assert!(stdout.contains("1.2.0   2025-11-20  40 downloads"), "{}", stdout);
+}
+
+#[test]
+fn test_info_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_stel"))
+        .args(["info", "--json", "http-client"])
+        .env("STEL_REGISTRY", serve_once(HTTP_CLIENT_VERSIONS))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).expect("stel info --json printed invalid JSON");
+    assert_eq!(info["name"], "http-client");
+    assert_eq!(info["versions"][0]["version"], "1.10.1");
+    assert_eq!(info["versions"][1]["downloads"], 40);
+    assert_eq!(info["schema_version"], 1);
+}
+
+#[test]
+fn test_registry_falls_back_to_mirrors_in_order() {
+    let home = std::env::temp_dir().join("stel_mirrors_home");
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(&home).unwrap();
+    let mirror = serve_once(HTTP_CLIENT_VERSIONS);
+    // Nothing listens on port 9, so the first mirror fails to connect
+    let config = format!("[registry]\ntimeout = 5\n\n[[registry.mirrors]]\nurl = \"http://127.0.0.1:9\"\n\n[[registry.mirrors]]\nurl = \"{}\"\ntimeout = 2\n", mirror);
+    fs::write(home.join("config.toml"), config).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_stel"))
+        .args(["info", "--json", "http-client"])
+        .env("STEL_HOME", &home)
+        .env("STEL_REGISTRY", serve_once_with_status("503 Service Unavailable", "{}"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).expect("stel info --json printed invalid JSON");
+    assert_eq!(info["versions"][0]["version"], "1.10.1");
+    assert!(stderr.contains("(503 Service Unavailable); trying mirror http://127.0.0.1:9"), "{}", stderr);
+    assert!(stderr.contains(&format!("trying mirror {}", mirror)), "{}", stderr);
+    assert!(stderr.contains(&format!("/api/packages/http-client served by mirror {}", mirror)), "{}", stderr);
+    let _ = fs::remove_dir_all(&home);
+}
+
+// A project declaring two entry points
+fn project_with_bins(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("stel.toml"), r#"
+[package]
+name = "tools"
+version = "0.1.0"
+
+[[bin]]
+name = "greet"
+path = "src/greet.stel"
+
+[[bin]]
+name = "count"
+path = "src/count.stel"
+"#).unwrap();
+    fs::write(dir.join("src/greet.stel"), "print(\"hello from greet\")\n").unwrap();
+    fs::write(dir.join("src/count.stel"), "print(\"count\", 1 + 2)\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_run_and_build_bin_targets() {
+    let dir = project_with_bins("stel_bin_targets");
+    let stel = env!("CARGO_BIN_EXE_stel");
+
+    let output = Command::new(stel).args(["run", "--bin", "count"]).current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("count 3"));
+
+    let output = Command::new(stel).arg("run").current_dir(&dir).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("available: greet, count"));
+
+    let output = Command::new(stel).args(["run", "--bin", "missing"]).current_dir(&dir).output().unwrap();
+    assert!(!output.status.success());
+
+    let output = Command::new(stel).arg("build").current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    fs::write(dir.join("src/count.stel"), "print(\"unterminated)\n").unwrap();
+    let output = Command::new(stel).arg("build").current_dir(&dir).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("count (src/count.stel)") && !stderr.contains("greet"), "{}", stderr);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_global_creates_launchers() {
+    let dir = project_with_bins("stel_install_global");
+    let home = dir.join("home");
+    let output = Command::new(env!("CARGO_BIN_EXE_stel"))
+        .args(["install", "--global"])
+        .env("STEL_HOME", &home)
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // The launchers find stellang on PATH
+    let stellang_dir = std::path::Path::new(env!("CARGO_BIN_EXE_stellang")).parent().unwrap();
+    let mut paths = vec![stellang_dir.to_path_buf()];
+    paths.extend(std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default()));
+    let path = std::env::join_paths(paths).unwrap();
+    for (bin, expected) in [("greet", "hello from greet"), ("count", "count 3")] {
+        let output = Command::new(home.join("bin").join(bin)).env("PATH", &path).current_dir("/").output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).contains(expected));
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_link_local_package() {
+    let root = std::env::temp_dir().join("stel_link");
+    let _ = fs::remove_dir_all(&root);
+    let (library, app, home) = (root.join("mylib"), root.join("app"), root.join("home"));
+    fs::create_dir_all(library.join("src")).unwrap();
+    fs::create_dir_all(&app).unwrap();
+    fs::write(library.join("stel.toml"), "[package]\nname = \"mylib\"\nversion = \"0.3.0-dev\"\n").unwrap();
+    fs::write(library.join("src/lib.stel"), "fn helper() { return 1 }\n").unwrap();
+    fs::write(app.join("stel.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nmylib = \"^0.2\"\n").unwrap();
+
+    let stel = |dir: &std::path::Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_stel"))
+            .args(args)
+            .env("STEL_HOME", &home)
+            // Nothing listens here, so any registry request fails fast
+            .env("STEL_REGISTRY", "http://127.0.0.1:9")
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "stel {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    };
+    stel(&library, &["link"]);
+    stel(&app, &["link", "mylib"]);
+    assert!(app.join("dependencies/mylib").is_symlink());
+    assert!(app.join("dependencies/mylib/src/lib.stel").exists());
+    assert!(fs::read_to_string(app.join(".stel/overrides.toml")).unwrap().contains("mylib"));
+
+    // Installing uses the linked directory without asking the registry
+    fs::remove_file(app.join("dependencies/mylib")).unwrap();
+    stel(&app, &["install"]);
+    assert!(app.join("dependencies/mylib").is_symlink());
+    let lock = fs::read_to_string(app.join("stel.lock")).unwrap();
+    assert!(lock.contains("0.3.0-dev") && lock.contains("path+"), "{}", lock);
+
+    stel(&app, &["unlink", "mylib"]);
+    assert!(!app.join("dependencies/mylib").exists());
+    assert!(library.join("src/lib.stel").exists());
+    assert!(!fs::read_to_string(app.join(".stel/overrides.toml")).unwrap().contains("mylib"));
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_build_and_run_profiles() {
+    let dir = std::env::temp_dir().join("stel_profiles");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"profiles\"\nversion = \"0.1.0\"\n").unwrap();
+    fs::write(dir.join("src/main.stel"), "print(\"debug is\", cfg(\"debug\"), 60 * 60)\n").unwrap();
+    let stel = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(args).current_dir(&dir).output().unwrap();
+        assert!(output.status.success(), "stel {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let stdout = stel(&["run"]);
+    assert!(stdout.contains("dev [unoptimized, debug assertions]") && stdout.contains("debug is true 3600"), "{}", stdout);
+    assert!(!dir.join(".stel/cache/build").exists());
+
+    let stdout = stel(&["build", "--release"]);
+    assert!(stdout.contains("release [optimized, cached]"), "{}", stdout);
+    let cached: Vec<_> = fs::read_dir(dir.join(".stel/cache/build/release")).unwrap().collect();
+    assert_eq!(cached.len(), 1);
+    assert!(stel(&["run", "--release"]).contains("debug is false 3600"));
+
+    // A changed source is parsed again rather than taken from the cache
+    fs::write(dir.join("src/main.stel"), "print(\"changed\", cfg(\"release\"))\n").unwrap();
+    assert!(stel(&["run", "--release"]).contains("changed true"));
+
+    // Profile settings in stel.toml override the defaults
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"profiles\"\nversion = \"0.1.0\"\n\n[profile.release]\ndebug_assertions = true\ncache = false\n").unwrap();
+    let stdout = stel(&["run", "--release"]);
+    assert!(stdout.contains("release [optimized, debug assertions]") && stdout.contains("changed false"), "{}", stdout);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_size_clean_and_eviction() {
+    let dir = std::env::temp_dir().join("stel_cache");
+    let _ = fs::remove_dir_all(&dir);
+    let cache = dir.join(".stel/cache");
+    fs::create_dir_all(cache.join("build/dev")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"cached\"\nversion = \"0.1.0\"\n").unwrap();
+    // Two packages and a build file, each last used a day later than the one before
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    let old = std::time::SystemTime::now() - day * 60;
+    for (i, (entry, size)) in [("http-client-1.2.0", 3000), ("json-0.1.0", 2000), ("build/dev/main.ast", 500)].into_iter().enumerate() {
+        let path = cache.join(entry);
+        if entry.starts_with("build") {
+            fs::write(&path, vec![0; size]).unwrap();
+        } else {
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("main.stel"), vec![0; size]).unwrap();
+        }
+        fs::File::open(&path).unwrap().set_modified(old + day * i as u32).unwrap();
+    }
+    let stel = |args: &[&str], max_size: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(args).env("STEL_CACHE_MAX_SIZE", max_size).current_dir(&dir).output().unwrap();
+        assert!(output.status.success(), "stel {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(stel(&["cache", "dir"], "1GB").trim().ends_with(".stel/cache"));
+    assert_eq!(stel(&["cache", "size"], "1GB").trim(), "5.4 KB in 2 package(s) and 1 build file(s) (limit 1.0 GB)");
+
+    // Installing trims the cache to its limit, least recently used first
+    let stdout = stel(&["install"], "3k");
+    assert!(stdout.contains("Evicted http-client-1.2.0") && !stdout.contains("json"), "{}", stdout);
+    assert!(!cache.join("http-client-1.2.0").exists() && cache.join("json-0.1.0").exists());
+
+    assert!(stel(&["cache", "clean", "--older-than", "60d"], "1GB").contains("Removed 0 cache entries"));
+    assert!(stel(&["cache", "clean", "--package", "json"], "1GB").contains("Removed 1 cache entry"));
+    assert!(cache.join("build/dev/main.ast").exists());
+    stel(&["cache", "clean"], "1GB");
+    assert!(!cache.join("build/dev/main.ast").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(["cache", "clean", "--older-than", "soon"]).current_dir(&dir).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_publish_lints_metadata() {
+    let dir = std::env::temp_dir().join("stel_publish_lint");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = "[package]
+name = \"linted\"
+version = \"0.1.0\"
+license = \"MIT OR Apache-2.0 WITH LLVM-exception\"
+description = \"A package\"
+repository = \"https://example.com/linted\"
+keywords = [\"json\", \"Not A Keyword\"]
+";
+    fs::write(dir.join("stel.toml"), manifest).unwrap();
+    let publish = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_stel")).arg("publish").args(args).current_dir(&dir).output().unwrap();
+        assert!(!output.status.success());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let stderr = publish(&[]);
+    assert!(stderr.contains("error: keywords: keyword 'Not A Keyword'") && stderr.contains("warning: readme: no README.md"), "{}", stderr);
+    assert!(stderr.contains("Not publishing: 1 metadata error(s)") && !stderr.contains("license"), "{}", stderr);
+
+    // Allowed checks are skipped, and the upload goes ahead (here, as far as the login check)
+    let stderr = publish(&["--allow", "keywords", "--allow=readme"]);
+    assert!(stderr.contains("Not logged in") && !stderr.contains("warning"), "{}", stderr);
+
+    fs::write(dir.join("stel.toml"), manifest.replace("MIT OR", "(MIT OR Proprietary)").replace("https://example.com/linted", "example.com")).unwrap();
+    let stderr = publish(&["--allow", "keywords"]);
+    assert!(stderr.contains("'Proprietary' is not a known SPDX license identifier") && stderr.contains("repository 'example.com' is not an http(s) URL"), "{}", stderr);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// A package archive holding `src/main.stel`
+fn package_archive(source: &str) -> Vec<u8> {
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    let mut header = tar::Header::new_gnu();
+    header.set_size(source.len() as u64);
+    header.set_mode(0o644);
+    tar.append_data(&mut header, "src/main.stel", source.as_bytes()).unwrap();
+    tar.into_inner().unwrap().finish().unwrap()
+}
+
+// A package archive like `package_archive`, with `manifest` as its stel.toml
+fn package_archive_with_manifest(manifest: &str, source: &str) -> Vec<u8> {
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    for (path, contents) in [("stel.toml", manifest), ("src/main.stel", source)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, path, contents.as_bytes()).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap()
+}
+
+// The `[package]` fields the registry publishes from an archive's stel.toml
+fn archive_toolchain(archive: &[u8]) -> (Option<String>, Option<String>) {
+    use std::io::Read;
+
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    for entry in tar.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        if entry.path().unwrap().to_str() == Some("stel.toml") {
+            let mut manifest = String::new();
+            entry.read_to_string(&mut manifest).unwrap();
+            let manifest: toml::Value = toml::from_str(&manifest).unwrap();
+            let field = |name: &str| manifest["package"].get(name).and_then(|value| value.as_str()).map(str::to_string);
+            return (field("stellang"), field("edition"));
+        }
+    }
+    (None, None)
+}
+
+// A registry serving info, version listings and archives of `(name, version,
+// archive)` packages, honouring byte ranges. Returns its URL and the requests
+// it gets, each as the path and the offset of the range asked for.
+fn serve_packages(packages: Vec<(&'static str, &'static str, Vec<u8>)>) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    serve_packages_slowly(packages, std::time::Duration::ZERO)
+}
+
+// `serve_packages`, sending each archive in four pieces `gap` apart
+fn serve_packages_slowly(packages: Vec<(&'static str, &'static str, Vec<u8>)>, gap: std::time::Duration) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    use std::io::{BufRead, BufReader, Write};
+    use sha2::Digest;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let registry = format!("http://{}", listener.local_addr().unwrap());
+    let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = std::sync::Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let path = request.split(' ').nth(1).unwrap().to_string();
+            let mut offset = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                    offset = range.trim().trim_end_matches('-').parse().unwrap();
+                }
+                line.clear();
+            }
+            seen.lock().unwrap().push(format!("{} {}", path, offset));
+            let info = |name: &str, version: &str, archive: &[u8]| {
+                let (stellang, edition) = archive_toolchain(archive);
+                let checksum = hex::encode(sha2::Sha256::digest(archive));
+                serde_json::json!({"name": name, "version": version, "description": null, "authors": null, "dependencies": {}, "download_url": "", "checksum": format!("sha256:{}", checksum), "stellang": stellang, "edition": edition})
+            };
+            let listed: Vec<serde_json::Value> = packages.iter().filter(|(name, _, _)| path == format!("/api/packages/{}", name)).map(|(name, version, archive)| info(name, version, archive)).collect();
+            if !listed.is_empty() {
+                let body = serde_json::json!({"name": path.trim_start_matches("/api/packages/"), "versions": listed}).to_string();
+                let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                continue;
+            }
+            let Some((name, version, archive)) = packages.iter().find(|(name, version, _)| path.starts_with(&format!("/api/packages/{}/{}", name, version))) else {
+                let _ = write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                continue;
+            };
+            let (status, body) = if path.ends_with("/download") {
+                let status = if offset > 0 { format!("206 Partial Content\r\nContent-Range: bytes {}-{}/{}", offset, archive.len() - 1, archive.len()) } else { "200 OK".to_string() };
+                (status, archive[offset..].to_vec())
+            } else {
+                ("200 OK".to_string(), info(name, version, archive).to_string().into_bytes())
+            };
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+            if gap.is_zero() || !path.ends_with("/download") {
+                let _ = stream.write_all(&body);
+                continue;
+            }
+            for piece in body.chunks(body.len().div_ceil(4)) {
+                let _ = stream.write_all(piece).and_then(|()| stream.flush());
+                std::thread::sleep(gap);
+            }
+        }
+    });
+    (registry, requests)
+}
+
+#[test]
+fn test_install_resumes_partial_downloads_in_parallel() {
+    let dir = std::env::temp_dir().join("stel_resume");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join(".stel/cache")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"resumed\"\nversion = \"0.1.0\"\n\n[dependencies]\na = \"1.0.0\"\nb = \"2.0.0\"\n").unwrap();
+    let a = package_archive("fn a() {\n    return 1\n}\n");
+    // An earlier install stopped partway through a's download
+    fs::write(dir.join(".stel/cache/a-1.0.0.tar.gz.part"), &a[..10]).unwrap();
+    let (registry, requests) = serve_packages(vec![("a", "1.0.0", a), ("b", "2.0.0", package_archive("fn b() {\n    return 2\n}\n"))]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(["install", "--jobs", "2"]).env("STEL_REGISTRY", &registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}{}", stdout, String::from_utf8_lossy(&output.stderr));
+    let requests = requests.lock().unwrap();
+    assert!(requests.contains(&"/api/packages/a/1.0.0/download 10".to_string()) && requests.contains(&"/api/packages/b/2.0.0/download 0".to_string()), "{:?}", requests);
+    assert!(stdout.contains("Downloaded a@1.0.0 (") && stdout.contains(&format!("from {}", registry)), "{}", stdout);
+    assert!(stdout.contains("Downloaded b@2.0.0"), "{}", stdout);
+    assert_eq!(fs::read_to_string(dir.join("dependencies/a/src/main.stel")).unwrap(), "fn a() {\n    return 1\n}\n");
+    assert_eq!(fs::read_to_string(dir.join("dependencies/b/src/main.stel")).unwrap(), "fn b() {\n    return 2\n}\n");
+    assert!(!dir.join(".stel/cache/a-1.0.0.tar.gz.part").exists() && !dir.join(".stel/cache/a-1.0.0.tar.gz").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(["install", "--jobs", "0"]).current_dir(&dir).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_projects_share_packages_in_the_store() {
+    use std::os::unix::fs::MetadataExt;
+
+    let root = std::env::temp_dir().join("stel_store");
+    let _ = fs::remove_dir_all(&root);
+    let (registry, requests) = serve_packages(vec![("shared", "1.2.0", package_archive("fn shared() {\n    return 3\n}\n"))]);
+    let install = |project: &str| {
+        let dir = root.join(project);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stel.toml"), format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\nshared = \"1.2.0\"\n", project)).unwrap();
+        let output = Command::new(env!("CARGO_BIN_EXE_stel")).arg("install").env("STEL_REGISTRY", &registry).env("STEL_HOME", root.join("home")).current_dir(&dir).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        fs::metadata(dir.join("dependencies/shared/src/main.stel")).unwrap()
+    };
+
+    let first = install("one");
+    let second = install("two");
+    // Both projects link the one stored copy, which only the first install downloaded
+    let mut stored: Vec<_> = fs::read_dir(root.join("home/store")).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+    stored.sort();
+    assert_eq!(stored.len(), 2, "{:?}", stored);
+    assert!(stored[0].starts_with("shared-1.2.0-") && stored[1] == format!("{}.tar.gz", stored[0]), "{:?}", stored);
+    assert_eq!((first.dev(), first.ino()), (second.dev(), second.ino()));
+    assert_eq!(second.nlink(), 3);
+    assert_eq!(requests.lock().unwrap().iter().filter(|request| request.ends_with("/download 0")).count(), 1);
+    // A project can't change the files the other one sees
+    assert!(second.permissions().readonly());
+
+    // Pruning keeps a package while any project links it
+    let prune = || {
+        let output = Command::new(env!("CARGO_BIN_EXE_stel")).args(["cache", "prune"]).env("STEL_HOME", root.join("home")).current_dir(&root).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        fs::read_dir(root.join("home/store")).unwrap().count()
+    };
+    fs::remove_dir_all(root.join("one/dependencies")).unwrap();
+    assert_eq!(prune(), 2);
+    fs::remove_dir_all(root.join("two/dependencies")).unwrap();
+    assert_eq!(prune(), 0);
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_build_script_output_is_importable_and_cached() {
+    let dir = std::env::temp_dir().join("stel_build_script");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"generated\"\nversion = \"0.1.0\"\nbuild = \"build.stel\"\n").unwrap();
+    fs::write(dir.join("colors.txt"), "red").unwrap();
+    // Turns colors.txt into a module defining favorite()
+    fs::write(
+        dir.join("build.stel"),
+        "let color = open(PACKAGE_DIR + \"/colors.txt\").read()\nlet out = open(OUT_DIR + \"/palette.stel\", \"w\")\nout.write(\"fn favorite() {\\n    return \\\"\" + color + \"\\\"\\n}\\n\")\nout.close()\n",
+    )
+    .unwrap();
+    fs::write(dir.join("src/main.stel"), "import palette\nprint(palette.favorite())\n").unwrap();
+    let stel = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_stel")).args(args).current_dir(&dir).output().unwrap();
+    let run = || {
+        let output = stel(&["run"]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        (String::from_utf8_lossy(&output.stdout).into_owned(), String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+
+    let (stdout, stderr) = run();
+    assert!(stderr.contains("Running build script build.stel of generated") && stdout.contains("red\n"), "{}", stdout);
+    // Unchanged inputs reuse the output; a changed file runs the script again
+    let (stdout, stderr) = run();
+    assert!(!stderr.contains("Running build script") && stdout.contains("red\n"), "{}", stdout);
+    fs::write(dir.join("colors.txt"), "blue").unwrap();
+    let (stdout, stderr) = run();
+    assert!(stderr.contains("Running build script") && stdout.contains("blue\n"), "{}", stdout);
+
+    // Only OUT_DIR is writable
+    fs::write(dir.join("build.stel"), "open(PACKAGE_DIR + \"/colors.txt\", \"w\").write(\"green\")\n").unwrap();
+    let output = stel(&["build"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Build script build.stel of generated failed: Traceback (most recent call last):\n  at ./build.stel:1\nPermissionError"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(dir.join("colors.txt")).unwrap(), "blue");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_install_refuses_packages_with_symlinks() {
+    let dir = std::env::temp_dir().join("stel_symlinked_package");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"linking\"\nversion = \"0.1.0\"\n\n[dependencies]\nsneaky = \"1.0.0\"\n").unwrap();
+    let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()));
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    tar.append_link(&mut header, "src/main.stel", "/etc/passwd").unwrap();
+    let (registry, _) = serve_packages(vec![("sneaky", "1.0.0", tar.into_inner().unwrap().finish().unwrap())]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).arg("install").env("STEL_REGISTRY", &registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("link or special file at src/main.stel"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!dir.join("dependencies/sneaky/src/main.stel").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_verify_reports_modified_packages() {
+    let dir = std::env::temp_dir().join("stel_verify");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"verified\"\nversion = \"0.1.0\"\n\n[dependencies]\nintact = \"1.0.0\"\ntouched = \"1.0.0\"\n").unwrap();
+    let (registry, _) = serve_packages(vec![("intact", "1.0.0", package_archive("fn intact() {\n    return 1\n}\n")), ("touched", "1.0.0", package_archive("fn touched() {\n    return 2\n}\n"))]);
+    let stel = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_stel")).args(args).env("STEL_REGISTRY", &registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    let output = stel(&["install"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(fs::read_to_string(dir.join("stel.lock")).unwrap().contains("integrity = "));
+
+    let output = stel(&["verify"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Verified 2 package(s)"));
+
+    // Replaced rather than edited in place, so the package store keeps the original
+    let source = dir.join("dependencies/touched/src/main.stel");
+    fs::remove_file(&source).unwrap();
+    fs::write(&source, "fn touched() {\n    return 3\n}\n").unwrap();
+    let output = stel(&["verify"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("✓ intact@1.0.0") && stdout.contains("✗ touched@1.0.0: files were modified or corrupted"), "{}", stdout);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 2 package(s) failed verification"));
+
+    // A changed store entry is caught before it's linked again, and downloaded afresh
+    let stored = fs::read_dir(dir.join("home/store")).unwrap().map(|entry| entry.unwrap().path()).find(|path| path.is_dir() && path.file_name().unwrap().to_string_lossy().starts_with("touched-")).unwrap();
+    fs::remove_file(stored.join("src/main.stel")).unwrap();
+    fs::write(stored.join("src/main.stel"), "fn touched() {\n    return 4\n}\n").unwrap();
+    let output = stel(&["install"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("from the package store: its files were modified"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(&source).unwrap(), "fn touched() {\n    return 2\n}\n");
+    assert!(stel(&["verify"]).status.success());
+
+    // The integrity hashes in stel.lock are checked on install, never replaced
+    let lock = fs::read_to_string(dir.join("stel.lock")).unwrap();
+    let at = lock.find("integrity = \"").unwrap() + "integrity = \"".len();
+    let forged = format!("{}{}{}", &lock[..at], "0".repeat(64), &lock[at + 64..]);
+    fs::write(dir.join("stel.lock"), &forged).unwrap();
+    let output = stel(&["install"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("don't match the integrity hash in stel.lock"), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(fs::read_to_string(dir.join("stel.lock")).unwrap(), forged);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_format_json_output() {
+    let dir = std::env::temp_dir().join("stel_format_json");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::create_dir_all(dir.join("tests")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"reported\"\nversion = \"0.1.0\"\n\n[dependencies]\nlib = \"1.0.0\"\n").unwrap();
+    fs::write(dir.join("src/main.stel"), "print(1)\n").unwrap();
+    fs::write(dir.join("tests/checks.stel"), "test \"fine\" {\n    assert_eq(1, 1)\n}\ntest \"broken\" {\n    print(\"noise\")\n    assert_eq(1, 2)\n}\n").unwrap();
+    let (registry, _) = serve_packages(vec![("lib", "1.0.0", package_archive("fn lib() {\n    return 1\n}\n"))]);
+    let stel = |args: &[&str], registry: &str| Command::new(env!("CARGO_BIN_EXE_stel")).args(args).env("STEL_REGISTRY", registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    assert!(stel(&["install"], &registry).status.success());
+    // Each command prints one JSON document with the schema version, and nothing else
+    let json = |args: &[&str], registry: &str| {
+        let output = stel(args, registry);
+        let document: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_else(|e| panic!("stel {:?} printed invalid JSON ({}): {}", args, e, String::from_utf8_lossy(&output.stdout)));
+        assert_eq!(document["schema_version"], 1, "{:?}", args);
+        (output.status.code(), document)
+    };
+
+    let (code, build) = json(&["build", "--format", "json"], &registry);
+    assert_eq!(code, Some(0));
+    assert_eq!((build["success"].as_bool(), build["profile"].as_str(), build["targets"][0]["path"].as_str()), (Some(true), Some("dev"), Some("src/main.stel")));
+
+    let (code, test) = json(&["test", "--format=json"], &registry);
+    assert_eq!(code, Some(1));
+    assert_eq!((test["passed"].as_u64(), test["failed"].as_u64()), (Some(1), Some(1)));
+    let broken = &test["tests"][1];
+    assert_eq!((broken["name"].as_str(), broken["passed"].as_bool(), broken["output"].as_str()), (Some("checks::broken"), Some(false), Some("noise\n")));
+    // The test file and line the assertion failed on
+    assert!(broken["error"].as_str().unwrap().starts_with("Traceback (most recent call last):\n  at tests/checks.stel:6\nAssertionError"), "{}", broken);
+
+    let (_, tree) = json(&["tree", "--format", "json"], &registry);
+    assert_eq!(tree["dependencies"][0], serde_json::json!({ "name": "lib", "requirement": "1.0.0", "dev": false, "locked": "1.0.0", "linked": false }));
+    let (_, outdated) = json(&["outdated", "--format", "json"], &registry);
+    assert_eq!((outdated["dependencies"][0]["status"].as_str(), outdated["outdated"].as_u64()), (Some("up_to_date"), Some(0)));
+    let (_, audit) = json(&["audit", "--json"], &registry);
+    assert_eq!((audit["issues"].as_array().map(Vec::len), audit["vulnerabilities"].as_u64()), (Some(0), Some(0)));
+    let (_, search) = json(&["search", "--format", "json", "http"], &serve_once(r#"{"total": 1, "packages": [{"name": "http-client", "version": "1.10.1", "description": "HTTP requests", "downloads": 60}]}"#));
+    assert_eq!((search["query"].as_str(), search["packages"][0]["name"].as_str()), (Some("http"), Some("http-client")));
+
+    assert_eq!(stel(&["tree", "--format", "yaml"], &registry).status.code(), Some(2));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_doctor_finds_problems_and_suggests_fixes() {
+    let dir = std::env::temp_dir().join("stel_doctor");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = "[package]\nname = \"patient\"\nversion = \"0.1.0\"\n\n[dependencies]\nlib = \"1.0.0\"\n";
+    fs::write(dir.join("stel.toml"), manifest).unwrap();
+    let (registry, _) = serve_packages(vec![("lib", "1.0.0", package_archive("fn lib() {\n    return 1\n}\n"))]);
+    let stel = |args: &[&str], registry: &str| Command::new(env!("CARGO_BIN_EXE_stel")).args(args).env("STEL_REGISTRY", registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    assert!(stel(&["install"], &registry).status.success());
+    let output = stel(&["doctor"], &registry);
+    assert!(output.status.success() && String::from_utf8_lossy(&output.stdout).contains("No problems found"), "{}", String::from_utf8_lossy(&output.stdout));
+
+    // A StelLang requirement nothing meets, an unlocked dependency, a package gone
+    // from dependencies/ and, with nothing listening on port 9, no registry
+    fs::write(dir.join("stel.toml"), manifest.replace("version = \"0.1.0\"\n", "version = \"0.1.0\"\nstellang = \">=99\"\n") + "extra = \"^2\"\n").unwrap();
+    fs::remove_dir_all(dir.join("dependencies/lib")).unwrap();
+    let output = stel(&["doctor"], "http://127.0.0.1:9");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("✓ stel.toml\n"), "{}", stdout);
+    assert!(stdout.contains("✗ StelLang version: patient needs StelLang >=99, but this is StelLang "), "{}", stdout);
+    assert!(stdout.contains("✗ stel.lock: extra is in stel.toml but not in stel.lock\n      fix: run 'stel install'"), "{}", stdout);
+    assert!(stdout.contains("✗ dependencies/: lib is not installed in dependencies/lib"), "{}", stdout);
+    assert!(stdout.contains("✗ registry: http://127.0.0.1:9 can't be reached") && stdout.contains("fix: check your network connection"), "{}", stdout);
+    assert!(stdout.contains("4 error(s), 0 warning(s)"), "{}", stdout);
+
+    fs::write(dir.join("stel.toml"), "[package]\nname = 3\n").unwrap();
+    let stdout = String::from_utf8_lossy(&stel(&["doctor"], &registry).stdout).into_owned();
+    assert!(stdout.contains("✗ stel.toml: stel.toml is not valid") && stdout.contains("fix: correct stel.toml"), "{}", stdout);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_stellang_requirement_and_edition() {
+    let dir = std::env::temp_dir().join("stel_toolchain");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nstellang = \">=0.1\"\nedition = \"2025\"\n\n[dependencies]\nlib = \"^1.0\"\n").unwrap();
+    let (registry, requests) = serve_packages(vec![
+        ("lib", "1.0.0", package_archive_with_manifest("[package]\nname = \"lib\"\nversion = \"1.0.0\"\nstellang = \">=0.1\"\n", "fn v() {\n    return 1\n}\n")),
+        ("lib", "1.1.0", package_archive_with_manifest("[package]\nname = \"lib\"\nversion = \"1.1.0\"\nstellang = \">=99\"\n", "fn v() {\n    return 2\n}\n")),
+        ("lib", "1.2.0", package_archive_with_manifest("[package]\nname = \"lib\"\nversion = \"1.2.0\"\nedition = \"2099\"\n", "fn v() {\n    return 3\n}\n")),
+    ]);
+    let stel = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_stel")).args(args).env("STEL_REGISTRY", &registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+
+    // The newest releases need another StelLang, so the resolver falls back to 1.0.0
+    let output = stel(&["install"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), stderr);
+    assert!(stderr.contains("Skipping lib@1.2.0: it is written for edition 2099") && stderr.contains("Skipping lib@1.1.0: it needs StelLang >=99"), "{}", stderr);
+    assert!(fs::read_to_string(dir.join("stel.lock")).unwrap().contains("version = \"1.0.0\""));
+    assert!(requests.lock().unwrap().contains(&"/api/packages/lib/1.0.0/download 0".to_string()));
+
+    // The project itself asks for a StelLang that isn't running
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nstellang = \">=99\"\n").unwrap();
+    for command in ["install", "build"] {
+        let output = stel(&[command]);
+        assert_eq!(output.status.code(), Some(1), "{}", command);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("app needs StelLang >=99, but this is StelLang "), "{}", command);
+    }
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"app\"\nversion = \"0.1.0\"\nedition = \"2099\"\n").unwrap();
+    let output = stel(&["build"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("app is written for edition 2099, which StelLang"));
+}
+
+#[test]
+fn test_publish_leaves_out_ignored_files() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let dir = std::env::temp_dir().join("stel_publish_ignore");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src/secrets")).unwrap();
+    fs::create_dir_all(dir.join(".stel")).unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"tidy\"\nversion = \"0.1.0\"\nlicense = \"MIT\"\ndescription = \"Tidy\"\nrepository = \"https://example.com/tidy\"\n").unwrap();
+    fs::write(dir.join(".stelignore"), "# keep these out of the archive\nsecrets/\n*.bak\n").unwrap();
+    fs::write(dir.join(".stel/token"), "token").unwrap();
+    fs::write(dir.join("README.md"), "# tidy\n").unwrap();
+    fs::write(dir.join("src/main.stel"), "print(1)\n").unwrap();
+    fs::write(dir.join("src/main.stel.bak"), "print(0)\n").unwrap();
+    fs::write(dir.join("src/secrets/key.stel"), "let key = \"hunter2\"\n").unwrap();
+
+    // Stand in for the registry, keeping the uploaded archive
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let registry = format!("http://{}", listener.local_addr().unwrap());
+    let upload = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (mut line, mut length) = (String::new(), 0);
+        while reader.read_line(&mut line).unwrap() > 2 {
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+            line.clear();
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}").unwrap();
+        body
+    });
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).arg("publish").env("STEL_REGISTRY", &registry).current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let archive = upload.join().unwrap();
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(&archive[..]));
+    let mut paths: Vec<String> = tar.entries().unwrap().map(|entry| entry.unwrap().path().unwrap().display().to_string()).collect();
+    paths.sort();
+    assert_eq!(paths, ["README.md", "src/main.stel", "stel.toml"]);
+}
+
+#[test]
+fn test_slow_downloads_are_not_cut_off() {
+    let dir = std::env::temp_dir().join("stel_slow_download");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("home")).unwrap();
+    // The timeout bounds each wait for data, not the whole download
+    fs::write(dir.join("home/config.toml"), "[registry]\ntimeout = 1\n").unwrap();
+    fs::write(dir.join("stel.toml"), "[package]\nname = \"patient\"\nversion = \"0.1.0\"\n\n[dependencies]\nslow = \"1.0.0\"\n").unwrap();
+    let (registry, _) = serve_packages_slowly(vec![("slow", "1.0.0", package_archive("fn slow() {\n    return 1\n}\n"))], std::time::Duration::from_millis(600));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_stel")).arg("install").env("STEL_REGISTRY", &registry).env("STEL_HOME", dir.join("home")).current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(dir.join("dependencies/slow/src/main.stel").is_file());
+}
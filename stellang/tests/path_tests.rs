@@ -0,0 +1,66 @@
+// path module tests for StelLang
+
+use std::path::MAIN_SEPARATOR_STR as SEP;
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(&format!("import path\n{}", code));
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn s(text: &str) -> Value {
+    Value::Str(text.to_string())
+}
+
+#[test]
+fn test_join_and_split() {
+    assert_eq!(eval(r#"path.join("src", "lang", "main.stl")"#).unwrap(), s(&["src", "lang", "main.stl"].join(SEP)));
+    assert_eq!(eval(r#"path.join("src", "/etc")"#).unwrap(), s("/etc"));
+    assert_eq!(eval(r#"path.split("src/lang/main.stl")"#).unwrap(), Value::Tuple(vec![s("src/lang"), s("main.stl")]));
+    assert_eq!(eval(r#"path.dirname("main.stl")"#).unwrap(), s(""));
+    assert_eq!(eval(r#"path.basename("src/lang/main.stl")"#).unwrap(), s("main.stl"));
+    assert_eq!(eval(r#"path.extension("archive.tar.gz")"#).unwrap(), s("gz"));
+    assert_eq!(eval(r#"path.extension("Makefile")"#).unwrap(), s(""));
+    assert_eq!(eval(r#"path.join("a", 1)"#).unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(eval(r#"path.normalize("a//b/./c/../d")"#).unwrap(), s(&["a", "b", "d"].join(SEP)));
+    assert_eq!(eval(r#"path.normalize("a\\b\\..\\c")"#).unwrap(), s(&["a", "c"].join(SEP)));
+    assert_eq!(eval(r#"path.normalize("../x/..")"#).unwrap(), s(".."));
+    assert_eq!(eval(r#"path.normalize("/../etc")"#).unwrap(), s(&format!("{}etc", SEP)));
+    assert_eq!(eval(r#"path.normalize("a/..")"#).unwrap(), s("."));
+}
+
+#[test]
+fn test_filesystem_queries() {
+    let dir = std::env::temp_dir().join(format!("stellang_{}_glob", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.stl"), "").unwrap();
+    std::fs::write(dir.join("sub").join("b.stl"), "").unwrap();
+    std::fs::write(dir.join("notes.txt"), "").unwrap();
+    let root = dir.to_string_lossy().replace('\\', "/");
+
+    let found = eval(&format!(r#"path.glob("{}/**/*.stl")"#, root)).unwrap();
+    let expected: Vec<Value> = [dir.join("a.stl"), dir.join("sub").join("b.stl")].iter().map(|p| s(&p.to_string_lossy())).collect();
+    assert_eq!(found, Value::List(expected));
+    assert_eq!(eval(&format!(r#"path.isdir("{}/sub")"#, root)).unwrap(), Value::Bool(true));
+    assert_eq!(eval(&format!(r#"path.isfile("{}/notes.txt")"#, root)).unwrap(), Value::Bool(true));
+    assert_eq!(eval(&format!(r#"path.exists("{}/missing")"#, root)).unwrap(), Value::Bool(false));
+    assert_eq!(eval(r#"path.glob("[")"#).unwrap_err().kind, ExceptionKind::ValueError);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let Value::Str(absolute) = eval(r#"path.absolute("x/../y")"#).unwrap() else { panic!("absolute() should return a str") };
+    assert_eq!(absolute, std::env::current_dir().unwrap().join("y").to_string_lossy());
+}
@@ -0,0 +1,83 @@
+// proc module tests: spawning programs, pipes, waiting and killing
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+use stellang::lang::sandbox::Capabilities;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn strs(items: &[&str]) -> Value {
+    Value::List(items.iter().map(|item| Value::Str(item.to_string())).collect())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_spawn_with_pipes() {
+    let code = "import proc
+let p = proc.spawn(\"cat\", [], {\"stdin\": \"pipe\", \"stdout\": \"pipe\"})
+p.write(\"one\\ntwo\\n\")
+let first = p.read_line()
+p.close_stdin()
+let rest = p.read()
+[first, rest, str(p.wait()), p.read_line()]";
+    assert_eq!(eval(code).unwrap(), strs(&["one\n", "two\n", "0", ""]));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exit_status_stderr_and_options() {
+    let code = "import proc
+let p = proc.spawn(\"sh\", [\"-c\", \"echo $GREETING from $(basename $(pwd)) >&2; exit 3\"], {\"stderr\": \"pipe\", \"cwd\": \"/tmp\", \"env\": {\"GREETING\": \"hi\"}})
+[str(p.wait()), p.read_err(), str(p.poll())]";
+    assert_eq!(eval(code).unwrap(), strs(&["3", "hi from tmp\n", "3"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_wait_timeout_and_kill() {
+    let code = "import proc
+let p = proc.spawn(\"sleep\", [10])
+let timed_out = false
+try {
+    p.wait(0.05)
+} catch TimeoutError {
+    timed_out = true
+}
+let running = p.poll() == None
+p.kill()
+[timed_out, running, p.wait() < 0, p.pid() > 0]";
+    assert_eq!(eval(code).unwrap(), Value::List(vec![Value::Bool(true); 4]));
+}
+
+#[test]
+fn test_spawn_errors() {
+    let err = eval("import proc\nproc.spawn(\"stel-no-such-program\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::FileNotFoundError);
+    assert!(err.args[0].starts_with("cannot run 'stel-no-such-program'"), "{}", err.args[0]);
+    let err = eval("import proc\nproc.spawn(\"true\", [], {\"stdout\": \"file\"})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import proc\nproc.spawn(\"true\", [], {\"shell\": true})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import proc\nproc.spawn(\"true\", [], {\"cwd\": 1})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unpiped_stream_cannot_be_read() {
+    let err = eval("import proc\nlet p = proc.spawn(\"true\")\np.wait()\np.read()").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import proc\nlet p = proc.spawn(\"true\")\np.write(\"x\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_spawn_needs_os_capability() {
+    let expr = Parser::parse_source("import proc\nproc.spawn(\"true\")").unwrap().unwrap();
+    let err = Interpreter::with_capabilities(Capabilities::sandboxed()).eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+}
@@ -0,0 +1,97 @@
+// dir/getattr/setattr/hasattr/vars tests for StelLang
+
+use std::collections::HashMap;
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, native::NativeModule};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    eval_in(&mut Interpreter::new(), code)
+}
+
+fn strings(names: &[&str]) -> Value {
+    Value::List(names.iter().map(|name| Value::Str(name.to_string())).collect())
+}
+
+// Classes have no surface syntax yet, so instances are placed in the environment directly
+fn with_point() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.env.insert("Point".to_string(), Value::Class {
+        name: "Point".to_string(),
+        methods: HashMap::new(),
+        fields: HashMap::from([("dims".to_string(), Value::Int(2))]),
+        base: None,
+    });
+    interpreter.env.insert("p".to_string(), Value::Instance {
+        class_name: "Point".to_string(),
+        fields: HashMap::from([("x".to_string(), Value::Int(1))]),
+        id: 1,
+    });
+    interpreter
+}
+
+#[test]
+fn test_getattr_and_hasattr() {
+    let mut interpreter = with_point();
+    assert_eq!(eval_in(&mut interpreter, "getattr(p, \"x\")").unwrap(), Value::Int(1));
+    assert_eq!(eval_in(&mut interpreter, "getattr(p, \"dims\")").unwrap(), Value::Int(2));
+    assert_eq!(eval_in(&mut interpreter, "getattr(p, \"z\", 0)").unwrap(), Value::Int(0));
+    assert_eq!(eval_in(&mut interpreter, "hasattr(p, \"x\")").unwrap(), Value::Bool(true));
+    assert_eq!(eval_in(&mut interpreter, "hasattr(p, \"z\")").unwrap(), Value::Bool(false));
+    assert_eq!(eval_in(&mut interpreter, "getattr(p, \"z\")").unwrap_err().kind, ExceptionKind::AttributeError);
+    assert_eq!(eval("getattr(\"abc\", \"upper\")()").unwrap(), Value::Str("ABC".to_string()));
+    assert_eq!(eval("getattr(\"abc\", 1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_setattr_updates_variable() {
+    let mut interpreter = with_point();
+    eval_in(&mut interpreter, "setattr(p, \"y\", 5)").unwrap();
+    assert_eq!(eval_in(&mut interpreter, "p.y").unwrap(), Value::Int(5));
+    eval_in(&mut interpreter, "setattr(Point, \"dims\", 3)").unwrap();
+    assert_eq!(eval_in(&mut interpreter, "p.dims").unwrap(), Value::Int(3));
+    let err = eval("let n = 1\nsetattr(n, \"y\", 2)").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::AttributeError);
+}
+
+#[test]
+fn test_dir_and_vars() {
+    let mut interpreter = with_point();
+    assert_eq!(eval_in(&mut interpreter, "dir(p)").unwrap(), strings(&["dims", "x"]));
+    assert_eq!(eval_in(&mut interpreter, "vars(p)").unwrap(), Value::Dict([(Value::Str("x".to_string()), Value::Int(1))].into_iter().collect()));
+    assert_eq!(eval("dir({})").unwrap(), strings(&["clear", "copy", "get", "items", "keys", "pop", "update", "values"]));
+    assert_eq!(eval("vars(1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_reflection_on_modules() {
+    fn double(args: &[Value]) -> Result<Value, Exception> {
+        match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Ok(Value::None),
+        }
+    }
+    let mut module = NativeModule::new("ops");
+    module.register_fn("double", double);
+    module.register_value("limit", Value::Int(10));
+    let mut interpreter = Interpreter::new();
+    interpreter.add_native_module(module);
+    assert_eq!(eval_in(&mut interpreter, "getattr(ops, \"double\")(4)").unwrap(), Value::Int(8));
+    assert_eq!(eval_in(&mut interpreter, "hasattr(ops, \"limit\")").unwrap(), Value::Bool(true));
+    let names = eval_in(&mut interpreter, "dir(ops)").unwrap();
+    let Value::List(names) = names else { panic!("dir() should return a list") };
+    assert!(names.contains(&Value::Str("double".to_string())) && names.contains(&Value::Str("limit".to_string())));
+}
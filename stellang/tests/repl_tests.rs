@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run_repl(input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stellang"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start REPL");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().expect("REPL did not exit")
+}
+
+#[test]
+fn test_quit_exits_with_status() {
+    let output = run_repl("quit(3)\nprint(\"unreachable\")\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("unreachable"));
+}
+
+#[test]
+fn test_exit_without_code() {
+    let output = run_repl("exit()\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_type_and_funcs_commands() {
+    let output = run_repl(":type 1.5\nfn add(a, b) { return a + b }\n:funcs\n:quit\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|l| l.ends_with("float")), "{}", stdout);
+    assert!(stdout.contains("add(a, b)"), "{}", stdout);
+}
+
+#[test]
+fn test_time_and_load_commands() {
+    let path = std::env::temp_dir().join("stellang_repl_load_test.stel");
+    std::fs::write(&path, "fn triple(n) { return n * 3 }").unwrap();
+    let output = run_repl(&format!(":load {}\n:time triple(14)\n", path.display()));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("42"), "{}", stdout);
+    assert!(stdout.contains("Elapsed:"), "{}", stdout);
+    let _ = std::fs::remove_file(path);
+
+    // Errors in a loaded file name it and their lines
+    let path = std::env::temp_dir().join("stellang_repl_load_error_test.stel");
+    std::fs::write(&path, "let a = 1\na / 0\n").unwrap();
+    let output = run_repl(&format!(":load {}\n", path.display()));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("  at {}:2\nZeroDivisionError", path.display())), "{}", stderr);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn test_error_output_is_readable() {
+    let output = run_repl("fn boom() { return 1 / 0 }\nboom()\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Traceback (most recent call last):\n  in boom()\nZeroDivisionError: division by zero"), "{}", stderr);
+    assert!(!stderr.contains("Exception {"), "{}", stderr);
+}
+
+#[test]
+fn test_json_error_format() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_stellang"))
+        .arg("--error-format=json")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to start REPL");
+    child.stdin.take().unwrap().write_all(b"[1][5]\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let line = String::from_utf8_lossy(&output.stderr).lines().next().unwrap_or_default().to_string();
+    let json: serde_json::Value = serde_json::from_str(&line).expect("stderr is not JSON");
+    assert_eq!(json["kind"], "IndexError");
+    assert_eq!(json["severity"], "error");
+}
+
+#[test]
+fn test_result_history() {
+    let output = run_repl("20 + 1\nprint(\"skipped\")\n_ * 2\n_1 + _2\n[_1, _2, _3, _]\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Results follow the prompt on the same line
+    let results: Vec<&str> = stdout.lines().map(|l| l.trim_start_matches(">>> ")).filter(|l| ["21", "42", "63", "None"].contains(l) || l.starts_with('[')).collect();
+    assert_eq!(results, ["21", "None", "42", "63", "[21, 42, 63, 63]"], "{}", stdout);
+}
@@ -0,0 +1,145 @@
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}, sandbox::Capabilities};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_sandbox_blocks_filesystem() {
+    let mut interpreter = Interpreter::sandboxed();
+    let result = eval_in(&mut interpreter, r#"open("Cargo.toml")"#);
+    assert_eq!(result.unwrap_err().kind, ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_sandbox_blocks_threads_and_stdin() {
+    let mut interpreter = Interpreter::sandboxed();
+    let code = r#"
+        fn work() {
+            return 1
+        }
+        spawn(work)
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code).unwrap_err().kind, ExceptionKind::PermissionError);
+    assert_eq!(eval_in(&mut interpreter, "input()").unwrap_err().kind, ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_sandbox_still_runs_pure_code() {
+    let mut interpreter = Interpreter::sandboxed();
+    let code = r#"
+        fn add(a, b) {
+            return a + b
+        }
+        print("hello from the sandbox")
+        add(2, 3)
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code), Ok(Value::Int(5)));
+}
+
+#[test]
+fn test_sandbox_whitelisted_function() {
+    let mut interpreter = Interpreter::sandboxed();
+    interpreter.allow_native("open");
+    let result = eval_in(&mut interpreter, r#"open("Cargo.toml").closed()"#);
+    assert_eq!(result, Ok(Value::Bool(false)));
+}
+
+#[test]
+fn test_user_function_cannot_bypass_sandbox() {
+    let mut interpreter = Interpreter::sandboxed();
+    let code = r#"
+        fn open(path) {
+            return path
+        }
+        open("Cargo.toml")
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code).unwrap_err().kind, ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_custom_capabilities() {
+    let mut interpreter = Interpreter::with_capabilities(Capabilities { stdout: false, ..Capabilities::all() }.allow("input"));
+    assert_eq!(eval_in(&mut interpreter, r#"print("x")"#).unwrap_err().kind, ExceptionKind::PermissionError);
+    assert!(interpreter.capabilities.allowed_functions.contains("input"));
+}
+
+#[test]
+fn test_sandbox_blocks_dynamic_code() {
+    let mut interpreter = Interpreter::sandboxed();
+    assert_eq!(eval_in(&mut interpreter, r#"eval("1 + 2")"#).unwrap_err().kind, ExceptionKind::PermissionError);
+    assert_eq!(eval_in(&mut interpreter, r#"exec("let x = 1")"#).unwrap_err().kind, ExceptionKind::PermissionError);
+    interpreter.allow_native("eval");
+    assert_eq!(eval_in(&mut interpreter, r#"eval("1 + 2")"#).unwrap(), Value::Int(3));
+}
+
+#[test]
+fn test_sandbox_blocks_path_queries() {
+    let mut interpreter = Interpreter::sandboxed();
+    let err = eval_in(&mut interpreter, "import path\npath.exists(\"Cargo.toml\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+    assert_eq!(eval_in(&mut interpreter, "path.basename(\"src/main.rs\")").unwrap(), Value::Str("main.rs".to_string()));
+    interpreter.allow_native("path.exists");
+    assert_eq!(eval_in(&mut interpreter, "path.exists(\"Cargo.toml\")").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn test_sandbox_blocks_config_loading() {
+    let mut interpreter = Interpreter::sandboxed();
+    let err = eval_in(&mut interpreter, "import config\nconfig.load({\"home\": \"\"})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::PermissionError);
+    assert_eq!(eval_in(&mut interpreter, "config.load_env()").unwrap_err().kind, ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_sandbox_allowed_dirs() {
+    let dir = std::env::temp_dir().join("stellang_sandbox_dirs");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    std::fs::write(dir.join("input.txt"), "data").unwrap();
+    let capabilities = Capabilities::sandboxed().allow_dir(&dir, false).allow_dir(dir.join("out"), true);
+    let mut interpreter = Interpreter::with_capabilities(capabilities);
+    let path = |name: &str| format!("{:?}", dir.join(name).display().to_string());
+
+    assert_eq!(eval_in(&mut interpreter, &format!("open({}).read()", path("input.txt"))), Ok(Value::Str("data".to_string())));
+    assert!(eval_in(&mut interpreter, &format!("open({}, \"w\").write(\"x\")", path("out/made.txt"))).is_ok());
+    assert_eq!(std::fs::read_to_string(dir.join("out/made.txt")).unwrap(), "x");
+    for denied in [format!("open({}, \"w\")", path("input.txt")), format!("open({})", path("out/../../elsewhere")), r#"open("Cargo.toml")"#.to_string()] {
+        assert_eq!(eval_in(&mut interpreter, &denied).unwrap_err().kind, ExceptionKind::PermissionError, "{}", denied);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_sandbox_allowed_dirs_resolve_symlinks() {
+    let dir = std::env::temp_dir().join("stellang_sandbox_links");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("out")).unwrap();
+    std::fs::create_dir_all(dir.join("outside")).unwrap();
+    std::fs::write(dir.join("outside/secret.txt"), "secret").unwrap();
+    std::os::unix::fs::symlink(dir.join("outside"), dir.join("out/escape")).unwrap();
+    std::os::unix::fs::symlink(dir.join("outside/planted.txt"), dir.join("out/dangling")).unwrap();
+    std::os::unix::fs::symlink(dir.join("out"), dir.join("out/here")).unwrap();
+    let mut interpreter = Interpreter::with_capabilities(Capabilities::sandboxed().allow_dir(dir.join("out"), true));
+    let path = |name: &str| format!("{:?}", dir.join(name).display().to_string());
+
+    // Links that stay inside the directory still work; ones that lead out don't
+    assert!(eval_in(&mut interpreter, &format!("open({}, \"w\").write(\"x\")", path("out/here/made.txt"))).is_ok());
+    for denied in [format!("open({})", path("out/escape/secret.txt")), format!("open({}, \"w\")", path("out/escape/new.txt")), format!("open({}, \"w\")", path("out/dangling"))] {
+        assert_eq!(eval_in(&mut interpreter, &denied).unwrap_err().kind, ExceptionKind::PermissionError, "{}", denied);
+    }
+    assert!(!dir.join("outside/planted.txt").exists() && !dir.join("outside/new.txt").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,50 @@
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let mut original = Interpreter::new();
+    let code = r#"
+        let total = 40
+        let table = {1: "one", "two": 2}
+        fn bump(n) {
+            return n + 2
+        }
+    "#;
+    eval_in(&mut original, code).unwrap();
+    let bytes = original.snapshot().unwrap();
+
+    let mut restored = Interpreter::new();
+    restored.restore(&bytes).unwrap();
+    assert_eq!(eval_in(&mut restored, "bump(total)"), Ok(Value::Int(42)));
+    assert_eq!(eval_in(&mut restored, "table[1]"), Ok(Value::Str("one".to_string())));
+}
+
+#[test]
+fn test_restore_rejects_garbage() {
+    let mut interpreter = Interpreter::new();
+    let err = interpreter.restore(b"definitely not an image").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_restore_keeps_capabilities() {
+    let bytes = Interpreter::new().snapshot().unwrap();
+    let mut sandbox = Interpreter::sandboxed();
+    sandbox.restore(&bytes).unwrap();
+    assert_eq!(eval_in(&mut sandbox, r#"open("Cargo.toml")"#).unwrap_err().kind, ExceptionKind::PermissionError);
+}
@@ -0,0 +1,183 @@
+// Execution statistics tests for StelLang
+
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::Exception};
+
+fn eval_in(interpreter: &mut Interpreter, code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_stats_disabled_by_default() {
+    let mut interpreter = Interpreter::new();
+    eval_in(&mut interpreter, "1 + 1").unwrap();
+    assert!(interpreter.stats().is_none());
+}
+
+#[test]
+fn test_function_calls_and_expressions() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = r#"
+        fn double(n) {
+            return n * 2
+        }
+        total = 0
+        i = 0
+        while i < 5 {
+            if i > 1 {
+                total = total + double(i)
+            }
+            i = i + 1
+        }
+        total
+    "#;
+    assert_eq!(eval_in(&mut interpreter, code).unwrap(), Value::Int(18));
+    let stats = interpreter.stats().unwrap();
+    assert_eq!(stats.function_calls["double"], 3);
+    assert_eq!(stats.expressions["FnDef"], 1);
+    assert_eq!(stats.expressions["If"], 5);
+    assert_eq!(stats.expressions["While"], 1);
+}
+
+#[test]
+fn test_allocations_by_kind() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = r#"
+        items = [1, 2, [3]]
+        names = {"a": "b"}
+        items
+        items
+    "#;
+    eval_in(&mut interpreter, code).unwrap();
+    let stats = interpreter.stats().unwrap();
+    assert_eq!(stats.allocations["list"], 2);
+    assert_eq!(stats.allocations["dict"], 1);
+    assert_eq!(stats.allocations["str"], 2);
+    // Reading a variable creates nothing
+    assert!(!stats.allocations.contains_key("int"));
+}
+
+#[test]
+fn test_exceptions_counted_once_where_raised() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = r#"
+        fn inner() {
+            return 1 / 0
+        }
+        fn outer() {
+            return inner()
+        }
+        try {
+            outer()
+        } catch e {
+            None
+        }
+        try {
+            throw ValueError("bad")
+        } catch e {
+            None
+        }
+        try {
+            [1][3]
+        } catch e {
+            None
+        }
+    "#;
+    eval_in(&mut interpreter, code).unwrap();
+    let stats = interpreter.stats().unwrap();
+    assert_eq!(stats.exceptions["ZeroDivisionError"], 1);
+    assert_eq!(stats.exceptions["ValueError"], 1);
+    assert_eq!(stats.exceptions["IndexError"], 1);
+    assert_eq!(stats.exceptions.len(), 3);
+}
+
+#[test]
+fn test_peak_env_size() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let baseline = interpreter.env.len();
+    eval_in(&mut interpreter, "a = 1\nb = 2\nc = 3").unwrap();
+    eval_in(&mut interpreter, "a").unwrap();
+    assert_eq!(interpreter.stats().unwrap().peak_env_size, baseline + 3);
+}
+
+#[test]
+fn test_threads_add_to_the_same_stats() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = r#"
+        fn work(n) {
+            return n * 2
+        }
+        join(spawn(work, 1))
+        work(2)
+    "#;
+    eval_in(&mut interpreter, code).unwrap();
+    let stats = interpreter.stats().unwrap();
+    assert_eq!(stats.function_calls["work"], 1);
+    assert_eq!(stats.function_calls["spawn"], 1);
+    assert_eq!(stats.expressions["Return"], 2);
+}
+
+#[test]
+fn test_json_report() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    eval_in(&mut interpreter, "print(\"x\")").unwrap();
+    let report = interpreter.stats().unwrap().to_json();
+    assert_eq!(report["function_calls"]["print"], 1);
+    for key in ["expressions", "allocations", "exceptions", "peak_env_size"] {
+        assert!(report.get(key).is_some(), "missing {}", key);
+    }
+}
+
+#[test]
+fn test_time_per_call_stack() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = r#"
+        fn leaf(n) { n + 1 }
+        fn mid(n) { leaf(n) + leaf(n) }
+        fn top() { mid(1) + leaf(2) }
+        top()
+    "#;
+    eval_in(&mut interpreter, code).unwrap();
+    let stats = interpreter.stats().unwrap();
+    let stacks: Vec<&str> = stats.self_time.keys().map(String::as_str).collect();
+    assert_eq!(stacks, ["top", "top;leaf", "top;mid", "top;mid;leaf"]);
+
+    // One `stack microseconds` line each, in the format flamegraph tools read
+    let collapsed = stats.collapsed_stacks();
+    assert_eq!(collapsed.lines().count(), 4);
+    for (line, stack) in collapsed.lines().zip(stacks) {
+        let (name, micros) = line.rsplit_once(' ').unwrap();
+        assert_eq!(name, stack);
+        assert!(micros.parse::<u128>().is_ok(), "{}", line);
+    }
+}
+
+#[test]
+fn test_time_per_line() {
+    let mut interpreter = Interpreter::new();
+    interpreter.enable_stats();
+    let code = "fn leaf(n) {\n    n + 1\n}\nfn top() {\n    let a = leaf(1)\n    let b = a * 2\n    leaf(b)\n}\ntop()\n";
+    let program = Parser::parse_source_with_lines(code).unwrap().unwrap();
+    interpreter.eval(&program).unwrap();
+    let stats = interpreter.stats().unwrap();
+    let stacks: Vec<&str> = stats.self_time.keys().map(String::as_str).collect();
+    // Calls from different lines of `top` are told apart
+    assert_eq!(stacks, ["top:5", "top:5;leaf:2", "top:6", "top:7", "top:7;leaf:2"]);
+}
@@ -0,0 +1,98 @@
+// String repetition, containment, indexing, splitting and joining, and
+// iterating over characters and bytes
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(&expr)
+}
+
+fn string(s: &str) -> Value {
+    Value::Str(s.to_string())
+}
+
+fn strings(items: &[&str]) -> Value {
+    Value::List(items.iter().map(|s| string(s)).collect())
+}
+
+#[test]
+fn test_repetition() {
+    assert_eq!(eval("\"ab\" * 3"), Ok(string("ababab")));
+    assert_eq!(eval("2 * \"ab\""), Ok(string("abab")));
+    assert_eq!(eval("\"ab\" * 0"), Ok(string("")));
+    assert_eq!(eval("\"ab\" * -1").unwrap_err().kind, ExceptionKind::ValueError);
+}
+
+#[test]
+fn test_containment() {
+    assert_eq!(eval("\"ell\" in \"hello\""), Ok(Value::Bool(true)));
+    assert_eq!(eval("\"\" in \"hello\""), Ok(Value::Bool(true)));
+    assert_eq!(eval("\"z\" not in \"hello\""), Ok(Value::Bool(true)));
+    assert_eq!(eval("1 in \"hello\"").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_indexing_counts_characters() {
+    assert_eq!(eval("\"héllo\"[1]"), Ok(string("é")));
+    assert_eq!(eval("\"héllo\"[4]"), Ok(string("o")));
+    let error = eval("\"héllo\"[5]").unwrap_err();
+    assert_eq!((error.kind, error.args), (ExceptionKind::IndexError, vec!["string index 5 out of range".to_string()]));
+    assert_eq!(eval("\"abc\"[-1]").unwrap_err().kind, ExceptionKind::IndexError);
+    assert_eq!(eval("\"\"[0]").unwrap_err().kind, ExceptionKind::IndexError);
+}
+
+#[test]
+fn test_chars_and_bytes() {
+    assert_eq!(eval("\"héy\".chars()"), Ok(strings(&["h", "é", "y"])));
+    assert_eq!(eval("\"\".chars()"), Ok(strings(&[])));
+    assert_eq!(eval("\"hé\".bytes()"), Ok(Value::Bytes(vec![104, 0xC3, 0xA9])));
+    let code = "
+        let vowels = 0
+        for c in \"education\".chars() {
+            if c in \"aeiou\" {
+                vowels = vowels + 1
+            }
+        }
+        let total = 0
+        for b in \"AB\".bytes() {
+            total = total + b
+        }
+        [vowels, total]
+    ";
+    assert_eq!(eval(code), Ok(Value::List(vec![Value::Int(5), Value::Int(131)])));
+}
+
+#[test]
+fn test_split_and_join_separators() {
+    assert_eq!(eval("\"a,,b\".split(\",\")"), Ok(strings(&["a", "", "b"])));
+    assert_eq!(eval("\"\".split(\",\")"), Ok(strings(&[""])));
+    assert_eq!(eval("\"  a  b \".split()"), Ok(strings(&["a", "b"])));
+    let error = eval("\"ab\".split(\"\")").unwrap_err();
+    assert_eq!(error.kind, ExceptionKind::ValueError);
+    assert!(error.args[0].starts_with("empty separator"));
+
+    assert_eq!(eval("\"\".join([\"a\", \"b\"])"), Ok(string("ab")));
+    assert_eq!(eval("\"-\".join([])"), Ok(string("")));
+    assert_eq!(eval("\"-\".join(tuple([\"a\", \"b\"]))"), Ok(string("a-b")));
+    assert_eq!(eval("\"\".join(\"héllo\".chars())"), Ok(string("héllo")));
+    assert_eq!(eval("\"-\".join(\"abc\")"), Ok(string("a-b-c")));
+    assert_eq!(eval("\"-\".join(5)").unwrap_err().kind, ExceptionKind::TypeError);
+}
+
+#[test]
+fn test_escape_sequences() {
+    assert_eq!(eval(r#""a\tb\nc""#), Ok(string("a\tb\nc")));
+    assert_eq!(eval(r#""say \"hi\"\\""#), Ok(string("say \"hi\"\\")));
+    assert_eq!(eval(r#""\r\0\'""#), Ok(string("\r\0'")));
+    // Strings may span lines; a backslash at the end of one joins it to the next
+    assert_eq!(eval("\"one\ntwo\""), Ok(string("one\ntwo")));
+    assert_eq!(eval("\"one \\\ntwo\""), Ok(string("one two")));
+    let err = Parser::parse_source(r#"let s = "C:\dir""#).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SyntaxError);
+    assert_eq!(err.message(), "Invalid escape sequence '\\d'");
+    assert_eq!(err.notes, ["at line 1, column 12", "hint: write '\\\\' for a backslash"]);
+}
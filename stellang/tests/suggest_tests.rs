@@ -0,0 +1,49 @@
+// "Did you mean ...?" suggestions on NameError and AttributeError
+
+use stellang::lang::exceptions::ExceptionKind;
+use stellang::lang::interpreter::Interpreter;
+use stellang::lang::parser::Parser;
+use stellang::lang::suggest::{closest, distance};
+
+// The message of the exception `code` raises
+fn error(code: &str, kind: ExceptionKind) -> String {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    let mut interpreter = Interpreter::new();
+    let err = interpreter.eval(&expr).unwrap_err();
+    assert_eq!(err.kind, kind, "{}", err);
+    err.message()
+}
+
+#[test]
+fn test_distance() {
+    assert_eq!(distance("count", "count"), 0);
+    assert_eq!(distance("conut", "count"), 1);
+    assert_eq!(distance("cont", "count"), 1);
+    assert_eq!(distance("kitten", "sitting"), 3);
+    assert_eq!(distance("", "abc"), 3);
+    assert_eq!(closest("lenght", ["len", "length", "height"]), Some("length"));
+    assert_eq!(closest("x", ["y", "z"]), Some("y"));
+    assert_eq!(closest("total", ["count", "items"]), None);
+}
+
+#[test]
+fn test_name_error_suggests_variables_functions_and_keywords() {
+    assert_eq!(error("let count = 1\nconut + 1", ExceptionKind::NameError), "name 'conut' is not defined (did you mean 'count'?)");
+    assert_eq!(error("fn average(xs) { 0 }\naverge([1])", ExceptionKind::NameError), "name 'averge' is not defined (did you mean 'average'?)");
+    assert_eq!(error("prnt(1)", ExceptionKind::NameError), "name 'prnt' is not defined (did you mean 'print'?)");
+    assert_eq!(error("let done = ture", ExceptionKind::NameError), "name 'ture' is not defined (did you mean 'true'?)");
+    assert_eq!(error("zzz", ExceptionKind::NameError), "name 'zzz' is not defined");
+}
+
+#[test]
+fn test_attribute_error_suggests_attributes() {
+    assert_eq!(error("\"abc\".uper()", ExceptionKind::AttributeError), "'str' object has no attribute 'uper' (did you mean 'upper'?)");
+    assert_eq!(
+        error("class P { fn __init__(self) { self.name = 1 } }\nlet p = P()\np.nme", ExceptionKind::AttributeError),
+        "'P' object has no attribute 'nme' (did you mean 'name'?)"
+    );
+    assert_eq!(
+        error("class P { fn greet(self) { 1 } }\nlet p = P()\np.gret()", ExceptionKind::AttributeError),
+        "'P' object has no attribute 'gret' (did you mean 'greet'?)"
+    );
+}
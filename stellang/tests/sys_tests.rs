@@ -0,0 +1,96 @@
+// sys module and platform cfg() tests
+
+use stellang::lang::exceptions::ExceptionKind;
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn run(code: &str) -> Value {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(&expr).expect("Runtime error")
+}
+
+fn string(value: &str) -> Value {
+    Value::Str(value.to_string())
+}
+
+#[test]
+fn test_sys_constants() {
+    assert_eq!(run("import sys\nsys.platform"), string(std::env::consts::OS));
+    assert_eq!(run("import sys\nsys.arch"), string(std::env::consts::ARCH));
+    assert_eq!(run("import sys\nsys.version"), string(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn test_cfg_platform() {
+    assert_eq!(run(&format!("cfg(\"{}\")", std::env::consts::OS)), Value::Bool(true));
+    assert_eq!(run(&format!("cfg(\"{}\")", std::env::consts::ARCH)), Value::Bool(true));
+    assert_eq!(run("cfg(\"unix\")"), Value::Bool(cfg!(unix)));
+    assert_eq!(run("cfg(\"windows\")"), Value::Bool(cfg!(windows)));
+    assert_eq!(run("cfg(\"plan9\")"), Value::Bool(false));
+}
+
+#[test]
+fn test_platform_guard() {
+    let code = "fn separator() {\n    if cfg(\"windows\") { return \"\\\\\" }\n    return \"/\"\n}\nseparator()";
+    assert_eq!(run(code), string(if cfg!(windows) { "\\" } else { "/" }));
+}
+
+#[test]
+fn test_sys_argv_and_path() {
+    stellang::lang::stdlib::sys::set_argv(vec!["script.stel".to_string(), "--verbose".to_string()]);
+    assert_eq!(run("import sys\nsys.argv"), Value::List(vec![string("script.stel"), string("--verbose")]));
+    let Value::List(path) = run("import sys\nsys.path") else { panic!("sys.path is not a list") };
+    assert!(path.contains(&string("dependencies/native")));
+    // Libraries planted in the working directory are not picked up
+    assert!(!path.contains(&string(".")));
+}
+
+#[test]
+fn test_sys_exit() {
+    let expr = Parser::parse_source("import sys\nsys.exit(3)").unwrap().unwrap();
+    let err = Interpreter::new().eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::SystemExit);
+    assert_eq!(err.exit_code(), Some(3));
+}
+
+#[test]
+fn test_sys_getsizeof() {
+    let empty = run("import sys\nsys.getsizeof(\"\")");
+    let (Value::Int(empty), Value::Int(longer)) = (empty, run("import sys\nsys.getsizeof(\"a longer string\")")) else {
+        panic!("sys.getsizeof() did not return ints");
+    };
+    assert!(empty > 0);
+    assert!(longer >= empty + 15);
+}
+
+#[test]
+fn test_sys_recursion_limit() {
+    assert_eq!(run("import sys\nsys.getrecursionlimit()"), Value::Int(1000));
+    let code = "import sys\nsys.setrecursionlimit(3)\nfn down(n) {\n    if n == 0 { return 0 }\n    return down(n - 1)\n}\ndown(10)";
+    let expr = Parser::parse_source(code).unwrap().unwrap();
+    let err = Interpreter::new().eval(&expr).unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::RecursionError);
+    // Each interpreter has its own limit
+    assert_eq!(run("import sys\nsys.getrecursionlimit()"), Value::Int(1000));
+
+    // No higher than the stack can hold, and not at all in a sandbox
+    let raise = |mut interpreter: Interpreter, code: &str| interpreter.eval(&Parser::parse_source(code).unwrap().unwrap()).unwrap_err().kind;
+    assert_eq!(raise(Interpreter::new(), "import sys\nsys.setrecursionlimit(10000000)"), ExceptionKind::ValueError);
+    assert_eq!(raise(Interpreter::sandboxed(), "import sys\nsys.setrecursionlimit(2000)"), ExceptionKind::PermissionError);
+}
+
+#[test]
+fn test_sys_stdout_is_interpreter_output() {
+    let expr = Parser::parse_source("import sys\nsys.stdout.write(\"to stdout\")\nprint(\"!\")").unwrap().unwrap();
+    let mut interpreter = Interpreter::new();
+    let captured = interpreter.capture_stdout();
+    interpreter.eval(&expr).expect("Runtime error");
+    assert_eq!(captured.contents(), "to stdout!\n");
+}
+
+#[test]
+fn test_sandbox_hides_stdin() {
+    let expr = Parser::parse_source("import sys\nsys.stdin").unwrap().unwrap();
+    assert!(Interpreter::sandboxed().eval(&expr).is_err());
+}
@@ -0,0 +1,58 @@
+// template module tests: variables, escaping, sections, loops and errors
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(code).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn render(call: &str) -> String {
+    match eval(&format!("import template\n{}", call)).unwrap() {
+        Value::Str(text) => text,
+        other => panic!("expected a str, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_variables_and_escaping() {
+    assert_eq!(render("template.render(\"Hello {{name}}!\", {\"name\": \"World\"})"), "Hello World!");
+    assert_eq!(render("template.render(\"{{ user.name }} is {{user.age}}, {{missing}}.\", {\"user\": {\"name\": \"Ann\", \"age\": 30}})"), "Ann is 30, .");
+    assert_eq!(render("template.render(\"{{html}} {{{html}}} {{& html}}\", {\"html\": \"<b>&</b>\"})"), "&lt;b&gt;&amp;&lt;/b&gt; <b>&</b> <b>&</b>");
+    assert_eq!(render("template.render(\"{{html}}\", {\"html\": \"<b>\"}, \"none\")"), "<b>");
+    assert_eq!(render("template.escape(\"'a' < \\\"b\\\"\")"), "&#39;a&#39; &lt; &quot;b&quot;");
+    assert_eq!(render("template.render(\"{{! a comment }}{{items.1}}\", {\"items\": [\"a\", \"b\"]})"), "b");
+}
+
+#[test]
+fn test_sections_and_loops() {
+    let items = "{\"items\": [{\"name\": \"a\", \"price\": 1}, {\"name\": \"b\", \"price\": 2}], \"shop\": \"S\"}";
+    assert_eq!(render(&format!("template.render(\"{{{{#items}}}}{{{{shop}}}}:{{{{name}}}}={{{{price}}}};{{{{/items}}}}\", {})", items)), "S:a=1;S:b=2;");
+    assert_eq!(render("template.render(\"{{#xs}}[{{.}}]{{/xs}}\", {\"xs\": [1, 2, 3]})"), "[1][2][3]");
+    assert_eq!(render("template.render(\"{{#on}}yes{{/on}}{{^on}}no{{/on}}\", {\"on\": true})"), "yes");
+    assert_eq!(render("template.render(\"{{#xs}}x{{/xs}}{{^xs}}empty{{/xs}}\", {\"xs\": []})"), "empty");
+    assert_eq!(render("template.render(\"{{#user}}{{name}}{{/user}}\", {\"user\": {\"name\": \"Ann\"}})"), "Ann");
+}
+
+#[test]
+fn test_standalone_tags_take_their_line() {
+    let code = "template.render(\"items:\\n{{#xs}}\\n  - {{.}}\\n{{/xs}}\\n{{! end }}\\ndone\", {\"xs\": [1, 2]})";
+    assert_eq!(render(code), "items:\n  - 1\n  - 2\ndone");
+}
+
+#[test]
+fn test_template_errors() {
+    let err = eval("import template\ntemplate.render(\"{{#a}}\\n{{/b}}\", {})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    assert_eq!(err.args, vec!["template line 2: '{{/b}}' closes section 'a'".to_string()]);
+    let err = eval("import template\ntemplate.render(\"{{#a}}x\", {})").unwrap_err();
+    assert_eq!(err.args, vec!["template line 1: section 'a' is never closed".to_string()]);
+    let err = eval("import template\ntemplate.render(\"{{name\", {})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import template\ntemplate.render(\"x\", {}, \"xml\")").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::ValueError);
+    let err = eval("import template\ntemplate.render(1, {})").unwrap_err();
+    assert_eq!(err.kind, ExceptionKind::TypeError);
+}
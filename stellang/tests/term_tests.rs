@@ -0,0 +1,51 @@
+// term module tests: styled text, cursor escapes and progress bars
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(&format!("import term\n{}", code)).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn string(value: &str) -> Value {
+    Value::Str(value.to_string())
+}
+
+// Whether escapes are written is process-wide, so both settings are checked in one test
+#[test]
+fn test_escapes_follow_set_enabled() {
+    eval("term.set_enabled(true)").unwrap();
+    assert_eq!(eval("term.color(\"red\", \"error\")").unwrap(), string("\x1b[31merror\x1b[0m"));
+    assert_eq!(eval("term.bold(42)").unwrap(), string("\x1b[1m42\x1b[0m"));
+    assert_eq!(eval("term.move_to(3, 10) + term.up() + term.left(4)").unwrap(), string("\x1b[3;10H\x1b[1A\x1b[4D"));
+    assert_eq!(eval("term.clear()").unwrap(), string("\x1b[2J\x1b[H"));
+    eval("term.set_enabled(false)").unwrap();
+    assert_eq!(eval("term.color(\"red\", \"error\") + term.clear() + term.up(3)").unwrap(), string("error"));
+    assert_eq!(eval("term.enabled()").unwrap(), Value::Bool(false));
+    eval("term.set_enabled(None)").unwrap();
+}
+
+#[test]
+fn test_progress() {
+    assert_eq!(eval("term.progress(5, 10, 10)").unwrap(), string("[#####-----]  50%"));
+    assert_eq!(eval("term.progress(3, 3, 4)").unwrap(), string("[####] 100%"));
+    // Past the total is full, not an overflowing bar
+    assert_eq!(eval("term.progress(7, 5, 4)").unwrap(), string("[####] 100%"));
+    assert_eq!(eval("term.progress(0, 0, 2)").unwrap(), string("[##] 100%"));
+}
+
+#[test]
+fn test_size() {
+    let Value::Tuple(size) = eval("term.size()").unwrap() else { panic!("expected a tuple") };
+    assert!(matches!(size.as_slice(), [Value::Int(columns), Value::Int(rows)] if *columns > 0 && *rows > 0));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(eval("term.color(\"mauve\", \"x\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("term.bold()").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("term.progress(\"a\", 3)").unwrap_err().kind, ExceptionKind::TypeError);
+    assert_eq!(eval("term.up(-1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
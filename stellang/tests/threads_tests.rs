@@ -0,0 +1,99 @@
+use stellang::lang::{lexer::Lexer, parser::Parser, interpreter::{Interpreter, Value}, exceptions::{Exception, ExceptionKind}};
+
+fn eval_code(code: &str) -> Result<Value, Exception> {
+    let mut lexer = Lexer::new(code);
+    let mut tokens = Vec::new();
+
+    loop {
+        let tok = lexer.next_token();
+        if tok == Ok(stellang::lang::lexer::Token::EOF) { break; }
+        tokens.push(tok.expect("Lexer error"));
+    }
+
+    let mut parser = Parser::new(tokens);
+    let expr = parser.parse().expect("Parse error").expect("No expression");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(&expr)
+}
+
+#[test]
+fn test_spawn_and_join_returns_result() {
+    let code = r#"
+        fn square(x) {
+            return x * x
+        }
+        let t = spawn(square, 7)
+        join(t)
+    "#;
+    assert_eq!(eval_code(code), Ok(Value::Int(49)));
+}
+
+#[test]
+fn test_channel_message_passing() {
+    let code = r#"
+        fn producer(ch, n) {
+            let i = 0
+            while i < n {
+                send(ch, i)
+                i = i + 1
+            }
+            ch.close()
+        }
+        let ch = chan()
+        let t = spawn(producer, ch, 5)
+        let total = 0
+        let v = recv(ch)
+        while v is not None {
+            total = total + v
+            v = recv(ch)
+        }
+        join(t)
+        total
+    "#;
+    assert_eq!(eval_code(code), Ok(Value::Int(10)));
+}
+
+#[test]
+fn test_multiple_workers_share_channel() {
+    let code = r#"
+        fn worker(ch, x) {
+            send(ch, x * 10)
+        }
+        let ch = chan()
+        let a = spawn(worker, ch, 1)
+        let b = spawn(worker, ch, 2)
+        join(a)
+        join(b)
+        recv(ch) + recv(ch)
+    "#;
+    assert_eq!(eval_code(code), Ok(Value::Int(30)));
+}
+
+#[test]
+fn test_thread_exception_reraised_on_join() {
+    let code = r#"
+        fn boom() {
+            return 1 / 0
+        }
+        join(spawn(boom))
+    "#;
+    assert_eq!(eval_code(code).unwrap_err().kind, ExceptionKind::ZeroDivisionError);
+}
+
+#[test]
+fn test_join_twice_is_an_error() {
+    let code = r#"
+        fn noop() {
+            return 1
+        }
+        let t = spawn(noop)
+        join(t)
+        join(t)
+    "#;
+    assert_eq!(eval_code(code).unwrap_err().kind, ExceptionKind::RuntimeError);
+}
+
+#[test]
+fn test_spawn_unknown_function() {
+    assert_eq!(eval_code("spawn(missing)").unwrap_err().kind, ExceptionKind::NameError);
+}
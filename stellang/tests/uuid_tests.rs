@@ -0,0 +1,46 @@
+// uuid module tests: v4 and v7 UUIDs and short ids
+
+use stellang::lang::exceptions::{Exception, ExceptionKind};
+use stellang::lang::interpreter::{Interpreter, Value};
+use stellang::lang::parser::Parser;
+
+fn eval(code: &str) -> Result<Value, Exception> {
+    let expr = Parser::parse_source(&format!("import uuid\n{}", code)).expect("Parse error").expect("No expression");
+    Interpreter::new().eval(&expr)
+}
+
+fn text(code: &str) -> String {
+    match eval(code).unwrap() {
+        Value::Str(s) => s,
+        other => panic!("expected a str, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_uuid_versions() {
+    let v4 = text("uuid.v4()");
+    assert_eq!(v4.len(), 36);
+    assert_eq!(&v4[14..15], "4");
+    assert_ne!(v4, text("uuid.v4()"));
+    let v7 = text("uuid.v7()");
+    assert_eq!(&v7[14..15], "7");
+    // v7 ids start with the time, so later ones sort after earlier ones
+    let (first, second) = (text("uuid.v7()"), text("uuid.v7()"));
+    assert!(first[..13] <= second[..13], "{} {}", first, second);
+    assert_eq!(eval("uuid.is_valid(uuid.v4())").unwrap(), Value::Bool(true));
+    assert_eq!(eval("uuid.is_valid(\"not-a-uuid\")").unwrap(), Value::Bool(false));
+}
+
+#[test]
+fn test_short_ids() {
+    let id = text("uuid.short()");
+    assert_eq!(id.len(), 21);
+    assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'), "{}", id);
+    let hex = text("uuid.short(40, \"0123456789abcdef\")");
+    assert_eq!(hex.len(), 40);
+    assert!(hex.chars().all(|c| c.is_ascii_hexdigit()), "{}", hex);
+    assert_eq!(text("uuid.short(5, \"x\")"), "xxxxx");
+    assert_eq!(eval("uuid.short(0)").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("uuid.short(4, \"\")").unwrap_err().kind, ExceptionKind::ValueError);
+    assert_eq!(eval("uuid.v4(1)").unwrap_err().kind, ExceptionKind::TypeError);
+}
@@ -0,0 +1,87 @@
+// The Visitor and Transformer traits external tools use to walk and rewrite ASTs
+
+use stellang::lang::ast::{walk_expr, walk_expr_mut, Expr, Transformer, Visitor};
+use stellang::lang::format::format_program;
+use stellang::lang::parser::Parser;
+use stellang::lang::symbol::Symbol;
+
+const PROGRAM: &str = r#"fn area(w, h) { w * h }
+let sizes = [area(1, 2), area(3, 4)]
+for (i, s) in enumerate(sizes) {
+    if s > 2 { print(i, s) } else { total = total + s }
+}
+try { throw ValueError("bad") } catch e { print(e) } finally { done = true }
+class Box { fn volume(self) { self.w * area(self.h, self.d) } }
+test "area" { assert_eq(area(2, 3), 6) }"#;
+
+fn parse(source: &str) -> Expr {
+    Parser::parse_source(source).expect("Parse error").expect("No expression")
+}
+
+// Names of the functions called anywhere in a program
+#[derive(Default)]
+struct Calls(Vec<Symbol>);
+
+impl Visitor for Calls {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::FnCall { callable, .. } = expr {
+            if let Expr::Ident(name) = callable.as_ref() {
+                self.0.push(*name);
+            }
+        }
+        walk_expr(self, expr);
+    }
+}
+
+#[derive(Default)]
+struct Count(usize);
+
+impl Visitor for Count {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0 += 1;
+        walk_expr(self, expr);
+    }
+}
+
+impl Transformer for Count {
+    fn transform_expr(&mut self, expr: &mut Expr) {
+        self.0 += 1;
+        walk_expr_mut(self, expr);
+    }
+}
+
+// Renames a variable everywhere it is read or assigned
+struct Rename<'a>(&'a str, &'a str);
+
+impl Transformer for Rename<'_> {
+    fn transform_expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Ident(name) | Expr::Assign { name, .. } | Expr::Let { name, .. } if name == self.0 => *name = Symbol::intern(self.1),
+            _ => {}
+        }
+        walk_expr_mut(self, expr);
+    }
+}
+
+#[test]
+fn test_visitor_reaches_every_node() {
+    let program = parse(PROGRAM);
+    let mut calls = Calls::default();
+    calls.visit_expr(&program);
+    assert_eq!(calls.0, ["area", "area", "enumerate", "print", "ValueError", "print", "area", "assert_eq", "area"]);
+
+    // Both traversals see the same nodes
+    let mut seen = Count::default();
+    seen.visit_expr(&program);
+    let mut rewritten = Count::default();
+    rewritten.transform_expr(&mut program.clone());
+    assert_eq!(seen.0, rewritten.0);
+    assert!(seen.0 > 50);
+}
+
+#[test]
+fn test_transformer_rewrites_in_place() {
+    let mut program = parse("let total = 0\nfor x in [1, 2] { total = total + x }\ntotal");
+    Rename("total", "sum_so_far").transform_expr(&mut program);
+    assert_eq!(format_program(&program), "let sum_so_far = 0\nfor x in [1, 2] {\n    sum_so_far = sum_so_far + x\n}\nsum_so_far");
+}